@@ -1,8 +1,10 @@
 use std::{collections::HashMap, fmt};
 
+use serde::{Deserialize, Serialize};
+
 use crate::module::ModuleId;
 
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct FunctionType {
     pub arg_types: Vec<AcornType>,
     pub return_type: Box<AcornType>,
@@ -68,7 +70,7 @@ impl FunctionType {
 }
 
 // Typeclasses are represented by the module they were defined in, and their name.
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct TypeClass {
     pub module_id: ModuleId,
     pub name: String,
@@ -76,7 +78,7 @@ pub struct TypeClass {
 
 // Every AcornValue has an AcornType.
 // This is the "richer" form of a type. The environment uses these types; the prover uses ids.
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum AcornType {
     // Nothing can ever be the empty type.
     Empty,
@@ -145,6 +147,20 @@ impl AcornType {
         }
     }
 
+    // Whether this type is the given type, or contains it within it somewhere.
+    pub fn contains(&self, other: &AcornType) -> bool {
+        if self == other {
+            return true;
+        }
+        match self {
+            AcornType::Function(function_type) => {
+                function_type.arg_types.iter().any(|t| t.contains(other))
+                    || function_type.return_type.contains(other)
+            }
+            _ => false,
+        }
+    }
+
     // Create the type you get when you apply this type to the given type.
     // Panics if the application is invalid.
     // Does partial application.
@@ -219,6 +235,31 @@ impl AcornType {
     }
 
     // Replaces type variables in the provided list with the corresponding type.
+    // Replaces the module ids in this type.
+    // This is for using a type in a project that numbers its modules differently.
+    pub fn remap_modules(&self, remap: &dyn Fn(ModuleId) -> ModuleId) -> AcornType {
+        let remap_class = |tc: &Option<TypeClass>| {
+            tc.as_ref().map(|tc| TypeClass {
+                module_id: remap(tc.module_id),
+                name: tc.name.clone(),
+            })
+        };
+        match self {
+            AcornType::Data(module, name) => AcornType::Data(remap(*module), name.clone()),
+            AcornType::Function(function_type) => AcornType::Function(FunctionType {
+                arg_types: function_type
+                    .arg_types
+                    .iter()
+                    .map(|t| t.remap_modules(remap))
+                    .collect(),
+                return_type: Box::new(function_type.return_type.remap_modules(remap)),
+            }),
+            AcornType::Variable(name, tc) => AcornType::Variable(name.clone(), remap_class(tc)),
+            AcornType::Arbitrary(name, tc) => AcornType::Arbitrary(name.clone(), remap_class(tc)),
+            AcornType::Empty | AcornType::Bool => self.clone(),
+        }
+    }
+
     pub fn instantiate(&self, params: &[(String, AcornType)]) -> AcornType {
         match self {
             AcornType::Variable(name, None) => {
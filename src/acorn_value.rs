@@ -1,12 +1,18 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::acorn_type::AcornType;
 use crate::atom::AtomId;
 use crate::constant_map::ConstantKey;
 use crate::module::ModuleId;
 use crate::token::TokenType;
+use crate::value_visitor::{
+    ConstantFinder, ConstantReplacer, ModuleRemapper, TermReplacer, TypeInstantiator, ValueFolder,
+    ValueVisitor, VariableFinder,
+};
 
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct FunctionApplication {
     pub function: Box<AcornValue>,
     pub args: Vec<AcornValue>,
@@ -56,7 +62,7 @@ impl FunctionApplication {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum BinaryOp {
     Implies,
     Equals,
@@ -84,7 +90,7 @@ impl fmt::Display for BinaryOp {
 }
 
 // An instance of a constant. Could be generic or not.
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Hash, Serialize, Deserialize)]
 pub struct ConstantInstance {
     pub module_id: ModuleId,
     pub name: String,
@@ -132,7 +138,7 @@ impl ConstantInstance {
 
 // Two AcornValue compare to equal if they are structurally identical.
 // Comparison doesn't do any evaluations.
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum AcornValue {
     // A variable that is bound to a value on the stack.
     // Represented by (stack index, type).
@@ -168,6 +174,11 @@ pub enum AcornValue {
         Box<AcornValue>,
         Vec<(Vec<AcornType>, AcornValue, AcornValue)>,
     ),
+
+    // Some value of the given type that satisfies the condition.
+    // The condition introduces one variable onto the stack, for the chosen value.
+    // When no value satisfies the condition, this is an arbitrary value of the type.
+    Choose(AcornType, Box<AcornValue>),
 }
 
 // An AcornValue has an implicit stack size that determines what index new stack variables
@@ -228,6 +239,12 @@ impl fmt::Display for Subvalue<'_> {
                 }
                 write!(f, " }}")
             }
+            AcornValue::Choose(choice_type, condition) => write!(
+                f,
+                "(choose {} where {})",
+                AcornType::decs_to_str(&vec![choice_type.clone()], self.stack_size),
+                Subvalue::new(condition, self.stack_size + 1)
+            ),
         }
     }
 }
@@ -299,6 +316,7 @@ impl AcornValue {
                     panic!("Match with no cases");
                 }
             }
+            AcornValue::Choose(choice_type, _) => choice_type.clone(),
         }
     }
 
@@ -403,6 +421,7 @@ impl AcornValue {
 
             AcornValue::IfThenElse(_, _, _) => false,
             AcornValue::Match(..) => false,
+            AcornValue::Choose(..) => false,
         }
     }
 
@@ -731,6 +750,10 @@ impl AcornValue {
                     .collect();
                 AcornValue::Match(Box::new(new_scrutinee), new_cases)
             }
+            AcornValue::Choose(choice_type, condition) => AcornValue::Choose(
+                choice_type,
+                Box::new(condition.bind_values(first_binding_index, stack_size + 1, values)),
+            ),
             AcornValue::Constant(_) | AcornValue::Bool(_) => self,
         }
     }
@@ -795,6 +818,10 @@ impl AcornValue {
                     .collect();
                 AcornValue::Match(Box::new(new_scrutinee), new_cases)
             }
+            AcornValue::Choose(choice_type, condition) => AcornValue::Choose(
+                choice_type,
+                Box::new(condition.insert_stack(index, increment)),
+            ),
             AcornValue::Constant(_) | AcornValue::Bool(_) => self,
         }
     }
@@ -920,12 +947,30 @@ impl AcornValue {
                     .collect();
                 AcornValue::Match(Box::new(new_scrutinee), new_cases)
             }
+            AcornValue::Choose(choice_type, condition) => AcornValue::Choose(
+                choice_type.clone(),
+                Box::new(condition.replace_function_equality(stack_size + 1)),
+            ),
             AcornValue::Variable(_, _) | AcornValue::Constant(_) | AcornValue::Bool(_) => {
                 self.clone()
             }
         }
     }
 
+    // Applies this value to the args, substituting them in directly if this is a lambda.
+    // Unlike expand_lambdas, this only expands the outermost lambda.
+    //
+    // stack_size is the number of variables that are already on the stack, for both this value
+    // and the args.
+    pub fn beta_reduce(self, stack_size: AtomId, args: &[AcornValue]) -> AcornValue {
+        match self {
+            AcornValue::Lambda(_, return_value) => {
+                return_value.bind_values(stack_size, stack_size, args)
+            }
+            function => AcornValue::new_apply(function, args.to_vec()),
+        }
+    }
+
     // Attempts to remove all lambdas from a value.
     //
     // Replaces lambda(...) { value } (args) by substituting the args into the value.
@@ -991,183 +1036,14 @@ impl AcornValue {
                     .collect();
                 AcornValue::Match(Box::new(new_scrutinee), new_cases)
             }
+            AcornValue::Choose(choice_type, condition) => AcornValue::Choose(
+                choice_type,
+                Box::new(condition.expand_lambdas(stack_size + 1)),
+            ),
             AcornValue::Variable(_, _) | AcornValue::Constant(_) | AcornValue::Bool(_) => self,
         }
     }
 
-    // The general idea is that these expressions are equivalent:
-    //
-    //   foo(if a then b else c)
-    //   if a then foo(b) else foo(c)
-    //
-    // extract_one_if tries to use this equivalence to extract one "if" statement, and return
-    // Some((condition, if_value, else_value))
-    //
-    // This function is very closely tied to replace_if and only has to work with the sorts of
-    // values it is called on, those at the boundary between boolean and non-boolean values.
-    fn extract_one_if(&self) -> Option<(AcornValue, AcornValue, AcornValue)> {
-        match self {
-            AcornValue::Application(app) => {
-                if let Some((a, b, c)) = app.function.extract_one_if() {
-                    // We can extract the if from the function
-                    Some((
-                        a,
-                        AcornValue::Application(FunctionApplication {
-                            function: Box::new(b),
-                            args: app.args.clone(),
-                        }),
-                        AcornValue::Application(FunctionApplication {
-                            function: Box::new(c),
-                            args: app.args.clone(),
-                        }),
-                    ))
-                } else {
-                    // We can't extract the if from the function, but we can extract it from the args
-                    for (i, arg) in app.args.iter().enumerate() {
-                        if let Some((a, b, c)) = arg.extract_one_if() {
-                            // We can extract the if from this arg
-                            let mut new_args = app.args.clone();
-                            new_args[i] = b;
-                            let b_args = new_args.clone();
-                            new_args[i] = c;
-                            let c_args = new_args;
-                            return Some((
-                                a,
-                                AcornValue::Application(FunctionApplication {
-                                    function: app.function.clone(),
-                                    args: b_args,
-                                }),
-                                AcornValue::Application(FunctionApplication {
-                                    function: app.function.clone(),
-                                    args: c_args,
-                                }),
-                            ));
-                        }
-                    }
-                    None
-                }
-            }
-
-            AcornValue::IfThenElse(a, b, c) => Some((*a.clone(), *b.clone(), *c.clone())),
-
-            AcornValue::Binary(op, left, right) => {
-                if let Some((a, b, c)) = left.extract_one_if() {
-                    Some((
-                        a,
-                        AcornValue::Binary(*op, Box::new(b), right.clone()),
-                        AcornValue::Binary(*op, Box::new(c), right.clone()),
-                    ))
-                } else if let Some((a, b, c)) = right.extract_one_if() {
-                    Some((
-                        a,
-                        AcornValue::Binary(*op, left.clone(), Box::new(b)),
-                        AcornValue::Binary(*op, left.clone(), Box::new(c)),
-                    ))
-                } else {
-                    None
-                }
-            }
-
-            AcornValue::Not(_)
-            | AcornValue::ForAll(_, _)
-            | AcornValue::Exists(_, _)
-            | AcornValue::Bool(_)
-            | AcornValue::Lambda(_, _)
-            | AcornValue::Variable(_, _)
-            | AcornValue::Constant(_)
-            | AcornValue::Match(..) => None,
-        }
-    }
-
-    // For an "if" among three boolean values, replace it with an equivalent value that
-    // doesn't use if-then-else nodes.
-    fn new_if_replacement(a: AcornValue, b: AcornValue, c: AcornValue) -> AcornValue {
-        let (a, b, c) = (a.replace_if(), b.replace_if(), c.replace_if());
-        let not_a_imp_c = AcornValue::new_implies(a.clone().negate(), c);
-        let a_imp_b = AcornValue::new_implies(a, b);
-        AcornValue::new_and(a_imp_b, not_a_imp_c)
-    }
-
-    // Replaces "if" nodes by extracting them into boolean values and then replacing them.
-    // This should only be called on boolean values.
-    // It handles the case where the if branches are boolean themselves, as well as the cases where
-    // the "if" is in a function argument, or on one side of a binary.
-    pub fn replace_if(self) -> AcornValue {
-        assert_eq!(self.get_type(), AcornType::Bool);
-
-        match self {
-            AcornValue::Binary(op, left, right) if (left.get_type() == AcornType::Bool) => {
-                // The subvalues are still boolean, so we can recurse.
-                let new_left = left.replace_if();
-                let new_right = right.replace_if();
-                return AcornValue::Binary(op, Box::new(new_left), Box::new(new_right));
-            }
-
-            AcornValue::Binary(_, _, _) | AcornValue::Application(_) => match self.extract_one_if()
-            {
-                Some((a, b, c)) => AcornValue::new_if_replacement(a, b, c),
-                None => self,
-            },
-
-            AcornValue::IfThenElse(a, b, c) => AcornValue::new_if_replacement(*a, *b, *c),
-
-            AcornValue::Not(value) => AcornValue::Not(Box::new(value.replace_if())),
-            AcornValue::ForAll(quants, value) => {
-                AcornValue::ForAll(quants, Box::new(value.replace_if()))
-            }
-            AcornValue::Exists(quants, value) => {
-                AcornValue::Exists(quants, Box::new(value.replace_if()))
-            }
-            AcornValue::Lambda(args, value) => {
-                AcornValue::Lambda(args, Box::new(value.replace_if()))
-            }
-            _ => self,
-        }
-    }
-
-    // Replaces "match" nodes by replacing them with a disjunction of all their cases.
-    // This should only be called on boolean values.
-    // It only handles the case where the match is directly below the right of a binary operator,
-    // because that lets you define constants and functions using match.
-    // We could easily implement left-match. But is it even possible to hit that code?
-    pub fn replace_match(self) -> AcornValue {
-        assert_eq!(self.get_type(), AcornType::Bool);
-
-        match self {
-            AcornValue::Binary(op, left, right) => {
-                if let AcornValue::Match(scrutinee, cases) = *right {
-                    let mut conjuncts = vec![];
-                    for (vars, pattern, result) in cases {
-                        // The meaning of the branch is:
-                        //   scrutinee = pattern implies op(left, result)
-                        let equality = AcornValue::new_equals(*scrutinee.clone(), pattern);
-                        let implication = AcornValue::new_implies(
-                            equality,
-                            AcornValue::Binary(op, left.clone(), Box::new(result)),
-                        );
-                        let conjunct = AcornValue::new_forall(vars, implication);
-                        conjuncts.push(conjunct);
-                    }
-                    return AcornValue::reduce(BinaryOp::And, conjuncts);
-                }
-                AcornValue::Binary(op, left, right)
-            }
-
-            // The cases where we recurse
-            AcornValue::Not(value) => AcornValue::Not(Box::new(value.replace_match())),
-            AcornValue::ForAll(quants, value) => {
-                AcornValue::ForAll(quants, Box::new(value.replace_match()))
-            }
-            AcornValue::Exists(quants, value) => {
-                AcornValue::Exists(quants, Box::new(value.replace_match()))
-            }
-            AcornValue::Lambda(args, value) => {
-                AcornValue::Lambda(args, Box::new(value.replace_match()))
-            }
-            _ => self,
-        }
-    }
-
     // Removes all "forall" nodes, collecting the quantified types into quantifiers.
     pub fn remove_forall(self, quantifiers: &mut Vec<AcornType>) -> AcornValue {
         match self {
@@ -1209,65 +1085,16 @@ impl AcornValue {
         stack_size: AtomId,
         replacer: &impl Fn(&ConstantInstance) -> Option<AcornValue>,
     ) -> AcornValue {
-        match self {
-            AcornValue::Variable(_, _) | AcornValue::Bool(_) => self.clone(),
-            AcornValue::Application(fa) => {
-                let new_function = fa.function.replace_constants(stack_size, replacer);
-                let new_args = fa
-                    .args
-                    .iter()
-                    .map(|x| x.replace_constants(stack_size, replacer))
-                    .collect();
-                AcornValue::Application(FunctionApplication {
-                    function: Box::new(new_function),
-                    args: new_args,
-                })
-            }
-            AcornValue::Lambda(arg_types, value) => {
-                let new_value =
-                    value.replace_constants(stack_size + arg_types.len() as AtomId, replacer);
-                AcornValue::Lambda(arg_types.clone(), Box::new(new_value))
-            }
-            AcornValue::Binary(op, left, right) => {
-                let new_left = left.replace_constants(stack_size, replacer);
-                let new_right = right.replace_constants(stack_size, replacer);
-                AcornValue::Binary(*op, Box::new(new_left), Box::new(new_right))
-            }
-            AcornValue::Not(x) => {
-                AcornValue::Not(Box::new(x.replace_constants(stack_size, replacer)))
-            }
-            AcornValue::ForAll(quants, value) => {
-                let new_value =
-                    value.replace_constants(stack_size + quants.len() as AtomId, replacer);
-                AcornValue::ForAll(quants.clone(), Box::new(new_value))
-            }
-            AcornValue::Exists(quants, value) => {
-                let new_value =
-                    value.replace_constants(stack_size + quants.len() as AtomId, replacer);
-                AcornValue::Exists(quants.clone(), Box::new(new_value))
-            }
-            AcornValue::Constant(c) => replacer(&c).unwrap_or_else(|| self.clone()),
-            AcornValue::IfThenElse(cond, if_value, else_value) => AcornValue::IfThenElse(
-                Box::new(cond.replace_constants(stack_size, replacer)),
-                Box::new(if_value.replace_constants(stack_size, replacer)),
-                Box::new(else_value.replace_constants(stack_size, replacer)),
-            ),
-            AcornValue::Match(scrutinee, cases) => {
-                let new_scrutinee = scrutinee.replace_constants(stack_size, replacer);
-                let new_cases = cases
-                    .into_iter()
-                    .map(|(new_vars, pattern, result)| {
-                        let new_stack_size = stack_size + new_vars.len() as AtomId;
-                        (
-                            new_vars.clone(),
-                            pattern.replace_constants(new_stack_size, replacer),
-                            result.replace_constants(new_stack_size, replacer),
-                        )
-                    })
-                    .collect();
-                AcornValue::Match(Box::new(new_scrutinee), new_cases)
-            }
+        ConstantReplacer {
+            replacer: |c: &ConstantInstance, _| replacer(c),
         }
+        .fold_value(self, stack_size)
+    }
+
+    // Replaces every occurrence of the "from" term with the "to" term.
+    // Neither term should use any stack variables.
+    pub fn replace_term(&self, from: &AcornValue, to: &AcornValue) -> AcornValue {
+        TermReplacer { from, to }.fold_value(self, 0)
     }
 
     // Returns an error string if this is not a valid top-level value.
@@ -1330,6 +1157,12 @@ impl AcornValue {
                 }
                 Ok(())
             }
+            AcornValue::Choose(choice_type, condition) => {
+                stack.push(choice_type.clone());
+                let answer = condition.validate_against_stack(stack);
+                stack.pop();
+                answer
+            }
             AcornValue::Not(x) => x.validate_against_stack(stack),
             AcornValue::Constant(_) | AcornValue::Bool(_) => Ok(()),
         }
@@ -1337,60 +1170,13 @@ impl AcornValue {
 
     // Replace some type variables with other types.
     pub fn instantiate(&self, params: &[(String, AcornType)]) -> AcornValue {
-        match self {
-            AcornValue::Variable(i, var_type) => {
-                AcornValue::Variable(*i, var_type.instantiate(params))
-            }
-            AcornValue::Application(app) => AcornValue::Application(FunctionApplication {
-                function: Box::new(app.function.instantiate(params)),
-                args: app.args.iter().map(|x| x.instantiate(params)).collect(),
-            }),
-            AcornValue::Lambda(args, value) => AcornValue::Lambda(
-                args.iter()
-                    .map(|x| x.instantiate(params))
-                    .collect::<Vec<_>>(),
-                Box::new(value.instantiate(params)),
-            ),
-            AcornValue::ForAll(args, value) => AcornValue::ForAll(
-                args.iter()
-                    .map(|x| x.instantiate(params))
-                    .collect::<Vec<_>>(),
-                Box::new(value.instantiate(params)),
-            ),
-            AcornValue::Exists(args, value) => AcornValue::Exists(
-                args.iter()
-                    .map(|x| x.instantiate(params))
-                    .collect::<Vec<_>>(),
-                Box::new(value.instantiate(params)),
-            ),
-            AcornValue::Binary(op, left, right) => AcornValue::Binary(
-                *op,
-                Box::new(left.instantiate(params)),
-                Box::new(right.instantiate(params)),
-            ),
-            AcornValue::IfThenElse(cond, if_value, else_value) => AcornValue::IfThenElse(
-                Box::new(cond.instantiate(params)),
-                Box::new(if_value.instantiate(params)),
-                Box::new(else_value.instantiate(params)),
-            ),
-            AcornValue::Match(scrutinee, cases) => {
-                let new_scrutinee = scrutinee.instantiate(params);
-                let new_cases = cases
-                    .iter()
-                    .map(|(vars, pattern, result)| {
-                        (
-                            vars.iter().map(|t| t.instantiate(params)).collect(),
-                            pattern.instantiate(params),
-                            result.instantiate(params),
-                        )
-                    })
-                    .collect();
-                AcornValue::Match(Box::new(new_scrutinee), new_cases)
-            }
-            AcornValue::Not(x) => AcornValue::Not(Box::new(x.instantiate(params))),
-            AcornValue::Constant(c) => AcornValue::Constant(c.instantiate(&params)),
-            AcornValue::Bool(_) => self.clone(),
-        }
+        TypeInstantiator { params }.fold_value(self, 0)
+    }
+
+    // Replaces the module ids everywhere in this value.
+    // This is for using a value in a project that numbers its modules differently.
+    pub fn remap_modules(&self, remap: &dyn Fn(ModuleId) -> ModuleId) -> AcornValue {
+        ModuleRemapper { remap }.fold_value(self, 0)
     }
 
     // A value is generic if anything within it has type variables.
@@ -1418,6 +1204,9 @@ impl AcornValue {
                         .iter()
                         .any(|(_, pattern, result)| pattern.is_generic() || result.is_generic())
             }
+            AcornValue::Choose(choice_type, condition) => {
+                choice_type.is_generic() || condition.is_generic()
+            }
         }
     }
 
@@ -1426,40 +1215,84 @@ impl AcornValue {
         filter: &impl Fn(&ConstantInstance) -> bool,
         output: &mut Vec<ConstantInstance>,
     ) {
-        match self {
-            AcornValue::Variable(_, _) | AcornValue::Bool(_) => {}
-            AcornValue::Application(app) => {
-                app.function.find_constants(filter, output);
-                for arg in &app.args {
-                    arg.find_constants(filter, output);
-                }
-            }
-            AcornValue::Lambda(_, value)
-            | AcornValue::ForAll(_, value)
-            | AcornValue::Exists(_, value) => value.find_constants(filter, output),
-            AcornValue::Binary(_, left, right) => {
-                left.find_constants(filter, output);
-                right.find_constants(filter, output);
-            }
-            AcornValue::IfThenElse(cond, if_value, else_value) => {
-                cond.find_constants(filter, output);
-                if_value.find_constants(filter, output);
-                else_value.find_constants(filter, output);
-            }
-            AcornValue::Match(scrutinee, cases) => {
-                scrutinee.find_constants(filter, output);
-                for (_, pattern, result) in cases {
-                    pattern.find_constants(filter, output);
-                    result.find_constants(filter, output);
-                }
-            }
-            AcornValue::Not(x) => x.find_constants(filter, output),
-            AcornValue::Constant(c) => {
-                if filter(c) {
-                    output.push(c.clone());
+        ConstantFinder { filter, output }.visit_value(self, 0);
+    }
+
+    // Whether this value refers to any stack variable in the range, when it's evaluated
+    // with stack_size variables already on the stack.
+    pub fn has_variable_in(&self, range: std::ops::Range<AtomId>, stack_size: AtomId) -> bool {
+        let mut finder = VariableFinder {
+            range,
+            found: false,
+        };
+        finder.visit_value(self, stack_size);
+        finder.found
+    }
+
+    // Recognizes a pointwise equality between functions, like
+    //   forall(x) { f(x) = g(x) }
+    // and returns the equality between functions that it implies by extensionality, like
+    //   f = g
+    // The removed arguments must be the innermost quantified variables, in order, and they
+    // can't appear anywhere else.
+    pub fn eta_reduce_equality(&self) -> Option<AcornValue> {
+        // Nested quantifiers can be treated as one.
+        let mut quants = vec![];
+        let mut body = self;
+        while let AcornValue::ForAll(new_quants, subvalue) = body {
+            quants.extend(new_quants.iter().cloned());
+            body = subvalue;
+        }
+        let AcornValue::Binary(BinaryOp::Equals, left, right) = body else {
+            return None;
+        };
+        let (AcornValue::Application(left), AcornValue::Application(right)) =
+            (left.as_ref(), right.as_ref())
+        else {
+            return None;
+        };
+
+        // Count how many trailing arguments can be removed.
+        let num_quants = quants.len();
+        let mut removed = 0;
+        while removed < num_quants && removed < left.args.len() && removed < right.args.len() {
+            let expected = (num_quants - removed - 1) as AtomId;
+            match (
+                &left.args[left.args.len() - removed - 1],
+                &right.args[right.args.len() - removed - 1],
+            ) {
+                (AcornValue::Variable(i, _), AcornValue::Variable(j, _))
+                    if *i == expected && *j == expected =>
+                {
+                    removed += 1
                 }
+                _ => break,
             }
         }
+        if removed == 0 {
+            return None;
+        }
+
+        let kept = num_quants - removed;
+        let left = AcornValue::new_apply(
+            (*left.function).clone(),
+            left.args[..left.args.len() - removed].to_vec(),
+        );
+        let right = AcornValue::new_apply(
+            (*right.function).clone(),
+            right.args[..right.args.len() - removed].to_vec(),
+        );
+        let range = kept as AtomId..num_quants as AtomId;
+        let stack_size = num_quants as AtomId;
+        if left.has_variable_in(range.clone(), stack_size)
+            || right.has_variable_in(range, stack_size)
+        {
+            return None;
+        }
+        Some(AcornValue::new_forall(
+            quants[..kept].to_vec(),
+            AcornValue::new_equals(left, right),
+        ))
     }
 
     // Converts all the type variables to arbitrary types.
@@ -1506,6 +1339,10 @@ impl AcornValue {
                     .collect();
                 AcornValue::Match(Box::new(new_scrutinee), new_cases)
             }
+            AcornValue::Choose(choice_type, condition) => AcornValue::Choose(
+                choice_type.to_arbitrary(),
+                Box::new(condition.to_arbitrary()),
+            ),
             AcornValue::Not(x) => AcornValue::Not(Box::new(x.to_arbitrary())),
             AcornValue::Constant(_) | AcornValue::Bool(_) => self.clone(),
         }
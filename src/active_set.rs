@@ -1,11 +1,17 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use im::{HashMap, Vector};
 
 use crate::clause::Clause;
-use crate::fingerprint::FingerprintUnifier;
+use crate::discrimination_tree::DiscriminationTree;
+use crate::kbo::Kbo;
 use crate::literal::Literal;
+use crate::path_index::PathIndex;
 use crate::pattern_tree::LiteralSet;
 use crate::proof_step::{ProofStep, Rule, Truthiness};
 use crate::rewrite_tree::{Rewrite, RewriteTree};
+use crate::subsumption::SubsumptionIndex;
 use crate::term::Term;
 use crate::term_graph::{TermGraph, TermId};
 use crate::unifier::{Scope, Unifier};
@@ -14,38 +20,117 @@ use crate::unifier::{Scope, Unifier};
 // The goal is that, given a new clause, it is efficient to determine what can be concluded
 // given that clause and one clause from the active set.
 // "Efficient" is relative - this still may take time roughly linear to the size of the active set.
+// Every store in here is persistent, so cloning the active set along with the prover shares
+// them, and the clone only copies the parts that it changes later.
 #[derive(Clone)]
 pub struct ActiveSet {
     // A vector for indexed reference
-    steps: Vec<ProofStep>,
+    steps: Vector<ProofStep>,
 
     // The long clauses (ie more than one literal) that we have proven.
-    long_clauses: HashSet<Clause>,
+    long_clauses: im::HashSet<Clause>,
 
     // The short clauses (ie just one literal) that we have proven.
     literal_set: LiteralSet,
 
     // An index of all the positive literals that we can do resolution with.
-    positive_res_targets: FingerprintUnifier<ResolutionTarget>,
+    positive_res_targets: DiscriminationTree<ResolutionTarget>,
 
     // An index of all the negative literals that we can do resolution with.
-    negative_res_targets: FingerprintUnifier<ResolutionTarget>,
+    negative_res_targets: DiscriminationTree<ResolutionTarget>,
 
     // A graph that encodes equalities and inequalities between terms.
     pub graph: TermGraph,
 
     // Information about every subterm that appears in an activated concrete literal,
     // except "true".
-    subterms: Vec<SubtermInfo>,
+    subterms: Vector<SubtermInfo>,
 
     // An index to find the id of a subterm for an exact match.
     subterm_map: HashMap<Term, usize>,
 
     // An index to find the id of subterms for a pattern match.
-    subterm_unifier: FingerprintUnifier<usize>,
+    subterm_unifier: DiscriminationTree<usize>,
 
     // A data structure to do the mechanical rewriting of subterms.
     rewrite_tree: RewriteTree,
+
+    // Which literals of a clause can be resolved with other clauses.
+    literal_selection: LiteralSelection,
+
+    // When there is an ordering, equalities are only used to rewrite from the larger side
+    // to the smaller side. Otherwise they get used in both directions.
+    kbo: Option<Kbo>,
+
+    // An index of the long clauses, to check whether one clause subsumes another.
+    subsumption: SubsumptionIndex,
+
+    // The unit equalities that can be oriented, indexed to rewrite terms from the larger side
+    // to the smaller side. Every clause gets rewritten with these as far as possible.
+    // Conditional equalities, like "p -> s = t", are in here too. They only get used when
+    // the active set already knows that the condition is true.
+    demodulators: RewriteTree,
+
+    // An index of the subterms of the long clauses that aren't redundant, to find the ones
+    // a new demodulator applies to.
+    path_index: PathIndex,
+
+    // The ids of long clauses that are redundant, because a later clause subsumes them or
+    // a demodulator simplifies them.
+    // They stay in most of the indices, but don't get used for resolution any more.
+    redundant: im::HashSet<usize>,
+
+    // The clauses that became redundant since the prover last took them, so that it can
+    // drop the passive clauses they generated.
+    newly_redundant: Vec<usize>,
+}
+
+// Which literals of a clause are used for resolution with other clauses.
+// Selecting negative literals means a clause must get rid of its hypotheses before
+// its conclusions get used, which cuts down on redundant inferences.
+// Different problems do best with different strategies.
+// Negative literals sort first in a clause, so a clause with no negative literals has
+// nothing to select, and all of its literals can be used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LiteralSelection {
+    // Every literal can be used.
+    #[default]
+    SelectNone,
+
+    // Only the largest negative literal can be used, when there is one.
+    // Size is measured by atom count, with ties broken by the term ordering.
+    SelectLargestNegative,
+
+    // Only the negative literals can be used, when there are any.
+    SelectAllNegative,
+}
+
+impl LiteralSelection {
+    // Whether the literal at this index can be used for inference.
+    pub fn is_eligible(&self, clause: &Clause, index: usize) -> bool {
+        let has_negative = clause.literals.first().is_some_and(|lit| !lit.positive);
+        if !has_negative {
+            return true;
+        }
+        match self {
+            LiteralSelection::SelectNone => true,
+            LiteralSelection::SelectAllNegative => !clause.literals[index].positive,
+            LiteralSelection::SelectLargestNegative => {
+                let largest = clause
+                    .literals
+                    .iter()
+                    .enumerate()
+                    .take_while(|(_, lit)| !lit.positive)
+                    .max_by(|(_, a), (_, b)| {
+                        a.atom_count()
+                            .cmp(&b.atom_count())
+                            .then_with(|| a.extended_kbo_cmp(b))
+                    })
+                    .map(|(i, _)| i);
+                largest == Some(index)
+            }
+        }
+    }
 }
 
 // A ResolutionTarget represents a literal that we could do resolution with.
@@ -101,19 +186,188 @@ struct SubtermLocation {
 impl ActiveSet {
     pub fn new() -> ActiveSet {
         ActiveSet {
-            steps: vec![],
-            long_clauses: HashSet::new(),
+            steps: Vector::new(),
+            long_clauses: im::HashSet::new(),
             literal_set: LiteralSet::new(),
-            positive_res_targets: FingerprintUnifier::new(),
-            negative_res_targets: FingerprintUnifier::new(),
+            positive_res_targets: DiscriminationTree::new(),
+            negative_res_targets: DiscriminationTree::new(),
             graph: TermGraph::new(),
-            subterms: vec![],
+            subterms: Vector::new(),
             subterm_map: HashMap::new(),
-            subterm_unifier: FingerprintUnifier::new(),
+            subterm_unifier: DiscriminationTree::new(),
             rewrite_tree: RewriteTree::new(),
+            literal_selection: LiteralSelection::default(),
+            kbo: None,
+            subsumption: SubsumptionIndex::new(),
+            demodulators: RewriteTree::new(),
+            path_index: PathIndex::new(),
+            redundant: im::HashSet::new(),
+            newly_redundant: vec![],
+        }
+    }
+
+    // This should be set before any steps are activated, because it affects how they
+    // are indexed.
+    pub fn set_literal_selection(&mut self, selection: LiteralSelection) {
+        self.literal_selection = selection;
+    }
+
+    // This should be set before any steps are activated, because it affects how rewrite
+    // patterns are indexed.
+    pub fn set_kbo(&mut self, kbo: Kbo) {
+        self.kbo = Some(kbo);
+    }
+
+    // Whether the ordering allows rewriting s into t.
+    fn can_rewrite(&self, s: &Term, t: &Term) -> bool {
+        match &self.kbo {
+            Some(kbo) => kbo.cmp(s, t) != Ordering::Less,
+            None => true,
         }
     }
 
+    // Which way a literal can be used for demodulation, if any.
+    // Returns whether it rewrites forwards, from left to right.
+    // Demodulation has to make terms smaller under every substitution.
+    fn demodulation_direction(&self, literal: &Literal) -> Option<bool> {
+        if !literal.positive || literal.right.is_true() {
+            return None;
+        }
+        match self.term_cmp(&literal.left, &literal.right) {
+            Ordering::Greater => Some(true),
+            Ordering::Less => Some(false),
+            Ordering::Equal => None,
+        }
+    }
+
+    // Whether this literal is known to be true, so that it discharges the condition of a
+    // conditional rewrite.
+    // Returns the ids of the clauses that show it.
+    fn discharge(&self, condition: &Literal) -> Option<Vec<usize>> {
+        match self.evaluate_literal(condition) {
+            Some((true, id)) => Some(id.into_iter().collect()),
+            _ => None,
+        }
+    }
+
+    // Rewrites a term with the demodulators in the tree until none of them apply, starting
+    // with the innermost subterms.
+    // A conditional demodulator only applies when discharge shows that its conditions are true.
+    // The ids of the demodulators used, and of the clauses that discharge their conditions,
+    // get added to rules.
+    fn demodulate(
+        tree: &RewriteTree,
+        term: &Term,
+        discharge: &impl Fn(&Literal) -> Option<Vec<usize>>,
+        rules: &mut Vec<usize>,
+    ) -> Term {
+        let args = term
+            .args
+            .iter()
+            .map(|arg| ActiveSet::demodulate(tree, arg, discharge, rules))
+            .collect();
+        let term = term.replace_args(args);
+        for rewrite in tree.get_rewrites(&term, term.least_unused_variable()) {
+            let mut reasons = vec![];
+            if rewrite
+                .conditions
+                .iter()
+                .all(|condition| match discharge(condition) {
+                    Some(ids) => {
+                        reasons.extend(ids);
+                        true
+                    }
+                    None => false,
+                })
+            {
+                rules.push(rewrite.pattern_id);
+                rules.extend(reasons);
+                return ActiveSet::demodulate(tree, &rewrite.term, discharge, rules);
+            }
+        }
+        term
+    }
+
+    // Demodulates each literal of a clause.
+    // Returns None if no demodulator applies.
+    fn demodulate_literals(
+        tree: &RewriteTree,
+        literals: &[Literal],
+        discharge: &impl Fn(&Literal) -> Option<Vec<usize>>,
+        rules: &mut Vec<usize>,
+    ) -> Option<Vec<Literal>> {
+        let initial_num_rules = rules.len();
+        let answer = literals
+            .iter()
+            .map(|literal| {
+                let left = ActiveSet::demodulate(tree, &literal.left, discharge, rules);
+                let right = ActiveSet::demodulate(tree, &literal.right, discharge, rules);
+                Literal::new(literal.positive, left, right)
+            })
+            .collect();
+        if rules.len() == initial_num_rules {
+            None
+        } else {
+            Some(answer)
+        }
+    }
+
+    // When there's a new demodulator, simplify the long clauses that it applies to.
+    // The simplified clauses are new steps, and the old ones become redundant.
+    fn backward_demodulate(
+        &mut self,
+        demodulator_id: usize,
+        demodulator_step: &ProofStep,
+        forwards: bool,
+        output: &mut Vec<ProofStep>,
+    ) {
+        let mut tree = RewriteTree::new();
+        let literal = &demodulator_step.clause.literals[0];
+        tree.insert_oriented(demodulator_id, literal, forwards, !forwards);
+        let pattern = if forwards {
+            &literal.left
+        } else {
+            &literal.right
+        };
+        let mut simplified = vec![];
+        for id in self.path_index.find_instances(pattern) {
+            let step = &self.steps[id];
+            if step.truthiness == Truthiness::Factual
+                && demodulator_step.truthiness == Truthiness::Factual
+            {
+                // No global-global simplification
+                continue;
+            }
+            let mut rules = vec![];
+            let Some(literals) =
+                ActiveSet::demodulate_literals(&tree, &step.clause.literals, &|_| None, &mut rules)
+            else {
+                continue;
+            };
+            output.push(ProofStep::new_simplified(
+                step.clone(),
+                &[(demodulator_id, demodulator_step)],
+                Clause::new(literals),
+            ));
+            simplified.push(id);
+        }
+        for id in simplified {
+            self.mark_redundant(id);
+        }
+    }
+
+    fn mark_redundant(&mut self, id: usize) {
+        if self.redundant.insert(id).is_none() {
+            self.path_index.remove(id);
+            self.newly_redundant.push(id);
+        }
+    }
+
+    // The ids of the clauses that became redundant since the last call.
+    pub fn take_newly_redundant(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.newly_redundant)
+    }
+
     pub fn len(&self) -> usize {
         self.steps.len()
     }
@@ -122,11 +376,28 @@ impl ActiveSet {
         clause.literals.len() > 1 && self.long_clauses.contains(clause)
     }
 
+    // Whether an active long clause subsumes this one.
+    // Factual clauses can't be used with other factual clauses, so they only subsume
+    // factual clauses.
+    fn is_subsumed(&self, clause: &Clause, truthiness: Truthiness) -> bool {
+        clause.literals.len() > 1
+            && self
+                .subsumption
+                .find_subsuming(clause, |id| {
+                    truthiness == Truthiness::Factual
+                        || self.get_step(id).truthiness != Truthiness::Factual
+                })
+                .is_some()
+    }
+
     // Finds all resolutions that can be done with a given proof step.
     // The "new clause" is the one that is being activated, and the "old clause" is the existing one.
     pub fn find_resolutions(&self, new_step: &ProofStep, output: &mut Vec<ProofStep>) {
         let new_step_id = self.next_id();
         for (i, new_literal) in new_step.clause.literals.iter().enumerate() {
+            if !self.literal_selection.is_eligible(&new_step.clause, i) {
+                continue;
+            }
             let target_map = if new_literal.positive {
                 &self.negative_res_targets
             } else {
@@ -135,6 +406,9 @@ impl ActiveSet {
 
             let targets = target_map.find_unifying(&new_literal.left);
             for target in targets {
+                if self.redundant.contains(&target.step_index) {
+                    continue;
+                }
                 let old_step = self.get_step(target.step_index);
                 let flipped = !target.left;
 
@@ -321,7 +595,7 @@ impl ActiveSet {
 
                     // Populate the subterm info.
                     let id = self.subterms.len();
-                    self.subterms.push(SubtermInfo {
+                    self.subterms.push_back(SubtermInfo {
                         term: u_subterm.clone(),
                         locations: vec![],
                         rewrites,
@@ -386,6 +660,10 @@ impl ActiveSet {
                 // Don't rewrite from "true"
                 continue;
             }
+            if !self.can_rewrite(s, t) {
+                // The ordering says this rewrite would make things bigger
+                continue;
+            }
 
             // Look for existing subterms that match s
             let subterm_ids: Vec<usize> = self
@@ -441,6 +719,7 @@ impl ActiveSet {
                     pattern_id,
                     forwards,
                     term: new_subterm,
+                    conditions: vec![],
                 });
             }
         }
@@ -536,50 +815,84 @@ impl ActiveSet {
     // if we can unify s and u, we can rewrite it to:
     //   t != v | u = v | R
     //
-    // "s = t" must be the first clause, but "u = v" can be any of them.
+    // "s = t" has to be a maximal positive literal, but "u = v" can be any other positive
+    // literal. R can contain negative literals.
+    // When both literals are maximal, only one of the two ways to pair them is used, since
+    // the other one produces a symmetric duplicate.
     //
     // I find this rule to be unintuitive, extracting an inequality from only equalities.
-    //
-    // TODO: This "first clause" constraint seems incomplete or mistaken.
-    pub fn equality_factoring(clause: &Clause) -> Vec<Clause> {
+    pub fn equality_factoring(&self, clause: &Clause) -> Vec<Clause> {
         let mut answer = vec![];
-        let st_literal = &clause.literals[0];
-        if !st_literal.positive {
-            return answer;
-        }
-        for (_, s, t) in st_literal.both_term_pairs() {
-            for i in 1..clause.literals.len() {
-                let uv_literal = &clause.literals[i];
-                if !uv_literal.positive {
-                    continue;
-                }
-
-                for (_, u, v) in uv_literal.both_term_pairs() {
-                    // The variables are all in the same scope, which we will call "left".
-                    let mut unifier = Unifier::new();
-                    if !unifier.unify(Scope::Left, s, Scope::Left, u) {
+        let maximal = self.maximal_positive_literals(clause);
+        for (i, st_literal) in clause.literals.iter().enumerate() {
+            if !maximal[i] {
+                continue;
+            }
+            for (_, s, t) in st_literal.both_term_pairs() {
+                for (j, uv_literal) in clause.literals.iter().enumerate() {
+                    if j == i || !uv_literal.positive || (j < i && maximal[j]) {
                         continue;
                     }
-                    let mut literals = vec![];
-                    literals.push(Literal::not_equals(
-                        unifier.apply(Scope::Left, t),
-                        unifier.apply(Scope::Left, v),
-                    ));
-                    literals.push(unifier.apply_to_literal(Scope::Left, uv_literal));
-                    for j in 1..clause.literals.len() {
-                        if j != i {
-                            literals
-                                .push(unifier.apply_to_literal(Scope::Left, &clause.literals[j]));
+
+                    for (_, u, v) in uv_literal.both_term_pairs() {
+                        // The variables are all in the same scope, which we will call "left".
+                        let mut unifier = Unifier::new();
+                        if !unifier.unify(Scope::Left, s, Scope::Left, u) {
+                            continue;
+                        }
+                        let mut literals = vec![];
+                        literals.push(Literal::not_equals(
+                            unifier.apply(Scope::Left, t),
+                            unifier.apply(Scope::Left, v),
+                        ));
+                        literals.push(unifier.apply_to_literal(Scope::Left, uv_literal));
+                        for (k, literal) in clause.literals.iter().enumerate() {
+                            if k != i && k != j {
+                                literals.push(unifier.apply_to_literal(Scope::Left, literal));
+                            }
                         }
+                        let new_clause = Clause::new(literals);
+                        answer.push(new_clause);
                     }
-                    let new_clause = Clause::new(literals);
-                    answer.push(new_clause);
                 }
             }
         }
         answer
     }
 
+    // Compares terms with the ordering that orients rewrites.
+    // Equal means that they cannot be ordered.
+    fn term_cmp(&self, s: &Term, t: &Term) -> Ordering {
+        match &self.kbo {
+            Some(kbo) => kbo.cmp(s, t),
+            None => s.kbo_cmp(t),
+        }
+    }
+
+    // Whether each literal is positive and not smaller than any other positive literal.
+    // One literal is smaller than another when each of its terms is smaller than one of the
+    // other literal's terms.
+    fn maximal_positive_literals(&self, clause: &Clause) -> Vec<bool> {
+        let smaller = |a: &Literal, b: &Literal| {
+            [&a.left, &a.right].iter().all(|x| {
+                [&b.left, &b.right]
+                    .iter()
+                    .any(|y| self.term_cmp(x, y) == Ordering::Less)
+            })
+        };
+        clause
+            .literals
+            .iter()
+            .map(|literal| {
+                literal.positive
+                    && !clause
+                        .literals
+                        .iter()
+                        .any(|other| other.positive && smaller(literal, other))
+            })
+            .collect()
+    }
+
     pub fn get_clause(&self, index: usize) -> &Clause {
         &self.steps[index].clause
     }
@@ -627,7 +940,9 @@ impl ActiveSet {
             return None;
         }
 
-        if self.is_known_long_clause(&step.clause) {
+        if self.is_known_long_clause(&step.clause)
+            || self.is_subsumed(&step.clause, step.truthiness)
+        {
             return None;
         }
 
@@ -656,7 +971,23 @@ impl ActiveSet {
             }
         }
 
-        if output_literals.len() == initial_num_literals {
+        // Rewrite what's left with the demodulators
+        let mut demodulators = vec![];
+        if let Some(literals) = ActiveSet::demodulate_literals(
+            &self.demodulators,
+            &output_literals,
+            &|condition| self.discharge(condition),
+            &mut demodulators,
+        ) {
+            output_literals = literals;
+            demodulators.sort();
+            demodulators.dedup();
+            for id in demodulators {
+                new_rules.push((id, self.get_step(id)));
+            }
+        }
+
+        if new_rules.is_empty() && output_literals.len() == initial_num_literals {
             // This proof step hasn't changed.
             step.clause.literals = output_literals;
             return Some(step);
@@ -666,7 +997,9 @@ impl ActiveSet {
         if simplified_clause.is_tautology() {
             return None;
         }
-        if self.is_known_long_clause(&simplified_clause) {
+        if self.is_known_long_clause(&simplified_clause)
+            || self.is_subsumed(&simplified_clause, step.truthiness)
+        {
             return None;
         }
         Some(ProofStep::new_simplified(
@@ -713,15 +1046,38 @@ impl ActiveSet {
 
         // Add resolution targets for the new clause.
         for (i, literal) in clause.literals.iter().enumerate() {
-            self.add_resolution_targets(step_index, i, literal);
+            if self.literal_selection.is_eligible(clause, i) {
+                self.add_resolution_targets(step_index, i, literal);
+            }
         }
 
         // Store long clauses here. Short clauses will be kept in the literal set.
         if clause.literals.len() > 1 {
+            // Backward subsumption, with the same truthiness rule as forward subsumption.
+            for id in self.subsumption.find_subsumed(clause) {
+                if self.get_step(id).truthiness == Truthiness::Factual
+                    || step.truthiness != Truthiness::Factual
+                {
+                    self.mark_redundant(id);
+                }
+            }
+            self.subsumption.insert(step_index, clause);
+            self.path_index.insert(step_index, clause);
             self.long_clauses.insert(clause.clone());
         }
 
-        self.steps.push(step);
+        self.steps.push_back(step);
+        step_index
+    }
+
+    // Stores a step that was split into parts, without using it for any inference.
+    // The parts get activated on their own.
+    pub fn insert_split(&mut self, step: ProofStep) -> usize {
+        let step_index = self.next_id();
+        if step.clause.literals.len() > 1 {
+            self.long_clauses.insert(step.clause.clone());
+        }
+        self.steps.push_back(step);
         step_index
     }
 
@@ -770,12 +1126,46 @@ impl ActiveSet {
             self.activate_rewrite_pattern(activated_id, &activated_step, output);
 
             // Index it so that it can be used as a rewrite pattern in the future.
-            self.rewrite_tree.insert_literal(activated_id, literal);
+            let forwards = self.can_rewrite(&literal.left, &literal.right);
+            let backwards = self.can_rewrite(&literal.right, &literal.left);
+            self.rewrite_tree
+                .insert_oriented(activated_id, literal, forwards, backwards);
+        }
+
+        // Using the literal as a demodulator.
+        if let Some(forwards) = self.demodulation_direction(literal) {
+            self.backward_demodulate(activated_id, activated_step, forwards, output);
+            self.demodulators
+                .insert_oriented(activated_id, literal, forwards, !forwards);
         }
 
         self.literal_set.insert(&literal, activated_id);
     }
 
+    // A clause like "not p or s = t" is the conditional equation "p -> s = t".
+    // When one of its equations can be oriented, it becomes a demodulator with the negations of
+    // the other literals as its conditions.
+    fn add_conditional_demodulator(&mut self, id: usize, clause: &Clause) {
+        for (i, literal) in clause.literals.iter().enumerate().rev() {
+            let Some(forwards) = self.demodulation_direction(literal) else {
+                continue;
+            };
+            let conditions: Vec<Literal> = clause
+                .literals
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, other)| other.negate())
+                .collect();
+            if self
+                .demodulators
+                .insert_conditional(id, literal, &conditions, forwards)
+            {
+                return;
+            }
+        }
+    }
+
     // Generate all the inferences that can be made from a given clause, plus some existing clause.
     // This function does not simplify the inferences, or use the inferences to simplify anything else.
     // The prover will do all forms of simplification separately.
@@ -795,7 +1185,7 @@ impl ActiveSet {
             ));
         }
 
-        for clause in ActiveSet::equality_factoring(&activated_step.clause) {
+        for clause in self.equality_factoring(&activated_step.clause) {
             output.push(ProofStep::new_direct(
                 &activated_step,
                 Rule::EqualityFactoring(activated_id),
@@ -815,6 +1205,8 @@ impl ActiveSet {
 
         if activated_step.clause.len() == 1 {
             self.activate_literal(&activated_step, &mut output);
+        } else {
+            self.add_conditional_demodulator(activated_id, &activated_step.clause);
         }
 
         self.insert(activated_step);
@@ -847,6 +1239,7 @@ impl ActiveSet {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::atom::{Atom, AtomId};
 
     #[test]
     fn test_activate_rewrite_pattern() {
@@ -884,6 +1277,37 @@ mod tests {
         assert_eq!(result.len(), 1);
     }
 
+    #[test]
+    fn test_kbo_orients_rewrites() {
+        // Counts the ways to rewrite c0(c3) = c2 with c1 = c3, as a pattern and as a target.
+        let count_rewrites = |heavy: AtomId| {
+            let mut kbo = Kbo::new();
+            kbo.set_weight(Atom::LocalConstant(heavy), 2);
+
+            let mut set = ActiveSet::new();
+            set.set_kbo(kbo.clone());
+            let mut step = ProofStep::mock("c0(c3) = c2");
+            step.truthiness = Truthiness::Hypothetical;
+            set.activate(step);
+            let mut pattern_results = vec![];
+            set.activate_rewrite_pattern(1, &ProofStep::mock("c1 = c3"), &mut pattern_results);
+
+            let mut set = ActiveSet::new();
+            set.set_kbo(kbo);
+            set.activate(ProofStep::mock("c1 = c3"));
+            let mut target_step = ProofStep::mock("c0(c3) = c2");
+            target_step.truthiness = Truthiness::Hypothetical;
+            let mut target_results = vec![];
+            set.activate_rewrite_target(1, &target_step, &mut target_results);
+
+            (pattern_results.len(), target_results.len())
+        };
+
+        // c3 can be rewritten into the lighter c1, but not the other way around.
+        assert_eq!(count_rewrites(3), (1, 1));
+        assert_eq!(count_rewrites(1), (0, 0));
+    }
+
     #[test]
     fn test_equality_resolution() {
         let old_clause = Clause::new(vec![
@@ -909,7 +1333,7 @@ mod tests {
             Literal::equals(Term::parse("x0"), Term::parse("c0")),
             Literal::equals(Term::parse("x1"), Term::parse("c0")),
         ]);
-        let new_clauses = ActiveSet::equality_factoring(&old_clause);
+        let new_clauses = ActiveSet::new().equality_factoring(&old_clause);
         let expected = Clause::parse("c0 = x0");
         for c in &new_clauses {
             if *c == expected {
@@ -935,6 +1359,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_subsumption() {
+        let mut set = ActiveSet::new();
+        let mut step = ProofStep::mock("c0(c2) or c1(c2) or c3");
+        step.truthiness = Truthiness::Hypothetical;
+        set.activate(step);
+        let mut step = ProofStep::mock("c0(x0) or c1(x0)");
+        step.truthiness = Truthiness::Hypothetical;
+        set.activate(step);
+
+        // Forward subsumption drops a new clause
+        let mut step = ProofStep::mock("c0(c4) or c1(c4) or not c3");
+        step.truthiness = Truthiness::Counterfactual;
+        assert!(set.simplify(step).is_none());
+
+        // Backward subsumption means only the general clause resolves with this
+        let mut step = ProofStep::mock("not c0(c2)");
+        step.truthiness = Truthiness::Counterfactual;
+        let mut results = vec![];
+        set.find_resolutions(&step, &mut results);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].clause.to_string(), "c1(c2)");
+        assert_eq!(set.take_newly_redundant(), vec![0]);
+        assert!(set.take_newly_redundant().is_empty());
+    }
+
+    #[test]
+    fn test_demodulation() {
+        let mut set = ActiveSet::new();
+        let mut step = ProofStep::mock("c4(c1(c2)) = c0 or c5(x0) = x0");
+        step.truthiness = Truthiness::Hypothetical;
+        set.activate(step);
+
+        // Backward demodulation simplifies the existing clause
+        let mut step = ProofStep::mock("c1(c2) = c3");
+        step.truthiness = Truthiness::Counterfactual;
+        let (id, new_steps) = set.activate(step);
+        let simplified: Vec<_> = new_steps
+            .iter()
+            .filter(|step| step.simplification_rules == vec![id])
+            .map(|step| &step.clause)
+            .collect();
+        assert_eq!(
+            simplified,
+            vec![&Clause::parse("c4(c3) = c0 or c5(x0) = x0")]
+        );
+        assert!(set.redundant.contains(&0));
+
+        // Forward demodulation simplifies new clauses
+        let mut step = ProofStep::mock("c5(c1(c1(c2))) = c0 or c4(x0) = x0");
+        step.truthiness = Truthiness::Counterfactual;
+        let step = set.simplify(step).unwrap();
+        assert_eq!(step.clause, Clause::parse("c5(c1(c3)) = c0 or c4(x0) = x0"));
+        assert_eq!(step.simplification_rules, vec![id]);
+    }
+
+    #[test]
+    fn test_conditional_demodulation() {
+        let mut set = ActiveSet::new();
+        let mut step = ProofStep::mock("c0(x0) != c2 or c1(x0) = x0");
+        step.truthiness = Truthiness::Hypothetical;
+        set.activate(step);
+
+        // The rewrite doesn't apply until the condition is known
+        let mut step = ProofStep::mock("c4(c1(c3)) = c5 or c6(x0) = x0");
+        step.truthiness = Truthiness::Counterfactual;
+        let unchanged = set.simplify(step.clone()).unwrap();
+        assert_eq!(unchanged.clause, step.clause);
+
+        let mut condition = ProofStep::mock("c0(c3) = c2");
+        condition.truthiness = Truthiness::Hypothetical;
+        let (id, _) = set.activate(condition);
+        let simplified = set.simplify(step).unwrap();
+        assert_eq!(
+            simplified.clause,
+            Clause::parse("c4(c3) = c5 or c6(x0) = x0")
+        );
+        assert_eq!(simplified.simplification_rules, vec![0, id]);
+    }
+
+    #[test]
+    fn test_literal_selection() {
+        let count_resolutions = |selection: LiteralSelection| {
+            let mut set = ActiveSet::new();
+            set.set_literal_selection(selection);
+            let mut step = ProofStep::mock("not c2(c0(c0(x0))) or c1(x0) != x0");
+            step.truthiness = Truthiness::Factual;
+            set.activate(step);
+            let mut step = ProofStep::mock("c1(c3) = c3");
+            step.truthiness = Truthiness::Counterfactual;
+            let (_, new_clauses) = set.activate(step);
+            new_clauses.len()
+        };
+        assert_eq!(count_resolutions(LiteralSelection::SelectNone), 1);
+        assert_eq!(count_resolutions(LiteralSelection::SelectAllNegative), 1);
+
+        // Only "not c2(c0(c0(x0)))" is selected, so the other literal can't be resolved.
+        assert_eq!(
+            count_resolutions(LiteralSelection::SelectLargestNegative),
+            0
+        );
+    }
+
     #[test]
     fn test_equality_factoring_variable_numbering() {
         // This is a bug we ran into
@@ -946,10 +1473,37 @@ mod tests {
 
         // Trichotomy
         let clause = Clause::parse("c1(x0, x1) or c1(x1, x0) or x0 = x1");
-        let output = ActiveSet::equality_factoring(&clause);
+        let output = ActiveSet::new().equality_factoring(&clause);
         assert_eq!(output[0].to_string(), "c1(x0, x0) or x0 = x0");
     }
 
+    #[test]
+    fn test_equality_factoring_with_negative_literals() {
+        // Neither equality comes first, since the negative literal sorts ahead of them.
+        let clause = Clause::parse("not c2(x0) or x0 = c0 or x1 = c0");
+        let new_clauses = ActiveSet::new().equality_factoring(&clause);
+        let expected = Clause::parse("not c2(x0) or c0 = x0");
+        assert!(new_clauses.contains(&expected));
+    }
+
+    #[test]
+    fn test_equality_factoring_skips_mirrored_pairs() {
+        // Both literals are maximal, so either could be "s = t". Each of the four ways to
+        // orient them should only be tried once, rather than once for each order.
+        let clause = Clause::parse("x0 = c0 or x1 = c0");
+        let new_clauses = ActiveSet::new().equality_factoring(&clause);
+        assert_eq!(new_clauses.len(), 4);
+    }
+
+    #[test]
+    fn test_equality_factoring_uses_maximal_literal() {
+        // c1(x0) = c0 is bigger than x1 = c0, so it has to be the literal that gets factored.
+        let clause = Clause::parse("c1(x0) = c0 or x1 = c0");
+        let new_clauses = ActiveSet::new().equality_factoring(&clause);
+        assert!(new_clauses.contains(&Clause::parse("c1(x0) = c0")));
+        assert!(!new_clauses.contains(&Clause::parse("c0 = x0")));
+    }
+
     #[test]
     fn test_self_referential_resolution() {
         // This is a bug we ran into. These things should not unify
@@ -961,4 +1515,30 @@ mod tests {
         set.find_resolutions(&step, &mut results);
         assert_eq!(results.len(), 0);
     }
+
+    #[test]
+    fn test_clone_is_independent() {
+        let mut set = ActiveSet::new();
+        set.activate(ProofStep::mock("c4(c1(c2)) = c0 or c5(x0) = x0"));
+        let mut cloned = set.clone();
+
+        // A rewrite activated in the clone doesn't leak into the original
+        let mut step = ProofStep::mock("c1(c2) = c3");
+        step.truthiness = Truthiness::Counterfactual;
+        cloned.activate(step);
+        assert_eq!(set.len(), 1);
+        assert_eq!(cloned.len(), 2);
+        assert!(cloned.redundant.contains(&0));
+        assert!(!set.redundant.contains(&0));
+
+        let mut step = ProofStep::mock("c5(c1(c2)) = c0 or c4(x0) = x0");
+        step.truthiness = Truthiness::Counterfactual;
+        let original = set.simplify(step.clone()).unwrap();
+        assert_eq!(original.clause, step.clause);
+        let simplified = cloned.simplify(step).unwrap();
+        assert_eq!(
+            simplified.clause,
+            Clause::parse("c5(c3) = c0 or c4(x0) = x0")
+        );
+    }
 }
@@ -0,0 +1,230 @@
+use std::cmp::Ordering;
+
+use crate::atom::Atom;
+use crate::literal::Literal;
+use crate::term::Term;
+
+// The symbols for addition and ordering in one numeric class.
+#[derive(Clone, Debug)]
+struct OrderedClass {
+    add: Atom,
+    lt: Option<Atom>,
+    lte: Option<Atom>,
+}
+
+// What a literal turns into, after linear arithmetic looks at it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Evaluation {
+    True,
+    False,
+    Normalized(Literal),
+    Unchanged,
+}
+
+// A decision procedure for the linear parts of literals over ordered numeric classes.
+// Each registered class must be an ordered cancellative commutative monoid, like Nat, Int,
+// or Real. Then sums can be flattened and reordered, and a summand that appears on both sides
+// of "=", "lt", or "lte" can be cancelled.
+// After cancellation, a literal with identical sides is decided, and any other literal is
+// replaced by its cancelled form, so that the prover doesn't need the raw axioms to see that
+// "a + b < b + c" is the same as "a < c".
+#[derive(Clone, Debug, Default)]
+pub struct LinearArithmetic {
+    classes: Vec<OrderedClass>,
+}
+
+impl LinearArithmetic {
+    pub fn new() -> LinearArithmetic {
+        LinearArithmetic::default()
+    }
+
+    // Registers a class by its addition and, if it has them, its ordering predicates.
+    pub fn register(&mut self, add: Atom, lt: Option<Atom>, lte: Option<Atom>) {
+        self.classes.push(OrderedClass { add, lt, lte });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    pub fn evaluate(&self, literal: &Literal) -> Evaluation {
+        if literal.right.is_true() {
+            let term = &literal.left;
+            if term.args.len() != 2 {
+                return Evaluation::Unchanged;
+            }
+            for class in &self.classes {
+                let strict = if Some(term.head) == class.lt {
+                    true
+                } else if Some(term.head) == class.lte {
+                    false
+                } else {
+                    continue;
+                };
+                return match cancel(class.add, &term.args[0], &term.args[1]) {
+                    Cancellation::Same => decided(literal.positive != strict),
+                    Cancellation::Reduced(left, right) => Evaluation::Normalized(Literal::new(
+                        literal.positive,
+                        term.replace_args(vec![left, right]),
+                        Term::new_true(),
+                    )),
+                    Cancellation::Unchanged => Evaluation::Unchanged,
+                };
+            }
+            return Evaluation::Unchanged;
+        }
+
+        for class in &self.classes {
+            if literal.left.head != class.add && literal.right.head != class.add {
+                continue;
+            }
+            return match cancel(class.add, &literal.left, &literal.right) {
+                Cancellation::Same => decided(literal.positive),
+                Cancellation::Reduced(left, right) => {
+                    Evaluation::Normalized(Literal::new(literal.positive, left, right))
+                }
+                Cancellation::Unchanged => Evaluation::Unchanged,
+            };
+        }
+        Evaluation::Unchanged
+    }
+}
+
+fn decided(value: bool) -> Evaluation {
+    if value {
+        Evaluation::True
+    } else {
+        Evaluation::False
+    }
+}
+
+enum Cancellation {
+    // Everything cancelled, so the two sides are equal.
+    Same,
+
+    // Some summands cancelled, leaving these two sides.
+    Reduced(Term, Term),
+
+    // Nothing cancelled, or cancelling would have left a side with no summands.
+    Unchanged,
+}
+
+// Cancels the summands that the two sides have in common.
+fn cancel(add: Atom, left: &Term, right: &Term) -> Cancellation {
+    let mut left_summands = vec![];
+    summands(add, left, &mut left_summands);
+    let mut right_summands = vec![];
+    summands(add, right, &mut right_summands);
+    // Summands are kept sorted, so that the same sum always comes out the same way.
+    let order = |a: &&Term, b: &&Term| a.extended_kbo_cmp(b);
+    left_summands.sort_by(order);
+    right_summands.sort_by(order);
+    if left_summands == right_summands {
+        return Cancellation::Same;
+    }
+
+    let mut left_rest = vec![];
+    let mut right_rest = vec![];
+    let mut i = 0;
+    let mut j = 0;
+    while i < left_summands.len() && j < right_summands.len() {
+        match order(&left_summands[i], &right_summands[j]) {
+            Ordering::Less => {
+                left_rest.push(left_summands[i]);
+                i += 1;
+            }
+            Ordering::Greater => {
+                right_rest.push(right_summands[j]);
+                j += 1;
+            }
+            Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    left_rest.extend(&left_summands[i..]);
+    right_rest.extend(&right_summands[j..]);
+
+    if left_rest.len() == left_summands.len() || left_rest.is_empty() || right_rest.is_empty() {
+        // There's no zero to stand in for an empty side.
+        return Cancellation::Unchanged;
+    }
+    let Some(add_term) = find_sum(add, left).or_else(|| find_sum(add, right)) else {
+        return Cancellation::Unchanged;
+    };
+    Cancellation::Reduced(
+        build_sum(add_term, &left_rest),
+        build_sum(add_term, &right_rest),
+    )
+}
+
+// Flattens a sum into its summands.
+fn summands<'a>(add: Atom, term: &'a Term, answer: &mut Vec<&'a Term>) {
+    if term.head == add && term.args.len() == 2 {
+        summands(add, &term.args[0], answer);
+        summands(add, &term.args[1], answer);
+    } else {
+        answer.push(term);
+    }
+}
+
+// Finds a sum within this term, to use as a template for building new sums.
+fn find_sum(add: Atom, term: &Term) -> Option<&Term> {
+    if term.head == add && term.args.len() == 2 {
+        Some(term)
+    } else {
+        None
+    }
+}
+
+// Adds up the summands, nesting to the right.
+fn build_sum(add_term: &Term, summands: &[&Term]) -> Term {
+    let (last, rest) = summands.split_last().unwrap();
+    rest.iter().rev().fold((*last).clone(), |sum, summand| {
+        add_term.replace_args(vec![(*summand).clone(), sum])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arithmetic() -> LinearArithmetic {
+        let mut arithmetic = LinearArithmetic::new();
+        arithmetic.register(
+            Atom::LocalConstant(0),
+            Some(Atom::LocalConstant(1)),
+            Some(Atom::LocalConstant(2)),
+        );
+        arithmetic
+    }
+
+    fn evaluate(s: &str) -> Evaluation {
+        arithmetic().evaluate(&Literal::parse(s))
+    }
+
+    #[test]
+    fn test_linear_arithmetic() {
+        assert_eq!(evaluate("c0(c3, c4) = c0(c4, c3)"), Evaluation::True);
+        assert_eq!(
+            evaluate("c0(c3, c0(c4, c5)) != c0(c0(c5, c3), c4)"),
+            Evaluation::False
+        );
+        assert_eq!(evaluate("c1(c0(c3, c4), c0(c4, c3))"), Evaluation::False);
+        assert_eq!(evaluate("not c1(c0(c3, c4), c0(c4, c3))"), Evaluation::True);
+        assert_eq!(evaluate("c2(c0(c3, x0), c0(x0, c3))"), Evaluation::True);
+        assert_eq!(
+            evaluate("c1(c0(c3, c4), c0(c5, c4))"),
+            Evaluation::Normalized(Literal::parse("c1(c3, c5)"))
+        );
+        assert_eq!(
+            evaluate("c0(c3, c0(c4, c5)) = c0(c6, c0(c5, c7))"),
+            Evaluation::Normalized(Literal::parse("c0(c3, c4) = c0(c6, c7)"))
+        );
+
+        // Cancelling would leave nothing on one side
+        assert_eq!(evaluate("c1(c3, c0(c3, c4))"), Evaluation::Unchanged);
+        assert_eq!(evaluate("c1(c3, c4)"), Evaluation::Unchanged);
+    }
+}
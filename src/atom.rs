@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 pub type AtomId = u16;
 
 pub const INVALID_ATOM_ID: AtomId = 0xffff;
@@ -8,7 +10,7 @@ pub const INVALID_ATOM_ID: AtomId = 0xffff;
 // An atomic value does not have any internal structure.
 // The Atom is a lower-level representation.
 // It is used in the prover, but not in the AcornValue / Environment.
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Atom {
     True,
 
@@ -0,0 +1,113 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::environment::{Environment, LineType};
+use crate::module::ModuleId;
+use crate::project::Project;
+use crate::proposition::{Source, SourceType};
+
+// A named axiom or theorem, identified by the module it's in and its name.
+type Name = (ModuleId, String);
+
+// The named facts that the proofs of a theorem cited.
+#[derive(Default)]
+struct Citations {
+    axioms: HashSet<Name>,
+    theorems: HashSet<Name>,
+}
+
+// The axiom audit tracks which axioms each theorem rests on.
+// It's created from a verification run, using the facts that each proof cited.
+// A theorem depends on the axioms its proofs cite directly, and on the axioms that any
+// theorem its proofs cite depends on.
+//
+// Only fresh proofs tell us what they cite, so a build with an audit shouldn't use the cache.
+// Facts without names, like anonymous theorems, are not tracked.
+#[derive(Default)]
+pub struct AxiomAudit {
+    // For each theorem, the named axioms and theorems that its proofs cited.
+    direct: HashMap<Name, Citations>,
+
+    // The sources cited by proofs in the module currently being proved.
+    // Keyed by the first line of the goal.
+    current: Vec<(u32, Vec<Source>)>,
+}
+
+impl AxiomAudit {
+    pub fn new() -> Self {
+        AxiomAudit::default()
+    }
+
+    // Called when a proof is found for a goal in the current module.
+    pub fn add_proof<'a>(&mut self, first_line: u32, sources: impl Iterator<Item = &'a Source>) {
+        self.current.push((first_line, sources.cloned().collect()));
+    }
+
+    // Called when all the goals in a module have been proved.
+    // Each proof counts towards the top-level theorem that contains its goal.
+    pub fn module_complete(&mut self, env: &Environment) {
+        for (first_line, sources) in std::mem::take(&mut self.current) {
+            let Some(LineType::Node(index)) = env.get_line_type(first_line) else {
+                continue;
+            };
+            let theorem = match &env.nodes[index].claim.source.source_type {
+                SourceType::Theorem(Some(name)) => (env.module_id, name.clone()),
+                _ => continue,
+            };
+            let citations = self.direct.entry(theorem).or_default();
+            for source in sources {
+                match &source.source_type {
+                    SourceType::Axiom(Some(name)) => {
+                        citations.axioms.insert((source.module, name.clone()));
+                    }
+                    SourceType::Theorem(Some(name)) => {
+                        citations.theorems.insert((source.module, name.clone()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // All the axioms that a theorem depends on, directly or through other theorems.
+    // Theorems that weren't proved in this build, like lemmas inside proof blocks, don't
+    // add anything. A lemma's own proof is part of the theorem that contains it anyway.
+    pub fn axioms_for(&self, theorem: &Name) -> BTreeSet<Name> {
+        let mut axioms = BTreeSet::new();
+        let mut seen = HashSet::from([theorem]);
+        let mut pending = vec![theorem];
+        while let Some(name) = pending.pop() {
+            let Some(citations) = self.direct.get(name) else {
+                continue;
+            };
+            axioms.extend(citations.axioms.iter().cloned());
+            for cited in &citations.theorems {
+                if seen.insert(cited) {
+                    pending.push(cited);
+                }
+            }
+        }
+        axioms
+    }
+
+    // One line for each theorem, listing the axioms it depends on.
+    // Names are qualified by their module, and the lines are sorted.
+    pub fn report(&self, project: &Project) -> Vec<String> {
+        let qualify = |(module_id, name): &Name| {
+            format!("{}.{}", project.get_module_descriptor(*module_id), name)
+        };
+        let mut lines: Vec<_> = self
+            .direct
+            .keys()
+            .map(|theorem| {
+                let axioms: Vec<_> = self.axioms_for(theorem).iter().map(qualify).collect();
+                if axioms.is_empty() {
+                    format!("{}: no axioms", qualify(theorem))
+                } else {
+                    format!("{}: {}", qualify(theorem), axioms.join(", "))
+                }
+            })
+            .collect();
+        lines.sort();
+        lines
+    }
+}
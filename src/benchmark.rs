@@ -0,0 +1,230 @@
+use std::fmt;
+
+use crate::module::ModuleDescriptor;
+use crate::prover::Outcome;
+
+// The budgets and repetitions to use when benchmarking the prover.
+#[derive(Debug, Clone)]
+pub struct BenchmarkConfig {
+    // How many times to run the search for each goal.
+    pub iterations: usize,
+
+    // The maximum size of the active set, like in Prover::search_for_contradiction.
+    pub activation_limit: i32,
+
+    // The time limit for each search, in seconds.
+    pub seconds: f32,
+
+    // Whether to search in verification mode.
+    pub verification: bool,
+}
+
+impl Default for BenchmarkConfig {
+    // The same budget as a normal verification build.
+    fn default() -> Self {
+        BenchmarkConfig {
+            iterations: 5,
+            activation_limit: 2000,
+            seconds: 5.0,
+            verification: true,
+        }
+    }
+}
+
+// The results of running the prover on a single goal, several times.
+#[derive(Debug, Clone)]
+pub struct GoalBenchmark {
+    pub module: ModuleDescriptor,
+
+    // The printable name of the goal.
+    pub goal_name: String,
+
+    // One entry per run.
+    pub outcomes: Vec<Outcome>,
+
+    // How long each run took, in seconds.
+    pub times: Vec<f64>,
+
+    // How many clauses each run activated.
+    pub activations: Vec<usize>,
+}
+
+// Returns the value at the given percentile, using the nearest-rank method.
+// Returns 0 for an empty list.
+fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+impl GoalBenchmark {
+    pub fn new(module: ModuleDescriptor, goal_name: String) -> GoalBenchmark {
+        GoalBenchmark {
+            module,
+            goal_name,
+            outcomes: vec![],
+            times: vec![],
+            activations: vec![],
+        }
+    }
+
+    pub fn add_run(&mut self, outcome: Outcome, seconds: f64, activations: usize) {
+        self.outcomes.push(outcome);
+        self.times.push(seconds);
+        self.activations.push(activations);
+    }
+
+    pub fn mean_time(&self) -> f64 {
+        mean(&self.times)
+    }
+
+    pub fn median_time(&self) -> f64 {
+        percentile(&self.times, 50.0)
+    }
+
+    pub fn p95_time(&self) -> f64 {
+        percentile(&self.times, 95.0)
+    }
+
+    pub fn mean_activations(&self) -> f64 {
+        let activations: Vec<_> = self.activations.iter().map(|a| *a as f64).collect();
+        mean(&activations)
+    }
+
+    // How many of the runs succeeded.
+    pub fn num_success(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|o| **o == Outcome::Success)
+            .count()
+    }
+}
+
+// A report of a whole benchmark run, with one entry per goal.
+// The version is included so that reports from different versions of the crate can be
+// compared against each other.
+#[derive(Debug, Clone)]
+pub struct BenchmarkReport {
+    pub version: String,
+    pub config: BenchmarkConfig,
+    pub goals: Vec<GoalBenchmark>,
+}
+
+impl BenchmarkReport {
+    pub fn new(config: BenchmarkConfig) -> BenchmarkReport {
+        BenchmarkReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config,
+            goals: vec![],
+        }
+    }
+
+    // Statistics over every run of every goal.
+    pub fn all_times(&self) -> Vec<f64> {
+        self.goals.iter().flat_map(|g| g.times.clone()).collect()
+    }
+
+    pub fn mean_time(&self) -> f64 {
+        mean(&self.all_times())
+    }
+
+    pub fn median_time(&self) -> f64 {
+        percentile(&self.all_times(), 50.0)
+    }
+
+    pub fn p95_time(&self) -> f64 {
+        percentile(&self.all_times(), 95.0)
+    }
+
+    pub fn total_activations(&self) -> usize {
+        self.goals
+            .iter()
+            .map(|g| g.activations.iter().sum::<usize>())
+            .sum()
+    }
+
+    // Writes the report in a tab-separated form, with one line per goal.
+    // This is easier to compare across versions than the human-readable form.
+    pub fn to_tsv(&self) -> String {
+        let mut answer = String::from(
+            "module\tgoal\tsuccess\truns\tmean_secs\tmedian_secs\tp95_secs\tmean_activations\n",
+        );
+        for goal in &self.goals {
+            answer.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.1}\n",
+                goal.module,
+                goal.goal_name,
+                goal.num_success(),
+                goal.outcomes.len(),
+                goal.mean_time(),
+                goal.median_time(),
+                goal.p95_time(),
+                goal.mean_activations(),
+            ));
+        }
+        answer
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "acorn {} benchmark: {} goals, {} iterations each",
+            self.version,
+            self.goals.len(),
+            self.config.iterations
+        )?;
+        for goal in &self.goals {
+            writeln!(
+                f,
+                "{} {}: {}/{} ok, mean {:.3}s, median {:.3}s, p95 {:.3}s, {:.1} activations",
+                goal.module,
+                goal.goal_name,
+                goal.num_success(),
+                goal.outcomes.len(),
+                goal.mean_time(),
+                goal.median_time(),
+                goal.p95_time(),
+                goal.mean_activations(),
+            )?;
+        }
+        write!(
+            f,
+            "overall: mean {:.3}s, median {:.3}s, p95 {:.3}s, {} activations",
+            self.mean_time(),
+            self.median_time(),
+            self.p95_time(),
+            self.total_activations()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goal_benchmark_stats() {
+        let mut goal = GoalBenchmark::new(ModuleDescriptor::Anonymous, "goal".to_string());
+        for i in 1..=20 {
+            goal.add_run(Outcome::Success, i as f64, 10 * i);
+        }
+        assert_eq!(goal.mean_time(), 10.5);
+        assert_eq!(goal.median_time(), 10.0);
+        assert_eq!(goal.p95_time(), 19.0);
+        assert_eq!(goal.mean_activations(), 105.0);
+        assert_eq!(goal.num_success(), 20);
+    }
+}
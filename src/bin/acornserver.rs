@@ -18,7 +18,7 @@ use tower_lsp::{Client, LanguageServer, LspService, Server};
 use acorn::block::NodeCursor;
 use acorn::interfaces::{
     DocumentProgress, InfoParams, InfoResponse, ProgressParams, ProgressResponse, SearchParams,
-    SearchResponse, SearchStatus,
+    SearchResponse, SearchStatus, SnapshotParams, SnapshotResponse,
 };
 use acorn::module::{LoadState, ModuleDescriptor};
 use acorn::project::Project;
@@ -53,6 +53,23 @@ fn to_path(url: &Url) -> Option<PathBuf> {
     }
 }
 
+// A quick fix that applies one edit to a document.
+fn quick_fix(url: &Url, title: String, edit: TextEdit) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(url.clone(), vec![edit])])),
+            ..WorkspaceEdit::default()
+        }),
+        ..CodeAction::default()
+    })
+}
+
+fn overlaps(a: &Range, b: &Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
 fn log_with_url(url: &Url, version: i32, message: &str) {
     let versioned = format!("{} v{}: {}", url, version, message);
     log(&versioned);
@@ -143,6 +160,9 @@ impl SearchTask {
                 return;
             }
         };
+        // Generate code with the bindings of the block the proof goes in, so that block-level
+        // settings like numerals apply.
+        let env = env.env_for_line(self.proof_insertion_line);
 
         log(&format!("running search task for {}", self.goal_name));
 
@@ -155,18 +175,27 @@ impl SearchTask {
                 Outcome::Success => {
                     let proof = prover.get_proof().unwrap();
                     let steps = prover.to_proof_info(&project, &env.bindings, &proof);
+                    let premises = prover.to_premise_info(&project, &proof);
 
                     let (code, error) = match proof.to_code(&env.bindings) {
                         Ok(code) => (Some(code), None),
                         Err(e) => (None, Some(e.to_string())),
                     };
 
-                    SearchStatus::success(code, error, steps, proof.needs_simplification(), &prover)
+                    SearchStatus::success(
+                        code,
+                        error,
+                        steps,
+                        premises,
+                        proof.needs_simplification(),
+                        &prover,
+                    )
                 }
 
                 Outcome::Inconsistent
                 | Outcome::Exhausted
                 | Outcome::Constrained
+                | Outcome::Countermodel
                 | Outcome::Error => SearchStatus::stopped(&prover, outcome),
 
                 Outcome::Timeout => SearchStatus::pending(&prover),
@@ -775,6 +804,55 @@ impl Backend {
             result,
         })
     }
+
+    fn snapshot_fail(
+        &self,
+        params: SnapshotParams,
+        message: &str,
+    ) -> jsonrpc::Result<SnapshotResponse> {
+        log(message);
+        Ok(SnapshotResponse {
+            search_id: params.search_id,
+            failure: Some(message.to_string()),
+            snapshot: None,
+        })
+    }
+
+    // Looks at the current search, which may still be running.
+    // The search only holds the prover lock during a partial search, so this doesn't block
+    // for long.
+    async fn handle_snapshot_request(
+        &self,
+        params: SnapshotParams,
+    ) -> jsonrpc::Result<SnapshotResponse> {
+        let locked_task = self.search_task.read().await;
+
+        let task = match locked_task.as_ref() {
+            Some(task) => task,
+            None => return self.snapshot_fail(params, "no search task available"),
+        };
+        if task.id != params.search_id {
+            let failure = format!(
+                "snapshot request has search id {}, task has id {}",
+                params.search_id, task.id
+            );
+            return self.snapshot_fail(params, &failure);
+        }
+        let project = self.project.read().await;
+        let prover = task.prover.read().await;
+        let env = match project.get_env(&task.descriptor) {
+            Some(env) => env,
+            None => {
+                return self.snapshot_fail(params, "no environment available");
+            }
+        };
+        let snapshot = prover.snapshot_state(&env.bindings, params.count);
+        Ok(SnapshotResponse {
+            search_id: params.search_id,
+            failure: None,
+            snapshot: Some(snapshot),
+        })
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -794,6 +872,7 @@ impl LanguageServer for Backend {
             capabilities: ServerCapabilities {
                 text_document_sync: Some(sync_options),
                 completion_provider: Some(CompletionOptions::default()),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 ..ServerCapabilities::default()
             },
         })
@@ -884,6 +963,47 @@ impl LanguageServer for Backend {
         }
     }
 
+    // Offers to remove unused imports, and to import names that aren't defined, when the
+    // requested range touches them.
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> jsonrpc::Result<Option<CodeActionResponse>> {
+        let url = params.text_document.uri;
+        let path = match to_path(&url) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let project = self.project.read().await;
+        let analysis = match project
+            .descriptor_from_path(&path)
+            .and_then(|descriptor| project.analyze_imports(&descriptor))
+        {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                log(&format!("import analysis failed: {}", e));
+                return Ok(None);
+            }
+        };
+        let mut actions = vec![];
+        for unused in analysis.unused {
+            if overlaps(&unused.range, &params.range) {
+                let title = format!("Remove unused import {}", unused.name);
+                actions.push(quick_fix(&url, title, unused.edit));
+            }
+        }
+        for missing in analysis.missing {
+            if !overlaps(&missing.range, &params.range) {
+                continue;
+            }
+            for (module, edit) in missing.options {
+                let title = format!("Import {} from {}", missing.name, module);
+                actions.push(quick_fix(&url, title, edit));
+            }
+        }
+        Ok(Some(actions))
+    }
+
     async fn shutdown(&self) -> jsonrpc::Result<()> {
         log("shutdown");
         Ok(())
@@ -899,6 +1019,7 @@ async fn main() {
         .custom_method("acorn/info", Backend::handle_info_request)
         .custom_method("acorn/progress", Backend::handle_progress_request)
         .custom_method("acorn/search", Backend::handle_search_request)
+        .custom_method("acorn/snapshot", Backend::handle_snapshot_request)
         .finish();
 
     Server::new(stdin, stdout, socket).serve(service).await;
@@ -0,0 +1,74 @@
+// Benchmarks the prover on an acorn file, or the whole project.
+//
+// Each goal is proved several times, and we report timing statistics along with the
+// number of activations. Run the same benchmark with different versions of the prover
+// on a fixed library to see whether performance has changed.
+//
+// Try:
+//   cargo build --release --bin=benchmark; ~/acorn/target/release/benchmark --tsv
+
+use std::path::PathBuf;
+
+use acorn::benchmark::BenchmarkConfig;
+use acorn::project::Project;
+use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    // Benchmark a single module.
+    // Can be either a filename or a module name.
+    #[clap()]
+    target: Option<String>,
+
+    // How many times to prove each goal.
+    #[clap(long, default_value = "5")]
+    iterations: usize,
+
+    // The maximum number of activations for each search.
+    #[clap(long, default_value = "2000")]
+    activations: i32,
+
+    // The time limit for each search, in seconds.
+    #[clap(long, default_value = "5.0")]
+    seconds: f32,
+
+    // Output tab-separated values, one line per goal.
+    #[clap(long)]
+    tsv: bool,
+}
+
+fn main() {
+    let mut project = Project::new_local().unwrap();
+
+    let args = Args::parse();
+    if let Some(target) = args.target {
+        if target.ends_with(".ac") {
+            // Looks like a filename
+            let path = PathBuf::from(&target);
+            if !project.add_target_by_path(&path) {
+                println!("File not found: {}", target);
+                return;
+            }
+        } else {
+            if !project.add_target_by_name(&target) {
+                println!("Module not found: {}", target);
+                return;
+            }
+        }
+    } else {
+        project.add_all_targets();
+    }
+
+    let config = BenchmarkConfig {
+        iterations: args.iterations,
+        activation_limit: args.activations,
+        seconds: args.seconds,
+        verification: true,
+    };
+    let report = project.benchmark(config);
+    if args.tsv {
+        print!("{}", report.to_tsv());
+    } else {
+        println!("{}", report);
+    }
+}
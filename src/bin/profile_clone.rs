@@ -0,0 +1,94 @@
+// Measures the cost of cloning the active set, which happens whenever the prover is cloned
+// for a nested block.
+// To profile using samply:
+//
+//   cargo build --bin=profile_clone --profile=fastdev
+//   samply record target/fastdev/profile_clone
+
+use acorn::active_set::ActiveSet;
+use acorn::proof_step::ProofStep;
+
+// A small deterministic generator, so that every run activates the same clauses.
+struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    fn next(&mut self, n: u64) -> u64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) % n
+    }
+
+    // Makes the text of a random term, using constants c0 through c8 along with a few
+    // variables. Each constant always takes the same number of arguments.
+    fn term(&mut self, depth: u32) -> String {
+        let c = self.next(9);
+        let num_args = if depth == 0 { 0 } else { c % 3 };
+        if num_args == 0 {
+            return if self.next(5) == 0 {
+                format!("x{}", self.next(3))
+            } else {
+                format!("c{}", 3 * self.next(3))
+            };
+        }
+        let args: Vec<_> = (0..num_args).map(|_| self.term(depth - 1)).collect();
+        format!("c{}({})", c, args.join(", "))
+    }
+
+    // Makes the text of a random clause with one to three literals.
+    fn clause(&mut self) -> String {
+        let literals: Vec<_> = (0..(1 + self.next(3)))
+            .map(|_| {
+                let op = if self.next(3) == 0 { "!=" } else { "=" };
+                format!("{} {} {}", self.term(3), op, self.term(3))
+            })
+            .collect();
+        literals.join(" or ")
+    }
+}
+
+fn main() {
+    let num_activated = 2000;
+    let num_clones = 200;
+    let mut generator = Generator { state: 1 };
+    let clauses: Vec<_> = (0..(num_activated + num_clones))
+        .map(|_| generator.clause())
+        .collect();
+
+    let start = std::time::Instant::now();
+    let mut active_set = ActiveSet::new();
+    for clause in &clauses[..num_activated] {
+        active_set.activate(ProofStep::mock(clause));
+    }
+    println!(
+        "activating {} clauses: {:.3} seconds",
+        num_activated,
+        start.elapsed().as_secs_f32()
+    );
+
+    let start = std::time::Instant::now();
+    for _ in 0..num_clones {
+        let cloned = active_set.clone();
+        drop(cloned);
+    }
+    println!(
+        "{} clones: {:.3} seconds",
+        num_clones,
+        start.elapsed().as_secs_f32()
+    );
+
+    // A nested block clones the prover and then adds a few facts of its own.
+    let start = std::time::Instant::now();
+    for clause in &clauses[num_activated..] {
+        let mut cloned = active_set.clone();
+        cloned.activate(ProofStep::mock(clause));
+    }
+    println!(
+        "{} clones with an activation each: {:.3} seconds",
+        num_clones,
+        start.elapsed().as_secs_f32()
+    );
+}
@@ -0,0 +1,95 @@
+// Compares the term indexes that find unification candidates.
+// To profile using samply:
+//
+//   cargo build --bin=profile_index --profile=fastdev
+//   samply record target/fastdev/profile_index
+
+use acorn::discrimination_tree::DiscriminationTree;
+use acorn::fingerprint::FingerprintUnifier;
+use acorn::term::Term;
+
+// A small deterministic generator, so that every run indexes the same terms.
+struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    fn next(&mut self, n: u64) -> u64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) % n
+    }
+
+    // Makes the text of a random term, using constants c0 through c9 as functions of
+    // up to two arguments, along with a few variables.
+    fn term(&mut self, depth: u32) -> String {
+        if depth == 0 || self.next(3) == 0 {
+            return if self.next(20) == 0 {
+                format!("x{}", self.next(3))
+            } else {
+                format!("c{}", self.next(10))
+            };
+        }
+        let head = format!("c{}", self.next(10));
+        let args: Vec<_> = (0..(1 + self.next(2)))
+            .map(|_| self.term(depth - 1))
+            .collect();
+        format!("{}({})", head, args.join(", "))
+    }
+}
+
+fn main() {
+    let num_stored = 20000;
+    let num_queries = 2000;
+    let mut generator = Generator { state: 1 };
+    let stored: Vec<_> = (0..num_stored)
+        .map(|_| Term::parse(&generator.term(4)))
+        .collect();
+    let queries: Vec<_> = (0..num_queries)
+        .map(|_| Term::parse(&generator.term(4)))
+        .collect();
+
+    let start = std::time::Instant::now();
+    let mut fingerprint = FingerprintUnifier::new();
+    for (i, term) in stored.iter().enumerate() {
+        fingerprint.insert(term, i);
+    }
+    println!(
+        "fingerprint insert: {:.3} seconds",
+        start.elapsed().as_secs_f32()
+    );
+
+    let start = std::time::Instant::now();
+    let mut tree = DiscriminationTree::new();
+    for (i, term) in stored.iter().enumerate() {
+        tree.insert(term, i);
+    }
+    println!(
+        "discrimination tree insert: {:.3} seconds",
+        start.elapsed().as_secs_f32()
+    );
+
+    let start = std::time::Instant::now();
+    let mut fingerprint_candidates = 0;
+    for query in &queries {
+        fingerprint_candidates += fingerprint.find_unifying(query).len();
+    }
+    println!(
+        "fingerprint query: {:.3} seconds, {} candidates",
+        start.elapsed().as_secs_f32(),
+        fingerprint_candidates
+    );
+
+    let start = std::time::Instant::now();
+    let mut tree_candidates = 0;
+    for query in &queries {
+        tree_candidates += tree.find_unifying(query).len();
+    }
+    println!(
+        "discrimination tree query: {:.3} seconds, {} candidates",
+        start.elapsed().as_secs_f32(),
+        tree_candidates
+    );
+}
@@ -51,7 +51,8 @@ async fn main() {
                 println!("Success!");
                 prover.get_and_print_proof();
                 let proof = prover.get_proof().unwrap();
-                match proof.to_code(&env.bindings) {
+                let block_env = env.env_for_line(goal_context.proof_insertion_line);
+                match proof.to_code(&block_env.bindings) {
                     Ok(code) => {
                         println!("\ngenerated code:\n");
                         for line in &code {
@@ -80,6 +81,10 @@ async fn main() {
             Outcome::Constrained => {
                 println!("Constrained.");
             }
+            Outcome::Countermodel => {
+                println!("Found a countermodel:");
+                print!("{}", prover.display_countermodel().unwrap_or_default());
+            }
             Outcome::Error => {
                 println!(
                     "Error: {}",
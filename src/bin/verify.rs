@@ -9,8 +9,17 @@
 //   cargo build --release --bin=verify; time ~/acorn/target/release/verify
 
 use std::path::PathBuf;
+use std::time::Duration;
 
+use acorn::builder::BuildStatus;
+use acorn::fact::TagFilter;
+use acorn::limits::SearchLimits;
+use acorn::model_finder::ModelFinder;
 use acorn::project::Project;
+use acorn::prover_config::ProverConfig;
+use acorn::relevance::RelevanceFilter;
+use acorn::smt::SmtConfig;
+use acorn::tptp::AtpConfig;
 use clap::Parser;
 
 #[derive(Parser)]
@@ -23,6 +32,84 @@ struct Args {
     // Create a dataset from the prover logs.
     #[clap(long)]
     dataset: bool,
+
+    // Report which axioms each theorem depends on.
+    // This reproves everything, rather than using the build cache.
+    #[clap(long)]
+    audit: bool,
+
+    // Load modules from compiled artifacts when they are up to date, and save compiled
+    // artifacts for the modules that build cleanly.
+    #[clap(long)]
+    compiled: bool,
+
+    // Leave imported facts with this tag out of every proof search.
+    // Can be repeated.
+    #[clap(long)]
+    exclude_tag: Vec<String>,
+
+    // Only give each goal the imported facts that share symbols with it, admitting this many
+    // tiers of related facts.
+    #[clap(long)]
+    relevance_depth: Option<usize>,
+
+    // When the prover can't verify a goal, try again with z3.
+    #[clap(long)]
+    smt: bool,
+
+    // When a goal still isn't verified, try again with an external prover.
+    // Can be "eprover" or "vampire".
+    #[clap(long)]
+    atp: Option<String>,
+
+    // When a goal can't be verified, look for a small countermodel that shows it's false.
+    #[clap(long)]
+    countermodels: bool,
+
+    // After a goal verifies, prove it again with only the facts the proof used, until that
+    // stops shrinking the proof. Makes the axiom audit more precise.
+    #[clap(long)]
+    minimize: bool,
+
+    // Pass up to this many lemmas that earlier proofs in a module derived on to later goals.
+    #[clap(long, default_value = "0")]
+    lemma_pool: usize,
+
+    // Limits for each goal's search, replacing the defaults.
+    // Modules and theorems can still override them.
+    #[clap(long)]
+    max_seconds: Option<f32>,
+    #[clap(long)]
+    max_active: Option<usize>,
+    #[clap(long)]
+    max_clauses: Option<usize>,
+    #[clap(long)]
+    max_memory_mb: Option<usize>,
+
+    // Ignore time limits, so that the results don't depend on the machine.
+    #[clap(long)]
+    deterministic: bool,
+
+    // Re-check every proof with the proof checker, and report a proof that fails as an error.
+    #[clap(long)]
+    check_proofs: bool,
+
+    // Save the steps of each proof in the build directory, for a later build to replay.
+    #[clap(long)]
+    record_proofs: bool,
+
+    // Check the saved proofs instead of searching, and fail on any goal they don't prove.
+    #[clap(long)]
+    replay: bool,
+
+    // When the prover can't verify a goal, try a portfolio of other prover strategies.
+    #[clap(long)]
+    portfolio: bool,
+
+    // Report unused imports, and names that could be imported from other modules,
+    // instead of verifying.
+    #[clap(long)]
+    imports: bool,
 }
 
 #[tokio::main]
@@ -30,6 +117,53 @@ async fn main() {
     let mut project = Project::new_local().unwrap();
 
     let args = Args::parse();
+    project.set_use_compiled_modules(args.compiled);
+    if !args.exclude_tag.is_empty() {
+        project.set_tag_filter(TagFilter::excluding(
+            args.exclude_tag.iter().map(|tag| tag.as_str()),
+        ));
+    }
+    if let Some(depth) = args.relevance_depth {
+        project.set_relevance_filter(Some(RelevanceFilter {
+            max_depth: depth,
+            ..RelevanceFilter::default()
+        }));
+    }
+    if args.smt {
+        project.set_smt_config(Some(SmtConfig::default()));
+    }
+    if let Some(atp) = &args.atp {
+        let config = match atp.as_str() {
+            "eprover" => AtpConfig::default(),
+            "vampire" => AtpConfig::vampire(),
+            _ => {
+                println!("Unknown external prover: {}", atp);
+                return;
+            }
+        };
+        project.set_atp_config(Some(config));
+    }
+    if args.countermodels {
+        project.set_model_finder(Some(ModelFinder::default()));
+    }
+    project.set_minimize_premises(args.minimize);
+    project.set_lemma_pool_size(args.lemma_pool);
+    project.set_deterministic(args.deterministic);
+    project.set_check_proofs(args.check_proofs);
+    project.set_record_proofs(args.record_proofs);
+    project.set_replay_proofs(args.replay);
+    let default_limits = SearchLimits::default();
+    project.set_search_limits(SearchLimits {
+        time: args
+            .max_seconds
+            .map_or(default_limits.time, Duration::from_secs_f32),
+        max_active: args.max_active.unwrap_or(default_limits.max_active),
+        max_clauses: args.max_clauses,
+        max_memory: args.max_memory_mb.map(|mb| mb << 20),
+    });
+    if args.portfolio {
+        project.set_portfolio(ProverConfig::portfolio());
+    }
     if let Some(target) = args.target {
         if target.ends_with(".ac") {
             // Looks like a filename
@@ -48,6 +182,20 @@ async fn main() {
         project.add_all_targets();
     }
 
+    if args.imports {
+        for target in project.targets() {
+            match project.analyze_imports(target) {
+                Ok(analysis) => {
+                    for line in analysis.report() {
+                        println!("{}, {}", target, line);
+                    }
+                }
+                Err(e) => println!("{}: {}", target, e),
+            }
+        }
+        return;
+    }
+
     // Set up the builder
     let mut builder = project.builder(|event| {
         if let Some(m) = event.log_message {
@@ -67,6 +215,9 @@ async fn main() {
     if args.dataset {
         builder.create_dataset();
     }
+    if args.audit {
+        builder.create_axiom_audit();
+    }
 
     // Build
     project.build(&mut builder);
@@ -74,4 +225,13 @@ async fn main() {
     if let Some(dataset) = builder.dataset {
         dataset.save();
     }
+    if let Some(audit) = builder.axiom_audit {
+        if builder.status == BuildStatus::Good {
+            for line in audit.report(&project) {
+                println!("{}", line);
+            }
+        } else {
+            println!("The build did not succeed, so there is no axiom audit.");
+        }
+    }
 }
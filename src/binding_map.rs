@@ -1,19 +1,25 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind};
+use im::{OrdMap, OrdSet};
+use serde::{Deserialize, Serialize};
+
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Documentation};
 
 use crate::acorn_type::{AcornType, TypeClass};
-use crate::acorn_value::{AcornValue, BinaryOp};
+use crate::acorn_value::{AcornValue, BinaryOp, ConstantInstance};
 use crate::atom::AtomId;
 use crate::code_gen_error::CodeGenError;
+use crate::code_style::CodeStyle;
 use crate::compilation::{self, ErrorSource};
 use crate::expression::{Declaration, Expression, Terminator};
+use crate::match_coverage::{MatchCoverage, PatternShape};
 use crate::module::{ModuleId, FIRST_NORMAL};
 use crate::project::Project;
-use crate::termination_checker::TerminationChecker;
 use crate::token::{self, Token, TokenIter, TokenType};
+use crate::value_visitor::{ConstantFinder, UsageCollector, ValueVisitor};
 
 // A representation of the variables on the stack.
+#[derive(Clone)]
 pub struct Stack {
     // Maps the name of the variable to their depth and their type.
     vars: HashMap<String, (AtomId, AcornType)>,
@@ -61,53 +67,108 @@ impl Stack {
 // and typecheck everything.
 // The BindingMap handles this. It does not handle Statements, just Expressions.
 // It does not have to be efficient enough to run in the inner loop of the prover.
-#[derive(Clone)]
+// Every block clones the bindings of the block around it, so the maps are persistent ones
+// that share structure. That keeps cloning cheap even for deeply nested proofs.
+// The maps are all ordered, so that iterating over them, and the serialized bytes of a
+// compiled module, are the same on every run.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BindingMap {
     // The module all these names are in.
     module: ModuleId,
 
     // Maps the name of a type to the type object.
-    type_names: BTreeMap<String, AcornType>,
+    type_names: OrdMap<String, AcornType>,
 
     // Maps the type object to the name of a type.
-    reverse_type_names: HashMap<AcornType, String>,
+    reverse_type_names: OrdMap<AcornType, String>,
 
     // Maps an identifier name to its type.
     // Has entries for both defined constants and aliases.
-    identifier_types: HashMap<String, AcornType>,
+    identifier_types: OrdMap<String, AcornType>,
 
     // Maps the name of a constant defined in this scope to information about it.
     // Doesn't handle variables defined on the stack, only ones that will be in scope for the
     // entirety of this environment.
     // Doesn't handle aliases.
     // Includes "<datatype>.<constant>" for members.
-    constants: BTreeMap<String, ConstantInfo>,
+    constants: OrdMap<String, ConstantInfo>,
 
     // The canonical identifier of a constant is the first place it is defined.
     // There may be other names in this environment that refer to the same thing.
     // When we create an AcornValue, we want to use the canonical name.
     // The alias -> canonical name mapping is stored here.
-    alias_to_canonical: HashMap<String, (ModuleId, String)>,
+    alias_to_canonical: OrdMap<String, (ModuleId, String)>,
 
     // Whenever a name from some other scope has a local alias in this one,
     // if we're generating code, we prefer to use the local name.
     // Thus, preferred_names maps the canonical identifier to a local alias.
-    canonical_to_alias: HashMap<(ModuleId, String), String>,
+    canonical_to_alias: OrdMap<(ModuleId, String), String>,
+
+    // A copy of the canonical constant's information, keyed by the alias.
+    // This way an alias has the same definition and constructor info as its canonical name.
+    alias_info: OrdMap<String, ConstantInfo>,
+
+    // When a block-local constant shadows a constant from an outer scope, the new constant is
+    // stored under an internal name like "k'1".
+    // This maps the name that users write to the internal name it currently refers to.
+    shadows: OrdMap<String, String>,
 
     // Names that refer to other modules.
     // After "import foo", "foo" refers to a module.
-    modules: BTreeMap<String, ModuleId>,
+    modules: OrdMap<String, ModuleId>,
 
     // The local name for imported modules.
-    reverse_modules: HashMap<ModuleId, String>,
+    reverse_modules: OrdMap<ModuleId, String>,
 
     // The default data type to use for numeric literals.
     default: Option<(ModuleId, String)>,
 
+    // The data type to use for string literals.
+    strings: Option<(ModuleId, String)>,
+
+    // The inductive type to use for list literals.
+    lists: Option<(ModuleId, String)>,
+
+    // The data type to use for numeric literals with a decimal point.
+    decimals: Option<(ModuleId, String)>,
+
+    // Functions to automatically convert values from one type to another.
+    // Keyed by (from type, to type).
+    coercions: OrdMap<(AcornType, AcornType), AcornValue>,
+
+    // The field names of each structure type defined in this module, in order.
+    structure_fields: OrdMap<String, Vec<String>>,
+
     // Whether this constant is the name of a theorem in this context.
     // Inside the block containing the proof of a theorem, the name is not considered to
     // be a theorem.
-    theorems: HashSet<String>,
+    theorems: OrdSet<String>,
+
+    // The constants whose definitions the prover only gets by an explicit unfold.
+    opaque: OrdSet<String>,
+
+    // The names that were imported from other modules with "from ... import".
+    imported: OrdSet<String>,
+
+    // The imported names that other modules can import from this one.
+    exported: OrdSet<String>,
+
+    // The names that were imported by wildcard imports, and the module each one came from.
+    wildcard_imports: OrdMap<String, ModuleId>,
+
+    // The doc comments for constants and types defined in this scope, joined into one string.
+    // Uses the same names as the constants map, so members are "<datatype>.<constant>".
+    documentation: OrdMap<String, String>,
+
+    // Attributes that modules have added to classes defined in other modules.
+    // Maps the class's (module, name) to each attribute name and the modules that define it.
+    // When more than one module defines an attribute, it has to be qualified to be used.
+    // Includes the extensions from this module and from the modules it imports.
+    class_extensions: OrdMap<(ModuleId, String), OrdMap<String, Vec<ModuleId>>>,
+
+    // The precedences declared for custom operators, like "++".
+    // Includes the declarations from this module and from the modules it imports.
+    precedences: BTreeMap<TokenType, i8>,
 }
 
 // A generic constant that we don't know the type of yet.
@@ -127,6 +188,20 @@ pub struct UnresolvedConstant {
     generic_type: AcornType,
 }
 
+impl UnresolvedConstant {
+    // Resolves this constant by instantiating its type parameters, in canonical order.
+    fn resolve(self, instance_params: Vec<AcornType>) -> AcornValue {
+        let named_params: Vec<_> = self
+            .params
+            .iter()
+            .cloned()
+            .zip(instance_params.iter().cloned())
+            .collect();
+        let resolved_type = self.generic_type.instantiate(&named_params);
+        AcornValue::new_constant(self.module_id, self.name, instance_params, resolved_type)
+    }
+}
+
 // Could be a value, but could also be an unresolved constant.
 pub enum PotentialValue {
     // (module, constant name, type, type parameters)
@@ -173,7 +248,23 @@ impl PotentialValue {
     }
 }
 
-#[derive(Clone)]
+// The predicates from refinement types in a scoped value.
+// Each predicate is a one-argument lambda, evaluated with all of the arguments on the stack.
+pub struct Refinements {
+    // One entry per argument, for the arguments that have refinement types.
+    pub args: Vec<Option<AcornValue>>,
+
+    // The refinement of the value type, if it has one.
+    pub value: Option<AcornValue>,
+}
+
+impl Refinements {
+    pub fn is_empty(&self) -> bool {
+        self.value.is_none() && self.args.iter().all(|p| p.is_none())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct ConstantInfo {
     // The names of the type parameters this constant was defined with, if any.
     // These type parameters can be used in the definition.
@@ -187,6 +278,45 @@ struct ConstantInfo {
     //   an index of which constructor it is
     //   how many total constructors there are
     constructor: Option<(AcornType, usize, usize)>,
+
+    // The internal names of the other constants that share this name, like "abs#1".
+    // Each one has a different type, and uses of the name pick one of them by type.
+    overloads: Vec<String>,
+}
+
+impl ConstantInfo {
+    fn remap_modules(&self, remap: &dyn Fn(ModuleId) -> ModuleId) -> ConstantInfo {
+        ConstantInfo {
+            params: self.params.clone(),
+            definition: self.definition.as_ref().map(|d| d.remap_modules(remap)),
+            constructor: self
+                .constructor
+                .as_ref()
+                .map(|(t, i, total)| (t.remap_modules(remap), *i, *total)),
+            overloads: self.overloads.clone(),
+        }
+    }
+}
+
+// The name that users write for a constant, stripping the suffix of an overload.
+fn overload_base(name: &str) -> &str {
+    match name.split_once('#') {
+        Some((base, _)) => base,
+        None => name,
+    }
+}
+
+// The name that a shadowing constant was declared with, stripping its internal suffix.
+fn shadow_base(name: &str) -> &str {
+    match name.split_once('\'') {
+        Some((base, _)) => base,
+        None => name,
+    }
+}
+
+// The name that users write for a constant, whether it's an overload, a shadow, or neither.
+pub fn user_name(name: &str) -> &str {
+    shadow_base(overload_base(name))
 }
 
 // Return an error if the types don't match.
@@ -208,8 +338,32 @@ pub fn check_type<'a>(
     Ok(())
 }
 
-fn keys_with_prefix<'a, T>(
-    map: &'a BTreeMap<String, T>,
+// If this type expression is a refinement type, returns the set-builder expression for it.
+fn refinement_expr(expression: &Expression) -> Option<&Expression> {
+    match expression {
+        Expression::Grouping(_, e, _) => refinement_expr(e),
+        Expression::SetBuilder(..) => Some(expression),
+        _ => None,
+    }
+}
+
+// The text of a decimal literal, given the texts of its numerator and denominator.
+// Returns None unless the denominator is a power of ten, greater than one.
+fn decimal_text(numerator: &str, denominator: &str) -> Option<String> {
+    let zeros = denominator.strip_prefix('1')?;
+    if zeros.is_empty() || !zeros.chars().all(|ch| ch == '0') {
+        return None;
+    }
+    if !numerator.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    let padded = format!("{:0>width$}", numerator, width = zeros.len() + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - zeros.len());
+    Some(format!("{}.{}", whole, fraction))
+}
+
+fn keys_with_prefix<'a, T: Clone>(
+    map: &'a OrdMap<String, T>,
     prefix: &'a str,
 ) -> impl Iterator<Item = &'a String> {
     map.range(prefix.to_string()..)
@@ -266,26 +420,142 @@ impl NamedEntity {
     }
 }
 
+// The state that is threaded through code generation for a single value.
+// next_x and next_k are the next candidate indices for x- and k-variables.
+struct CodeGenContext<'a> {
+    next_x: u32,
+    next_k: u32,
+    style: &'a CodeStyle,
+}
+
 impl BindingMap {
     pub fn new(module: ModuleId) -> Self {
         assert!(module >= FIRST_NORMAL);
         let mut answer = BindingMap {
             module,
-            type_names: BTreeMap::new(),
-            reverse_type_names: HashMap::new(),
-            identifier_types: HashMap::new(),
-            constants: BTreeMap::new(),
-            alias_to_canonical: HashMap::new(),
-            canonical_to_alias: HashMap::new(),
-            modules: BTreeMap::new(),
-            reverse_modules: HashMap::new(),
+            type_names: OrdMap::new(),
+            reverse_type_names: OrdMap::new(),
+            identifier_types: OrdMap::new(),
+            constants: OrdMap::new(),
+            alias_to_canonical: OrdMap::new(),
+            canonical_to_alias: OrdMap::new(),
+            alias_info: OrdMap::new(),
+            shadows: OrdMap::new(),
+            modules: OrdMap::new(),
+            reverse_modules: OrdMap::new(),
             default: None,
-            theorems: HashSet::new(),
+            strings: None,
+            decimals: None,
+            lists: None,
+            coercions: OrdMap::new(),
+            structure_fields: OrdMap::new(),
+            theorems: OrdSet::new(),
+            opaque: OrdSet::new(),
+            imported: OrdSet::new(),
+            exported: OrdSet::new(),
+            wildcard_imports: OrdMap::new(),
+            documentation: OrdMap::new(),
+            class_extensions: OrdMap::new(),
+            precedences: BTreeMap::new(),
         };
         answer.add_type_alias("Bool", AcornType::Bool);
         answer
     }
 
+    // Replaces every module id in the bindings, including the bindings' own module.
+    // This is for loading bindings into a project that numbers its modules differently.
+    pub fn remap_modules(&self, remap: &dyn Fn(ModuleId) -> ModuleId) -> BindingMap {
+        let remap_name = |(module, name): &(ModuleId, String)| (remap(*module), name.clone());
+        BindingMap {
+            module: remap(self.module),
+            type_names: self
+                .type_names
+                .iter()
+                .map(|(name, t)| (name.clone(), t.remap_modules(remap)))
+                .collect(),
+            reverse_type_names: self
+                .reverse_type_names
+                .iter()
+                .map(|(t, name)| (t.remap_modules(remap), name.clone()))
+                .collect(),
+            identifier_types: self
+                .identifier_types
+                .iter()
+                .map(|(name, t)| (name.clone(), t.remap_modules(remap)))
+                .collect(),
+            constants: self
+                .constants
+                .iter()
+                .map(|(name, info)| (name.clone(), info.remap_modules(remap)))
+                .collect(),
+            alias_to_canonical: self
+                .alias_to_canonical
+                .iter()
+                .map(|(alias, canonical)| (alias.clone(), remap_name(canonical)))
+                .collect(),
+            canonical_to_alias: self
+                .canonical_to_alias
+                .iter()
+                .map(|(canonical, alias)| (remap_name(canonical), alias.clone()))
+                .collect(),
+            alias_info: self
+                .alias_info
+                .iter()
+                .map(|(alias, info)| (alias.clone(), info.remap_modules(remap)))
+                .collect(),
+            shadows: self.shadows.clone(),
+            modules: self
+                .modules
+                .iter()
+                .map(|(name, module)| (name.clone(), remap(*module)))
+                .collect(),
+            reverse_modules: self
+                .reverse_modules
+                .iter()
+                .map(|(module, name)| (remap(*module), name.clone()))
+                .collect(),
+            default: self.default.as_ref().map(remap_name),
+            strings: self.strings.as_ref().map(remap_name),
+            decimals: self.decimals.as_ref().map(remap_name),
+            lists: self.lists.as_ref().map(remap_name),
+            coercions: self
+                .coercions
+                .iter()
+                .map(|((from, to), f)| {
+                    let key = (from.remap_modules(remap), to.remap_modules(remap));
+                    (key, f.remap_modules(remap))
+                })
+                .collect(),
+            structure_fields: self.structure_fields.clone(),
+            theorems: self.theorems.clone(),
+            opaque: self.opaque.clone(),
+            imported: self.imported.clone(),
+            exported: self.exported.clone(),
+            wildcard_imports: self
+                .wildcard_imports
+                .iter()
+                .map(|(name, module)| (name.clone(), remap(*module)))
+                .collect(),
+            documentation: self.documentation.clone(),
+            class_extensions: self
+                .class_extensions
+                .iter()
+                .map(|(class, attributes)| {
+                    let attributes = attributes
+                        .iter()
+                        .map(|(name, modules)| {
+                            let modules: Vec<ModuleId> =
+                                modules.iter().map(|m| remap(*m)).collect();
+                            (name.clone(), modules)
+                        })
+                        .collect::<OrdMap<_, _>>();
+                    (remap_name(class), attributes)
+                })
+                .collect(),
+            precedences: self.precedences.clone(),
+        }
+    }
+
     ////////////////////////////////////////////////////////////////////////////////
     // Simple helper functions.
     ////////////////////////////////////////////////////////////////////////////////
@@ -383,7 +653,7 @@ impl BindingMap {
     }
 
     pub fn get_params(&self, identifier: &str) -> Vec<String> {
-        match self.constants.get(identifier) {
+        match self.get_constant_info(identifier) {
             Some(info) => info.params.clone(),
             None => vec![],
         }
@@ -405,13 +675,37 @@ impl BindingMap {
     // Returns the defined value, if there is a defined value.
     // If there isn't, returns None.
     pub fn get_definition(&self, name: &str) -> Option<&AcornValue> {
-        self.constants.get(name)?.definition.as_ref()
+        self.get_constant_info(name)?.definition.as_ref()
+    }
+
+    pub fn module_id(&self) -> ModuleId {
+        self.module
+    }
+
+    // Whether the constant is a constructor of some data type.
+    pub fn is_constructor(&self, name: &str) -> bool {
+        self.get_constant_info(name)
+            .is_some_and(|info| info.constructor.is_some())
+    }
+
+    // The constants that the definition of the given constant refers to, besides itself.
+    // For a theorem, the definition is its claim.
+    // Constants without a definition don't depend on anything.
+    pub fn get_dependencies(&self, name: &str) -> BTreeSet<(ModuleId, String)> {
+        let mut usage = UsageCollector {
+            defining: Some((self.module, name.to_string())),
+            ..UsageCollector::default()
+        };
+        if let Some(definition) = self.get_definition(name) {
+            usage.visit_value(definition, 0);
+        }
+        usage.constants
     }
 
     // Returns the defined value and its parameters in their canonical order.
     // Returns None if there is no definition.
     pub fn get_definition_and_params(&self, name: &str) -> Option<(&AcornValue, &[String])> {
-        let info = self.constants.get(name)?;
+        let info = self.get_constant_info(name)?;
         Some((info.definition.as_ref()?, &info.params))
     }
 
@@ -425,6 +719,97 @@ impl BindingMap {
         self.default = Some((module, type_name));
     }
 
+    pub fn set_string_type(&mut self, module: ModuleId, type_name: String) {
+        self.strings = Some((module, type_name));
+    }
+
+    pub fn set_list_type(&mut self, module: ModuleId, type_name: String) {
+        self.lists = Some((module, type_name));
+    }
+
+    pub fn set_decimal_type(&mut self, module: ModuleId, type_name: String) {
+        self.decimals = Some((module, type_name));
+    }
+
+    pub fn set_structure_fields(&mut self, type_name: &str, fields: Vec<String>) {
+        self.structure_fields.insert(type_name.to_string(), fields);
+    }
+
+    // Returns the member functions that access the fields of a structure type, in order.
+    // Returns None if this type isn't a structure.
+    pub fn get_structure_members(
+        &self,
+        project: &Project,
+        acorn_type: &AcornType,
+    ) -> Option<Vec<AcornValue>> {
+        let (module, type_name) = match acorn_type {
+            AcornType::Data(module, type_name) => (*module, type_name),
+            _ => return None,
+        };
+        let bindings = if module == self.module {
+            self
+        } else {
+            project.get_bindings(module)?
+        };
+        let fields = bindings.structure_fields.get(type_name)?;
+        let mut answer = vec![];
+        for field in fields {
+            let member_name = format!("{}.{}", type_name, field);
+            answer.push(bindings.get_constant_value(&member_name)?.force_value());
+        }
+        Some(answer)
+    }
+
+    pub fn add_coercion(&mut self, from_type: AcornType, to_type: AcornType, function: AcornValue) {
+        self.coercions.insert((from_type, to_type), function);
+    }
+
+    pub fn get_coercion(&self, from_type: &AcornType, to_type: &AcornType) -> Option<&AcornValue> {
+        self.coercions.get(&(from_type.clone(), to_type.clone()))
+    }
+
+    // Applies a coercion to convert the value to the given type, if there is one.
+    fn coerce(&self, value: &AcornValue, to_type: &AcornType) -> Option<AcornValue> {
+        let function = self.get_coercion(&value.get_type(), to_type)?;
+        Some(AcornValue::new_apply(function.clone(), vec![value.clone()]))
+    }
+
+    // If this value is a coercion applied to something, returns the thing it was applied to.
+    fn strip_coercion<'a>(&self, value: &'a AcornValue) -> &'a AcornValue {
+        if let AcornValue::Application(fa) = value {
+            if fa.args.len() == 1 && self.coercions.values().any(|f| *f == *fa.function) {
+                return &fa.args[0];
+            }
+        }
+        value
+    }
+
+    // Whether this constant is the given constructor of the type we use for list literals.
+    fn is_list_constructor(&self, module: ModuleId, name: &str, constructor: &str) -> bool {
+        match &self.lists {
+            Some((list_module, type_name)) => {
+                *list_module == module && name == format!("{}.{}", type_name, constructor)
+            }
+            None => false,
+        }
+    }
+
+    // Whether this is the type we use for decimal literals.
+    fn is_decimal_type(&self, acorn_type: &AcornType) -> bool {
+        match (&self.decimals, acorn_type) {
+            (Some((module, type_name)), AcornType::Data(m, t)) => module == m && type_name == t,
+            _ => false,
+        }
+    }
+
+    // Whether this is the type we use for string literals.
+    fn is_string_type(&self, acorn_type: &AcornType) -> bool {
+        match (&self.strings, acorn_type) {
+            (Some((module, type_name)), AcornType::Data(m, t)) => module == m && type_name == t,
+            _ => false,
+        }
+    }
+
     // Adds a constant.
     // This can also add members, by providing a name like "Foo.bar".
     pub fn add_constant(
@@ -445,22 +830,87 @@ impl BindingMap {
             params,
             definition,
             constructor,
+            overloads: vec![],
         };
         self.constants.insert(name.to_string(), info);
+
+        // An overload also has to be registered as a candidate for its base name.
+        let base = overload_base(name);
+        if base != name {
+            match self.constants.get_mut(base) {
+                Some(info) => info.overloads.push(name.to_string()),
+                None => panic!("overload {} has no base constant", name),
+            }
+        }
     }
 
     // Be really careful about this, it seems likely to break things.
-    fn remove_constant(&mut self, name: &str) {
+    pub fn remove_constant(&mut self, name: &str) {
         if !self.name_in_use(name) {
             panic!("removing constant {} which is already not present", name);
         }
         self.identifier_types.remove(name);
         self.constants.remove(name);
+        let base = overload_base(name);
+        if base != name {
+            if let Some(info) = self.constants.get_mut(base) {
+                info.overloads.retain(|overload| overload != name);
+            }
+        }
+    }
+
+    // Whether a new constant with this type can share the name of an existing constant.
+    // Only non-generic constants can be overloaded, and every constant with the name
+    // must have a different type.
+    pub fn can_overload(&self, name: &str, constant_type: &AcornType) -> bool {
+        let info = match self.constants.get(name) {
+            Some(info) => info,
+            None => return false,
+        };
+        if !info.params.is_empty() || info.constructor.is_some() || name.contains('.') {
+            return false;
+        }
+        self.overload_candidates(name)
+            .iter()
+            .all(|candidate| self.identifier_types.get(candidate) != Some(constant_type))
+    }
+
+    // The internal name for the next constant that overloads this name.
+    // Users can't write a '#' in an identifier, so it can't collide with anything.
+    pub fn overload_name(&self, name: &str) -> String {
+        let count = match self.constants.get(name) {
+            Some(info) => info.overloads.len(),
+            None => 0,
+        };
+        format!("{}#{}", name, count + 1)
+    }
+
+    // The internal names of all the constants that share this name, starting with the
+    // original one. There's more than one only if the name is overloaded.
+    fn overload_candidates(&self, name: &str) -> Vec<String> {
+        match self.constants.get(name) {
+            Some(info) => {
+                let mut answer = vec![name.to_string()];
+                answer.extend(info.overloads.iter().cloned());
+                answer
+            }
+            None => vec![],
+        }
+    }
+
+    // The information about a constant defined here, or about the constant an alias refers to.
+    fn get_constant_info(&self, name: &str) -> Option<&ConstantInfo> {
+        self.constants
+            .get(name)
+            .or_else(|| self.alias_info.get(name))
     }
 
     // Adds a local alias for an already-existing constant.
+    // The alias keeps the theorem status, definition, and constructor info of the canonical
+    // constant, so that it behaves the same way the canonical name does.
     pub fn add_alias(
         &mut self,
+        project: &Project,
         name: &str,
         canonical_module: ModuleId,
         canonical_name: String,
@@ -469,6 +919,27 @@ impl BindingMap {
         if self.name_in_use(name) {
             panic!("cannot alias name {} because it is already bound", name);
         }
+        let (canonical_module, canonical_name) =
+            self.alias_target(project, canonical_module, canonical_name);
+        let canonical_bindings = if canonical_module == self.module {
+            &*self
+        } else {
+            project.get_bindings(canonical_module).unwrap()
+        };
+        let info = canonical_bindings
+            .get_constant_info(&canonical_name)
+            .cloned();
+        let theorem = canonical_bindings.is_theorem(&canonical_name);
+        let opaque = canonical_bindings.is_opaque(&canonical_name);
+        if let Some(info) = info {
+            self.alias_info.insert(name.to_string(), info);
+        }
+        if theorem {
+            self.theorems.insert(name.to_string());
+        }
+        if opaque {
+            self.opaque.insert(name.to_string());
+        }
         self.identifier_types.insert(name.to_string(), acorn_type);
         let canonical = (canonical_module, canonical_name);
         if canonical_module != self.module {
@@ -480,10 +951,68 @@ impl BindingMap {
         self.alias_to_canonical.insert(name.to_string(), canonical);
     }
 
+    // Follows a chain of aliases, possibly through several modules, to the constant at the end.
+    // Every alias records its ultimate target, so chains are at most one step long, but this
+    // makes sure that stays true.
+    // Panics if the chain loops, since names must be defined before they can be aliased.
+    fn alias_target(
+        &self,
+        project: &Project,
+        module: ModuleId,
+        name: String,
+    ) -> (ModuleId, String) {
+        let mut target = (module, name);
+        let mut seen = HashSet::new();
+        loop {
+            let bindings = if target.0 == self.module {
+                self
+            } else {
+                project.get_bindings(target.0).unwrap()
+            };
+            let Some(next) = bindings.alias_to_canonical.get(&target.1) else {
+                return target;
+            };
+            if !seen.insert(target.clone()) {
+                panic!("alias cycle through {}", target.1);
+            }
+            target = next.clone();
+        }
+    }
+
     pub fn is_constant(&self, name: &str) -> bool {
         self.constants.contains_key(name)
     }
 
+    // Whether a block-local constant could shadow this name.
+    // Only constants can be shadowed, not types or modules.
+    pub fn can_shadow(&self, name: &str) -> bool {
+        self.identifier_types
+            .contains_key(self.resolve_shadow(name))
+    }
+
+    // Starts shadowing a name, and returns the internal name to define the new constant with.
+    // For the rest of this scope, the name refers to the new constant.
+    pub fn shadow(&mut self, name: &str) -> String {
+        let mut i = 1;
+        loop {
+            let internal = format!("{}'{}", name, i);
+            if !self.name_in_use(&internal) {
+                self.shadows.insert(name.to_string(), internal.clone());
+                return internal;
+            }
+            i += 1;
+        }
+    }
+
+    // The internal name of the constant that a name refers to.
+    // This is only different from the name itself when the name is shadowed.
+    pub fn resolve_shadow<'a>(&'a self, name: &'a str) -> &'a str {
+        match self.shadows.get(name) {
+            Some(internal) => internal,
+            None => name,
+        }
+    }
+
     pub fn mark_as_theorem(&mut self, name: &str) {
         self.theorems.insert(name.to_string());
     }
@@ -492,6 +1021,84 @@ impl BindingMap {
         self.theorems.contains(name)
     }
 
+    pub fn mark_as_opaque(&mut self, name: &str) {
+        self.opaque.insert(name.to_string());
+    }
+
+    pub fn is_opaque(&self, name: &str) -> bool {
+        self.opaque.contains(name)
+    }
+
+    pub fn is_imported(&self, name: &str) -> bool {
+        self.imported.contains(name)
+    }
+
+    pub fn mark_as_exported(&mut self, name: &str) {
+        self.exported.insert(name.to_string());
+    }
+
+    pub fn is_exported(&self, name: &str) -> bool {
+        self.exported.contains(name)
+    }
+
+    // The names of the constants defined in this scope, not including aliases.
+    pub fn constant_names(&self) -> impl Iterator<Item = &String> {
+        self.constants.keys()
+    }
+
+    // The constants that local aliases like "let x = y" refer to.
+    // Names imported with "from ... import" are not included.
+    pub fn local_alias_targets(&self) -> impl Iterator<Item = &(ModuleId, String)> {
+        self.alias_to_canonical
+            .iter()
+            .filter(|(name, _)| !self.imported.contains(*name))
+            .map(|(_, canonical)| canonical)
+    }
+
+    // The exported names that start with a prefix, in sorted order.
+    fn exported_with_prefix(&self, prefix: &str) -> Vec<&String> {
+        let mut answer: Vec<_> = self
+            .exported
+            .iter()
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        answer.sort();
+        answer
+    }
+
+    // Attaches documentation to a constant or type defined in this scope.
+    // Each line of the doc comment is provided separately.
+    pub fn set_documentation(&mut self, name: &str, doc_comments: &[String]) {
+        if doc_comments.is_empty() {
+            return;
+        }
+        self.documentation
+            .insert(name.to_string(), doc_comments.join("\n"));
+    }
+
+    // Returns the documentation for a name defined in this scope, if there is any.
+    // Aliases are followed to the module where the name was originally defined.
+    pub fn get_documentation<'a>(&'a self, project: &'a Project, name: &str) -> Option<&'a str> {
+        if let Some(doc) = self.documentation.get(name) {
+            return Some(doc);
+        }
+        let (module, canonical_name) = match self.type_names.get(name) {
+            Some(AcornType::Data(module, type_name)) => (module, type_name),
+            _ => {
+                let (module, canonical_name) = self.alias_to_canonical.get(name)?;
+                (module, canonical_name)
+            }
+        };
+        if *module == self.module {
+            return self.documentation.get(canonical_name).map(|s| s.as_str());
+        }
+        project
+            .get_bindings(*module)?
+            .documentation
+            .get(canonical_name)
+            .map(|s| s.as_str())
+    }
+
     // Type variables should get removed when they go out of scope.
     fn remove_type_variable(&mut self, name: &str) {
         match self.type_names.remove(name) {
@@ -511,13 +1118,138 @@ impl BindingMap {
         self.reverse_modules.insert(module, name.to_string());
     }
 
+    // Records that this module gives a class from another module a new attribute.
+    pub fn add_class_extension(&mut self, class_module: ModuleId, class_name: &str, attr: &str) {
+        let key = (class_module, class_name.to_string());
+        let modules = self
+            .class_extensions
+            .entry(key)
+            .or_default()
+            .entry(attr.to_string())
+            .or_default();
+        if !modules.contains(&self.module) {
+            modules.push(self.module);
+        }
+    }
+
+    // Declares the precedence of a custom operator.
+    // Every use of an operator has to parse the same way, so its precedence can't change.
+    pub fn declare_precedence(
+        &mut self,
+        token_type: TokenType,
+        precedence: i8,
+    ) -> Result<(), String> {
+        match self.precedences.get(&token_type) {
+            Some(existing) if *existing != precedence => Err(format!(
+                "the precedence of {} is already declared as {}",
+                token_type.to_str(),
+                existing
+            )),
+            _ => {
+                self.precedences.insert(token_type, precedence);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn precedences(&self) -> &BTreeMap<TokenType, i8> {
+        &self.precedences
+    }
+
+    // Brings in the precedences that an imported module declares or imports.
+    pub fn import_precedences(&mut self, imported: &BindingMap) -> Result<(), String> {
+        for (token_type, precedence) in &imported.precedences {
+            self.declare_precedence(*token_type, *precedence)?;
+        }
+        Ok(())
+    }
+
+    // Brings in the class extensions that an imported module defines or imports.
+    pub fn import_class_extensions(&mut self, imported: &BindingMap) {
+        for (class, attributes) in &imported.class_extensions {
+            let entry = self.class_extensions.entry(class.clone()).or_default();
+            for (attr, providers) in attributes {
+                let modules = entry.entry(attr.clone()).or_default();
+                for provider in providers {
+                    if !modules.contains(provider) {
+                        modules.push(*provider);
+                    }
+                }
+            }
+        }
+    }
+
+    // The modules other than the class's own module that give it this attribute.
+    pub fn extension_providers(
+        &self,
+        class_module: ModuleId,
+        class_name: &str,
+        attr: &str,
+    ) -> &[ModuleId] {
+        self.class_extensions
+            .get(&(class_module, class_name.to_string()))
+            .and_then(|attributes| attributes.get(attr))
+            .map_or(&[], |modules| modules.as_slice())
+    }
+
+    // Finds the bindings that define an attribute of a class.
+    // That's usually the class's own module, but it can be a module that extended the class.
+    // When several modules extend the class with the same attribute, the error message
+    // explains how to pick one.
+    fn attribute_bindings<'a>(
+        &'a self,
+        project: &'a Project,
+        class_module: ModuleId,
+        class_name: &str,
+        attr: &str,
+    ) -> Result<&'a BindingMap, String> {
+        let bindings_for = |module: ModuleId| {
+            if module == self.module {
+                self
+            } else {
+                project.get_bindings(module).unwrap()
+            }
+        };
+        let own = bindings_for(class_module);
+        if own.has_identifier(&format!("{}.{}", class_name, attr)) {
+            return Ok(own);
+        }
+        match self.extension_providers(class_module, class_name, attr) {
+            [] => Ok(own),
+            [provider] => Ok(bindings_for(*provider)),
+            providers => {
+                let names: Vec<_> = providers
+                    .iter()
+                    .map(|m| project.get_module_descriptor(*m).to_string())
+                    .collect();
+                Err(format!(
+                    "{}.{} is ambiguous, since it is defined in: {}. \
+                     qualify it with a module, like x.({}.{})",
+                    class_name,
+                    attr,
+                    names.join(", "),
+                    names[0],
+                    attr
+                ))
+            }
+        }
+    }
+
     pub fn is_module(&self, name: &str) -> bool {
         self.modules.contains_key(name)
     }
 
+    pub fn get_module_for_name(&self, name: &str) -> Option<ModuleId> {
+        self.modules.get(name).copied()
+    }
+
     // Whether this value is calling a theorem on some arguments.
+    // A theorem with no arguments, like a label, is also a citation by itself.
     pub fn is_citation(&self, project: &Project, claim: &AcornValue) -> bool {
-        match claim.is_named_function_call() {
+        match claim
+            .is_named_function_call()
+            .or_else(|| claim.as_simple_constant())
+        {
             Some((module_id, name)) => {
                 if module_id == self.module {
                     self.is_theorem(&name)
@@ -530,6 +1262,11 @@ impl BindingMap {
         }
     }
 
+    fn get_completion_documentation(&self, project: &Project, name: &str) -> Option<Documentation> {
+        self.get_documentation(project, name)
+            .map(|doc| Documentation::String(doc.to_string()))
+    }
+
     // Gets completions when we are typing a member name.
     fn get_member_completions(
         &self,
@@ -549,6 +1286,7 @@ impl BindingMap {
                 let completion = CompletionItem {
                     label: key.split('.').last()?.to_string(),
                     kind: Some(CompletionItemKind::FIELD),
+                    documentation: bindings.get_completion_documentation(project, key),
                     ..Default::default()
                 };
                 answer.push(completion);
@@ -612,7 +1350,8 @@ impl BindingMap {
             }
             // Constants
             for key in keys_with_prefix(&self.constants, prefix) {
-                if key.contains('.') {
+                if key.contains('.') || user_name(key) != key {
+                    // Members, overloads, and shadows are completed under other names.
                     continue;
                 }
                 if importing {
@@ -628,16 +1367,31 @@ impl BindingMap {
                 let completion = CompletionItem {
                     label: key.clone(),
                     kind: Some(CompletionItemKind::CONSTANT),
+                    documentation: self.get_completion_documentation(project, key),
                     ..Default::default()
                 };
                 answer.push(completion);
             }
+            if importing {
+                // Exported constants can be imported as if they were defined here.
+                for key in self.exported_with_prefix(prefix) {
+                    if self.type_names.contains_key(key) {
+                        continue;
+                    }
+                    let completion = CompletionItem {
+                        label: key.clone(),
+                        kind: Some(CompletionItemKind::CONSTANT),
+                        ..Default::default()
+                    };
+                    answer.push(completion);
+                }
+            }
         }
 
         if first_char.map(|c| c.is_uppercase()).unwrap_or(true) {
             // Types
             for key in keys_with_prefix(&self.type_names, prefix) {
-                if importing {
+                if importing && !self.exported.contains(key) {
                     let data_type = self.type_names.get(key)?;
                     match data_type {
                         AcornType::Data(module, name) => {
@@ -651,6 +1405,7 @@ impl BindingMap {
                 let completion = CompletionItem {
                     label: key.clone(),
                     kind: Some(CompletionItemKind::CLASS),
+                    documentation: self.get_completion_documentation(project, key),
                     ..Default::default()
                 };
                 answer.push(completion);
@@ -704,12 +1459,56 @@ impl BindingMap {
                 Err(left.error("unexpected function application in type expression"))
             }
             Expression::Grouping(_, e, _) => self.evaluate_type(project, e),
-            Expression::Binder(token, _, _, _) | Expression::IfThenElse(token, _, _, _, _) => {
+            Expression::Binder(token, _, _, _)
+            | Expression::IfThenElse(token, _, _, _, _)
+            | Expression::Choose(token, _, _) => {
                 Err(token.error("unexpected token in type expression"))
             }
             Expression::Match(token, _, _, _) => {
                 Err(token.error("unexpected match token in type expression"))
             }
+            Expression::SetBuilder(token, _, _, _) => Err(token.error(
+                "refinement types can only be used for let statements, theorem arguments, \
+                 and define return types",
+            )),
+            Expression::List(token, _, _) => {
+                Err(token.error("unexpected list literal in type expression"))
+            }
+        }
+    }
+
+    // Evaluates an expression that represents a type, which may be a refinement type
+    // like { x: Nat | x != Nat.0 }.
+    // Returns the base type, along with the refining predicate if there is one.
+    // The predicate is a one-argument lambda, evaluated with the provided stack.
+    pub fn evaluate_refined_type(
+        &self,
+        stack: &mut Stack,
+        project: &Project,
+        expression: &Expression,
+    ) -> compilation::Result<(AcornType, Option<AcornValue>)> {
+        match refinement_expr(expression) {
+            Some(refinement) => {
+                let base_type = self.evaluate_refinement_base(project, refinement)?;
+                let predicate = self.evaluate_value_with_stack(stack, project, refinement, None)?;
+                Ok((base_type, Some(predicate)))
+            }
+            None => Ok((self.evaluate_type(project, expression)?, None)),
+        }
+    }
+
+    // Evaluates the base type of a refinement type, from the declaration of its bound variable.
+    fn evaluate_refinement_base(
+        &self,
+        project: &Project,
+        refinement: &Expression,
+    ) -> compilation::Result<AcornType> {
+        match refinement {
+            Expression::SetBuilder(_, declaration, _, _) => {
+                let (_, base_type) = self.evaluate_declaration(project, declaration)?;
+                Ok(base_type)
+            }
+            _ => Err(refinement.error("expected a refinement type")),
         }
     }
 
@@ -760,20 +1559,74 @@ impl BindingMap {
         declarations: I,
         class_name: Option<&str>,
     ) -> compilation::Result<(Vec<String>, Vec<AcornType>)>
+    where
+        I: IntoIterator<Item = &'a Declaration>,
+    {
+        self.bind_args_helper(stack, project, declarations, class_name, None)
+    }
+
+    // Like bind_args, but the arguments may have refinement types.
+    // The refining predicate for each argument, if it has one, is appended to predicates.
+    // The predicates are evaluated with all of the arguments on the stack, so a predicate
+    // can refer to the other arguments.
+    pub fn bind_refined_args<'a, I>(
+        &self,
+        stack: &mut Stack,
+        project: &Project,
+        declarations: I,
+        class_name: Option<&str>,
+        predicates: &mut Vec<Option<AcornValue>>,
+    ) -> compilation::Result<(Vec<String>, Vec<AcornType>)>
+    where
+        I: IntoIterator<Item = &'a Declaration>,
+    {
+        let mut refinements = vec![];
+        let answer = self.bind_args_helper(
+            stack,
+            project,
+            declarations,
+            class_name,
+            Some(&mut refinements),
+        )?;
+        for refinement in refinements {
+            let predicate = match refinement {
+                Some(e) => Some(self.evaluate_value_with_stack(stack, project, e, None)?),
+                None => None,
+            };
+            predicates.push(predicate);
+        }
+        Ok(answer)
+    }
+
+    // If refinements is provided, refinement types are allowed, and their set-builder
+    // expressions are collected there, one entry per argument.
+    fn bind_args_helper<'a, I>(
+        &self,
+        stack: &mut Stack,
+        project: &Project,
+        declarations: I,
+        class_name: Option<&str>,
+        mut refinements: Option<&mut Vec<Option<&'a Expression>>>,
+    ) -> compilation::Result<(Vec<String>, Vec<AcornType>)>
     where
         I: IntoIterator<Item = &'a Declaration>,
     {
         let mut names = Vec::new();
         let mut types = Vec::new();
         for (i, declaration) in declarations.into_iter().enumerate() {
-            if class_name.is_some() && i == 0 {
+            if let (Some(class_name), 0) = (class_name, i) {
                 match declaration {
                     Declaration::SelfToken(_) => {
+                        if let Some(refinements) = refinements.as_mut() {
+                            refinements.push(None);
+                        }
                         names.push("self".to_string());
-                        types.push(AcornType::Data(
-                            self.module,
-                            class_name.unwrap().to_string(),
-                        ));
+                        // The class might be imported, if this module is extending it.
+                        let class_type = match self.get_type_for_name(class_name) {
+                            Some(class_type) => class_type.clone(),
+                            None => AcornType::Data(self.module, class_name.to_string()),
+                        };
+                        types.push(class_type);
                         continue;
                     }
                     _ => {
@@ -783,7 +1636,23 @@ impl BindingMap {
                     }
                 }
             }
-            let (name, acorn_type) = self.evaluate_declaration(project, declaration)?;
+            let refinement = match declaration {
+                Declaration::Typed(_, type_expr) => refinement_expr(type_expr),
+                Declaration::SelfToken(_) => None,
+            };
+            let (name, acorn_type) = match (refinements.as_mut(), refinement) {
+                (Some(refinements), Some(refinement)) => {
+                    refinements.push(Some(refinement));
+                    let base_type = self.evaluate_refinement_base(project, refinement)?;
+                    (declaration.token().to_string(), base_type)
+                }
+                (refinements, _) => {
+                    if let Some(refinements) = refinements {
+                        refinements.push(None);
+                    }
+                    self.evaluate_declaration(project, declaration)?
+                }
+            };
             if self.name_in_use(&name) {
                 return Err(declaration
                     .token()
@@ -834,25 +1703,38 @@ impl BindingMap {
 
     // Evalutes a pattern match. Infers their types from the pattern.
     // Returns an error if the pattern is not a constructor of the expected type.
+    // Patterns can be nested, like Nat.suc(Nat.suc(k)).
     // Returns:
-    //   a value for the constructor function
-    //   a list of (name, type) pairs
-    //   the index of which constructor this is
-    //   the total number of constructors
+    //   a list of (name, type) pairs for the variables in the pattern, in order
+    //   the shape of the pattern
     pub fn evaluate_pattern(
         &self,
         project: &Project,
         expected_type: &AcornType,
         pattern: &Expression,
-    ) -> compilation::Result<(AcornValue, Vec<(String, AcornType)>, usize, usize)> {
-        let (fn_exp, args) = match pattern {
+    ) -> compilation::Result<(Vec<(String, AcornType)>, PatternShape)> {
+        let mut args = vec![];
+        let shape = self.evaluate_subpattern(project, expected_type, pattern, &mut args)?;
+        Ok((args, shape))
+    }
+
+    // Evaluates a pattern that should be a constructor, possibly applied to subpatterns.
+    // Appends the variables in the pattern to args.
+    fn evaluate_subpattern(
+        &self,
+        project: &Project,
+        expected_type: &AcornType,
+        pattern: &Expression,
+        args: &mut Vec<(String, AcornType)>,
+    ) -> compilation::Result<PatternShape> {
+        let (fn_exp, arg_exps) = match pattern {
             Expression::Apply(function, args) => (function, args),
             _ => {
                 // This could be a no-argument constructor.
                 let constructor = self.evaluate_value(project, pattern, None)?;
                 let (i, total) =
                     self.expect_constructor(project, expected_type, &constructor, pattern)?;
-                return Ok((constructor, vec![], i, total));
+                return Ok(PatternShape::Constructor(i, total, vec![]));
             }
         };
         let constructor = self.evaluate_value(project, fn_exp, None)?;
@@ -869,33 +1751,38 @@ impl BindingMap {
             }
             _ => return Err(fn_exp.error("expected a function")),
         };
-        let name_exps = args.flatten_list(false)?;
-        if name_exps.len() != arg_types.len() {
-            return Err(args.error(&format!(
+        let sub_exps = arg_exps.flatten_list(false)?;
+        if sub_exps.len() != arg_types.len() {
+            return Err(arg_exps.error(&format!(
                 "expected {} arguments but got {}",
                 arg_types.len(),
-                name_exps.len()
+                sub_exps.len()
             )));
         }
-        let mut args = vec![];
-        for (name_exp, arg_type) in name_exps.into_iter().zip(arg_types.into_iter()) {
-            let name = match name_exp {
+        let mut subpatterns = vec![];
+        for (sub_exp, arg_type) in sub_exps.into_iter().zip(arg_types) {
+            let name = match sub_exp {
                 Expression::Singleton(token) => token.text().to_string(),
-                _ => return Err(name_exp.error("expected a simple name in pattern")),
+                _ => {
+                    // A nested pattern.
+                    subpatterns.push(self.evaluate_subpattern(project, &arg_type, sub_exp, args)?);
+                    continue;
+                }
             };
             if self.name_in_use(&name) {
-                return Err(name_exp.error(&format!("name '{}' is already bound", name)));
+                return Err(sub_exp.error(&format!("name '{}' is already bound", name)));
             }
             // Check if we already saw this name
             if args.iter().any(|(n, _)| n == &name) {
-                return Err(name_exp.error(&format!(
+                return Err(sub_exp.error(&format!(
                     "cannot use the name '{}' twice in one pattern",
                     name
                 )));
             }
-            args.push((name, arg_type))
+            args.push((name, arg_type));
+            subpatterns.push(PatternShape::Variable);
         }
-        Ok((constructor, args, i, total))
+        Ok(PatternShape::Constructor(i, total, subpatterns))
     }
 
     // This function evaluates numbers when we already know what type they are.
@@ -918,6 +1805,15 @@ impl BindingMap {
             }
         }
 
+        if let Some((whole, fraction)) = Token::decimal_parts(s) {
+            return self
+                .evaluate_decimal_number(token, project, module, type_name, whole, fraction);
+        }
+
+        if let Some((radix, digits)) = Token::radix_digits(s) {
+            return self.evaluate_radix_number(token, project, module, type_name, radix, digits);
+        }
+
         if s.len() == 1 {
             return Err(token.error(&format!("digit {}.{} is not defined", type_name, s)));
         }
@@ -944,6 +1840,233 @@ impl BindingMap {
         Ok(value)
     }
 
+    // A numeral with a decimal point is a fraction, with a power of ten as the denominator.
+    // It is created with the from_parts hook, so 3.14 is from_parts(314, 100).
+    fn evaluate_decimal_number(
+        &self,
+        token: &Token,
+        project: &Project,
+        module: ModuleId,
+        type_name: &str,
+        whole: &str,
+        fraction: &str,
+    ) -> compilation::Result<AcornValue> {
+        let digits = format!("{}{}", whole, fraction);
+        let numerator = match digits.trim_start_matches('0') {
+            "" => "0",
+            trimmed => trimmed,
+        };
+        let denominator = format!("1{}", "0".repeat(fraction.len()));
+        let numerator =
+            self.evaluate_number_with_type(token, project, module, type_name, numerator)?;
+        let denominator =
+            self.evaluate_number_with_type(token, project, module, type_name, &denominator)?;
+        let from_parts =
+            match self.evaluate_class_variable(project, module, type_name, "from_parts") {
+                Some(PotentialValue::Resolved(f)) => f,
+                Some(PotentialValue::Unresolved(_)) => {
+                    return Err(
+                        token.error(&format!("{}.from_parts has unresolved type", type_name))
+                    )
+                }
+                None => {
+                    return Err(token.error(&format!(
+                        "{}.from_parts must be defined to read decimal literals",
+                        type_name
+                    )))
+                }
+            };
+        Ok(AcornValue::new_apply(
+            from_parts,
+            vec![numerator, denominator],
+        ))
+    }
+
+    // Hexadecimal and binary numerals are read one digit at a time, like decimal ones,
+    // but with the read_hex or read_binary hook instead of read.
+    // Each digit is represented by the decimal numeral for its value, so 0xff is
+    // read_hex(15, 15).
+    fn evaluate_radix_number(
+        &self,
+        token: &Token,
+        project: &Project,
+        module: ModuleId,
+        type_name: &str,
+        radix: u32,
+        digits: &str,
+    ) -> compilation::Result<AcornValue> {
+        let hook = Token::radix_hook(radix);
+        let mut answer: Option<AcornValue> = None;
+        for ch in digits.chars() {
+            let digit = match ch.to_digit(radix) {
+                Some(d) => d.to_string(),
+                None => return Err(token.error(&format!("invalid digit '{}'", ch))),
+            };
+            let digit =
+                self.evaluate_number_with_type(token, project, module, type_name, &digit)?;
+            let initial = match answer {
+                Some(initial) => initial,
+                None => {
+                    answer = Some(digit);
+                    continue;
+                }
+            };
+            let read_fn = match self.evaluate_class_variable(project, module, type_name, hook) {
+                Some(PotentialValue::Resolved(f)) => f,
+                Some(PotentialValue::Unresolved(_)) => {
+                    return Err(token.error(&format!("{}.{} has unresolved type", type_name, hook)))
+                }
+                None => {
+                    return Err(token.error(&format!(
+                        "{}.{} must be defined to read this numeric literal",
+                        type_name, hook
+                    )))
+                }
+            };
+            answer = Some(AcornValue::new_apply(read_fn, vec![initial, digit]));
+        }
+        match answer {
+            Some(answer) => Ok(answer),
+            None => Err(token.error("expected digits in numeric literal")),
+        }
+    }
+
+    // A string literal is built up from the empty string by appending characters.
+    // "ab" is Str.empty.append(97).append(98), where the characters are numeric literals,
+    // using the type of the second argument to append.
+    fn evaluate_string_literal(
+        &self,
+        token: &Token,
+        project: &Project,
+    ) -> compilation::Result<AcornValue> {
+        let (module, type_name) = match &self.strings {
+            Some((module, type_name)) => (*module, type_name),
+            None => {
+                return Err(token.error("you must set a type for string literals"));
+            }
+        };
+        let contents = token.string_contents()?;
+        let mut value = match self.evaluate_class_variable(project, module, type_name, "empty") {
+            Some(PotentialValue::Resolved(value)) => value,
+            _ => {
+                return Err(token.error(&format!(
+                    "{}.empty must be defined to read string literals",
+                    type_name
+                )))
+            }
+        };
+        if contents.is_empty() {
+            return Ok(value);
+        }
+        let append_fn = match self.evaluate_class_variable(project, module, type_name, "append") {
+            Some(PotentialValue::Resolved(f)) => f,
+            _ => {
+                return Err(token.error(&format!(
+                    "{}.append must be defined to read string literals",
+                    type_name
+                )))
+            }
+        };
+        let (char_module, char_type_name) = match append_fn.get_type() {
+            AcornType::Function(ftype) if ftype.arg_types.len() == 2 => match &ftype.arg_types[1] {
+                AcornType::Data(m, t) => (*m, t.clone()),
+                _ => {
+                    return Err(token.error(&format!(
+                        "{}.append must take a data type to append",
+                        type_name
+                    )))
+                }
+            },
+            _ => return Err(token.error(&format!("{}.append must take two arguments", type_name))),
+        };
+        for ch in contents.chars() {
+            let code = (ch as u32).to_string();
+            let char_value = self.evaluate_number_with_type(
+                token,
+                project,
+                char_module,
+                &char_type_name,
+                &code,
+            )?;
+            value = AcornValue::new_apply(append_fn.clone(), vec![value, char_value]);
+        }
+        Ok(value)
+    }
+
+    // Finds the nil or cons constructor used to read list literals.
+    // List literals are only supported for a list type with a single element type, so a
+    // generic constructor is an error rather than something we try to instantiate.
+    fn get_list_constructor(
+        &self,
+        project: &Project,
+        module: ModuleId,
+        type_name: &str,
+        attr: &str,
+        expression: &Expression,
+    ) -> compilation::Result<AcornValue> {
+        match self.evaluate_class_variable(project, module, type_name, attr) {
+            Some(PotentialValue::Resolved(value)) => Ok(value),
+            Some(PotentialValue::Unresolved(_)) => Err(expression.error(&format!(
+                "{}.{} is generic, but list literals only support lists of a fixed element type",
+                type_name, attr
+            ))),
+            None => Err(expression.error(&format!(
+                "{}.{} must be defined to read list literals",
+                type_name, attr
+            ))),
+        }
+    }
+
+    // Evaluates a list literal like [a, b, c] as cons(a, cons(b, cons(c, nil))).
+    fn evaluate_list_literal(
+        &self,
+        stack: &mut Stack,
+        project: &Project,
+        expression: &Expression,
+        elements: &[Expression],
+    ) -> compilation::Result<AcornValue> {
+        let (module, type_name) = match &self.lists {
+            Some((module, type_name)) => (*module, type_name),
+            None => {
+                return Err(expression.error("you must set a type for list literals"));
+            }
+        };
+        let list_type = AcornType::Data(module, type_name.clone());
+        let mut value = self.get_list_constructor(project, module, type_name, "nil", expression)?;
+        self.expect_constructor(project, &list_type, &value, expression)?;
+        if elements.is_empty() {
+            return Ok(value);
+        }
+        let cons_fn = self.get_list_constructor(project, module, type_name, "cons", expression)?;
+        self.expect_constructor(project, &list_type, &cons_fn, expression)?;
+        let element_type = match cons_fn.get_type() {
+            AcornType::Function(ftype)
+                if ftype.arg_types.len() == 2 && ftype.arg_types[1] == list_type =>
+            {
+                ftype.arg_types[0].clone()
+            }
+            _ => {
+                return Err(expression.error(&format!(
+                    "{}.cons must take an element and a list",
+                    type_name
+                )))
+            }
+        };
+        let mut element_values = vec![];
+        for element in elements {
+            element_values.push(self.evaluate_value_with_stack(
+                stack,
+                project,
+                element,
+                Some(&element_type),
+            )?);
+        }
+        for element_value in element_values.into_iter().rev() {
+            value = AcornValue::new_apply(cons_fn.clone(), vec![element_value, value]);
+        }
+        Ok(value)
+    }
+
     // Evaluates a name scoped by a type name, like MyClass.foo
     fn evaluate_class_variable(
         &self,
@@ -952,11 +2075,9 @@ impl BindingMap {
         type_name: &str,
         var_name: &str,
     ) -> Option<PotentialValue> {
-        let bindings = if module == self.module {
-            &self
-        } else {
-            project.get_bindings(module).unwrap()
-        };
+        let bindings = self
+            .attribute_bindings(project, module, type_name, var_name)
+            .ok()?;
         let constant_name = format!("{}.{}", type_name, var_name);
         bindings.get_constant_value(&constant_name)
     }
@@ -971,50 +2092,219 @@ impl BindingMap {
         self.evaluate_value_with_stack(&mut Stack::new(), project, expression, expected_type)
     }
 
+    // Evaluates an expression that names a constant, like "foo" or "Nat.add".
+    // Returns the canonical module and name of the constant.
+    // Generic constants don't need to be resolved, since we only want the name.
+    pub fn evaluate_constant_name(
+        &self,
+        project: &Project,
+        expression: &Expression,
+    ) -> compilation::Result<(ModuleId, String)> {
+        match self.evaluate_potential_value(&mut Stack::new(), project, expression, None)? {
+            PotentialValue::Unresolved(u) => Ok((u.module_id, u.name)),
+            PotentialValue::Resolved(AcornValue::Constant(c)) => Ok((c.module_id, c.name)),
+            PotentialValue::Resolved(_) => Err(expression.error("expected the name of a constant")),
+        }
+    }
+
+    // Evaluates the type of a function from the signature of its definition, without its value.
+    // Returns the type parameter names along with the function type.
+    // This is how a function can be referred to before its definition is evaluated.
+    pub fn evaluate_function_type(
+        &mut self,
+        project: &Project,
+        type_param_tokens: &[Token],
+        args: &[Declaration],
+        value_type_expr: &Expression,
+        class_name: Option<&str>,
+    ) -> compilation::Result<(Vec<String>, AcornType)> {
+        let mut type_param_names: Vec<String> = vec![];
+        for token in type_param_tokens {
+            if self.type_names.contains_key(token.text()) {
+                for name in type_param_names.iter().rev() {
+                    self.remove_type_variable(name);
+                }
+                return Err(token.error("cannot redeclare a type in a generic type list"));
+            }
+            self.add_type_variable(token.text(), None);
+            type_param_names.push(token.text().to_string());
+        }
+        // Refinements don't change the type, so we can ignore them here.
+        let mut stack = Stack::new();
+        let mut refinements = vec![];
+        let fn_type = self
+            .bind_refined_args(&mut stack, project, args, class_name, &mut refinements)
+            .and_then(|(_, arg_types)| {
+                let (value_type, _) =
+                    self.evaluate_refined_type(&mut stack, project, value_type_expr)?;
+                Ok(AcornType::new_functional(arg_types, value_type))
+            });
+        for name in type_param_names.iter().rev() {
+            self.remove_type_variable(name);
+        }
+        Ok((type_param_names, fn_type?))
+    }
+
+    // Evaluates the measure for a "decreasing" definition, in terms of the function arguments.
+    // Returns the measure along with the "lt" function used to compare measures.
+    // We trust that lt is well-founded; nothing here checks it.
+    pub fn evaluate_measure(
+        &mut self,
+        project: &Project,
+        type_param_names: &[String],
+        arg_names: &[String],
+        arg_types: &[AcornType],
+        measure_expr: &Expression,
+    ) -> compilation::Result<(AcornValue, AcornValue)> {
+        for name in type_param_names {
+            self.add_type_variable(name, None);
+        }
+        let mut stack = Stack::new();
+        for (name, arg_type) in arg_names.iter().zip(arg_types) {
+            stack.insert(name.to_string(), arg_type.clone());
+        }
+        let measure = self.evaluate_value_with_stack(&mut stack, project, measure_expr, None);
+        for name in type_param_names.iter().rev() {
+            self.remove_type_variable(name);
+        }
+        let measure = measure?;
+
+        let measure_type = measure.get_type();
+        let (module, type_name) = match &measure_type {
+            AcornType::Data(module, type_name) => (*module, type_name.clone()),
+            _ => {
+                return Err(measure_expr.error(&format!(
+                    "a measure of type {} cannot be compared with <",
+                    measure_type
+                )))
+            }
+        };
+        let less_than = match self.evaluate_class_variable(project, module, &type_name, "lt") {
+            Some(PotentialValue::Resolved(lt)) => lt,
+            _ => return Err(measure_expr.error(&format!("{}.lt is not defined", type_name))),
+        };
+        let expected_type =
+            AcornType::new_functional(vec![measure_type.clone(), measure_type], AcornType::Bool);
+        check_type(measure_expr, Some(&expected_type), &less_than.get_type())?;
+        Ok((measure, less_than))
+    }
+
     // Evaluates a variable attached to an instance like foo.bar.
     // token is used for reporting errors but may not correspond to anything in particular.
     fn evaluate_instance_variable(
         &self,
-        source: &dyn ErrorSource,
+        source: &dyn ErrorSource,
+        project: &Project,
+        instance: AcornValue,
+        name: &str,
+    ) -> compilation::Result<AcornValue> {
+        let base_type = instance.get_type();
+        if let AcornType::Data(module, type_name) = &base_type {
+            let bindings = self
+                .attribute_bindings(project, *module, type_name, name)
+                .map_err(|message| source.error(&message))?;
+            bindings.apply_attribute(source, instance, name)
+        } else {
+            Err(source.error(&format!("objects of type {:?} have no members", base_type)))
+        }
+    }
+
+    // Applies an attribute that these bindings define to an instance of its class.
+    // The attribute might come from the module that defined the class, or from a module
+    // that the caller chose explicitly.
+    fn apply_attribute(
+        &self,
+        source: &dyn ErrorSource,
+        instance: AcornValue,
+        name: &str,
+    ) -> compilation::Result<AcornValue> {
+        let type_name = match instance.get_type() {
+            AcornType::Data(_, type_name) => type_name,
+            base_type => {
+                return Err(
+                    source.error(&format!("objects of type {:?} have no members", base_type))
+                )
+            }
+        };
+        let constant_name = format!("{}.{}", type_name, name);
+        let function = match self.get_constant_value(&constant_name) {
+            Some(PotentialValue::Resolved(value)) => value,
+            Some(PotentialValue::Unresolved(_)) => {
+                return Err(source.error(&format!("{}.{} has unresolved type", type_name, name)))
+            }
+            None => {
+                return Err(source.error(&format!("unknown instance variable '{}'", constant_name)))
+            }
+        };
+        // We need to typecheck that the apply is okay
+        match function.get_type() {
+            AcornType::Function(function_type) => {
+                check_type(
+                    source,
+                    Some(&function_type.arg_types[0]),
+                    &instance.get_type(),
+                )?;
+            }
+            _ => {
+                return Err(source.error("expected member to be a function"));
+            }
+        };
+        Ok(AcornValue::new_apply(function, vec![instance]))
+    }
+
+    // Evaluates an attribute that's qualified by the module that provides it, like the
+    // "(order.le)" in "x.(order.le)" or "Nat.(order.le)".
+    // This picks one attribute when more than one module gives a class an attribute
+    // with the same name.
+    fn evaluate_qualified_attribute(
+        &self,
+        stack: &mut Stack,
         project: &Project,
-        instance: AcornValue,
-        name: &str,
-    ) -> compilation::Result<AcornValue> {
-        let base_type = instance.get_type();
-        if let AcornType::Data(module, type_name) = base_type {
-            let bindings = if module == self.module {
-                &self
-            } else {
-                project.get_bindings(module).unwrap()
-            };
-            let constant_name = format!("{}.{}", type_name, name);
-            let function = match bindings.get_constant_value(&constant_name) {
-                Some(PotentialValue::Resolved(value)) => value,
-                Some(PotentialValue::Unresolved(_)) => {
-                    return Err(source.error(&format!("{}.{} has unresolved type", type_name, name)))
-                }
-                None => {
-                    return Err(
-                        source.error(&format!("unknown instance variable '{}'", constant_name))
-                    )
-                }
-            };
-            // We need to typecheck that the apply is okay
-            match function.get_type() {
-                AcornType::Function(function_type) => {
-                    check_type(
-                        source,
-                        Some(&function_type.arg_types[0]),
-                        &instance.get_type(),
-                    )?;
-                }
-                _ => {
-                    return Err(source.error("expected member to be a function"));
+        left: &Expression,
+        qualified: &Expression,
+    ) -> compilation::Result<NamedEntity> {
+        let (module_expr, attr_token) = match qualified {
+            Expression::Binary(module_expr, token, attr) if token.token_type == TokenType::Dot => {
+                match attr.as_ref() {
+                    Expression::Singleton(attr_token) => (module_expr, attr_token),
+                    _ => return Err(attr.error("expected an attribute name")),
                 }
-            };
-            Ok(AcornValue::new_apply(function, vec![instance]))
+            }
+            _ => {
+                return Err(
+                    qualified.error("expected a module and an attribute, like (module.name)")
+                )
+            }
+        };
+        let provider = match self.evaluate_entity(stack, project, module_expr)? {
+            NamedEntity::Module(module) => module,
+            _ => return Err(module_expr.error("expected a module")),
+        };
+        let bindings = if provider == self.module {
+            self
         } else {
-            Err(source.error(&format!("objects of type {:?} have no members", base_type)))
+            match project.get_bindings(provider) {
+                Some(bindings) => bindings,
+                None => return Err(module_expr.error("could not load bindings for module")),
+            }
+        };
+        let name = attr_token.text();
+        match self.evaluate_entity(stack, project, left)? {
+            NamedEntity::Value(instance) => {
+                let value = bindings.apply_attribute(attr_token, instance, name)?;
+                Ok(NamedEntity::Value(value))
+            }
+            NamedEntity::Type(AcornType::Data(_, type_name)) => {
+                match bindings.get_constant_value(&format!("{}.{}", type_name, name)) {
+                    Some(PotentialValue::Resolved(value)) => Ok(NamedEntity::Value(value)),
+                    Some(PotentialValue::Unresolved(u)) => Ok(NamedEntity::Unresolved(u)),
+                    None => Err(attr_token.error(&format!(
+                        "{} has no member named '{}' from that module",
+                        type_name, name
+                    ))),
+                }
+            }
+            _ => Err(left.error("only values and data types have qualified attributes")),
         }
     }
 
@@ -1046,6 +2336,10 @@ impl BindingMap {
                         )?;
                         return Ok(NamedEntity::Value(value));
                     }
+                    if let Err(message) = self.attribute_bindings(project, module, &type_name, name)
+                    {
+                        return Err(name_token.error(&message));
+                    }
                     match self.evaluate_class_variable(project, module, &type_name, name) {
                         Some(PotentialValue::Resolved(value)) => Ok(NamedEntity::Value(value)),
                         Some(PotentialValue::Unresolved(u)) => Ok(NamedEntity::Unresolved(u)),
@@ -1082,18 +2376,38 @@ impl BindingMap {
                         } else if let Some((i, t)) = stack.get(name) {
                             // This is a stack variable
                             Ok(NamedEntity::Value(AcornValue::Variable(*i, t.clone())))
-                        } else if let Some(potential) = self.get_constant_value(name) {
+                        } else if self.overload_candidates(self.resolve_shadow(name)).len() > 1 {
+                            // Overloads can only be resolved where the evaluator knows the
+                            // types involved.
+                            Err(name_token.error(&format!(
+                                "'{}' is overloaded, so it can't be used here",
+                                name
+                            )))
+                        } else if let Some(potential) =
+                            self.get_constant_value(self.resolve_shadow(name))
+                        {
                             Ok(potential.to_named_entity())
                         } else {
                             Err(name_token.error(&format!("unknown identifier '{}'", name)))
                         }
                     }
                     TokenType::Numeral => {
-                        let (module, type_name) = match &self.default {
-                            Some((module, type_name)) => (module, type_name),
-                            None => {
-                                return Err(name_token
-                                    .error("you must set a default type for numeric literals"));
+                        let (module, type_name) = if Token::decimal_parts(name).is_some() {
+                            match &self.decimals {
+                                Some((module, type_name)) => (module, type_name),
+                                None => {
+                                    return Err(name_token
+                                        .error("you must set a type for decimal literals"));
+                                }
+                            }
+                        } else {
+                            match &self.default {
+                                Some((module, type_name)) => (module, type_name),
+                                None => {
+                                    return Err(name_token.error(
+                                        "you must set a default type for numeric literals",
+                                    ));
+                                }
                             }
                         };
                         let value = self.evaluate_number_with_type(
@@ -1105,6 +2419,10 @@ impl BindingMap {
                         )?;
                         Ok(NamedEntity::Value(value))
                     }
+                    TokenType::StringLiteral => {
+                        let value = self.evaluate_string_literal(name_token, project)?;
+                        Ok(NamedEntity::Value(value))
+                    }
                     TokenType::SelfToken => {
                         if let Some((i, t)) = stack.get(name) {
                             // This is a stack variable
@@ -1129,6 +2447,11 @@ impl BindingMap {
     ) -> compilation::Result<NamedEntity> {
         let right_token = match right {
             Expression::Singleton(token) => token,
+            Expression::Grouping(left_paren, qualified, _)
+                if left_paren.token_type == TokenType::LeftParen =>
+            {
+                return self.evaluate_qualified_attribute(stack, project, left, qualified);
+            }
             _ => return Err(right.error("expected an identifier after a dot")),
         };
         let left_entity = self.evaluate_entity(stack, project, left)?;
@@ -1189,7 +2512,7 @@ impl BindingMap {
         expected_type: Option<&AcornType>,
     ) -> compilation::Result<AcornValue> {
         let left_value = self.evaluate_value_with_stack(stack, project, left, None)?;
-        let right_value = self.evaluate_value_with_stack(stack, project, right, None)?;
+        let mut right_value = self.evaluate_value_with_stack(stack, project, right, None)?;
 
         // Get the partial application to the left
         let partial = self.evaluate_instance_variable(expression, project, left_value, name)?;
@@ -1208,6 +2531,9 @@ impl BindingMap {
                     return Err(expression
                         .error(&format!("expected a binary function for '{}' method", name)));
                 }
+                if let Some(coerced) = self.coerce(&right_value, &f.arg_types[1]) {
+                    right_value = coerced;
+                }
                 check_type(expression, Some(&f.arg_types[1]), &right_value.get_type())?;
             }
             _ => return Err(expression.error(&format!("unexpected type for '{}' method", name))),
@@ -1219,6 +2545,96 @@ impl BindingMap {
         Ok(value)
     }
 
+    // The names that a wildcard import of this module brings in.
+    // These are the constants and types defined here, plus exported names, but not theorems.
+    // Generic and overloaded constants can't be imported by name, so they're skipped too.
+    pub fn importable_names(&self) -> Vec<String> {
+        let mut answer = vec![];
+        for (name, info) in &self.constants {
+            if name.contains('.')
+                || overload_base(name) != name
+                || !info.params.is_empty()
+                || !info.overloads.is_empty()
+                || self.theorems.contains(name)
+            {
+                continue;
+            }
+            answer.push(name.clone());
+        }
+        for (name, acorn_type) in &self.type_names {
+            if let AcornType::Data(module, type_name) = acorn_type {
+                if *module == self.module && type_name == name {
+                    answer.push(name.clone());
+                }
+            }
+        }
+        answer.extend(self.exported_with_prefix("").into_iter().cloned());
+        answer.sort();
+        answer
+    }
+
+    // Whether a name refers to the same value or type here as it does in another module.
+    fn binds_same_entity(&self, project: &Project, name: &str, other: &BindingMap) -> bool {
+        let token = TokenType::Identifier.new_token(name);
+        let stack = Stack::new();
+        match (
+            self.evaluate_name(&token, project, &stack, None),
+            other.evaluate_name(&token, project, &stack, None),
+        ) {
+            (Ok(NamedEntity::Value(a)), Ok(NamedEntity::Value(b))) => a == b,
+            (Ok(NamedEntity::Type(a)), Ok(NamedEntity::Type(b))) => a == b,
+            _ => false,
+        }
+    }
+
+    // Imports all the importable names from another module, for a wildcard import.
+    // Names that are already bound to the same thing are skipped.
+    // If any other names collide, nothing is imported, and the error lists every collision.
+    pub fn import_all(
+        &mut self,
+        project: &Project,
+        module: ModuleId,
+        source: &dyn ErrorSource,
+    ) -> compilation::Result<()> {
+        let bindings = match project.get_bindings(module) {
+            Some(b) => b,
+            None => return Err(source.error("could not load bindings for imported module")),
+        };
+        let mut names = vec![];
+        let mut conflicts = vec![];
+        for name in bindings.importable_names() {
+            if !self.name_in_use(&name) {
+                names.push(name);
+                continue;
+            }
+            if self.binds_same_entity(project, &name, bindings) {
+                continue;
+            }
+            let earlier = self
+                .wildcard_imports
+                .get(&name)
+                .and_then(|m| self.reverse_modules.get(m));
+            match earlier {
+                Some(module_name) => {
+                    conflicts.push(format!("{} (also imported from {})", name, module_name))
+                }
+                None => conflicts.push(name),
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(source.error(&format!(
+                "wildcard import conflicts with names already in scope: {}",
+                conflicts.join(", ")
+            )));
+        }
+        for name in names {
+            let token = TokenType::Identifier.new_token(&name);
+            self.import_name(project, module, &token)?;
+            self.wildcard_imports.insert(name, module);
+        }
+        Ok(())
+    }
+
     // Imports a name from another module.
     // The name could either be a type or a value.
     pub fn import_name(
@@ -1241,12 +2657,22 @@ impl BindingMap {
                 )
             }
         };
+        let name = name_token.text();
+        if bindings.imported.contains(name) && !bindings.exported.contains(name) {
+            // Names pass through a module only when it exports them.
+            return Err(name_token.error(&format!(
+                "{} is imported into that module, but not exported from it",
+                name
+            )));
+        }
         let entity = bindings.evaluate_name(name_token, project, &Stack::new(), None)?;
+        self.imported.insert(name.to_string());
         match entity {
             NamedEntity::Value(value) => {
                 // Add a local alias that mirrors this constant's name in the imported module.
                 if let Some((ext_module, ext_name)) = value.as_simple_constant() {
                     self.add_alias(
+                        project,
                         &name_token.text(),
                         ext_module,
                         ext_name.to_string(),
@@ -1303,14 +2729,10 @@ impl BindingMap {
         }
 
         // Determine the parameters for the instance function
-        let mut named_params = vec![];
         let mut instance_params = vec![];
         for param_name in &unresolved.params {
             match mapping.get(param_name) {
-                Some(t) => {
-                    named_params.push((param_name.clone(), t.clone()));
-                    instance_params.push(t.clone());
-                }
+                Some(t) => instance_params.push(t.clone()),
                 None => {
                     return Err(
                         source.error(&format!("parameter {} could not be inferred", param_name))
@@ -1318,14 +2740,7 @@ impl BindingMap {
                 }
             }
         }
-        let resolved_function_type = unresolved.generic_type.instantiate(&named_params);
-
-        let instance_fn = AcornValue::new_constant(
-            unresolved.module_id,
-            unresolved.name,
-            instance_params,
-            resolved_function_type,
-        );
+        let instance_fn = unresolved.resolve(instance_params);
         let value = AcornValue::new_apply(instance_fn, args);
         if expected_type.is_some() {
             check_type(source, expected_type, &value.get_type())?;
@@ -1342,8 +2757,142 @@ impl BindingMap {
         expression: &Expression,
         expected_type: Option<&AcornType>,
     ) -> compilation::Result<AcornValue> {
-        let potential = self.evaluate_potential_value(stack, project, expression, expected_type)?;
-        potential.value(expression)
+        let error = match self.evaluate_potential_value(stack, project, expression, expected_type) {
+            Ok(potential) => return potential.value(expression),
+            Err(e) => e,
+        };
+
+        // The value may just have the wrong type, and need a coercion.
+        if let Some(to_type) = expected_type {
+            if self.coercions.keys().any(|(_, t)| t == to_type) {
+                if let Ok(value) = self.evaluate_value_with_stack(stack, project, expression, None)
+                {
+                    if let Some(coerced) = self.coerce(&value, to_type) {
+                        return Ok(coerced);
+                    }
+                }
+            }
+        }
+        Err(error)
+    }
+
+    // If this expression is an overloaded name, returns a value for each of its constants.
+    // A stack variable shadows the constants, so then the name isn't overloaded.
+    fn overloaded_values(&self, stack: &Stack, expression: &Expression) -> Option<Vec<AcornValue>> {
+        let name = match expression {
+            Expression::Singleton(token) if token.token_type == TokenType::Identifier => {
+                token.text()
+            }
+            _ => return None,
+        };
+        if stack.get(name).is_some() {
+            return None;
+        }
+        let candidates = self.overload_candidates(self.resolve_shadow(name));
+        if candidates.len() < 2 {
+            return None;
+        }
+        let values = candidates
+            .into_iter()
+            .map(|candidate| {
+                let constant_type = self.identifier_types[&candidate].clone();
+                AcornValue::new_constant(self.module, candidate, vec![], constant_type)
+            })
+            .collect();
+        Some(values)
+    }
+
+    // Picks the overload of a name that has the expected type.
+    // The overloads all have different types, so there can't be more than one.
+    fn resolve_overload(
+        &self,
+        token: &Token,
+        candidates: Vec<AcornValue>,
+        expected_type: Option<&AcornType>,
+    ) -> compilation::Result<AcornValue> {
+        let expected_type = match expected_type {
+            Some(t) => t,
+            None => {
+                return Err(token.error(&format!(
+                    "'{}' is overloaded, so its type must be clear from context",
+                    token.text()
+                )));
+            }
+        };
+        match candidates
+            .into_iter()
+            .find(|candidate| candidate.get_type() == *expected_type)
+        {
+            Some(value) => Ok(value),
+            None => Err(token.error(&format!(
+                "no overload of '{}' has type {}",
+                token.text(),
+                expected_type
+            ))),
+        }
+    }
+
+    // Applies an overloaded function to some arguments.
+    // Exactly one of the overloads must accept the argument types and return the expected type.
+    fn apply_overload(
+        &self,
+        stack: &Stack,
+        project: &Project,
+        function_expr: &Expression,
+        candidates: Vec<AcornValue>,
+        args_expr: &Expression,
+        expected_type: Option<&AcornType>,
+    ) -> compilation::Result<AcornValue> {
+        let arg_exprs = match args_expr {
+            Expression::Grouping(_, e, _) => e.flatten_comma_separated_list(),
+            _ => return Err(args_expr.error("expected a comma-separated list")),
+        };
+        let mut matches = vec![];
+        for function in candidates {
+            let function_type = match function.get_type() {
+                AcornType::Function(f) => f,
+                _ => continue,
+            };
+            if function_type.arg_types.len() < arg_exprs.len() {
+                continue;
+            }
+
+            // Each attempt gets its own copy of the stack, in case it fails partway through.
+            let mut attempt = stack.clone();
+            let mut args = vec![];
+            for (arg_expr, arg_type) in arg_exprs.iter().zip(&function_type.arg_types) {
+                match self.evaluate_value_with_stack(
+                    &mut attempt,
+                    project,
+                    arg_expr,
+                    Some(arg_type),
+                ) {
+                    Ok(arg) if arg.get_type() == *arg_type => args.push(arg),
+                    _ => break,
+                }
+            }
+            if args.len() < arg_exprs.len() {
+                continue;
+            }
+            let value = AcornValue::new_apply(function, args);
+            if let Some(t) = expected_type {
+                if value.get_type() != *t {
+                    continue;
+                }
+            }
+            matches.push(value);
+        }
+        let name = function_expr.to_string();
+        match matches.len() {
+            0 => {
+                Err(function_expr.error(&format!("no overload of '{}' fits these arguments", name)))
+            }
+            1 => Ok(matches.pop().unwrap()),
+            _ => Err(function_expr.error(&format!(
+                "ambiguous use of '{}', since more than one overload fits",
+                name
+            ))),
+        }
     }
 
     // Evaluates an expression that could describe a value, but could also describe
@@ -1365,7 +2914,14 @@ impl BindingMap {
                     check_type(token, expected_type, &AcornType::Bool)?;
                     AcornValue::Bool(token.token_type == TokenType::True)
                 }
-                TokenType::Identifier | TokenType::Numeral | TokenType::SelfToken => {
+                TokenType::Identifier
+                | TokenType::Numeral
+                | TokenType::StringLiteral
+                | TokenType::SelfToken => {
+                    if let Some(candidates) = self.overloaded_values(stack, expression) {
+                        let value = self.resolve_overload(token, candidates, expected_type)?;
+                        return Ok(PotentialValue::Resolved(value));
+                    }
                     let entity = self.evaluate_name(token, project, stack, None)?;
                     match entity {
                         NamedEntity::Value(value) => {
@@ -1518,7 +3074,40 @@ impl BindingMap {
                     }
                 },
             },
+            Expression::Apply(function_expr, args_expr) if args_expr.is_type_param_list() => {
+                // Explicit type parameters, like identity<Nat>
+                let unresolved =
+                    match self.evaluate_potential_value(stack, project, function_expr, None)? {
+                        PotentialValue::Unresolved(u) => u,
+                        PotentialValue::Resolved(_) => {
+                            return Err(args_expr.error("this value has no type parameters"));
+                        }
+                    };
+                let mut instance_params = vec![];
+                self.evaluate_type_list(project, args_expr, &mut instance_params)?;
+                if instance_params.len() != unresolved.params.len() {
+                    return Err(args_expr.error(&format!(
+                        "expected {} type parameters, but got {}",
+                        unresolved.params.len(),
+                        instance_params.len()
+                    )));
+                }
+                let value = unresolved.resolve(instance_params);
+                check_type(expression, expected_type, &value.get_type())?;
+                value
+            }
             Expression::Apply(function_expr, args_expr) => {
+                if let Some(candidates) = self.overloaded_values(stack, function_expr) {
+                    let value = self.apply_overload(
+                        stack,
+                        project,
+                        function_expr,
+                        candidates,
+                        args_expr,
+                        expected_type,
+                    )?;
+                    return Ok(PotentialValue::Resolved(value));
+                }
                 let function =
                     self.evaluate_potential_value(stack, project, function_expr, None)?;
                 let function_type = function.get_type();
@@ -1621,30 +3210,82 @@ impl BindingMap {
                 )?;
                 AcornValue::IfThenElse(Box::new(cond), Box::new(if_value), Box::new(else_value))
             }
+            Expression::SetBuilder(_, decl, condition, _) => {
+                // A set is represented by its membership function.
+                let (arg_names, arg_types) =
+                    self.bind_args(stack, project, std::iter::once(decl.as_ref()), None)?;
+                let condition = self.evaluate_value_with_stack(
+                    stack,
+                    project,
+                    condition,
+                    Some(&AcornType::Bool),
+                );
+                stack.remove_all(&arg_names);
+                let value = AcornValue::Lambda(arg_types, Box::new(condition?));
+                check_type(expression, expected_type, &value.get_type())?;
+                value
+            }
+            Expression::Choose(_, decl, condition) => {
+                let (arg_names, mut arg_types) =
+                    self.bind_args(stack, project, std::iter::once(decl.as_ref()), None)?;
+                let condition = self.evaluate_value_with_stack(
+                    stack,
+                    project,
+                    condition,
+                    Some(&AcornType::Bool),
+                );
+                stack.remove_all(&arg_names);
+                let value = AcornValue::Choose(arg_types.pop().unwrap(), Box::new(condition?));
+                check_type(expression, expected_type, &value.get_type())?;
+                value
+            }
+            Expression::List(_, elements, _) => {
+                let value = self.evaluate_list_literal(stack, project, expression, elements)?;
+                check_type(expression, expected_type, &value.get_type())?;
+                value
+            }
             Expression::Match(_, scrutinee_exp, case_exps, _) => {
                 let mut expected_type: Option<AcornType> = expected_type.cloned();
                 let scrutinee =
                     self.evaluate_value_with_stack(stack, project, scrutinee_exp, None)?;
                 let scrutinee_type = scrutinee.get_type();
-                let mut cases = vec![];
-                let mut indices = vec![];
-                let mut all_cases = false;
-                for (pattern_exp, result_exp) in case_exps {
-                    let (_, args, i, total) =
+
+                // All the arms with the same pattern get merged into a single case.
+                // The guarded arms turn into if-then-else branches, falling through to the
+                // unguarded arm, so a case isn't complete until its unguarded arm shows up.
+                let mut coverage = MatchCoverage::new();
+                let mut cases: Vec<(Vec<AcornType>, AcornValue, Option<AcornValue>)> = vec![];
+                let mut guarded_arms: Vec<Vec<(AcornValue, AcornValue)>> = vec![];
+                for (pattern_exp, guard_exp, result_exp) in case_exps {
+                    let (args, shape) =
                         self.evaluate_pattern(project, &scrutinee_type, pattern_exp)?;
+                    let position = match coverage.add(shape, guard_exp.is_some()) {
+                        Some(position) => position,
+                        None => {
+                            return Err(
+                                pattern_exp.error("this pattern overlaps with an earlier case")
+                            )
+                        }
+                    };
                     for (name, arg_type) in &args {
                         stack.insert(name.clone(), arg_type.clone());
                     }
-                    if indices.contains(&i) {
-                        return Err(pattern_exp
-                            .error("cannot have multiple cases for the same constructor"));
-                    }
-                    indices.push(i);
-                    if total == indices.len() {
-                        all_cases = true;
+                    if position == cases.len() {
+                        let pattern =
+                            self.evaluate_value_with_stack(stack, project, pattern_exp, None)?;
+                        let arg_types = args.iter().map(|(_, t)| t.clone()).collect();
+                        cases.push((arg_types, pattern, None));
+                        guarded_arms.push(vec![]);
                     }
-                    let pattern =
-                        self.evaluate_value_with_stack(stack, project, pattern_exp, None)?;
+                    let guard = match guard_exp {
+                        Some(guard_exp) => Some(self.evaluate_value_with_stack(
+                            stack,
+                            project,
+                            guard_exp,
+                            Some(&AcornType::Bool),
+                        )?),
+                        None => None,
+                    };
                     let result = self.evaluate_value_with_stack(
                         stack,
                         project,
@@ -1654,17 +3295,37 @@ impl BindingMap {
                     if expected_type.is_none() {
                         expected_type = Some(result.get_type());
                     }
-                    let mut arg_types = vec![];
-                    for (name, arg_type) in args {
+                    for (name, _) in args {
                         stack.remove(&name);
-                        arg_types.push(arg_type);
                     }
-                    cases.push((arg_types, pattern, result));
+                    match guard {
+                        Some(guard) => guarded_arms[position].push((guard, result)),
+                        None => cases[position].2 = Some(result),
+                    }
+                }
+                let mut merged_cases = vec![];
+                for ((arg_types, pattern, result), guarded) in cases.into_iter().zip(guarded_arms) {
+                    let mut result = match result {
+                        Some(result) => result,
+                        None => {
+                            return Err(expression.error(
+                                "each guarded case needs an unguarded case to fall back on",
+                            ))
+                        }
+                    };
+                    for (guard, guarded_result) in guarded.into_iter().rev() {
+                        result = AcornValue::IfThenElse(
+                            Box::new(guard),
+                            Box::new(guarded_result),
+                            Box::new(result),
+                        );
+                    }
+                    merged_cases.push((arg_types, pattern, result));
                 }
-                if !all_cases {
-                    return Err(expression.error("not all constructors are covered in this match"));
+                if !coverage.is_exhaustive() {
+                    return Err(expression.error("not all cases are covered in this match"));
                 }
-                AcornValue::Match(Box::new(scrutinee), cases)
+                AcornValue::Match(Box::new(scrutinee), merged_cases)
             }
         };
         Ok(PotentialValue::Resolved(value))
@@ -1681,7 +3342,6 @@ impl BindingMap {
     // value_type_expr is an optional expression for the type of the value.
     //   (None means expect a boolean value.)
     // value_expr is the expression for the value itself.
-    // function_name, when it is provided, can be used recursively.
     //
     // This function mutates the binding map but sets it back to its original state when finished.
     //
@@ -1691,6 +3351,7 @@ impl BindingMap {
     //   a list of argument types
     //   an optional unbound value. (None means axiom.)
     //   the value type
+    //   the predicates for any refinement types in the arguments or the value type
     //
     // Wherever the argument types and the value type include the type parameters, they will
     // be type variables.
@@ -1708,13 +3369,13 @@ impl BindingMap {
         value_type_expr: Option<&Expression>,
         value_expr: &Expression,
         class_name: Option<&str>,
-        function_name: Option<&str>,
     ) -> compilation::Result<(
         Vec<String>,
         Vec<String>,
         Vec<AcornType>,
         Option<AcornValue>,
         AcornType,
+        Refinements,
     )> {
         // Bind all the type parameters and arguments
         let mut type_param_names: Vec<String> = vec![];
@@ -1726,39 +3387,22 @@ impl BindingMap {
             type_param_names.push(token.text().to_string());
         }
         let mut stack = Stack::new();
-        let (arg_names, arg_types) = self.bind_args(&mut stack, project, args, class_name)?;
+        let mut arg_refinements = vec![];
+        let (arg_names, arg_types) =
+            self.bind_refined_args(&mut stack, project, args, class_name, &mut arg_refinements)?;
 
         // Figure out types.
-        let value_type = match value_type_expr {
-            Some(e) => self.evaluate_type(project, e)?,
-            None => AcornType::Bool,
+        let (value_type, value_refinement) = match value_type_expr {
+            Some(e) => self.evaluate_refined_type(&mut stack, project, e)?,
+            None => (AcornType::Bool, None),
         };
 
-        if let Some(function_name) = function_name {
-            let fn_type = AcornType::new_functional(arg_types.clone(), value_type.clone());
-            self.add_constant(function_name, type_param_names.clone(), fn_type, None, None);
-        }
-
         // Evaluate the inner value using our modified bindings
         let value = if value_expr.is_axiom() {
             None
         } else {
             let value =
                 self.evaluate_value_with_stack(&mut stack, project, value_expr, Some(&value_type))?;
-
-            if let Some(function_name) = function_name {
-                let mut checker = TerminationChecker::new(
-                    self.module,
-                    function_name.to_string(),
-                    arg_types.len(),
-                );
-                if !checker.check(&value) {
-                    return Err(
-                        value_expr.error("the compiler thinks this looks like an infinite loop")
-                    );
-                }
-            }
-
             Some(value)
         };
 
@@ -1766,11 +3410,19 @@ impl BindingMap {
         for name in type_param_names.iter().rev() {
             self.remove_type_variable(&name);
         }
-        if let Some(function_name) = function_name {
-            self.remove_constant(&function_name);
-        }
 
-        Ok((type_param_names, arg_names, arg_types, value, value_type))
+        let refinements = Refinements {
+            args: arg_refinements,
+            value: value_refinement,
+        };
+        Ok((
+            type_param_names,
+            arg_names,
+            arg_types,
+            value,
+            value_type,
+            refinements,
+        ))
     }
 
     // Finds the names of all constants that are in this module but unknown to this binding map.
@@ -1778,47 +3430,19 @@ impl BindingMap {
     pub fn find_unknown_local_constants(
         &self,
         value: &AcornValue,
-        answer: &mut HashMap<String, AcornType>,
+        answer: &mut BTreeMap<String, AcornType>,
     ) {
-        match value {
-            AcornValue::Variable(_, _) | AcornValue::Bool(_) => {}
-            AcornValue::Constant(c) => {
-                if c.module_id == self.module && !self.constants.contains_key(&c.name) {
-                    assert!(c.params.is_empty());
-                    answer.insert(c.name.to_string(), c.instance_type.clone());
-                }
-            }
-
-            AcornValue::Application(app) => {
-                self.find_unknown_local_constants(&app.function, answer);
-                for arg in &app.args {
-                    self.find_unknown_local_constants(arg, answer);
-                }
-            }
-            AcornValue::Lambda(_, value)
-            | AcornValue::ForAll(_, value)
-            | AcornValue::Exists(_, value) => {
-                self.find_unknown_local_constants(value, answer);
-            }
-            AcornValue::Binary(_, left, right) => {
-                self.find_unknown_local_constants(left, answer);
-                self.find_unknown_local_constants(right, answer);
-            }
-            AcornValue::IfThenElse(cond, then_value, else_value) => {
-                self.find_unknown_local_constants(cond, answer);
-                self.find_unknown_local_constants(then_value, answer);
-                self.find_unknown_local_constants(else_value, answer);
-            }
-            AcornValue::Match(scrutinee, cases) => {
-                self.find_unknown_local_constants(scrutinee, answer);
-                for (_, pattern, result) in cases {
-                    self.find_unknown_local_constants(pattern, answer);
-                    self.find_unknown_local_constants(result, answer);
-                }
-            }
-            AcornValue::Not(value) => {
-                self.find_unknown_local_constants(value, answer);
-            }
+        let mut unknown = vec![];
+        ConstantFinder {
+            filter: |c: &ConstantInstance| {
+                c.module_id == self.module && !self.constants.contains_key(&c.name)
+            },
+            output: &mut unknown,
+        }
+        .visit_value(value, 0);
+        for c in unknown {
+            assert!(c.params.is_empty());
+            answer.insert(c.name, c.instance_type);
         }
     }
 
@@ -1829,18 +3453,22 @@ impl BindingMap {
     // Returns an error if this type can't be encoded as an expression.
     // Currently this should only happen when it's defined in a module that isn't directly imported.
     // In theory we could fix this, but we would have to track the web of dependencies.
-    fn type_to_expr(&self, acorn_type: &AcornType) -> Result<Expression, CodeGenError> {
+    fn type_to_expr(
+        &self,
+        acorn_type: &AcornType,
+        style: &CodeStyle,
+    ) -> Result<Expression, CodeGenError> {
         if let AcornType::Function(ft) = acorn_type {
             let mut args = vec![];
             for arg_type in &ft.arg_types {
-                args.push(self.type_to_expr(arg_type)?);
+                args.push(self.type_to_expr(arg_type, style)?);
             }
             let lhs = if args.len() == 1 {
                 args.pop().unwrap()
             } else {
                 Expression::generate_grouping(args)
             };
-            let rhs = self.type_to_expr(&ft.return_type)?;
+            let rhs = self.type_to_expr(&ft.return_type, style)?;
             return Ok(Expression::Binary(
                 Box::new(lhs),
                 TokenType::RightArrow.generate(),
@@ -1848,8 +3476,17 @@ impl BindingMap {
             ));
         }
 
-        // Check if there's a local alias for this type
-        if let Some(name) = self.reverse_type_names.get(acorn_type) {
+        // Check if there's a local alias for this type.
+        // Qualified names skip the alias, unless the type is local or its module isn't imported.
+        let qualifiable = match acorn_type {
+            AcornType::Data(module, _) => {
+                *module != self.module && self.reverse_modules.contains_key(module)
+            }
+            _ => false,
+        };
+        if style.qualified_names && qualifiable {
+            // Fall through to the module-qualified name.
+        } else if let Some(name) = self.reverse_type_names.get(acorn_type) {
             return Ok(Expression::generate_identifier(name));
         }
 
@@ -1890,16 +3527,41 @@ impl BindingMap {
         }
     }
 
+    // Like value_to_code, but for a type.
+    pub fn type_to_code(&self, acorn_type: &AcornType) -> Result<String, CodeGenError> {
+        Ok(self
+            .type_to_expr(acorn_type, &CodeStyle::canonical())?
+            .to_string())
+    }
+
     // If this value cannot be expressed in a single chunk of code, returns an error.
     // For example, it might refer to a constant that is not in scope.
     // Takes a next_k parameter so that it can be used sequentially in the middle of
     // a bunch of code generation.
     pub fn value_to_code(&self, value: &AcornValue) -> Result<String, CodeGenError> {
+        self.value_to_code_with_style(value, &CodeStyle::canonical())
+    }
+
+    // Like value_to_code, but the style controls naming, parentheses, operators, and wrapping.
+    pub fn value_to_code_with_style(
+        &self,
+        value: &AcornValue,
+        style: &CodeStyle,
+    ) -> Result<String, CodeGenError> {
         let mut var_names = vec![];
-        let mut next_x = 0;
-        let mut next_k = 0;
-        let expr = self.value_to_expr(value, &mut var_names, &mut next_x, &mut next_k)?;
-        Ok(expr.to_string())
+        let mut ctx = CodeGenContext {
+            next_x: 0,
+            next_k: 0,
+            style,
+        };
+        let mut expr = self.value_to_expr(value, &mut var_names, &mut ctx)?;
+        if style.full_parens {
+            expr = expr.parenthesize_operators();
+        }
+        Ok(match style.max_width {
+            Some(max_width) => expr.to_wrapped_string(max_width),
+            None => expr.to_string(),
+        })
     }
 
     // Given a module and a name, find an expression that refers to the name.
@@ -1907,7 +3569,12 @@ impl BindingMap {
     //   module, the canonical module of the entity we are trying to express
     // is different from
     //   self.module, the module we are trying to express the name in
-    fn name_to_expr(&self, module: ModuleId, name: &str) -> Result<Expression, CodeGenError> {
+    fn name_to_expr(
+        &self,
+        module: ModuleId,
+        name: &str,
+        style: &CodeStyle,
+    ) -> Result<Expression, CodeGenError> {
         let mut parts = name.split('.').collect::<Vec<_>>();
 
         // Handle numeric literals
@@ -1922,7 +3589,7 @@ impl BindingMap {
             }
 
             // Otherwise, we need to scope it by the type
-            let numeric_type = self.name_to_expr(module, parts[0])?;
+            let numeric_type = self.name_to_expr(module, parts[0], style)?;
             return Ok(Expression::Binary(
                 Box::new(numeric_type),
                 TokenType::Dot.generate(),
@@ -1930,24 +3597,58 @@ impl BindingMap {
             ));
         }
 
+        // The empty string
+        if parts.len() == 2 && parts[1] == "empty" {
+            if let Some((string_module, string_type_name)) = &self.strings {
+                if *string_module == module && string_type_name == parts[0] {
+                    return Ok(Expression::Singleton(Token::generate_string_literal("")));
+                }
+            }
+        }
+
+        // The empty list
+        if self.is_list_constructor(module, name, "nil") {
+            return Ok(Expression::generate_list(vec![]));
+        }
+
         if parts.len() > 2 {
             return Err(CodeGenError::UnhandledValue("unexpected dots".to_string()));
         }
 
         // Handle local constants
         if module == self.module {
+            // A constant that's hidden by a shadowing constant can't be referred to by name.
+            let written = user_name(name);
+            if self.resolve_shadow(written) != overload_base(name) {
+                return Err(CodeGenError::UnhandledValue(format!(
+                    "shadowed constant {}",
+                    written
+                )));
+            }
+
             // TODO: this generates an identifier token with a dot, which is wrong
-            return Ok(Expression::generate_identifier(name));
+            // Overloads are written with their shared name, and resolved by type.
+            return Ok(Expression::generate_identifier(written));
+        }
+        if overload_base(name) != name {
+            return Err(CodeGenError::UnhandledValue(format!(
+                "overloaded constant {} from another module",
+                name
+            )));
         }
 
         // Check if there's a local alias for this constant.
+        // Qualified names skip aliases whenever the module itself can be named.
+        let skip_aliases = style.qualified_names && self.reverse_modules.contains_key(&module);
         let key = (module, name.to_string());
         if let Some(alias) = self.canonical_to_alias.get(&key) {
-            return Ok(Expression::generate_identifier(alias));
+            if !skip_aliases {
+                return Ok(Expression::generate_identifier(alias));
+            }
         }
 
         // If it's a member function, check if there's a local alias for its struct.
-        if parts.len() == 2 {
+        if parts.len() == 2 && !skip_aliases {
             let data_type = AcornType::Data(module, parts[0].to_string());
             if let Some(type_alias) = self.reverse_type_names.get(&data_type) {
                 let lhs = Expression::generate_identifier(type_alias);
@@ -1970,6 +3671,28 @@ impl BindingMap {
         }
     }
 
+    // Generates an expression for a constant.
+    // If explicit_params is true, the type parameters are written out, like identity<Nat>.
+    fn constant_to_expr(
+        &self,
+        c: &ConstantInstance,
+        explicit_params: bool,
+        style: &CodeStyle,
+    ) -> Result<Expression, CodeGenError> {
+        let name_expr = self.name_to_expr(c.module_id, &c.name, style)?;
+        if !explicit_params || c.params.is_empty() {
+            return Ok(name_expr);
+        }
+        let mut param_exprs = vec![];
+        for param in &c.params {
+            param_exprs.push(self.type_to_expr(param, style)?);
+        }
+        Ok(Expression::Apply(
+            Box::new(name_expr),
+            Box::new(Expression::generate_type_param_list(param_exprs)),
+        ))
+    }
+
     // If use_x is true we use x-variables; otherwise we use k-variables.
     fn generate_quantifier_expr(
         &self,
@@ -1978,25 +3701,24 @@ impl BindingMap {
         value: &AcornValue,
         var_names: &mut Vec<String>,
         use_x: bool,
-        next_x: &mut u32,
-        next_k: &mut u32,
+        ctx: &mut CodeGenContext,
     ) -> Result<Expression, CodeGenError> {
         let initial_var_names_len = var_names.len();
         let mut decls = vec![];
         for arg_type in quants {
             let var_name = if use_x {
-                self.next_x_var(next_x)
+                self.next_x_var(&mut ctx.next_x)
             } else {
-                self.next_k_var(next_k)
+                self.next_k_var(&mut ctx.next_k)
             };
             let name_token = TokenType::Identifier.new_token(&var_name);
             var_names.push(var_name);
-            let type_expr = self.type_to_expr(arg_type)?;
+            let type_expr = self.type_to_expr(arg_type, ctx.style)?;
             let var_name = Declaration::Typed(name_token, type_expr);
             let decl = var_name;
             decls.push(decl);
         }
-        let subresult = self.value_to_expr(value, var_names, next_x, next_k)?;
+        let subresult = self.value_to_expr(value, var_names, ctx)?;
         var_names.truncate(initial_var_names_len);
         Ok(Expression::Binder(
             token_type.generate(),
@@ -2006,26 +3728,72 @@ impl BindingMap {
         ))
     }
 
+    // The name of the attribute that this function is, for instances of the class.
+    // Attributes that extend a class from another module only count when no other module
+    // defines the same attribute, so that the plain name isn't ambiguous.
+    fn member_name(&self, function: &AcornValue, class: &AcornType) -> Option<String> {
+        if let Some(name) = function.is_member(class) {
+            return Some(name);
+        }
+        let (module, name) = function.as_name()?;
+        let (type_name, attr) = name.split_once('.')?;
+        match class {
+            AcornType::Data(class_module, class_name)
+                if class_name == type_name
+                    && self.extension_providers(*class_module, class_name, attr) == [module] =>
+            {
+                Some(attr.to_string())
+            }
+            _ => None,
+        }
+    }
+
     // Convert an AcornValue to an Expression.
     fn value_to_expr(
         &self,
         value: &AcornValue,
         var_names: &mut Vec<String>,
-        next_x: &mut u32,
-        next_k: &mut u32,
+        ctx: &mut CodeGenContext,
     ) -> Result<Expression, CodeGenError> {
         match value {
             AcornValue::Variable(i, _) => {
                 Ok(Expression::generate_identifier(&var_names[*i as usize]))
             }
             AcornValue::Application(fa) => {
+                // Coercions get inserted automatically wherever the type of an argument is
+                // known in advance, so we can leave them out there.
+                // That isn't the case for arguments to generic functions, or for the instance
+                // that a member function is called on.
+                let is_member = self
+                    .member_name(&fa.function, &fa.args[0].get_type())
+                    .is_some();
+                let is_generic =
+                    matches!(fa.function.as_ref(), AcornValue::Constant(c) if !c.params.is_empty());
                 let mut args = vec![];
-                for arg in &fa.args {
-                    args.push(self.value_to_expr(arg, var_names, next_x, next_k)?);
+                for (i, arg) in fa.args.iter().enumerate() {
+                    let arg = if !is_generic && (i > 0 || !is_member) {
+                        self.strip_coercion(arg)
+                    } else {
+                        arg
+                    };
+                    args.push(self.value_to_expr(arg, var_names, ctx)?);
+                }
+
+                // List literals
+                if let Some((module, name)) = fa.function.as_simple_constant() {
+                    if args.len() == 2
+                        && self.is_list_constructor(module, name, "cons")
+                        && matches!(args[1], Expression::List(..))
+                    {
+                        if let Expression::List(left, mut elements, right) = args.pop().unwrap() {
+                            elements.insert(0, args.pop().unwrap());
+                            return Ok(Expression::List(left, elements, right));
+                        }
+                    }
                 }
 
-                if let Some(name) = fa.function.is_member(&fa.args[0].get_type()) {
-                    if args.len() == 1 {
+                if let Some(name) = self.member_name(&fa.function, &fa.args[0].get_type()) {
+                    if args.len() == 1 && ctx.style.infix_operators {
                         // Prefix operators
                         if let Some(op) = TokenType::from_prefix_magic_method_name(&name) {
                             return Ok(Expression::generate_unary(op, args.pop().unwrap()));
@@ -2034,26 +3802,73 @@ impl BindingMap {
 
                     if args.len() == 2 {
                         // Infix operators
-                        if let Some(op) = TokenType::from_infix_magic_method_name(&name) {
+                        let infix_op = TokenType::from_infix_magic_method_name(&name)
+                            .filter(|_| ctx.style.infix_operators);
+                        if let Some(op) = infix_op {
                             let right = args.pop().unwrap();
                             let left = args.pop().unwrap();
+                            let op = op.generate().with_precedence(&self.precedences);
                             return Ok(Expression::generate_binary(left, op, right));
                         }
 
                         // Long numeric literals
-                        if name == "read" && args[0].is_number() {
+                        if name == "read" && args[0].is_decimal_number() {
                             if let Some(digit) = args[1].to_digit() {
                                 let left = args.remove(0);
                                 return Ok(Expression::generate_number(left, digit));
                             }
                         }
+
+                        // Decimal literals
+                        if name == "from_parts" && args[0].is_decimal_number() {
+                            let text = args[1]
+                                .number_text()
+                                .zip(args[0].number_text())
+                                .and_then(|(d, n)| decimal_text(n, d));
+                            if let Some(text) = text {
+                                let left = args.remove(0);
+                                if self.is_decimal_type(&fa.args[0].get_type()) {
+                                    return Ok(Expression::Singleton(
+                                        TokenType::Numeral.new_token(&text),
+                                    ));
+                                }
+                                return Ok(left.with_number_text(&text));
+                            }
+                        }
+
+                        // Hexadecimal and binary numeric literals
+                        for radix in [16, 2] {
+                            if name != Token::radix_hook(radix) {
+                                continue;
+                            }
+                            let digit = args[1].number_text().and_then(|n| n.parse().ok());
+                            if let Some(digit) = digit {
+                                if let Some(text) = args[0].radix_number_text(radix, digit) {
+                                    let left = args.remove(0);
+                                    return Ok(left.with_number_text(&text));
+                                }
+                            }
+                        }
+
+                        // String literals
+                        if name == "append" && self.is_string_type(&fa.args[0].get_type()) {
+                            let code = args[1].number_text().and_then(|n| n.parse().ok());
+                            if let (Some(mut contents), Some(ch)) =
+                                (args[0].string_contents(), code.and_then(char::from_u32))
+                            {
+                                contents.push(ch);
+                                return Ok(Expression::Singleton(Token::generate_string_literal(
+                                    &contents,
+                                )));
+                            }
+                        }
                     }
 
                     // General member functions
                     let instance = args.remove(0);
                     let bound = Expression::generate_binary(
                         instance,
-                        TokenType::Dot,
+                        TokenType::Dot.generate(),
                         Expression::generate_identifier(&name),
                     );
                     if args.len() == 0 {
@@ -2069,18 +3884,31 @@ impl BindingMap {
                     }
                 }
 
-                let f = self.value_to_expr(&fa.function, var_names, next_x, next_k)?;
+                let f = match fa.function.as_ref() {
+                    AcornValue::Constant(c) => {
+                        // The type parameters can be inferred from the arguments, as long as
+                        // each one shows up in the argument types.
+                        // This is a heuristic, since we only have the instance types.
+                        let arg_types: Vec<_> = fa.args.iter().map(|a| a.get_type()).collect();
+                        let inferable = c
+                            .params
+                            .iter()
+                            .all(|p| arg_types.iter().any(|t| t.contains(p)));
+                        self.constant_to_expr(c, !inferable, ctx.style)?
+                    }
+                    function => self.value_to_expr(function, var_names, ctx)?,
+                };
                 let grouped_args = Expression::generate_grouping(args);
                 Ok(Expression::Apply(Box::new(f), Box::new(grouped_args)))
             }
             AcornValue::Binary(op, left, right) => {
-                let left = self.value_to_expr(left, var_names, next_x, next_k)?;
-                let right = self.value_to_expr(right, var_names, next_x, next_k)?;
+                let left = self.value_to_expr(left, var_names, ctx)?;
+                let right = self.value_to_expr(right, var_names, ctx)?;
                 let token = op.token_type().generate();
                 Ok(Expression::Binary(Box::new(left), token, Box::new(right)))
             }
             AcornValue::Not(x) => {
-                let x = self.value_to_expr(x, var_names, next_x, next_k)?;
+                let x = self.value_to_expr(x, var_names, ctx)?;
                 Ok(Expression::generate_unary(TokenType::Not, x))
             }
             AcornValue::ForAll(quants, value) => self.generate_quantifier_expr(
@@ -2089,8 +3917,7 @@ impl BindingMap {
                 value,
                 var_names,
                 true,
-                next_x,
-                next_k,
+                ctx,
             ),
             AcornValue::Exists(quants, value) => self.generate_quantifier_expr(
                 TokenType::Exists,
@@ -2098,17 +3925,34 @@ impl BindingMap {
                 value,
                 var_names,
                 false,
-                next_x,
-                next_k,
+                ctx,
             ),
+            AcornValue::Lambda(quants, value)
+                if ctx.style.set_builder
+                    && quants.len() == 1
+                    && value.get_type() == AcornType::Bool =>
+            {
+                // Boolean functions of one argument can be read as sets.
+                let var_name = self.next_x_var(&mut ctx.next_x);
+                let name_token = TokenType::Identifier.new_token(&var_name);
+                let type_expr = self.type_to_expr(&quants[0], ctx.style)?;
+                var_names.push(var_name);
+                let condition = self.value_to_expr(value, var_names, ctx);
+                var_names.pop();
+                Ok(Expression::SetBuilder(
+                    TokenType::LeftBrace.generate(),
+                    Box::new(Declaration::Typed(name_token, type_expr)),
+                    Box::new(condition?),
+                    TokenType::RightBrace.generate(),
+                ))
+            }
             AcornValue::Lambda(quants, value) => self.generate_quantifier_expr(
                 TokenType::Function,
                 quants,
                 value,
                 var_names,
                 true,
-                next_x,
-                next_k,
+                ctx,
             ),
             AcornValue::Bool(b) => {
                 let token = if *b {
@@ -2119,15 +3963,13 @@ impl BindingMap {
                 Ok(Expression::Singleton(token))
             }
             AcornValue::Constant(c) => {
-                // Here we are assuming that the context will be enough to disambiguate
-                // the type of the templated name.
-                // I'm not sure if this is a good assumption.
-                self.name_to_expr(c.module_id, &c.name)
+                // Without any arguments, there's nothing to infer type parameters from.
+                self.constant_to_expr(c, !c.params.is_empty(), ctx.style)
             }
             AcornValue::IfThenElse(condition, if_value, else_value) => {
-                let condition = self.value_to_expr(condition, var_names, next_x, next_k)?;
-                let if_value = self.value_to_expr(if_value, var_names, next_x, next_k)?;
-                let else_value = self.value_to_expr(else_value, var_names, next_x, next_k)?;
+                let condition = self.value_to_expr(condition, var_names, ctx)?;
+                let if_value = self.value_to_expr(if_value, var_names, ctx)?;
+                let else_value = self.value_to_expr(else_value, var_names, ctx)?;
                 Ok(Expression::IfThenElse(
                     TokenType::If.generate(),
                     Box::new(condition),
@@ -2139,6 +3981,27 @@ impl BindingMap {
             AcornValue::Match(_scrutinee, _cases) => {
                 todo!("codegen match expressions");
             }
+            AcornValue::Choose(choice_type, condition) => {
+                let var_name = self.next_x_var(&mut ctx.next_x);
+                let name_token = TokenType::Identifier.new_token(&var_name);
+                let type_expr = self.type_to_expr(choice_type, ctx.style)?;
+                var_names.push(var_name);
+                let condition = self.value_to_expr(condition, var_names, ctx);
+                var_names.pop();
+                let choose = Expression::Choose(
+                    TokenType::Choose.generate(),
+                    Box::new(Declaration::Typed(name_token, type_expr)),
+                    Box::new(condition?),
+                );
+
+                // The condition would swallow anything that comes after it, like later
+                // arguments, so the parentheses are always needed.
+                Ok(Expression::Grouping(
+                    TokenType::LeftParen.generate(),
+                    Box::new(choose),
+                    TokenType::RightParen.generate(),
+                ))
+            }
         }
     }
 
@@ -2159,7 +4022,9 @@ impl BindingMap {
 
     pub fn assert_type_ok(&mut self, input_code: &str) {
         let acorn_type = self.str_to_type(input_code);
-        let type_expr = self.type_to_expr(&acorn_type).unwrap();
+        let type_expr = self
+            .type_to_expr(&acorn_type, &CodeStyle::canonical())
+            .unwrap();
         let reconstructed_code = type_expr.to_string();
         let reevaluated_type = self.str_to_type(&reconstructed_code);
         assert_eq!(acorn_type, reevaluated_type);
@@ -2193,7 +4058,13 @@ impl BindingMap {
     // Check that this code, when converted to a value and back to code, is the same.
     pub fn expect_good_code(&self, input_code: &str) {
         let project = Project::new_mock();
-        let expression = Expression::expect_value(input_code);
+        let mut tokens = TokenIter::new(Token::scan(input_code));
+        for (token_type, precedence) in &self.precedences {
+            tokens.declare_precedence(*token_type, *precedence);
+        }
+        let (expression, _) =
+            Expression::parse_value(&mut tokens, Terminator::Is(TokenType::NewLine))
+                .expect("parse_value failed");
         let value = self
             .evaluate_value(&project, &expression, None)
             .expect("evaluate_value failed");
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 use tower_lsp::lsp_types::Range;
@@ -35,31 +35,56 @@ pub struct Block {
     // but we let the conclusion be determined by the code in the block.
     pub goal: Option<Goal>,
 
+    // A constraint on the arguments that the block assumes, relative to its internal
+    // environment. Claims exported from the block are conditional on it.
+    constraint: Option<AcornValue>,
+
     // The environment created inside the block.
     pub env: Environment,
+
+    // For a theorem, the way it was stated. The body can change the goal, so we keep the
+    // original around.
+    stated: Option<StatedTheorem>,
+}
+
+// A theorem's type parameters, premises, and goal, relative to its block's environment.
+// Each premise comes with its range.
+struct StatedTheorem {
+    type_params: Vec<String>,
+    premises: Vec<(AcornValue, Range)>,
+    goal: AcornValue,
 }
 
 // The different ways to construct a block
 pub enum BlockParams<'a> {
-    // (theorem name, theorem range, premise, goal)
+    // (theorem name, theorem range, premises, goal)
     //
-    // The premise and goal are unbound, to be proved based on the args of the theorem.
+    // The premises and goal are unbound, to be proved based on the args of the theorem.
+    // Each premise is a separate hypothesis, so "a and b -> c" has two premises.
     //
     // The theorem should already be defined by this name in the external environment.
     // It is either a bool, or a function from something -> bool.
     // The meaning of the theorem is that it is true for all args.
     //
-    // The premise is optional.
+    // There may be no premises at all.
+    // Each premise can have a label, to cite it by.
     Theorem(
         Option<&'a str>,
         Range,
-        Option<(AcornValue, Range)>,
+        Vec<(AcornValue, Range, Option<String>)>,
         AcornValue,
     ),
 
     // The assumption to be used by the block, and the range of this assumption.
     Conditional(&'a AcornValue, Range),
 
+    // The goal to be proved by contradiction, and the range of the statement.
+    // The block assumes the negation of the goal, and has to prove false.
+    Contradiction(&'a AcornValue, Range),
+
+    // The label and claim of a have statement, and the range of the statement.
+    Have(&'a str, AcornValue, Range),
+
     // The expression to solve for, and the range of the "solve <target>" component.
     Solve(AcornValue, Range),
 
@@ -69,16 +94,27 @@ pub enum BlockParams<'a> {
     FunctionSatisfy(AcornValue, AcornType, Range),
 
     // MatchCase represents a single case within a match statement.
-    // The scrutinee, the constructor, the pattern arguments, and the range of the pattern.
-    MatchCase(AcornValue, AcornValue, Vec<(String, AcornType)>, Range),
+    // The scrutinee, the pattern, the pattern arguments, the conditions from guards,
+    // and the range of the pattern.
+    // The pattern and the conditions are unbound, with a variable for each of the
+    // pattern arguments.
+    MatchCase(
+        AcornValue,
+        AcornValue,
+        Vec<(String, AcornType)>,
+        Vec<AcornValue>,
+        Range,
+    ),
 
     // The value is the boolean existence condition that we must prove is true, in order
     // for the constraint to express an inhabited type.
     // The range is of the constraint portion of the statement.
     Constraint(AcornValue, Range),
 
+    // The unbound constraint on the args, if there is one, and the range of the constraint.
+    ForAll(Option<(AcornValue, Range)>),
+
     // No special params needed
-    ForAll,
     Problem,
 }
 
@@ -102,13 +138,26 @@ impl Block {
             .collect();
 
         // Inside the block, the arguments are constants.
-        for (arg_name, generic_arg_type) in &args {
-            let specific_arg_type = generic_arg_type.instantiate(&param_pairs);
-            subenv
-                .bindings
-                .add_constant(&arg_name, vec![], specific_arg_type, None, None);
-        }
+        // An argument can shadow a constant from outside the block. The caller checks that
+        // this is allowed, and here the argument gets its internal name.
+        let args: Vec<_> = args
+            .into_iter()
+            .map(|(arg_name, generic_arg_type)| {
+                let arg_name = if subenv.bindings.name_in_use(&arg_name) {
+                    subenv.bindings.shadow(&arg_name)
+                } else {
+                    arg_name
+                };
+                let specific_arg_type = generic_arg_type.instantiate(&param_pairs);
+                subenv
+                    .bindings
+                    .add_constant(&arg_name, vec![], specific_arg_type, None, None);
+                (arg_name, generic_arg_type)
+            })
+            .collect();
 
+        let mut constraint = None;
+        let mut stated = None;
         let goal = match params {
             BlockParams::Conditional(condition, range) => {
                 subenv.add_node(
@@ -119,7 +168,24 @@ impl Block {
                 );
                 None
             }
-            BlockParams::Theorem(theorem_name, theorem_range, premise, unbound_goal) => {
+            BlockParams::Contradiction(goal, range) => {
+                subenv.add_node(
+                    project,
+                    true,
+                    Proposition::premise(goal.clone().negate(), env.module_id, range),
+                    None,
+                );
+                Some(Goal::Prove(Proposition::anonymous(
+                    AcornValue::Bool(false),
+                    env.module_id,
+                    range,
+                )))
+            }
+            BlockParams::Have(label, claim, range) => Some(Goal::Prove(
+                Proposition::anonymous(claim, env.module_id, range)
+                    .with_label(Some(label.to_string())),
+            )),
+            BlockParams::Theorem(theorem_name, theorem_range, premises, unbound_goal) => {
                 let arg_values = args
                     .iter()
                     .map(|(name, _)| {
@@ -138,21 +204,31 @@ impl Block {
                     subenv.add_identity_props(project, name);
                 }
 
-                if let Some((unbound_premise, premise_range)) = premise {
-                    // Add the premise to the environment, when proving the theorem.
-                    // The premise is unbound, so we need to bind the block's arg values.
+                let mut bound_premises = vec![];
+                for (unbound_premise, premise_range, label) in premises {
+                    // Add each premise to the environment, when proving the theorem.
+                    // The premises are unbound, so we need to bind the block's arg values.
                     let bound = unbound_premise.bind_values(0, 0, &arg_values);
+                    bound_premises.push((bound.clone(), premise_range));
+                    if let Some(label) = &label {
+                        subenv.add_label(label, bound.clone(), premise_range);
+                    }
 
                     subenv.add_node(
                         project,
                         true,
-                        Proposition::premise(bound, env.module_id, premise_range),
+                        Proposition::premise(bound, env.module_id, premise_range).with_label(label),
                         None,
                     );
                 }
 
                 // We can prove the goal either in bound or in function form
                 let bound_goal = unbound_goal.bind_values(0, 0, &arg_values);
+                stated = Some(StatedTheorem {
+                    type_params: type_params.clone(),
+                    premises: bound_premises,
+                    goal: bound_goal.clone(),
+                });
                 Some(Goal::Prove(Proposition::theorem(
                     false,
                     bound_goal,
@@ -180,7 +256,7 @@ impl Block {
                 let prop = Proposition::anonymous(bound_goal, env.module_id, range);
                 Some(Goal::Prove(prop))
             }
-            BlockParams::MatchCase(scrutinee, constructor, pattern_args, range) => {
+            BlockParams::MatchCase(scrutinee, pattern, pattern_args, conditions, range) => {
                 // Inside the block, the pattern arguments are constants.
                 let mut arg_values = vec![];
                 for (arg_name, arg_type) in pattern_args {
@@ -195,9 +271,18 @@ impl Block {
                             .force_value(),
                     );
                 }
-                // Inside the block, we can assume the pattern matches.
-                let applied = AcornValue::new_apply(constructor, arg_values);
-                let equality = AcornValue::new_equals(scrutinee, applied);
+                // Inside the block, we can assume the pattern matches, and the guards agree.
+                for condition in conditions {
+                    let condition = condition.bind_values(0, 0, &arg_values);
+                    subenv.add_node(
+                        project,
+                        true,
+                        Proposition::premise(condition, env.module_id, range),
+                        None,
+                    );
+                }
+                let pattern = pattern.bind_values(0, 0, &arg_values);
+                let equality = AcornValue::new_equals(scrutinee, pattern);
                 subenv.add_node(
                     project,
                     true,
@@ -215,9 +300,32 @@ impl Block {
                 )))
             }
             BlockParams::Solve(target, range) => Some(Goal::Solve(target, range)),
-            BlockParams::ForAll | BlockParams::Problem => None,
+            BlockParams::ForAll(Some((unbound_constraint, range))) => {
+                let arg_values = args
+                    .iter()
+                    .map(|(name, _)| {
+                        subenv
+                            .bindings
+                            .get_constant_value(name)
+                            .unwrap()
+                            .force_value()
+                    })
+                    .collect::<Vec<_>>();
+                let bound = unbound_constraint.bind_values(0, 0, &arg_values);
+                subenv.add_node(
+                    project,
+                    true,
+                    Proposition::premise(bound.clone(), env.module_id, range),
+                    None,
+                );
+                constraint = Some(bound);
+                None
+            }
+            BlockParams::ForAll(None) | BlockParams::Problem => None,
         };
 
+        // The statements in the body can change the goal.
+        subenv.goal = goal;
         match body {
             Some(body) => {
                 subenv.add_line_types(
@@ -241,11 +349,50 @@ impl Block {
         };
         Ok(Block {
             args,
+            goal: subenv.goal.take(),
+            constraint,
             env: subenv,
-            goal,
+            stated,
         })
     }
 
+    // Writes the theorem that this block proves, leaving out the premises with the given
+    // ranges, like "theorem foo(a: Nat) { a = a }".
+    // Returns None if this block isn't a theorem, or the theorem can't be written as code.
+    pub fn restate_theorem(&self, name: &str, dropped: &[Range]) -> Option<String> {
+        let stated = self.stated.as_ref()?;
+        let kept: Vec<_> = stated
+            .premises
+            .iter()
+            .filter(|(_, range)| !dropped.contains(range))
+            .map(|(premise, _)| premise.clone())
+            .collect();
+        let claim = if kept.is_empty() {
+            stated.goal.clone()
+        } else {
+            AcornValue::new_implies(AcornValue::reduce(BinaryOp::And, kept), stated.goal.clone())
+        };
+        let bindings = &self.env.bindings;
+        let mut args = vec![];
+        for (arg_name, arg_type) in &self.args {
+            args.push(format!(
+                "{}: {}",
+                arg_name,
+                bindings.type_to_code(arg_type).ok()?
+            ));
+        }
+        let mut answer = format!("theorem {}", name);
+        if !stated.type_params.is_empty() {
+            answer.push_str(&format!("<{}>", stated.type_params.join(", ")));
+        }
+        if !args.is_empty() {
+            answer.push_str(&format!("({})", args.join(", ")));
+        }
+        let claim = bindings.value_to_code(&claim).ok()?;
+        answer.push_str(&format!(" {{ {} }}", claim));
+        Some(answer)
+    }
+
     // Convert a boolean value from the block's environment to a value in the outer environment.
     fn export_bool(&self, outer_env: &Environment, inner_value: &AcornValue) -> AcornValue {
         // The constants that were block arguments will export as "forall" variables.
@@ -257,12 +404,13 @@ impl Block {
         }
 
         // Find all unexportable constants
-        let mut unexportable: HashMap<String, AcornType> = HashMap::new();
+        let mut unexportable: BTreeMap<String, AcornType> = BTreeMap::new();
         outer_env
             .bindings
             .find_unknown_local_constants(inner_value, &mut unexportable);
 
-        // Unexportable constants that are not arguments export as "exists" variables.
+        // Unexportable constants that are not arguments export as "exists" variables,
+        // in the order of their names.
         let mut exists_names = vec![];
         let mut exists_types = vec![];
         for (name, t) in unexportable {
@@ -317,7 +465,13 @@ impl Block {
                 return Err(token.error("expected a claim in this block"));
             }
         };
-        let outer_claim = self.export_bool(outer_env, inner_claim);
+        let outer_claim = match &self.constraint {
+            Some(constraint) => {
+                let conditional = AcornValue::new_implies(constraint.clone(), inner_claim.clone());
+                self.export_bool(outer_env, &conditional)
+            }
+            None => self.export_bool(outer_env, inner_claim),
+        };
         Ok((outer_claim, range))
     }
 
@@ -390,29 +544,7 @@ impl Node {
             assert!(block.is_none());
         }
 
-        // Expand theorems in the proposition.
-        let value = proposition.value.replace_constants(0, &|c| {
-            let bindings = if env.module_id == c.module_id {
-                &env.bindings
-            } else {
-                &project
-                    .get_env_by_id(c.module_id)
-                    .expect("missing module during add_proposition")
-                    .bindings
-            };
-            if bindings.is_theorem(&c.name) {
-                match bindings.get_definition_and_params(&c.name) {
-                    Some((def, params)) => {
-                        let pairs: Vec<_> = params.iter().cloned().zip(c.params.clone()).collect();
-                        Some(def.instantiate(&pairs))
-                    }
-                    None => None,
-                }
-            } else {
-                None
-            }
-        });
-
+        let value = env.expand_theorems(project, &proposition.value);
         let claim = proposition.with_value(value);
         Node {
             structural,
@@ -532,9 +664,9 @@ impl<'a> NodeCursor<'a> {
     }
 
     // All facts that can be used to prove the current node.
-    // This includes imported facts.
+    // This includes the imported facts that the project's tag filter allows.
     pub fn usable_facts(&self, project: &Project) -> Vec<Fact> {
-        let mut facts = project.imported_facts(self.env().module_id);
+        let mut facts = project.imported_facts(self.env().module_id, project.tag_filter());
         for (env, i) in &self.annotated_path {
             for prop in &env.nodes[0..*i] {
                 let truthiness = if env.top_level {
@@ -557,6 +689,17 @@ impl<'a> NodeCursor<'a> {
 
     // Get a goal context for the current node.
     pub fn goal_context(&self) -> Result<GoalContext, String> {
+        let mut goal_context = self.unlimited_goal_context()?;
+        for (env, index) in &self.annotated_path {
+            goal_context
+                .limits
+                .merge(&env.nodes[*index].claim.attributes.limits);
+        }
+        Ok(goal_context)
+    }
+
+    // The goal context, without the limits from surrounding theorems.
+    fn unlimited_goal_context(&self) -> Result<GoalContext, String> {
         let node = self.current();
         if node.structural {
             return Err(format!(
@@ -1,15 +1,19 @@
 use std::sync::atomic::AtomicU32;
 use std::time::Duration;
 
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Range};
 
+use crate::axiom_audit::AxiomAudit;
 use crate::compilation::Error;
 use crate::dataset::Dataset;
 use crate::environment::Environment;
 use crate::features::Features;
 use crate::goal::GoalContext;
+use crate::interfaces::Counterexample;
 use crate::module::ModuleDescriptor;
+use crate::proposition::SourceType;
 use crate::prover::{Outcome, Prover};
+use crate::search_stats::{SearchReport, SearchStats};
 
 static NEXT_BUILD_ID: AtomicU32 = AtomicU32::new(1);
 
@@ -36,6 +40,14 @@ pub struct BuildEvent {
     // Whenever we verify a goal, report the lines that the goal covers.
     // Note that this is only the final goal. Subgoals might have failed to verify.
     pub verified: Option<(u32, u32)>,
+
+    // When a goal has a countermodel, the details of it.
+    // The diagnostic carries the same information in its data field.
+    pub counterexample: Option<Counterexample>,
+
+    // When a goal's search finishes, how the search went.
+    // Goals verified from the cache don't have these.
+    pub search_stats: Option<SearchStats>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -110,9 +122,19 @@ pub struct Builder<'a> {
     // I guess if there is no current module, it's vacuously good.
     current_module_good: bool,
 
+    // The ranges of the premises that were used by proofs in the current module.
+    used_premises: Vec<Range>,
+
+    // The line ranges of goals in the current module that we didn't find a fresh proof for.
+    // We don't know which premises these goals depend on.
+    unproven_lines: Vec<(u32, u32)>,
+
     // If dataset is not None, we are gathering data for training.
     pub dataset: Option<Dataset>,
 
+    // If axiom_audit is not None, we are tracking which axioms each theorem depends on.
+    pub axiom_audit: Option<AxiomAudit>,
+
     // The Builder also tracks statistics.
     // Think of these as having a "goal_done" denominator.
     // When we use the cache, we don't use it to modify these statistics.
@@ -131,6 +153,9 @@ pub struct Builder<'a> {
 
     // The total amount of time spent proving, in seconds.
     pub proving_time: f64,
+
+    // The search statistics for each goal that was searched.
+    pub search_report: SearchReport,
 }
 
 impl<'a> Builder<'a> {
@@ -145,12 +170,16 @@ impl<'a> Builder<'a> {
             log_when_slow: false,
             current_module: None,
             current_module_good: true,
+            used_premises: vec![],
+            unproven_lines: vec![],
             dataset: None,
+            axiom_audit: None,
             num_success: 0,
             num_activated: 0,
             sum_square_activated: 0,
             num_clauses: 0,
             proving_time: 0.0,
+            search_report: SearchReport::default(),
         }
     }
 
@@ -162,6 +191,8 @@ impl<'a> Builder<'a> {
             module: self.module().clone(),
             diagnostic: None,
             verified: None,
+            counterexample: None,
+            search_stats: None,
         }
     }
 
@@ -175,7 +206,13 @@ impl<'a> Builder<'a> {
 
     // Called when a single module is loaded successfully.
     pub fn module_loaded(&mut self, env: &Environment) {
-        self.goals_total += env.iter_goals().count() as i32;
+        // A conjunction is verified one conjunct at a time, so each conjunct counts as a goal.
+        for node in env.iter_goals() {
+            self.goals_total += match node.goal_context() {
+                Ok(goal_context) => goal_context.subgoals().len() as i32,
+                Err(_) => 1,
+            };
+        }
     }
 
     // When create_dataset is called, that tells the Builder to gather data for training.
@@ -185,6 +222,13 @@ impl<'a> Builder<'a> {
         self.dataset = Some(Dataset::new());
     }
 
+    // When create_axiom_audit is called, the Builder tracks which axioms each theorem uses.
+    // Only call this before the build starts.
+    pub fn create_axiom_audit(&mut self) {
+        assert_eq!(self.goals_done, 0);
+        self.axiom_audit = Some(AxiomAudit::new());
+    }
+
     // Called when the entire loading phase is done.
     pub fn loading_phase_complete(&mut self) {
         let event = BuildEvent {
@@ -225,6 +269,8 @@ impl<'a> Builder<'a> {
     pub fn module_proving_started(&mut self, descriptor: ModuleDescriptor) {
         self.current_module = Some(descriptor);
         self.current_module_good = true;
+        self.used_premises.clear();
+        self.unproven_lines.clear();
     }
 
     // Returns whether the module completed without any errors or warnings.
@@ -259,11 +305,26 @@ impl<'a> Builder<'a> {
         let num_passive = prover.num_passive() as i32;
         self.num_clauses += num_activated + num_passive;
         self.sum_square_activated += (num_activated * num_activated) as u64;
+        self.search_report
+            .add(&goal_context.name, prover.search_stats());
 
+        let lines = (goal_context.first_line, goal_context.last_line);
         match outcome {
             Outcome::Success => match prover.get_proof() {
-                None => self.log_proving_warning(&prover, &goal_context, "had a missing proof"),
+                None => {
+                    self.unproven_lines.push(lines);
+                    self.log_proving_warning(prover, goal_context, "had a missing proof")
+                }
                 Some(proof) => {
+                    for source in proof.iter_sources() {
+                        if source.source_type == SourceType::Premise {
+                            self.used_premises.push(source.range);
+                        }
+                    }
+                    if let Some(audit) = &mut self.axiom_audit {
+                        audit.add_proof(goal_context.first_line, proof.iter_sources());
+                    }
+
                     if proof.needs_simplification() {
                         self.log_proving_warning(&prover, &goal_context, "needs simplification");
                     } else {
@@ -276,7 +337,7 @@ impl<'a> Builder<'a> {
                                 &format!("took {}", elapsed_str),
                             );
                         } else {
-                            self.log_proving_success(goal_context);
+                            self.log_proving_success(goal_context, Some(prover.search_stats()));
                         }
                     }
 
@@ -291,36 +352,163 @@ impl<'a> Builder<'a> {
                 }
             },
             Outcome::Exhausted => {
+                self.unproven_lines.push(lines);
                 self.log_proving_warning(&prover, &goal_context, "could not be verified")
             }
             Outcome::Inconsistent => {
+                self.unproven_lines.push(lines);
                 self.log_proving_warning(&prover, &goal_context, "- prover found an inconsistency")
             }
-            Outcome::Timeout => self.log_proving_warning(
-                &prover,
-                &goal_context,
-                &format!("timed out after {}", elapsed_str),
-            ),
+            Outcome::Timeout => {
+                self.unproven_lines.push(lines);
+                self.log_proving_warning(
+                    prover,
+                    goal_context,
+                    &format!("timed out after {}", elapsed_str),
+                )
+            }
             Outcome::Interrupted => {
+                self.unproven_lines.push(lines);
                 self.log_proving_error(&prover, &goal_context, "was interrupted");
             }
             Outcome::Error => {
+                self.unproven_lines.push(lines);
                 self.log_proving_error(&prover, &goal_context, "had an error");
             }
-            Outcome::Constrained => self.log_proving_warning(
-                &prover,
-                &goal_context,
-                "stopped after hitting constraints",
-            ),
+            Outcome::Countermodel => {
+                self.unproven_lines.push(lines);
+                self.log_countermodel(prover, goal_context);
+            }
+            Outcome::Constrained => {
+                self.unproven_lines.push(lines);
+                let message = match prover.limit_hit() {
+                    Some(limit) => format!("stopped after hitting the {}", limit),
+                    None => "stopped after hitting constraints".to_string(),
+                };
+                self.log_proving_warning(prover, goal_context, &message)
+            }
+        }
+    }
+
+    // Looks for theorems with hypotheses that none of their proofs used.
+    // Those theorems could be stated more generally, so we report an informational diagnostic.
+    // Call this after all the goals in the module have been searched.
+    // We only report on a theorem when we found a fresh proof for every goal in it.
+    pub fn check_unused_hypotheses(&mut self, env: &Environment) {
+        if self.status.is_error() {
+            // The build stopped partway, so some goals weren't searched.
+            return;
+        }
+        for node in &env.nodes {
+            let block = match &node.block {
+                Some(block) => block,
+                None => continue,
+            };
+            self.check_unused_hypotheses(&block.env);
+            let theorem_name = match &node.claim.source.source_type {
+                SourceType::Theorem(name) => name,
+                _ => continue,
+            };
+            let first_line = block.env.first_line;
+            let last_line = block.env.last_line();
+            if self
+                .unproven_lines
+                .iter()
+                .any(|(first, last)| *first <= last_line && first_line <= *last)
+            {
+                continue;
+            }
+            let unused: Vec<_> = block
+                .env
+                .nodes
+                .iter()
+                .map(|premise| &premise.claim)
+                .filter(|claim| {
+                    claim.source.source_type == SourceType::Premise
+                        && !self.used_premises.contains(&claim.source.range)
+                })
+                .collect();
+            let dropped: Vec<_> = unused.iter().map(|claim| claim.source.range).collect();
+            let restated = theorem_name
+                .as_ref()
+                .and_then(|name| block.restate_theorem(name, &dropped));
+            for claim in unused {
+                let source = &claim.source;
+                let hypothesis = match block.env.bindings.value_to_code(&claim.value) {
+                    Ok(code) => format!("hypothesis '{}'", code),
+                    Err(_) => "a hypothesis".to_string(),
+                };
+                let theorem = match theorem_name {
+                    Some(name) => name.to_string(),
+                    None => "this theorem".to_string(),
+                };
+                let mut message = format!(
+                    "{} is not used in the proof of {}, so it could be dropped",
+                    hypothesis, theorem
+                );
+                if let Some(restated) = &restated {
+                    message.push_str(&format!(". It could be stated as: {}", restated));
+                }
+                let diagnostic = Diagnostic {
+                    range: source.range,
+                    severity: Some(DiagnosticSeverity::INFORMATION),
+                    message: message.clone(),
+                    ..Diagnostic::default()
+                };
+                let event = BuildEvent {
+                    log_message: Some(message),
+                    diagnostic: Some(diagnostic),
+                    ..self.default_event()
+                };
+                (self.event_handler)(event);
+            }
+        }
+    }
+
+    // Reports the imported names and block-local definitions that nothing refers to.
+    // These are warnings, but the module still verifies, so they don't change the build status.
+    // Call this after proving, so the warnings don't hide verified goals.
+    pub fn check_unused_names(&mut self, env: &Environment) {
+        for (name, range) in env.find_unused_names() {
+            self.log_code_warning(range, format!("'{}' is never used", name));
         }
     }
 
+    // Reports the names that shadow another name without the "shadow" keyword.
+    // Like unused names, these don't change the build status.
+    pub fn check_implicit_shadows(&mut self, env: &Environment) {
+        for (name, range) in env.find_implicit_shadows() {
+            let message = format!(
+                "'{}' shadows a name from outside this block. use 'shadow' if that's intended",
+                name
+            );
+            self.log_code_warning(range, message);
+        }
+    }
+
+    // Logs a warning about the code itself, rather than about a proof.
+    fn log_code_warning(&mut self, range: Range, message: String) {
+        let diagnostic = Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::WARNING),
+            message: message.clone(),
+            ..Diagnostic::default()
+        };
+        let event = BuildEvent {
+            log_message: Some(message),
+            diagnostic: Some(diagnostic),
+            ..self.default_event()
+        };
+        (self.event_handler)(event);
+    }
+
     // Logs a successful proof.
-    fn log_proving_success(&mut self, goal_context: &GoalContext) {
+    fn log_proving_success(&mut self, goal_context: &GoalContext, stats: Option<SearchStats>) {
         let line_pair = (goal_context.first_line, goal_context.last_line);
         let event = BuildEvent {
             progress: Some((self.goals_done, self.goals_total)),
             verified: Some(line_pair),
+            search_stats: stats,
             ..self.default_event()
         };
         (self.event_handler)(event);
@@ -330,7 +518,9 @@ impl<'a> Builder<'a> {
     // Call as an alternative to search_finished.
     pub fn log_proving_success_cached(&mut self, goal_context: &GoalContext) {
         self.goals_done += 1;
-        self.log_proving_success(goal_context);
+        self.unproven_lines
+            .push((goal_context.first_line, goal_context.last_line));
+        self.log_proving_success(goal_context, None);
     }
 
     // Create a build event for a proof that was other than successful.
@@ -355,6 +545,7 @@ impl<'a> Builder<'a> {
             progress: Some((self.goals_done, self.goals_total)),
             log_message: Some(full_message),
             diagnostic: Some(diagnostic),
+            search_stats: Some(prover.search_stats()),
             ..self.default_event()
         }
     }
@@ -377,6 +568,32 @@ impl<'a> Builder<'a> {
         self.status.warn();
     }
 
+    // Logs a warning for a goal that has a countermodel, with the details attached.
+    fn log_countermodel(&mut self, prover: &Prover, goal_context: &GoalContext) {
+        let counterexample = prover.counterexample();
+        let mut message = "is probably false. Countermodel:".to_string();
+        if let Some(c) = &counterexample {
+            for line in &c.assignments {
+                message.push_str(&format!("\n  {}", line));
+            }
+            if !c.violated.is_empty() {
+                message.push_str("\nThe countermodel violates:");
+                for fact in &c.violated {
+                    message.push_str(&format!("\n  {}", fact));
+                }
+            }
+        }
+        let mut event =
+            self.make_event(prover, goal_context, &message, DiagnosticSeverity::WARNING);
+        if let Some(diagnostic) = &mut event.diagnostic {
+            diagnostic.data = serde_json::to_value(&counterexample).ok();
+        }
+        event.counterexample = counterexample;
+        (self.event_handler)(event);
+        self.current_module_good = false;
+        self.status.warn();
+    }
+
     // Logs an error during the proving phase.
     fn log_proving_error(&mut self, prover: &Prover, goal_context: &GoalContext, message: &str) {
         let mut event = self.make_event(prover, goal_context, message, DiagnosticSeverity::WARNING);
@@ -413,5 +630,8 @@ impl<'a> Builder<'a> {
         println!("{:.2} average clauses", num_clauses);
         let proving_time_ms = 1000.0 * self.proving_time / self.num_success as f64;
         println!("{:.1} ms average proving time", proving_time_ms);
+        if !self.search_report.is_empty() {
+            print!("{}", self.search_report);
+        }
     }
 }
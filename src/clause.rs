@@ -1,12 +1,14 @@
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::atom::{Atom, AtomId};
 use crate::literal::Literal;
 
 // A clause is a disjunction (an "or") of literals, universally quantified over some variables.
 // We include the types of the universal variables it is quantified over.
 // It cannot contain existential quantifiers.
-#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
 pub struct Clause {
     pub literals: Vec<Literal>,
 }
@@ -124,6 +126,45 @@ impl Clause {
         true
     }
 
+    // Groups the literals into components that don't share any variables with each other.
+    // Each ground literal is a component of its own.
+    // Components are ordered by their first literal, and keep the original variable ids.
+    pub fn components(&self) -> Vec<Vec<Literal>> {
+        // Each component is its variables, plus the indices of its literals.
+        let mut components: Vec<(Vec<Atom>, Vec<usize>)> = vec![];
+        for (i, literal) in self.literals.iter().enumerate() {
+            let mut vars: Vec<Atom> = literal
+                .typed_atoms()
+                .into_iter()
+                .map(|(_, atom)| atom)
+                .filter(|atom| atom.is_variable())
+                .collect();
+            let mut indices = vec![i];
+            let mut j = 0;
+            while j < components.len() {
+                if components[j].0.iter().any(|v| vars.contains(v)) {
+                    let (other_vars, other_indices) = components.remove(j);
+                    vars.extend(other_vars);
+                    indices.extend(other_indices);
+                } else {
+                    j += 1;
+                }
+            }
+            indices.sort();
+            components.push((vars, indices));
+        }
+        components.sort_by_key(|(_, indices)| indices[0]);
+        components
+            .into_iter()
+            .map(|(_, indices)| {
+                indices
+                    .into_iter()
+                    .map(|i| self.literals[i].clone())
+                    .collect()
+            })
+            .collect()
+    }
+
     // Whether any top level term has the given atom as its head.
     pub fn has_head(&self, atom: &Atom) -> bool {
         self.literals.iter().any(|x| x.has_head(atom))
@@ -140,3 +181,23 @@ impl Clause {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clause_components() {
+        let clause = Clause::parse("c0(x0, x1) or c1(x2) or c2(c3) or c1(x1)");
+        let mut sizes: Vec<_> = clause
+            .components()
+            .into_iter()
+            .map(|literals| literals.len())
+            .collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 1, 2]);
+
+        let clause = Clause::parse("c0(x0) or c1(x0)");
+        assert_eq!(clause.components().len(), 1);
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use im::{HashMap, Vector};
+
+use crate::clause::Clause;
+use crate::proof_step::Truthiness;
+
+// Each distinct clause that the prover has seen gets an id.
+pub type ClauseId = u32;
+
+// Stores every clause that's in the active or passive set, once.
+// A clause that gets generated again through a different inference path can be recognized
+// right away, rather than after it's been scored, queued, and activated.
+// The same clause with a different truthiness is a different entry, since a factual clause
+// can be used in ways that a counterfactual one can't.
+// Like the passive set, it's persistent, so that cloning the prover doesn't copy it.
+#[derive(Clone, Default)]
+pub struct ClauseArena {
+    // Indexed by clause id.
+    // A clause that has been forgotten keeps its id, but its entry is None.
+    clauses: Vector<Option<(Arc<Clause>, Truthiness)>>,
+
+    ids: HashMap<(Arc<Clause>, Truthiness), ClauseId>,
+}
+
+impl ClauseArena {
+    pub fn new() -> ClauseArena {
+        ClauseArena::default()
+    }
+
+    // Returns the id of the clause, and whether it's new.
+    pub fn intern(&mut self, clause: &Clause, truthiness: Truthiness) -> (ClauseId, bool) {
+        let key = (Arc::new(clause.clone()), truthiness);
+        if let Some(&id) = self.ids.get(&key) {
+            return (id, false);
+        }
+        let id = self.clauses.len() as ClauseId;
+        self.clauses.push_back(Some(key.clone()));
+        self.ids.insert(key, id);
+        (id, true)
+    }
+
+    // Stops recognizing the clause, so that it's new if it gets generated again.
+    // This is for clauses that were dropped without being activated.
+    pub fn forget(&mut self, clause: &Clause, truthiness: Truthiness) {
+        let key = (Arc::new(clause.clone()), truthiness);
+        if let Some(id) = self.ids.remove(&key) {
+            self.clauses[id as usize] = None;
+        }
+    }
+
+    // None if the clause has been forgotten.
+    pub fn get(&self, id: ClauseId) -> Option<&Clause> {
+        self.clauses[id as usize]
+            .as_ref()
+            .map(|(clause, _)| clause.as_ref())
+    }
+
+    // The id of a clause that has been interned and not forgotten.
+    pub fn get_id(&self, clause: &Clause, truthiness: Truthiness) -> Option<ClauseId> {
+        self.ids
+            .get(&(Arc::new(clause.clone()), truthiness))
+            .copied()
+    }
+
+    // The clauses that haven't been forgotten, in order of id.
+    pub fn iter(&self) -> impl Iterator<Item = &Clause> {
+        self.clauses
+            .iter()
+            .flatten()
+            .map(|(clause, _)| clause.as_ref())
+    }
+
+    // The number of clauses that haven't been forgotten.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clause_arena() {
+        let mut arena = ClauseArena::new();
+        let clause = Clause::parse("c0(x0) or c1(x0)");
+        let (id, new) = arena.intern(&clause, Truthiness::Factual);
+        assert!(new);
+        assert_eq!(
+            arena.intern(&Clause::parse("c0(x0) or c1(x0)"), Truthiness::Factual),
+            (id, false)
+        );
+        assert!(arena.intern(&clause, Truthiness::Counterfactual).1);
+        assert_eq!(arena.get(id), Some(&clause));
+        assert_eq!(arena.get_id(&clause, Truthiness::Factual), Some(id));
+
+        arena.forget(&clause, Truthiness::Factual);
+        assert_eq!(arena.get(id), None);
+        assert_eq!(arena.get_id(&clause, Truthiness::Factual), None);
+        assert_eq!(arena.len(), 1);
+        assert!(arena.intern(&clause, Truthiness::Factual).1);
+    }
+
+    #[test]
+    fn test_cloned_arena_is_independent() {
+        let mut arena = ClauseArena::new();
+        let clause = Clause::parse("c0(x0) or c1(x0)");
+        let (id, _) = arena.intern(&clause, Truthiness::Factual);
+        let mut cloned = arena.clone();
+        cloned.forget(&clause, Truthiness::Factual);
+        assert!(cloned.intern(&Clause::parse("c2"), Truthiness::Factual).1);
+        assert_eq!(arena.get(id), Some(&clause));
+        assert_eq!(arena.len(), 1);
+        assert_eq!(cloned.len(), 1);
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+
+use crate::arithmetic::{Evaluation, LinearArithmetic};
+use crate::atom::{Atom, AtomId};
+use crate::clause::Clause;
+use crate::literal::Literal;
+use crate::specializer::Specializer;
+use crate::subsumption::subsumes;
+use crate::term::Term;
+
+// How many times each simplification rule was used.
+#[derive(Clone, Debug, Default)]
+pub struct SimplificationStats {
+    // Clauses that were dropped for being always true.
+    pub tautologies: usize,
+
+    // Literals like "x != x" that were dropped for being always false.
+    pub trivial_inequalities: usize,
+
+    // Literals that were dropped because the clause already had them.
+    pub duplicate_literals: usize,
+
+    // Clauses that were replaced by a shorter instance of themselves.
+    pub condensations: usize,
+
+    // Literals that linear arithmetic decided or normalized.
+    pub arithmetic: usize,
+
+    // Clauses that changed when we normalized their AC operators.
+    pub ac_normalizations: usize,
+}
+
+impl SimplificationStats {
+    // How many times any of the rules were used.
+    pub fn total(&self) -> usize {
+        self.tautologies
+            + self.trivial_inequalities
+            + self.duplicate_literals
+            + self.condensations
+            + self.arithmetic
+            + self.ac_normalizations
+    }
+}
+
+// The simplifications that only look at a single clause.
+// Every generated clause goes through these before it's added to the passive set.
+#[derive(Clone, Default)]
+pub struct ClauseSimplifier {
+    pub stats: SimplificationStats,
+
+    // The numeric classes whose literals we can decide. Empty unless it's been set.
+    arithmetic: LinearArithmetic,
+
+    // The operators that are associative and commutative, so that clauses get normalized
+    // modulo AC.
+    ac_operators: HashSet<Atom>,
+
+    // The operators we have seen a commutativity or associativity axiom for.
+    commutative: HashSet<Atom>,
+    associative: HashSet<Atom>,
+}
+
+impl ClauseSimplifier {
+    pub fn new() -> ClauseSimplifier {
+        ClauseSimplifier::default()
+    }
+
+    pub fn set_arithmetic(&mut self, arithmetic: LinearArithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    // Normalizes clauses modulo associativity and commutativity of this operator.
+    // This trusts that the operator really is AC, so it's only for operators that are
+    // proven to be, or annotated with "@ac".
+    pub fn add_ac_operator(&mut self, operator: Atom) {
+        self.ac_operators.insert(operator);
+    }
+
+    // Checks whether a fact is the commutativity or associativity axiom for an operator.
+    // Once an operator has both, it gets normalized modulo AC.
+    pub fn check_ac_axiom(&mut self, clause: &Clause) {
+        let [literal] = clause.literals.as_slice() else {
+            return;
+        };
+        if !literal.positive {
+            return;
+        }
+        for (left, right) in [
+            (&literal.left, &literal.right),
+            (&literal.right, &literal.left),
+        ] {
+            if let Some(operator) = commutativity_operator(left, right) {
+                self.commutative.insert(operator);
+            } else if let Some(operator) = associativity_operator(left, right) {
+                self.associative.insert(operator);
+            } else {
+                continue;
+            }
+            let operator = left.head;
+            if self.commutative.contains(&operator) && self.associative.contains(&operator) {
+                self.add_ac_operator(operator);
+            }
+            return;
+        }
+    }
+
+    // Normalizes the applications of AC operators in every literal.
+    pub fn normalize_ac(&mut self, clause: &Clause) -> Clause {
+        if self.ac_operators.is_empty() {
+            return clause.clone();
+        }
+        let literals: Vec<Literal> = clause
+            .literals
+            .iter()
+            .map(|literal| literal.map(&mut |term| term.ac_normalize(&self.ac_operators)))
+            .collect();
+        if literals == clause.literals {
+            return clause.clone();
+        }
+        self.stats.ac_normalizations += 1;
+        Clause::new(literals)
+    }
+
+    // Returns None if the clause is a tautology, and the simplified clause otherwise.
+    pub fn simplify(&mut self, clause: &Clause) -> Option<Clause> {
+        let clause = &self.normalize_ac(clause);
+        if clause.is_tautology() {
+            self.stats.tautologies += 1;
+            return None;
+        }
+        let arithmetic_clause = self.simplify_arithmetic(clause)?;
+        let clause = &arithmetic_clause;
+
+        let mut literals: Vec<Literal> = vec![];
+        for literal in &clause.literals {
+            if literal.is_impossible() {
+                self.stats.trivial_inequalities += 1;
+            } else if literals.contains(literal) {
+                self.stats.duplicate_literals += 1;
+            } else {
+                literals.push(literal.clone());
+            }
+        }
+        let mut answer = if literals.len() == clause.len() {
+            clause.clone()
+        } else {
+            Clause::new(literals)
+        };
+
+        while let Some(condensed) = condense(&answer) {
+            self.stats.condensations += 1;
+            answer = condensed;
+        }
+        Some(answer)
+    }
+
+    // Only applies linear arithmetic.
+    // Returns None if some literal is true, and otherwise drops the false literals and
+    // normalizes the rest.
+    pub fn simplify_arithmetic(&mut self, clause: &Clause) -> Option<Clause> {
+        if self.arithmetic.is_empty() {
+            return Some(clause.clone());
+        }
+        let mut changed = false;
+        let mut literals = vec![];
+        for literal in &clause.literals {
+            match self.arithmetic.evaluate(literal) {
+                Evaluation::True => {
+                    self.stats.arithmetic += 1;
+                    return None;
+                }
+                Evaluation::False => {
+                    self.stats.arithmetic += 1;
+                    changed = true;
+                }
+                Evaluation::Normalized(normalized) => {
+                    self.stats.arithmetic += 1;
+                    changed = true;
+                    literals.push(normalized);
+                }
+                Evaluation::Unchanged => literals.push(literal.clone()),
+            }
+        }
+        if changed {
+            Some(Clause::new(literals))
+        } else {
+            Some(clause.clone())
+        }
+    }
+}
+
+// If left = right is "f(x, y) = f(y, x)", returns f.
+fn commutativity_operator(left: &Term, right: &Term) -> Option<Atom> {
+    let (x, y) = binary_args(left)?;
+    if left.head != right.head || x == y || !x.is_variable() || !y.is_variable() {
+        return None;
+    }
+    if right.args == [y.clone(), x.clone()] {
+        Some(left.head)
+    } else {
+        None
+    }
+}
+
+// If left = right is "f(f(x, y), z) = f(x, f(y, z))", returns f.
+fn associativity_operator(left: &Term, right: &Term) -> Option<Atom> {
+    let (xy, z) = binary_args(left)?;
+    let (x, y) = binary_args(xy)?;
+    let (x2, yz) = binary_args(right)?;
+    let (y2, z2) = binary_args(yz)?;
+    let heads = [xy.head, right.head, yz.head];
+    if heads.iter().any(|head| *head != left.head)
+        || !x.is_variable()
+        || !y.is_variable()
+        || !z.is_variable()
+        || x == y
+        || y == z
+        || x == z
+    {
+        return None;
+    }
+    if (x, y, z) == (x2, y2, z2) {
+        Some(left.head)
+    } else {
+        None
+    }
+}
+
+fn binary_args(term: &Term) -> Option<(&Term, &Term)> {
+    if term.head.is_variable() {
+        return None;
+    }
+    match term.args.as_slice() {
+        [a, b] => Some((a, b)),
+        _ => None,
+    }
+}
+
+// Condensation finds a substitution that makes two literals of a clause the same.
+// When the resulting shorter clause still subsumes the original, it's equivalent, so it
+// can replace the original.
+// For example, "c0(x0) or c0(c1)" condenses to "c0(c1)".
+// Returns None if there's no way to condense the clause.
+pub fn condense(clause: &Clause) -> Option<Clause> {
+    if !clause.has_any_variable() {
+        return None;
+    }
+    for (i, general) in clause.literals.iter().enumerate() {
+        for (j, special) in clause.literals.iter().enumerate() {
+            if i == j || general.positive != special.positive {
+                continue;
+            }
+            for (u, v) in [
+                (&special.left, &special.right),
+                (&special.right, &special.left),
+            ] {
+                let mut specializer = Specializer::new();
+                if !specializer.match_terms(&general.left, u)
+                    || !specializer.match_terms(&general.right, v)
+                {
+                    continue;
+                }
+                // Variables that didn't get matched stay as they are.
+                for var_id in 0..clause.num_quantifiers() {
+                    if let Some(var_type) = clause_var_type(clause, var_id) {
+                        let var = Term::atom(var_type, Atom::Variable(var_id));
+                        specializer.match_var(var_id, &var);
+                    }
+                }
+                let literals = clause
+                    .literals
+                    .iter()
+                    .map(|literal| {
+                        Literal::new(
+                            literal.positive,
+                            specializer.specialize(&literal.left),
+                            specializer.specialize(&literal.right),
+                        )
+                    })
+                    .collect();
+                let condensed = Clause::new(literals);
+                if condensed.len() < clause.len() && subsumes(&condensed, clause) {
+                    return Some(condensed);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn clause_var_type(clause: &Clause, var_id: AtomId) -> Option<AtomId> {
+    clause
+        .literals
+        .iter()
+        .find_map(|literal| literal.var_type(var_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condense() {
+        let condensed = condense(&Clause::parse("c0(x0) or c0(c1)"));
+        assert_eq!(condensed, Some(Clause::parse("c0(c1)")));
+
+        let condensed = condense(&Clause::parse("c0(x0, c1) or c0(c2, x1) or c3(x1)"));
+        assert_eq!(condensed, None);
+
+        let mut simplifier = ClauseSimplifier::new();
+        let clause = Clause::parse("c2(x0) = c1 or c2(c3) = c1 or x1 = c4");
+        assert_eq!(
+            simplifier.simplify(&clause),
+            Some(Clause::parse("c2(c3) = c1 or x0 = c4"))
+        );
+        assert_eq!(simplifier.stats.condensations, 1);
+    }
+
+    #[test]
+    fn test_ac_normalization() {
+        let mut simplifier = ClauseSimplifier::new();
+        let clause = Clause::parse("c0(c0(c1, c2), c3) = c4");
+        assert_eq!(simplifier.normalize_ac(&clause), clause);
+
+        // Once c0 is known to be AC, every grouping and ordering looks the same
+        simplifier.check_ac_axiom(&Clause::parse("c0(x0, x1) = c0(x1, x0)"));
+        assert_eq!(simplifier.normalize_ac(&clause), clause);
+        simplifier.check_ac_axiom(&Clause::parse("c0(c0(x0, x1), x2) = c0(x0, c0(x1, x2))"));
+        let other = Clause::parse("c0(c3, c0(c2, c1)) = c4");
+        assert_eq!(
+            simplifier.normalize_ac(&clause),
+            simplifier.normalize_ac(&other)
+        );
+        assert_eq!(
+            simplifier.simplify(&Clause::parse("c0(c1, c2) = c0(c2, c1)")),
+            None
+        );
+    }
+}
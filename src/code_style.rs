@@ -0,0 +1,65 @@
+// Settings for how generated code should look.
+// Different consumers want different things. Code that gets inserted into a file should match
+// what a person would write, while code that only gets displayed can be more explicit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeStyle {
+    // Whether imported names are always written with their module, like "nat.Nat",
+    // even when there is a local alias for them.
+    pub qualified_names: bool,
+
+    // Code longer than this gets wrapped onto multiple lines.
+    // None means it's always a single line.
+    pub max_width: Option<usize>,
+
+    // Whether to parenthesize every operator expression nested in another one, even when
+    // precedence makes it unnecessary.
+    pub full_parens: bool,
+
+    // Whether to write magic methods with their operators, like "a + b" instead of "a.add(b)".
+    pub infix_operators: bool,
+
+    // Whether to write boolean functions of one argument as sets, like "{ x: Nat | p(x) }".
+    // Not every such function is meant as a set, so this is off unless the code is about sets.
+    pub set_builder: bool,
+}
+
+impl Default for CodeStyle {
+    fn default() -> CodeStyle {
+        CodeStyle::canonical()
+    }
+}
+
+impl CodeStyle {
+    // The way people write code.
+    pub fn canonical() -> CodeStyle {
+        CodeStyle {
+            qualified_names: false,
+            max_width: None,
+            full_parens: false,
+            infix_operators: true,
+            set_builder: false,
+        }
+    }
+
+    // For proofs that the prover inserts into a file.
+    // Long claims get wrapped so that the proof stays readable.
+    pub fn proof() -> CodeStyle {
+        CodeStyle {
+            max_width: Some(100),
+            ..CodeStyle::canonical()
+        }
+    }
+
+    // For displaying a value on its own, like in the info panel.
+    // The reader can't see the surrounding code, so we are explicit about where names
+    // come from and how operators group.
+    pub fn hover() -> CodeStyle {
+        CodeStyle {
+            qualified_names: true,
+            max_width: None,
+            full_parens: true,
+            infix_operators: true,
+            set_builder: false,
+        }
+    }
+}
@@ -3,7 +3,10 @@
 
 use std::error::Error;
 use std::fs;
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
 pub fn files_dir() -> PathBuf {
     let d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -33,3 +36,51 @@ pub fn most_recent_onnx_model() -> Result<PathBuf, Box<dyn Error>> {
     };
     Ok(d.join(filename))
 }
+
+// Runs an external program with the given input on stdin, and returns what it wrote to stdout.
+// The program is killed if it runs longer than the timeout.
+pub fn run_with_timeout(
+    command: &str,
+    args: &[String],
+    input: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("could not run {}: {}", command, e))?;
+    let mut stdin = child.stdin.take().unwrap();
+    if let Err(e) = stdin.write_all(input.as_bytes()) {
+        let _ = child.kill();
+        return Err(format!("could not write to {}: {}", command, e));
+    }
+    drop(stdin);
+
+    // Read stdout on another thread, so that a chatty program can't fill up the pipe and block.
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if start.elapsed() < timeout => std::thread::sleep(Duration::from_millis(10)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err("timeout".to_string());
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    reader
+        .join()
+        .map_err(|_| format!("could not read output of {}", command))
+}
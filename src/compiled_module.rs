@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::binding_map::BindingMap;
+use crate::environment::Environment;
+use crate::module::{ModuleDescriptor, ModuleHash, ModuleId};
+use crate::project::Project;
+use crate::proposition::Proposition;
+
+// Bump this whenever the serialized format changes, so that old artifacts are ignored.
+const FORMAT_VERSION: u32 = 2;
+
+// A compiled module is everything that other modules need from a module: its bindings and
+// the facts it exports. It's saved as a .acm file after a module builds cleanly, so that later
+// runs can load it without parsing and elaborating the source again.
+//
+// Module ids are only meaningful within a single project, so a compiled module records the
+// descriptor of each module it refers to. Loading it remaps the ids to the loading project's.
+#[derive(Serialize, Deserialize)]
+pub struct CompiledModule {
+    format_version: u32,
+
+    // The id this module had when it was compiled.
+    module_id: ModuleId,
+
+    // The id and descriptor of each module this one depends on, when it was compiled.
+    dependencies: Vec<(ModuleId, ModuleDescriptor)>,
+
+    // The hash of the module when it was compiled.
+    // The compiled module is only valid if the source and its dependencies still match it.
+    pub hash: ModuleHash,
+
+    bindings: BindingMap,
+
+    // The claims of the top-level nodes. These are the facts that the module exports.
+    facts: Vec<Proposition>,
+}
+
+impl CompiledModule {
+    pub fn new(project: &Project, env: &Environment, hash: ModuleHash) -> CompiledModule {
+        let dependencies = project
+            .all_dependencies(env.module_id)
+            .into_iter()
+            .map(|id| (id, project.get_module_descriptor(id).clone()))
+            .collect();
+        CompiledModule {
+            format_version: FORMAT_VERSION,
+            module_id: env.module_id,
+            dependencies,
+            hash,
+            bindings: env.bindings.clone(),
+            facts: env.nodes.iter().map(|node| node.claim.clone()).collect(),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("failed to serialize compiled module")
+    }
+
+    // Returns None if the bytes aren't a compiled module in the current format.
+    pub fn deserialize(bytes: &[u8]) -> Option<CompiledModule> {
+        let compiled: CompiledModule = bincode::deserialize(bytes).ok()?;
+        if compiled.format_version != FORMAT_VERSION {
+            return None;
+        }
+        Some(compiled)
+    }
+
+    pub fn module_id(&self) -> ModuleId {
+        self.module_id
+    }
+
+    // The modules this one depends on, in the order they should be loaded.
+    pub fn dependencies(&self) -> &[(ModuleId, ModuleDescriptor)] {
+        &self.dependencies
+    }
+
+    // Creates the environment for this module, with module ids changed by the remap function.
+    pub fn into_environment(self, remap: &dyn Fn(ModuleId) -> ModuleId) -> Environment {
+        let bindings = self.bindings.remap_modules(remap);
+        let facts = self
+            .facts
+            .iter()
+            .map(|fact| fact.remap_modules(remap))
+            .collect();
+        Environment::from_compiled(remap(self.module_id), bindings, facts)
+    }
+}
@@ -106,4 +106,33 @@ impl ConstantMap {
         let key = &self.local_constants[atom_id as usize].as_ref().unwrap();
         (key.module, &key.name)
     }
+
+    // Every constant the prover has an atom for, along with its module and name.
+    pub fn iter(&self) -> impl Iterator<Item = (Atom, ModuleId, &str)> {
+        let globals = self
+            .global_constants
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| {
+                let key = key.as_ref()?;
+                Some((
+                    Atom::GlobalConstant(i as AtomId),
+                    key.module,
+                    key.name.as_str(),
+                ))
+            });
+        let locals = self
+            .local_constants
+            .iter()
+            .enumerate()
+            .filter_map(|(i, key)| {
+                let key = key.as_ref()?;
+                Some((
+                    Atom::LocalConstant(i as AtomId),
+                    key.module,
+                    key.name.as_str(),
+                ))
+            });
+        globals.chain(locals)
+    }
 }
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use im::Vector;
+
+use crate::atom::Atom;
+use crate::term::Term;
+use crate::type_map::TypeId;
+
+// Each subterm of a term turns into one key, in preorder.
+// Since each key knows how many args it has, the keys determine the shape of the term.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Key {
+    // A subterm with a variable head. It could unify with any subterm of the same type.
+    // An applied variable is a star too, because it can unify with a longer application,
+    // which shifts which arguments line up.
+    // Variable ids aren't stored, so the tree only finds candidates, and the caller still
+    // needs to unify them.
+    Star(TypeId),
+
+    // A subterm with this type and head, applied to this many args.
+    Symbol(TypeId, Atom, usize),
+}
+
+impl Key {
+    fn term_type(&self) -> TypeId {
+        match self {
+            Key::Star(t) => *t,
+            Key::Symbol(t, _, _) => *t,
+        }
+    }
+
+    fn num_args(&self) -> usize {
+        match self {
+            Key::Star(_) => 0,
+            Key::Symbol(_, _, n) => *n,
+        }
+    }
+}
+
+fn flatten(term: &Term, output: &mut Vec<Key>) {
+    if term.head.is_variable() {
+        output.push(Key::Star(term.get_term_type()));
+        return;
+    }
+    output.push(Key::Symbol(
+        term.get_term_type(),
+        term.head,
+        term.args.len(),
+    ));
+    for arg in &term.args {
+        flatten(arg, output);
+    }
+}
+
+// The children are shared, so that cloning a tree doesn't copy its nodes. Inserting copies
+// just the nodes along the path to the new value, if they are shared.
+#[derive(Clone, Debug)]
+struct Node<T: Clone> {
+    children: HashMap<Key, Arc<Node<T>>>,
+
+    // Only the nodes at the end of a whole term have values.
+    // Each value is stored along with the order it was inserted in.
+    values: Vector<(usize, T)>,
+}
+
+impl<T: Clone> Node<T> {
+    fn new() -> Node<T> {
+        Node {
+            children: HashMap::new(),
+            values: Vector::new(),
+        }
+    }
+
+    // Finds the nodes that come after skipping some number of whole subterms.
+    // The first subterm skipped must have the given type, if there is one.
+    fn skip<'a>(
+        &'a self,
+        term_type: Option<TypeId>,
+        subterms: usize,
+        output: &mut Vec<&'a Node<T>>,
+    ) {
+        for (key, child) in &self.children {
+            if let Some(t) = term_type {
+                if key.term_type() != t {
+                    continue;
+                }
+            }
+            let remaining = subterms - 1 + key.num_args();
+            if remaining == 0 {
+                output.push(child.as_ref());
+            } else {
+                child.skip(None, remaining, output);
+            }
+        }
+    }
+
+    // Finds the values for terms that could unify with the query, from this point on.
+    // next[i] is where the query subterm that starts at i ends.
+    fn find_unifying<'a>(
+        &'a self,
+        query: &[Key],
+        next: &[usize],
+        i: usize,
+        output: &mut Vec<&'a (usize, T)>,
+    ) {
+        if i == query.len() {
+            output.extend(self.values.iter());
+            return;
+        }
+        let term_type = query[i].term_type();
+        if let Key::Star(_) = query[i] {
+            let mut nodes = vec![];
+            self.skip(Some(term_type), 1, &mut nodes);
+            for node in nodes {
+                node.find_unifying(query, next, i + 1, output);
+            }
+            return;
+        }
+        if let Some(child) = self.children.get(&query[i]) {
+            child.find_unifying(query, next, i + 1, output);
+        }
+        if let Some(child) = self.children.get(&Key::Star(term_type)) {
+            child.find_unifying(query, next, next[i], output);
+        }
+    }
+}
+
+// A data structure designed to quickly find which terms unify with a query term.
+// It's a discrimination tree, where each path from the root is the preorder traversal of
+// a stored term. A query only walks the parts of the tree that could match it, so unlike
+// the FingerprintUnifier, it doesn't have to look at every term that's been inserted.
+// Its nodes are shared between clones, so it's cheap to clone along with the active set.
+#[derive(Clone, Debug)]
+pub struct DiscriminationTree<T: Clone> {
+    root: Node<T>,
+    len: usize,
+}
+
+impl<T: Clone> DiscriminationTree<T> {
+    pub fn new() -> DiscriminationTree<T> {
+        DiscriminationTree {
+            root: Node::new(),
+            len: 0,
+        }
+    }
+
+    pub fn insert(&mut self, term: &Term, value: T) {
+        let mut keys = vec![];
+        flatten(term, &mut keys);
+        let mut node = &mut self.root;
+        for key in keys {
+            let child = node
+                .children
+                .entry(key)
+                .or_insert_with(|| Arc::new(Node::new()));
+            node = Arc::make_mut(child);
+        }
+        node.values.push_back((self.len, value));
+        self.len += 1;
+    }
+
+    // Find all T whose term this term could unify with.
+    // They come in the order they were inserted.
+    pub fn find_unifying(&self, term: &Term) -> Vec<&T> {
+        let mut query = vec![];
+        flatten(term, &mut query);
+
+        // Where each subterm ends, so that a stored variable can skip over it.
+        let mut next = vec![0; query.len()];
+        let mut ends = vec![];
+        for i in (0..query.len()).rev() {
+            let mut end = i + 1;
+            for _ in 0..query[i].num_args() {
+                end = ends.pop().unwrap();
+            }
+            next[i] = end;
+            ends.push(end);
+        }
+
+        let mut output = vec![];
+        self.root.find_unifying(&query, &next, 0, &mut output);
+        output.sort_by_key(|(order, _)| *order);
+        output.into_iter().map(|(_, value)| value).collect()
+    }
+}
+
+impl<T: Clone> Default for DiscriminationTree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discrimination_tree() {
+        let mut tree = DiscriminationTree::new();
+        tree.insert(&Term::parse("c2(x0, x1, c0)"), 1);
+        tree.insert(&Term::parse("c2(c1, c3(c4), c0)"), 2);
+        tree.insert(&Term::parse("c2(c1, c3(c5), c0)"), 3);
+        tree.insert(&Term::parse("c3(c4)"), 4);
+        assert_eq!(
+            tree.find_unifying(&Term::parse("c2(c1, c3(x0), c0)")),
+            vec![&1, &2, &3]
+        );
+        assert_eq!(
+            tree.find_unifying(&Term::parse("c2(x0, c3(c4), x1)")),
+            vec![&1, &2]
+        );
+        assert_eq!(tree.find_unifying(&Term::parse("x0")), vec![&1, &2, &3, &4]);
+        assert_eq!(
+            tree.find_unifying(&Term::parse("c3(c1)")),
+            Vec::<&i32>::new()
+        );
+
+        // An applied variable can line up with the end of a longer application
+        tree.insert(&Term::parse("x0(c1)"), 5);
+        assert_eq!(tree.find_unifying(&Term::parse("c2(c3, c1)")), vec![&5]);
+    }
+}
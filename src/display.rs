@@ -3,8 +3,10 @@ use std::fmt;
 use crate::atom::Atom;
 use crate::clause::Clause;
 use crate::literal::Literal;
+use crate::model_finder::Model;
 use crate::normalizer::Normalizer;
 use crate::term::Term;
+use crate::type_map::{TypeId, BOOL};
 
 struct DisplayAtom<'a> {
     atom: Atom,
@@ -121,3 +123,50 @@ impl fmt::Display for DisplayClause<'_> {
         Ok(())
     }
 }
+
+// Shows a finite model one line at a time, with the elements of each type numbered.
+pub struct DisplayModel<'a> {
+    pub model: &'a Model,
+    pub normalizer: &'a Normalizer,
+}
+
+impl DisplayModel<'_> {
+    fn element(&self, type_id: TypeId, value: usize) -> String {
+        if type_id == BOOL {
+            (value == 1).to_string()
+        } else {
+            format!("{}#{}", self.normalizer.type_map.get_type(type_id), value)
+        }
+    }
+}
+
+impl fmt::Display for DisplayModel<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (type_id, size) in &self.model.sizes {
+            let elements: Vec<String> = (0..*size).map(|i| self.element(*type_id, i)).collect();
+            writeln!(
+                f,
+                "{} = {{{}}}",
+                self.normalizer.type_map.get_type(*type_id),
+                elements.join(", ")
+            )?;
+        }
+        for (key, entries) in &self.model.values {
+            let (atom, _) = key;
+            let (arg_types, value_type) = &self.model.signatures[key];
+            for (args, value) in entries {
+                write!(f, "{}", self.normalizer.atom_str(atom))?;
+                if !args.is_empty() {
+                    let args: Vec<String> = args
+                        .iter()
+                        .zip(arg_types)
+                        .map(|(arg, t)| self.element(*t, *arg))
+                        .collect();
+                    write!(f, "({})", args.join(", "))?;
+                }
+                writeln!(f, " = {}", self.element(*value_type, *value))?;
+            }
+        }
+        Ok(())
+    }
+}
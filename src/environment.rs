@@ -1,20 +1,27 @@
-use std::collections::HashMap;
-
+use im::OrdMap;
 use tower_lsp::lsp_types::Range;
 
 use crate::acorn_type::AcornType;
 use crate::acorn_value::{AcornValue, BinaryOp, FunctionApplication};
 use crate::atom::AtomId;
-use crate::binding_map::{BindingMap, Stack};
+use crate::binding_map::{user_name, BindingMap, PotentialValue, Refinements, Stack};
 use crate::block::{Block, BlockParams, Node, NodeCursor};
 use crate::compilation::{self, Error, ErrorSource};
-use crate::fact::Fact;
+use crate::expression::{Declaration, Expression};
+use crate::fact::{Fact, TagFilter};
+use crate::goal::Goal;
+use crate::match_coverage::{MatchCoverage, PatternShape};
 use crate::module::ModuleId;
 use crate::project::{LoadError, Project};
 use crate::proof_step::Truthiness;
-use crate::proposition::Proposition;
-use crate::statement::{Body, DefineStatement, LetStatement, Statement, StatementInfo};
+use crate::proposition::{Attributes, Proposition, SourceType};
+use crate::statement::{
+    Attribute, Body, ClassStatement, DefineStatement, DestructuringLetStatement, LetStatement,
+    RewriteStatement, Statement, StatementInfo, UnfoldStatement,
+};
+use crate::termination_checker::{MeasureChecker, TerminationChecker};
 use crate::token::{Token, TokenIter, TokenType};
+use crate::value_visitor::{UsageCollector, ValueVisitor};
 
 // Each line has a LineType, to handle line-based user interface.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -40,6 +47,144 @@ pub enum LineType {
     Closing,
 }
 
+// Splits the premise of a theorem into separate hypotheses, along with the range of the
+// expression for each one.
+// "a and b" is two hypotheses. The value and expression should have matching structure;
+// if they don't, we just treat the whole premise as one hypothesis.
+fn split_hypotheses(value: &AcornValue, expression: &Expression) -> Vec<(AcornValue, Range)> {
+    let expression = match expression {
+        Expression::Grouping(_, e, _) => return split_hypotheses(value, e),
+        e => e,
+    };
+    if let (
+        AcornValue::Binary(BinaryOp::And, left_value, right_value),
+        Expression::Binary(left_expr, token, right_expr),
+    ) = (value, expression)
+    {
+        if token.token_type == TokenType::And {
+            let mut answer = split_hypotheses(left_value, left_expr);
+            answer.extend(split_hypotheses(right_value, right_expr));
+            return answer;
+        }
+    }
+    vec![(value.clone(), expression.range())]
+}
+
+// Implicit type params are never written at the call site, so they must be inferable.
+// That means each one has to show up in the type of some argument.
+fn check_implicit_params(
+    type_params: &[Token],
+    arg_types: &[AcornType],
+) -> compilation::Result<()> {
+    for token in type_params {
+        if !arg_types.iter().any(|t| t.has_type_variable(token.text())) {
+            return Err(token.error(&format!(
+                "implicit type parameter {} must be used in an argument type",
+                token
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Errors if any of the arguments have refinement types.
+// Only theorems can use their arguments' refinements, as hypotheses.
+fn check_unrefined_args(
+    args: &[Declaration],
+    refinements: &Refinements,
+    message: &str,
+) -> compilation::Result<()> {
+    for (arg, predicate) in args.iter().zip(&refinements.args) {
+        if predicate.is_some() {
+            return Err(arg.token().error(message));
+        }
+    }
+    Ok(())
+}
+
+// Reads the attributes that precede a statement.
+fn read_attributes(attributes: &[Attribute]) -> compilation::Result<Attributes> {
+    let mut answer = Attributes::default();
+    for attribute in attributes {
+        let token = &attribute.name;
+        match &attribute.arg {
+            Some(arg) if token.text() == "tag" => answer.tags.push(arg.text().to_string()),
+            Some(arg) => {
+                let value = arg
+                    .text()
+                    .parse()
+                    .map_err(|_| arg.error(&format!("invalid value for {}", attribute)))?;
+                if !answer.limits.set(token.text(), value) {
+                    return Err(token.error(&format!("unknown attribute: {}", attribute)));
+                }
+            }
+            None => {
+                if !answer.set(token.text()) {
+                    return Err(token.error(&format!("unknown attribute: {}", attribute)));
+                }
+            }
+        }
+    }
+    Ok(answer)
+}
+
+// Definitions can have tags and "@ac", but not the attributes that change how the prover
+// works on a goal.
+fn definition_attributes(attributes: &[Attribute]) -> compilation::Result<Attributes> {
+    for attribute in attributes {
+        if attribute.name.text() == "precedence" {
+            let message = format!("{} can only be used on a magic method", attribute);
+            return Err(attribute.name.error(&message));
+        }
+        let single = read_attributes(std::slice::from_ref(attribute))?;
+        if single.simp || single.no_auto || single.limits != Default::default() {
+            let message = format!("{} can only be used on theorems", attribute);
+            return Err(attribute.name.error(&message));
+        }
+    }
+    read_attributes(attributes)
+}
+
+// The claim that a constant equals its definition, along with the constant itself.
+// Returns None if the constant has no definition.
+fn identity_claim(
+    bindings: &BindingMap,
+    module_id: ModuleId,
+    name: &str,
+) -> Option<(AcornValue, AcornValue)> {
+    let definition = bindings.get_definition(name)?.clone();
+
+    // This constant can be generic, with type variables in it.
+    let constant_type_clone = bindings.get_type_for_identifier(name).unwrap().clone();
+    let params = bindings
+        .get_params(name)
+        .into_iter()
+        .map(|name| AcornType::Variable(name, None))
+        .collect();
+    let constant =
+        AcornValue::new_constant(module_id, name.to_string(), params, constant_type_clone);
+
+    let claim = if let AcornValue::Lambda(acorn_types, return_value) = definition {
+        let args: Vec<_> = acorn_types
+            .iter()
+            .enumerate()
+            .map(|(i, acorn_type)| AcornValue::Variable(i as AtomId, acorn_type.clone()))
+            .collect();
+        let app = AcornValue::new_apply(constant.clone(), args);
+        AcornValue::ForAll(
+            acorn_types,
+            Box::new(AcornValue::Binary(
+                BinaryOp::Equals,
+                Box::new(app),
+                return_value,
+            )),
+        )
+    } else {
+        AcornValue::new_equals(constant.clone(), definition)
+    };
+    Some((claim, constant))
+}
+
 // The Environment takes Statements as input and processes them.
 // It does not prove anything directly, but it is responsible for determining which
 // things need to be proved, and which statements are usable in which proofs.
@@ -55,8 +200,17 @@ pub struct Environment {
     // Each node depends only on the nodes before it.
     pub nodes: Vec<Node>,
 
-    // The region in the source document where a name was defined
-    definition_ranges: HashMap<String, Range>,
+    // The region in the source document where a name was defined.
+    // Like the bindings, this is a persistent map so that child environments can share it.
+    definition_ranges: OrdMap<String, Range>,
+
+    // The names that shadow a constant from outside their block without the "shadow" keyword,
+    // and where they are declared.
+    implicit_shadows: Vec<(String, Range)>,
+
+    // The region in the source document where each name was imported with "from ... import".
+    // Wildcard imports aren't included.
+    import_ranges: OrdMap<String, Range>,
 
     // Whether a plain "false" is anywhere in this environment.
     // This indicates that the environment is supposed to have contradictory facts.
@@ -74,6 +228,24 @@ pub struct Environment {
 
     // Whether this environment is at the top level of a module.
     pub top_level: bool,
+
+    // The goal of the block that this environment is for, if it has one.
+    // Statements like "assume" and "suffices" change it while the block is being built.
+    pub goal: Option<Goal>,
+}
+
+// A saved state of an Environment, to roll back to after adding statements speculatively.
+// Adding statements only appends nodes and lines, so we just remember how many there were.
+// The maps are persistent, so saving them shares structure rather than copying.
+pub struct EnvironmentSnapshot {
+    bindings: BindingMap,
+    definition_ranges: OrdMap<String, Range>,
+    import_ranges: OrdMap<String, Range>,
+    num_implicit_shadows: usize,
+    num_nodes: usize,
+    num_lines: usize,
+    includes_explicit_false: bool,
+    goal: Option<Goal>,
 }
 
 impl Environment {
@@ -82,15 +254,39 @@ impl Environment {
             module_id,
             bindings: BindingMap::new(module_id),
             nodes: Vec::new(),
-            definition_ranges: HashMap::new(),
+            definition_ranges: OrdMap::new(),
+            implicit_shadows: vec![],
+            import_ranges: OrdMap::new(),
             includes_explicit_false: false,
             first_line: 0,
             line_types: Vec::new(),
             implicit: false,
             top_level: true,
+            goal: None,
         }
     }
 
+    // Creates the environment for a module that was loaded from a compiled artifact.
+    // It has the bindings and facts that other modules use, but no proofs or line information,
+    // so there is nothing in it to verify.
+    pub fn from_compiled(
+        module_id: ModuleId,
+        bindings: BindingMap,
+        facts: Vec<Proposition>,
+    ) -> Self {
+        let mut env = Environment::new(module_id);
+        env.bindings = bindings;
+        env.nodes = facts
+            .into_iter()
+            .map(|claim| Node {
+                structural: true,
+                claim,
+                block: None,
+            })
+            .collect();
+        env
+    }
+
     // Create a child environment.
     pub fn child(&self, first_line: u32, implicit: bool) -> Self {
         Environment {
@@ -98,14 +294,47 @@ impl Environment {
             bindings: self.bindings.clone(),
             nodes: Vec::new(),
             definition_ranges: self.definition_ranges.clone(),
+            implicit_shadows: vec![],
+            import_ranges: OrdMap::new(),
             includes_explicit_false: false,
             first_line,
             line_types: Vec::new(),
             implicit,
             top_level: false,
+            goal: None,
+        }
+    }
+
+    // Saves the current state, so that statements can be added and then rolled back.
+    pub fn snapshot(&self) -> EnvironmentSnapshot {
+        EnvironmentSnapshot {
+            bindings: self.bindings.clone(),
+            definition_ranges: self.definition_ranges.clone(),
+            import_ranges: self.import_ranges.clone(),
+            num_implicit_shadows: self.implicit_shadows.len(),
+            num_nodes: self.nodes.len(),
+            num_lines: self.line_types.len(),
+            includes_explicit_false: self.includes_explicit_false,
+            goal: self.goal.clone(),
         }
     }
 
+    // Rolls back to a snapshot of this environment, dropping everything added since.
+    // Modules that the project loaded for the dropped statements stay loaded.
+    pub fn restore(&mut self, snapshot: EnvironmentSnapshot) {
+        assert!(snapshot.num_nodes <= self.nodes.len());
+        assert!(snapshot.num_lines <= self.line_types.len());
+        self.bindings = snapshot.bindings;
+        self.definition_ranges = snapshot.definition_ranges;
+        self.import_ranges = snapshot.import_ranges;
+        self.implicit_shadows
+            .truncate(snapshot.num_implicit_shadows);
+        self.nodes.truncate(snapshot.num_nodes);
+        self.line_types.truncate(snapshot.num_lines);
+        self.includes_explicit_false = snapshot.includes_explicit_false;
+        self.goal = snapshot.goal;
+    }
+
     fn next_line(&self) -> u32 {
         self.line_types.len() as u32 + self.first_line
     }
@@ -167,55 +396,300 @@ impl Environment {
         self.nodes.len() - 1
     }
 
+    // Replaces any theorems in the value with their definitions.
+    pub fn expand_theorems(&self, project: &Project, value: &AcornValue) -> AcornValue {
+        value.replace_constants(0, &|c| {
+            let bindings = if self.module_id == c.module_id {
+                &self.bindings
+            } else {
+                &project
+                    .get_env_by_id(c.module_id)
+                    .expect("missing module during add_proposition")
+                    .bindings
+            };
+            if bindings.is_theorem(&c.name) {
+                match bindings.get_definition_and_params(&c.name) {
+                    Some((def, params)) => {
+                        let pairs: Vec<_> = params.iter().cloned().zip(c.params.clone()).collect();
+                        Some(def.instantiate(&pairs))
+                    }
+                    None => None,
+                }
+            } else {
+                None
+            }
+        })
+    }
+
     // Adds a proposition, or multiple propositions, to represent the definition of the provided
     // constant.
+    // Opaque constants don't get any, until they are unfolded.
     pub fn add_identity_props(&mut self, project: &Project, name: &str) {
-        let definition = if let Some(d) = self.bindings.get_definition(name) {
-            d.clone()
-        } else {
+        if self.bindings.is_opaque(name) {
             return;
+        }
+        let (claim, constant) = match identity_claim(&self.bindings, self.module_id, name) {
+            Some(pair) => pair,
+            None => return,
         };
+        let range = *self.definition_ranges.get(name).unwrap();
 
-        // This constant can be generic, with type variables in it.
-        let constant_type_clone = self.bindings.get_type_for_identifier(name).unwrap().clone();
-        let param_names = self.bindings.get_params(name);
-        let params = param_names
-            .into_iter()
-            .map(|name| AcornType::Variable(name, None))
-            .collect();
-        let constant = AcornValue::new_constant(
-            self.module_id,
-            name.to_string(),
-            params,
-            constant_type_clone,
+        self.add_node(
+            project,
+            true,
+            Proposition::constant_definition(claim, self.module_id, range, constant),
+            None,
         );
+    }
 
-        let claim = if let AcornValue::Lambda(acorn_types, return_value) = definition {
-            let args: Vec<_> = acorn_types
-                .iter()
-                .enumerate()
-                .map(|(i, acorn_type)| AcornValue::Variable(i as AtomId, acorn_type.clone()))
-                .collect();
-            let app = AcornValue::new_apply(constant.clone(), args);
-            AcornValue::ForAll(
-                acorn_types,
-                Box::new(AcornValue::Binary(
-                    BinaryOp::Equals,
-                    Box::new(app),
-                    return_value,
-                )),
-            )
-        } else {
-            AcornValue::new_equals(constant.clone(), definition)
+    // Adds a definition's attributes to all the nodes starting at the given index.
+    // Definitions can create several nodes, so we mark them after they are created.
+    fn mark_nodes_since(&mut self, index: usize, attributes: &Attributes) {
+        for node in &mut self.nodes[index..] {
+            node.claim.attributes.ac |= attributes.ac;
+            node.claim
+                .attributes
+                .tags
+                .extend(attributes.tags.iter().cloned());
+        }
+    }
+
+    // Rewrites either the goal or a hypothesis with a cited equation, left to right.
+    // The rewritten claim becomes the next proposition.
+    // When the goal is rewritten, the rewritten goal replaces it for the rest of the block.
+    fn add_rewrite_statement(
+        &mut self,
+        project: &mut Project,
+        statement: &Statement,
+        rs: &RewriteStatement,
+    ) -> compilation::Result<()> {
+        let citation =
+            self.bindings
+                .evaluate_value(project, &rs.equation, Some(&AcornType::Bool))?;
+        if !self.bindings.is_citation(project, &citation) {
+            return Err(rs.equation.error("rewrite needs a theorem to cite"));
+        }
+        let equation = self.expand_theorems(project, &citation).expand_lambdas(0);
+        let (left, right) = match &equation {
+            AcornValue::Binary(BinaryOp::Equals, left, right) => (left, right),
+            _ => {
+                return Err(rs.equation.error(&format!(
+                    "rewrite needs an equation, but the citation means '{}'",
+                    equation
+                )));
+            }
         };
-        let range = self.definition_ranges.get(name).unwrap().clone();
 
+        // The citation is proved like any other citation, before we use it.
+        let range = statement.range();
+        let index = self.add_node(
+            project,
+            false,
+            Proposition::anonymous(equation.clone(), self.module_id, rs.equation.range()),
+            None,
+        );
+        self.add_node_lines(index, &range);
+
+        match &rs.target {
+            Some(target) => {
+                let hypothesis =
+                    self.bindings
+                        .evaluate_value(project, target, Some(&AcornType::Bool))?;
+                let hypothesis = self.expand_theorems(project, &hypothesis).expand_lambdas(0);
+                let rewritten = hypothesis.replace_term(left, right);
+                if rewritten == hypothesis {
+                    return Err(target.error(&format!("there is no '{}' to rewrite", left)));
+                }
+                let index = self.add_node(
+                    project,
+                    false,
+                    Proposition::anonymous(rewritten, self.module_id, range),
+                    None,
+                );
+                self.add_node_lines(index, &range);
+            }
+            None => {
+                let goal = match &self.goal {
+                    Some(Goal::Prove(goal)) => goal.clone(),
+                    _ => {
+                        return Err(statement.error(
+                            "rewrite can only change the goal when there is a goal to prove",
+                        ));
+                    }
+                };
+                let rewritten = goal.value.replace_term(left, right);
+                if rewritten == goal.value {
+                    return Err(rs
+                        .equation
+                        .error(&format!("the goal has no '{}' to rewrite", left)));
+                }
+                let implication = AcornValue::new_implies(rewritten.clone(), goal.value.clone());
+                let index = self.add_node(
+                    project,
+                    false,
+                    Proposition::anonymous(implication, self.module_id, range),
+                    None,
+                );
+                self.add_node_lines(index, &range);
+                self.goal = Some(Goal::Prove(goal.with_value(rewritten)));
+            }
+        }
+        Ok(())
+    }
+
+    // Reveals the definition of an opaque constant, for the rest of this block.
+    fn add_unfold_statement(
+        &mut self,
+        project: &Project,
+        us: &UnfoldStatement,
+        range: Range,
+    ) -> compilation::Result<()> {
+        if self.top_level {
+            return Err(us
+                .target
+                .error("unfold can only be used inside a proof block"));
+        }
+        let (module_id, name) = self.bindings.evaluate_constant_name(project, &us.target)?;
+        let bindings = if module_id == self.module_id {
+            &self.bindings
+        } else {
+            project.get_bindings(module_id).unwrap()
+        };
+        if !bindings.is_opaque(&name) {
+            return Err(us.target.error(&format!("{} is not opaque", name)));
+        }
+        let (claim, constant) = match identity_claim(bindings, module_id, &name) {
+            Some(pair) => pair,
+            None => return Err(us.target.error(&format!("{} has no definition", name))),
+        };
         self.add_node(
             project,
             true,
             Proposition::constant_definition(claim, self.module_id, range, constant),
             None,
         );
+        Ok(())
+    }
+
+    // Finds the names introduced in this environment that nothing else refers to.
+    // These are the names imported with "from ... import" that aren't exported, and the
+    // constants defined inside proof blocks, since nothing outside the block can see them.
+    // Theorems and labels are never reported, because the prover uses them without citation.
+    // Returns (name, range) pairs in the order they appear in the source.
+    pub fn find_unused_names(&self) -> Vec<(String, Range)> {
+        let mut answer = vec![];
+        self.add_unused_names(None, &mut answer);
+        answer.sort_by_key(|(_, range)| (range.start.line, range.start.character));
+        answer
+    }
+
+    // Adds the unused names for this environment and the blocks within it.
+    // The outer environment is the one this block is in, if it's a block.
+    fn add_unused_names(&self, outer: Option<&Environment>, answer: &mut Vec<(String, Range)>) {
+        let mut usage = UsageCollector::default();
+        self.collect_usage(&mut usage);
+
+        for (name, range) in &self.import_ranges {
+            if self.bindings.is_exported(name) {
+                continue;
+            }
+            let key = if let Some(AcornType::Data(module, type_name)) =
+                self.bindings.get_type_for_name(name)
+            {
+                if usage.data_types.contains(&(*module, type_name.clone())) {
+                    continue;
+                }
+                None
+            } else {
+                match self.bindings.get_constant_value(name) {
+                    Some(PotentialValue::Resolved(value)) => value
+                        .as_name()
+                        .map(|(module, name)| (module, name.to_string())),
+                    _ => None,
+                }
+            };
+            if key.is_none_or(|key| !usage.constants.contains(&key)) {
+                answer.push((name.clone(), *range));
+            }
+        }
+
+        if let Some(outer) = outer {
+            for (name, range) in &self.definition_ranges {
+                if outer.definition_ranges.contains_key(name)
+                    || !self.bindings.is_constant(name)
+                    || self.bindings.is_theorem(name)
+                {
+                    continue;
+                }
+                if !usage.constants.contains(&(self.module_id, name.clone())) {
+                    answer.push((user_name(name).to_string(), *range));
+                }
+            }
+        }
+
+        for node in &self.nodes {
+            if let Some(block) = &node.block {
+                block.env.add_unused_names(Some(self), answer);
+            }
+        }
+    }
+
+    // Collects everything that the values in this environment refer to, including the
+    // blocks within it.
+    fn collect_usage(&self, usage: &mut UsageCollector) {
+        if self.top_level {
+            // Constants can be declared without any proposition mentioning them, so we check
+            // their types and definitions directly.
+            // Blocks share these bindings, so this only needs to happen once.
+            for name in self.bindings.constant_names() {
+                usage.defining = Some((self.module_id, name.clone()));
+                if let Some(constant_type) = self.bindings.get_type_for_identifier(name) {
+                    usage.add_type(constant_type);
+                }
+                if let Some(definition) = self.bindings.get_definition(name) {
+                    usage.visit_value(definition, 0);
+                }
+            }
+            usage.defining = None;
+            for canonical in self.bindings.local_alias_targets() {
+                usage.constants.insert(canonical.clone());
+            }
+        }
+        for node in &self.nodes {
+            usage.defining = match &node.claim.source.source_type {
+                SourceType::ConstantDefinition(constant) => constant
+                    .as_name()
+                    .map(|(module, name)| (module, name.to_string())),
+                _ => None,
+            };
+            usage.visit_value(&node.claim.value, 0);
+            usage.defining = None;
+            if let Some(block) = &node.block {
+                block.env.collect_usage(usage);
+            }
+        }
+    }
+
+    // Finds the names that shadow a constant from outside their block without saying so,
+    // in this environment and the blocks within it.
+    // Returns (name, range) pairs in the order they appear in the source.
+    pub fn find_implicit_shadows(&self) -> Vec<(String, Range)> {
+        let mut answer = self.implicit_shadows.clone();
+        for node in &self.nodes {
+            if let Some(block) = &node.block {
+                answer.extend(block.env.find_implicit_shadows());
+            }
+        }
+        answer.sort_by_key(|(_, range)| (range.start.line, range.start.character));
+        answer
+    }
+
+    // Makes a proposition citable by its label, like a theorem with no arguments.
+    pub fn add_label(&mut self, label: &str, claim: AcornValue, range: Range) {
+        self.bindings
+            .add_constant(label, vec![], AcornType::Bool, Some(claim), None);
+        self.bindings.mark_as_theorem(label);
+        self.definition_ranges.insert(label.to_string(), range);
     }
 
     pub fn get_definition(&self, name: &str) -> Option<&AcornValue> {
@@ -300,22 +774,77 @@ impl Environment {
         Ok(last_claim)
     }
 
+    // Records the attributes that a class block adds to a class from another module.
+    // An attribute can't already be on the class, either from the class's own module or from
+    // another module that this one imports.
+    fn add_class_extensions(
+        &mut self,
+        project: &Project,
+        class_module: ModuleId,
+        cs: &ClassStatement,
+    ) -> compilation::Result<()> {
+        let class_bindings = project.get_bindings(class_module).unwrap();
+        for substatement in &cs.body.statements {
+            let name_tokens: Vec<&Token> = match &substatement.statement {
+                StatementInfo::Let(ls) => {
+                    if ls.name_token.token_type == TokenType::Numeral {
+                        return Err(ls.name_token.error(
+                            "numeric literals can only be defined in the module of their class",
+                        ));
+                    }
+                    vec![&ls.name_token]
+                }
+                StatementInfo::Define(ds) => vec![&ds.name_token],
+                StatementInfo::DefineGroup(dss) => dss.iter().map(|ds| &ds.name_token).collect(),
+                _ => vec![],
+            };
+            for token in name_tokens {
+                let attr = token.text();
+                if class_bindings.has_identifier(&format!("{}.{}", cs.name, attr))
+                    || !self
+                        .bindings
+                        .extension_providers(class_module, &cs.name, attr)
+                        .is_empty()
+                {
+                    return Err(token.error(&format!(
+                        "{} already has an attribute named '{}'",
+                        cs.name, attr
+                    )));
+                }
+                self.bindings
+                    .add_class_extension(class_module, &cs.name, attr);
+            }
+        }
+        Ok(())
+    }
+
     // Adds a "let" statement to the environment, that may be within a class block.
+    // If the let has a refinement type, returns the index of the node for its proof obligation.
     fn add_let_statement(
         &mut self,
         project: &Project,
         class: Option<&str>,
         ls: &LetStatement,
         range: Range,
-    ) -> compilation::Result<()> {
+    ) -> compilation::Result<Option<usize>> {
         if class.is_none() && ls.name_token.token_type == TokenType::Numeral {
             return Err(ls
                 .name_token
                 .error("numeric literals may not be defined outside of a class"));
         }
+        if ls.name_token.token_type == TokenType::Numeral
+            && !ls.name.chars().all(|ch| ch.is_ascii_digit())
+        {
+            return Err(ls
+                .name_token
+                .error("only decimal integers may be defined as numeric literals"));
+        }
         if ls.name == "self"
             || ls.name == "new"
             || ls.name == "read"
+            || ls.name == "read_hex"
+            || ls.name == "read_binary"
+            || ls.name == "from_parts"
             || (class.is_some() && TokenType::is_magic_method_name(&ls.name))
         {
             return Err(ls.name_token.error(&format!(
@@ -323,17 +852,47 @@ impl Environment {
                 ls.name
             )));
         }
+        if ls.shadow && (self.top_level || class.is_some()) {
+            return Err(ls
+                .name_token
+                .error("only names inside a block can shadow other names"));
+        }
         let name = match class {
             Some(c) => format!("{}.{}", c, ls.name),
-            None => ls.name.clone(),
+            None => self.bindings.resolve_shadow(&ls.name).to_string(),
         };
-        if self.bindings.name_in_use(&name) {
+        let in_use = self.bindings.name_in_use(&name);
+
+        // Inside a block, a constant can shadow a constant from outside the block.
+        let shadowable = in_use && !self.top_level && self.bindings.can_shadow(&ls.name);
+        if in_use && !shadowable && !self.bindings.is_constant(&name) {
             return Err(ls.name_token.error(&format!(
                 "constant name '{}' already defined in this scope",
                 name
             )));
         }
-        let acorn_type = self.bindings.evaluate_type(project, &ls.type_expr)?;
+        let (acorn_type, refinement) =
+            self.bindings
+                .evaluate_refined_type(&mut Stack::new(), project, &ls.type_expr)?;
+
+        // Without the "shadow" keyword, a name that can be overloaded is an overload.
+        let shadowing =
+            shadowable && (ls.shadow || !self.bindings.can_overload(&name, &acorn_type));
+        let overloaded = in_use && !shadowing;
+
+        // A constant can share its name with other constants, as long as the types differ.
+        // Each overload is stored under an internal name.
+        let name = if overloaded {
+            if !self.bindings.can_overload(&name, &acorn_type) {
+                return Err(ls.name_token.error(&format!(
+                    "constant name '{}' already defined in this scope",
+                    name
+                )));
+            }
+            self.bindings.overload_name(&name)
+        } else {
+            name
+        };
         if ls.name_token.token_type == TokenType::Numeral {
             if acorn_type != AcornType::Data(self.module_id, class.unwrap().to_string()) {
                 return Err(ls
@@ -349,98 +908,414 @@ impl Environment {
                     .evaluate_value(project, &ls.value, Some(&acorn_type))?,
             )
         };
-        if let Some(value) = &value {
-            if let Some((canonical_module, canonical_name)) = value.as_simple_constant() {
+        let axiomatic = value.is_none();
+
+        // The value can still refer to the shadowed constant, so we only shadow it now.
+        let name = if shadowing {
+            if !ls.shadow {
+                self.implicit_shadows
+                    .push((ls.name.clone(), ls.name_token.range()));
+            }
+            self.bindings.shadow(&ls.name)
+        } else {
+            name
+        };
+        let subject = match &value {
+            Some(value) if overloaded && value.as_simple_constant().is_some() => {
+                return Err(ls
+                    .value
+                    .error("an overload must define a new constant, not an alias"));
+            }
+            Some(value) if value.as_simple_constant().is_some() => {
                 // 'let x = y' creates an alias for y, not a new constant.
+                let (canonical_module, canonical_name) = value.as_simple_constant().unwrap();
                 self.bindings.add_alias(
+                    project,
                     &name,
                     canonical_module,
                     canonical_name.to_string(),
                     value.get_type(),
                 );
-                return Ok(());
+                value.clone()
             }
-        }
+            _ => {
+                let constant = AcornValue::new_constant(
+                    self.module_id,
+                    name.clone(),
+                    vec![],
+                    acorn_type.clone(),
+                );
+                self.bindings
+                    .add_constant(&name, vec![], acorn_type, value, None);
+                self.definition_ranges.insert(name.clone(), range);
+                self.add_identity_props(project, &name);
+                constant
+            }
+        };
 
-        self.bindings
-            .add_constant(&name, vec![], acorn_type, value, None);
-        self.definition_ranges.insert(name.clone(), range);
-        self.add_identity_props(project, &name);
+        // A refinement type means the value must satisfy the predicate.
+        let Some(predicate) = refinement else {
+            return Ok(None);
+        };
+        let claim = predicate.beta_reduce(0, &[subject]);
+        let index = self.add_node(
+            project,
+            axiomatic,
+            Proposition::anonymous(claim, self.module_id, range),
+            None,
+        );
+        Ok(Some(index))
+    }
+
+    // Adds a destructuring "let" statement to the environment.
+    // Each name is defined as the corresponding member of the structure value.
+    fn add_destructuring_let(
+        &mut self,
+        project: &Project,
+        dls: &DestructuringLetStatement,
+        range: Range,
+    ) -> compilation::Result<()> {
+        let value = self.bindings.evaluate_value(project, &dls.value, None)?;
+        let value_type = value.get_type();
+        let members = match self.bindings.get_structure_members(project, &value_type) {
+            Some(members) => members,
+            None => {
+                return Err(dls.value.error(&format!(
+                    "cannot destructure a value of type {}",
+                    value_type
+                )))
+            }
+        };
+        let names_expr = match &dls.pattern {
+            Expression::Apply(constructor_expr, names_expr) => {
+                let constructor = self
+                    .bindings
+                    .evaluate_value(project, constructor_expr, None)?;
+                let is_new = match (&value_type, constructor.as_simple_constant()) {
+                    (AcornType::Data(module, type_name), Some((c_module, c_name))) => {
+                        *module == c_module && c_name == format!("{}.new", type_name)
+                    }
+                    _ => false,
+                };
+                if !is_new {
+                    return Err(constructor_expr
+                        .error(&format!("expected the constructor for {}", value_type)));
+                }
+                names_expr
+            }
+            pattern => pattern,
+        };
+        let name_exprs = names_expr.flatten_list(false)?;
+        if name_exprs.len() != members.len() {
+            return Err(names_expr.error(&format!(
+                "expected {} names but found {}",
+                members.len(),
+                name_exprs.len()
+            )));
+        }
+        for (name_expr, member) in name_exprs.into_iter().zip(members) {
+            let name = match name_expr {
+                Expression::Singleton(token)
+                    if token.token_type == TokenType::Identifier
+                        && token.text().starts_with(|c: char| c.is_ascii_lowercase()) =>
+                {
+                    token.text()
+                }
+                _ => return Err(name_expr.error("expected a constant name")),
+            };
+            if self.bindings.name_in_use(name) {
+                return Err(name_expr.error(&format!(
+                    "constant name '{}' already defined in this scope",
+                    name
+                )));
+            }
+            let member_value = AcornValue::new_apply(member, vec![value.clone()]);
+            self.bindings.add_constant(
+                name,
+                vec![],
+                member_value.get_type(),
+                Some(member_value),
+                None,
+            );
+            self.definition_ranges.insert(name.to_string(), range);
+            self.add_identity_props(project, name);
+        }
         Ok(())
     }
 
     // Adds a "define" statement to the environment, that may be within a class block.
+    // If the return type is a refinement type, returns the index of the node for its
+    // proof obligation.
     fn add_define_statement(
         &mut self,
         project: &Project,
         class_name: Option<&str>,
         ds: &DefineStatement,
         range: Range,
-    ) -> compilation::Result<()> {
-        if ds.name == "new" || ds.name == "self" {
-            return Err(ds.name_token.error(&format!(
-                "'{}' is a reserved word. use a different name",
-                ds.name
-            )));
+    ) -> compilation::Result<Option<usize>> {
+        self.add_define_group(project, class_name, std::slice::from_ref(ds), range)
+    }
+
+    // Adds a group of "define" statements whose functions can call each other.
+    // Either all of the functions are added, or none of them are.
+    // Returns the index of the last node for a proof obligation, if there are any.
+    fn add_define_group(
+        &mut self,
+        project: &Project,
+        class_name: Option<&str>,
+        dss: &[DefineStatement],
+        range: Range,
+    ) -> compilation::Result<Option<usize>> {
+        let mut names: Vec<String> = vec![];
+        for ds in dss {
+            if ds.name == "new" || ds.name == "self" {
+                return Err(ds.name_token.error(&format!(
+                    "'{}' is a reserved word. use a different name",
+                    ds.name
+                )));
+            }
+            let name = match class_name {
+                Some(c) => format!("{}.{}", c, ds.name),
+                None => ds.name.clone(),
+            };
+            if (self.bindings.name_in_use(&name) && !self.bindings.is_constant(&name))
+                || names.contains(&name)
+            {
+                return Err(ds.name_token.error(&format!(
+                    "function name '{}' already defined in this scope",
+                    name
+                )));
+            }
+            names.push(name);
         }
-        let name = match class_name {
-            Some(c) => format!("{}.{}", c, ds.name),
-            None => ds.name.clone(),
-        };
-        if self.bindings.name_in_use(&name) {
-            return Err(ds.name_token.error(&format!(
-                "function name '{}' already defined in this scope",
-                name
-            )));
+
+        // While the values are evaluated, each function in the group is a constant without
+        // a definition, so that the functions can call themselves and each other.
+        let mut signatures = vec![];
+        for ds in dss {
+            signatures.push(self.bindings.evaluate_function_type(
+                project,
+                &ds.type_params,
+                &ds.args,
+                &ds.return_type,
+                class_name,
+            )?);
         }
 
-        // Calculate the function value
-        let (param_names, _, arg_types, unbound_value, value_type) =
-            self.bindings.evaluate_scoped_value(
+        // A function that reuses the name of an existing constant is an overload of it.
+        for ((ds, name), (param_names, fn_type)) in dss.iter().zip(&mut names).zip(&signatures) {
+            if !self.bindings.name_in_use(name) {
+                continue;
+            }
+            if !param_names.is_empty() || !self.bindings.can_overload(name, fn_type) {
+                return Err(ds.name_token.error(&format!(
+                    "function name '{}' already defined in this scope",
+                    name
+                )));
+            }
+            *name = self.bindings.overload_name(name);
+        }
+        for (name, (param_names, fn_type)) in names.iter().zip(signatures) {
+            self.bindings
+                .add_constant(name, param_names, fn_type, None, None);
+        }
+        let mut evaluated = vec![];
+        let mut error = None;
+        for ds in dss {
+            match self.bindings.evaluate_scoped_value(
                 project,
                 &ds.type_params,
                 &ds.args,
                 Some(&ds.return_type),
                 &ds.return_value,
                 class_name,
-                Some(&name),
-            )?;
+            ) {
+                Ok(e) => evaluated.push(e),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        for name in &names {
+            self.bindings.remove_constant(name);
+        }
+        if let Some(e) = error {
+            return Err(e);
+        }
 
-        if let Some(class_name) = class_name {
-            let class_type = AcornType::Data(self.module_id, class_name.to_string());
-            if arg_types[0] != class_type {
-                return Err(ds.args[0].token().error("self must be the class type"));
+        for (ds, (_, _, arg_types, _, value_type, refinements)) in dss.iter().zip(&evaluated) {
+            if ds.implicit_type_params {
+                check_implicit_params(&ds.type_params, arg_types)?;
             }
+            if ds.opaque && ds.return_value.is_axiom() {
+                return Err(ds
+                    .name_token
+                    .error("axiomatic definitions cannot be opaque"));
+            }
+            check_unrefined_args(
+                &ds.args,
+                refinements,
+                "refinement types cannot be used for define arguments",
+            )?;
+
+            if let Some(class_name) = class_name {
+                let class_type = self.bindings.get_type_for_name(class_name).unwrap().clone();
+                if arg_types[0] != class_type {
+                    return Err(ds.args[0].token().error("self must be the class type"));
+                }
 
-            if ds.name == "read" {
-                if arg_types.len() != 2 || arg_types[1] != class_type || value_type != class_type {
+                if (ds.name == "read"
+                    || ds.name == "read_hex"
+                    || ds.name == "read_binary"
+                    || ds.name == "from_parts")
+                    && (arg_types.len() != 2
+                        || arg_types[1] != class_type
+                        || *value_type != class_type)
+                {
                     return Err(ds.name_token.error(&format!(
-                        "{}.read should be type ({}, {}) -> {}",
-                        class_name, class_name, class_name, class_name
+                        "{}.{} should be type ({}, {}) -> {}",
+                        class_name, ds.name, class_name, class_name, class_name
                     )));
                 }
             }
         }
 
-        if let Some(v) = unbound_value {
-            let fn_value = AcornValue::new_lambda(arg_types, v);
-            // Add the function value to the environment
-            self.bindings.add_constant(
-                &name,
-                param_names,
-                fn_value.get_type(),
-                Some(fn_value),
-                None,
-            );
-        } else {
-            let new_axiom_type = AcornType::new_functional(arg_types, value_type);
-            self.bindings
-                .add_constant(&name, param_names, new_axiom_type, None, None);
-        };
+        // Recursive definitions must terminate. With a measure, we leave it to the prover
+        // to show that the measure decreases. Otherwise, the termination checker has to be
+        // convinced syntactically.
+        let mut obligation_index = None;
+        if dss.len() > 1 {
+            if let Some(measure_expr) = dss.iter().find_map(|ds| ds.measure.as_ref()) {
+                return Err(measure_expr.error(
+                    "decreasing measures cannot be used in a group of mutually recursive definitions",
+                ));
+            }
+        }
+        let mut functions = vec![];
+        let mut values = vec![];
+        for (ds, (name, (_, _, arg_types, unbound_value, _, _))) in
+            dss.iter().zip(names.iter().zip(&evaluated))
+        {
+            if let Some(v) = unbound_value {
+                functions.push((name.clone(), arg_types.len()));
+                values.push(v);
+            } else if let Some(measure_expr) = &ds.measure {
+                return Err(measure_expr.error("axiomatic definitions cannot have a measure"));
+            }
+        }
+        match (&dss[0].measure, values.first()) {
+            (Some(measure_expr), Some(v)) => {
+                let (param_names, arg_names, arg_types, _, _, _) = &evaluated[0];
+                let (measure, less_than) = self.bindings.evaluate_measure(
+                    project,
+                    param_names,
+                    arg_names,
+                    arg_types,
+                    measure_expr,
+                )?;
+                let mut checker = MeasureChecker::new(
+                    self.module_id,
+                    names[0].clone(),
+                    arg_types.len(),
+                    measure,
+                    less_than,
+                );
+                let claim = match checker.check(v) {
+                    Some(claim) => claim,
+                    None => {
+                        return Err(dss[0]
+                            .return_value
+                            .error("a decreasing measure can only check direct recursive calls"))
+                    }
+                };
+                let claim = AcornValue::new_forall(arg_types.clone(), claim);
+                obligation_index = Some(self.add_node(
+                    project,
+                    false,
+                    Proposition::anonymous(claim, self.module_id, range),
+                    None,
+                ));
+            }
+            _ => {
+                let mut checker = TerminationChecker::new_group(self.module_id, functions);
+                if !checker.check_group(&values) {
+                    let value_expr = match dss.iter().find(|ds| !ds.return_value.is_axiom()) {
+                        Some(ds) => &ds.return_value,
+                        None => &dss[0].return_value,
+                    };
+                    return Err(
+                        value_expr.error("the compiler thinks this looks like an infinite loop")
+                    );
+                }
+            }
+        }
 
-        self.definition_ranges.insert(name.clone(), range);
-        self.add_identity_props(project, &name);
-        Ok(())
+        for (name, (param_names, _, arg_types, unbound_value, value_type, _)) in
+            names.iter().zip(&evaluated)
+        {
+            if let Some(v) = unbound_value {
+                let fn_value = AcornValue::new_lambda(arg_types.clone(), v.clone());
+                // Add the function value to the environment
+                self.bindings.add_constant(
+                    name,
+                    param_names.clone(),
+                    fn_value.get_type(),
+                    Some(fn_value),
+                    None,
+                );
+            } else {
+                let new_axiom_type =
+                    AcornType::new_functional(arg_types.clone(), value_type.clone());
+                self.bindings
+                    .add_constant(name, param_names.clone(), new_axiom_type, None, None);
+            };
+            self.definition_ranges.insert(name.clone(), range);
+        }
+        for (ds, name) in dss.iter().zip(&names) {
+            if ds.opaque {
+                self.bindings.mark_as_opaque(name);
+            }
+            self.add_identity_props(project, name);
+        }
+
+        // A refined return type means every return value must satisfy the predicate.
+        for (name, (_, _, arg_types, unbound_value, _, refinements)) in
+            names.into_iter().zip(evaluated)
+        {
+            let predicate = match refinements.value {
+                Some(predicate) => predicate,
+                None => continue,
+            };
+            let params = self
+                .bindings
+                .get_params(&name)
+                .into_iter()
+                .map(|param| AcornType::Variable(param, None))
+                .collect();
+            let fn_type = self
+                .bindings
+                .get_type_for_identifier(&name)
+                .unwrap()
+                .clone();
+            let constant = AcornValue::new_constant(self.module_id, name, params, fn_type);
+            let args: Vec<_> = arg_types
+                .iter()
+                .enumerate()
+                .map(|(i, arg_type)| AcornValue::Variable(i as AtomId, arg_type.clone()))
+                .collect();
+            let stack_size = args.len() as AtomId;
+            let app = AcornValue::new_apply(constant, args);
+            let claim =
+                AcornValue::new_forall(arg_types, predicate.beta_reduce(stack_size, &[app]));
+            obligation_index = Some(self.add_node(
+                project,
+                unbound_value.is_none(),
+                Proposition::anonymous(claim, self.module_id, range),
+                None,
+            ));
+        }
+        Ok(obligation_index)
     }
 
     // Adds a statement to the environment.
@@ -468,23 +1343,70 @@ impl Environment {
                 if ts.type_expr.is_axiom() {
                     self.bindings.add_data_type(&ts.name);
                 } else {
+                    let mut names = vec![];
+                    ts.type_expr.add_free_names(&mut vec![], &mut names);
+                    if let Some(token) = names.iter().find(|token| token.text() == ts.name) {
+                        return Err(token
+                            .error(&format!("type alias '{}' cannot refer to itself", ts.name)));
+                    }
                     let acorn_type = self.bindings.evaluate_type(project, &ts.type_expr)?;
                     self.bindings.add_type_alias(&ts.name, acorn_type);
                 };
+                self.bindings
+                    .set_documentation(&ts.name, &statement.doc_comments);
                 Ok(())
             }
 
             StatementInfo::Let(ls) => {
+                match self.add_let_statement(project, None, ls, statement.range())? {
+                    Some(index) => self.add_node_lines(index, &statement.range()),
+                    None => self.add_other_lines(statement),
+                }
+                self.bindings
+                    .set_documentation(&ls.name, &statement.doc_comments);
+                Ok(())
+            }
+
+            StatementInfo::DestructuringLet(dls) => {
                 self.add_other_lines(statement);
-                self.add_let_statement(project, None, ls, statement.range())
+                self.add_destructuring_let(project, dls, statement.range())
             }
 
             StatementInfo::Define(ds) => {
-                self.add_other_lines(statement);
-                self.add_define_statement(project, None, ds, statement.range())
+                let attributes = definition_attributes(&ds.attributes)?;
+                let num_nodes = self.nodes.len();
+                match self.add_define_statement(project, None, ds, statement.range())? {
+                    Some(index) => self.add_node_lines(index, &statement.range()),
+                    None => self.add_other_lines(statement),
+                }
+                self.mark_nodes_since(num_nodes, &attributes);
+                self.bindings
+                    .set_documentation(&ds.name, &statement.doc_comments);
+                Ok(())
+            }
+
+            StatementInfo::DefineGroup(dss) => {
+                let attributes = definition_attributes(&dss[0].attributes)?;
+                let num_nodes = self.nodes.len();
+                match self.add_define_group(project, None, dss, statement.range())? {
+                    Some(index) => self.add_node_lines(index, &statement.range()),
+                    None => self.add_other_lines(statement),
+                }
+                self.mark_nodes_since(num_nodes, &attributes);
+                for ds in dss {
+                    self.bindings
+                        .set_documentation(&ds.name, &statement.doc_comments);
+                }
+                Ok(())
             }
 
             StatementInfo::Theorem(ts) => {
+                if ts.lemma && self.top_level {
+                    return Err(statement
+                        .first_token
+                        .error("lemmas can only be used inside proof blocks"));
+                }
+
                 // Figure out the range for this theorem definition.
                 // It's smaller than the whole theorem statement because it doesn't
                 // include the proof block.
@@ -505,7 +1427,7 @@ impl Environment {
                         .insert(name.to_string(), range.clone());
                 }
 
-                let (type_params, arg_names, arg_types, value, _) =
+                let (type_params, arg_names, arg_types, value, _, refinements) =
                     self.bindings.evaluate_scoped_value(
                         project,
                         &ts.type_params,
@@ -513,12 +1435,27 @@ impl Environment {
                         None,
                         &ts.claim,
                         None,
-                        None,
                     )?;
+                if ts.implicit_type_params {
+                    check_implicit_params(&ts.type_params, &arg_types)?;
+                }
 
                 let unbound_claim =
                     value.ok_or_else(|| ts.claim.error("theorems must have values"))?;
 
+                let attributes = read_attributes(&ts.attributes)?;
+                if attributes.simp
+                    && !matches!(unbound_claim, AcornValue::Binary(BinaryOp::Equals, _, _))
+                {
+                    return Err(ts.claim.error("@simp can only be used on equalities"));
+                }
+                if attributes.simp && !refinements.is_empty() {
+                    return Err(statement.error("@simp theorems cannot have refinement types"));
+                }
+                if attributes.no_auto && ts.name.is_none() {
+                    return Err(statement.error("@no_auto theorems must have a name"));
+                }
+
                 let is_citation = self.bindings.is_citation(project, &unbound_claim);
                 if is_citation && ts.body.is_some() {
                     return Err(statement.error("citations do not need proof blocks"));
@@ -529,25 +1466,69 @@ impl Environment {
                     block_args.push((arg_name.clone(), arg_type.clone()));
                 }
 
+                let (mut premises, goal) = match &unbound_claim {
+                    AcornValue::Binary(BinaryOp::Implies, left, right) => {
+                        let premises = match (ts.claim.premise(), &ts.premise_label) {
+                            (p, Some(label)) => {
+                                // A labeled premise stays whole, so the label cites all of it.
+                                if self.bindings.name_in_use(label.text())
+                                    || arg_names.iter().any(|name| name == label.text())
+                                {
+                                    return Err(label
+                                        .error(&format!("label '{}' is already in use", label)));
+                                }
+                                let range = match p {
+                                    Some(p) => p.range(),
+                                    None => ts.claim.range(),
+                                };
+                                vec![(*left.clone(), range, Some(label.to_string()))]
+                            }
+                            (Some(p), None) => split_hypotheses(left, p)
+                                .into_iter()
+                                .map(|(value, range)| (value, range, None))
+                                .collect(),
+                            (None, None) => {
+                                // I don't think this should happen, but it's awkward for the
+                                // compiler to enforce, so pick a not-too-wrong default.
+                                vec![(*left.clone(), ts.claim.range(), None)]
+                            }
+                        };
+                        (premises, *right.clone())
+                    }
+                    c => {
+                        if let Some(label) = &ts.premise_label {
+                            return Err(label.error("only the premise of a claim can be labeled"));
+                        }
+                        (vec![], c.clone())
+                    }
+                };
+
+                // Arguments with refinement types give us hypotheses.
+                let stack_size = arg_types.len() as AtomId;
+                let mut hypotheses = vec![];
+                for (i, predicate) in refinements.args.into_iter().enumerate() {
+                    if let Some(predicate) = predicate {
+                        let arg = AcornValue::Variable(i as AtomId, arg_types[i].clone());
+                        let hypothesis = predicate.beta_reduce(stack_size, &[arg]);
+                        hypotheses.push((hypothesis, ts.args[i].token().range(), None));
+                    }
+                }
+                let unbound_claim = if hypotheses.is_empty() {
+                    unbound_claim
+                } else {
+                    let conjunction = AcornValue::reduce(
+                        BinaryOp::And,
+                        hypotheses.iter().map(|(h, _, _)| h.clone()).collect(),
+                    );
+                    hypotheses.extend(premises);
+                    premises = hypotheses;
+                    AcornValue::new_implies(conjunction, unbound_claim)
+                };
+
                 // Externally we use the theorem in unnamed, "forall" form
                 let external_claim =
                     AcornValue::new_forall(arg_types.clone(), unbound_claim.clone());
 
-                let (premise, goal) = match &unbound_claim {
-                    AcornValue::Binary(BinaryOp::Implies, left, right) => {
-                        let premise_range = match ts.claim.premise() {
-                            Some(p) => p.range(),
-                            None => {
-                                // I don't think this should happen, but it's awkward for the
-                                // compiler to enforce, so pick a not-too-wrong default.
-                                ts.claim.range()
-                            }
-                        };
-                        (Some((*left.clone(), premise_range)), *right.clone())
-                    }
-                    c => (None, c.clone()),
-                };
-
                 // We define the theorem using "lambda" form.
                 // The definition happens here, in the outside environment, because the
                 // theorem is usable by name in this environment.
@@ -573,7 +1554,7 @@ impl Environment {
                         &self,
                         type_params,
                         block_args,
-                        BlockParams::Theorem(ts.name.as_deref(), range, premise, goal),
+                        BlockParams::Theorem(ts.name.as_deref(), range, premises, goal),
                         statement.first_line(),
                         statement.last_line(),
                         ts.body.as_ref(),
@@ -589,31 +1570,44 @@ impl Environment {
                         self.module_id,
                         range,
                         ts.name.clone(),
-                    ),
+                    )
+                    .with_attributes(attributes),
                     block,
                 );
                 self.add_node_lines(index, &statement.range());
                 if let Some(name) = &ts.name {
                     self.bindings.mark_as_theorem(name);
+                    self.bindings
+                        .set_documentation(name, &statement.doc_comments);
                 }
 
                 Ok(())
             }
 
             StatementInfo::Prop(ps) => {
+                if let Some(label) = &ps.label {
+                    if self.bindings.name_in_use(label.text()) {
+                        return Err(label.error(&format!("label '{}' is already in use", label)));
+                    }
+                }
                 let claim =
                     self.bindings
                         .evaluate_value(project, &ps.claim, Some(&AcornType::Bool))?;
                 if claim == AcornValue::Bool(false) {
                     self.includes_explicit_false = true;
                 }
+                let label = ps.label.as_ref().map(|label| label.to_string());
+                if let Some(label) = &label {
+                    self.add_label(label, claim.clone(), statement.range());
+                }
 
                 if self.bindings.is_citation(project, &claim) {
                     // We already know this is true, so we don't need to prove it
                     self.add_node(
                         project,
                         true,
-                        Proposition::anonymous(claim, self.module_id, statement.range()),
+                        Proposition::anonymous(claim, self.module_id, statement.range())
+                            .with_label(label),
                         None,
                     );
                     self.add_other_lines(statement);
@@ -621,7 +1615,8 @@ impl Environment {
                     let index = self.add_node(
                         project,
                         false,
-                        Proposition::anonymous(claim, self.module_id, statement.range()),
+                        Proposition::anonymous(claim, self.module_id, statement.range())
+                            .with_label(label),
                         None,
                     );
                     self.add_node_lines(index, &statement.range());
@@ -634,19 +1629,56 @@ impl Environment {
                     // ForAll statements with an empty body can just be ignored
                     return Ok(());
                 }
-                let mut args = vec![];
+                let mut args: Vec<(String, AcornType)> = vec![];
                 for quantifier in &fas.quantifiers {
                     let (arg_name, arg_type) =
                         self.bindings.evaluate_declaration(project, quantifier)?;
+                    if args.iter().any(|(name, _)| *name == arg_name) {
+                        return Err(quantifier
+                            .token()
+                            .error("cannot declare a name twice in one argument list"));
+                    }
+
+                    // The arguments are local to the block, so they can shadow outer constants.
+                    if self.bindings.name_in_use(&arg_name) {
+                        if !self.bindings.can_shadow(&arg_name) {
+                            return Err(quantifier.token().error(&format!(
+                                "name '{}' already defined in this scope",
+                                arg_name
+                            )));
+                        }
+                        if !fas.shadow {
+                            self.implicit_shadows
+                                .push((arg_name.clone(), quantifier.token().range()));
+                        }
+                    }
                     args.push((arg_name, arg_type));
                 }
 
+                // The constraint is unbound, with the quantified variables as its variables.
+                let constraint = match &fas.constraint {
+                    Some(expr) => {
+                        let mut stack = Stack::new();
+                        for (name, arg_type) in &args {
+                            stack.insert(name.clone(), arg_type.clone());
+                        }
+                        let value = self.bindings.evaluate_value_with_stack(
+                            &mut stack,
+                            project,
+                            expr,
+                            Some(&AcornType::Bool),
+                        )?;
+                        Some((value, expr.range()))
+                    }
+                    None => None,
+                };
+
                 let block = Block::new(
                     project,
                     &self,
                     vec![],
                     args,
-                    BlockParams::ForAll,
+                    BlockParams::ForAll(constraint),
                     statement.first_line(),
                     statement.last_line(),
                     Some(&fas.body),
@@ -760,7 +1792,7 @@ impl Environment {
                 self.definition_ranges
                     .insert(fss.name.clone(), definition_range);
 
-                let (_, mut arg_names, mut arg_types, condition, _) =
+                let (_, mut arg_names, mut arg_types, condition, _, refinements) =
                     self.bindings.evaluate_scoped_value(
                         project,
                         &[],
@@ -768,8 +1800,12 @@ impl Environment {
                         None,
                         &fss.condition,
                         None,
-                        None,
                     )?;
+                check_unrefined_args(
+                    &fss.declarations,
+                    &refinements,
+                    "refinement types cannot be used in a satisfy statement",
+                )?;
 
                 let unbound_condition =
                     condition.ok_or_else(|| statement.error("missing condition"))?;
@@ -834,6 +1870,8 @@ impl Environment {
 
                 let index = self.add_node(project, false, prop, Some(block));
                 self.add_node_lines(index, &statement.range());
+                self.bindings
+                    .set_documentation(&fss.name, &statement.doc_comments);
                 Ok(())
             }
 
@@ -905,6 +1943,15 @@ impl Environment {
 
                 // The member functions take the type itself to a particular member.
                 let struct_type = self.bindings.add_data_type(&ss.name);
+                self.bindings.set_structure_fields(
+                    &ss.name,
+                    ss.fields
+                        .iter()
+                        .map(|(t, _)| t.text().to_string())
+                        .collect(),
+                );
+                self.bindings
+                    .set_documentation(&ss.name, &statement.doc_comments);
                 let mut member_fns = vec![];
                 for (member_fn_name, field_type) in member_fn_names.iter().zip(&field_types) {
                     let member_fn_type =
@@ -1054,6 +2101,8 @@ impl Environment {
 
                 // Add the new type first, because we can have self-reference in the inductive type.
                 let inductive_type = self.bindings.add_data_type(&is.name);
+                self.bindings
+                    .set_documentation(&is.name, &statement.doc_comments);
 
                 // Parse (member name, list of arg types) for each constructor.
                 let mut constructors = vec![];
@@ -1328,29 +2377,89 @@ impl Environment {
                     ));
                 }
                 self.bindings.import_module(local_name, module_id);
+                self.bindings
+                    .import_class_extensions(project.get_bindings(module_id).unwrap());
+                self.bindings
+                    .import_precedences(project.get_bindings(module_id).unwrap())
+                    .map_err(|e| statement.error(&e))?;
 
                 // Bring the imported names into this environment
+                if is.wildcard {
+                    self.bindings.import_all(project, module_id, statement)?;
+                }
                 for name in &is.names {
                     self.bindings.import_name(project, module_id, name)?;
+                    self.import_ranges
+                        .insert(name.text().to_string(), name.range());
+                }
+
+                Ok(())
+            }
+
+            StatementInfo::Module(ms) => {
+                self.add_other_lines(statement);
+                if !self.top_level {
+                    return Err(statement.error("module statements must be at the top level"));
                 }
+                if self.bindings.name_in_use(&ms.name) {
+                    return Err(ms.name_token.error(&format!(
+                        "module name '{}' already defined in this scope",
+                        ms.name
+                    )));
+                }
+                let module_id = project.add_nested_module(
+                    self.module_id,
+                    &ms.name_token,
+                    &ms.body.statements,
+                )?;
+                self.bindings.import_module(&ms.name, module_id);
+                if let Some(bindings) = project.get_bindings(module_id) {
+                    self.bindings.import_class_extensions(bindings);
+                }
+                Ok(())
+            }
 
+            StatementInfo::Export(es) => {
+                self.add_other_lines(statement);
+                if !self.top_level {
+                    return Err(statement.error("export statements must be at the top level"));
+                }
+                if let Some(module_token) = &es.module {
+                    let module_id = match self.bindings.get_module_for_name(module_token.text()) {
+                        Some(module_id) => module_id,
+                        None => {
+                            return Err(module_token
+                                .error(&format!("unknown module '{}'", module_token.text())));
+                        }
+                    };
+                    self.bindings.import_name(project, module_id, &es.name)?;
+                }
+                let name = es.name.text();
+                if !self.bindings.is_imported(name) {
+                    let message = if self.bindings.name_in_use(name) {
+                        format!(
+                            "'{}' is defined in this module, so it is already exported",
+                            name
+                        )
+                    } else {
+                        format!("'{}' must be imported before it can be exported", name)
+                    };
+                    return Err(es.name.error(&message));
+                }
+                self.bindings.mark_as_exported(name);
                 Ok(())
             }
 
             StatementInfo::Class(cs) => {
                 self.add_other_lines(statement);
-                match self.bindings.get_type_for_name(&cs.name) {
+                let class_module = match self.bindings.get_type_for_name(&cs.name) {
                     Some(AcornType::Data(module, name)) => {
-                        if module != &self.module_id {
-                            return Err(cs
-                                .name_token
-                                .error("we can only bind members to types in the current module"));
-                        }
                         if name != &cs.name {
                             return Err(cs
                                 .name_token
                                 .error("we cannot bind members to type aliases"));
                         }
+                        *module
                     }
                     Some(_) => {
                         return Err(cs
@@ -1363,6 +2472,9 @@ impl Environment {
                             .error(&format!("undefined type name '{}'", cs.name)));
                     }
                 };
+                if class_module != self.module_id {
+                    self.add_class_extensions(project, class_module, cs)?;
+                }
                 for substatement in &cs.body.statements {
                     match &substatement.statement {
                         StatementInfo::Let(ls) => {
@@ -1372,14 +2484,55 @@ impl Environment {
                                 ls,
                                 substatement.range(),
                             )?;
+                            self.bindings.set_documentation(
+                                &format!("{}.{}", cs.name, ls.name),
+                                &substatement.doc_comments,
+                            );
                         }
                         StatementInfo::Define(ds) => {
+                            // A declared precedence is the only attribute that isn't for nodes.
+                            let precedence = ds.declared_precedence()?;
+                            let others: Vec<_> = ds
+                                .attributes
+                                .iter()
+                                .filter(|a| a.name.text() != "precedence")
+                                .cloned()
+                                .collect();
+                            let attributes = definition_attributes(&others)?;
+                            let num_nodes = self.nodes.len();
                             self.add_define_statement(
                                 project,
                                 Some(&cs.name),
                                 ds,
                                 substatement.range(),
                             )?;
+                            if let Some((token_type, precedence)) = precedence {
+                                self.bindings
+                                    .declare_precedence(token_type, precedence)
+                                    .map_err(|e| ds.name_token.error(&e))?;
+                            }
+                            self.mark_nodes_since(num_nodes, &attributes);
+                            self.bindings.set_documentation(
+                                &format!("{}.{}", cs.name, ds.name),
+                                &substatement.doc_comments,
+                            );
+                        }
+                        StatementInfo::DefineGroup(dss) => {
+                            let attributes = definition_attributes(&dss[0].attributes)?;
+                            let num_nodes = self.nodes.len();
+                            self.add_define_group(
+                                project,
+                                Some(&cs.name),
+                                dss,
+                                substatement.range(),
+                            )?;
+                            self.mark_nodes_since(num_nodes, &attributes);
+                            for ds in dss {
+                                self.bindings.set_documentation(
+                                    &format!("{}.{}", cs.name, ds.name),
+                                    &substatement.doc_comments,
+                                );
+                            }
                         }
                         _ => {
                             return Err(substatement.error(
@@ -1402,6 +2555,185 @@ impl Environment {
                 }
             }
 
+            StatementInfo::Strings(ss) => {
+                self.add_other_lines(statement);
+                let acorn_type = self.bindings.evaluate_type(project, &ss.type_expr)?;
+                if let AcornType::Data(module, typename) = acorn_type {
+                    self.bindings.set_string_type(module, typename);
+                    Ok(())
+                } else {
+                    Err(ss.type_expr.error("strings type must be a data type"))
+                }
+            }
+
+            StatementInfo::Lists(ls) => {
+                self.add_other_lines(statement);
+                let acorn_type = self.bindings.evaluate_type(project, &ls.type_expr)?;
+                if let AcornType::Data(module, typename) = acorn_type {
+                    self.bindings.set_list_type(module, typename);
+                    Ok(())
+                } else {
+                    Err(ls.type_expr.error("lists type must be a data type"))
+                }
+            }
+
+            StatementInfo::Rewrite(rs) => self.add_rewrite_statement(project, statement, rs),
+
+            StatementInfo::Have(hs) => {
+                let label = hs.label.text();
+                if self.bindings.name_in_use(label) {
+                    return Err(hs
+                        .label
+                        .error(&format!("label '{}' is already in use", label)));
+                }
+                let claim =
+                    self.bindings
+                        .evaluate_value(project, &hs.claim, Some(&AcornType::Bool))?;
+                let range = statement.range();
+                let block = match &hs.body {
+                    Some(body) => Some(Block::new(
+                        project,
+                        self,
+                        vec![],
+                        vec![],
+                        BlockParams::Have(label, claim.clone(), range),
+                        statement.first_line(),
+                        statement.last_line(),
+                        Some(body),
+                    )?),
+                    None => None,
+                };
+                let prop = Proposition::anonymous(claim.clone(), self.module_id, range)
+                    .with_label(Some(label.to_string()));
+                let index = self.add_node(project, false, prop, block);
+                self.add_node_lines(index, &range);
+
+                // The label only becomes usable once the claim has been proved.
+                self.add_label(label, claim, range);
+                Ok(())
+            }
+
+            StatementInfo::Assume(a) => {
+                let goal = match &self.goal {
+                    Some(Goal::Prove(goal)) => goal.clone(),
+                    _ => {
+                        return Err(statement.error("assume can only be used to prove a goal"));
+                    }
+                };
+                let hypothesis =
+                    self.bindings
+                        .evaluate_value(project, &a.claim, Some(&AcornType::Bool))?;
+                let consequence = match &goal.value {
+                    AcornValue::Binary(BinaryOp::Implies, premise, consequence)
+                        if **premise == hypothesis =>
+                    {
+                        consequence.as_ref().clone()
+                    }
+                    _ => {
+                        return Err(a.claim.error(&format!(
+                            "the goal is not of the form '{} implies ...'",
+                            hypothesis
+                        )));
+                    }
+                };
+                self.add_node(
+                    project,
+                    true,
+                    Proposition::premise(hypothesis, self.module_id, statement.range()),
+                    None,
+                );
+                self.add_other_lines(statement);
+                self.goal = Some(Goal::Prove(goal.with_value(consequence)));
+                Ok(())
+            }
+
+            StatementInfo::Suffices(ss) => {
+                let goal = match &self.goal {
+                    Some(Goal::Prove(goal)) => goal.clone(),
+                    _ => {
+                        return Err(statement.error("suffices can only be used to prove a goal"));
+                    }
+                };
+                let claim =
+                    self.bindings
+                        .evaluate_value(project, &ss.claim, Some(&AcornType::Bool))?;
+
+                // First we prove that the new goal implies the old one.
+                let range = statement.range();
+                let block = match &ss.body {
+                    Some(body) => Some(Block::new(
+                        project,
+                        self,
+                        vec![],
+                        vec![],
+                        BlockParams::Theorem(
+                            None,
+                            range,
+                            vec![(claim.clone(), ss.claim.range(), None)],
+                            goal.value.clone(),
+                        ),
+                        statement.first_line(),
+                        statement.last_line(),
+                        Some(body),
+                    )?),
+                    None => None,
+                };
+                let implication = AcornValue::new_implies(claim.clone(), goal.value.clone());
+                let index = self.add_node(
+                    project,
+                    false,
+                    Proposition::anonymous(implication, self.module_id, range),
+                    block,
+                );
+                self.add_node_lines(index, &range);
+                self.goal = Some(Goal::Prove(goal.with_value(claim)));
+                Ok(())
+            }
+
+            StatementInfo::Unfold(us) => {
+                self.add_other_lines(statement);
+                self.add_unfold_statement(project, us, statement.range())
+            }
+
+            StatementInfo::Decimals(ds) => {
+                self.add_other_lines(statement);
+                let acorn_type = self.bindings.evaluate_type(project, &ds.type_expr)?;
+                if let AcornType::Data(module, typename) = acorn_type {
+                    self.bindings.set_decimal_type(module, typename);
+                    Ok(())
+                } else {
+                    Err(ds.type_expr.error("decimals type must be a data type"))
+                }
+            }
+
+            StatementInfo::Coercion(cs) => {
+                self.add_other_lines(statement);
+                let function = self.bindings.evaluate_value(project, &cs.function, None)?;
+                let (from_type, to_type) = match function.get_type() {
+                    AcornType::Function(ftype) if ftype.arg_types.len() == 1 => {
+                        (ftype.arg_types[0].clone(), *ftype.return_type)
+                    }
+                    _ => {
+                        return Err(cs
+                            .function
+                            .error("a coercion must be a function of one argument"));
+                    }
+                };
+                if from_type == to_type {
+                    return Err(cs
+                        .function
+                        .error("a coercion must convert between two different types"));
+                }
+                if self.bindings.get_coercion(&from_type, &to_type).is_some() {
+                    return Err(cs.function.error(&format!(
+                        "there is already a coercion from {} to {}",
+                        from_type, to_type
+                    )));
+                }
+                self.bindings.add_coercion(from_type, to_type, function);
+                Ok(())
+            }
+
             StatementInfo::Solve(ss) => {
                 let target = self.bindings.evaluate_value(project, &ss.target, None)?;
                 let solve_range = Range {
@@ -1467,24 +2799,88 @@ impl Environment {
                 Ok(())
             }
 
+            StatementInfo::ByContradiction(body) => {
+                let goal = match &self.goal {
+                    Some(Goal::Prove(goal)) => goal.clone(),
+                    _ => {
+                        return Err(
+                            statement.error("by_contradiction can only be used to prove a goal")
+                        );
+                    }
+                };
+                let block = Block::new(
+                    project,
+                    self,
+                    vec![],
+                    vec![],
+                    BlockParams::Contradiction(&goal.value, statement.range()),
+                    statement.first_line(),
+                    statement.last_line(),
+                    Some(body),
+                )?;
+
+                // The block establishes the goal itself.
+                let prop = Proposition::anonymous(goal.value, self.module_id, statement.range());
+                let index = self.add_node(project, false, prop, Some(block));
+                self.add_node_lines(index, &statement.range());
+                Ok(())
+            }
+
             StatementInfo::Match(ms) => {
                 let scrutinee = self.bindings.evaluate_value(project, &ms.scrutinee, None)?;
                 let scrutinee_type = scrutinee.get_type();
-                let mut indices = vec![];
+                let mut coverage = MatchCoverage::new();
+                let mut earlier_guards: Vec<Vec<AcornValue>> = vec![];
+                let mut blocks = vec![];
                 let mut disjuncts = vec![];
-                for (pattern, body) in &ms.cases {
-                    let (constructor, args, i, total) =
+                for (pattern, guard, body) in &ms.cases {
+                    let (args, shape) =
                         self.bindings
                             .evaluate_pattern(project, &scrutinee_type, pattern)?;
-                    if indices.contains(&i) {
-                        return Err(pattern.error("duplicate pattern in match statement"));
+                    let group = match coverage.add(shape, guard.is_some()) {
+                        Some(group) => group,
+                        None => {
+                            return Err(pattern.error("this pattern overlaps with an earlier case"))
+                        }
+                    };
+                    if group == earlier_guards.len() {
+                        earlier_guards.push(vec![]);
+                    }
+
+                    // The pattern and the guards are unbound, with the pattern arguments as
+                    // their variables.
+                    let mut stack = Stack::new();
+                    for (name, arg_type) in &args {
+                        stack.insert(name.clone(), arg_type.clone());
+                    }
+                    let pattern_value = self.bindings.evaluate_value_with_stack(
+                        &mut stack,
+                        project,
+                        pattern,
+                        Some(&scrutinee_type),
+                    )?;
+
+                    // A case only applies when its own guard holds, and none of the earlier
+                    // guards for the same pattern do.
+                    let earlier = &mut earlier_guards[group];
+                    let mut conditions: Vec<AcornValue> =
+                        earlier.iter().map(|g| g.clone().negate()).collect();
+                    if let Some(guard) = guard {
+                        let guard = self.bindings.evaluate_value_with_stack(
+                            &mut stack,
+                            project,
+                            guard,
+                            Some(&AcornType::Bool),
+                        )?;
+                        earlier.push(guard.clone());
+                        conditions.push(guard);
                     }
-                    indices.push(i);
 
                     let params = BlockParams::MatchCase(
                         scrutinee.clone(),
-                        constructor,
+                        pattern_value,
                         args,
+                        conditions,
                         pattern.range(),
                     );
 
@@ -1501,34 +2897,166 @@ impl Environment {
 
                     let (disjunct, _) = block.export_last_claim(self, &body.right_brace)?;
                     disjuncts.push(disjunct);
+                    blocks.push((block, body));
+                }
+                if !coverage.all_guards_have_fallbacks() {
+                    return Err(ms
+                        .scrutinee
+                        .error("each guarded case needs an unguarded case to fall back on"));
+                }
+                if !coverage.is_exhaustive() {
+                    return Err(ms
+                        .scrutinee
+                        .error("not all cases are covered in match statement"));
+                }
 
-                    if total == indices.len() {
-                        if ms.cases.len() > total {
-                            // The next iteration will report an error
-                            continue;
+                // Only the last block gets the exported proposition.
+                // The others get vacuous propositions.
+                let last_index = blocks.len() - 1;
+                let mut disjunction = Some(AcornValue::reduce(BinaryOp::Or, disjuncts));
+                for (i, (block, body)) in blocks.into_iter().enumerate() {
+                    let claim = if i == last_index {
+                        disjunction.take().unwrap()
+                    } else {
+                        AcornValue::Bool(true)
+                    };
+                    let prop = Proposition::anonymous(claim, self.module_id, statement.range());
+                    let index = self.add_node(project, false, prop, Some(block));
+                    self.add_node_lines(index, &body.range());
+                }
+                Ok(())
+            }
+
+            StatementInfo::Induction(is) => {
+                let goal = match &self.goal {
+                    Some(Goal::Prove(goal)) => goal.clone(),
+                    _ => {
+                        return Err(statement.error("induction can only be used to prove a goal"));
+                    }
+                };
+                let variable = self.bindings.evaluate_value(project, &is.variable, None)?;
+                let constant = match &variable {
+                    AcornValue::Constant(c) if !c.is_generic() => c.clone(),
+                    _ => return Err(is.variable.error("induction must be on a variable")),
+                };
+                let var_type = variable.get_type();
+
+                // The goal, with the variable replaced by a value that uses the first
+                // stack_size variables.
+                let instantiate = |value: &AcornValue, stack_size: AtomId| {
+                    goal.value
+                        .clone()
+                        .insert_stack(0, stack_size)
+                        .replace_constants(stack_size, &|c| {
+                            if *c == constant {
+                                Some(value.clone())
+                            } else {
+                                None
+                            }
+                        })
+                };
+
+                let mut coverage = MatchCoverage::new();
+                let mut conjuncts = vec![];
+                for (pattern, body) in &is.cases {
+                    let (args, shape) = self
+                        .bindings
+                        .evaluate_pattern(project, &var_type, pattern)?;
+                    let simple = match &shape {
+                        PatternShape::Constructor(_, _, subpatterns) => {
+                            subpatterns.iter().all(|s| *s == PatternShape::Variable)
                         }
+                        PatternShape::Variable => false,
+                    };
+                    if !simple {
+                        return Err(pattern
+                            .error("induction cases must be a constructor applied to variables"));
+                    }
+                    if coverage.add(shape, false).is_none() {
+                        return Err(pattern.error("this constructor already has a case"));
+                    }
 
-                        let disjunction = AcornValue::reduce(BinaryOp::Or, disjuncts);
-                        let prop =
-                            Proposition::anonymous(disjunction, self.module_id, statement.range());
-                        let index = self.add_node(project, false, prop, Some(block));
-                        self.add_node_lines(index, &body.range());
-                        return Ok(());
+                    // The pattern, the hypotheses, and the goal are unbound, with the
+                    // constructor arguments as their variables.
+                    let mut stack = Stack::new();
+                    for (name, arg_type) in &args {
+                        stack.insert(name.clone(), arg_type.clone());
                     }
+                    let pattern_value = self.bindings.evaluate_value_with_stack(
+                        &mut stack,
+                        project,
+                        pattern,
+                        Some(&var_type),
+                    )?;
+                    let num_args = args.len() as AtomId;
+                    let hypotheses: Vec<_> = args
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, arg_type))| *arg_type == var_type)
+                        .map(|(i, (_, arg_type))| {
+                            let arg = AcornValue::Variable(i as AtomId, arg_type.clone());
+                            instantiate(&arg, num_args)
+                        })
+                        .collect();
+                    let case_goal = instantiate(&pattern_value, num_args);
 
-                    // This is a vacuous proposition because we only put an exported
-                    // proposition on the last block.
-                    let vacuous_prop = Proposition::anonymous(
-                        AcornValue::Bool(true),
-                        self.module_id,
-                        statement.range(),
-                    );
-                    let index = self.add_node(project, false, vacuous_prop, Some(block));
+                    let arg_types: Vec<_> = args.iter().map(|(_, t)| t.clone()).collect();
+                    let unbound_claim = if hypotheses.is_empty() {
+                        case_goal.clone()
+                    } else {
+                        AcornValue::new_implies(
+                            AcornValue::reduce(BinaryOp::And, hypotheses.clone()),
+                            case_goal.clone(),
+                        )
+                    };
+                    let claim = AcornValue::new_forall(arg_types, unbound_claim);
+                    conjuncts.push(claim.clone());
+
+                    let premises = hypotheses
+                        .into_iter()
+                        .map(|h| (h, pattern.range(), None))
+                        .collect();
+                    let block = Block::new(
+                        project,
+                        self,
+                        vec![],
+                        args,
+                        BlockParams::Theorem(None, pattern.range(), premises, case_goal),
+                        body.left_brace.line_number,
+                        body.right_brace.line_number,
+                        Some(body),
+                    )?;
+                    let prop = Proposition::anonymous(claim, self.module_id, pattern.range());
+                    let index = self.add_node(project, false, prop, Some(block));
                     self.add_node_lines(index, &body.range());
                 }
-                Err(ms
-                    .scrutinee
-                    .error("not all cases are covered in match statement"))
+                if !coverage.is_exhaustive() {
+                    return Err(is.variable.error("not all constructors have a case"));
+                }
+
+                // The induction principle for this goal is an instance of the induction
+                // principle for the type, so we don't need to prove it again.
+                let conclusion = AcornValue::new_forall(
+                    vec![var_type.clone()],
+                    instantiate(&AcornValue::Variable(0, var_type.clone()), 1),
+                );
+                let principle = AcornValue::new_implies(
+                    AcornValue::reduce(BinaryOp::And, conjuncts),
+                    conclusion,
+                );
+                self.add_node(
+                    project,
+                    true,
+                    Proposition::anonymous(principle, self.module_id, statement.range()),
+                    None,
+                );
+                self.add_node(
+                    project,
+                    false,
+                    Proposition::anonymous(goal.value, self.module_id, statement.range()),
+                    None,
+                );
+                Ok(())
             }
 
             StatementInfo::Typeclass(_ts) => {
@@ -1546,6 +3074,10 @@ impl Environment {
     ) -> compilation::Result<()> {
         let mut tokens = TokenIter::new(tokens);
         loop {
+            // Imported modules can declare precedences too.
+            for (token_type, precedence) in self.bindings.precedences() {
+                tokens.declare_precedence(*token_type, *precedence);
+            }
             match Statement::parse(&mut tokens, false) {
                 Ok((Some(statement), _)) => {
                     if let Err(e) = self.add_statement(project, &statement) {
@@ -1558,12 +3090,14 @@ impl Environment {
         }
     }
 
-    // Get all facts that this environment exports.
-    pub fn exported_facts(&self) -> Vec<Fact> {
+    // Get all facts that this environment exports, leaving out the ones the filter excludes.
+    pub fn exported_facts(&self, filter: &TagFilter) -> Vec<Fact> {
         assert!(self.top_level);
         let mut facts = vec![];
         for node in &self.nodes {
-            facts.push(Fact::new(node.claim.clone(), Truthiness::Factual));
+            if filter.allows(&node.claim.attributes) {
+                facts.push(Fact::new(node.claim.clone(), Truthiness::Factual));
+            }
         }
         facts
     }
@@ -1744,7 +3278,7 @@ impl Environment {
             if let LineType::Node(prop_index) = line_type {
                 let prop = &self.nodes[*prop_index];
                 if let Some(block) = &prop.block {
-                    assert!(block.env.covers_line(line as u32));
+                    assert!(block.env.covers_line(self.first_line + line as u32));
                 }
             }
         }
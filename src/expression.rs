@@ -66,7 +66,27 @@ pub enum Expression {
     // must have an inductive type.
     // For the pair (exp1, exp2) where exp1 matches the scrutinee, the value of our expression is exp2.
     // The last token is the closing brace.
-    Match(Token, Box<Expression>, Vec<(Expression, Expression)>, Token),
+    Match(
+        Token,
+        Box<Expression>,
+        Vec<(Expression, Option<Expression>, Expression)>,
+        Token,
+    ),
+
+    // A set-builder expression like { x: Nat | p(x) }.
+    // The first token is the left brace, and the last token is the right brace.
+    // The declaration is the variable being bound, and the expression is the condition.
+    SetBuilder(Token, Box<Declaration>, Box<Expression>, Token),
+
+    // A choice expression like "choose x: Nat where p(x)".
+    // The token is the "choose" keyword.
+    // The declaration is the variable being chosen, and the expression is the condition.
+    // The condition extends as far to the right as it can, so there's no closing token.
+    Choose(Token, Box<Declaration>, Box<Expression>),
+
+    // A list literal like [a, b, c].
+    // The tokens are the brackets, and the expressions are the elements, which may be empty.
+    List(Token, Vec<Expression>, Token),
 }
 
 impl fmt::Display for Expression {
@@ -117,11 +137,31 @@ impl fmt::Display for Expression {
             }
             Expression::Match(_, scrutinee, cases, _) => {
                 write!(f, "match {} {{", scrutinee)?;
-                for (pat, exp) in cases {
-                    write!(f, " {} {{ {} }}", pat, exp)?;
+                for (pat, guard, exp) in cases {
+                    write!(f, " {}", pat)?;
+                    if let Some(guard) = guard {
+                        write!(f, " if {}", guard)?;
+                    }
+                    write!(f, " {{ {} }}", exp)?;
                 }
                 write!(f, " }}")
             }
+            Expression::SetBuilder(_, decl, condition, _) => {
+                write!(f, "{{ {} | {} }}", decl, condition)
+            }
+            Expression::Choose(token, decl, condition) => {
+                write!(f, "{} {} where {}", token, decl, condition)
+            }
+            Expression::List(_, elements, _) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
         }
     }
 }
@@ -161,6 +201,18 @@ impl Declaration {
         }
     }
 
+    // Adds the free names in the declared type, and then binds the declared name.
+    // The caller is responsible for unbinding it.
+    pub fn bind(&self, bound: &mut Vec<String>, answer: &mut Vec<Token>) {
+        match self {
+            Declaration::Typed(name_token, type_expr) => {
+                type_expr.add_free_names(bound, answer);
+                bound.push(name_token.text().to_string());
+            }
+            Declaration::SelfToken(token) => bound.push(token.text().to_string()),
+        }
+    }
+
     // Parses an expression that should contain a single declaration.
     // This rejects numerals.
     pub fn parse(tokens: &mut TokenIter, terminator: Terminator) -> Result<(Declaration, Token)> {
@@ -251,6 +303,9 @@ impl Expression {
             Expression::Binder(token, _, _, _) => token,
             Expression::IfThenElse(token, _, _, _, _) => token,
             Expression::Match(token, _, _, _) => token,
+            Expression::SetBuilder(left_brace, _, _, _) => left_brace,
+            Expression::Choose(token, _, _) => token,
+            Expression::List(left_bracket, _, _) => left_bracket,
         }
     }
 
@@ -264,6 +319,9 @@ impl Expression {
             Expression::Binder(_, _, _, right_brace) => right_brace,
             Expression::IfThenElse(_, _, _, _, right_brace) => right_brace,
             Expression::Match(_, _, _, right_brace) => right_brace,
+            Expression::SetBuilder(_, _, _, right_brace) => right_brace,
+            Expression::Choose(_, _, condition) => condition.last_token(),
+            Expression::List(_, _, right_bracket) => right_bracket,
         }
     }
 
@@ -317,8 +375,27 @@ impl Expression {
                 println!("Match:");
                 println!("  token: {}", token);
                 println!("  scrutinee: {}", scrutinee);
-                for (pat, exp) in cases {
-                    println!("  case: {} => {}", pat, exp);
+                for (pat, guard, exp) in cases {
+                    match guard {
+                        Some(guard) => println!("  case: {} if {} => {}", pat, guard, exp),
+                        None => println!("  case: {} => {}", pat, exp),
+                    }
+                }
+            }
+            Expression::SetBuilder(_, decl, condition, _) => {
+                println!("SetBuilder:");
+                println!("  declaration: {}", decl);
+                println!("  condition: {}", condition);
+            }
+            Expression::Choose(_, decl, condition) => {
+                println!("Choose:");
+                println!("  declaration: {}", decl);
+                println!("  condition: {}", condition);
+            }
+            Expression::List(_, elements, _) => {
+                println!("List:");
+                for element in elements {
+                    println!("  element: {}", element);
                 }
             }
         }
@@ -355,9 +432,35 @@ impl Expression {
         )
     }
 
+    // Generates a list of type parameters, like <T, U>.
+    pub fn generate_type_param_list(mut exprs: Vec<Expression>) -> Expression {
+        assert_ne!(exprs.len(), 0);
+        let mut answer = exprs.remove(0);
+        for e in exprs {
+            answer = Expression::Binary(Box::new(answer), TokenType::Comma.generate(), Box::new(e));
+        }
+        Expression::Grouping(
+            TokenType::LessThan.generate(),
+            Box::new(answer),
+            TokenType::GreaterThan.generate(),
+        )
+    }
+
+    // Generates a list literal, like [a, b, c].
+    pub fn generate_list(elements: Vec<Expression>) -> Expression {
+        Expression::List(
+            TokenType::LeftBracket.generate(),
+            elements,
+            TokenType::RightBracket.generate(),
+        )
+    }
+
     // Generates a unary expression, parenthesizing if necessary according to precedence.
     pub fn generate_unary(op: TokenType, mut expr: Expression) -> Expression {
-        if expr.top_level_precedence(true) < op.unary_precedence() {
+        // Repeated symbolic operators are parenthesized, so that we write -(-a) rather than --a.
+        let repeated = op == TokenType::Minus
+            && matches!(&expr, Expression::Unary(t, _) if t.token_type == TokenType::Minus);
+        if repeated || expr.top_level_precedence(true) < op.unary_precedence() {
             expr = Expression::Grouping(
                 TokenType::LeftParen.generate(),
                 Box::new(expr),
@@ -368,11 +471,8 @@ impl Expression {
     }
 
     // Generates a binary expression, parenthesizing if necessary according to precedence.
-    pub fn generate_binary(
-        mut left: Expression,
-        op: TokenType,
-        mut right: Expression,
-    ) -> Expression {
+    // The operator token carries the precedence, which may have been declared.
+    pub fn generate_binary(mut left: Expression, op: Token, mut right: Expression) -> Expression {
         if left.top_level_precedence(false) < op.binary_precedence() {
             left = Expression::Grouping(
                 TokenType::LeftParen.generate(),
@@ -387,7 +487,217 @@ impl Expression {
                 TokenType::RightParen.generate(),
             );
         }
-        Expression::Binary(Box::new(left), op.generate(), Box::new(right))
+        Expression::Binary(Box::new(left), op, Box::new(right))
+    }
+
+    // Parenthesizes every operator expression that is an operand of another operator,
+    // so that reading it doesn't depend on knowing the precedence rules.
+    pub fn parenthesize_operators(self) -> Expression {
+        match self {
+            Expression::Unary(token, sub) => {
+                let sub = sub.parenthesize_operators().parenthesize_operand();
+                Expression::Unary(token, Box::new(sub))
+            }
+            Expression::Binary(left, token, right) => {
+                let mut left = left.parenthesize_operators();
+                let mut right = right.parenthesize_operators();
+                if token.token_type != TokenType::Dot && token.token_type != TokenType::Comma {
+                    left = left.parenthesize_operand();
+                    right = right.parenthesize_operand();
+                }
+                Expression::Binary(Box::new(left), token, Box::new(right))
+            }
+            Expression::Apply(left, right) => Expression::Apply(
+                Box::new(left.parenthesize_operators()),
+                Box::new(right.parenthesize_operators()),
+            ),
+            Expression::Grouping(left, e, right) => {
+                Expression::Grouping(left, Box::new(e.parenthesize_operators()), right)
+            }
+            Expression::Binder(token, args, body, right) => {
+                Expression::Binder(token, args, Box::new(body.parenthesize_operators()), right)
+            }
+            Expression::IfThenElse(token, condition, if_value, else_value, right) => {
+                Expression::IfThenElse(
+                    token,
+                    Box::new(condition.parenthesize_operators()),
+                    Box::new(if_value.parenthesize_operators()),
+                    Box::new(else_value.parenthesize_operators()),
+                    right,
+                )
+            }
+            Expression::Match(token, scrutinee, cases, right) => Expression::Match(
+                token,
+                Box::new(scrutinee.parenthesize_operators()),
+                cases
+                    .into_iter()
+                    .map(|(pattern, guard, result)| {
+                        (pattern, guard, result.parenthesize_operators())
+                    })
+                    .collect(),
+                right,
+            ),
+            Expression::SetBuilder(left, declaration, condition, right) => Expression::SetBuilder(
+                left,
+                declaration,
+                Box::new(condition.parenthesize_operators()),
+                right,
+            ),
+            Expression::Choose(token, declaration, condition) => Expression::Choose(
+                token,
+                declaration,
+                Box::new(condition.parenthesize_operators()),
+            ),
+            Expression::List(left, elements, right) => Expression::List(
+                left,
+                elements
+                    .into_iter()
+                    .map(|e| e.parenthesize_operators())
+                    .collect(),
+                right,
+            ),
+            Expression::Singleton(_) => self,
+        }
+    }
+
+    // Wraps this expression in parentheses if it's an operator expression.
+    fn parenthesize_operand(self) -> Expression {
+        let is_operator = match &self {
+            Expression::Unary(..) => true,
+            Expression::Binary(_, token, _) => {
+                token.token_type != TokenType::Dot && token.token_type != TokenType::Comma
+            }
+            _ => false,
+        };
+        if !is_operator {
+            return self;
+        }
+        Expression::Grouping(
+            TokenType::LeftParen.generate(),
+            Box::new(self),
+            TokenType::RightParen.generate(),
+        )
+    }
+
+    // Writes this expression as code, breaking it onto multiple lines when a line would be
+    // longer than max_width.
+    // A newline ends an expression unless it's inside some sort of brackets, so lines only
+    // get broken inside brackets. An expression with no brackets stays on one line.
+    pub fn to_wrapped_string(&self, max_width: usize) -> String {
+        self.wrap(0, max_width, false)
+    }
+
+    // The first line of the output starts at column "indent", and later lines include
+    // their own indentation.
+    // "breakable" is whether a newline can go between the operands of a binary operator here.
+    fn wrap(&self, indent: usize, max_width: usize, breakable: bool) -> String {
+        let flat = self.to_string();
+        if indent + flat.len() <= max_width {
+            return flat;
+        }
+        let pad = " ".repeat(indent);
+        let inner = indent + 4;
+        let inner_pad = " ".repeat(inner);
+        match self {
+            Expression::Unary(token, sub) => {
+                let prefix = if token.token_type == TokenType::Minus {
+                    token.to_string()
+                } else {
+                    format!("{} ", token)
+                };
+                let sub = sub.wrap(indent + prefix.len(), max_width, breakable);
+                format!("{}{}", prefix, sub)
+            }
+            Expression::Binary(left, token, right) if token.token_type == TokenType::Dot => {
+                format!("{}.{}", left.wrap(indent, max_width, breakable), right)
+            }
+            Expression::Binary(left, token, right) if breakable => {
+                let separator = if token.token_type == TokenType::Comma {
+                    ",".to_string()
+                } else {
+                    format!(" {}", token)
+                };
+                format!(
+                    "{}{}\n{}{}",
+                    left.wrap(indent, max_width, true),
+                    separator,
+                    pad,
+                    right.wrap(indent, max_width, true)
+                )
+            }
+            Expression::Apply(function, args) => match args.as_ref() {
+                Expression::Grouping(left, e, _) if left.token_type == TokenType::LeftParen => {
+                    let parts: Vec<_> = e
+                        .flatten_comma_separated_list()
+                        .into_iter()
+                        .map(|part| part.wrap(inner, max_width, true))
+                        .collect();
+                    format!(
+                        "{}(\n{}{}\n{})",
+                        function.wrap(indent, max_width, breakable),
+                        inner_pad,
+                        parts.join(&format!(",\n{}", inner_pad)),
+                        pad
+                    )
+                }
+                _ => flat,
+            },
+            Expression::Grouping(left, e, right) if left.token_type == TokenType::LeftParen => {
+                format!(
+                    "{}\n{}{}\n{}{}",
+                    left,
+                    inner_pad,
+                    e.wrap(inner, max_width, true),
+                    pad,
+                    right
+                )
+            }
+            Expression::Binder(token, args, body, _) => {
+                let decls: Vec<_> = args.iter().map(|arg| arg.to_string()).collect();
+                format!(
+                    "{}({}) {{\n{}{}\n{}}}",
+                    token,
+                    decls.join(", "),
+                    inner_pad,
+                    body.wrap(inner, max_width, true),
+                    pad
+                )
+            }
+            Expression::IfThenElse(_, condition, if_value, else_value, _) => {
+                format!(
+                    "if {} {{\n{}{}\n{}}} else {{\n{}{}\n{}}}",
+                    condition.wrap(indent + 3, max_width, breakable),
+                    inner_pad,
+                    if_value.wrap(inner, max_width, true),
+                    pad,
+                    inner_pad,
+                    else_value.wrap(inner, max_width, true),
+                    pad
+                )
+            }
+            Expression::SetBuilder(_, declaration, condition, _) => {
+                format!(
+                    "{{ {} |\n{}{}\n{}}}",
+                    declaration,
+                    inner_pad,
+                    condition.wrap(inner, max_width, true),
+                    pad
+                )
+            }
+            Expression::List(_, elements, _) if !elements.is_empty() => {
+                let parts: Vec<_> = elements
+                    .iter()
+                    .map(|element| element.wrap(inner, max_width, true))
+                    .collect();
+                format!(
+                    "[\n{}{}\n{}]",
+                    inner_pad,
+                    parts.join(&format!(",\n{}", inner_pad)),
+                    pad
+                )
+            }
+            _ => flat,
+        }
     }
 
     // Converts this expression to a numeric digit, if possible.
@@ -420,17 +730,71 @@ impl Expression {
         }
     }
 
+    // Whether this is a number in decimal, of any type.
+    pub fn is_decimal_number(&self) -> bool {
+        match self.number_text() {
+            Some(text) => Token::radix_digits(text).is_none(),
+            None => false,
+        }
+    }
+
+    // The text of this expression, if it's a number of any type.
+    pub fn number_text(&self) -> Option<&str> {
+        match self {
+            Expression::Singleton(token) if token.token_type == TokenType::Numeral => {
+                Some(token.text())
+            }
+            Expression::Binary(_, token, right) if token.token_type == TokenType::Dot => {
+                right.number_text()
+            }
+            _ => None,
+        }
+    }
+
+    // The contents of this expression, if it's a string literal.
+    pub fn string_contents(&self) -> Option<String> {
+        match self {
+            Expression::Singleton(token) if token.token_type == TokenType::StringLiteral => {
+                token.string_contents().ok()
+            }
+            _ => None,
+        }
+    }
+
     // Appends a digit.
     // 'initial' must be a number.
     pub fn generate_number(initial: Expression, digit: char) -> Expression {
-        match initial {
-            Expression::Singleton(token) => {
-                let mut text = token.text().to_string();
-                text.push(digit);
-                Expression::Singleton(TokenType::Numeral.new_token(&text))
+        let mut text = initial
+            .number_text()
+            .expect("expected a number")
+            .to_string();
+        text.push(digit);
+        initial.with_number_text(&text)
+    }
+
+    // The text we get by appending a digit in the given radix, keeping the number in that base.
+    // A decimal number less than the radix is treated as its first digit.
+    // Returns None if this isn't a number that can be written in that base.
+    pub fn radix_number_text(&self, radix: u32, digit: u32) -> Option<String> {
+        let text = self.number_text()?;
+        let digit = char::from_digit(digit, radix)?;
+        match Token::radix_digits(text) {
+            Some((r, _)) if r == radix => Some(format!("{}{}", text, digit)),
+            Some(_) => None,
+            None => {
+                let first = char::from_digit(text.parse().ok()?, radix)?;
+                let prefix = if radix == 16 { "0x" } else { "0b" };
+                Some(format!("{}{}{}", prefix, first, digit))
             }
+        }
+    }
+
+    // Replaces the text of a number, keeping its type.
+    pub fn with_number_text(self, text: &str) -> Expression {
+        match self {
+            Expression::Singleton(_) => Expression::Singleton(TokenType::Numeral.new_token(text)),
             Expression::Binary(left, token, right) if token.token_type == TokenType::Dot => {
-                let new_right = Expression::generate_number(*right, digit);
+                let new_right = right.with_number_text(text);
                 Expression::Binary(left, token, Box::new(new_right))
             }
             _ => panic!("expected a number"),
@@ -446,10 +810,16 @@ impl Expression {
             | Expression::Grouping(..)
             | Expression::Binder(..)
             | Expression::IfThenElse(..)
-            | Expression::Match(..) => {
+            | Expression::Match(..)
+            | Expression::SetBuilder(..)
+            | Expression::List(..) => {
                 // These expressions never need to be parenthesized.
                 i8::MAX
             }
+            Expression::Choose(..) => {
+                // The condition would swallow anything after it.
+                0
+            }
             Expression::Unary(token, _) => {
                 if unary_counts_as_max {
                     i8::MAX
@@ -484,6 +854,110 @@ impl Expression {
         }
     }
 
+    // Adds the identifiers that this expression refers to, but doesn't bind itself, and that
+    // aren't in "bound".
+    // Only the first part of a dotted name like "Nat.suc" counts, since the rest of it is
+    // looked up in the first part.
+    // This happens before evaluation, so it works on code that doesn't compile.
+    pub fn add_free_names(&self, bound: &mut Vec<String>, answer: &mut Vec<Token>) {
+        match self {
+            Expression::Singleton(token) => {
+                if token.token_type == TokenType::Identifier
+                    && !bound.iter().any(|name| name == token.text())
+                {
+                    answer.push(token.clone());
+                }
+            }
+            Expression::Unary(_, subexpression) => subexpression.add_free_names(bound, answer),
+            Expression::Binary(left, token, right) => {
+                left.add_free_names(bound, answer);
+                match right.as_ref() {
+                    // A qualified attribute like "x.(order.le)" names the module it's from.
+                    Expression::Grouping(_, qualified, _) if token.token_type == TokenType::Dot => {
+                        qualified.add_free_names(bound, answer)
+                    }
+                    _ if token.token_type == TokenType::Dot => {}
+                    _ => right.add_free_names(bound, answer),
+                }
+            }
+            Expression::Apply(left, right) => {
+                left.add_free_names(bound, answer);
+                right.add_free_names(bound, answer);
+            }
+            Expression::Grouping(_, e, _) => e.add_free_names(bound, answer),
+            Expression::Binder(_, args, body, _) => {
+                let depth = bound.len();
+                for arg in args {
+                    arg.bind(bound, answer);
+                }
+                body.add_free_names(bound, answer);
+                bound.truncate(depth);
+            }
+            Expression::IfThenElse(_, condition, if_block, else_block, _) => {
+                condition.add_free_names(bound, answer);
+                if_block.add_free_names(bound, answer);
+                else_block.add_free_names(bound, answer);
+            }
+            Expression::Match(_, scrutinee, cases, _) => {
+                scrutinee.add_free_names(bound, answer);
+                for (pattern, guard, result) in cases {
+                    let depth = bound.len();
+                    pattern.bind_pattern(bound, answer);
+                    if let Some(guard) = guard {
+                        guard.add_free_names(bound, answer);
+                    }
+                    result.add_free_names(bound, answer);
+                    bound.truncate(depth);
+                }
+            }
+            Expression::SetBuilder(_, declaration, condition, _)
+            | Expression::Choose(_, declaration, condition) => {
+                let depth = bound.len();
+                declaration.bind(bound, answer);
+                condition.add_free_names(bound, answer);
+                bound.truncate(depth);
+            }
+            Expression::List(_, elements, _) => {
+                for element in elements {
+                    element.add_free_names(bound, answer);
+                }
+            }
+        }
+    }
+
+    // Treats this expression as a pattern like "Nat.suc(k)", binding the variables in it.
+    // The constructor is a free name, and the arguments are bound.
+    // The caller is responsible for unbinding them.
+    pub fn bind_pattern(&self, bound: &mut Vec<String>, answer: &mut Vec<Token>) {
+        match self {
+            Expression::Apply(constructor, args) => {
+                constructor.add_free_names(bound, answer);
+                args.bind_pattern(bound, answer);
+            }
+            Expression::Grouping(_, e, _) => {
+                for part in e.flatten_comma_separated_list() {
+                    match part {
+                        Expression::Singleton(token)
+                            if token.token_type == TokenType::Identifier =>
+                        {
+                            bound.push(token.text().to_string());
+                        }
+                        _ => part.bind_pattern(bound, answer),
+                    }
+                }
+            }
+            _ => self.add_free_names(bound, answer),
+        }
+    }
+
+    // Whether this is a list of type parameters, like "<T, U>".
+    pub fn is_type_param_list(&self) -> bool {
+        match self {
+            Expression::Grouping(left, _, _) => left.token_type == TokenType::LessThan,
+            _ => false,
+        }
+    }
+
     // Flattens an expression like "1, 2, 3"
     pub fn flatten_comma_separated_list(&self) -> Vec<&Expression> {
         match self {
@@ -539,6 +1013,22 @@ impl Expression {
         Expression::parse(tokens, ExpressionType::Value, terminator)
     }
 
+    // Parse the part of a match arm that comes before its body.
+    // This is a pattern, optionally followed by "if <guard>".
+    // Consumes the left brace that starts the body and returns it.
+    pub fn parse_match_arm_head(
+        tokens: &mut TokenIter,
+    ) -> Result<(Expression, Option<Expression>, Token)> {
+        let (pattern, terminator) =
+            Expression::parse_value(tokens, Terminator::Or(TokenType::LeftBrace, TokenType::If))?;
+        if terminator.token_type == TokenType::LeftBrace {
+            return Ok((pattern, None, terminator));
+        }
+        let (guard, left_brace) =
+            Expression::parse_value(tokens, Terminator::Is(TokenType::LeftBrace))?;
+        Ok((pattern, Some(guard), left_brace))
+    }
+
     // Parse an expression that should represent a type, or part of a type.
     // Consumes the terminating token and returns it.
     pub fn parse_type(
@@ -655,7 +1145,12 @@ fn parse_partial_expressions(
 ) -> Result<(VecDeque<PartialExpression>, Token)> {
     let mut partials = VecDeque::<PartialExpression>::new();
     while let Some(token) = tokens.next() {
-        if termination.matches(&token.token_type) {
+        // A type can't be empty, so a left brace at the start of a type is a refinement type,
+        // even when a left brace would otherwise terminate it.
+        let starts_refinement = expected_type == ExpressionType::Type
+            && token.token_type == TokenType::LeftBrace
+            && partials.is_empty();
+        if termination.matches(&token.token_type) && !starts_refinement {
             return Ok((partials, token));
         }
         if token.token_type == TokenType::LessThan {
@@ -728,10 +1223,66 @@ fn parse_partial_expressions(
                 partials.push_back(PartialExpression::Expression(group));
             }
 
+            TokenType::LeftBrace => {
+                // In a type, this is a refinement type rather than a set.
+                if expected_type == ExpressionType::Type && !partials.is_empty() {
+                    return Err(token.error("unexpected left brace in type"));
+                }
+                let (decl, _) = Declaration::parse(tokens, Terminator::Is(TokenType::Pipe))?;
+                if let Declaration::SelfToken(self_token) = &decl {
+                    return Err(self_token.error("cannot bind 'self' in a set-builder expression"));
+                }
+                let (condition, right_brace) =
+                    Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
+                let set_builder =
+                    Expression::SetBuilder(token, Box::new(decl), Box::new(condition), right_brace);
+                partials.push_back(PartialExpression::Expression(set_builder));
+            }
+
+            TokenType::Choose => {
+                if expected_type != ExpressionType::Value {
+                    return Err(token.error("'choose' cannot be used here"));
+                }
+                let (decl, _) = Declaration::parse(tokens, Terminator::Is(TokenType::Where))?;
+                if let Declaration::SelfToken(self_token) = &decl {
+                    return Err(self_token.error("cannot bind 'self' in a choose expression"));
+                }
+
+                // The condition takes up the rest of the expression.
+                let (condition, terminator) = Expression::parse_value(tokens, termination)?;
+                let choose = Expression::Choose(token, Box::new(decl), Box::new(condition));
+                partials.push_back(PartialExpression::Expression(choose));
+                return Ok((partials, terminator));
+            }
+
+            TokenType::LeftBracket => {
+                if expected_type != ExpressionType::Value {
+                    return Err(token.error("list literals cannot be used here"));
+                }
+                let mut elements = vec![];
+                let right_bracket = match tokens.peek() {
+                    Some(t) if t.token_type == TokenType::RightBracket => tokens.next().unwrap(),
+                    _ => {
+                        let (contents, right_bracket) = Expression::parse_value(
+                            tokens,
+                            Terminator::Is(TokenType::RightBracket),
+                        )?;
+                        flatten_into(contents, &mut elements);
+                        right_bracket
+                    }
+                };
+                let list = Expression::List(token, elements, right_bracket);
+                partials.push_back(PartialExpression::Expression(list));
+            }
+
             TokenType::Identifier | TokenType::Axiom => {
                 partials.push_back(PartialExpression::Expression(Expression::Singleton(token)));
             }
-            TokenType::Numeral | TokenType::True | TokenType::False | TokenType::SelfToken => {
+            TokenType::Numeral
+            | TokenType::StringLiteral
+            | TokenType::True
+            | TokenType::False
+            | TokenType::SelfToken => {
                 if expected_type == ExpressionType::Type {
                     return Err(token.error("expected a type but found a value"));
                 }
@@ -798,11 +1349,10 @@ fn parse_partial_expressions(
                         }
                     }
 
-                    let (pattern, _) =
-                        Expression::parse_value(tokens, Terminator::Is(TokenType::LeftBrace))?;
+                    let (pattern, guard, _) = Expression::parse_match_arm_head(tokens)?;
                     let (exp, _) =
                         Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
-                    cases.push((pattern, exp));
+                    cases.push((pattern, guard, exp));
                 };
                 if cases.is_empty() {
                     return Err(tokens.error("expected at least one case"));
@@ -826,6 +1376,17 @@ fn parse_partial_expressions(
     Err(tokens.error("expected expression but got EOF"))
 }
 
+// Takes ownership of the parts of a comma-separated expression like "1, 2, 3".
+fn flatten_into(expression: Expression, output: &mut Vec<Expression>) {
+    match expression {
+        Expression::Binary(left, token, right) if token.token_type == TokenType::Comma => {
+            flatten_into(*left, output);
+            flatten_into(*right, output);
+        }
+        e => output.push(e),
+    }
+}
+
 // Find the index of the operator that should operate last. (Ie, the root of the tree.)
 // If there are no operators, return None.
 fn find_last_operator(partials: &VecDeque<PartialExpression>) -> Result<Option<usize>> {
@@ -1177,6 +1738,13 @@ mod tests {
         check_not_value("match a { }");
     }
 
+    #[test]
+    fn test_match_guard_expressions() {
+        check_value("match a { Foo.bar(b) if b { b } Foo.bar(b) { not b } }");
+        check_value("match n { Nat.suc(k) if k != Nat.0 { k } Nat.suc(k) { n } Nat.0 { n } }");
+        check_not_value("match a { Foo.bar(b) if { b } }");
+    }
+
     #[test]
     fn test_unary_negate_expression() {
         check_value("-1");
@@ -1189,6 +1757,24 @@ mod tests {
         check_value("-(a * b)");
     }
 
+    #[test]
+    fn test_nested_negate_expression() {
+        check_value("-(-a)");
+        check_value("-(-(-a))");
+    }
+
+    #[test]
+    fn test_custom_operator_expressions() {
+        check_value("a ++ b ++ c");
+        check_value("a ++ (b ++ c)");
+        check_value("f ∘ g ∘ h");
+        check_value("x |> f |> g");
+        check_value("a + b ++ c = c ++ a");
+        check_value("(f ∘ g)(x) = f(g(x))");
+        check_value("x |> f ∘ g = g(x) |> f");
+        check_not_type("a ++ b");
+    }
+
     #[test]
     fn test_implies_expression() {
         check_value("a implies b");
@@ -1203,4 +1789,45 @@ mod tests {
 
         // check_value("foo.bar<T>");
     }
+
+    #[test]
+    fn test_list_expressions() {
+        check_value("[]");
+        check_value("[a]");
+        check_value("[a, b + c, f(d)]");
+        check_value("[[a], []]");
+        check_value("length([a, b])");
+        check_not_value("[a, b");
+        check_not_value("[a,]");
+        check_not_type("[Nat]");
+    }
+
+    #[test]
+    fn test_choose_expressions() {
+        check_value("choose x: Nat where x > 0");
+        check_value("(choose x: Nat where x > 0) + 1");
+        check_value("foo(choose x: Nat where foo(x) = choose y: Nat where y > x)");
+        check_not_value("choose x where x > 0");
+        check_not_value("choose x: Nat");
+        check_not_value("choose x: Nat where");
+    }
+
+    #[test]
+    fn test_set_builder_expressions() {
+        check_value("{ x: Nat | x > 0 }");
+        check_value("{ x: Nat | foo(x) or { y: Nat | y = x }(x) }");
+        check_value("contains({ x: Nat | x = 0 }, 0)");
+        check_not_value("{ x | x > 0 }");
+        check_not_value("{ x: Nat | }");
+        check_not_value("{ self | true }");
+    }
+
+    #[test]
+    fn test_refinement_type_expressions() {
+        check_type("{ x: Nat | true }");
+        check_type("{ x: Nat | x != Nat.0 }");
+        check_type("({ x: Nat | x > 0 })");
+        check_not_type("Nat -> { x: Nat | true }");
+        check_not_type("{ self | true }");
+    }
 }
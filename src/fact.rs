@@ -1,7 +1,9 @@
+use std::collections::HashSet;
+
 use crate::acorn_type::AcornType;
 use crate::acorn_value::AcornValue;
 use crate::proof_step::Truthiness;
-use crate::proposition::{Proposition, Source, SourceType};
+use crate::proposition::{Attributes, Proposition, Source, SourceType};
 
 // A fact is a proposition that we already know to be true.
 #[derive(Clone, Debug)]
@@ -9,6 +11,7 @@ pub struct Fact {
     pub value: AcornValue,
     pub source: Source,
     pub truthiness: Truthiness,
+    pub attributes: Attributes,
 }
 
 impl Fact {
@@ -17,6 +20,7 @@ impl Fact {
             value: proposition.value,
             source: proposition.source,
             truthiness,
+            attributes: proposition.attributes,
         }
     }
 
@@ -45,6 +49,34 @@ impl Fact {
             value,
             source,
             truthiness: self.truthiness,
+            attributes: self.attributes.clone(),
         }
     }
 }
+
+// Decides which facts to use, based on their tags.
+// A fact is excluded if it has any of the excluded tags.
+#[derive(Clone, Debug, Default)]
+pub struct TagFilter {
+    excluded: HashSet<String>,
+}
+
+impl TagFilter {
+    // A filter that excludes facts with any of the given tags.
+    pub fn excluding<'a>(tags: impl IntoIterator<Item = &'a str>) -> TagFilter {
+        TagFilter {
+            excluded: tags.into_iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.excluded.is_empty()
+    }
+
+    pub fn allows(&self, attributes: &Attributes) -> bool {
+        !attributes
+            .tags
+            .iter()
+            .any(|tag| self.excluded.contains(tag))
+    }
+}
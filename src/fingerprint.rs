@@ -1,4 +1,6 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
+
+use im::{HashMap, OrdMap};
 
 use crate::atom::Atom;
 use crate::literal::Literal;
@@ -25,6 +27,11 @@ impl FingerprintComponent {
     pub fn new(term: &Term, path: &&[usize]) -> FingerprintComponent {
         let mut current_term = term;
         for &i in *path {
+            if current_term.head.is_variable() && !current_term.args.is_empty() {
+                // An applied variable can unify with a longer application, which shifts
+                // which arguments line up.
+                return FingerprintComponent::Below;
+            }
             if i >= current_term.args.len() {
                 if current_term.atomic_variable().is_some() {
                     return FingerprintComponent::Below;
@@ -175,12 +182,13 @@ impl LiteralFingerprint {
 
 // A data structure designed to quickly find which literals are a specialization of a query literal.
 // Identifies literals by a usize id.
+// The trees are persistent, so that it's cheap to clone along with the passive set.
 #[derive(Clone)]
-pub struct FingerprintSpecializer<T> {
-    trees: HashMap<TypeId, BTreeMap<LiteralFingerprint, Vec<T>>>,
+pub struct FingerprintSpecializer<T: Clone> {
+    trees: HashMap<TypeId, OrdMap<LiteralFingerprint, Vec<T>>>,
 }
 
-impl<T> FingerprintSpecializer<T> {
+impl<T: Clone> FingerprintSpecializer<T> {
     pub fn new() -> FingerprintSpecializer<T> {
         FingerprintSpecializer {
             trees: HashMap::new(),
@@ -189,10 +197,7 @@ impl<T> FingerprintSpecializer<T> {
 
     pub fn insert(&mut self, literal: &Literal, value: T) {
         let fingerprint = LiteralFingerprint::new(&literal.left, &literal.right);
-        let tree = self
-            .trees
-            .entry(literal.left.get_term_type())
-            .or_insert(BTreeMap::new());
+        let tree = self.trees.entry(literal.left.get_term_type()).or_default();
         tree.entry(fingerprint).or_insert(vec![]).push(value);
     }
 
@@ -245,4 +250,12 @@ mod tests {
         assert!(tree.find_unifying(&term1).len() > 0);
         assert!(tree.find_unifying(&term2).len() > 0);
     }
+
+    #[test]
+    fn test_fingerprint_tree_applied_variable() {
+        // An applied variable can line up with the end of a longer application
+        let mut tree = FingerprintUnifier::new();
+        tree.insert(&Term::parse("x0(c1)"), 3);
+        assert!(tree.find_unifying(&Term::parse("c2(c3, c1)")).contains(&&3));
+    }
 }
@@ -1,7 +1,8 @@
 use tower_lsp::lsp_types::Range;
 
-use crate::acorn_value::AcornValue;
+use crate::acorn_value::{AcornValue, BinaryOp};
 use crate::environment::Environment;
+use crate::limits::LimitOverrides;
 use crate::module::ModuleId;
 use crate::proposition::Proposition;
 
@@ -54,6 +55,9 @@ pub struct GoalContext {
     // This range includes the entire proof block for this goal, if there is one.
     pub first_line: u32,
     pub last_line: u32,
+
+    // Limits from the attributes of the theorems this goal is inside.
+    pub limits: LimitOverrides,
 }
 
 impl GoalContext {
@@ -90,6 +94,57 @@ impl GoalContext {
             inconsistency_okay: env.includes_explicit_false,
             first_line,
             last_line,
+            limits: LimitOverrides::default(),
+        }
+    }
+
+    // Splits a goal that is a conjunction into a goal for each conjunct, so that each one gets
+    // its own search, and a failure points at the conjunct that couldn't be proven.
+    // Any other goal stays whole.
+    pub fn subgoals(self) -> Vec<GoalContext> {
+        let proposition = match &self.goal {
+            Goal::Prove(proposition) => proposition,
+            Goal::Solve(..) => return vec![self],
+        };
+        let parts = split_conjunction(&proposition.value);
+        if parts.len() < 2 {
+            return vec![self];
+        }
+        let num_parts = parts.len();
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| GoalContext {
+                module_id: self.module_id,
+                name: format!("{} (part {} of {})", self.name, i + 1, num_parts),
+                goal: Goal::Prove(Proposition {
+                    value,
+                    ..proposition.clone()
+                }),
+                proof_insertion_line: self.proof_insertion_line,
+                insert_block: self.insert_block,
+                inconsistency_okay: self.inconsistency_okay,
+                first_line: self.first_line,
+                last_line: self.last_line,
+                limits: self.limits,
+            })
+            .collect()
+    }
+}
+
+// The conjuncts of a value, looking through universal quantifiers.
+// Each conjunct keeps the quantifiers it was inside of.
+fn split_conjunction(value: &AcornValue) -> Vec<AcornValue> {
+    match value {
+        AcornValue::Binary(BinaryOp::And, left, right) => {
+            let mut parts = split_conjunction(left);
+            parts.extend(split_conjunction(right));
+            parts
         }
+        AcornValue::ForAll(quants, body) => split_conjunction(body)
+            .into_iter()
+            .map(|part| AcornValue::ForAll(quants.clone(), Box::new(part)))
+            .collect(),
+        _ => vec![value.clone()],
     }
 }
@@ -0,0 +1,308 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+use crate::module::{ModuleDescriptor, ModuleId};
+use crate::project::Project;
+use crate::statement::{Statement, StatementInfo};
+use crate::token::Token;
+
+// An import that the module doesn't need.
+pub struct UnusedImport {
+    // The imported name, or the full name of the module when the whole statement is unused.
+    pub name: String,
+
+    // Where the name is, or the whole statement.
+    pub range: Range,
+
+    // The edit that removes the import.
+    pub edit: TextEdit,
+}
+
+// A name that the module uses without defining or importing it, when some loaded module
+// could provide it.
+pub struct MissingImport {
+    pub name: String,
+
+    // Where the name is first used.
+    pub range: Range,
+
+    // The full names of the modules the name could be imported from, sorted.
+    // Each module has an edit that adds the import.
+    pub options: Vec<(String, TextEdit)>,
+}
+
+// Which imports a module needs, based on the names its statements refer to.
+// This works on the statements before evaluation, so it can help fix a module that
+// doesn't compile.
+pub struct ImportAnalysis {
+    // The full names of the imported modules that the statements use names from.
+    pub required_modules: BTreeSet<String>,
+
+    pub unused: Vec<UnusedImport>,
+    pub missing: Vec<MissingImport>,
+}
+
+// What we know about one top-level import statement.
+struct ImportInfo<'a> {
+    statement: &'a Statement,
+    full_name: String,
+    local_name: &'a str,
+    names: &'a [Token],
+    wildcard: bool,
+    module_id: Option<ModuleId>,
+}
+
+impl ImportAnalysis {
+    // module_id is the id of the module being analyzed, if it has one.
+    // Modules that depend on it are never suggested, since that would be a circular import.
+    pub fn new(
+        project: &Project,
+        module_id: Option<ModuleId>,
+        statements: &[Statement],
+    ) -> ImportAnalysis {
+        // The free names of each top-level statement, in order.
+        // Top-level names aren't bound here, since we care about which ones get used.
+        let mut free = vec![];
+        let mut defined = HashSet::new();
+        let mut imports = vec![];
+        for statement in statements {
+            statement.add_free_names(&mut vec![], &mut free);
+            defined.extend(statement.introduced_names());
+            if let StatementInfo::Import(is) = &statement.statement {
+                let full_name = is.components.join(".");
+                let descriptor = ModuleDescriptor::Name(full_name.clone());
+                imports.push(ImportInfo {
+                    statement,
+                    module_id: project.get_env(&descriptor).map(|env| env.module_id),
+                    full_name,
+                    local_name: is.components.last().unwrap(),
+                    names: &is.names,
+                    wildcard: is.wildcard,
+                });
+            }
+        }
+        let used: HashSet<&str> = free.iter().map(|token| token.text()).collect();
+
+        let mut wildcard_names = HashSet::new();
+        let mut required_modules = BTreeSet::new();
+        let mut unused = vec![];
+        for info in &imports {
+            let importable = match (info.wildcard, info.module_id) {
+                (true, Some(id)) => project.get_bindings(id).unwrap().importable_names(),
+                _ => vec![],
+            };
+            let module_used = used.contains(info.local_name)
+                || importable.iter().any(|name| used.contains(name.as_str()));
+            wildcard_names.extend(importable);
+            let unused_names: Vec<&Token> = info
+                .names
+                .iter()
+                .filter(|name| !used.contains(name.text()))
+                .collect();
+            if module_used || unused_names.len() < info.names.len() {
+                required_modules.insert(info.full_name.clone());
+            }
+            unused.extend(unused_imports(
+                project,
+                info,
+                &imports,
+                module_used,
+                &unused_names,
+            ));
+        }
+        unused.sort_by_key(|u| (u.range.start.line, u.range.start.character));
+
+        // Each name the module might be missing, with where it's first used.
+        let mut first_use: Vec<&Token> = vec![];
+        for token in &free {
+            let name = token.text();
+            if !defined.contains(name)
+                && !wildcard_names.contains(name)
+                && !first_use.iter().any(|t| t.text() == name)
+            {
+                first_use.push(token);
+            }
+        }
+        let providers = if first_use.is_empty() {
+            HashMap::new()
+        } else {
+            importable_by_module(project, module_id)
+        };
+        let missing = first_use
+            .into_iter()
+            .filter_map(|token| {
+                let modules = providers.get(token.text())?;
+                let options = modules
+                    .iter()
+                    .map(|module| {
+                        let edit = add_import_edit(statements, &imports, module, token.text());
+                        (module.clone(), edit)
+                    })
+                    .collect();
+                Some(MissingImport {
+                    name: token.text().to_string(),
+                    range: token.range(),
+                    options,
+                })
+            })
+            .collect();
+
+        ImportAnalysis {
+            required_modules,
+            unused,
+            missing,
+        }
+    }
+
+    // One line for each finding, for printing.
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for u in &self.unused {
+            lines.push(format!(
+                "line {}: unused import: {}",
+                u.range.start.line + 1,
+                u.name
+            ));
+        }
+        for m in &self.missing {
+            let modules: Vec<_> = m
+                .options
+                .iter()
+                .map(|(module, _)| module.as_str())
+                .collect();
+            lines.push(format!(
+                "line {}: {} is not imported. it's in: {}",
+                m.range.start.line + 1,
+                m.name,
+                modules.join(", ")
+            ));
+        }
+        lines
+    }
+}
+
+// Finds the unused parts of one import statement.
+// The module itself is only unused if nothing refers to it, and its facts are still
+// imported through another module, so removing it can't break any proofs.
+fn unused_imports(
+    project: &Project,
+    info: &ImportInfo,
+    imports: &[ImportInfo],
+    module_used: bool,
+    unused_names: &[&Token],
+) -> Vec<UnusedImport> {
+    let facts_elsewhere = info.module_id.is_some_and(|id| {
+        imports.iter().any(|other| {
+            other.module_id.is_some_and(|other_id| {
+                other_id != id && project.all_dependencies(other_id).contains(&id)
+            })
+        })
+    });
+    let keep_module = module_used || !facts_elsewhere;
+    if unused_names.len() == info.names.len() && !keep_module {
+        let range = statement_lines(info.statement);
+        return vec![UnusedImport {
+            name: info.full_name.clone(),
+            range: info.statement.range(),
+            edit: TextEdit::new(range, String::new()),
+        }];
+    }
+
+    unused_names
+        .iter()
+        .map(|name| {
+            let edit = if info.names.len() == 1 {
+                TextEdit::new(info.statement.range(), format!("import {}", info.full_name))
+            } else {
+                TextEdit::new(name_removal_range(info.names, name), String::new())
+            };
+            UnusedImport {
+                name: name.text().to_string(),
+                range: name.range(),
+                edit,
+            }
+        })
+        .collect()
+}
+
+// The range that covers a statement's lines, including the final newline.
+fn statement_lines(statement: &Statement) -> Range {
+    Range {
+        start: Position::new(statement.first_line(), 0),
+        end: Position::new(statement.last_line() + 1, 0),
+    }
+}
+
+// The range to remove one name from a list of names, along with the comma next to it.
+fn name_removal_range(names: &[Token], name: &Token) -> Range {
+    let i = names
+        .iter()
+        .position(|n| n.range() == name.range())
+        .unwrap();
+    if i + 1 < names.len() {
+        Range {
+            start: name.start_pos(),
+            end: names[i + 1].start_pos(),
+        }
+    } else {
+        Range {
+            start: names[i - 1].end_pos(),
+            end: name.end_pos(),
+        }
+    }
+}
+
+// The edit that imports a name from a module.
+// It extends an existing "from ... import" statement for the module if there is one.
+// Otherwise, it adds a new statement after the last import, or at the top.
+fn add_import_edit(
+    statements: &[Statement],
+    imports: &[ImportInfo],
+    module: &str,
+    name: &str,
+) -> TextEdit {
+    let existing = imports
+        .iter()
+        .find(|info| info.full_name == module && !info.names.is_empty());
+    if let Some(info) = existing {
+        let end = info.names.last().unwrap().end_pos();
+        return TextEdit::new(Range::new(end, end), format!(", {}", name));
+    }
+    let line = match imports.last() {
+        Some(info) => info.statement.last_line() + 1,
+        None => statements.first().map_or(0, |s| s.first_line()),
+    };
+    let start = Position::new(line, 0);
+    TextEdit::new(
+        Range::new(start, start),
+        format!("from {} import {}\n", module, name),
+    )
+}
+
+// Maps each name that loaded modules can import to the full names of those modules.
+// The module being analyzed, and the modules that depend on it, are left out.
+fn importable_by_module(
+    project: &Project,
+    module_id: Option<ModuleId>,
+) -> HashMap<String, Vec<String>> {
+    let mut answer: HashMap<String, Vec<String>> = HashMap::new();
+    for env in project.loaded_envs() {
+        let ModuleDescriptor::Name(module_name) = project.get_module_descriptor(env.module_id)
+        else {
+            continue;
+        };
+        if let Some(id) = module_id {
+            if env.module_id == id || project.all_dependencies(env.module_id).contains(&id) {
+                continue;
+            }
+        }
+        for name in env.bindings.importable_names() {
+            answer.entry(name).or_default().push(module_name.clone());
+        }
+    }
+    for modules in answer.values_mut() {
+        modules.sort();
+    }
+    answer
+}
@@ -98,6 +98,18 @@ pub struct Location {
     pub range: Range,
 }
 
+// A fact that a proof used, and where it came from.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PremiseInfo {
+    // Like "the 'add_zero' theorem".
+    pub description: String,
+
+    // Where the fact is defined, so that the UI can link to it.
+    // None if we can't find the file, like for mock modules.
+    pub location: Option<Location>,
+}
+
 // Information about one step in a proof.
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -112,6 +124,9 @@ pub struct ProofStepInfo {
     // Description of the rule used in this proof step
     pub rule: String,
 
+    // An explanation of this step for the user, like "rewrote x + 0 to x using add_zero".
+    pub justification: String,
+
     // Source is set when this proof step is based on a specific part of a codebase,
     // and we can find a location for it.
     pub location: Option<Location>,
@@ -139,6 +154,10 @@ pub struct SearchStatus {
     // If we failed to find a proof, this is None.
     pub steps: Option<Vec<ProofStepInfo>>,
 
+    // Every fact the proof used, including the ones whose steps got condensed away.
+    // If we failed to find a proof, this is None.
+    pub premises: Option<Vec<PremiseInfo>>,
+
     // A stringification of the prover's Outcome, if it has finished.
     pub outcome: Option<String>,
 
@@ -149,6 +168,9 @@ pub struct SearchStatus {
     // How many clauses we have activated.
     // Any id below this, we can handle info requests to provide information for it.
     pub num_activated: usize,
+
+    // If the search found a countermodel, the details of it.
+    pub counterexample: Option<Counterexample>,
 }
 
 impl SearchStatus {
@@ -157,9 +179,11 @@ impl SearchStatus {
             code: None,
             code_error: None,
             steps: None,
+            premises: None,
             outcome: None,
             needs_simplification: None,
             num_activated: 0,
+            counterexample: None,
         }
     }
 
@@ -168,6 +192,7 @@ impl SearchStatus {
         code: Option<Vec<String>>,
         code_error: Option<String>,
         steps: Vec<ProofStepInfo>,
+        premises: Vec<PremiseInfo>,
         needs_simplification: bool,
         prover: &Prover,
     ) -> SearchStatus {
@@ -175,9 +200,11 @@ impl SearchStatus {
             code,
             code_error,
             steps: Some(steps),
+            premises: Some(premises),
             outcome: Some(Outcome::Success.to_string()),
             needs_simplification: Some(needs_simplification),
             num_activated: prover.num_activated(),
+            counterexample: None,
         }
     }
 
@@ -187,9 +214,11 @@ impl SearchStatus {
             code: None,
             code_error: None,
             steps: None,
+            premises: None,
             outcome: None,
             needs_simplification: None,
             num_activated: prover.num_activated(),
+            counterexample: None,
         }
     }
 
@@ -199,13 +228,28 @@ impl SearchStatus {
             code: None,
             code_error: None,
             steps: None,
+            premises: None,
             outcome: Some(outcome.to_string()),
             needs_simplification: None,
             num_activated: prover.num_activated(),
+            counterexample: prover.counterexample(),
         }
     }
 }
 
+// A counterexample to a goal, from a finite model where the goal is false.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Counterexample {
+    // How the model interprets each type and symbol, like "f(Nat#0) = Nat#1".
+    pub assignments: Vec<String>,
+
+    // Known facts that the model makes false.
+    // The model was only built to satisfy the facts the prover had reached, so when this
+    // is nonempty the goal may still be provable.
+    pub violated: Vec<String>,
+}
+
 // The SearchResponse is sent from language server -> extension -> webview with the result of a
 // proof search, or information about a partial result.
 //
@@ -308,3 +352,52 @@ pub struct InfoResponse {
     pub failure: Option<String>,
     pub result: Option<InfoResult>,
 }
+
+// A passive clause, along with the score that decides when it gets activated.
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoredClauseInfo {
+    pub clause: ClauseInfo,
+
+    // Higher scores get activated first.
+    pub score: f32,
+}
+
+// A view of a search that may still be in progress, to help figure out why a goal is stuck.
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverSnapshot {
+    // The clauses that come from negating the goal.
+    pub goal: Vec<ClauseInfo>,
+
+    // The passive clauses that are next in line to be activated, best first.
+    pub next_passive: Vec<ScoredClauseInfo>,
+
+    // The clauses that were activated most recently, most recent first.
+    pub recent_activations: Vec<ClauseInfo>,
+
+    pub num_activated: usize,
+    pub num_passive: usize,
+}
+
+// The SnapshotParams are sent from webview -> extension -> language server, to look at the
+// state of a search.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotParams {
+    // Which search we want to look at.
+    pub search_id: i32,
+
+    // How many passive clauses and recent activations to include.
+    pub count: usize,
+}
+
+// Like the InfoResponse, a request that's out of sync with the server gets a failure string
+// and no snapshot.
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotResponse {
+    pub search_id: i32,
+    pub failure: Option<String>,
+    pub snapshot: Option<ProverSnapshot>,
+}
@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::atom::Atom;
+use crate::term::Term;
+
+// A Knuth-Bendix ordering where each symbol can have its own weight and precedence.
+// Term::kbo_cmp uses fixed weights, which is fine for normalizing literals, but it doesn't know
+// anything about what the symbols mean. This ordering can be configured for each prover, to
+// decide which way equalities should be used for rewriting.
+#[derive(Clone, Debug, Default)]
+pub struct Kbo {
+    // Symbols that aren't in here have weight 1. Variables always have weight 1.
+    weights: HashMap<Atom, u32>,
+
+    // Symbols that aren't in here have precedence 0.
+    // Ties in precedence are broken by comparing the atoms themselves.
+    precedence: HashMap<Atom, u32>,
+}
+
+impl Kbo {
+    pub fn new() -> Kbo {
+        Kbo::default()
+    }
+
+    // Weights must be positive, so that a term is always heavier than its subterms.
+    pub fn set_weight(&mut self, atom: Atom, weight: u32) {
+        assert!(weight > 0, "kbo weights must be positive");
+        self.weights.insert(atom, weight);
+    }
+
+    pub fn set_precedence(&mut self, atom: Atom, precedence: u32) {
+        self.precedence.insert(atom, precedence);
+    }
+
+    fn weight(&self, term: &Term, var_counts: &mut Vec<u32>) -> u32 {
+        let mut answer = match term.head {
+            Atom::Variable(i) => {
+                let i = i as usize;
+                if var_counts.len() <= i {
+                    var_counts.resize(i + 1, 0);
+                }
+                var_counts[i] += 1;
+                1
+            }
+            atom => self.weights.get(&atom).copied().unwrap_or(1),
+        };
+        for arg in &term.args {
+            answer += self.weight(arg, var_counts);
+        }
+        answer
+    }
+
+    // Compares the heads of two terms by precedence.
+    // Variables aren't comparable to anything but themselves.
+    fn precedence_cmp(&self, a: &Atom, b: &Atom) -> Ordering {
+        if a == b || matches!(a, Atom::Variable(_)) || matches!(b, Atom::Variable(_)) {
+            return Ordering::Equal;
+        }
+        let prec = |atom: &Atom| self.precedence.get(atom).copied().unwrap_or(0);
+        prec(a).cmp(&prec(b)).then_with(|| a.cmp(b))
+    }
+
+    // Returns Greater if s > t and Less if t > s.
+    // Like Term::kbo_cmp, Equal means that they cannot be ordered. The ordering is stable
+    // under substitution, so a rewrite from the greater term to the lesser one always makes
+    // terms smaller.
+    pub fn cmp(&self, s: &Term, t: &Term) -> Ordering {
+        let mut s_vars = vec![];
+        let s_weight = self.weight(s, &mut s_vars);
+        let mut t_vars = vec![];
+        let t_weight = self.weight(t, &mut t_vars);
+
+        // s can only be greater if it has every variable at least as often as t does.
+        let candidate = match s_weight.cmp(&t_weight) {
+            Ordering::Equal => self.equal_weight_cmp(s, t),
+            weight_cmp => weight_cmp,
+        };
+        match candidate {
+            Ordering::Greater if covers(&s_vars, &t_vars) => Ordering::Greater,
+            Ordering::Less if covers(&t_vars, &s_vars) => Ordering::Less,
+            _ => Ordering::Equal,
+        }
+    }
+
+    // The tiebreak between terms of equal weight: first by head precedence, then by the
+    // arguments from left to right.
+    fn equal_weight_cmp(&self, s: &Term, t: &Term) -> Ordering {
+        if s.head != t.head {
+            return self.precedence_cmp(&s.head, &t.head);
+        }
+        if s.args.len() != t.args.len() {
+            return Ordering::Equal;
+        }
+        for (s_arg, t_arg) in s.args.iter().zip(&t.args) {
+            if s_arg == t_arg {
+                continue;
+            }
+            return self.cmp(s_arg, t_arg);
+        }
+        Ordering::Equal
+    }
+}
+
+// Whether every variable occurs at least as many times in the first count as in the second.
+fn covers(counts: &[u32], other: &[u32]) -> bool {
+    other
+        .iter()
+        .enumerate()
+        .all(|(i, n)| counts.get(i).copied().unwrap_or(0) >= *n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atom::AtomId;
+
+    fn local(id: AtomId) -> Atom {
+        Atom::LocalConstant(id)
+    }
+
+    fn cmp(kbo: &Kbo, s: &str, t: &str) -> Ordering {
+        kbo.cmp(&Term::parse(s), &Term::parse(t))
+    }
+
+    #[test]
+    fn test_kbo_default_weights() {
+        let kbo = Kbo::new();
+        assert_eq!(cmp(&kbo, "c0(c1)", "c1"), Ordering::Greater);
+        assert_eq!(cmp(&kbo, "c0(x0)", "x0"), Ordering::Greater);
+        assert_eq!(cmp(&kbo, "x0", "x1"), Ordering::Equal);
+
+        // Neither side has all the variables of the other
+        assert_eq!(cmp(&kbo, "c0(x0)", "c0(x1)"), Ordering::Equal);
+        assert_eq!(cmp(&kbo, "c0(x0, c1)", "c2(x1)"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_kbo_weights_and_precedence() {
+        let mut kbo = Kbo::new();
+        kbo.set_weight(local(1), 5);
+        assert_eq!(cmp(&kbo, "c1", "c0(c2)"), Ordering::Greater);
+        assert_eq!(cmp(&kbo, "c0(c1)", "c0(c0(c0(c2)))"), Ordering::Greater);
+
+        // Equal weights fall back to precedence
+        assert_eq!(cmp(&kbo, "c2(c3)", "c4(c3)"), Ordering::Less);
+        kbo.set_precedence(local(2), 1);
+        assert_eq!(cmp(&kbo, "c2(c3)", "c4(c3)"), Ordering::Greater);
+        assert_eq!(cmp(&kbo, "c2(x0)", "c4(x0)"), Ordering::Greater);
+    }
+}
@@ -1,16 +1,24 @@
 pub mod acorn_type;
 pub mod acorn_value;
 pub mod active_set;
+pub mod arithmetic;
 pub mod atom;
+pub mod axiom_audit;
+pub mod benchmark;
 pub mod binding_map;
 pub mod block;
 pub mod builder;
 pub mod clause;
+pub mod clause_arena;
+pub mod clause_simplifier;
 pub mod code_gen_error;
+pub mod code_style;
 pub mod common;
 pub mod compilation;
+pub mod compiled_module;
 pub mod constant_map;
 pub mod dataset;
+pub mod discrimination_tree;
 pub mod display;
 pub mod environment;
 pub mod expression;
@@ -18,28 +26,45 @@ pub mod fact;
 pub mod features;
 pub mod fingerprint;
 pub mod goal;
+pub mod import_analysis;
 pub mod interfaces;
+pub mod kbo;
+pub mod limits;
 pub mod literal;
 pub mod live_document;
+pub mod match_coverage;
+pub mod model_finder;
 pub mod module;
+pub mod module_cache;
 pub mod monomorphizer;
+pub mod normalization_cache;
 pub mod normalizer;
 pub mod ort_model;
 pub mod passive_set;
+pub mod path_index;
 pub mod pattern_tree;
 pub mod project;
 pub mod proof;
+pub mod proof_checker;
+pub mod proof_record;
 pub mod proof_step;
 pub mod proposition;
 pub mod prover;
+pub mod prover_config;
+pub mod relevance;
 pub mod rewrite_tree;
 pub mod score;
 pub mod scorer;
+pub mod search_stats;
+pub mod smt;
 pub mod specializer;
 pub mod statement;
+pub mod subsumption;
 pub mod term;
 pub mod term_graph;
 pub mod termination_checker;
 pub mod token;
+pub mod tptp;
 pub mod type_map;
 pub mod unifier;
+pub mod value_visitor;
@@ -0,0 +1,140 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// Limits on how much work a proof search does before it gives up.
+// The time limit is the only one that depends on the machine, so the other limits are the
+// ones that make a search reproducible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchLimits {
+    pub time: Duration,
+
+    // How many clauses can be activated.
+    pub max_active: usize,
+
+    // How many clauses can be in the active and passive sets together.
+    pub max_clauses: Option<usize>,
+
+    // An estimate of how many bytes the generated clauses take up.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for SearchLimits {
+    // The limits for verification.
+    // The time limit is set high enough that hopefully it will not apply, because we don't
+    // want the result of verification to be machine-dependent.
+    fn default() -> SearchLimits {
+        SearchLimits {
+            time: Duration::from_secs(5),
+            max_active: 2000,
+            max_clauses: None,
+            max_memory: None,
+        }
+    }
+}
+
+impl SearchLimits {
+    // Replaces the limits that the overrides set.
+    pub fn with_overrides(&self, overrides: &LimitOverrides) -> SearchLimits {
+        SearchLimits {
+            time: overrides.time.unwrap_or(self.time),
+            max_active: overrides.max_active.unwrap_or(self.max_active),
+            max_clauses: overrides.max_clauses.or(self.max_clauses),
+            max_memory: overrides.max_memory.or(self.max_memory),
+        }
+    }
+
+    // The value of one of the limits, for reporting.
+    pub fn describe(&self, limit: Limit) -> String {
+        match limit {
+            Limit::Time => format!("{:.1}s", self.time.as_secs_f32()),
+            Limit::Active => self.max_active.to_string(),
+            Limit::Clauses => self
+                .max_clauses
+                .map_or("none".to_string(), |n| n.to_string()),
+            Limit::Memory => self
+                .max_memory
+                .map_or("none".to_string(), |n| format!("{}MB", n >> 20)),
+        }
+    }
+}
+
+// Limits set for a particular module or theorem, to replace the project-wide ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitOverrides {
+    pub time: Option<Duration>,
+    pub max_active: Option<usize>,
+    pub max_clauses: Option<usize>,
+    pub max_memory: Option<usize>,
+}
+
+impl LimitOverrides {
+    // Sets a limit from an attribute like "@max_seconds(20)".
+    // Memory is given in megabytes.
+    // Returns false if there is no limit with this name.
+    pub fn set(&mut self, name: &str, value: usize) -> bool {
+        match name {
+            "max_seconds" => self.time = Some(Duration::from_secs(value as u64)),
+            "max_active" => self.max_active = Some(value),
+            "max_clauses" => self.max_clauses = Some(value),
+            "max_memory_mb" => self.max_memory = Some(value << 20),
+            _ => return false,
+        }
+        true
+    }
+
+    // Layers the inner overrides on top of these ones, so the inner ones take precedence.
+    pub fn merge(&mut self, inner: &LimitOverrides) {
+        self.time = inner.time.or(self.time);
+        self.max_active = inner.max_active.or(self.max_active);
+        self.max_clauses = inner.max_clauses.or(self.max_clauses);
+        self.max_memory = inner.max_memory.or(self.max_memory);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == LimitOverrides::default()
+    }
+}
+
+// Which limit stopped a search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Time,
+    Active,
+    Clauses,
+    Memory,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Time => write!(f, "time limit"),
+            Limit::Active => write!(f, "activation limit"),
+            Limit::Clauses => write!(f, "clause limit"),
+            Limit::Memory => write!(f, "memory limit"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_overrides() {
+        let mut module = LimitOverrides::default();
+        assert!(module.set("max_active", 5000));
+        assert!(module.set("max_memory_mb", 100));
+        let mut theorem = LimitOverrides::default();
+        assert!(theorem.set("max_active", 100));
+        assert!(!theorem.set("max_bananas", 3));
+        module.merge(&theorem);
+
+        let limits = SearchLimits::default().with_overrides(&module);
+        assert_eq!(limits.max_active, 100);
+        assert_eq!(limits.max_memory, Some(100 << 20));
+        assert_eq!(limits.max_clauses, None);
+        assert_eq!(limits.time, Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+// The shape of a pattern in a match, ignoring the names of its variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternShape {
+    // A variable matches anything.
+    Variable,
+
+    // A constructor applied to subpatterns.
+    // The index of the constructor, the total number of constructors for its type,
+    // and the subpatterns for its arguments.
+    Constructor(usize, usize, Vec<PatternShape>),
+}
+
+impl PatternShape {
+    // Whether there is some value that matches both patterns.
+    pub fn overlaps(&self, other: &PatternShape) -> bool {
+        match (self, other) {
+            (PatternShape::Variable, _) | (_, PatternShape::Variable) => true,
+            (PatternShape::Constructor(i, _, args1), PatternShape::Constructor(j, _, args2)) => {
+                i == j && args1.iter().zip(args2).all(|(a1, a2)| a1.overlaps(a2))
+            }
+        }
+    }
+}
+
+// The rows of a pattern matrix that are left after we assume the first column was made
+// by the given constructor. The arguments of the constructor become new columns.
+fn specialize(
+    rows: &[Vec<PatternShape>],
+    constructor: usize,
+    arity: usize,
+) -> Vec<Vec<PatternShape>> {
+    let mut answer = vec![];
+    for row in rows {
+        let mut new_row = match &row[0] {
+            PatternShape::Variable => vec![PatternShape::Variable; arity],
+            PatternShape::Constructor(i, _, args) => {
+                if *i != constructor {
+                    continue;
+                }
+                args.clone()
+            }
+        };
+        new_row.extend(row[1..].iter().cloned());
+        answer.push(new_row);
+    }
+    answer
+}
+
+// Whether there is some value that matches the row, but none of the rows of the matrix.
+// This is the standard "usefulness" check for pattern matrices.
+fn is_useful(rows: &[Vec<PatternShape>], row: &[PatternShape]) -> bool {
+    if row.is_empty() {
+        return rows.is_empty();
+    }
+    let rest = &row[1..];
+    match &row[0] {
+        PatternShape::Constructor(i, _, args) => {
+            let mut new_row = args.clone();
+            new_row.extend(rest.iter().cloned());
+            is_useful(&specialize(rows, *i, args.len()), &new_row)
+        }
+        PatternShape::Variable => {
+            // Find the arity of each constructor used in the first column.
+            let mut arities = BTreeMap::new();
+            let mut total = 0;
+            for r in rows {
+                if let PatternShape::Constructor(i, t, args) = &r[0] {
+                    arities.insert(*i, args.len());
+                    total = *t;
+                }
+            }
+            if !arities.is_empty() && arities.len() == total {
+                // Every constructor appears, so we have to check each one.
+                return arities.iter().any(|(i, arity)| {
+                    let mut new_row = vec![PatternShape::Variable; *arity];
+                    new_row.extend(rest.iter().cloned());
+                    is_useful(&specialize(rows, *i, *arity), &new_row)
+                });
+            }
+
+            // Some constructor is missing, so only the rows that start with a variable can
+            // match it.
+            let default_rows: Vec<_> = rows
+                .iter()
+                .filter(|r| r[0] == PatternShape::Variable)
+                .map(|r| r[1..].to_vec())
+                .collect();
+            is_useful(&default_rows, rest)
+        }
+    }
+}
+
+// Tracks the cases of a match as they are added, to check that they don't overlap and
+// that together they cover every possible value.
+// Guarded cases are grouped with the other cases that have exactly the same shape.
+// A group is complete once it gets a case without a guard, which must come last.
+#[derive(Default)]
+pub struct MatchCoverage {
+    // The shape of each group of cases, and whether it is complete.
+    groups: Vec<(PatternShape, bool)>,
+}
+
+impl MatchCoverage {
+    pub fn new() -> MatchCoverage {
+        MatchCoverage { groups: vec![] }
+    }
+
+    // Adds a case, returning the index of the group it belongs to.
+    // Returns None if the case overlaps with an earlier case that it can't be grouped with.
+    pub fn add(&mut self, shape: PatternShape, guarded: bool) -> Option<usize> {
+        let mut group = None;
+        for (i, (group_shape, complete)) in self.groups.iter().enumerate() {
+            if !*complete && *group_shape == shape {
+                group = Some(i);
+            } else if group_shape.overlaps(&shape) {
+                return None;
+            }
+        }
+        let group = match group {
+            Some(i) => i,
+            None => {
+                self.groups.push((shape, false));
+                self.groups.len() - 1
+            }
+        };
+        if !guarded {
+            self.groups[group].1 = true;
+        }
+        Some(group)
+    }
+
+    // Whether every group of guarded cases ends with an unguarded case.
+    pub fn all_guards_have_fallbacks(&self) -> bool {
+        self.groups.iter().all(|(_, complete)| *complete)
+    }
+
+    // Whether the unguarded cases match every possible value.
+    pub fn is_exhaustive(&self) -> bool {
+        let rows: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|(_, complete)| *complete)
+            .map(|(shape, _)| vec![shape.clone()])
+            .collect();
+        !is_useful(&rows, &[PatternShape::Variable])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Shapes for a type like Nat, with zero as constructor 0 and suc as constructor 1.
+    fn zero() -> PatternShape {
+        PatternShape::Constructor(0, 2, vec![])
+    }
+
+    fn suc(arg: PatternShape) -> PatternShape {
+        PatternShape::Constructor(1, 2, vec![arg])
+    }
+
+    fn var() -> PatternShape {
+        PatternShape::Variable
+    }
+
+    #[test]
+    fn test_nested_coverage() {
+        let mut coverage = MatchCoverage::new();
+        assert_eq!(coverage.add(zero(), false), Some(0));
+        assert_eq!(coverage.add(suc(zero()), false), Some(1));
+        assert!(!coverage.is_exhaustive());
+        assert_eq!(coverage.add(suc(suc(var())), false), Some(2));
+        assert!(coverage.is_exhaustive());
+    }
+
+    #[test]
+    fn test_overlapping_cases() {
+        let mut coverage = MatchCoverage::new();
+        assert_eq!(coverage.add(suc(zero()), false), Some(0));
+        assert_eq!(coverage.add(suc(var()), false), None);
+        assert_eq!(coverage.add(suc(suc(var())), false), Some(1));
+        assert_eq!(coverage.add(suc(suc(zero())), false), None);
+    }
+
+    #[test]
+    fn test_guarded_coverage() {
+        let mut coverage = MatchCoverage::new();
+        assert_eq!(coverage.add(suc(var()), true), Some(0));
+        assert_eq!(coverage.add(zero(), false), Some(1));
+        assert!(!coverage.all_guards_have_fallbacks());
+        assert_eq!(coverage.add(suc(var()), true), Some(0));
+        assert_eq!(coverage.add(suc(var()), false), Some(0));
+        assert!(coverage.all_guards_have_fallbacks());
+        assert!(coverage.is_exhaustive());
+        assert_eq!(coverage.add(suc(var()), true), None);
+    }
+}
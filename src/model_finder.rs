@@ -0,0 +1,367 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::atom::Atom;
+use crate::clause::Clause;
+use crate::term::Term;
+use crate::type_map::{TypeId, BOOL};
+
+// A symbol is an atom applied to a particular number of arguments.
+pub type SymbolKey = (Atom, usize);
+
+// A finite interpretation of some clauses.
+// Each type has elements numbered from zero. Bool always has two, where 0 is false and 1 is true.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Model {
+    // The number of elements in each type, other than Bool.
+    pub sizes: BTreeMap<TypeId, usize>,
+
+    // For each symbol and number of arguments, the value on each tuple of arguments.
+    pub values: BTreeMap<SymbolKey, Vec<(Vec<usize>, usize)>>,
+
+    // The argument types and value type of each symbol.
+    pub signatures: BTreeMap<SymbolKey, (Vec<TypeId>, TypeId)>,
+}
+
+impl Model {
+    fn domain(&self, type_id: TypeId) -> Option<usize> {
+        if type_id == BOOL {
+            Some(2)
+        } else {
+            self.sizes.get(&type_id).copied()
+        }
+    }
+
+    fn evaluate(&self, term: &Term, assignment: &[usize]) -> Option<usize> {
+        match term.head {
+            Atom::Variable(i) if term.args.is_empty() => Some(assignment[i as usize]),
+            Atom::Variable(_) => None,
+            Atom::True => Some(1),
+            atom => {
+                let mut args = vec![];
+                for arg in &term.args {
+                    args.push(self.evaluate(arg, assignment)?);
+                }
+                let entries = self.values.get(&(atom, args.len()))?;
+                entries
+                    .iter()
+                    .find(|(entry_args, _)| *entry_args == args)
+                    .map(|(_, value)| *value)
+            }
+        }
+    }
+
+    // Whether the clause is true in this model, for every value of its variables.
+    // None if the clause uses symbols or types that the model doesn't interpret.
+    pub fn satisfies(&self, clause: &Clause) -> Option<bool> {
+        let mut domains = vec![];
+        for var_id in 0..clause.num_quantifiers() {
+            let var_type = clause.literals.iter().find_map(|l| l.var_type(var_id));
+            domains.push(match var_type {
+                Some(t) => self.domain(t)?,
+                None => 1,
+            });
+        }
+        for assignment in tuples(&domains) {
+            let mut satisfied = false;
+            for literal in &clause.literals {
+                let left = self.evaluate(&literal.left, &assignment)?;
+                let right = self.evaluate(&literal.right, &assignment)?;
+                if (left == right) == literal.positive {
+                    satisfied = true;
+                    break;
+                }
+            }
+            if !satisfied {
+                return Some(false);
+            }
+        }
+        Some(true)
+    }
+}
+
+// Searches for small finite models of a set of clauses.
+// Finding a model that includes the negated goal means that the goal is probably false.
+// It's only "probably", because the model only satisfies the clauses it was given, not
+// necessarily every fact the prover knows.
+#[derive(Clone, Debug)]
+pub struct ModelFinder {
+    // Every type other than Bool gets the same number of elements, up to this many.
+    pub max_size: usize,
+
+    // Limits on the size of the search, so that it always finishes quickly.
+    pub max_cells: usize,
+    pub max_instances: usize,
+    pub max_steps: usize,
+}
+
+impl Default for ModelFinder {
+    fn default() -> ModelFinder {
+        ModelFinder {
+            max_size: 3,
+            max_cells: 64,
+            max_instances: 10000,
+            max_steps: 100000,
+        }
+    }
+}
+
+impl ModelFinder {
+    // Returns None if there's no model within the limits.
+    pub fn find(&self, clauses: &[&Clause]) -> Option<Model> {
+        // Applying a variable as a function isn't something a finite table can handle.
+        if clauses.iter().any(|clause| {
+            clause
+                .literals
+                .iter()
+                .any(|l| applies_variable(&l.left) || applies_variable(&l.right))
+        }) {
+            return None;
+        }
+        for size in 1..=self.max_size {
+            if let Some(model) = Search::new(self, clauses, size)?.run() {
+                return Some(model);
+            }
+        }
+        None
+    }
+}
+
+struct Symbol {
+    atom: Atom,
+    arg_types: Vec<TypeId>,
+    value_type: TypeId,
+}
+
+// The state of the search for a model where every type has the same size.
+struct Search<'a> {
+    finder: &'a ModelFinder,
+    clauses: &'a [&'a Clause],
+    size: usize,
+    symbols: Vec<Symbol>,
+    symbol_ids: HashMap<SymbolKey, usize>,
+
+    // A cell is a symbol applied to particular arguments. Its value is what we search for.
+    cells: Vec<(usize, Vec<usize>)>,
+    cell_ids: HashMap<(usize, Vec<usize>), usize>,
+    values: Vec<Option<usize>>,
+
+    // Each clause with a value for each of its variables.
+    instances: Vec<(usize, Vec<usize>)>,
+    steps: usize,
+}
+
+impl<'a> Search<'a> {
+    // Returns None if the search would be too large.
+    fn new(finder: &'a ModelFinder, clauses: &'a [&'a Clause], size: usize) -> Option<Search<'a>> {
+        let mut search = Search {
+            finder,
+            clauses,
+            size,
+            symbols: vec![],
+            symbol_ids: HashMap::new(),
+            cells: vec![],
+            cell_ids: HashMap::new(),
+            values: vec![],
+            instances: vec![],
+            steps: 0,
+        };
+        for clause in clauses {
+            for literal in &clause.literals {
+                search.add_symbols(&literal.left);
+                search.add_symbols(&literal.right);
+            }
+        }
+
+        for symbol_id in 0..search.symbols.len() {
+            let domains: Vec<usize> = search.symbols[symbol_id]
+                .arg_types
+                .iter()
+                .map(|t| search.domain(*t))
+                .collect();
+            for args in tuples(&domains) {
+                if search.cells.len() >= finder.max_cells {
+                    return None;
+                }
+                search
+                    .cell_ids
+                    .insert((symbol_id, args.clone()), search.cells.len());
+                search.cells.push((symbol_id, args));
+            }
+        }
+        search.values = vec![None; search.cells.len()];
+
+        for (i, clause) in clauses.iter().enumerate() {
+            let domains: Vec<usize> = (0..clause.num_quantifiers())
+                .map(|var_id| {
+                    let var_type = clause.literals.iter().find_map(|l| l.var_type(var_id));
+                    var_type.map_or(1, |t| search.domain(t))
+                })
+                .collect();
+            for assignment in tuples(&domains) {
+                if search.instances.len() >= finder.max_instances {
+                    return None;
+                }
+                search.instances.push((i, assignment));
+            }
+        }
+        Some(search)
+    }
+
+    fn domain(&self, type_id: TypeId) -> usize {
+        if type_id == BOOL {
+            2
+        } else {
+            self.size
+        }
+    }
+
+    fn add_symbols(&mut self, term: &Term) {
+        if !term.head.is_variable() && term.head != Atom::True {
+            let key = (term.head, term.args.len());
+            if !self.symbol_ids.contains_key(&key) {
+                self.symbol_ids.insert(key, self.symbols.len());
+                self.symbols.push(Symbol {
+                    atom: term.head,
+                    arg_types: term.args.iter().map(|arg| arg.term_type).collect(),
+                    value_type: term.term_type,
+                });
+            }
+        }
+        for arg in &term.args {
+            self.add_symbols(arg);
+        }
+    }
+
+    // None means the value depends on a cell we haven't picked yet.
+    fn evaluate(&self, term: &Term, assignment: &[usize]) -> Option<usize> {
+        match term.head {
+            Atom::Variable(i) => Some(assignment[i as usize]),
+            Atom::True => Some(1),
+            atom => {
+                let mut args = vec![];
+                for arg in &term.args {
+                    args.push(self.evaluate(arg, assignment)?);
+                }
+                let symbol_id = self.symbol_ids[&(atom, term.args.len())];
+                self.values[self.cell_ids[&(symbol_id, args)]]
+            }
+        }
+    }
+
+    // Whether any instance is already false.
+    fn is_refuted(&self) -> bool {
+        self.instances.iter().any(|(i, assignment)| {
+            self.clauses[*i].literals.iter().all(|literal| {
+                let left = self.evaluate(&literal.left, assignment);
+                let right = self.evaluate(&literal.right, assignment);
+                match (left, right) {
+                    (Some(left), Some(right)) => (left == right) != literal.positive,
+                    _ => false,
+                }
+            })
+        })
+    }
+
+    fn extend(&mut self, next: usize) -> Option<bool> {
+        self.steps += 1;
+        if self.steps > self.finder.max_steps {
+            return None;
+        }
+        if self.is_refuted() {
+            return Some(false);
+        }
+        if next == self.cells.len() {
+            return Some(true);
+        }
+        let (symbol_id, _) = self.cells[next];
+        for value in 0..self.domain(self.symbols[symbol_id].value_type) {
+            self.values[next] = Some(value);
+            if self.extend(next + 1)? {
+                return Some(true);
+            }
+        }
+        self.values[next] = None;
+        Some(false)
+    }
+
+    fn run(mut self) -> Option<Model> {
+        if self.extend(0)? {
+            let mut model = Model {
+                sizes: BTreeMap::new(),
+                values: BTreeMap::new(),
+                signatures: BTreeMap::new(),
+            };
+            for (cell, value) in self.cells.iter().zip(&self.values) {
+                let (symbol_id, args) = cell;
+                let symbol = &self.symbols[*symbol_id];
+                let key = (symbol.atom, args.len());
+                for type_id in symbol.arg_types.iter().chain([&symbol.value_type]) {
+                    if *type_id != BOOL {
+                        model.sizes.insert(*type_id, self.size);
+                    }
+                }
+                model
+                    .signatures
+                    .insert(key, (symbol.arg_types.clone(), symbol.value_type));
+                model
+                    .values
+                    .entry(key)
+                    .or_default()
+                    .push((args.clone(), value.unwrap()));
+            }
+            Some(model)
+        } else {
+            None
+        }
+    }
+}
+
+fn applies_variable(term: &Term) -> bool {
+    (term.head.is_variable() && !term.args.is_empty()) || term.args.iter().any(applies_variable)
+}
+
+// Every tuple where each entry is less than the corresponding domain size.
+fn tuples(domains: &[usize]) -> Vec<Vec<usize>> {
+    let mut answer = vec![vec![]];
+    for domain in domains {
+        answer = answer
+            .into_iter()
+            .flat_map(|tuple| {
+                (0..*domain).map(move |value| {
+                    let mut tuple = tuple.clone();
+                    tuple.push(value);
+                    tuple
+                })
+            })
+            .collect();
+    }
+    answer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(clauses: &[&str]) -> Option<Model> {
+        let clauses: Vec<Clause> = clauses.iter().map(|s| Clause::parse(s)).collect();
+        let refs: Vec<&Clause> = clauses.iter().collect();
+        ModelFinder::default().find(&refs)
+    }
+
+    #[test]
+    fn test_model_finder() {
+        // Satisfiable once there are two elements
+        let model = find(&["c0(c1) = c2", "c1 != c2", "c0(x0) != x0"]).unwrap();
+        assert_eq!(model.sizes.values().copied().collect::<Vec<_>>(), vec![2]);
+
+        assert_eq!(
+            model.satisfies(&Clause::parse("c0(c0(x0)) = x0")),
+            Some(true)
+        );
+        assert_eq!(model.satisfies(&Clause::parse("c0(x0) = c1")), Some(false));
+        assert_eq!(model.satisfies(&Clause::parse("c3 = c1")), None);
+
+        // No finite model can satisfy these
+        assert_eq!(find(&["c0(x0) = c1", "c0(c2) != c1"]), None);
+    }
+}
@@ -2,6 +2,7 @@ use std::hash::{Hash, Hasher};
 use std::{fmt, path::PathBuf};
 
 use fxhash::FxHasher;
+use serde::{Deserialize, Serialize};
 
 use crate::compilation;
 use crate::environment::Environment;
@@ -68,7 +69,7 @@ impl Module {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ModuleHash {
     // There is one prefix hash per line in the file.
     // Each one hashes that line and all the lines before it.
@@ -122,6 +123,12 @@ impl ModuleHasher {
         }
     }
 
+    // Whether the source text matches the source text of the given hash.
+    // This ignores the dependencies, so it can be checked before they are loaded.
+    pub fn matches_source(&self, hash: &ModuleHash) -> bool {
+        self.prefix_hashes == hash.prefix_hashes
+    }
+
     // Should be called in an order that's consistent across different hashes of the same module
     pub fn add_dependency(&mut self, module: &Module) {
         if let Some(h) = &module.hash {
@@ -157,7 +164,7 @@ pub enum LoadState {
 }
 
 // A Descriptor expresses the different ways that a module user can specify a module.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ModuleDescriptor {
     // Anything that can't be referred to
     Anonymous,
@@ -169,6 +176,10 @@ pub enum ModuleDescriptor {
     // A filename.
     // This sort of module can be loaded by a project, but not referred to in code.
     File(PathBuf),
+
+    // A module declared with a "module" statement inside the file of its parent module.
+    // It's created when the parent is loaded, and only the parent can refer to it.
+    Nested(Box<ModuleDescriptor>, String),
 }
 
 impl fmt::Display for ModuleDescriptor {
@@ -177,6 +188,7 @@ impl fmt::Display for ModuleDescriptor {
             ModuleDescriptor::Anonymous => write!(f, "<anonymous>"),
             ModuleDescriptor::Name(name) => write!(f, "{}", name),
             ModuleDescriptor::File(path) => write!(f, "{}", path.display()),
+            ModuleDescriptor::Nested(parent, name) => write!(f, "{}.{}", parent, name),
         }
     }
 }
@@ -0,0 +1,41 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::module::ModuleHash;
+
+// What the build cache remembers about a module from the last time it was cleanly built.
+#[derive(Clone, Debug)]
+pub struct ModuleCache {
+    // The hash of the module when it was built.
+    pub hash: ModuleHash,
+
+    // For each goal, the premises that its minimized proof used.
+    // Keyed by the name of the goal rather than its line, since lines move around as the
+    // module is edited, and the premises are most useful for goals that need proving again.
+    // Goals that were proved without minimizing don't have an entry.
+    premises: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ModuleCache {
+    pub fn new(hash: ModuleHash) -> ModuleCache {
+        ModuleCache {
+            hash,
+            premises: BTreeMap::new(),
+        }
+    }
+
+    // Goals can share a name, so this adds to any premises already stored for the name.
+    pub fn add_premises(&mut self, goal_name: &str, premises: impl IntoIterator<Item = String>) {
+        self.premises
+            .entry(goal_name.to_string())
+            .or_default()
+            .extend(premises);
+    }
+
+    pub fn get_premises(&self, goal_name: &str) -> Option<&BTreeSet<String>> {
+        self.premises.get(goal_name)
+    }
+
+    pub fn has_premises(&self) -> bool {
+        !self.premises.is_empty()
+    }
+}
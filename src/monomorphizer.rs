@@ -1,6 +1,7 @@
-use std::collections::HashMap;
 use std::fmt;
 
+use im::{HashMap, Vector};
+
 use crate::acorn_type::AcornType;
 use crate::acorn_value::{AcornValue, ConstantInstance};
 use crate::constant_map::ConstantKey;
@@ -73,11 +74,12 @@ impl ConstantParams {
 }
 
 // A helper structure to determine which monomorphs are necessary.
+// It uses persistent collections, since it gets cloned along with the prover.
 #[derive(Clone)]
 pub struct Monomorphizer {
     // Facts that have some type variable in them.
     // The type variables aren't consistent between different facts.
-    generic_facts: Vec<Fact>,
+    generic_facts: Vector<Fact>,
 
     // This works like an output buffer.
     // Each output fact is fully monomorphized.
@@ -91,7 +93,7 @@ pub struct Monomorphizer {
     // we don't handle the case where it requires matching multiple constants
     // to determine a useful monomorphization for a fact.
     // But we might do more than these "single-shot" monomorphizations in the future.
-    instantiations_for_fact: Vec<Vec<FactParams>>,
+    instantiations_for_fact: Vector<Vec<FactParams>>,
 
     // The instantiations we have done for each constant.
     // Indexed by constant id.
@@ -108,9 +110,9 @@ pub struct Monomorphizer {
 impl Monomorphizer {
     pub fn new() -> Monomorphizer {
         Monomorphizer {
-            generic_facts: vec![],
+            generic_facts: Vector::new(),
             output_facts: vec![],
-            instantiations_for_fact: vec![],
+            instantiations_for_fact: Vector::new(),
             instantiations_for_constant: HashMap::new(),
             generic_constants: HashMap::new(),
         }
@@ -143,8 +145,8 @@ impl Monomorphizer {
             return;
         }
 
-        self.generic_facts.push(fact);
-        self.instantiations_for_fact.push(vec![]);
+        self.generic_facts.push_back(fact);
+        self.instantiations_for_fact.push_back(vec![]);
 
         // Store a reference to our generic constants in the index
         for c in generic_constants.clone() {
@@ -229,7 +231,7 @@ impl Monomorphizer {
         // Our goal is to find the "fact params", a way in which we can instantiate
         // the whole fact so that the instance params become the monomorph params.
         assert_eq!(generic_params.params.len(), monomorph_params.params.len());
-        let mut fact_params = HashMap::new();
+        let mut fact_params = std::collections::HashMap::new();
         for (generic_type, monomorph_type) in generic_params
             .params
             .iter()
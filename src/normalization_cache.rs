@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use dashmap::DashMap;
+
+use crate::acorn_type::AcornType;
+use crate::acorn_value::AcornValue;
+use crate::clause::Clause;
+use crate::normalizer::{Normalizer, PortableClauses};
+
+// Every goal gets its own prover, and the provers for a project see many of the same facts,
+// so normalizing facts is a lot of repeated work.
+// The cache keeps the clauses for each proposition in a form that any prover can import,
+// so that each proposition only needs to be normalized once per project.
+#[derive(Default)]
+pub struct NormalizationCache {
+    // None means that each normalizer has to normalize the proposition itself.
+    entries: DashMap<(AcornValue, bool), Option<Arc<PortableClauses>>>,
+}
+
+impl NormalizationCache {
+    pub fn new() -> NormalizationCache {
+        NormalizationCache::default()
+    }
+
+    // Returns the clauses for the proposition, in the ids of the provided normalizer.
+    // Returns None if the proposition can't be cached, in which case the caller should
+    // normalize it directly.
+    pub fn normalize(
+        &self,
+        normalizer: &mut Normalizer,
+        proposition: &AcornValue,
+        local: bool,
+    ) -> Option<Vec<Clause>> {
+        let key = (proposition.clone(), local);
+        let portable = match self.entries.get(&key) {
+            Some(entry) => entry.clone(),
+            None => {
+                let portable = if proposition.validate().is_ok()
+                    && proposition.get_type() == AcornType::Bool
+                {
+                    Normalizer::normalize_portable(proposition, local).map(Arc::new)
+                } else {
+                    None
+                };
+                self.entries.insert(key, portable.clone());
+                portable
+            }
+        }?;
+        Some(normalizer.import(&portable))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::environment::Environment;
+
+    use super::*;
+
+    #[test]
+    fn test_normalization_cache() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_zero(a: Nat) { add(a, zero) = a }
+            axiom add_suc(a: Nat, b: Nat) { add(a, suc(b)) = suc(add(a, b)) }
+            axiom suc_injective(a: Nat, b: Nat) { suc(a) = suc(b) -> a = b }
+            axiom has_pred(a: Nat) { a = zero or exists(b: Nat) { a = suc(b) } }
+            "#,
+        );
+        let names = ["add_suc", "add_zero", "suc_injective"];
+        let cache = NormalizationCache::new();
+
+        // Normalizing through the cache gives the same clauses as normalizing directly.
+        let mut direct = Normalizer::new();
+        let mut cached = Normalizer::new();
+        for name in names {
+            let claim = env.get_theorem_claim(name).unwrap();
+            let expected = direct.normalize(&claim, false).expect_clauses();
+            let actual = cache.normalize(&mut cached, &claim, false).unwrap();
+            assert_eq!(actual, expected);
+        }
+
+        // A normalizer that has seen different things first still gets its own ids.
+        let mut other = Normalizer::new();
+        for name in names.iter().rev() {
+            let claim = env.get_theorem_claim(name).unwrap();
+            let expected = other.clone().normalize(&claim, false).expect_clauses();
+            let actual = cache.normalize(&mut other, &claim, false).unwrap();
+            assert_eq!(actual, expected);
+        }
+        assert_eq!(cache.len(), 3);
+
+        // Skolems depend on the normalizer, so they don't get cached.
+        let claim = env.get_theorem_claim("has_pred").unwrap();
+        assert!(cache.normalize(&mut other, &claim, false).is_none());
+    }
+}
@@ -1,14 +1,21 @@
+use std::collections::{BTreeMap, HashMap};
+
 use crate::acorn_type::AcornType;
-use crate::acorn_value::{AcornValue, BinaryOp, FunctionApplication};
+use crate::acorn_value::{AcornValue, BinaryOp, ConstantInstance, FunctionApplication};
 use crate::atom::{Atom, AtomId};
 use crate::clause::Clause;
 use crate::constant_map::ConstantMap;
 use crate::display::DisplayClause;
 use crate::environment::Environment;
 use crate::literal::Literal;
-use crate::module::SKOLEM;
+use crate::module::{ModuleId, SKOLEM};
 use crate::term::Term;
-use crate::type_map::{TypeId, TypeMap};
+use crate::type_map::{TypeId, TypeMap, BOOL};
+use crate::value_visitor::{fold_children, ValueFolder};
+
+// When distributing an "or" over two conjunctions would make more clauses than this, one of
+// the conjunctions gets named instead, so the number of clauses stays linear.
+const MAX_DISTRIBUTED_CLAUSES: usize = 32;
 
 #[derive(Debug)]
 pub struct NormalizationError(pub String);
@@ -60,6 +67,23 @@ impl Normalization {
     }
 }
 
+// Clauses that don't depend on the state of any particular normalizer, so that they can be
+// shared between provers.
+// The clauses use the ids of a fresh normalizer, and the tables say what those ids refer to.
+#[derive(Debug)]
+pub struct PortableClauses {
+    clauses: Vec<Clause>,
+
+    // types[type_id] is the AcornType.
+    types: Vec<AcornType>,
+
+    // The constants, with their atoms.
+    constants: Vec<(Atom, ModuleId, String)>,
+
+    // monomorphs[monomorph_id] is the ConstantInstance.
+    monomorphs: Vec<ConstantInstance>,
+}
+
 #[derive(Clone)]
 pub struct Normalizer {
     // Types of the skolem functions produced
@@ -69,6 +93,21 @@ pub struct Normalizer {
     pub type_map: TypeMap,
 
     constant_map: ConstantMap,
+
+    // The names given to components of split clauses.
+    // Each name is a boolean skolem constant that implies its component.
+    component_names: HashMap<Clause, Atom>,
+
+    // The skolem functions that replace choice expressions.
+    // The key is the types on the stack, along with the choice itself, so that the same
+    // choice always gets the same skolem.
+    choices: BTreeMap<(Vec<AcornType>, AcornValue), AcornValue>,
+
+    // The skolem functions that replace each existential.
+    // The key is the types on the stack, along with the existential itself, so that the same
+    // existential always gets the same skolems. The provers for a module's goals are cloned
+    // from a common prover, so this lets them share skolems for the facts they have in common.
+    skolems: BTreeMap<(Vec<AcornType>, AcornValue), Vec<AcornValue>>,
 }
 
 impl Normalizer {
@@ -77,6 +116,9 @@ impl Normalizer {
             skolem_types: vec![],
             type_map: TypeMap::new(),
             constant_map: ConstantMap::new(),
+            component_names: HashMap::new(),
+            choices: BTreeMap::new(),
+            skolems: BTreeMap::new(),
         }
     }
 
@@ -88,6 +130,39 @@ impl Normalizer {
         AcornValue::new_constant(SKOLEM, name, vec![], acorn_type)
     }
 
+    // The constants that have been normalized so far, with their atoms.
+    pub fn constants(&self) -> impl Iterator<Item = (Atom, ModuleId, &str)> {
+        self.constant_map.iter()
+    }
+
+    // Whether this constant has been normalized so far.
+    pub fn has_constant(&self, module: ModuleId, name: &str) -> bool {
+        self.constant_map.has_constant(module, name)
+    }
+
+    // Returns a boolean term that names this component of a split clause, and whether the
+    // name is new. A component gets the same name each time, so that the clauses that
+    // share it also share whatever gets proven about it.
+    pub fn name_component(&mut self, component: &Clause) -> (Term, bool) {
+        if let Some(atom) = self.component_names.get(component) {
+            return (Term::atom(BOOL, *atom), false);
+        }
+        let atom = Atom::Skolem(self.skolem_types.len() as AtomId);
+        self.skolem_types.push(AcornType::Bool);
+        self.component_names.insert(component.clone(), atom);
+        (Term::atom(BOOL, atom), true)
+    }
+
+    // Returns a boolean term that is always false, and whether it's new.
+    // It's the name of the empty component, so its definition is just "not p".
+    pub fn false_term(&mut self) -> (Term, bool) {
+        self.name_component(&Clause::impossible())
+    }
+
+    pub fn is_false(&self, term: &Term) -> bool {
+        term.args.is_empty() && self.component_names.get(&Clause::impossible()) == Some(&term.head)
+    }
+
     pub fn is_skolem(&self, atom: &Atom) -> bool {
         matches!(atom, Atom::Skolem(_))
     }
@@ -119,21 +194,31 @@ impl Normalizer {
             }
 
             AcornValue::Exists(quants, subvalue) => {
-                // The current stack will be the arguments for the skolem functions
-                let mut args = vec![];
-                for (i, univ) in stack.iter().enumerate() {
-                    args.push(AcornValue::Variable(i as AtomId, univ.clone()));
-                }
+                // Find a skolem function for each of the quantifiers.
+                let key = (
+                    stack.clone(),
+                    AcornValue::Exists(quants.clone(), subvalue.clone()),
+                );
+                let skolem_fns = match self.skolems.get(&key) {
+                    Some(skolem_fns) => skolem_fns.clone(),
+                    None => {
+                        let skolem_fns: Vec<_> = quants
+                            .into_iter()
+                            .map(|quant| {
+                                let skolem_type = AcornType::new_functional(stack.clone(), quant);
+                                self.new_skolem_value(skolem_type)
+                            })
+                            .collect();
+                        self.skolems.insert(key, skolem_fns.clone());
+                        skolem_fns
+                    }
+                };
 
-                // Find a replacement for each of the quantifiers.
-                // Each one will be a skolem function applied to the current stack.
-                let mut replacements = vec![];
-                for quant in quants {
-                    let skolem_type = AcornType::new_functional(stack.clone(), quant);
-                    let skolem_fn = self.new_skolem_value(skolem_type);
-                    let replacement = AcornValue::new_apply(skolem_fn, args.clone());
-                    replacements.push(replacement);
-                }
+                // Each replacement is a skolem function applied to the current stack.
+                let replacements: Vec<_> = skolem_fns
+                    .iter()
+                    .map(|skolem_fn| apply_to_stack(skolem_fn, stack))
+                    .collect();
 
                 // Replace references to the existential quantifiers
                 let stack_size = stack.len() as AtomId;
@@ -261,18 +346,21 @@ impl Normalizer {
     // Returns Ok(Some(cnf)) if it can be turned into CNF.
     // Returns Ok(None) if it's an impossibility.
     // Returns an error if we failed in some user-reportable way.
+    // The definitions of any subformulas that get named are added to definitions, and
+    // they also need to be true.
     fn into_literal_lists(
         &mut self,
         value: &AcornValue,
         local: bool,
+        definitions: &mut Vec<Vec<Literal>>,
     ) -> Result<Option<Vec<Vec<Literal>>>> {
         match value {
             AcornValue::Binary(BinaryOp::And, left, right) => {
-                let mut left = match self.into_literal_lists(left, local)? {
+                let mut left = match self.into_literal_lists(left, local, definitions)? {
                     Some(left) => left,
                     None => return Ok(None),
                 };
-                let right = match self.into_literal_lists(right, local)? {
+                let right = match self.into_literal_lists(right, local, definitions)? {
                     Some(right) => right,
                     None => return Ok(None),
                 };
@@ -280,12 +368,20 @@ impl Normalizer {
                 Ok(Some(left))
             }
             AcornValue::Binary(BinaryOp::Or, left, right) => {
-                let left = self.into_literal_lists(left, local)?;
-                let right = self.into_literal_lists(right, local)?;
+                let left = self.into_literal_lists(left, local, definitions)?;
+                let right = self.into_literal_lists(right, local, definitions)?;
                 match (left, right) {
                     (None, None) => Ok(None),
                     (Some(result), None) | (None, Some(result)) => Ok(Some(result)),
-                    (Some(left), Some(right)) => {
+                    (Some(mut left), Some(mut right)) => {
+                        if left.len() * right.len() > MAX_DISTRIBUTED_CLAUSES {
+                            // Distributing would blow up, so name the bigger side instead.
+                            if left.len() > right.len() {
+                                left = vec![vec![self.name_subformula(left, definitions)]];
+                            } else {
+                                right = vec![vec![self.name_subformula(right, definitions)]];
+                            }
+                        }
                         let mut results = vec![];
                         for left_result in &left {
                             for right_result in &right {
@@ -311,12 +407,53 @@ impl Normalizer {
         }
     }
 
+    // Gives a conjunction of clauses a name, a new predicate on its variables, in the style of
+    // Tseitin. The name only appears positively, so its definition only needs to say that the
+    // name implies each clause.
+    fn name_subformula(
+        &mut self,
+        literal_lists: Vec<Vec<Literal>>,
+        definitions: &mut Vec<Vec<Literal>>,
+    ) -> Literal {
+        let mut var_types = BTreeMap::new();
+        for literal in literal_lists.iter().flatten() {
+            for i in 0..literal.num_quantifiers() {
+                if let Some(var_type) = literal.var_type(i) {
+                    var_types.insert(i, var_type);
+                }
+            }
+        }
+        let arg_types = var_types
+            .values()
+            .map(|t| self.type_map.get_type(*t).clone())
+            .collect();
+        let name_type = AcornType::new_functional(arg_types, AcornType::Bool);
+        let head = Atom::Skolem(self.skolem_types.len() as AtomId);
+        let head_type = self.type_map.add_type(&name_type);
+        self.skolem_types.push(name_type);
+        let args = var_types
+            .into_iter()
+            .map(|(i, var_type)| Term::atom(var_type, Atom::Variable(i)))
+            .collect();
+        let name = Term::new(BOOL, head_type, head, args);
+        for literals in literal_lists {
+            let mut definition = vec![Literal::negative(name.clone())];
+            definition.extend(literals);
+            definitions.push(definition);
+        }
+        Literal::positive(name)
+    }
+
     // Turns a value that is already in CNF into a Normalization
     fn normalize_cnf(&mut self, value: AcornValue, local: bool) -> Normalization {
         let mut universal = vec![];
         let value = value.remove_forall(&mut universal);
-        match self.into_literal_lists(&value, local) {
-            Ok(Some(lists)) => self.normalize_literal_lists(lists),
+        let mut definitions = vec![];
+        match self.into_literal_lists(&value, local, &mut definitions) {
+            Ok(Some(mut lists)) => {
+                lists.extend(definitions);
+                self.normalize_literal_lists(lists)
+            }
             Ok(None) => Normalization::Impossible,
             Err(NormalizationError(s)) => {
                 // value is essentially a subvalue with the universal quantifiers removed,
@@ -347,8 +484,9 @@ impl Normalizer {
         // println!("\nnormalizing: {}", value);
         let value = value.replace_function_equality(0);
         let value = value.expand_lambdas(0);
-        let value = value.replace_if();
-        let value = value.replace_match();
+        let value = lift_branches(value, 0, true);
+        // Lifting can leave a lambda branch applied to arguments.
+        let value = value.expand_lambdas(0);
         let value = value.move_negation_inwards(true, false);
         // println!("negin'd: {}", value);
         let value = self.skolemize(&vec![], value);
@@ -357,8 +495,131 @@ impl Normalizer {
         self.normalize_cnf(value, local)
     }
 
+    // Returns the skolem term that replaces a choice, given the types on the stack.
+    // The first time we see a choice, its defining property goes into definitions.
+    fn replace_choice(
+        &mut self,
+        stack: &[AcornType],
+        choice: AcornValue,
+        definitions: &mut Vec<AcornValue>,
+    ) -> AcornValue {
+        let key = (stack.to_vec(), choice);
+        let function = match self.choices.get(&key) {
+            Some(function) => function.clone(),
+            None => {
+                let (stack, choice) = key;
+                let skolem_type = AcornType::new_functional(stack.clone(), choice.get_type());
+                let skolem = self.new_skolem_value(skolem_type);
+                definitions.push(choice_definition(&stack, &choice, &skolem));
+                self.choices.insert((stack, choice), skolem.clone());
+                skolem
+            }
+        };
+        apply_to_stack(&function, stack)
+    }
+
     // Converts a value to CNF.
+    // Choice expressions are replaced by skolem functions first, and their definitions are
+    // normalized along with the value.
     pub fn normalize(&mut self, value: &AcornValue, local: bool) -> Normalization {
+        let mut replacer = ChoiceReplacer {
+            normalizer: self,
+            stack: vec![],
+            definitions: vec![],
+        };
+        if let Some((stack, choice_type, condition, constant)) = defined_choice(value) {
+            // A constant that is defined as a choice can be its own choice function.
+            // A skolem would work too, but then the prover would have to figure out that
+            // they are equal before it could use the choice.
+            replacer.stack = stack.clone();
+            replacer.stack.push(choice_type.clone());
+            let condition = replacer.fold_value(condition, stack.len() as AtomId + 1);
+            replacer.stack.clear();
+            let choice = AcornValue::Choose(choice_type.clone(), Box::new(condition));
+            let key = (stack, choice);
+            if !replacer.normalizer.choices.contains_key(&key) {
+                let definition = choice_definition(&key.0, &key.1, constant);
+                replacer.definitions.push(definition);
+                replacer.normalizer.choices.insert(key, constant.clone());
+            }
+        }
+        let value = replacer.fold_value(value, 0);
+        let definitions = replacer.definitions;
+        let mut answer = self.normalize_without_choices(&value, local);
+        for definition in definitions {
+            answer = answer.and(self.convert_then_normalize(&definition, local));
+        }
+        answer
+    }
+
+    // Normalizes a value with a fresh normalizer, so that the result can be imported into any
+    // normalizer.
+    // Returns None when the result could depend on what a normalizer has already seen, which
+    // happens with skolems, choices, and aliases. It also returns None when the value doesn't
+    // normalize to clauses.
+    pub fn normalize_portable(value: &AcornValue, local: bool) -> Option<PortableClauses> {
+        if let AcornValue::Binary(BinaryOp::Equals, left, right) = value {
+            if left.as_simple_constant().is_some() && right.as_simple_constant().is_some() {
+                return None;
+            }
+        }
+        let mut scratch = Normalizer::new();
+        let clauses = match scratch.normalize(value, local) {
+            Normalization::Clauses(clauses) => clauses,
+            _ => return None,
+        };
+        if !scratch.skolem_types.is_empty() || !scratch.choices.is_empty() {
+            return None;
+        }
+        Some(PortableClauses {
+            clauses,
+            types: scratch.type_map.types().to_vec(),
+            constants: scratch
+                .constants()
+                .map(|(atom, module, name)| (atom, module, name.to_string()))
+                .collect(),
+            monomorphs: scratch.type_map.monomorphs().cloned().collect(),
+        })
+    }
+
+    // Translates portable clauses into this normalizer's ids.
+    // Anything new gets an id in the same order as it would from normalizing the value here.
+    pub fn import(&mut self, portable: &PortableClauses) -> Vec<Clause> {
+        let types: Vec<TypeId> = portable
+            .types
+            .iter()
+            .map(|t| self.type_map.add_type(t))
+            .collect();
+        let mut atoms = HashMap::new();
+        for (atom, module, name) in &portable.constants {
+            let local = atom.is_local_constant();
+            atoms.insert(*atom, self.constant_map.add_constant(*module, name, local));
+        }
+        for (i, c) in portable.monomorphs.iter().enumerate() {
+            let term = self.type_map.term_from_monomorph(c);
+            atoms.insert(Atom::Monomorph(i as AtomId), term.head);
+        }
+        portable
+            .clauses
+            .iter()
+            .map(|clause| {
+                let literals = clause
+                    .literals
+                    .iter()
+                    .map(|literal| {
+                        Literal::new(
+                            literal.positive,
+                            import_term(&literal.left, &types, &atoms),
+                            import_term(&literal.right, &types, &atoms),
+                        )
+                    })
+                    .collect();
+                Clause::new(literals)
+            })
+            .collect()
+    }
+
+    fn normalize_without_choices(&mut self, value: &AcornValue, local: bool) -> Normalization {
         if let AcornValue::Binary(BinaryOp::Equals, left, right) = &value {
             // Check for defining one constant to equal another constant.
             if let Some((left_module, left_name)) = left.as_simple_constant() {
@@ -378,17 +639,25 @@ impl Normalizer {
                     }
                 }
             }
+        }
 
-            // Check for the sort of functional equality that can be represented as a literal.
-            if left.get_type().is_functional() && left.is_term() && right.is_term() {
-                // We want to represent this two ways.
-                // One as an equality between functions, another as an equality between
-                // primitive types, after applying the functions.
-                // If we handled functional types better in unification we might not need this.
-                let functional = self.normalize_cnf(value.clone(), local);
-                let primitive = self.convert_then_normalize(value, local);
-                return functional.and(primitive);
-            }
+        // Check for the sort of functional equality that can be represented as a literal.
+        if is_functional_literal(value) {
+            // We want to represent this two ways.
+            // One as an equality between functions, another as an equality between
+            // primitive types, after applying the functions.
+            // If we handled functional types better in unification we might not need this.
+            let functional = self.normalize_cnf(value.clone(), local);
+            let primitive = self.convert_then_normalize(value, local);
+            return functional.and(primitive);
+        }
+
+        // A pointwise equality between functions also gets represented as an equality between
+        // the functions themselves, so that it can be used to rewrite the functions as arguments.
+        if let Some(functional) = value.eta_reduce_equality() {
+            let pointwise = self.convert_then_normalize(value, local);
+            let functional = self.normalize_cnf(functional, local);
+            return pointwise.and(functional);
         }
 
         self.convert_then_normalize(value, local)
@@ -473,6 +742,15 @@ impl Normalizer {
         AcornValue::new_forall(var_types, answer)
     }
 
+    // Converts a term back to a value.
+    // Returns None if the term has variables, since they would be left unbound.
+    pub fn denormalize_term_value(&self, term: &Term) -> Option<AcornValue> {
+        if term.has_any_variable() {
+            return None;
+        }
+        Some(self.denormalize_term(term, &mut vec![]))
+    }
+
     pub fn atom_str(&self, atom: &Atom) -> String {
         match atom {
             Atom::True => "true".to_string(),
@@ -498,7 +776,11 @@ impl Normalizer {
         denormalized
             .validate()
             .expect("denormalized clause should validate");
-        let renormalized = self.normalize(&denormalized, true).expect_clauses();
+        let mut renormalized = self.normalize(&denormalized, true).expect_clauses();
+        if is_functional_literal(&denormalized) || denormalized.eta_reduce_equality().is_some() {
+            // Functional equalities are represented both pointwise and between functions.
+            renormalized.truncate(1);
+        }
         if renormalized.len() != 1 {
             println!("original clause: {}", clause);
             println!("denormalized: {}", denormalized);
@@ -547,6 +829,320 @@ impl Normalizer {
     }
 }
 
+// Replaces the type ids and atoms of a portable term.
+fn import_term(term: &Term, types: &[TypeId], atoms: &HashMap<Atom, Atom>) -> Term {
+    Term::new(
+        types[term.term_type as usize],
+        types[term.head_type as usize],
+        *atoms.get(&term.head).unwrap_or(&term.head),
+        term.args
+            .iter()
+            .map(|arg| import_term(arg, types, atoms))
+            .collect(),
+    )
+}
+
+// Replaces each choice expression with a skolem function applied to the whole stack.
+// This tracks the types on the stack, since they are the skolem's argument types.
+struct ChoiceReplacer<'a> {
+    normalizer: &'a mut Normalizer,
+    stack: Vec<AcornType>,
+
+    // The defining properties of any new skolem functions.
+    definitions: Vec<AcornValue>,
+}
+
+impl ValueFolder for ChoiceReplacer<'_> {
+    fn fold_value(&mut self, value: &AcornValue, stack_size: AtomId) -> AcornValue {
+        match value {
+            AcornValue::Lambda(args, _)
+            | AcornValue::ForAll(args, _)
+            | AcornValue::Exists(args, _) => {
+                self.stack.extend(args.iter().cloned());
+                let answer = fold_children(self, value, stack_size);
+                self.stack.truncate(stack_size as usize);
+                answer
+            }
+            AcornValue::Match(scrutinee, cases) => {
+                let new_scrutinee = self.fold_value(scrutinee, stack_size);
+                let mut new_cases = vec![];
+                for (new_vars, pattern, result) in cases {
+                    self.stack.extend(new_vars.iter().cloned());
+                    let new_stack_size = self.stack.len() as AtomId;
+                    let pattern = self.fold_value(pattern, new_stack_size);
+                    let result = self.fold_value(result, new_stack_size);
+                    self.stack.truncate(stack_size as usize);
+                    new_cases.push((new_vars.clone(), pattern, result));
+                }
+                AcornValue::Match(Box::new(new_scrutinee), new_cases)
+            }
+            AcornValue::Choose(choice_type, condition) => {
+                // Replace any choices within the condition first.
+                self.stack.push(choice_type.clone());
+                let condition = self.fold_value(condition, stack_size + 1);
+                self.stack.pop();
+                let choice = AcornValue::Choose(choice_type.clone(), Box::new(condition));
+                self.normalizer
+                    .replace_choice(&self.stack, choice, &mut self.definitions)
+            }
+            _ => fold_children(self, value, stack_size),
+        }
+    }
+}
+
+// The defining property of a function that makes a choice, given the types on the stack:
+//   forall(stack) { exists(y) { p(y) } -> p(function(stack)) }
+fn choice_definition(
+    stack: &[AcornType],
+    choice: &AcornValue,
+    function: &AcornValue,
+) -> AcornValue {
+    let AcornValue::Choose(choice_type, condition) = choice else {
+        panic!("expected a choice but got {}", choice);
+    };
+    let stack_size = stack.len() as AtomId;
+    let term = apply_to_stack(function, stack);
+    AcornValue::new_forall(
+        stack.to_vec(),
+        AcornValue::new_implies(
+            AcornValue::Exists(vec![choice_type.clone()], condition.clone()),
+            condition
+                .as_ref()
+                .clone()
+                .bind_values(stack_size, stack_size, &[term]),
+        ),
+    )
+}
+
+// Matches the definition of a constant as a choice, like
+//   forall(x0, x1) { c(x0, x1) = choose(y) { p(x0, x1, y) } }
+// Returns the stack types, the choice type, the choice's condition, and the constant.
+fn defined_choice(
+    value: &AcornValue,
+) -> Option<(Vec<AcornType>, &AcornType, &AcornValue, &AcornValue)> {
+    let (stack, equation) = match value {
+        AcornValue::ForAll(quants, equation) => (quants.clone(), equation.as_ref()),
+        _ => (vec![], value),
+    };
+    let AcornValue::Binary(BinaryOp::Equals, left, right) = equation else {
+        return None;
+    };
+    let AcornValue::Choose(choice_type, condition) = right.as_ref() else {
+        return None;
+    };
+    let constant = match left.as_ref() {
+        AcornValue::Application(app)
+            if app.args.len() == stack.len()
+                && app.args.iter().enumerate().all(
+                    |(i, arg)| matches!(arg, AcornValue::Variable(j, _) if *j == i as AtomId),
+                ) =>
+        {
+            app.function.as_ref()
+        }
+        constant if stack.is_empty() => constant,
+        _ => return None,
+    };
+    if !matches!(constant, AcornValue::Constant(_)) {
+        return None;
+    }
+    Some((stack, choice_type, condition, constant))
+}
+
+// Applies a function to every variable on the stack.
+fn apply_to_stack(function: &AcornValue, stack: &[AcornType]) -> AcornValue {
+    let args = stack
+        .iter()
+        .enumerate()
+        .map(|(i, t)| AcornValue::Variable(i as AtomId, t.clone()))
+        .collect();
+    AcornValue::new_apply(function.clone(), args)
+}
+
+// Lifts if-then-else and match out of the atoms that contain them, so that each branch becomes
+// its own clause rather than an opaque term. In a positive position,
+//   p(if c then a else b)
+// becomes
+//   (c -> p(a)) and (not c -> p(b))
+// and in a negative position it becomes
+//   (c and p(a)) or (not c and p(b))
+// so that the CNF conversion yields two clauses either way.
+// Other "if"s on the same condition are resolved within each branch, so a repeated condition
+// doesn't multiply the number of clauses.
+// A match is lifted the same way, with a branch for each case that quantifies over the
+// pattern's variables.
+fn lift_branches(value: AcornValue, stack_size: AtomId, positive: bool) -> AcornValue {
+    match value {
+        AcornValue::Not(x) => AcornValue::Not(Box::new(lift_branches(*x, stack_size, !positive))),
+        AcornValue::Binary(BinaryOp::Implies, left, right) => AcornValue::Binary(
+            BinaryOp::Implies,
+            Box::new(lift_branches(*left, stack_size, !positive)),
+            Box::new(lift_branches(*right, stack_size, positive)),
+        ),
+        AcornValue::Binary(op @ (BinaryOp::And | BinaryOp::Or), left, right) => AcornValue::Binary(
+            op,
+            Box::new(lift_branches(*left, stack_size, positive)),
+            Box::new(lift_branches(*right, stack_size, positive)),
+        ),
+        AcornValue::ForAll(quants, x) => {
+            let new_stack_size = stack_size + quants.len() as AtomId;
+            AcornValue::ForAll(
+                quants,
+                Box::new(lift_branches(*x, new_stack_size, positive)),
+            )
+        }
+        AcornValue::Exists(quants, x) => {
+            let new_stack_size = stack_size + quants.len() as AtomId;
+            AcornValue::Exists(
+                quants,
+                Box::new(lift_branches(*x, new_stack_size, positive)),
+            )
+        }
+        atom => {
+            let lifted = match find_branching(&atom) {
+                Some(AcornValue::IfThenElse(condition, _, _)) => {
+                    let condition = condition.as_ref().clone();
+                    let if_branch = IfResolver {
+                        condition: &condition,
+                        branch: true,
+                    }
+                    .fold_value(&atom, stack_size);
+                    let else_branch = IfResolver {
+                        condition: &condition,
+                        branch: false,
+                    }
+                    .fold_value(&atom, stack_size);
+                    if positive {
+                        AcornValue::new_and(
+                            AcornValue::new_implies(condition.clone(), if_branch),
+                            AcornValue::new_implies(condition.negate(), else_branch),
+                        )
+                    } else {
+                        AcornValue::new_or(
+                            AcornValue::new_and(condition.clone(), if_branch),
+                            AcornValue::new_and(condition.negate(), else_branch),
+                        )
+                    }
+                }
+                Some(AcornValue::Match(scrutinee, cases)) => {
+                    // The patterns are exhaustive and don't overlap, so the value is the result
+                    // for exactly one case, and exactly one assignment of its pattern variables.
+                    let target = AcornValue::Match(scrutinee.clone(), cases.clone());
+                    let mut branches = vec![];
+                    for (vars, pattern, result) in cases {
+                        let mut resolver = MatchResolver {
+                            target: &target,
+                            result,
+                            stack_size,
+                            num_vars: vars.len() as AtomId,
+                        };
+                        let branch = resolver.fold_value(&atom, stack_size);
+                        let equality = AcornValue::new_equals(*scrutinee.clone(), pattern.clone());
+                        branches.push(if positive {
+                            AcornValue::new_forall(
+                                vars.clone(),
+                                AcornValue::new_implies(equality, branch),
+                            )
+                        } else {
+                            AcornValue::new_exists(
+                                vars.clone(),
+                                AcornValue::new_and(equality, branch),
+                            )
+                        });
+                    }
+                    let op = if positive {
+                        BinaryOp::And
+                    } else {
+                        BinaryOp::Or
+                    };
+                    AcornValue::reduce(op, branches)
+                }
+                _ => return atom,
+            };
+            // The conditions and the branches may have more branching to lift.
+            lift_branches(lifted, stack_size, positive)
+        }
+    }
+}
+
+// Finds the outermost "if" or "match" in an atom.
+// Binders are skipped, because their variables can't be moved outside of them.
+fn find_branching(value: &AcornValue) -> Option<&AcornValue> {
+    match value {
+        AcornValue::IfThenElse(..) | AcornValue::Match(..) => Some(value),
+        AcornValue::Application(app) => {
+            find_branching(&app.function).or_else(|| app.args.iter().find_map(find_branching))
+        }
+        AcornValue::Binary(_, left, right) => {
+            find_branching(left).or_else(|| find_branching(right))
+        }
+        AcornValue::Not(x) => find_branching(x),
+        _ => None,
+    }
+}
+
+// Replaces each "if" on the given condition with one of its branches.
+// Stack indices are absolute, so the condition looks the same under any binder.
+struct IfResolver<'a> {
+    condition: &'a AcornValue,
+    branch: bool,
+}
+
+impl ValueFolder for IfResolver<'_> {
+    fn fold_value(&mut self, value: &AcornValue, stack_size: AtomId) -> AcornValue {
+        match value {
+            AcornValue::IfThenElse(condition, if_value, else_value)
+                if condition.as_ref() == self.condition =>
+            {
+                let branch = if self.branch { if_value } else { else_value };
+                self.fold_value(branch, stack_size)
+            }
+            _ => fold_children(self, value, stack_size),
+        }
+    }
+}
+
+// Replaces a match at the top of the atom with the result for one of its cases.
+// The case's pattern variables get quantified around the atom, so any variables that binders
+// within the atom introduce have to move up to make room for them.
+struct MatchResolver<'a> {
+    target: &'a AcornValue,
+    result: &'a AcornValue,
+    stack_size: AtomId,
+    num_vars: AtomId,
+}
+
+impl ValueFolder for MatchResolver<'_> {
+    fn fold_value(&mut self, value: &AcornValue, stack_size: AtomId) -> AcornValue {
+        if stack_size == self.stack_size && value == self.target {
+            return self.result.clone();
+        }
+        fold_children(self, value, stack_size)
+    }
+
+    fn fold_variable(
+        &mut self,
+        index: AtomId,
+        var_type: &AcornType,
+        _stack_size: AtomId,
+    ) -> AcornValue {
+        if index >= self.stack_size {
+            AcornValue::Variable(index + self.num_vars, var_type.clone())
+        } else {
+            AcornValue::Variable(index, var_type.clone())
+        }
+    }
+}
+
+// Whether this value is an equality between functions that can be represented as a literal.
+fn is_functional_literal(value: &AcornValue) -> bool {
+    match value {
+        AcornValue::Binary(BinaryOp::Equals, left, right) => {
+            left.get_type().is_functional() && left.is_term() && right.is_term()
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -733,6 +1329,146 @@ mod tests {
         norm.check(&env, "goal", &["zerof(x0, x1) = zerof(x2, x1)"]);
     }
 
+    #[test]
+    fn test_pointwise_functional_equality() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let addx: (Nat, Nat) -> Nat = axiom
+            let addy: (Nat, Nat) -> Nat = axiom
+            axiom add_eq(a: Nat, b: Nat) { addx(a, b) = addy(a, b) }
+            axiom add_zero_eq { forall(b: Nat) { addx(zero, b) = addy(zero, b) } }
+            axiom add_comm_eq(a: Nat, b: Nat) { addx(a, b) = addy(b, a) }
+            "#,
+        );
+        let mut norm = Normalizer::new();
+        norm.check(
+            &env,
+            "add_eq",
+            &["addy(x0, x1) = addx(x0, x1)", "addy = addx"],
+        );
+        norm.check(
+            &env,
+            "add_zero_eq",
+            &["addy(zero, x0) = addx(zero, x0)", "addy(zero) = addx(zero)"],
+        );
+        norm.check(&env, "add_comm_eq", &["addy(x0, x1) = addx(x1, x0)"]);
+    }
+
+    #[test]
+    fn test_normalizing_choice() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let ltx: (Nat, Nat) -> Bool = axiom
+            theorem goal(a: Nat) { ltx(a, choose b: Nat where ltx(a, b)) }
+            let next: Nat -> Nat = axiom
+            axiom next_def(a: Nat) { next(a) = choose b: Nat where ltx(b, a) }
+            "#,
+        );
+        let mut norm = Normalizer::new();
+        norm.check(
+            &env,
+            "goal",
+            &["ltx(x0, s0(x0))", "not ltx(x0, x1) or ltx(x0, s0(x0))"],
+        );
+        norm.check(&env, "next_def", &["not ltx(x0, x1) or ltx(next(x1), x1)"]);
+    }
+
+    #[test]
+    fn test_lifting_ifs() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let p: Nat -> Bool = axiom
+            let f: Nat -> Nat = axiom
+            let g: (Nat, Nat) -> Nat = axiom
+            theorem nested(a: Nat, b: Nat) {
+                f(if p(a) { if p(b) { a } else { b } } else { zero }) = zero
+            }
+            theorem quantified { exists(x: Nat) { f(if p(x) { x } else { zero }) = x } }
+            theorem negated(a: Nat) { f(if p(a) { a } else { zero }) != a }
+            theorem repeated(a: Nat) { g(if p(a) { a } else { zero }, if p(a) { zero } else { a }) = a }
+            theorem functional(a: Nat) { (if p(a) { p } else { function(x: Nat) { p(f(x)) } })(a) }
+            "#,
+        );
+        let mut norm = Normalizer::new();
+        norm.check(
+            &env,
+            "nested",
+            &[
+                "not p(x0) or not p(x1) or zero = f(x0)",
+                "not p(x0) or zero = f(x1) or p(x1)",
+                "f(zero) = zero or p(x0)",
+            ],
+        );
+        norm.check(
+            &env,
+            "quantified",
+            &["not p(s0) or f(s0) = s0", "f(zero) = s0 or p(s0)"],
+        );
+        norm.check(
+            &env,
+            "negated",
+            &["f(x0) != x0 or not p(x0)", "f(zero) != x0 or p(x0)"],
+        );
+        norm.check(
+            &env,
+            "repeated",
+            &["not p(x0) or g(x0, zero) = x0", "g(zero, x0) = x0 or p(x0)"],
+        );
+        norm.check(
+            &env,
+            "functional",
+            &["not p(x0) or p(x0)", "p(f(x0)) or p(x0)"],
+        );
+    }
+
+    #[test]
+    fn test_lifting_match() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            let f: Nat -> Nat = axiom
+            let p: Nat -> Bool = axiom
+            theorem arg(n: Nat) { f(match n { Nat.zero { Nat.zero } Nat.suc(k) { k } }) = n }
+            theorem prop(n: Nat) { match n { Nat.zero { p(n) } Nat.suc(k) { p(k) } } }
+            theorem negated(n: Nat) { not p(match n { Nat.zero { n } Nat.suc(k) { k } }) }
+            "#,
+        );
+        let mut norm = Normalizer::new();
+        norm.check(
+            &env,
+            "arg",
+            &[
+                "Nat.zero != x0 or f(Nat.zero) = x0",
+                "Nat.suc(x0) != x1 or f(x0) = x1",
+            ],
+        );
+        norm.check(
+            &env,
+            "prop",
+            &["Nat.zero != x0 or p(x0)", "Nat.suc(x0) != x1 or p(x0)"],
+        );
+        norm.check(
+            &env,
+            "negated",
+            &[
+                "not p(x0) or Nat.zero != x0",
+                "not p(x0) or Nat.suc(x0) != x1",
+            ],
+        );
+    }
+
     #[test]
     fn test_normalizing_exists() {
         let mut env = Environment::new_test();
@@ -749,6 +1485,76 @@ mod tests {
         norm.check(&env, "goal", &["addx(s0, zero) = one"]);
     }
 
+    #[test]
+    fn test_sharing_skolems_for_the_same_exists() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let one: Nat = axiom
+            let addx: (Nat, Nat) -> Nat = axiom
+            theorem goal { exists(x: Nat) { addx(x, zero) = one } }
+            theorem again { zero = one or exists(x: Nat) { addx(x, zero) = one } }
+            "#,
+        );
+        let mut norm = Normalizer::new();
+        norm.check(&env, "goal", &["addx(s0, zero) = one"]);
+        norm.check(&env, "again", &["addx(s0, zero) = one or one = zero"]);
+    }
+
+    #[test]
+    fn test_naming_subformulas() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let one: Nat = axiom
+            let p: Nat -> Bool = axiom
+            let q: Nat -> Bool = axiom
+            let r: Nat -> Bool = axiom
+            let s: Nat -> Bool = axiom
+            theorem small(a: Nat) { (p(a) and q(a)) or (r(a) and s(a)) }
+            theorem big(a: Nat) {
+                (p(a) and p(zero) and p(one) and q(a) and q(zero) and q(one)) or
+                (r(a) and r(zero) and r(one) and s(a) and s(zero) and s(one))
+            }
+            "#,
+        );
+        let mut norm = Normalizer::new();
+        norm.check(
+            &env,
+            "small",
+            &[
+                "r(x0) or p(x0)",
+                "s(x0) or p(x0)",
+                "r(x0) or q(x0)",
+                "s(x0) or q(x0)",
+            ],
+        );
+
+        // Distributing this one would make 36 clauses, so the right side gets a name.
+        norm.check(
+            &env,
+            "big",
+            &[
+                "s0(x0) or p(x0)",
+                "p(zero) or s0(x0)",
+                "p(one) or s0(x0)",
+                "q(x0) or s0(x0)",
+                "q(zero) or s0(x0)",
+                "q(one) or s0(x0)",
+                "not s0(x0) or r(x0)",
+                "not s0(x0) or r(zero)",
+                "not s0(x0) or r(one)",
+                "not s0(x0) or s(x0)",
+                "not s0(x0) or s(zero)",
+                "not s0(x0) or s(one)",
+            ],
+        );
+    }
+
     #[test]
     fn test_denormalizing_disjunction() {
         let mut env = Environment::new_test();
@@ -2,29 +2,50 @@ use crate::clause::Clause;
 use crate::features::Features;
 use crate::fingerprint::FingerprintSpecializer;
 use crate::literal::Literal;
-use crate::proof_step::ProofStep;
+use crate::proof_step::{ProofStep, ProofStepId};
 use crate::score::Score;
 use crate::scorer::{default_scorer, Scorer};
 use crate::specializer::Specializer;
 use crate::term::Term;
-use std::collections::hash_map::Entry;
-use std::collections::{BTreeSet, HashMap};
+use im::hashmap::Entry;
+use im::{HashMap, OrdSet, Vector};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // The PassiveSet stores a bunch of clauses.
 // A clause in the passive set can be activated, and it can be simplified, but to do
 // anything more complicated it needs to be activated first.
+// The collections are persistent, so that cloning the passive set is cheap, since the prover
+// gets cloned for every nested block.
 #[derive(Clone)]
 pub struct PassiveSet {
     // Stores clauses in the passive set, along with their score.
     // We never shrink this vector, we just replace its entries with None.
     // The index into clauses acts like an id, but that id doesn't mean anything outside of the
     // PassiveSet.
-    clauses: Vec<Option<(ProofStep, Score)>>,
+    clauses: Vector<Option<(ProofStep, Score)>>,
 
     // Stores (score, clause id).
     // The queue lets us pick the highest-scoring clause to activate next.
-    queue: BTreeSet<(Score, usize)>,
+    // Every clause in the passive set is in this queue.
+    queue: OrdSet<(Score, usize)>,
+
+    // Decides which queue the next clause comes from.
+    schedule: PassiveSchedule,
+
+    // How many clauses have been popped according to the schedule.
+    scheduled_pops: usize,
+
+    // The other queues only get entries when the schedule uses them.
+    // They aren't cleaned up when clauses leave the passive set, so when we pop from them
+    // we need to check that the clause is still there.
+
+    // The ids of the clauses, which are in the order they were added.
+    by_age: OrdSet<usize>,
+
+    // Stores (score, clause id) for the clauses that are a single positive equality.
+    unit_equalities: OrdSet<(Score, usize)>,
 
     // Stores (clause id, literal index) for each literal in each passive clause.
     // We currently don't clean this up by removing old clause ids, so when we retrieve from
@@ -48,6 +69,91 @@ pub struct PassiveSet {
     // For now this doesn't really matter, but maybe in the future the scorer will have a large model,
     // some affiliated GPU state, something like that.
     scorer: Arc<dyn Scorer + Send + Sync>,
+
+    // The clauses generated from each active clause, keyed by the active id.
+    // This isn't cleaned up when clauses leave the passive set, so the ids may be stale.
+    children: HashMap<usize, Vec<usize>>,
+
+    // An estimate of the bytes taken by every clause that has been added, other than the
+    // orphans that were dropped.
+    // Activated clauses come from here, so this covers them too.
+    memory: usize,
+
+    // How many batches have been scored, and how long scoring took.
+    scorer_calls: usize,
+    scoring_time: Duration,
+}
+
+// The contents of a passive set, in a form that can be saved and restored later.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PassiveCheckpoint {
+    // The clauses, in the order they were added, along with their scores.
+    steps: Vec<(ProofStep, Score)>,
+
+    scheduled_pops: usize,
+    verification_phase: bool,
+}
+
+// A queue that the passive set can pick the next clause from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PassiveQueue {
+    // The highest-scoring clause.
+    Score,
+
+    // The clause that has been waiting the longest.
+    // This makes sure that every clause gets activated eventually.
+    Age,
+
+    // The highest-scoring clause that is a single positive equality, like "a = b".
+    // These are the clauses that can be used for rewriting.
+    UnitEquality,
+}
+
+// How the passive set picks which clause to activate next.
+// It cycles through the queues in order, picking from each one as many times as its weight.
+// So [(Score, 4), (Age, 1)] picks the four highest-scoring clauses, then the oldest one.
+// When a queue is empty, the highest-scoring clause is picked instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassiveSchedule {
+    pub queues: Vec<(PassiveQueue, u32)>,
+}
+
+impl Default for PassiveSchedule {
+    // Always picks the highest-scoring clause.
+    fn default() -> PassiveSchedule {
+        PassiveSchedule {
+            queues: vec![(PassiveQueue::Score, 1)],
+        }
+    }
+}
+
+impl PassiveSchedule {
+    // The classic "age-weight ratio", mixing score-based picks with oldest-first picks.
+    pub fn age_weight_ratio(age: u32, weight: u32) -> PassiveSchedule {
+        PassiveSchedule {
+            queues: vec![(PassiveQueue::Age, age), (PassiveQueue::Score, weight)],
+        }
+    }
+
+    fn uses(&self, queue: PassiveQueue) -> bool {
+        self.queues.iter().any(|(q, w)| *q == queue && *w > 0)
+    }
+
+    // Which queue to use for the nth pick.
+    fn queue_for(&self, n: usize) -> PassiveQueue {
+        let total: usize = self.queues.iter().map(|(_, w)| *w as usize).sum();
+        if total == 0 {
+            return PassiveQueue::Score;
+        }
+        let mut n = n % total;
+        for (queue, weight) in &self.queues {
+            if n < *weight as usize {
+                return *queue;
+            }
+            n -= *weight as usize;
+        }
+        unreachable!()
+    }
 }
 
 // Whether (left1, right2) can be specialized to get (left2, right2).
@@ -101,26 +207,81 @@ fn make_simplified(
     Some(Clause::new(new_literals))
 }
 
+// An estimate of the bytes that storing this step takes.
+fn step_memory(step: &ProofStep) -> usize {
+    std::mem::size_of::<(ProofStep, Score)>()
+        + step.clause.atom_count() as usize * std::mem::size_of::<Term>()
+}
+
 impl PassiveSet {
     pub fn new() -> PassiveSet {
+        PassiveSet::with_scorer(default_scorer().into())
+    }
+
+    fn with_scorer(scorer: Arc<dyn Scorer + Send + Sync>) -> PassiveSet {
         PassiveSet {
-            clauses: vec![],
-            queue: BTreeSet::new(),
+            clauses: Vector::new(),
+            queue: OrdSet::new(),
+            schedule: PassiveSchedule::default(),
+            scheduled_pops: 0,
+            by_age: OrdSet::new(),
+            unit_equalities: OrdSet::new(),
             literals: FingerprintSpecializer::new(),
             singles: HashMap::new(),
             contradiction: None,
             verification_phase: true,
-            scorer: default_scorer().into(),
+            scorer,
+            children: HashMap::new(),
+            memory: 0,
+            scorer_calls: 0,
+            scoring_time: Duration::ZERO,
         }
     }
 
+    // This should be set before any steps are added, since the queues are filled as steps
+    // are added.
+    pub fn set_schedule(&mut self, schedule: PassiveSchedule) {
+        self.schedule = schedule;
+    }
+
+    // Like the schedule, this should be set before any steps are added, since steps are
+    // scored as they are added.
+    pub fn set_scorer(&mut self, scorer: Arc<dyn Scorer + Send + Sync>) {
+        self.scorer = scorer;
+    }
+
+    pub fn checkpoint(&self) -> PassiveCheckpoint {
+        PassiveCheckpoint {
+            steps: self.clauses.iter().flatten().cloned().collect(),
+            scheduled_pops: self.scheduled_pops,
+            verification_phase: self.verification_phase,
+        }
+    }
+
+    // Replaces everything in the passive set with the contents of a checkpoint.
+    // The schedule and the scorer stay as they are. The stored scores get used, rather than
+    // scoring the clauses again.
+    pub fn restore(&mut self, checkpoint: PassiveCheckpoint) {
+        let mut restored = PassiveSet::with_scorer(self.scorer.clone());
+        restored.schedule = self.schedule.clone();
+        for (step, score) in checkpoint.steps {
+            restored.push_with_score(step, score);
+        }
+        restored.scheduled_pops = checkpoint.scheduled_pops;
+        restored.verification_phase = checkpoint.verification_phase;
+        *self = restored;
+    }
+
     // Adding many new steps at once.
     pub fn push_batch(&mut self, steps: Vec<ProofStep>) {
         if steps.is_empty() {
             return;
         }
+        let start = Instant::now();
         let features = steps.iter().map(Features::new).collect::<Vec<_>>();
         let scores = Score::batch(self.scorer.as_ref(), &features);
+        self.scorer_calls += 1;
+        self.scoring_time += start.elapsed();
         for (step, score) in steps.into_iter().zip(scores.into_iter()) {
             self.push_with_score(step, score);
         }
@@ -151,7 +312,23 @@ impl PassiveSet {
                 }
             }
         }
-        self.clauses.push(Some((step, score)));
+        if self.schedule.uses(PassiveQueue::Age) {
+            self.by_age.insert(id);
+        }
+        if self.schedule.uses(PassiveQueue::UnitEquality)
+            && step.clause.literals.len() == 1
+            && step.clause.literals[0].positive
+            && !step.clause.literals[0].is_boolean()
+        {
+            self.unit_equalities.insert((score, id));
+        }
+        for parent in step.rule.premises() {
+            if let ProofStepId::Active(parent) = parent {
+                self.children.entry(parent).or_default().push(id);
+            }
+        }
+        self.memory += step_memory(&step);
+        self.clauses.push_back(Some((step, score)));
         self.queue.insert((score, id));
     }
 
@@ -161,7 +338,7 @@ impl PassiveSet {
         if !self.verification_phase {
             return false;
         }
-        if let Some((score, _)) = self.queue.iter().next_back() {
+        if let Some((score, _)) = self.queue.get_max() {
             score.is_usable_for_verification()
         } else {
             false
@@ -169,8 +346,10 @@ impl PassiveSet {
     }
 
     pub fn pop(&mut self) -> Option<ProofStep> {
-        // Remove the largest entry from queue
-        let (score, id) = self.queue.pop_last()?;
+        let (score, id) = match self.pop_scheduled() {
+            Some(entry) => entry,
+            None => self.queue.remove_max()?,
+        };
         if !score.is_usable_for_verification() {
             self.verification_phase = false;
         }
@@ -180,6 +359,31 @@ impl PassiveSet {
         }
     }
 
+    // Removes the entry for the clause that the schedule picks, from the queue and from the
+    // schedule's queue.
+    // Returns None when the highest-scoring clause should be picked instead.
+    // While we're still verifying, we always pick by score, so that a proof found by the
+    // verification search doesn't depend on the schedule.
+    fn pop_scheduled(&mut self) -> Option<(Score, usize)> {
+        if self.verification_phase {
+            return None;
+        }
+        let queue = self.schedule.queue_for(self.scheduled_pops);
+        self.scheduled_pops += 1;
+        loop {
+            let id = match queue {
+                PassiveQueue::Score => return None,
+                PassiveQueue::Age => self.by_age.remove_min()?,
+                PassiveQueue::UnitEquality => self.unit_equalities.remove_max()?.1,
+            };
+            if let Some((_, score)) = &self.clauses[id] {
+                let entry = (*score, id);
+                self.queue.remove(&entry);
+                return Some(entry);
+            }
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
@@ -261,6 +465,19 @@ impl PassiveSet {
         }
     }
 
+    // Forgets the contradiction, so that the search can look for another proof.
+    // The two clauses stay in the queue, but they won't be reported as a contradiction again.
+    pub fn clear_contradiction(&mut self) {
+        let Some((id, _)) = self.contradiction.take() else {
+            return;
+        };
+        if let Some((step, _)) = &self.clauses[id] {
+            let literal = &step.clause.literals[0];
+            self.singles
+                .remove(&(literal.left.clone(), literal.right.clone()));
+        }
+    }
+
     // Called when we activate a new true literal.
     // Simplifies the passive set by removing literals that are now known to be true.
     // Checks both directions.
@@ -280,11 +497,69 @@ impl PassiveSet {
         }
     }
 
+    // Called when an active clause becomes redundant.
+    // The clauses it generated are redundant too, since whatever made it redundant can
+    // generate them, or something better. So we drop them.
+    // Clauses that were only simplified by it aren't affected.
+    // Returns the steps that were dropped.
+    pub fn remove_orphans(&mut self, active_id: usize) -> Vec<ProofStep> {
+        let mut orphans = vec![];
+        let Some(children) = self.children.remove(&active_id) else {
+            return orphans;
+        };
+        for id in children {
+            if let Some((id1, id2)) = self.contradiction {
+                if id == id1 || id == id2 {
+                    // We still need this one to report the contradiction.
+                    continue;
+                }
+            }
+            let Some((step, score)) = self.clauses[id].take() else {
+                continue;
+            };
+            self.queue.remove(&(score, id));
+            if step.clause.literals.len() == 1 {
+                let literal = &step.clause.literals[0];
+                let key = (literal.left.clone(), literal.right.clone());
+                if self.singles.get(&key).map(|(_, single_id)| *single_id) == Some(id) {
+                    self.singles.remove(&key);
+                }
+            }
+            self.memory -= step_memory(&step);
+            orphans.push(step);
+        }
+        orphans
+    }
+
+    // Only the dropped orphans get freed during a search, so this mostly goes up.
+    pub fn approximate_memory(&self) -> usize {
+        self.memory
+    }
+
     // The number of clauses remaining in the passive set.
     pub fn len(&self) -> usize {
         self.queue.len()
     }
 
+    pub fn scorer_calls(&self) -> usize {
+        self.scorer_calls
+    }
+
+    pub fn scoring_time(&self) -> Duration {
+        self.scoring_time
+    }
+
+    // Like iter_steps, but with the scores.
+    pub fn iter_scored_steps(&self) -> impl Iterator<Item = (&ProofStep, &Score)> {
+        self.queue
+            .iter()
+            .rev()
+            .map(|(_, id)| match &self.clauses[*id] {
+                Some((step, score)) => (step, score),
+                None => panic!("Queue and clauses are out of sync"),
+            })
+    }
+
     // Iterates over the steps from highest-scoring to lowest-scoring.
     pub fn iter_steps(&self) -> impl Iterator<Item = &ProofStep> {
         self.queue
@@ -308,6 +583,24 @@ impl PassiveSet {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::proof_step::Rule;
+
+    #[test]
+    fn test_passive_schedule() {
+        let schedule = PassiveSchedule::age_weight_ratio(1, 2);
+        let queues: Vec<_> = (0..4).map(|n| schedule.queue_for(n)).collect();
+        assert_eq!(
+            queues,
+            vec![
+                PassiveQueue::Age,
+                PassiveQueue::Score,
+                PassiveQueue::Score,
+                PassiveQueue::Age
+            ]
+        );
+        assert!(schedule.uses(PassiveQueue::Age));
+        assert!(!schedule.uses(PassiveQueue::UnitEquality));
+    }
 
     #[test]
     fn test_passive_set_simplification() {
@@ -318,4 +611,21 @@ mod tests {
         let step = passive_set.pop().unwrap();
         assert_eq!(step.clause.to_string(), "<empty>");
     }
+
+    #[test]
+    fn test_removing_orphans() {
+        let mut passive_set = PassiveSet::new();
+        let parent = ProofStep::mock("c0(x0) or c1(x0)");
+        let child =
+            |id, s| ProofStep::new_direct(&parent, Rule::EqualityResolution(id), Clause::parse(s));
+        passive_set.push_batch(vec![child(3, "c0(c2)"), child(4, "c0(c3)")]);
+        passive_set.push_batch(vec![child(3, "c1(c2)")]);
+        assert_eq!(passive_set.remove_orphans(3).len(), 2);
+        assert_eq!(passive_set.len(), 1);
+
+        // The dropped clause doesn't contradict new ones
+        passive_set.push_batch(vec![child(4, "not c0(c2)")]);
+        assert!(passive_set.get_contradiction().is_none());
+        assert_eq!(passive_set.len(), 2);
+    }
 }
@@ -0,0 +1,164 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use im::{HashMap, OrdSet};
+
+use crate::atom::Atom;
+use crate::clause::Clause;
+use crate::term::Term;
+use crate::type_map::TypeId;
+
+// A symbol at a particular path, relative to the root of a subterm.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct PathKey {
+    path: Vec<usize>,
+    term_type: TypeId,
+    head: Atom,
+    num_args: usize,
+}
+
+// Calls f with a key for each position in the term that has a symbol.
+// We don't look inside applied variables, because the arguments of an applied variable
+// don't line up with the arguments of its instances.
+fn for_each_key(term: &Term, path: &mut Vec<usize>, f: &mut impl FnMut(PathKey)) {
+    if term.head.is_variable() {
+        return;
+    }
+    f(PathKey {
+        path: path.clone(),
+        term_type: term.get_term_type(),
+        head: term.head,
+        num_args: term.args.len(),
+    });
+    for (i, arg) in term.args.iter().enumerate() {
+        path.push(i);
+        for_each_key(arg, path, f);
+        path.pop();
+    }
+}
+
+fn push_subterms<'a>(term: &'a Term, output: &mut Vec<&'a Term>) {
+    output.push(term);
+    for arg in &term.args {
+        push_subterms(arg, output);
+    }
+}
+
+// The entries of a clause, each with the keys it's filed under.
+type Filing = Vec<(usize, Vec<PathKey>)>;
+
+// A path index over the subterms of clauses, for finding the clauses that contain an
+// instance of a given term.
+// Every subterm is an entry, filed under each of its symbols along with the path to it.
+// An instance of a term has the same symbol everywhere the term has a symbol, so the
+// entries that could be instances are the ones filed under all of the term's keys.
+// Unlike the discrimination tree, clauses can be removed again.
+// The maps are persistent, so that cloning the index shares them.
+#[derive(Clone, Debug, Default)]
+pub struct PathIndex {
+    // The clause id for each live entry.
+    entries: HashMap<usize, usize>,
+    next_entry: usize,
+
+    // The entries filed under each key.
+    keys: HashMap<PathKey, OrdSet<usize>>,
+
+    // For each clause id, its entries and where they are filed.
+    clauses: HashMap<usize, Arc<Filing>>,
+}
+
+impl PathIndex {
+    pub fn new() -> PathIndex {
+        PathIndex::default()
+    }
+
+    pub fn insert(&mut self, id: usize, clause: &Clause) {
+        let mut subterms = vec![];
+        for literal in &clause.literals {
+            push_subterms(&literal.left, &mut subterms);
+            push_subterms(&literal.right, &mut subterms);
+        }
+        let mut filed = vec![];
+        for subterm in subterms {
+            let entry = self.next_entry;
+            self.next_entry += 1;
+            self.entries.insert(entry, id);
+            let mut keys = vec![];
+            for_each_key(subterm, &mut vec![], &mut |key| {
+                self.keys.entry(key.clone()).or_default().insert(entry);
+                keys.push(key);
+            });
+            filed.push((entry, keys));
+        }
+        Arc::make_mut(self.clauses.entry(id).or_default()).extend(filed);
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        let Some(filed) = self.clauses.remove(&id) else {
+            return;
+        };
+        for (entry, keys) in filed.iter() {
+            self.entries.remove(entry);
+            for key in keys {
+                if let Some(entries) = self.keys.get_mut(key) {
+                    entries.remove(entry);
+                    if entries.is_empty() {
+                        self.keys.remove(key);
+                    }
+                }
+            }
+        }
+    }
+
+    // Finds the ids of the clauses that could contain an instance of this term, in order.
+    // The caller still needs to check them by matching.
+    pub fn find_instances(&self, term: &Term) -> Vec<usize> {
+        let mut sets = vec![];
+        let mut missing = false;
+        for_each_key(term, &mut vec![], &mut |key| match self.keys.get(&key) {
+            Some(entries) => sets.push(entries),
+            None => missing = true,
+        });
+        if missing {
+            return vec![];
+        }
+        let ids: BTreeSet<usize> = match sets.iter().min_by_key(|entries| entries.len()) {
+            Some(smallest) => smallest
+                .iter()
+                .filter(|entry| sets.iter().all(|entries| entries.contains(entry)))
+                .map(|entry| self.entries[entry])
+                .collect(),
+            None => self.entries.values().copied().collect(),
+        };
+        ids.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_index() {
+        let mut index = PathIndex::new();
+        index.insert(0, &Clause::parse("c0(c1(c2)) = c3 or c4(c5)"));
+        index.insert(1, &Clause::parse("c0(c1(c3)) = c3 or c4(x0)"));
+        index.insert(2, &Clause::parse("c0(c2) = c3 or c4(c2)"));
+        assert_eq!(index.find_instances(&Term::parse("c0(c1(x0))")), vec![0, 1]);
+        assert_eq!(index.find_instances(&Term::parse("c4(c2)")), vec![2]);
+        assert_eq!(index.find_instances(&Term::parse("c1(c2)")), vec![0]);
+        assert_eq!(index.find_instances(&Term::parse("x0")), vec![0, 1, 2]);
+        assert_eq!(
+            index.find_instances(&Term::parse("c6")),
+            Vec::<usize>::new()
+        );
+
+        index.remove(0);
+        assert_eq!(index.find_instances(&Term::parse("c0(c1(x0))")), vec![1]);
+        assert_eq!(
+            index.find_instances(&Term::parse("c1(c2)")),
+            Vec::<usize>::new()
+        );
+        assert_eq!(index.find_instances(&Term::parse("x0")), vec![1, 2]);
+    }
+}
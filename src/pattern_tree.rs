@@ -1,4 +1,6 @@
-use qp_trie::{Entry, SubTrie, Trie};
+use std::ops::Bound;
+
+use im::{OrdMap, Vector};
 
 use crate::atom::{Atom, AtomId};
 use crate::literal::Literal;
@@ -405,6 +407,20 @@ fn key_from_pair(term1: &Term, term2: &Term) -> Vec<u8> {
     key
 }
 
+// The trie maps keys to value ids. It's an ordered map rather than a real trie, since the keys
+// that start with a given prefix are all next to each other, and an ordered map can be
+// persistent, so that cloning it is cheap.
+type Trie = OrdMap<Vec<u8>, usize>;
+
+// The first entry in the trie whose key starts with the prefix, if there is one.
+fn first_with_prefix<'a>(trie: &'a Trie, prefix: &[u8]) -> Option<&'a Vec<u8>> {
+    let range = (Bound::Included(prefix), Bound::Unbounded);
+    match trie.range::<_, [u8]>(range).next() {
+        Some((key, _)) if key.starts_with(prefix) => Some(key),
+        _ => None,
+    }
+}
+
 // Finds leaves in the trie that match the given term components.
 // These term components could represent a single term, or they could represent a
 // sequence of terms that are a suffix of a subterm. The "right part" of a subterm cut by a path.
@@ -416,8 +432,9 @@ fn key_from_pair(term1: &Term, term2: &Term) -> Vec<u8> {
 //
 // If the search stops early, key and replacements are left in their final state.
 // If the search does fully complete, we reset key and replacements to their initial state.
+// Only the part of the trie whose keys start with the key is searched.
 fn find_matches_while<'a, F>(
-    subtrie: &SubTrie<Vec<u8>, usize>,
+    trie: &Trie,
     key: &mut Vec<u8>,
     components: &'a [TermComponent],
     replacements: &mut Vec<&'a [TermComponent]>,
@@ -426,12 +443,12 @@ fn find_matches_while<'a, F>(
 where
     F: FnMut(usize, &Vec<&[TermComponent]>) -> bool,
 {
-    if subtrie.is_empty() {
+    let Some(sample) = first_with_prefix(trie, key) else {
         return true;
-    }
+    };
 
     if components.is_empty() {
-        match subtrie.get(key as &[u8]) {
+        match trie.get(key as &[u8]) {
             Some(value_id) => {
                 return callback(*value_id, replacements);
             }
@@ -441,7 +458,6 @@ where
                 // keys don't have a prefix relationship.
                 // This indicates some sort of problem, like that some atom
                 // is being used with an inconsistent number of args.
-                let (sample, _) = subtrie.iter().next().unwrap();
                 panic!(
                     "\nkey mismatch.\nquerying: {}\nexisting: {}\n",
                     Edge::debug_bytes(key),
@@ -460,8 +476,7 @@ where
         if first == replacements[i] {
             // This term could match x_i as a backreference.
             Edge::Atom(Atom::Variable(i as u16)).append_to(key);
-            let new_subtrie = subtrie.subtrie(key as &[u8]);
-            if !find_matches_while(&new_subtrie, key, rest, replacements, callback) {
+            if !find_matches_while(trie, key, rest, replacements, callback) {
                 return false;
             }
             key.truncate(initial_key_len);
@@ -470,10 +485,9 @@ where
 
     // Case 2: the first term could match an entirely new variable
     Edge::Atom(Atom::Variable(replacements.len() as u16)).append_to(key);
-    let new_subtrie = subtrie.subtrie(key as &[u8]);
-    if !new_subtrie.is_empty() {
+    if first_with_prefix(trie, key).is_some() {
         replacements.push(first);
-        if !find_matches_while(&new_subtrie, key, rest, replacements, callback) {
+        if !find_matches_while(trie, key, rest, replacements, callback) {
             return false;
         }
         replacements.pop();
@@ -498,8 +512,7 @@ where
         }
     };
     edge.append_to(key);
-    let new_subtrie = subtrie.subtrie(key as &[u8]);
-    if !find_matches_while(&new_subtrie, key, &components[1..], replacements, callback) {
+    if !find_matches_while(trie, key, &components[1..], replacements, callback) {
         return false;
     }
     key.truncate(initial_key_len);
@@ -507,26 +520,26 @@ where
 }
 
 #[derive(Clone, Debug)]
-pub struct PatternTree<T> {
+pub struct PatternTree<T: Clone> {
     // Maps to an index into values.
-    // The values are stored separately because subtrie lifetimes get weird.
-    trie: Trie<Vec<u8>, usize>,
+    // The values are stored separately, so that a match can just report the value id.
+    trie: Trie,
 
-    pub values: Vec<T>,
+    pub values: Vector<T>,
 }
 
-impl<T> PatternTree<T> {
+impl<T: Clone> PatternTree<T> {
     pub fn new() -> PatternTree<T> {
         PatternTree {
             trie: Trie::new(),
-            values: vec![],
+            values: Vector::new(),
         }
     }
 
     pub fn insert_term(&mut self, term: &Term, value: T) {
         let path = key_from_term(term);
         let value_id = self.values.len();
-        self.values.push(value);
+        self.values.push_back(value);
         self.trie.insert(path, value_id);
     }
 
@@ -534,7 +547,7 @@ impl<T> PatternTree<T> {
     pub fn insert_pair(&mut self, term1: &Term, term2: &Term, value: T) {
         let key = key_from_pair(term1, term2);
         let value_id = self.values.len();
-        self.values.push(value);
+        self.values.push_back(value);
         self.trie.insert(key, value_id);
     }
 
@@ -548,8 +561,7 @@ impl<T> PatternTree<T> {
     where
         F: FnMut(usize, &Vec<&[TermComponent]>) -> bool,
     {
-        let subtrie = self.trie.subtrie(key);
-        find_matches_while(&subtrie, key, components, replacements, callback)
+        find_matches_while(&self.trie, key, components, replacements, callback)
     }
 
     // Finds a single match, if possible.
@@ -583,17 +595,16 @@ impl<T> PatternTree<T> {
 
 impl PatternTree<()> {
     // Appends to the existing value if possible. Otherwises, inserts a vec![U].
-    pub fn insert_or_append<U>(pt: &mut PatternTree<Vec<U>>, term: &Term, value: U) {
+    pub fn insert_or_append<U: Clone>(pt: &mut PatternTree<Vec<U>>, term: &Term, value: U) {
         let key = key_from_term(term);
-        match pt.trie.entry(key) {
-            Entry::Occupied(entry) => {
-                let value_id = entry.get();
+        match pt.trie.get(&key) {
+            Some(value_id) => {
                 pt.values[*value_id].push(value);
             }
-            Entry::Vacant(entry) => {
+            None => {
                 let value_id = pt.values.len();
-                pt.values.push(vec![value]);
-                entry.insert(value_id);
+                pt.values.push_back(vec![value]);
+                pt.trie.insert(key, value_id);
             }
         }
     }
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -9,18 +9,32 @@ use regex::Regex;
 use tower_lsp::lsp_types::{CompletionItem, Url};
 use walkdir::WalkDir;
 
+use crate::benchmark::{BenchmarkConfig, BenchmarkReport, GoalBenchmark};
 use crate::binding_map::BindingMap;
 use crate::block::NodeCursor;
 use crate::builder::{BuildEvent, BuildStatus, Builder};
-use crate::compilation;
+use crate::compilation::{self, ErrorSource};
+use crate::compiled_module::CompiledModule;
 use crate::environment::Environment;
-use crate::fact::Fact;
+use crate::fact::{Fact, TagFilter};
 use crate::goal::GoalContext;
+use crate::import_analysis::ImportAnalysis;
+use crate::limits::{LimitOverrides, SearchLimits};
+use crate::model_finder::ModelFinder;
 use crate::module::{
     LoadState, Module, ModuleDescriptor, ModuleHash, ModuleHasher, ModuleId, FIRST_NORMAL,
 };
-use crate::prover::Prover;
+use crate::module_cache::ModuleCache;
+use crate::normalization_cache::NormalizationCache;
+use crate::proof_record::ModuleProofs;
+use crate::proposition::Source;
+use crate::prover::{Outcome, Prover};
+use crate::prover_config::ProverConfig;
+use crate::relevance::RelevanceFilter;
+use crate::smt::SmtConfig;
+use crate::statement::Statement;
 use crate::token::Token;
+use crate::tptp::AtpConfig;
 
 // The Project is responsible for importing different files and assigning them module ids.
 pub struct Project {
@@ -50,13 +64,76 @@ pub struct Project {
     module_map: HashMap<ModuleDescriptor, ModuleId>,
 
     // The module names that we want to build.
-    targets: HashSet<ModuleDescriptor>,
+    targets: BTreeSet<ModuleDescriptor>,
 
-    // The cache contains a hash for each module from the last time it was cleanly built.
-    build_cache: Arc<DashMap<ModuleDescriptor, ModuleHash>>,
+    // The cache contains a hash for each module from the last time it was cleanly built,
+    // along with the premises of any minimized proofs.
+    build_cache: Arc<DashMap<ModuleDescriptor, ModuleCache>>,
 
     // Used as a flag to stop a build in progress.
     pub build_stopped: Arc<AtomicBool>,
+
+    // Which imported facts to give the prover, based on their tags.
+    tag_filter: TagFilter,
+
+    // If set, each goal only gets the imported facts that look relevant to it.
+    relevance_filter: Option<RelevanceFilter>,
+
+    // If set, goals that the prover can't verify get another try with an SMT solver.
+    smt_config: Option<SmtConfig>,
+
+    // If set, goals that are still unverified get a try with an external first-order prover.
+    atp_config: Option<AtpConfig>,
+
+    // If set, goals that can't be verified are checked for small countermodels.
+    model_finder: Option<ModelFinder>,
+
+    // Whether to shrink the set of facts behind each successful proof, by proving the goal again
+    // with only the facts that the proof used.
+    minimize_premises: bool,
+
+    // How many lemmas to carry from one goal to the next within a module.
+    // Each successful proof contributes the unit clauses it derived from global facts, and the
+    // provers for later goals start out knowing the most recent ones. Zero turns this off.
+    lemma_pool_size: usize,
+
+    // The limits for each goal's search, unless a module or theorem overrides them.
+    search_limits: SearchLimits,
+
+    // Limits for particular modules, which theorem attributes can override in turn.
+    module_limits: HashMap<ModuleDescriptor, LimitOverrides>,
+
+    // Whether the provers ignore time limits, so that every build finds the same proofs.
+    deterministic: bool,
+
+    // Whether each proof gets re-checked by the proof checker after it's found.
+    // A proof that fails the check makes its goal an error.
+    check_proofs: bool,
+
+    // Prover strategies to try, in order, on goals that the default prover can't verify.
+    portfolio: Vec<ProverConfig>,
+
+    // Maps (module, goal name) to the name of the portfolio strategy that last verified it.
+    // That strategy gets tried first the next time.
+    portfolio_winners: Arc<DashMap<(ModuleId, String), String>>,
+
+    // The normalized clauses for the facts that provers have seen, shared between provers.
+    normalization_cache: Arc<NormalizationCache>,
+
+    // Whether to load modules from compiled artifacts when they are up to date, and to save
+    // compiled artifacts for modules that build cleanly.
+    use_compiled_modules: bool,
+
+    // Mock projects keep their compiled artifacts in memory rather than on the filesystem.
+    mock_compiled_files: Arc<DashMap<PathBuf, Vec<u8>>>,
+
+    // Whether to save the steps of each proof the build finds, so that a later build can
+    // replay them.
+    record_proofs: bool,
+
+    // Whether to check the saved proofs instead of searching. A goal whose saved proof is
+    // missing or doesn't check out is an error.
+    replay_proofs: bool,
 }
 
 // An error found while importing a module.
@@ -97,6 +174,27 @@ fn check_valid_module_part(s: &str, error_name: &str) -> Result<(), LoadError> {
     Ok(())
 }
 
+// The state that builds up while proving the goals of a single module.
+struct ProvingState {
+    // Lemmas from earlier proofs in the module, which later goals can use.
+    lemmas: Vec<Fact>,
+
+    // The proofs to save, if we are recording them.
+    recorded: Option<ModuleProofs>,
+
+    // The cache entry for this module, to store if the module builds cleanly.
+    new_cache: ModuleCache,
+
+    // The cache entry from the last time this module built cleanly.
+    old_cache: Option<ModuleCache>,
+}
+
+impl ProvingState {
+    fn old_premises(&self, goal_name: &str) -> Option<BTreeSet<String>> {
+        self.old_cache.as_ref()?.get_premises(goal_name).cloned()
+    }
+}
+
 impl Project {
     pub fn new(library_root: PathBuf) -> Project {
         Project {
@@ -105,9 +203,27 @@ impl Project {
             open_files: HashMap::new(),
             modules: Module::default_modules(),
             module_map: HashMap::new(),
-            targets: HashSet::new(),
+            targets: BTreeSet::new(),
             build_cache: Arc::new(DashMap::new()),
             build_stopped: Arc::new(AtomicBool::new(false)),
+            tag_filter: TagFilter::default(),
+            relevance_filter: None,
+            smt_config: None,
+            atp_config: None,
+            model_finder: None,
+            minimize_premises: false,
+            lemma_pool_size: 0,
+            search_limits: SearchLimits::default(),
+            module_limits: HashMap::new(),
+            deterministic: false,
+            check_proofs: false,
+            portfolio: vec![],
+            portfolio_winners: Arc::new(DashMap::new()),
+            normalization_cache: Arc::new(NormalizationCache::new()),
+            use_compiled_modules: false,
+            mock_compiled_files: Arc::new(DashMap::new()),
+            record_proofs: false,
+            replay_proofs: false,
         }
     }
 
@@ -200,6 +316,11 @@ impl Project {
         self.add_target_by_descriptor(&descriptor)
     }
 
+    // The build targets, sorted.
+    pub fn targets(&self) -> Vec<&ModuleDescriptor> {
+        self.targets.iter().collect()
+    }
+
     // Adds a target for all files in this directory.
     pub fn add_all_targets(&mut self) {
         if !self.use_filesystem {
@@ -299,8 +420,12 @@ impl Project {
     // Builds all open modules, logging build events.
     pub fn build(&self, builder: &mut Builder) {
         // Build in alphabetical order by module name for consistency.
-        let mut targets = self.targets.iter().collect::<Vec<_>>();
-        targets.sort();
+        // Nested modules are built right after the module whose file they're in.
+        let targets: Vec<_> = self
+            .targets
+            .iter()
+            .flat_map(|target| self.with_nested_modules(target))
+            .collect();
 
         builder.log_info(format!(
             "verifying modules: {}",
@@ -360,27 +485,93 @@ impl Project {
     // Verifies all goals within this target.
     fn verify_target(&self, target: &ModuleDescriptor, env: &Environment, builder: &mut Builder) {
         let current_hash = self.get_hash(env.module_id).unwrap();
-        let cached_hash = self
+        let cached = self
             .build_cache
             .get(target)
             .map(|entry| entry.value().clone());
+        let cached_hash = cached.as_ref().map(|cache| cache.hash.clone());
 
         builder.module_proving_started(target.clone());
 
         // Fast and slow modes should be interchangeable here.
         // If we run into a bug with fast mode, try using slow mode to debug.
-        self.for_each_prover_fast(env, &mut |prover, goal_context| {
-            if current_hash.matches_through_line(&cached_hash, goal_context.last_line) {
+        // An axiom audit needs to see every proof, so it can't use the cache.
+        // Neither can recording or replaying proofs.
+        let use_cache = builder.axiom_audit.is_none() && !self.record_proofs && !self.replay_proofs;
+        let mut state = ProvingState {
+            lemmas: vec![],
+            recorded: self
+                .record_proofs
+                .then(|| ModuleProofs::new(current_hash.clone())),
+            new_cache: ModuleCache::new(current_hash.clone()),
+            old_cache: cached,
+        };
+        let stored = match self.replay_proofs.then(|| self.load_module_proofs(target)) {
+            Some(Some(proofs)) if &proofs.hash == current_hash => Ok(proofs),
+            Some(Some(_)) => Err("the saved proofs are out of date"),
+            Some(None) => Err("there are no saved proofs for this module"),
+            None => Err("proofs are not being replayed"),
+        };
+        let has_premises = state
+            .old_cache
+            .as_ref()
+            .is_some_and(|cache| cache.has_premises());
+        let mut callback = |prover, goal_context: GoalContext, facts: Option<Vec<Fact>>| {
+            if self.replay_proofs {
+                self.replay(
+                    prover,
+                    goal_context,
+                    stored.as_ref().map_err(|e| *e),
+                    builder,
+                )
+            } else if use_cache
+                && current_hash.matches_through_line(&cached_hash, goal_context.last_line)
+            {
+                // The premises of the cached proof are still good.
+                if let Some(premises) = state.old_premises(&goal_context.name) {
+                    state.new_cache.add_premises(&goal_context.name, premises);
+                }
                 builder.log_proving_success_cached(&goal_context);
                 true
             } else {
-                self.prove(prover, goal_context, builder)
+                self.prove(prover, goal_context, facts, &mut state, builder)
             }
-        });
+        };
+        if self.relevance_filter.is_none()
+            && !self.minimize_premises
+            && self.portfolio.is_empty()
+            && !has_premises
+        {
+            self.for_each_prover_fast(env, &mut |prover, goal_context| {
+                callback(prover, goal_context, None)
+            });
+        } else {
+            // Minimizing, the portfolio, and cached premises need to know which facts each
+            // prover started with.
+            self.for_each_prover_filtered(
+                env,
+                self.relevance_filter.as_ref(),
+                &mut |prover, goal_context, facts| callback(prover, goal_context, Some(facts)),
+            );
+        }
+
+        if let Some(recorded) = &state.recorded {
+            if let Err(e) = self.save_module_proofs(target, recorded) {
+                builder.log_info(format!("error saving proofs for {}: {}", target, e));
+            }
+        }
 
+        builder.check_unused_hypotheses(env);
+        builder.check_unused_names(env);
+        builder.check_implicit_shadows(env);
+        if let Some(audit) = &mut builder.axiom_audit {
+            audit.module_complete(env);
+        }
         if builder.module_proving_complete(target) {
-            self.build_cache
-                .insert(target.clone(), current_hash.clone());
+            self.build_cache.insert(target.clone(), state.new_cache);
+            if let Err(e) = self.save_compiled_module(target, env, current_hash) {
+                builder.log_info(format!("error saving compiled {}: {}", target, e));
+            }
         }
     }
 
@@ -399,9 +590,43 @@ impl Project {
             for fact in node.usable_facts(&self) {
                 prover.add_fact(fact);
             }
-            prover.set_goal(&goal_context);
-            if !callback(prover, goal_context) {
-                return;
+            for subgoal in goal_context.subgoals() {
+                let mut prover = prover.clone();
+                prover.set_goal(&subgoal);
+                if !callback(prover, subgoal) {
+                    return;
+                }
+            }
+        }
+    }
+
+    // Create a prover for each goal in this environment, and call the callback on it,
+    // along with the facts the prover was given.
+    // An error status makes us stop early.
+    // Like the slow version, but if there is a filter, each prover only gets the facts that it
+    // selects for its goal, so the provers can't share state.
+    pub fn for_each_prover_filtered(
+        &self,
+        env: &Environment,
+        filter: Option<&RelevanceFilter>,
+        callback: &mut impl FnMut(Prover, GoalContext, Vec<Fact>) -> bool,
+    ) {
+        for node in env.iter_goals() {
+            let goal_context = node.goal_context().expect("no goal context");
+            let mut facts = node.usable_facts(self);
+            if let Some(filter) = filter {
+                facts = filter.select(facts, goal_context.goal.value(), env.module_id);
+            }
+            let mut prover = Prover::new(self, false);
+            for fact in &facts {
+                prover.add_fact(fact.clone());
+            }
+            for subgoal in goal_context.subgoals() {
+                let mut prover = prover.clone();
+                prover.set_goal(&subgoal);
+                if !callback(prover, subgoal, facts.clone()) {
+                    return;
+                }
             }
         }
     }
@@ -420,7 +645,7 @@ impl Project {
             return;
         }
         let mut prover = Prover::new(&self, false);
-        for fact in self.imported_facts(env.module_id) {
+        for fact in self.imported_facts(env.module_id, &self.tag_filter) {
             prover.add_fact(fact);
         }
         let mut node = NodeCursor::new(&env, 0);
@@ -472,9 +697,12 @@ impl Project {
 
         if node.current().has_goal() {
             let goal_context = node.goal_context().unwrap();
-            prover.set_goal(&goal_context);
-            if !callback(prover, goal_context) {
-                return false;
+            for subgoal in goal_context.subgoals() {
+                let mut prover = prover.clone();
+                prover.set_goal(&subgoal);
+                if !callback(prover, subgoal) {
+                    return false;
+                }
             }
         }
 
@@ -482,17 +710,280 @@ impl Project {
     }
 
     // Proves a single goal in the target, using the provided prover.
+    // If we know which facts the prover started with, a successful proof gets minimized.
+    // The prover also gets the lemmas from earlier goals, and a successful proof adds to them.
+    // If we're recording proofs, a successful proof is recorded if it replays in a prover that
+    // starts out like this one, so proofs that use lemmas or other provers' facts aren't.
     // Reports using the handler as appropriate.
     // Returns true if we should keep building, false if we should stop.
-    fn prove(&self, mut prover: Prover, goal_context: GoalContext, builder: &mut Builder) -> bool {
+    fn prove(
+        &self,
+        mut prover: Prover,
+        goal_context: GoalContext,
+        facts: Option<Vec<Fact>>,
+        state: &mut ProvingState,
+        builder: &mut Builder,
+    ) -> bool {
         let start = std::time::Instant::now();
-        let outcome = prover.verification_search();
+        let replay_prover = state.recorded.as_ref().map(|_| prover.clone());
+        let lemmas = &mut state.lemmas;
+        for lemma in lemmas.iter() {
+            prover.add_fact(lemma.clone());
+        }
+
+        // If an earlier build minimized this goal's proof, first try just those premises.
+        let cached_facts = match (&facts, state.old_cache.as_ref()) {
+            (Some(facts), Some(cache)) => cache
+                .get_premises(&goal_context.name)
+                .map(|premises| self.select_premises(facts, premises)),
+            _ => None,
+        };
+        let cached_prover = cached_facts.and_then(|cached_facts| {
+            let mut candidate =
+                self.configured_prover(&ProverConfig::default(), &cached_facts, &goal_context);
+            let outcome = candidate.search_with_limits(&self.goal_limits(&goal_context), true);
+            (outcome == Outcome::Success).then_some(candidate)
+        });
+        let mut minimal = cached_prover.is_some();
+        let outcome = match cached_prover {
+            Some(cached_prover) => {
+                prover = cached_prover;
+                Outcome::Success
+            }
+            None => self.verify_with_fallback(&mut prover, &goal_context, facts.as_deref()),
+        };
+        if outcome == Outcome::Success && self.lemma_pool_size > 0 {
+            for lemma in prover.get_lemmas(&goal_context) {
+                if !lemmas.iter().any(|known| known.value == lemma.value) {
+                    lemmas.push(lemma);
+                }
+            }
+            let excess = lemmas.len().saturating_sub(self.lemma_pool_size);
+            lemmas.drain(..excess);
+        }
+        if let (Outcome::Success, Some(facts), true) = (outcome, facts, self.minimize_premises) {
+            prover = self.minimize_proof(prover, facts, &goal_context);
+            minimal = true;
+        }
+        if minimal {
+            // Keep the premises around, so the next build can try them first.
+            if let Some(proof) = prover.get_proof() {
+                let premises = proof.iter_sources().map(|source| self.premise_key(source));
+                state.new_cache.add_premises(&goal_context.name, premises);
+            }
+        }
+        if let (Some(recorded), Some(mut replay_prover)) = (state.recorded.as_mut(), replay_prover)
+        {
+            if let Some(record) = prover.proof_record() {
+                if replay_prover.replay(&record) == Outcome::Success {
+                    recorded.insert(&goal_context, record);
+                }
+            }
+        }
 
         builder.search_finished(&prover, &goal_context, outcome, start.elapsed());
 
         !builder.status.is_error()
     }
 
+    // Checks the saved proof of a single goal, rather than searching for one.
+    // Returns true if we should keep building, false if we should stop.
+    fn replay(
+        &self,
+        mut prover: Prover,
+        goal_context: GoalContext,
+        stored: Result<&ModuleProofs, &str>,
+        builder: &mut Builder,
+    ) -> bool {
+        let start = std::time::Instant::now();
+        let record = stored.map(|proofs| proofs.get(&goal_context));
+        let outcome = match record {
+            Ok(Some(record)) => prover.replay(record),
+            Ok(None) => {
+                prover.error = Some("there is no saved proof for this goal".to_string());
+                Outcome::Error
+            }
+            Err(message) => {
+                prover.error = Some(message.to_string());
+                Outcome::Error
+            }
+        };
+        builder.search_finished(&prover, &goal_context, outcome, start.elapsed());
+        !builder.status.is_error()
+    }
+
+    // Runs the verification search, and then the fallback strategies that are configured,
+    // until one of them succeeds.
+    // Fallbacks only get a try when the search didn't reach a conclusion by itself.
+    // The portfolio needs to build new provers, so it only runs when we know the facts.
+    // When a portfolio strategy succeeds, its prover replaces the one passed in.
+    pub fn verify_with_fallback(
+        &self,
+        prover: &mut Prover,
+        goal_context: &GoalContext,
+        facts: Option<&[Fact]>,
+    ) -> Outcome {
+        let outcome = prover.search_with_limits(&self.goal_limits(goal_context), true);
+        if !matches!(
+            outcome,
+            Outcome::Exhausted | Outcome::Constrained | Outcome::Timeout
+        ) {
+            return outcome;
+        }
+        if let Some(facts) = facts {
+            if let Some(winner) = self.run_portfolio(facts, goal_context) {
+                *prover = winner;
+                return Outcome::Success;
+            }
+        }
+        if let Some(config) = &self.smt_config {
+            if prover.smt_search(config) == Outcome::Success {
+                return Outcome::Success;
+            }
+        }
+        if let Some(config) = &self.atp_config {
+            if prover.atp_search(config) == Outcome::Success {
+                return Outcome::Success;
+            }
+        }
+        if let Some(finder) = &self.model_finder {
+            if prover.countermodel_search(finder) == Outcome::Countermodel {
+                return Outcome::Countermodel;
+            }
+        }
+        outcome
+    }
+
+    // The limit overrides for a goal, from its module and then from its theorem.
+    fn goal_overrides(&self, goal_context: &GoalContext) -> LimitOverrides {
+        let descriptor = self.get_module_descriptor(goal_context.module_id);
+        let mut overrides = self
+            .module_limits
+            .get(descriptor)
+            .copied()
+            .unwrap_or_default();
+        overrides.merge(&goal_context.limits);
+        overrides
+    }
+
+    // The limits for a goal's verification search.
+    pub fn goal_limits(&self, goal_context: &GoalContext) -> SearchLimits {
+        self.search_limits
+            .with_overrides(&self.goal_overrides(goal_context))
+    }
+
+    // Tries each portfolio strategy in turn, starting with the one that last worked for this
+    // goal, and returns the prover from the first one that succeeds.
+    // In deterministic mode the strategies always go in order, so that earlier builds don't
+    // change the result.
+    fn run_portfolio(&self, facts: &[Fact], goal_context: &GoalContext) -> Option<Prover> {
+        let key = (goal_context.module_id, goal_context.name.clone());
+        let mut configs: Vec<&ProverConfig> = self.portfolio.iter().collect();
+        if !self.deterministic {
+            let previous = self.portfolio_winners.get(&key).map(|e| e.value().clone());
+            configs.sort_by_key(|config| Some(&config.name) != previous.as_ref());
+        }
+        for config in configs {
+            let mut prover = self.configured_prover(config, facts, goal_context);
+            let overrides = self.goal_overrides(goal_context);
+            if prover.configured_search(config, &overrides) == Outcome::Success {
+                self.portfolio_winners.insert(key, config.name.clone());
+                return Some(prover);
+            }
+            if prover.error.is_some() {
+                return None;
+            }
+        }
+        None
+    }
+
+    // The name of the portfolio strategy that last verified this goal, if any did.
+    pub fn portfolio_winner(&self, module_id: ModuleId, goal_name: &str) -> Option<String> {
+        self.portfolio_winners
+            .get(&(module_id, goal_name.to_string()))
+            .map(|e| e.value().clone())
+    }
+
+    // Creates a prover with this configuration, and sets it up to prove the goal from the facts.
+    pub fn configured_prover(
+        &self,
+        config: &ProverConfig,
+        facts: &[Fact],
+        goal_context: &GoalContext,
+    ) -> Prover {
+        let mut prover = Prover::new(self, false);
+        prover.configure(config);
+        for fact in facts {
+            prover.add_fact(fact.clone());
+        }
+        prover.set_goal(goal_context);
+        if config.module_kbo {
+            if let Some(bindings) = self.get_bindings(goal_context.module_id) {
+                let kbo = prover.module_kbo(self, bindings);
+                prover.set_kbo(kbo);
+            }
+        }
+        prover
+    }
+
+    // Takes a prover that has found a proof using these facts, and keeps proving the goal again
+    // with only the facts that the last proof used, until that stops removing any.
+    // Returns the prover with the smallest proof.
+    // The smaller proof is what the builder records, so later checks like unused hypotheses
+    // and axiom audits only see the premises a goal really needs.
+    pub fn minimize_proof(
+        &self,
+        prover: Prover,
+        facts: Vec<Fact>,
+        goal_context: &GoalContext,
+    ) -> Prover {
+        let mut best = prover;
+        let mut facts = facts;
+        loop {
+            let Some(proof) = best.get_proof() else {
+                return best;
+            };
+            let used: Vec<_> = proof.iter_sources().collect();
+            let kept: Vec<Fact> = facts
+                .iter()
+                .filter(|fact| used.contains(&&fact.source))
+                .cloned()
+                .collect();
+            if kept.len() == facts.len() {
+                return best;
+            }
+            let mut candidate =
+                self.configured_prover(&ProverConfig::default(), &kept, goal_context);
+            if candidate.search_with_limits(&self.goal_limits(goal_context), true)
+                != Outcome::Success
+            {
+                // Sometimes a proof is only easy to find with the extra facts around.
+                return best;
+            }
+            best = candidate;
+            facts = kept;
+        }
+    }
+
+    // Identifies a premise across builds, by its module and a description of where it's from.
+    // Module ids can change between builds, so the module is identified by its descriptor.
+    fn premise_key(&self, source: &Source) -> String {
+        format!(
+            "{}: {}",
+            self.get_module_descriptor(source.module),
+            source.description()
+        )
+    }
+
+    // The facts that match the premises of a cached proof.
+    fn select_premises(&self, facts: &[Fact], premises: &BTreeSet<String>) -> Vec<Fact> {
+        facts
+            .iter()
+            .filter(|fact| premises.contains(&self.premise_key(&fact.source)))
+            .cloned()
+            .collect()
+    }
+
     // Does the build and returns when it's done, rather than asynchronously.
     // Returns (status, events, num_success, cache).
     pub fn sync_build(&self) -> (BuildStatus, Vec<BuildEvent>, i32) {
@@ -505,6 +996,40 @@ impl Project {
         (status, events, num_success)
     }
 
+    // Runs the prover on every goal in the targets, several times each, and reports timing.
+    // Targets that fail to load are skipped.
+    // Unlike a build, this doesn't stop when a goal fails, because failures are part of
+    // what we want to measure.
+    pub fn benchmark(&self, config: BenchmarkConfig) -> BenchmarkReport {
+        let mut report = BenchmarkReport::new(config.clone());
+        for target in &self.targets {
+            let env = match self.get_module(target) {
+                LoadState::Ok(env) => env,
+                _ => continue,
+            };
+            self.for_each_prover_fast(env, &mut |prover, goal_context| {
+                let mut goal = GoalBenchmark::new(target.clone(), goal_context.name.clone());
+                for _ in 0..config.iterations {
+                    let mut prover = prover.clone();
+                    let start = std::time::Instant::now();
+                    let outcome = prover.search_for_contradiction(
+                        config.activation_limit,
+                        config.seconds,
+                        config.verification,
+                    );
+                    goal.add_run(
+                        outcome,
+                        start.elapsed().as_secs_f64(),
+                        prover.num_activated(),
+                    );
+                }
+                report.goals.push(goal);
+                true
+            });
+        }
+        report
+    }
+
     // Set the file content. This has priority over the actual filesystem.
     pub fn mock(&mut self, filename: &str, content: &str) {
         assert!(!self.use_filesystem);
@@ -519,6 +1044,10 @@ impl Project {
         }
     }
 
+    pub fn get_module_descriptor(&self, module_id: ModuleId) -> &ModuleDescriptor {
+        &self.modules[module_id as usize].descriptor
+    }
+
     pub fn get_module(&self, descriptor: &ModuleDescriptor) -> &LoadState {
         match self.module_map.get(descriptor) {
             Some(id) => self.get_module_by_id(*id),
@@ -554,7 +1083,7 @@ impl Project {
         errors
     }
 
-    fn read_file(&mut self, path: &PathBuf) -> Result<String, LoadError> {
+    fn read_file(&self, path: &PathBuf) -> Result<String, LoadError> {
         if let Some((content, _)) = self.open_files.get(path) {
             return Ok(content.clone());
         }
@@ -626,6 +1155,7 @@ impl Project {
         let name = match descriptor {
             ModuleDescriptor::Name(name) => name,
             ModuleDescriptor::File(path) => return Some(path.clone()),
+            ModuleDescriptor::Nested(parent, _) => return self.path_from_descriptor(parent),
             ModuleDescriptor::Anonymous => return None,
         };
 
@@ -675,6 +1205,13 @@ impl Project {
         self.modules.push(Module::new(descriptor.clone()));
         self.module_map.insert(descriptor.clone(), module_id);
 
+        if let Some((env, module_hash)) =
+            self.load_compiled_module(module_id, descriptor, &path, &text)
+        {
+            self.modules[module_id as usize].load_ok(env, module_hash);
+            return Ok(module_id);
+        }
+
         let mut env = Environment::new(module_id);
         let tokens = Token::scan(&text);
         if let Err(e) = env.add_tokens(self, tokens) {
@@ -692,6 +1229,207 @@ impl Project {
         Ok(module_id)
     }
 
+    pub fn set_use_compiled_modules(&mut self, use_compiled_modules: bool) {
+        self.use_compiled_modules = use_compiled_modules;
+    }
+
+    // Where the compiled artifact for a module goes.
+    // Only modules with names get compiled artifacts. They go in the "build" directory of
+    // the library, in the same place relative to it as the source is to the library.
+    fn compiled_path(&self, descriptor: &ModuleDescriptor) -> Option<PathBuf> {
+        self.build_path(descriptor, "acm")
+    }
+
+    fn build_path(&self, descriptor: &ModuleDescriptor, extension: &str) -> Option<PathBuf> {
+        let ModuleDescriptor::Name(name) = descriptor else {
+            return None;
+        };
+        let mut path = self.library_root.join("build");
+        for part in name.split('.') {
+            path.push(part);
+        }
+        path.set_extension(extension);
+        Some(path)
+    }
+
+    // Saves the proofs recorded for a module. They go next to its compiled artifact.
+    fn save_module_proofs(
+        &self,
+        descriptor: &ModuleDescriptor,
+        proofs: &ModuleProofs,
+    ) -> io::Result<()> {
+        let Some(path) = self.build_path(descriptor, "acp") else {
+            return Ok(());
+        };
+        let bytes = proofs.serialize();
+        if !self.use_filesystem {
+            self.mock_compiled_files.insert(path, bytes);
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, bytes)
+    }
+
+    fn load_module_proofs(&self, descriptor: &ModuleDescriptor) -> Option<ModuleProofs> {
+        let path = self.build_path(descriptor, "acp")?;
+        let bytes = if self.use_filesystem {
+            std::fs::read(path).ok()?
+        } else {
+            self.mock_compiled_files.get(&path)?.clone()
+        };
+        ModuleProofs::deserialize(&bytes)
+    }
+
+    // Saves the compiled artifact for a module that built cleanly.
+    // Modules in a file with nested modules aren't saved, since loading the compiled module
+    // wouldn't create the nested ones.
+    fn save_compiled_module(
+        &self,
+        descriptor: &ModuleDescriptor,
+        env: &Environment,
+        hash: &ModuleHash,
+    ) -> io::Result<()> {
+        if !self.use_compiled_modules || self.with_nested_modules(descriptor).len() > 1 {
+            return Ok(());
+        }
+        let Some(path) = self.compiled_path(descriptor) else {
+            return Ok(());
+        };
+        let has_nested_dependency = self.all_dependencies(env.module_id).iter().any(|id| {
+            matches!(
+                self.get_module_descriptor(*id),
+                ModuleDescriptor::Nested(..)
+            )
+        });
+        if has_nested_dependency {
+            return Ok(());
+        }
+        let bytes = CompiledModule::new(self, env, hash.clone()).serialize();
+        if !self.use_filesystem {
+            self.mock_compiled_files.insert(path, bytes);
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, bytes)
+    }
+
+    // Tries to load a module from its compiled artifact, rather than from source.
+    // Returns None if there is no artifact, or if it's out of date.
+    // Files that are open in an editor are always loaded from source, since the editor needs
+    // the full environment. Mock projects keep every file open, so they don't count.
+    fn load_compiled_module(
+        &mut self,
+        module_id: ModuleId,
+        descriptor: &ModuleDescriptor,
+        path: &PathBuf,
+        text: &str,
+    ) -> Option<(Environment, ModuleHash)> {
+        if !self.use_compiled_modules || (self.use_filesystem && self.open_files.contains_key(path))
+        {
+            return None;
+        }
+        let compiled_path = self.compiled_path(descriptor)?;
+        let bytes = if self.use_filesystem {
+            std::fs::read(compiled_path).ok()?
+        } else {
+            self.mock_compiled_files.get(&compiled_path)?.clone()
+        };
+        let compiled = CompiledModule::deserialize(&bytes)?;
+
+        // If the source has changed, the artifact is stale, and there's no point in loading
+        // its dependencies.
+        let mut hasher = ModuleHasher::new(text);
+        if !hasher.matches_source(&compiled.hash) {
+            return None;
+        }
+
+        // Load the dependencies, to find out what their ids are in this project.
+        let mut ids = HashMap::new();
+        ids.insert(compiled.module_id(), module_id);
+        for (old_id, dependency) in compiled.dependencies() {
+            let new_id = self.load_module(dependency).ok()?;
+            self.get_env_by_id(new_id)?;
+            ids.insert(*old_id, new_id);
+        }
+
+        // The artifact is only valid if no dependency has changed either.
+        let expected_hash = compiled.hash.clone();
+        let env = compiled.into_environment(&|id| ids.get(&id).copied().unwrap_or(id));
+        for dependency_id in env.bindings.direct_dependencies() {
+            hasher.add_dependency(&self.modules[dependency_id as usize]);
+        }
+        let module_hash = hasher.finish();
+        if module_hash != expected_hash {
+            return None;
+        }
+        Some((env, module_hash))
+    }
+
+    // Adds a module that is declared with a "module" statement inside its parent's file.
+    // Since they share a file, errors in the nested module are returned as errors in the parent.
+    pub fn add_nested_module(
+        &mut self,
+        parent_id: ModuleId,
+        name_token: &Token,
+        statements: &[Statement],
+    ) -> compilation::Result<ModuleId> {
+        let name = name_token.text();
+        if let Err(LoadError(message)) = check_valid_module_part(name, name) {
+            return Err(name_token.error(&message));
+        }
+        let parent = match self.modules.get(parent_id as usize) {
+            Some(module) => module.descriptor.clone(),
+            None => ModuleDescriptor::Anonymous,
+        };
+        let descriptor = ModuleDescriptor::Nested(Box::new(parent), name.to_string());
+        let module_id = self.modules.len() as ModuleId;
+        self.modules.push(Module::new(descriptor.clone()));
+        self.module_map.insert(descriptor.clone(), module_id);
+
+        let mut env = Environment::new(module_id);
+        for statement in statements {
+            if let Err(e) = env.add_statement(self, statement) {
+                let secondary = compilation::Error::secondary(
+                    name_token,
+                    name_token,
+                    &format!("error in '{}' module", name),
+                );
+                self.modules[module_id as usize].load_error(secondary);
+                return Err(e);
+            }
+        }
+
+        // Goals are located by their line in the file, so the hash covers the whole file.
+        let text = match self.path_from_descriptor(&descriptor) {
+            Some(path) => self.read_file(&path).unwrap_or_default(),
+            None => String::new(),
+        };
+        let mut hasher = ModuleHasher::new(&text);
+        for dependency_id in env.bindings.direct_dependencies() {
+            hasher.add_dependency(&self.modules[dependency_id as usize]);
+        }
+        let module_hash = hasher.finish();
+        self.modules[module_id as usize].load_ok(env, module_hash);
+        Ok(module_id)
+    }
+
+    // This module, followed by the modules nested inside it, recursively.
+    fn with_nested_modules(&self, descriptor: &ModuleDescriptor) -> Vec<ModuleDescriptor> {
+        let mut answer = vec![descriptor.clone()];
+        for module in &self.modules {
+            if let ModuleDescriptor::Nested(parent, _) = &module.descriptor {
+                if **parent == *descriptor {
+                    answer.extend(self.with_nested_modules(&module.descriptor));
+                }
+            }
+        }
+        answer
+    }
+
     pub fn load_module_by_name(&mut self, module_name: &str) -> Result<ModuleId, LoadError> {
         let descriptor = ModuleDescriptor::Name(module_name.to_string());
         self.load_module(&descriptor)
@@ -738,40 +1476,196 @@ impl Project {
         }
     }
 
-    // All facts that the given module imports.
-    pub fn imported_facts(&self, module_id: ModuleId) -> Vec<Fact> {
+    // Maps every constant and theorem in the loaded modules to the constants that its
+    // definition or claim refers to.
+    // Aliases are left out, since they are the same constant as the thing they alias.
+    pub fn dependency_graph(&self) -> BTreeMap<(ModuleId, String), BTreeSet<(ModuleId, String)>> {
+        let mut graph = BTreeMap::new();
+        for module_id in 0..self.modules.len() as ModuleId {
+            let Some(env) = self.get_env_by_id(module_id) else {
+                continue;
+            };
+            for name in env.bindings.constant_names() {
+                let dependencies = env.bindings.get_dependencies(name);
+                graph.insert((module_id, name.clone()), dependencies);
+            }
+        }
+        graph
+    }
+
+    // All facts that the given module imports, leaving out the ones the filter excludes.
+    pub fn imported_facts(&self, module_id: ModuleId, filter: &TagFilter) -> Vec<Fact> {
         let mut facts = vec![];
         for dependency in self.all_dependencies(module_id) {
             let env = self.get_env_by_id(dependency).unwrap();
-            facts.extend(env.exported_facts());
+            facts.extend(env.exported_facts(filter));
         }
         facts
     }
 
-    // path is the file we're in.
-    // env_line is zero-based. It's the closest unchanged line, to use for finding the environment.
-    // prefix is the entire line they've typed so far. Generally different from env_line.
-    // Returns a list of completions, or None if we don't have any.
-    pub fn get_completions(
+    // The environments of all the modules that loaded without errors.
+    pub fn loaded_envs(&self) -> impl Iterator<Item = &Environment> + '_ {
+        self.modules
+            .iter()
+            .filter_map(|module| match &module.state {
+                LoadState::Ok(env) => Some(env),
+                _ => None,
+            })
+    }
+
+    // Analyzes which imports a module needs, from its source.
+    // The module doesn't have to compile, but only statements before any parse error count.
+    // Missing imports are only suggested from modules that are already loaded.
+    pub fn analyze_imports(
         &self,
-        path: Option<&Path>,
-        env_line: u32,
-        prefix: &str,
-    ) -> Option<Vec<CompletionItem>> {
-        let re = Regex::new(r"[a-zA-Z0-9._]+").unwrap();
-        let parts: Vec<&str> = re.find_iter(prefix).map(|mat| mat.as_str()).collect();
-        if parts.len() == 4 && parts[0] == "from" && parts[2] == "import" {
-            // We are in a "from X import Y" statement.
-            let name = parts[1];
-            let partial = parts[3];
-            let descriptor = ModuleDescriptor::Name(name.to_string());
-            let env = match self.get_env(&descriptor) {
-                Some(env) => env,
-                None => {
-                    // The module isn't loaded, so we don't know what names it has.
-                    if name == "nat" && "Nat".starts_with(partial) {
-                        // Cheat to optimize the tutorial.
-                        // If we always loaded nat, we wouldn't need this.
+        descriptor: &ModuleDescriptor,
+    ) -> Result<ImportAnalysis, LoadError> {
+        let path = match self.path_from_descriptor(descriptor) {
+            Some(path) => path,
+            None => return Err(LoadError(format!("no source for module: {}", descriptor))),
+        };
+        let text = self.read_file(&path)?;
+        let module_id = self.module_map.get(descriptor).copied();
+        Ok(ImportAnalysis::new(
+            self,
+            module_id,
+            &Statement::parse_prefix(&text),
+        ))
+    }
+
+    pub fn tag_filter(&self) -> &TagFilter {
+        &self.tag_filter
+    }
+
+    // Sets which imported facts the prover gets when proving goals.
+    // Cached builds used a different set of facts, so they no longer count.
+    pub fn set_tag_filter(&mut self, filter: TagFilter) {
+        self.tag_filter = filter;
+        self.build_cache.clear();
+    }
+
+    // Sets which SMT solver to fall back on, or None to only use the prover.
+    pub fn set_smt_config(&mut self, config: Option<SmtConfig>) {
+        self.smt_config = config;
+    }
+
+    // Sets which external first-order prover to fall back on, or None to not use one.
+    pub fn set_atp_config(&mut self, config: Option<AtpConfig>) {
+        self.atp_config = config;
+    }
+
+    // Sets how to look for countermodels of goals that can't be verified, or None to not look.
+    pub fn set_model_finder(&mut self, finder: Option<ModelFinder>) {
+        self.model_finder = finder;
+    }
+
+    // Sets whether successful proofs are minimized before the builder records them.
+    pub fn set_minimize_premises(&mut self, minimize: bool) {
+        self.minimize_premises = minimize;
+    }
+
+    // Sets how many lemmas from earlier goals in a module get passed on to later goals.
+    // Like the limits, this can change which goals verify, so the build cache is cleared.
+    pub fn set_lemma_pool_size(&mut self, size: usize) {
+        self.lemma_pool_size = size;
+        self.build_cache.clear();
+    }
+
+    // Sets the limits for every goal's search.
+    // Changing the limits can change which goals verify, so the build cache is cleared.
+    pub fn set_search_limits(&mut self, limits: SearchLimits) {
+        self.search_limits = limits;
+        self.build_cache.clear();
+    }
+
+    // Sets limits for the goals in one module, on top of the project-wide ones.
+    pub fn set_module_limits(&mut self, descriptor: ModuleDescriptor, overrides: LimitOverrides) {
+        self.module_limits.insert(descriptor, overrides);
+        self.build_cache.clear();
+    }
+
+    pub fn deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    pub fn check_proofs(&self) -> bool {
+        self.check_proofs
+    }
+
+    // Sets whether proofs get re-checked after they're found.
+    // Cached goals would skip the check, so the build cache is cleared.
+    pub fn set_check_proofs(&mut self, check_proofs: bool) {
+        self.check_proofs = check_proofs;
+        self.build_cache.clear();
+    }
+
+    // Sets whether builds save the steps of the proofs they find, for replaying later.
+    // Cached goals wouldn't get recorded, so the build cache is cleared.
+    pub fn set_record_proofs(&mut self, record_proofs: bool) {
+        self.record_proofs = record_proofs;
+        self.build_cache.clear();
+    }
+
+    // Sets whether builds check the saved proofs instead of searching.
+    // This is meant for CI, where it's fast and doesn't depend on how the search goes.
+    // The proofs have to be recorded with the same settings, so that the provers match.
+    pub fn set_replay_proofs(&mut self, replay_proofs: bool) {
+        self.replay_proofs = replay_proofs;
+        self.build_cache.clear();
+    }
+
+    pub fn normalization_cache(&self) -> Arc<NormalizationCache> {
+        self.normalization_cache.clone()
+    }
+
+    // Sets whether provers ignore time limits, so that a given input always produces the same
+    // proof and the same number of steps, on any machine.
+    // Searches are then only bounded by the deterministic limits.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    // Sets the strategies to try on goals that the default prover can't verify.
+    // An empty portfolio turns this off.
+    pub fn set_portfolio(&mut self, portfolio: Vec<ProverConfig>) {
+        self.portfolio = portfolio;
+    }
+
+    pub fn relevance_filter(&self) -> Option<&RelevanceFilter> {
+        self.relevance_filter.as_ref()
+    }
+
+    // Sets how imported facts are filtered for each goal, or None to use all of them.
+    // Like the tag filter, this changes what the prover sees, so the build cache is cleared.
+    pub fn set_relevance_filter(&mut self, filter: Option<RelevanceFilter>) {
+        self.relevance_filter = filter;
+        self.build_cache.clear();
+    }
+
+    // path is the file we're in.
+    // env_line is zero-based. It's the closest unchanged line, to use for finding the environment.
+    // prefix is the entire line they've typed so far. Generally different from env_line.
+    // Returns a list of completions, or None if we don't have any.
+    pub fn get_completions(
+        &self,
+        path: Option<&Path>,
+        env_line: u32,
+        prefix: &str,
+    ) -> Option<Vec<CompletionItem>> {
+        let re = Regex::new(r"[a-zA-Z0-9._]+").unwrap();
+        let parts: Vec<&str> = re.find_iter(prefix).map(|mat| mat.as_str()).collect();
+        if parts.len() == 4 && parts[0] == "from" && parts[2] == "import" {
+            // We are in a "from X import Y" statement.
+            let name = parts[1];
+            let partial = parts[3];
+            let descriptor = ModuleDescriptor::Name(name.to_string());
+            let env = match self.get_env(&descriptor) {
+                Some(env) => env,
+                None => {
+                    // The module isn't loaded, so we don't know what names it has.
+                    if name == "nat" && "Nat".starts_with(partial) {
+                        // Cheat to optimize the tutorial.
+                        // If we always loaded nat, we wouldn't need this.
                         return Some(vec![CompletionItem {
                             label: "Nat".to_string(),
                             kind: Some(tower_lsp::lsp_types::CompletionItemKind::CLASS),
@@ -848,6 +1742,18 @@ impl Project {
     // Checks that the given expression can be parsed and turned back into code.
     #[cfg(test)]
     fn check_code_into(&mut self, module_name: &str, input: &str, expected: &str) {
+        let style = crate::code_style::CodeStyle::canonical();
+        self.check_styled_code(module_name, input, &style, expected);
+    }
+
+    #[cfg(test)]
+    fn check_styled_code(
+        &mut self,
+        module_name: &str,
+        input: &str,
+        style: &crate::code_style::CodeStyle,
+        expected: &str,
+    ) {
         use crate::expression::Expression;
         let module_id = self.expect_ok(module_name);
         let expression = Expression::expect_value(input);
@@ -858,7 +1764,7 @@ impl Project {
             .expect("could not evaluate");
         let output = env
             .bindings
-            .value_to_code(&value)
+            .value_to_code_with_style(&value, style)
             .expect("could not convert to code");
 
         if output != expected {
@@ -894,6 +1800,8 @@ impl Project {
 
 #[cfg(test)]
 mod tests {
+    use crate::binding_map::PotentialValue;
+    use crate::code_style::CodeStyle;
     use crate::environment::LineType;
 
     use super::*;
@@ -1082,6 +1990,192 @@ mod tests {
         p.expect_build_ok();
     }
 
+    #[test]
+    fn test_code_styles() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            class Nat {
+                define add(self, other: Nat) -> Nat { axiom }
+                define mul(self, other: Nat) -> Nat { axiom }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            import nat
+            type Nat: nat.Nat
+            let z: Nat = nat.zero
+        "#,
+        );
+        let canonical = CodeStyle::canonical();
+        p.check_styled_code("main", "z + z * z", &canonical, "z + z * z");
+
+        let qualified = CodeStyle {
+            qualified_names: true,
+            ..CodeStyle::canonical()
+        };
+        p.check_styled_code("main", "z", &qualified, "nat.zero");
+        p.check_styled_code(
+            "main",
+            "forall(x: Nat) { x = z }",
+            &qualified,
+            "forall(x0: nat.Nat) { x0 = nat.zero }",
+        );
+
+        let full_parens = CodeStyle {
+            full_parens: true,
+            ..CodeStyle::canonical()
+        };
+        p.check_styled_code("main", "z + z * z", &full_parens, "z + (z * z)");
+
+        let methods = CodeStyle {
+            infix_operators: false,
+            ..CodeStyle::canonical()
+        };
+        p.check_styled_code("main", "z + z * z", &methods, "z.add(z.mul(z))");
+
+        let sets = CodeStyle {
+            set_builder: true,
+            ..CodeStyle::canonical()
+        };
+        p.check_styled_code(
+            "main",
+            "{ x: Nat | x = z }",
+            &canonical,
+            "function(x0: Nat) { x0 = z }",
+        );
+        p.check_styled_code("main", "{ x: Nat | x = z }", &sets, "{ x0: Nat | x0 = z }");
+        p.check_styled_code(
+            "main",
+            "function(x: Nat, y: Nat) { x = y }",
+            &sets,
+            "function(x0: Nat, x1: Nat) { x0 = x1 }",
+        );
+
+        let narrow = CodeStyle {
+            max_width: Some(30),
+            ..CodeStyle::canonical()
+        };
+        p.check_styled_code(
+            "main",
+            "forall(x: Nat) { x + z = z + x }",
+            &narrow,
+            "forall(x0: Nat) {\n    x0 + z = z + x0\n}",
+        );
+    }
+
+    #[test]
+    fn test_qualified_attributes() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            type Nat: axiom
+            class Nat {
+                define add(self, other: Nat) -> Nat { axiom }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from nat import Nat
+            theorem goal(a: Nat, b: Nat) { a.(nat.add)(b) = Nat.(nat.add)(a, b) }
+        "#,
+        );
+        p.expect_ok("main");
+        p.check_code_into("main", "nat.Nat.(nat.add)", "Nat.add");
+        p.mock(
+            "/mock/bad.ac",
+            r#"
+            from nat import Nat
+            theorem goal(a: Nat, b: Nat) { a.(nat.sub)(b) = a }
+        "#,
+        );
+        p.expect_module_err("bad");
+    }
+
+    #[test]
+    fn test_extending_imported_classes() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            type Nat: axiom
+            class Nat {
+                define add(self, other: Nat) -> Nat { axiom }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/double.ac",
+            r#"
+            from nat import Nat
+            class Nat {
+                define double(self) -> Nat { self + self }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/other.ac",
+            r#"
+            from nat import Nat
+            class Nat {
+                define double(self) -> Nat { self }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from nat import Nat
+            import double
+            theorem goal(a: Nat) { a.double = a + a }
+        "#,
+        );
+        p.expect_ok("main");
+        p.check_code("main", "forall(x0: Nat) { x0.double = x0 + x0 }");
+
+        // When two modules add the same attribute, it has to be qualified.
+        p.mock(
+            "/mock/both.ac",
+            r#"
+            from nat import Nat
+            import double
+            import other
+            theorem goal(a: Nat) { a.(double.double) = a.(other.double) }
+        "#,
+        );
+        p.expect_ok("both");
+        p.mock(
+            "/mock/ambiguous.ac",
+            r#"
+            from nat import Nat
+            import double
+            import other
+            theorem goal(a: Nat) { a.double = a }
+        "#,
+        );
+        p.expect_module_err("ambiguous");
+
+        // Extensions can't replace attributes the class already has.
+        p.mock(
+            "/mock/replace.ac",
+            r#"
+            from nat import Nat
+            class Nat {
+                define add(self, other: Nat) -> Nat { other }
+            }
+        "#,
+        );
+        p.expect_module_err("replace");
+    }
+
     #[test]
     fn test_imported_member_functions() {
         let mut p = Project::new_mock();
@@ -1111,6 +2205,71 @@ mod tests {
         p.check_code_into("main", "boolpair.BoolPair.second", "BoolPair.second");
     }
 
+    #[test]
+    fn test_imported_operator_precedence() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/strs.ac",
+            r#"
+            type Str: axiom
+            type Num: axiom
+            type Fn: axiom
+            class Str {
+                define mul(self, other: Str) -> Num { axiom }
+                @precedence(12) define pipe(self, g: Fn) -> Str { axiom }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/fns.ac",
+            r#"
+            type Fn: axiom
+            class Fn {
+                define pipe(self, g: Fn) -> Fn { axiom }
+                @precedence(8) define compose(self, g: Fn) -> Fn { axiom }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from strs import Str, Num, Fn
+            let s: Str = axiom
+            let f: Fn = axiom
+            let n: Num = s * s |> f
+        "#,
+        );
+        p.expect_ok("main");
+        p.check_code("main", "n");
+        p.check_code_into("main", "s * (s |> f)", "s * s |> f");
+
+        p.mock(
+            "/mock/both.ac",
+            r#"
+            import strs
+            import fns
+        "#,
+        );
+        p.mock(
+            "/mock/conflict.ac",
+            r#"
+            type Fn: axiom
+            class Fn {
+                @precedence(9) define pipe(self, g: Fn) -> Fn { axiom }
+            }
+        "#,
+        );
+        p.expect_ok("both");
+        p.mock(
+            "/mock/bad.ac",
+            r#"
+            import strs
+            import conflict
+        "#,
+        );
+        p.expect_module_err("bad");
+    }
+
     #[test]
     fn test_structure_aliasing() {
         let mut p = Project::new_mock();
@@ -1127,6 +2286,52 @@ mod tests {
         p.check_code_into("stuff", "Bar.member", "Foo.member");
     }
 
+    #[test]
+    fn test_alias_chains() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/base.ac",
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define double(n: Nat) -> Nat { axiom }
+            "#,
+        );
+        p.mock(
+            "/mock/mid.ac",
+            r#"
+            from base import Nat, double
+            type N: Nat
+            let twice: N -> N = double
+            "#,
+        );
+        p.mock(
+            "/mock/top.ac",
+            r#"
+            from mid import N, twice
+            type M: N
+            let again: M -> M = twice
+            "#,
+        );
+        p.mock("/mock/loop.ac", "type A: A");
+        p.mock("/mock/loop2.ac", "type A: A -> Bool");
+        p.expect_ok("top");
+        p.check_code_into("top", "again(M.zero)", "twice(N.zero)");
+        let top = p
+            .get_env(&ModuleDescriptor::Name("top".to_string()))
+            .unwrap();
+        let value = top.bindings.get_constant_value("again").unwrap();
+        let PotentialValue::Resolved(value) = value else {
+            panic!("unresolved alias");
+        };
+        let base = p.load_module_by_name("base").unwrap();
+        assert_eq!(value.as_simple_constant(), Some((base, "double")));
+        p.expect_module_err("loop");
+        p.expect_module_err("loop2");
+    }
+
     #[test]
     fn test_names_imported_via_from() {
         let mut p = Project::new_mock();
@@ -1158,41 +2363,709 @@ mod tests {
     }
 
     #[test]
-    fn test_build_catches_unsolved_solve_blocks() {
-        let mut p = Project::new_mock();
-        p.mock("/mock/main.ac", "solve false by {\n}");
-        p.expect_ok("main");
-        p.expect_build_fails();
-    }
-
-    #[test]
-    fn test_imported_numbers_codegen_basic() {
+    fn test_aliases_keep_theorem_status() {
         let mut p = Project::new_mock();
         p.mock(
             "/mock/nat.ac",
             r#"
             inductive Nat {
-                0
+                zero
                 suc(Nat)
             }
-
-            numerals Nat
-
-            class Nat {
-                define add(self, other: Nat) -> Nat {
-                    0
-                }
-            }
-        "#,
+            define next(n: Nat) -> Nat { Nat.suc(n) }
+            define double(n: Nat) -> Nat { axiom }
+            axiom double_comm(a: Nat, b: Nat) { double(a) = double(b) }
+            "#,
         );
         p.mock(
             "/mock/main.ac",
             r#"
-            from nat import Nat
+            from nat import Nat, next, double, double_comm
+            let also_next: Nat -> Nat = next
+            theorem goal(n: Nat) { double(n) = double(Nat.zero) } by {
+                double_comm(n, Nat.zero)
+            }
             "#,
         );
-        p.check_code_into("main", "nat.Nat.0", "Nat.0");
-        p.check_code_into("main", "Nat.suc(Nat.0)", "Nat.0.suc");
+        p.expect_ok("main");
+        let env = p
+            .get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+        assert!(env.bindings.is_theorem("double_comm"));
+        assert!(!env.bindings.is_theorem("double"));
+        assert!(env.get_definition("double_comm").is_some());
+        assert!(env.get_definition("next").is_some());
+        assert!(env.get_definition("also_next").is_some());
+    }
+
+    #[test]
+    fn test_tag_filter() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/lib.ac",
+            r#"
+            let thing1: Bool = axiom
+            let thing2: Bool = axiom
+            @tag(algebra) axiom same {
+                thing1 = thing2
+            }
+            @tag(order) define flip(b: Bool) -> Bool {
+                not b
+            }
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            import lib
+            theorem goal {
+                lib.thing2 = lib.thing1
+            }
+            "#,
+        );
+        let main_id = p.load_module_by_name("main").unwrap();
+        let env = p
+            .get_env(&ModuleDescriptor::Name("lib".to_string()))
+            .unwrap();
+        let all = env.exported_facts(&TagFilter::default()).len();
+        let without_order = env.exported_facts(&TagFilter::excluding(["order"])).len();
+        assert_eq!(without_order, all - 1);
+        assert_eq!(
+            p.imported_facts(main_id, &TagFilter::excluding(["algebra", "order"]))
+                .len(),
+            all - 2
+        );
+        p.expect_build_ok();
+
+        // Without the tagged axiom, the goal can't be proved
+        p.set_tag_filter(TagFilter::excluding(["algebra"]));
+        p.expect_build_fails();
+    }
+
+    #[test]
+    fn test_relevance_filter() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/lib.ac",
+            r#"
+            let thing1: Bool = axiom
+            let thing2: Bool = axiom
+            let thing3: Bool = axiom
+            let thing4: Bool = axiom
+            let thing5: Bool = axiom
+            axiom one_two {
+                thing1 = thing2
+            }
+            axiom two_three {
+                thing2 = thing3
+            }
+            axiom four_five {
+                thing4 = thing5
+            }
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            import lib
+            theorem goal {
+                lib.thing1 = lib.thing3
+            }
+            "#,
+        );
+        let main_id = p.load_module_by_name("main").unwrap();
+        let env = p
+            .get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+        let node = env.iter_goals().next().unwrap();
+        let goal_context = node.goal_context().unwrap();
+        let all = node.usable_facts(&p);
+        let count = |filter: RelevanceFilter| {
+            filter
+                .select(all.clone(), goal_context.goal.value(), main_id)
+                .len()
+        };
+
+        // The goal triggers one_two and two_three, but nothing leads to four_five.
+        assert_eq!(count(RelevanceFilter::default()), all.len() - 1);
+
+        // With no tiers, only the facts without constants get through.
+        assert_eq!(count(RelevanceFilter::new(1.5, 0)), all.len() - 3);
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define double(n: Nat) -> Nat {
+                match n {
+                    Nat.zero {
+                        Nat.zero
+                    }
+                    Nat.suc(pred) {
+                        Nat.suc(Nat.suc(double(pred)))
+                    }
+                }
+            }
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from nat import Nat, double
+            let two: Nat = Nat.suc(Nat.suc(Nat.zero))
+            let also_two: Nat = two
+            theorem double_zero {
+                double(Nat.zero) = Nat.zero
+            }
+            "#,
+        );
+        p.expect_ok("main");
+        let nat = p.load_module_by_name("nat").unwrap();
+        let main = p.load_module_by_name("main").unwrap();
+        let graph = p.dependency_graph();
+        let deps = |module_id: ModuleId, name: &str| {
+            let mut names: Vec<_> = graph[&(module_id, name.to_string())]
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(deps(nat, "Nat.zero"), Vec::<String>::new());
+        assert_eq!(deps(nat, "double"), vec!["Nat.suc", "Nat.zero"]);
+        assert_eq!(deps(main, "two"), vec!["Nat.suc", "Nat.zero"]);
+        assert_eq!(deps(main, "double_zero"), vec!["Nat.zero", "double"]);
+        assert!(!graph.contains_key(&(main, "also_two".to_string())));
+        assert!(graph[&(main, "double_zero".to_string())].contains(&(nat, "double".to_string())));
+    }
+
+    #[test]
+    fn test_axiom_audit() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/lib.ac",
+            r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            axiom a_true { a }
+            axiom a_imp_b { a implies b }
+            axiom unused { c }
+            theorem b_true { b }
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from lib import a, b, b_true
+            theorem a_and_b { a and b } by {
+                b_true
+            }
+            theorem taut { a or not a }
+            "#,
+        );
+        p.add_target_by_name("lib");
+        p.add_target_by_name("main");
+        let mut builder = p.builder(|_| {});
+        builder.create_axiom_audit();
+        p.build(&mut builder);
+        assert_eq!(builder.status, BuildStatus::Good);
+        let report = builder.axiom_audit.unwrap().report(&p);
+        assert_eq!(
+            report,
+            vec![
+                "lib.b_true: lib.a_imp_b, lib.a_true",
+                "main.a_and_b: lib.a_imp_b, lib.a_true",
+                "main.taut: no axioms",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minimize_premises() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            axiom a_true { a }
+            axiom a_imp_b { a implies b }
+            axiom c_imp_b { c implies b }
+            axiom unused { c or not c }
+            theorem b_true { b }
+            "#,
+        );
+        p.set_minimize_premises(true);
+        p.add_target_by_name("main");
+        let mut builder = p.builder(|_| {});
+        builder.create_axiom_audit();
+        p.build(&mut builder);
+        assert_eq!(builder.status, BuildStatus::Good);
+        let report = builder.axiom_audit.unwrap().report(&p);
+        assert_eq!(report, vec!["main.b_true: main.a_imp_b, main.a_true"]);
+    }
+
+    #[test]
+    fn test_minimized_premises_are_cached() {
+        let mut p = Project::new_mock();
+        let main_text = r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            axiom a_true { a }
+            axiom a_imp_b { a implies b }
+            axiom c_imp_b { c implies b }
+            axiom unused { c or not c }
+            theorem b_true { b }
+            "#;
+        p.mock("/mock/main.ac", main_text);
+        p.set_minimize_premises(true);
+        assert_eq!(p.expect_build_ok(), 1);
+        let main = ModuleDescriptor::Name("main".to_string());
+        let expected = BTreeSet::from([
+            "main: the 'a_imp_b' axiom".to_string(),
+            "main: the 'a_true' axiom".to_string(),
+        ]);
+        let premises = |p: &Project| {
+            p.build_cache
+                .get(&main)
+                .unwrap()
+                .get_premises("b_true")
+                .cloned()
+        };
+        assert_eq!(premises(&p), Some(expected.clone()));
+
+        // After an edit, the goal is proved again from the cached premises, even without
+        // minimizing, and they stay in the cache.
+        p.set_minimize_premises(false);
+        let touched_main = format!("// Touch\n{}", main_text);
+        p.update_file(PathBuf::from("/mock/main.ac"), &touched_main, 1)
+            .expect("update failed");
+        assert_eq!(p.expect_build_ok(), 1);
+        assert_eq!(premises(&p), Some(expected));
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            let a: Bool = axiom
+            @max_active(100) theorem goal {
+                a or not a
+            } by {
+                a or not a
+            }
+            "#,
+        );
+        let main = ModuleDescriptor::Name("main".to_string());
+        let mut overrides = LimitOverrides::default();
+        overrides.set("max_active", 50);
+        overrides.set("max_clauses", 1000);
+        p.set_module_limits(main.clone(), overrides);
+        p.load_module_by_name("main").unwrap();
+        let env = p.get_env(&main).unwrap();
+        for node in env.iter_goals() {
+            // The theorem's limit applies inside its proof too.
+            let limits = p.goal_limits(&node.goal_context().unwrap());
+            assert_eq!(limits.max_active, 100);
+            assert_eq!(limits.max_clauses, Some(1000));
+            assert_eq!(limits.time, SearchLimits::default().time);
+        }
+    }
+
+    #[test]
+    fn test_portfolio() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            axiom a_true { a }
+            axiom a_imp_b { a implies b }
+            axiom b_imp_c { b implies c }
+            theorem goal { c }
+            "#,
+        );
+        // The first strategy can't activate enough clauses to find a proof.
+        p.set_portfolio(vec![
+            ProverConfig {
+                name: "cramped".to_string(),
+                limits: SearchLimits {
+                    max_active: 1,
+                    ..SearchLimits::default()
+                },
+                ..ProverConfig::default()
+            },
+            ProverConfig {
+                name: "roomy".to_string(),
+                ..ProverConfig::default()
+            },
+        ]);
+        let main_id = p.load_module_by_name("main").unwrap();
+        let env = p
+            .get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+        let node = env.iter_goals().next().unwrap();
+        let goal_context = node.goal_context().unwrap();
+        let facts = node.usable_facts(&p);
+
+        assert!(p.run_portfolio(&facts, &goal_context).is_some());
+        assert_eq!(
+            p.portfolio_winner(main_id, &goal_context.name),
+            Some("roomy".to_string())
+        );
+    }
+
+    #[test]
+    fn test_compiled_modules() {
+        let mut p = Project::new_mock();
+        p.set_use_compiled_modules(true);
+        let nat_text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            axiom suc_ne_zero(n: Nat) { suc(n) != zero }
+            let one: Nat = suc(zero)
+            theorem one_ne_zero { one != zero }
+            "#;
+        let main_text = r#"
+            from nat import zero, one
+            theorem goal { zero != one }
+            "#;
+        p.mock("/mock/nat.ac", nat_text);
+        p.mock("/mock/main.ac", main_text);
+        p.expect_build_ok();
+        assert!(p
+            .mock_compiled_files
+            .contains_key(&PathBuf::from("/mock/build/nat.acm")));
+
+        // Changing main reloads everything, but nat is unchanged, so it comes from its
+        // compiled artifact. It has no proofs left to verify.
+        let touched_main = format!("// Touch\n{}", main_text);
+        p.update_file(PathBuf::from("/mock/main.ac"), &touched_main, 1)
+            .expect("update failed");
+        let nat = p
+            .get_env(&ModuleDescriptor::Name("nat".to_string()))
+            .unwrap();
+        assert_eq!(nat.iter_goals().count(), 0);
+        assert!(nat.bindings.is_theorem("one_ne_zero"));
+        let main = p
+            .get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+        assert_eq!(main.iter_goals().count(), 1);
+        p.expect_build_ok();
+
+        // Now both modules load from their artifacts, with different module ids than they
+        // were compiled with.
+        p.update_file(PathBuf::from("/mock/nat.ac"), nat_text, 1)
+            .expect("update failed");
+        let nat_id = p.load_module_by_name("nat").unwrap();
+        let main = p
+            .get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+        assert_eq!(main.iter_goals().count(), 0);
+        assert_eq!(main.bindings.direct_dependencies(), vec![nat_id]);
+        p.expect_build_ok();
+
+        // Changing nat makes both artifacts out of date.
+        let changed_nat = format!("{}\ntheorem zero_eq_zero {{ zero = zero }}\n", nat_text);
+        p.update_file(PathBuf::from("/mock/nat.ac"), &changed_nat, 2)
+            .expect("update failed");
+        let main = p
+            .get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+        assert_eq!(main.iter_goals().count(), 1);
+        p.expect_build_ok();
+    }
+
+    #[test]
+    fn test_stale_compiled_module_skips_dependencies() {
+        let mut p = Project::new_mock();
+        p.set_use_compiled_modules(true);
+        let nat_text = "type Nat: axiom\nlet zero: Nat = axiom\n";
+        p.mock("/mock/nat.ac", nat_text);
+        p.mock(
+            "/mock/main.ac",
+            "from nat import zero\ntheorem goal { zero = zero }\n",
+        );
+        p.expect_build_ok();
+
+        // A new project with the same artifacts, where main no longer imports nat.
+        // Only the stale artifact for main mentions nat, so nat shouldn't get loaded.
+        let mut p2 = Project::new_mock();
+        p2.set_use_compiled_modules(true);
+        p2.mock_compiled_files = p.mock_compiled_files.clone();
+        p2.open_files
+            .insert(PathBuf::from("/mock/nat.ac"), (nat_text.to_string(), 0));
+        p2.mock("/mock/main.ac", "theorem goal { true }\n");
+        p2.load_module_by_name("main").unwrap();
+        let nat = ModuleDescriptor::Name("nat".to_string());
+        assert!(!p2.module_map.contains_key(&nat));
+    }
+
+    #[test]
+    fn test_compiled_modules_are_deterministic() {
+        let compile = || {
+            let mut p = Project::new_mock();
+            p.mock(
+                "/mock/nat.ac",
+                r#"
+                inductive Nat {
+                    zero
+                    suc(Nat)
+                }
+                "#,
+            );
+            p.mock(
+                "/mock/lib.ac",
+                r#"
+                from nat import Nat
+                type N: Nat
+                let one: N = Nat.suc(Nat.zero)
+                let also_one: N = one
+                /// Two.
+                let two: N = Nat.suc(one)
+                define plus_two(n: N) -> N { Nat.suc(Nat.suc(n)) }
+                axiom two_ne_one { two != one }
+                theorem three_ne_one { plus_two(one) != one }
+                "#,
+            );
+            let lib = p.expect_ok("lib");
+            let env = p.get_env_by_id(lib).unwrap();
+            let hash = p.modules[lib as usize].hash.clone().unwrap();
+            CompiledModule::new(&p, env, hash).serialize()
+        };
+        assert!(compile() == compile());
+    }
+
+    #[test]
+    fn test_exported_names() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define double(n: Nat) -> Nat { axiom }
+            "#,
+        );
+        p.mock(
+            "/mock/facade.ac",
+            r#"
+            from nat import Nat
+            export Nat
+            export nat.double
+            "#,
+        );
+        p.mock(
+            "/mock/hidden.ac",
+            r#"
+            from nat import Nat
+            "#,
+        );
+        let main = PathBuf::from("/mock/main.ac");
+        p.mock(
+            main.to_str().unwrap(),
+            r#"
+            from facade import Nat, double
+            theorem goal(n: Nat) { double(n) = double(n) }
+            "#,
+        );
+        p.mock("/mock/bad.ac", "from hidden import Nat");
+        p.mock("/mock/local.ac", "let b: Bool = true\nexport b");
+        p.mock("/mock/unknown.ac", "export Nat");
+        p.expect_ok("facade");
+        p.expect_ok("main");
+        p.expect_module_err("bad");
+        p.expect_module_err("local");
+        p.expect_module_err("unknown");
+        p.check_code("main", "double(Nat.zero)");
+
+        // Exported names are suggested for import, but other imported names aren't.
+        for (prefix, expected) in [
+            ("from facade import d", vec!["double"]),
+            ("from facade import N", vec!["Nat"]),
+            ("from hidden import N", vec![]),
+        ] {
+            let completions = p.get_completions(Some(&main), 0, prefix).unwrap();
+            let labels: Vec<_> = completions.iter().map(|c| c.label.as_str()).collect();
+            assert_eq!(labels, expected, "completions for '{}'", prefix);
+        }
+    }
+
+    #[test]
+    fn test_wildcard_imports() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define double(n: Nat) -> Nat { axiom }
+            define id<T>(x: T) -> T { x }
+            theorem double_zero { double(Nat.zero) = double(Nat.zero) }
+            "#,
+        );
+        p.mock(
+            "/mock/int.ac",
+            r#"
+            from nat import Nat
+            type Int: axiom
+            let zero: Int = axiom
+            define double(n: Int) -> Int { axiom }
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from nat import *
+            let two: Nat = double(Nat.suc(Nat.zero))
+            "#,
+        );
+        p.mock(
+            "/mock/thm.ac",
+            "from nat import *\nlet b: Bool = double_zero",
+        );
+        p.mock(
+            "/mock/generic.ac",
+            "from nat import *\nlet n: Nat = id(Nat.zero)",
+        );
+        p.mock("/mock/both.ac", "from nat import *\nfrom int import *");
+        p.mock(
+            "/mock/local.ac",
+            "let double: Bool = true\nfrom nat import *",
+        );
+        p.expect_ok("main");
+        p.check_code("main", "double(Nat.zero)");
+
+        // Theorems and generic constants don't come along.
+        p.expect_module_err("thm");
+        p.expect_module_err("generic");
+
+        // Both modules define double, so the second import fails.
+        let module_id = p.load_module_by_name("both").unwrap();
+        match p.get_module_by_id(module_id) {
+            LoadState::Error(e) => assert!(
+                e.to_string().contains("double (also imported from nat)"),
+                "unexpected error: {}",
+                e
+            ),
+            _ => panic!("expected a wildcard conflict"),
+        }
+        p.expect_module_err("local");
+    }
+
+    #[test]
+    fn test_nested_modules() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            module nat {
+                inductive Nat {
+                    zero
+                    suc(Nat)
+                }
+                define double(n: Nat) -> Nat { axiom }
+                theorem double_self(n: Nat) { double(n) = double(n) }
+            }
+            let two: nat.Nat = nat.Nat.suc(nat.Nat.suc(nat.Nat.zero))
+            theorem goal { nat.double(two) = nat.double(two) }
+            "#,
+        );
+        p.mock(
+            "/mock/leak.ac",
+            "module foo {\n let b: Bool = true\n}\nlet c: Bool = b",
+        );
+        p.mock("/mock/bad.ac", "module foo {\n let b: Bool = c\n}");
+        p.mock("/mock/upper.ac", "module Foo {\n}");
+        p.mock("/mock/dup.ac", "module foo {\n}\nmodule foo {\n}");
+        p.expect_ok("main");
+        p.check_code("main", "nat.double(two)");
+
+        // The nested module's names stay in its own namespace.
+        p.expect_module_err("leak");
+        p.expect_module_err("bad");
+        p.expect_module_err("upper");
+        p.expect_module_err("dup");
+
+        // Building the file also proves the goals in its nested modules.
+        let main_text = p.read_file(&PathBuf::from("/mock/main.ac")).unwrap();
+        let mut p = Project::new_mock();
+        p.mock("/mock/main.ac", &main_text);
+        p.add_target_by_name("main");
+        assert_eq!(p.expect_build_ok(), 2);
+    }
+
+    #[test]
+    fn test_build_checks_nested_modules() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            module foo {
+                let b: Bool = axiom
+                theorem goal { b }
+            }
+            "#,
+        );
+        p.add_target_by_name("main");
+        p.expect_build_fails();
+    }
+
+    #[test]
+    fn test_build_catches_unsolved_solve_blocks() {
+        let mut p = Project::new_mock();
+        p.mock("/mock/main.ac", "solve false by {\n}");
+        p.expect_ok("main");
+        p.expect_build_fails();
+    }
+
+    #[test]
+    fn test_imported_numbers_codegen_basic() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            inductive Nat {
+                0
+                suc(Nat)
+            }
+
+            numerals Nat
+
+            class Nat {
+                define add(self, other: Nat) -> Nat {
+                    0
+                }
+            }
+        "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from nat import Nat
+            "#,
+        );
+        p.check_code_into("main", "nat.Nat.0", "Nat.0");
+        p.check_code_into("main", "Nat.suc(Nat.0)", "Nat.0.suc");
         p.check_code_into("main", "Nat.add(Nat.0, Nat.0)", "Nat.0 + Nat.0");
     }
 
@@ -1364,7 +3237,7 @@ mod tests {
         check("from nat import N", 0, &["Nat"]);
         check("ba", 7, &["bar"]);
         check("fo", 7, &["forall", "foo"]);
-        check("b", 7, &["by", "bar", "bop"]);
+        check("b", 7, &["by", "by_contradiction", "bar", "bop"]);
         check("Nat.s", 7, &["suc"]);
         check("foo.s", 7, &["suc"]);
         check("nat.N", 7, &["Nat"]);
@@ -1374,6 +3247,32 @@ mod tests {
         check("foo.", 7, &["0", "induction", "suc"]);
     }
 
+    #[test]
+    fn test_by_contradiction_completions() {
+        let mut p = Project::new_mock();
+        let main = PathBuf::from("/mock/main.ac");
+        p.mock(
+            main.to_str().unwrap(),
+            r#"
+            let thing: Bool = axiom
+            theorem goal {
+                thing or not thing
+            } by {
+                // This should be line 5. Let's test completions here.
+            }
+            "#,
+        );
+        p.get_env(&ModuleDescriptor::Name("main".to_string()))
+            .unwrap();
+
+        let labels = |prefix: &str| -> Vec<String> {
+            let completions = p.get_completions(Some(&main), 5, prefix).unwrap();
+            completions.into_iter().map(|c| c.label).collect()
+        };
+        assert_eq!(labels("by"), vec!["by", "by_contradiction"]);
+        assert_eq!(labels("by_"), vec!["by_contradiction"]);
+    }
+
     #[test]
     fn test_build_cache() {
         let mut p = Project::new_mock();
@@ -1442,4 +3341,236 @@ mod tests {
         let num_success = p.expect_build_ok();
         assert_eq!(num_success, 2);
     }
+
+    #[test]
+    fn test_unused_hypothesis() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            theorem goal(a: Bool, b: Bool) {
+                a and b -> a
+            }
+        "#,
+        );
+        assert!(p.add_target_by_name("main"));
+        let (status, events, _) = p.sync_build();
+        assert_eq!(status, BuildStatus::Good);
+        let messages = events
+            .iter()
+            .filter_map(|e| e.diagnostic.as_ref())
+            .map(|d| d.message.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "hypothesis 'b' is not used in the proof of goal, so it could be dropped. \
+                It could be stated as: theorem goal(a: Bool, b: Bool) { a -> a }"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_names() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/nat.ac",
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define double(n: Nat) -> Nat { axiom }
+            define triple(n: Nat) -> Nat { axiom }
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from nat import Nat, double, triple
+            theorem goal(n: Nat) {
+                double(n) = double(n)
+            } by {
+                let m: Nat = Nat.suc(n)
+                let k: Nat = Nat.suc(m)
+            }
+            "#,
+        );
+        assert!(p.add_target_by_name("main"));
+        let (status, events, _) = p.sync_build();
+        assert_eq!(status, BuildStatus::Good);
+        let messages = events
+            .iter()
+            .filter_map(|e| e.diagnostic.as_ref())
+            .map(|d| d.message.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec!["'triple' is never used", "'k' is never used"]
+        );
+    }
+
+    #[test]
+    fn test_implicit_shadowing_warns() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            type Nat: axiom
+            let k: Nat = axiom
+            forall(k: Nat) {
+                k = k
+            }
+            shadow forall(k: Nat) {
+                k = k
+            }
+            "#,
+        );
+        assert!(p.add_target_by_name("main"));
+        let (status, events, _) = p.sync_build();
+        assert_eq!(status, BuildStatus::Good);
+        let lines = events
+            .iter()
+            .filter_map(|e| e.diagnostic.as_ref())
+            .map(|d| d.range.start.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![3]);
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            theorem one {
+                true = true
+            }
+            theorem two {
+                false = false
+            }
+        "#,
+        );
+        assert!(p.add_target_by_name("main"));
+        let config = BenchmarkConfig {
+            iterations: 3,
+            ..BenchmarkConfig::default()
+        };
+        let report = p.benchmark(config);
+        assert_eq!(report.goals.len(), 2);
+        for goal in &report.goals {
+            assert_eq!(goal.outcomes.len(), 3);
+            assert_eq!(goal.num_success(), 3);
+        }
+    }
+
+    #[test]
+    fn test_conjunction_goals_split() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            axiom a_true { a }
+            axiom b_true { b }
+            theorem ab { a and b }
+            theorem abc { a and b and c }
+            "#,
+        );
+        p.add_target_by_name("main");
+        let (status, events, num_success) = p.sync_build();
+        assert_eq!(status, BuildStatus::Warning);
+        assert_eq!(num_success, 4);
+        let messages: Vec<_> = events
+            .iter()
+            .filter_map(|e| Some(e.diagnostic.as_ref()?.message.clone()))
+            .collect();
+        assert_eq!(messages, vec!["abc (part 3 of 3) could not be verified"]);
+        let (done, total) = events.last().unwrap().progress.unwrap();
+        assert_eq!((done, total), (5, 5));
+    }
+
+    #[test]
+    fn test_build_events_carry_search_stats() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            type Nat: axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom fa { f(a) }
+            theorem ga { g(a) }
+            theorem gb(b: Nat) { g(b) }
+            "#,
+        );
+        p.add_target_by_name("main");
+        let mut events = vec![];
+        let mut builder = p.builder(|event| events.push(event));
+        p.build(&mut builder);
+        let report = std::mem::take(&mut builder.search_report);
+        drop(builder);
+
+        // Both the proof and the failure report their statistics.
+        let stats: Vec<_> = events
+            .iter()
+            .filter_map(|e| e.search_stats.as_ref())
+            .collect();
+        assert_eq!(stats.len(), 2);
+        assert!(stats
+            .iter()
+            .all(|s| s.activations > 0 && s.scorer_calls > 0));
+        assert!(stats[1].generated > 0);
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            report.total().activations,
+            stats.iter().map(|s| s.activations).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_replaying_proofs() {
+        let mut p = Project::new_mock();
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_zero(x: Nat) { add(x, zero) = x }
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom fa { f(add(a, zero)) }
+            theorem ga { g(a) }
+            theorem gb(b: Nat) { f(b) -> g(add(b, zero)) }
+            "#;
+        p.mock("/mock/main.ac", text);
+
+        // There's nothing to replay until the proofs are recorded.
+        p.set_replay_proofs(true);
+        p.expect_build_fails();
+
+        p.set_replay_proofs(false);
+        p.set_record_proofs(true);
+        let num_success = p.expect_build_ok();
+        assert_eq!(num_success, 2);
+        let path = PathBuf::from("/mock/build/main.acp");
+        let bytes = p.mock_compiled_files.get(&path).unwrap().clone();
+        assert_eq!(ModuleProofs::deserialize(&bytes).unwrap().len(), 2);
+
+        p.set_record_proofs(false);
+        p.set_replay_proofs(true);
+        assert_eq!(p.expect_build_ok(), 2);
+
+        // Changing the module makes the saved proofs out of date.
+        let touched = format!("// Touch\n{}", text);
+        p.update_file(PathBuf::from("/mock/main.ac"), &touched, 1)
+            .expect("update failed");
+        p.expect_build_fails();
+    }
 }
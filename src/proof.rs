@@ -1,10 +1,14 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+use serde::Serialize;
+
 use crate::acorn_value::AcornValue;
 use crate::binding_map::BindingMap;
 use crate::clause::Clause;
+use crate::clause_arena::ClauseId;
 use crate::code_gen_error::CodeGenError;
+use crate::code_style::CodeStyle;
 use crate::display::DisplayClause;
 use crate::normalizer::Normalizer;
 use crate::proof_step::{ProofStep, ProofStepId, Rule};
@@ -90,6 +94,9 @@ struct ProofNode<'a> {
     // From the ProofStep
     depth: u32,
     printable: bool,
+
+    // Whether the ProofStep was a rewrite.
+    rewrite: bool,
 }
 
 impl<'a> ProofNode<'a> {
@@ -118,14 +125,14 @@ impl<'a> ProofNode<'a> {
                 if self.negated {
                     value = value.pretty_negate();
                 }
-                bindings.value_to_code(&value)
+                bindings.value_to_code_with_style(&value, &CodeStyle::proof())
             }
             NodeValue::Contradiction => Ok("false".to_string()),
             NodeValue::NegatedGoal(v) => {
                 if self.negated {
                     Err(CodeGenError::ExplicitGoal)
                 } else {
-                    bindings.value_to_code(v)
+                    bindings.value_to_code_with_style(v, &CodeStyle::proof())
                 }
             }
         }
@@ -168,6 +175,45 @@ pub struct Proof<'a> {
 
     // The difficulty of finding this proof.
     difficulty: Difficulty,
+
+    // The prover's clause arena ids for the steps, where it has them.
+    // These stay the same for a clause however the search goes, so exports use them.
+    clause_ids: HashMap<ProofStepId, ClauseId>,
+}
+
+// The derivation of a proof as a graph, for exporting.
+// There is a node for each step of the proof, including the ones that condense away.
+#[derive(Serialize)]
+struct ProofDag {
+    nodes: Vec<DagNode>,
+    edges: Vec<DagEdge>,
+}
+
+#[derive(Serialize)]
+struct DagNode {
+    id: String,
+    clause: String,
+    rule: String,
+
+    // Where an assumption comes from.
+    source: Option<String>,
+}
+
+// An edge goes from a premise to the step that used it.
+#[derive(Serialize)]
+struct DagEdge {
+    from: String,
+    to: String,
+    rule: String,
+}
+
+// Quotes a string for the dot language. Newlines become line breaks in labels.
+fn dot_quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
 }
 
 fn remove_edge(nodes: &mut Vec<ProofNode>, from: NodeId, to: NodeId) {
@@ -211,6 +257,7 @@ impl<'a> Proof<'a> {
             condensed: false,
             id_map: HashMap::new(),
             difficulty,
+            clause_ids: HashMap::new(),
         };
 
         let negated_goal = ProofNode {
@@ -221,6 +268,7 @@ impl<'a> Proof<'a> {
             sources: vec![],
             depth: 0,
             printable: false,
+            rewrite: false,
         };
         proof.nodes.push(negated_goal);
 
@@ -243,6 +291,7 @@ impl<'a> Proof<'a> {
             sources: vec![],
             depth: step.depth,
             printable: step.printable,
+            rewrite: matches!(step.rule, Rule::Rewrite(_)),
         });
 
         if let Rule::Assumption(info) = &step.rule {
@@ -261,11 +310,114 @@ impl<'a> Proof<'a> {
         self.all_steps.push((id, step));
     }
 
+    // Records the clause arena id for a step, so that exports can use it.
+    pub fn set_clause_id(&mut self, id: ProofStepId, clause_id: ClauseId) {
+        self.clause_ids.insert(id, clause_id);
+    }
+
+    // The name of a step in exports.
+    // Steps without a clause arena id fall back to their ids within the search.
+    fn export_id(&self, id: ProofStepId) -> String {
+        match (self.clause_ids.get(&id), id) {
+            (Some(clause_id), _) => format!("clause{}", clause_id),
+            (None, ProofStepId::Active(i)) => format!("active{}", i),
+            (None, ProofStepId::Passive(i)) => format!("passive{}", i),
+            (None, ProofStepId::Final) => "contradiction".to_string(),
+        }
+    }
+
+    fn to_dag(&self) -> ProofDag {
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        for (id, step) in &self.all_steps {
+            let to = self.export_id(*id);
+            let clause = if step.clause.is_impossible() {
+                "false".to_string()
+            } else {
+                DisplayClause {
+                    clause: &step.clause,
+                    normalizer: self.normalizer,
+                }
+                .to_string()
+            };
+            let source = match &step.rule {
+                Rule::Assumption(info) => Some(info.source.description()),
+                _ => None,
+            };
+            nodes.push(DagNode {
+                id: to.clone(),
+                clause,
+                rule: step.rule.name().to_string(),
+                source,
+            });
+            for premise in step.rule.premises() {
+                edges.push(DagEdge {
+                    from: self.export_id(premise),
+                    to: to.clone(),
+                    rule: step.rule.name().to_string(),
+                });
+            }
+            for simplifier in &step.simplification_rules {
+                edges.push(DagEdge {
+                    from: self.export_id(ProofStepId::Active(*simplifier)),
+                    to: to.clone(),
+                    rule: "Simplification".to_string(),
+                });
+            }
+        }
+        ProofDag { nodes, edges }
+    }
+
+    // The derivation of the proof in the Graphviz dot language.
+    pub fn to_dot(&self) -> String {
+        let dag = self.to_dag();
+        let mut lines = vec!["digraph proof {".to_string()];
+        for node in &dag.nodes {
+            let label = match &node.source {
+                Some(source) => format!("{}\n{}", node.clause, source),
+                None => node.clause.clone(),
+            };
+            lines.push(format!(
+                "  {} [label={}];",
+                dot_quote(&node.id),
+                dot_quote(&label)
+            ));
+        }
+        for edge in &dag.edges {
+            lines.push(format!(
+                "  {} -> {} [label={}];",
+                dot_quote(&edge.from),
+                dot_quote(&edge.to),
+                dot_quote(&edge.rule)
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    // The derivation of the proof as JSON, with a list of nodes and a list of edges.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.to_dag()).expect("proof dag should serialize")
+    }
+
     pub fn has_active_id(&self, active_id: usize) -> bool {
         let id = ProofStepId::Active(active_id);
         self.id_map.contains_key(&id)
     }
 
+    // The sources of all the assumptions this proof depends on, other than the negated goal.
+    // This includes steps that were condensed away.
+    pub fn iter_sources(&self) -> impl Iterator<Item = &Source> {
+        self.all_steps
+            .iter()
+            .filter_map(|(_, step)| match &step.rule {
+                Rule::Assumption(info) if info.source.source_type != SourceType::NegatedGoal => {
+                    Some(&info.source)
+                }
+                _ => None,
+            })
+    }
+
     // Contracts this node if possible.
     // (The goal and contradictions cannot be contracted.)
     //
@@ -338,6 +490,91 @@ impl<'a> Proof<'a> {
         false
     }
 
+    // Steps that prove exactly what some theorem says are replaced by citing the theorem,
+    // and steps that prove exactly what one of their premises says are dropped.
+    fn remove_redundant(&mut self) {
+        let mut citations: HashMap<&Clause, &Source> = HashMap::new();
+        for (_, step) in &self.all_steps {
+            if let Rule::Assumption(info) = &step.rule {
+                if info.source.source_type != SourceType::NegatedGoal {
+                    citations.entry(&step.clause).or_insert(&info.source);
+                }
+            }
+        }
+        for node_id in 1..self.nodes.len() as NodeId {
+            let node = &self.nodes[node_id as usize];
+            let clause = match node.value {
+                NodeValue::Clause(clause) => clause,
+                _ => continue,
+            };
+            if node.depth == 0 || node.is_isolated() {
+                continue;
+            }
+            if let Some(source) = citations.get(clause) {
+                // The node becomes an assumption, so it will be made implicit.
+                let premises = std::mem::take(&mut self.nodes[node_id as usize].premises);
+                for premise_id in premises {
+                    self.nodes[premise_id as usize]
+                        .consequences
+                        .retain(|x| *x != node_id);
+                }
+                let node = &mut self.nodes[node_id as usize];
+                node.sources = vec![source];
+                node.depth = 0;
+                continue;
+            }
+            let repeats_premise = node.premises.iter().any(|premise_id| {
+                let premise = &self.nodes[*premise_id as usize];
+                matches!(premise.value, NodeValue::Clause(c) if c == clause)
+            });
+            if repeats_premise {
+                self.contract(node_id);
+            }
+        }
+    }
+
+    // A rewrite that is only used as the input to another rewrite doesn't need its own line
+    // of code, because the later line can do both rewrites at once.
+    fn merge_rewrites(&mut self) {
+        for node_id in 1..self.nodes.len() as NodeId {
+            let node = &self.nodes[node_id as usize];
+            if !node.rewrite || node.consequences.len() != 1 {
+                continue;
+            }
+            let consequence = &self.nodes[node.consequences[0] as usize];
+            if consequence.rewrite {
+                self.contract(node_id);
+            }
+        }
+    }
+
+    // Removes the nodes that don't lead to a contradiction, and so aren't used in the proof.
+    // Removing a node can leave its premises unused, so this repeats until nothing changes.
+    fn remove_unused(&mut self) {
+        loop {
+            let mut changed = false;
+            for node_id in 1..self.nodes.len() as NodeId {
+                let node = &self.nodes[node_id as usize];
+                if !node.consequences.is_empty() || node.is_isolated() {
+                    continue;
+                }
+                if let NodeValue::Contradiction = node.value {
+                    continue;
+                }
+                let premises = std::mem::take(&mut self.nodes[node_id as usize].premises);
+                for premise_id in premises {
+                    self.nodes[premise_id as usize]
+                        .consequences
+                        .retain(|x| *x != node_id);
+                }
+                changed = true;
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+
     // Remove nodes that we don't want to turn into explicit lines of code.
     fn remove_implicit(&mut self) {
         for node_id in 0..self.nodes.len() as NodeId {
@@ -472,6 +709,9 @@ impl<'a> Proof<'a> {
     // Call just once.
     pub fn condense(&mut self) {
         assert!(!self.condensed);
+        self.remove_redundant();
+        self.merge_rewrites();
+        self.remove_unused();
         self.remove_implicit();
         self.try_to_make_direct(0);
         self.condensed = true;
@@ -571,7 +811,9 @@ impl<'a> Proof<'a> {
         if node.starts_reduction() {
             proven.insert(node_id);
             let condition = node.to_code(normalizer, bindings)?;
-            output.push(format!("{}if {} {{", "\t".repeat(tab_level), condition));
+            let tabs = "\t".repeat(tab_level);
+            let condition = condition.replace('\n', &format!("\n{}", tabs));
+            output.push(format!("{}if {} {{", tabs, condition));
             let contradiction = match self.find_contradiction(node_id) {
                 Some(id) => id,
                 None => {
@@ -615,7 +857,10 @@ impl<'a> Proof<'a> {
 
         match node.to_code(normalizer, bindings) {
             Ok(code) => {
-                output.push(format!("{}{}", "\t".repeat(tab_level), code));
+                // Wrapped code spans several lines, each of which needs the indentation.
+                for line in code.lines() {
+                    output.push(format!("{}{}", "\t".repeat(tab_level), line));
+                }
                 Ok(())
             }
             Err(e) => {
@@ -626,3 +871,103 @@ impl<'a> Proof<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::proof_step::Truthiness;
+    use crate::term::Term;
+
+    use super::*;
+
+    fn negated_goal(s: &str) -> ProofStep {
+        let source = Source {
+            source_type: SourceType::NegatedGoal,
+            ..Source::mock()
+        };
+        ProofStep::new_assumption(Clause::parse(s), Truthiness::Counterfactual, &source, None)
+    }
+
+    // Rewrites the first argument of the target's left side into the new subterm.
+    fn rewrite(
+        pattern_id: usize,
+        pattern: &ProofStep,
+        target_id: usize,
+        target: &ProofStep,
+        new_subterm: &str,
+    ) -> ProofStep {
+        let new_subterm = Term::parse(new_subterm);
+        let mut step = ProofStep::new_rewrite(
+            pattern_id,
+            pattern,
+            target_id,
+            target,
+            true,
+            &[0],
+            &new_subterm,
+        );
+        step.printable = true;
+        step
+    }
+
+    // The clauses that are still part of the condensed proof.
+    fn live_clauses(proof: &Proof) -> Vec<String> {
+        proof
+            .nodes
+            .iter()
+            .filter(|node| !node.is_isolated())
+            .filter_map(|node| match node.value {
+                NodeValue::Clause(clause) => Some(clause.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_condense_merges_rewrites() {
+        let normalizer = Normalizer::new();
+        let axiom = ProofStep::mock("c1(c2)");
+        let goal = negated_goal("not c1(c4)");
+        let first = ProofStep::mock("c3 = c2");
+        let second = ProofStep::mock("c4 = c3");
+        let mut r1 = rewrite(2, &first, 0, &axiom, "c3");
+        r1.depth = 1;
+        let mut r2 = rewrite(3, &second, 4, &r1, "c4");
+        r2.depth = 2;
+        let last = ProofStep::new_resolution(1, &goal, 5, &r2, Clause::impossible());
+        assert_eq!(r2.clause.to_string(), "c1(c4)");
+
+        let mut proof = Proof::new(&normalizer, &AcornValue::Bool(false), Difficulty::Simple);
+        proof.add_step(ProofStepId::Active(0), &axiom);
+        proof.add_step(ProofStepId::Active(1), &goal);
+        proof.add_step(ProofStepId::Active(2), &first);
+        proof.add_step(ProofStepId::Active(3), &second);
+        proof.add_step(ProofStepId::Active(4), &r1);
+        proof.add_step(ProofStepId::Active(5), &r2);
+        proof.add_step(ProofStepId::Final, &last);
+        proof.condense();
+        assert_eq!(live_clauses(&proof), vec!["c1(c4)"]);
+    }
+
+    #[test]
+    fn test_condense_cites_theorems() {
+        let normalizer = Normalizer::new();
+        let axiom = ProofStep::mock("c1(c2)");
+        let goal = negated_goal("not c1(c3)");
+        let eq = ProofStep::mock("c3 = c2");
+        let mut r = rewrite(2, &eq, 0, &axiom, "c3");
+        r.depth = 1;
+        let theorem = ProofStep::mock("c1(c3)");
+        let last = ProofStep::new_resolution(1, &goal, 3, &r, Clause::impossible());
+
+        let mut proof = Proof::new(&normalizer, &AcornValue::Bool(false), Difficulty::Simple);
+        proof.add_step(ProofStepId::Active(0), &axiom);
+        proof.add_step(ProofStepId::Active(1), &goal);
+        proof.add_step(ProofStepId::Active(2), &eq);
+        proof.add_step(ProofStepId::Active(3), &r);
+        proof.add_step(ProofStepId::Active(4), &theorem);
+        proof.add_step(ProofStepId::Final, &last);
+        proof.condense();
+        assert!(live_clauses(&proof).is_empty());
+        assert!(!proof.has_simplification());
+    }
+}
@@ -0,0 +1,865 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::atom::{Atom, AtomId};
+use crate::clause::Clause;
+use crate::clause_simplifier::ClauseSimplifier;
+use crate::literal::Literal;
+use crate::proof_step::{ProofStep, ProofStepId, Rule};
+use crate::term::Term;
+
+// The proof checker re-derives the steps of a proof from their parents, without using any of
+// the prover's indexes, unifiers, or inference code, so that a bug in them can't make an
+// unsound proof look valid.
+// Steps from rules it doesn't know, like splitting and the external solvers, are left
+// unchecked rather than failing, and so are steps that use higher-order unification.
+// The theory simplifications in the ClauseSimplifier are trusted.
+
+// The result of checking a single step.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Checked,
+    Unchecked,
+    Failed(String),
+}
+
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    pub checked: usize,
+    pub unchecked: usize,
+
+    // A description of each step that could not be re-derived.
+    pub failures: Vec<String>,
+}
+
+// Unification would need higher-order unification to continue.
+struct Unsupported;
+
+// A substitution for variables. Variables that aren't in it stay as they are.
+#[derive(Default)]
+struct Substitution {
+    map: HashMap<AtomId, Term>,
+}
+
+impl Substitution {
+    fn apply(&self, term: &Term) -> Term {
+        let args: Vec<Term> = term.args.iter().map(|arg| self.apply(arg)).collect();
+        if let Atom::Variable(i) = term.head {
+            if let Some(value) = self.map.get(&i) {
+                // An applied variable applies its value to the arguments.
+                let value = self.apply(value);
+                let mut all_args = value.args;
+                all_args.extend(args);
+                return Term::new(term.term_type, value.head_type, value.head, all_args);
+            }
+        }
+        Term::new(term.term_type, term.head_type, term.head, args)
+    }
+
+    fn occurs(&self, i: AtomId, term: &Term) -> bool {
+        if let Atom::Variable(j) = term.head {
+            if i == j {
+                return true;
+            }
+            if let Some(value) = self.map.get(&j) {
+                if self.occurs(i, value) {
+                    return true;
+                }
+            }
+        }
+        term.args.iter().any(|arg| self.occurs(i, arg))
+    }
+
+    // Finds a most general unifier, extending this substitution.
+    fn unify(&mut self, a: &Term, b: &Term) -> Result<bool, Unsupported> {
+        let a = self.apply(a);
+        let b = self.apply(b);
+        if a.term_type != b.term_type {
+            return Ok(false);
+        }
+        if (a.head.is_variable() && !a.args.is_empty())
+            || (b.head.is_variable() && !b.args.is_empty())
+        {
+            return Err(Unsupported);
+        }
+        if let Atom::Variable(i) = a.head {
+            if a == b {
+                return Ok(true);
+            }
+            if self.occurs(i, &b) {
+                return Ok(false);
+            }
+            self.map.insert(i, b);
+            return Ok(true);
+        }
+        if b.head.is_variable() {
+            return self.unify(&b, &a);
+        }
+        if a.head != b.head || a.head_type != b.head_type || a.args.len() != b.args.len() {
+            return Ok(false);
+        }
+        for (x, y) in a.args.iter().zip(&b.args) {
+            if !self.unify(x, y)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Extends this substitution so that the pattern becomes the target.
+    // Variables in the target are treated like constants.
+    fn match_terms(&mut self, pattern: &Term, target: &Term) -> bool {
+        if pattern.term_type != target.term_type {
+            return false;
+        }
+        if let Atom::Variable(i) = pattern.head {
+            // An applied variable matches the start of a longer application.
+            let n = pattern.args.len();
+            if target.args.len() < n {
+                return false;
+            }
+            let split = target.args.len() - n;
+            let value = Term::new(
+                pattern.head_type,
+                target.head_type,
+                target.head,
+                target.args[..split].to_vec(),
+            );
+            match self.map.get(&i) {
+                Some(existing) if *existing != value => return false,
+                Some(_) => {}
+                None => {
+                    self.map.insert(i, value);
+                }
+            }
+            return pattern
+                .args
+                .iter()
+                .zip(&target.args[split..])
+                .all(|(p, t)| self.match_terms(p, t));
+        }
+        if pattern.head != target.head
+            || pattern.head_type != target.head_type
+            || pattern.args.len() != target.args.len()
+        {
+            return false;
+        }
+        pattern
+            .args
+            .iter()
+            .zip(&target.args)
+            .all(|(p, t)| self.match_terms(p, t))
+    }
+}
+
+fn shift_term(term: &Term, shift: AtomId) -> Term {
+    let head = match term.head {
+        Atom::Variable(i) => Atom::Variable(i + shift),
+        head => head,
+    };
+    let args = term.args.iter().map(|arg| shift_term(arg, shift)).collect();
+    Term::new(term.term_type, term.head_type, head, args)
+}
+
+// Renames the variables of a clause, so that it doesn't share any with a clause that has
+// fewer than shift of them.
+fn shift_literals(clause: &Clause, shift: AtomId) -> Vec<Literal> {
+    clause
+        .literals
+        .iter()
+        .map(|literal| Literal {
+            positive: literal.positive,
+            left: shift_term(&literal.left, shift),
+            right: shift_term(&literal.right, shift),
+        })
+        .collect()
+}
+
+// The most states to try when searching for the simplifications the prover did.
+const MAX_SIMPLIFICATION_STATES: usize = 1000;
+
+fn shift_literal(literal: &Literal, shift: AtomId) -> Literal {
+    Literal {
+        positive: literal.positive,
+        left: shift_term(&literal.left, shift),
+        right: shift_term(&literal.right, shift),
+    }
+}
+
+fn binds_all(sub: &Substitution, term: &Term) -> bool {
+    if let Atom::Variable(i) = term.head {
+        if !sub.map.contains_key(&i) {
+            return false;
+        }
+    }
+    term.args.iter().all(|arg| binds_all(sub, arg))
+}
+
+// Whether the literal is an instance of the pattern, in either orientation.
+fn is_instance(pattern: &Literal, literal: &Literal) -> bool {
+    pattern.positive == literal.positive
+        && [
+            (&literal.left, &literal.right),
+            (&literal.right, &literal.left),
+        ]
+        .into_iter()
+        .any(|(left, right)| {
+            let mut sub = Substitution::default();
+            sub.match_terms(&pattern.left, left) && sub.match_terms(&pattern.right, right)
+        })
+}
+
+// Drops the literals that a simplifier contradicts, and the ones like x != x.
+fn drop_refuted(literals: Vec<Literal>, simplifiers: &[Literal]) -> Vec<Literal> {
+    literals
+        .into_iter()
+        .filter(|literal| {
+            if !literal.positive && literal.left == literal.right {
+                return false;
+            }
+            let negated = Literal {
+                positive: !literal.positive,
+                left: literal.left.clone(),
+                right: literal.right.clone(),
+            };
+            !simplifiers.iter().any(|s| is_instance(s, &negated))
+        })
+        .collect()
+}
+
+// Every clause that one rewrite with a simplifying equation turns the literals into.
+fn rewrite_once(literals: &[Literal], simplifiers: &[Literal]) -> Vec<Vec<Literal>> {
+    let mut answer = vec![];
+    for (i, literal) in literals.iter().enumerate() {
+        for (side, other, left_side) in [
+            (&literal.left, &literal.right, true),
+            (&literal.right, &literal.left, false),
+        ] {
+            let mut paths = vec![];
+            subterm_paths(side, &mut vec![], &mut paths);
+            for path in paths {
+                let subterm = get_at_path(side, &path);
+                for equation in simplifiers.iter().filter(|s| s.positive) {
+                    for (from, to) in [
+                        (&equation.left, &equation.right),
+                        (&equation.right, &equation.left),
+                    ] {
+                        if from.is_variable() {
+                            continue;
+                        }
+                        let mut sub = Substitution::default();
+                        if !sub.match_terms(from, subterm) || !binds_all(&sub, to) {
+                            continue;
+                        }
+                        let new_side = replace_at_path(side, &path, sub.apply(to));
+                        let (left, right) = if left_side {
+                            (new_side, other.clone())
+                        } else {
+                            (other.clone(), new_side)
+                        };
+                        let mut rewritten = literals.to_vec();
+                        rewritten[i] = Literal::new(literal.positive, left, right);
+                        answer.push(rewritten);
+                    }
+                }
+            }
+        }
+    }
+    answer
+}
+
+fn apply_to_literal(sub: &Substitution, literal: &Literal) -> Literal {
+    Literal::new(
+        literal.positive,
+        sub.apply(&literal.left),
+        sub.apply(&literal.right),
+    )
+}
+
+// Whether some instance of the general clause has all of its literals in the special clause.
+// If it does, the special clause follows from the general one.
+fn subsumes(general: &[Literal], special: &Clause, sub: &mut Substitution) -> bool {
+    let Some((first, rest)) = general.split_first() else {
+        return true;
+    };
+    for literal in &special.literals {
+        if literal.positive != first.positive {
+            continue;
+        }
+        for (left, right) in [
+            (&literal.left, &literal.right),
+            (&literal.right, &literal.left),
+        ] {
+            let mut extended = Substitution {
+                map: sub.map.clone(),
+            };
+            if extended.match_terms(&first.left, left)
+                && extended.match_terms(&first.right, right)
+                && subsumes(rest, special, &mut extended)
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// Every subterm of a term, with the path to it.
+fn subterm_paths(term: &Term, path: &mut Vec<usize>, output: &mut Vec<Vec<usize>>) {
+    output.push(path.clone());
+    for (i, arg) in term.args.iter().enumerate() {
+        path.push(i);
+        subterm_paths(arg, path, output);
+        path.pop();
+    }
+}
+
+fn get_at_path<'a>(term: &'a Term, path: &[usize]) -> &'a Term {
+    match path.split_first() {
+        None => term,
+        Some((i, rest)) => get_at_path(&term.args[*i], rest),
+    }
+}
+
+fn replace_at_path(term: &Term, path: &[usize], replacement: Term) -> Term {
+    match path.split_first() {
+        None => replacement,
+        Some((i, rest)) => {
+            let mut args = term.args.clone();
+            args[*i] = replace_at_path(&term.args[*i], rest, replacement);
+            Term::new(term.term_type, term.head_type, term.head, args)
+        }
+    }
+}
+
+pub struct ProofChecker {
+    simplifier: ClauseSimplifier,
+
+    // The facts that a simplified assumption can have come from.
+    facts: Vec<Clause>,
+
+    // The single-literal clauses that simplified the step being checked.
+    simplifiers: Vec<Literal>,
+}
+
+impl ProofChecker {
+    // The simplifier should be the one the prover used, so that the checker can apply the
+    // same theory simplifications.
+    pub fn new(simplifier: ClauseSimplifier) -> ProofChecker {
+        ProofChecker {
+            simplifier,
+            facts: vec![],
+            simplifiers: vec![],
+        }
+    }
+
+    // Assumptions that were simplified by other clauses are checked against these facts.
+    pub fn add_fact(&mut self, clause: Clause) {
+        self.facts.push(clause);
+    }
+
+    // Whether the conclusion follows from one of the clauses the rule could have produced.
+    // The prover simplifies clauses after producing them, so it's enough for the conclusion
+    // to follow from a candidate after the simplifications.
+    fn follows_from(&mut self, candidate: Vec<Literal>, conclusion: &Clause) -> bool {
+        if self.simplifiers.is_empty() {
+            return self.follows_directly(candidate, conclusion);
+        }
+
+        // The prover drops the literals that its simplifiers contradict, and rewrites with the
+        // ones that are equations. We don't know which rewrites it did, so we search for them.
+        let shift = candidate
+            .iter()
+            .map(|literal| literal.num_quantifiers())
+            .max()
+            .unwrap_or(0);
+        let simplifiers: Vec<Literal> = self
+            .simplifiers
+            .iter()
+            .map(|literal| shift_literal(literal, shift))
+            .collect();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([candidate]);
+        while let Some(literals) = queue.pop_front() {
+            if seen.len() >= MAX_SIMPLIFICATION_STATES {
+                break;
+            }
+            let literals = drop_refuted(literals, &simplifiers);
+            if !seen.insert(literals.clone()) {
+                continue;
+            }
+            if self.follows_directly(literals.clone(), conclusion) {
+                return true;
+            }
+            queue.extend(rewrite_once(&literals, &simplifiers));
+        }
+        false
+    }
+
+    // Whether the conclusion follows from the candidate after the theory simplifications.
+    fn follows_directly(&mut self, candidate: Vec<Literal>, conclusion: &Clause) -> bool {
+        if subsumes(&candidate, conclusion, &mut Substitution::default()) {
+            return true;
+        }
+        match self.simplifier.simplify(&Clause::new(candidate)) {
+            Some(simplified) => subsumes(
+                &simplified.literals,
+                conclusion,
+                &mut Substitution::default(),
+            ),
+            None => false,
+        }
+    }
+
+    // The short clause usually has one literal, but the prover also resolves pairs of
+    // two-literal clauses, so any literal of the short clause can be the one resolved away.
+    fn check_resolution(
+        &mut self,
+        short: &Clause,
+        long: &Clause,
+        conclusion: &Clause,
+    ) -> Result<bool, Unsupported> {
+        let long_literals = shift_literals(long, short.num_quantifiers());
+        let mut unsupported = false;
+        for (i, short_literal) in short.literals.iter().enumerate() {
+            for (j, literal) in long_literals.iter().enumerate() {
+                if literal.positive == short_literal.positive {
+                    continue;
+                }
+                for (left, right) in [
+                    (&literal.left, &literal.right),
+                    (&literal.right, &literal.left),
+                ] {
+                    let mut sub = Substitution::default();
+                    match sub
+                        .unify(&short_literal.left, left)
+                        .and_then(|ok| Ok(ok && sub.unify(&short_literal.right, right)?))
+                    {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(Unsupported) => {
+                            unsupported = true;
+                            continue;
+                        }
+                    }
+                    let short_rest = short.literals.iter().enumerate().filter(|(k, _)| *k != i);
+                    let long_rest = long_literals.iter().enumerate().filter(|(k, _)| *k != j);
+                    let resolvent = short_rest
+                        .chain(long_rest)
+                        .map(|(_, literal)| apply_to_literal(&sub, literal))
+                        .collect();
+                    if self.follows_from(resolvent, conclusion) {
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+        if unsupported {
+            Err(Unsupported)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn check_rewrite(&mut self, pattern: &Clause, target: &Clause, conclusion: &Clause) -> bool {
+        if pattern.literals.len() != 1 || target.literals.len() != 1 {
+            return false;
+        }
+        let equation = &shift_literals(pattern, target.num_quantifiers())[0];
+        if !equation.positive {
+            return false;
+        }
+        let target = &target.literals[0];
+        for (from, to) in [
+            (&equation.left, &equation.right),
+            (&equation.right, &equation.left),
+        ] {
+            for (side, other) in [(&target.left, &target.right), (&target.right, &target.left)] {
+                let mut paths = vec![];
+                subterm_paths(side, &mut vec![], &mut paths);
+                for path in paths {
+                    let mut sub = Substitution::default();
+                    if !sub.match_terms(from, get_at_path(side, &path)) {
+                        continue;
+                    }
+                    let new_side = replace_at_path(side, &path, sub.apply(to));
+                    let rewritten = Literal::new(target.positive, new_side, other.clone());
+                    if self.follows_from(vec![rewritten], conclusion) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // Equality resolution drops a literal u != v whose sides unify.
+    fn check_equality_resolution(
+        &mut self,
+        clause: &Clause,
+        conclusion: &Clause,
+    ) -> Result<bool, Unsupported> {
+        let mut unsupported = false;
+        for (i, literal) in clause.literals.iter().enumerate() {
+            if literal.positive {
+                continue;
+            }
+            let mut sub = Substitution::default();
+            match sub.unify(&literal.left, &literal.right) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(Unsupported) => {
+                    unsupported = true;
+                    continue;
+                }
+            }
+            let rest = clause
+                .literals
+                .iter()
+                .enumerate()
+                .filter(|(k, _)| *k != i)
+                .map(|(_, literal)| apply_to_literal(&sub, literal))
+                .collect();
+            if self.follows_from(rest, conclusion) {
+                return Ok(true);
+            }
+        }
+        if unsupported {
+            Err(Unsupported)
+        } else {
+            Ok(false)
+        }
+    }
+
+    // Equality factoring turns s = t or u = v into t != v or u = v, when s and u unify.
+    fn check_equality_factoring(
+        &mut self,
+        clause: &Clause,
+        conclusion: &Clause,
+    ) -> Result<bool, Unsupported> {
+        let mut unsupported = false;
+        for (i, st_literal) in clause.literals.iter().enumerate() {
+            for (j, uv_literal) in clause.literals.iter().enumerate() {
+                if i == j || !st_literal.positive || !uv_literal.positive {
+                    continue;
+                }
+                for (s, t) in [
+                    (&st_literal.left, &st_literal.right),
+                    (&st_literal.right, &st_literal.left),
+                ] {
+                    for (u, v) in [
+                        (&uv_literal.left, &uv_literal.right),
+                        (&uv_literal.right, &uv_literal.left),
+                    ] {
+                        let mut sub = Substitution::default();
+                        match sub.unify(s, u) {
+                            Ok(true) => {}
+                            Ok(false) => continue,
+                            Err(Unsupported) => {
+                                unsupported = true;
+                                continue;
+                            }
+                        }
+                        let mut literals = vec![
+                            Literal::new(false, sub.apply(t), sub.apply(v)),
+                            apply_to_literal(&sub, uv_literal),
+                        ];
+                        literals.extend(
+                            clause
+                                .literals
+                                .iter()
+                                .enumerate()
+                                .filter(|(k, _)| *k != i && *k != j)
+                                .map(|(_, literal)| apply_to_literal(&sub, literal)),
+                        );
+                        if self.follows_from(literals, conclusion) {
+                            return Ok(true);
+                        }
+                    }
+                }
+            }
+        }
+        if unsupported {
+            Err(Unsupported)
+        } else {
+            Ok(false)
+        }
+    }
+
+    // Function elimination turns f(a, b) != f(a, c) into b != c, when only one argument differs.
+    fn check_function_elimination(&mut self, clause: &Clause, conclusion: &Clause) -> bool {
+        for (i, literal) in clause.literals.iter().enumerate() {
+            let (left, right) = (&literal.left, &literal.right);
+            if literal.positive
+                || left.head != right.head
+                || left.head_type != right.head_type
+                || left.args.len() != right.args.len()
+            {
+                continue;
+            }
+            let differences: Vec<usize> = (0..left.args.len())
+                .filter(|k| left.args[*k] != right.args[*k])
+                .collect();
+            if let [k] = differences[..] {
+                let mut literals = clause.literals.clone();
+                literals[i] = Literal::new(false, left.args[k].clone(), right.args[k].clone());
+                if self.follows_from(literals, conclusion) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Whether two of the clauses resolve to the conclusion.
+    fn check_contradiction(
+        &mut self,
+        clauses: &[&Clause],
+        conclusion: &Clause,
+    ) -> Result<bool, Unsupported> {
+        let mut unsupported = false;
+        for (i, short) in clauses.iter().enumerate() {
+            for long in &clauses[i + 1..] {
+                match self.check_resolution(short, long, conclusion) {
+                    Ok(true) => return Ok(true),
+                    Ok(false) => {}
+                    Err(Unsupported) => unsupported = true,
+                }
+            }
+        }
+        if unsupported {
+            Err(Unsupported)
+        } else {
+            Ok(false)
+        }
+    }
+
+    // Whether some fact simplifies to the conclusion.
+    fn check_simplified_assumption(&mut self, conclusion: &Clause) -> bool {
+        let facts = std::mem::take(&mut self.facts);
+        let found = facts
+            .iter()
+            .filter(|fact| fact.literals.len() >= conclusion.literals.len())
+            .any(|fact| self.follows_from(fact.literals.clone(), conclusion));
+        self.facts = facts;
+        found
+    }
+
+    // Checks that a step follows from its parents by its rule.
+    // When a step was simplified by other clauses, we search for the simplifications, but we
+    // only know how to apply the single-literal ones.
+    pub fn check_step(
+        &mut self,
+        step: &ProofStep,
+        parents: &HashMap<ProofStepId, &ProofStep>,
+    ) -> Verdict {
+        let parent = |id: usize| parents.get(&ProofStepId::Active(id)).map(|s| &s.clause);
+        let conclusion = &step.clause;
+        self.simplifiers = step
+            .simplification_rules
+            .iter()
+            .filter_map(|id| parent(*id))
+            .filter(|clause| clause.literals.len() == 1)
+            .map(|clause| clause.literals[0].clone())
+            .collect();
+        let result = match &step.rule {
+            Rule::Assumption(_) if step.simplification_rules.is_empty() => return Verdict::Checked,
+            Rule::Assumption(_) => Ok(self.check_simplified_assumption(conclusion)),
+            Rule::Resolution(info) => match (parent(info.short_id), parent(info.long_id)) {
+                (Some(short), Some(long)) => self.check_resolution(short, long, conclusion),
+                _ => return Verdict::Unchecked,
+            },
+            Rule::Rewrite(info) => match (parent(info.pattern_id), parent(info.target_id)) {
+                (Some(pattern), Some(target)) => {
+                    Ok(self.check_rewrite(pattern, target, conclusion))
+                }
+                _ => return Verdict::Unchecked,
+            },
+            Rule::Specialization(id) => match parent(*id) {
+                Some(general) => Ok(self.follows_from(general.literals.clone(), conclusion)),
+                None => return Verdict::Unchecked,
+            },
+            Rule::EqualityResolution(id) => match parent(*id) {
+                Some(clause) => self.check_equality_resolution(clause, conclusion),
+                None => return Verdict::Unchecked,
+            },
+            Rule::EqualityFactoring(id) => match parent(*id) {
+                Some(clause) => self.check_equality_factoring(clause, conclusion),
+                None => return Verdict::Unchecked,
+            },
+            Rule::FunctionElimination(id) => match parent(*id) {
+                Some(clause) => Ok(self.check_function_elimination(clause, conclusion)),
+                None => return Verdict::Unchecked,
+            },
+            Rule::PassiveContradiction(n) => {
+                let clauses: Vec<&Clause> = (0..*n)
+                    .filter_map(|id| parents.get(&ProofStepId::Passive(id)))
+                    .map(|step| &step.clause)
+                    .collect();
+                self.check_contradiction(&clauses, conclusion)
+            }
+            Rule::MultipleRewrite(info) => {
+                let Some(inequality) = info.inequality_id.and_then(parent) else {
+                    // The sides are different constructors, which the checker doesn't know about.
+                    return Verdict::Unchecked;
+                };
+                let equations = info
+                    .active_ids
+                    .iter()
+                    .map(|id| ProofStepId::Active(*id))
+                    .chain(info.passive_ids.iter().map(|id| ProofStepId::Passive(*id)));
+                self.simplifiers = equations
+                    .filter_map(|id| parents.get(&id))
+                    .filter(|step| step.clause.literals.len() == 1)
+                    .map(|step| step.clause.literals[0].clone())
+                    .collect();
+                Ok(self.follows_from(inequality.literals.clone(), conclusion))
+            }
+            _ => return Verdict::Unchecked,
+        };
+        match result {
+            Ok(true) => Verdict::Checked,
+            Err(Unsupported) => Verdict::Unchecked,
+            Ok(false) if !step.simplification_rules.is_empty() => Verdict::Unchecked,
+            Ok(false) => {
+                Verdict::Failed(format!("{} does not follow by {:?}", conclusion, step.rule))
+            }
+        }
+    }
+
+    // Checks each step of a proof, in order.
+    pub fn check_proof(&mut self, steps: &[(ProofStepId, &ProofStep)]) -> CheckReport {
+        let parents: HashMap<ProofStepId, &ProofStep> =
+            steps.iter().map(|(id, step)| (*id, *step)).collect();
+        let mut report = CheckReport::default();
+        for (_, step) in steps {
+            match self.check_step(step, &parents) {
+                Verdict::Checked => report.checked += 1,
+                Verdict::Unchecked => report.unchecked += 1,
+                Verdict::Failed(message) => report.failures.push(message),
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(step: &ProofStep, parents: &[(usize, &ProofStep)]) -> Verdict {
+        let parents = parents
+            .iter()
+            .map(|(id, parent)| (ProofStepId::Active(*id), *parent))
+            .collect();
+        ProofChecker::new(ClauseSimplifier::new()).check_step(step, &parents)
+    }
+
+    #[test]
+    fn test_checking_resolution() {
+        let long = ProofStep::mock("c0(x0) != c1 or c2(x0, x1) = c3");
+        let short = ProofStep::mock("c0(c4) = c1");
+        let step = ProofStep::new_resolution(0, &long, 1, &short, Clause::parse("c2(c4, x0) = c3"));
+        assert_eq!(check(&step, &[(0, &long), (1, &short)]), Verdict::Checked);
+
+        // A resolution that drops the wrong literal
+        let step = ProofStep::new_resolution(0, &long, 1, &short, Clause::parse("c2(c5, x0) = c3"));
+        assert!(matches!(
+            check(&step, &[(0, &long), (1, &short)]),
+            Verdict::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_checking_rewrite() {
+        let pattern = ProofStep::mock("c0(x0, c1) = x0");
+        let target = ProofStep::mock("c2(c0(c3, c1)) = c4");
+        let step = ProofStep::new_rewrite(0, &pattern, 1, &target, true, &[0], &Term::parse("c3"));
+        assert_eq!(
+            check(&step, &[(0, &pattern), (1, &target)]),
+            Verdict::Checked
+        );
+
+        // Rewriting to something the pattern doesn't say
+        let step = ProofStep::new_rewrite(0, &pattern, 1, &target, true, &[0], &Term::parse("c1"));
+        assert!(matches!(
+            check(&step, &[(0, &pattern), (1, &target)]),
+            Verdict::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_checking_specialization() {
+        let general = ProofStep::mock("c0(x0, x1) = c1 or c2(x1) = c3");
+        let step = ProofStep::new_specialization(
+            0,
+            &general,
+            Clause::parse("c0(c4, x0) = c1 or c2(x0) = c3"),
+        );
+        assert_eq!(check(&step, &[(0, &general)]), Verdict::Checked);
+
+        let step = ProofStep::new_specialization(0, &general, Clause::parse("c0(c4, x0) = c1"));
+        assert!(matches!(check(&step, &[(0, &general)]), Verdict::Failed(_)));
+    }
+
+    #[test]
+    fn test_checking_equality_rules() {
+        let source = ProofStep::mock("c0(x0) != c0(c1) or c2(x0) = c3");
+        let step = ProofStep::new_direct(
+            &source,
+            Rule::EqualityResolution(0),
+            Clause::parse("c2(c1) = c3"),
+        );
+        assert_eq!(check(&step, &[(0, &source)]), Verdict::Checked);
+
+        let source = ProofStep::mock("c0(x0) = c1 or c0(c2) = c3");
+        let step = ProofStep::new_direct(
+            &source,
+            Rule::EqualityFactoring(0),
+            Clause::parse("c1 != c3 or c0(c2) = c3"),
+        );
+        assert_eq!(check(&step, &[(0, &source)]), Verdict::Checked);
+
+        let source = ProofStep::mock("c0(c1, c2) != c0(c1, c3)");
+        let step = ProofStep::new_direct(
+            &source,
+            Rule::FunctionElimination(0),
+            Clause::parse("c2 != c3"),
+        );
+        assert_eq!(check(&step, &[(0, &source)]), Verdict::Checked);
+        let step = ProofStep::new_direct(
+            &source,
+            Rule::FunctionElimination(0),
+            Clause::parse("c1 != c3"),
+        );
+        assert!(matches!(check(&step, &[(0, &source)]), Verdict::Failed(_)));
+    }
+
+    #[test]
+    fn test_checking_simplified_steps() {
+        let long = ProofStep::mock("c6(x0) != c7 or c0(c5(x0)) = c1 or c2(x0) = c3");
+        let short = ProofStep::mock("c6(c4) = c7");
+        let rule = ProofStep::mock("c2(c4) != c3");
+        let demodulator = ProofStep::mock("c5(x0) = x0");
+        let resolvent = || {
+            ProofStep::new_resolution(
+                0,
+                &long,
+                1,
+                &short,
+                Clause::parse("c0(c5(c4)) = c1 or c2(c4) = c3"),
+            )
+        };
+        let parents = [(0, &long), (1, &short), (2, &rule), (3, &demodulator)];
+        let step = ProofStep::new_simplified(
+            resolvent(),
+            &[(2, &rule), (3, &demodulator)],
+            Clause::parse("c0(c4) = c1"),
+        );
+        assert_eq!(check(&step, &parents), Verdict::Checked);
+
+        // Without the demodulator, there's no way to get rid of c5.
+        let step =
+            ProofStep::new_simplified(resolvent(), &[(2, &rule)], Clause::parse("c0(c4) = c1"));
+        assert_eq!(check(&step, &parents), Verdict::Unchecked);
+    }
+}
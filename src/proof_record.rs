@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::goal::GoalContext;
+use crate::module::ModuleHash;
+use crate::proof_step::{ProofStep, ProofStepId};
+
+// Bump this whenever the serialized format changes, so that old records are ignored.
+const FORMAT_VERSION: u32 = 1;
+
+// The steps of a proof, saved so that a later build can check the proof again without searching.
+// Like a checkpoint, the clauses only mean something to a prover that was given the same facts
+// in the same order as the one that found the proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofRecord {
+    pub steps: Vec<(ProofStepId, ProofStep)>,
+}
+
+// The proof records for the goals of one module.
+// It's saved as a .acp file next to the compiled modules. The records are only valid while
+// the module's hash matches, since otherwise the goals and facts may have changed.
+#[derive(Serialize, Deserialize)]
+pub struct ModuleProofs {
+    format_version: u32,
+
+    pub hash: ModuleHash,
+
+    // Keyed by the first line of the goal and its name, since names alone aren't unique.
+    proofs: BTreeMap<(u32, String), ProofRecord>,
+}
+
+impl ModuleProofs {
+    pub fn new(hash: ModuleHash) -> ModuleProofs {
+        ModuleProofs {
+            format_version: FORMAT_VERSION,
+            hash,
+            proofs: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, goal_context: &GoalContext, record: ProofRecord) {
+        let key = (goal_context.first_line, goal_context.name.clone());
+        self.proofs.insert(key, record);
+    }
+
+    pub fn get(&self, goal_context: &GoalContext) -> Option<&ProofRecord> {
+        let key = (goal_context.first_line, goal_context.name.clone());
+        self.proofs.get(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("failed to serialize proof records")
+    }
+
+    // Returns None if the bytes aren't proof records in the current format.
+    pub fn deserialize(bytes: &[u8]) -> Option<ModuleProofs> {
+        let proofs: ModuleProofs = bincode::deserialize(bytes).ok()?;
+        if proofs.format_version != FORMAT_VERSION {
+            return None;
+        }
+        Some(proofs)
+    }
+}
@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::atom::Atom;
 use crate::clause::Clause;
 use crate::literal::Literal;
@@ -8,7 +10,7 @@ use crate::proposition::{Source, SourceType};
 use crate::term::Term;
 
 // The different sorts of proof steps.
-#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum ProofStepId {
     // A proof step that was activated and exists in the active set.
     Active(usize),
@@ -31,7 +33,7 @@ impl ProofStepId {
 }
 
 // The "truthiness" categorizes the different types of true statements, relative to a proof.
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum Truthiness {
     // A "factual" truth is true globally, regardless of this particular proof.
     Factual,
@@ -61,7 +63,7 @@ impl Truthiness {
 }
 
 // Information about a resolution inference.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ResolutionInfo {
     // Which clauses were used as the sources.
     // The short clause must have only one literal.
@@ -75,7 +77,7 @@ pub struct ResolutionInfo {
 // Information about a rewrite inference.
 // Rewrites have two parts, the "pattern" that determines what gets rewritten into what,
 // and the "target" which contains the subterm that gets rewritten.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RewriteInfo {
     // Which clauses were used as the sources.
     pub pattern_id: usize,
@@ -87,14 +89,15 @@ pub struct RewriteInfo {
 }
 
 // Always a contradiction, found by rewriting one side of an inequality into the other.
-#[derive(Debug, PartialEq, Eq, Clone)]
+// There's no inequality step when the two sides are different constructors.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct MultipleRewriteInfo {
-    pub inequality_id: usize,
+    pub inequality_id: Option<usize>,
     pub active_ids: Vec<usize>,
     pub passive_ids: Vec<u32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AssumptionInfo {
     pub source: Source,
 
@@ -103,7 +106,7 @@ pub struct AssumptionInfo {
 }
 
 // The rules that can generate new clauses, along with the clause ids used to generate.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum Rule {
     Assumption(AssumptionInfo),
 
@@ -117,16 +120,26 @@ pub enum Rule {
     FunctionElimination(usize),
     Specialization(usize),
 
+    // A clause split into components that don't share variables.
+    // The source clause itself isn't used for inference, only the parts it was split into.
+    Split(usize),
+
     // A contradiction found by repeatedly rewriting identical terms.
     MultipleRewrite(MultipleRewriteInfo),
 
     // A contradiction between a number of passive clauses.
     PassiveContradiction(u32),
+
+    // A contradiction that an external SMT solver found between these active clauses.
+    Smt(Vec<usize>),
+
+    // A contradiction that an external first-order prover found between these active clauses.
+    Atp(Vec<usize>),
 }
 
 impl Rule {
     // The ids of the clauses that this rule directly depends on.
-    fn premises(&self) -> Vec<ProofStepId> {
+    pub fn premises(&self) -> Vec<ProofStepId> {
         match self {
             Rule::Assumption(_) => vec![],
             Rule::Resolution(info) => vec![
@@ -140,9 +153,13 @@ impl Rule {
             Rule::EqualityFactoring(rewritten)
             | Rule::EqualityResolution(rewritten)
             | Rule::FunctionElimination(rewritten)
-            | Rule::Specialization(rewritten) => vec![ProofStepId::Active(*rewritten)],
+            | Rule::Specialization(rewritten)
+            | Rule::Split(rewritten) => vec![ProofStepId::Active(*rewritten)],
             Rule::MultipleRewrite(multi_rewrite_info) => {
-                let mut answer = vec![ProofStepId::Active(multi_rewrite_info.inequality_id)];
+                let mut answer = vec![];
+                if let Some(id) = multi_rewrite_info.inequality_id {
+                    answer.push(ProofStepId::Active(id));
+                }
                 for id in &multi_rewrite_info.active_ids {
                     answer.push(ProofStepId::Active(*id));
                 }
@@ -152,6 +169,9 @@ impl Rule {
                 answer
             }
             Rule::PassiveContradiction(n) => (0..*n).map(|id| ProofStepId::Passive(id)).collect(),
+            Rule::Smt(ids) | Rule::Atp(ids) => {
+                ids.iter().map(|id| ProofStepId::Active(*id)).collect()
+            }
         }
     }
 
@@ -165,8 +185,11 @@ impl Rule {
             Rule::EqualityResolution(_) => "Equality Resolution",
             Rule::FunctionElimination(_) => "Function Elimination",
             Rule::Specialization(_) => "Specialization",
+            Rule::Split(_) => "Split",
             Rule::MultipleRewrite(..) => "Multiple Rewrite",
             Rule::PassiveContradiction(..) => "Passive Contradiction",
+            Rule::Smt(..) => "SMT",
+            Rule::Atp(..) => "ATP",
         }
     }
 
@@ -194,7 +217,7 @@ impl Rule {
 
 // A proof is made up of ProofSteps.
 // Each ProofStep contains an output clause, plus a bunch of information we track about it.
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ProofStep {
     // The proof step is primarily defined by a clause that it proves.
     // Semantically, this clause is implied by the input clauses (activated and existing).
@@ -293,6 +316,21 @@ impl ProofStep {
         }
     }
 
+    // Construct a ProofStep for one part of a split clause.
+    // Splitting just restates the clause, so it doesn't add to the proof size.
+    pub fn new_split(split_id: usize, split_step: &ProofStep, clause: Clause) -> ProofStep {
+        let printable = clause.is_printable();
+        ProofStep {
+            clause,
+            truthiness: split_step.truthiness,
+            rule: Rule::Split(split_id),
+            simplification_rules: vec![],
+            proof_size: split_step.proof_size,
+            depth: split_step.depth,
+            printable,
+        }
+    }
+
     // Construct a new ProofStep via resolution.
     pub fn new_resolution(
         long_id: usize,
@@ -421,7 +459,7 @@ impl ProofStep {
 
     // A proof step for finding a contradiction via a series of rewrites.
     pub fn new_multiple_rewrite(
-        inequality_id: usize,
+        inequality_id: Option<usize>,
         active_ids: Vec<usize>,
         passive_ids: Vec<u32>,
         truthiness: Truthiness,
@@ -468,6 +506,29 @@ impl ProofStep {
         }
     }
 
+    // A contradiction between active steps, found by an external tool.
+    // The rule should cite the ids of the steps.
+    pub fn new_external(rule: Rule, steps: &[&ProofStep]) -> ProofStep {
+        let mut truthiness = Truthiness::Factual;
+        let mut depth = 0;
+        let mut proof_size = 0;
+        for step in steps {
+            truthiness = truthiness.combine(step.truthiness);
+            depth = std::cmp::max(depth, step.depth);
+            proof_size += step.proof_size;
+        }
+
+        ProofStep {
+            clause: Clause::impossible(),
+            truthiness,
+            rule,
+            simplification_rules: vec![],
+            proof_size,
+            depth,
+            printable: true,
+        }
+    }
+
     // Construct a ProofStep with fake heuristic data for testing
     pub fn mock(s: &str) -> ProofStep {
         let clause = Clause::parse(s);
@@ -1,10 +1,12 @@
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::Range;
 
 use crate::acorn_value::AcornValue;
+use crate::limits::LimitOverrides;
 use crate::module::ModuleId;
 
 // The different reasons that can lead us to create a proposition.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SourceType {
     // An axiom, which may have a name.
     Axiom(Option<String>),
@@ -29,7 +31,7 @@ pub enum SourceType {
 }
 
 // The information about where a proposition comes from.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Source {
     // The module where this value was defined
     pub module: ModuleId,
@@ -81,14 +83,55 @@ impl Source {
     }
 }
 
+// Attributes that can be attached to a theorem, to change how the prover uses it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attributes {
+    // "@simp" theorems are used as rewrite rules from the very start of a search.
+    pub simp: bool,
+
+    // "@no_auto" theorems are not given to the prover automatically.
+    // They can still be used by citing them explicitly.
+    pub no_auto: bool,
+
+    // "@ac" marks a definition as an associative and commutative operator.
+    pub ac: bool,
+
+    // Tags, like "@tag(algebra)", don't mean anything on their own, but they let whole
+    // areas of the library be left out of a proof search.
+    pub tags: Vec<String>,
+
+    // Limits like "@max_active(5000)" change how hard the prover works on this theorem's goals.
+    pub limits: LimitOverrides,
+}
+
+impl Attributes {
+    // Sets the attribute with the given name.
+    // Returns false if there is no such attribute.
+    pub fn set(&mut self, name: &str) -> bool {
+        match name {
+            "simp" => self.simp = true,
+            "no_auto" => self.no_auto = true,
+            "ac" => self.ac = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
 // A value along with information on where to find it in the source.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proposition {
     // A boolean value. The essence of the proposition is "value is true".
     pub value: AcornValue,
 
     // Where this proposition came from.
     pub source: Source,
+
+    // Only theorems and axioms have attributes.
+    pub attributes: Attributes,
+
+    // A label that later statements can cite this proposition by, like "h1" in "h1: a = b".
+    pub label: Option<String>,
 }
 
 impl Proposition {
@@ -111,6 +154,8 @@ impl Proposition {
                 range,
                 source_type,
             },
+            attributes: Attributes::default(),
+            label: None,
         }
     }
 
@@ -122,6 +167,8 @@ impl Proposition {
                 range,
                 source_type: SourceType::Anonymous,
             },
+            attributes: Attributes::default(),
+            label: None,
         }
     }
 
@@ -138,6 +185,8 @@ impl Proposition {
                 range,
                 source_type: SourceType::TypeDefinition(name),
             },
+            attributes: Attributes::default(),
+            label: None,
         }
     }
 
@@ -154,6 +203,8 @@ impl Proposition {
                 range,
                 source_type: SourceType::ConstantDefinition(constant),
             },
+            attributes: Attributes::default(),
+            label: None,
         }
     }
 
@@ -165,6 +216,8 @@ impl Proposition {
                 range,
                 source_type: SourceType::Premise,
             },
+            attributes: Attributes::default(),
+            label: None,
         }
     }
 
@@ -176,6 +229,8 @@ impl Proposition {
                 range: self.source.range,
                 source_type: SourceType::NegatedGoal,
             },
+            attributes: Attributes::default(),
+            label: None,
         }
     }
 
@@ -184,14 +239,46 @@ impl Proposition {
         Proposition {
             value,
             source: self.source.clone(),
+            attributes: self.attributes.clone(),
+            label: self.label.clone(),
         }
     }
 
-    // Theorems and axioms can have names
+    // Replaces the module ids in this proposition, both in its value and its source.
+    // This is for using a proposition in a project that numbers its modules differently.
+    pub fn remap_modules(&self, remap: &dyn Fn(ModuleId) -> ModuleId) -> Proposition {
+        let source_type = match &self.source.source_type {
+            SourceType::ConstantDefinition(constant) => {
+                SourceType::ConstantDefinition(constant.remap_modules(remap))
+            }
+            source_type => source_type.clone(),
+        };
+        Proposition {
+            value: self.value.remap_modules(remap),
+            source: Source {
+                module: remap(self.source.module),
+                range: self.source.range,
+                source_type,
+            },
+            attributes: self.attributes.clone(),
+            label: self.label.clone(),
+        }
+    }
+
+    pub fn with_attributes(self, attributes: Attributes) -> Proposition {
+        Proposition { attributes, ..self }
+    }
+
+    pub fn with_label(self, label: Option<String>) -> Proposition {
+        Proposition { label, ..self }
+    }
+
+    // Theorems and axioms can have names.
+    // Other propositions are named by their label, if they have one.
     pub fn name(&self) -> Option<&str> {
         match &self.source.source_type {
             SourceType::Axiom(name) | SourceType::Theorem(name) => name.as_deref(),
-            _ => None,
+            _ => self.label.as_deref(),
         }
     }
 }
@@ -1,29 +1,53 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::Url;
 
 use crate::acorn_type::AcornType;
 use crate::acorn_value::AcornValue;
-use crate::active_set::ActiveSet;
+use crate::active_set::{ActiveSet, LiteralSelection};
+use crate::arithmetic::LinearArithmetic;
+use crate::atom::Atom;
 use crate::binding_map::BindingMap;
 use crate::clause::Clause;
-use crate::display::DisplayClause;
+use crate::clause_arena::ClauseArena;
+use crate::clause_simplifier::ClauseSimplifier;
+use crate::code_style::CodeStyle;
+use crate::display::{DisplayClause, DisplayModel, DisplayTerm};
 use crate::fact::Fact;
 use crate::goal::{Goal, GoalContext};
-use crate::interfaces::{ClauseInfo, InfoResult, Location, ProofStepInfo};
+use crate::interfaces::{
+    ClauseInfo, Counterexample, InfoResult, Location, PremiseInfo, ProofStepInfo, ProverSnapshot,
+    ScoredClauseInfo,
+};
+use crate::kbo::Kbo;
+use crate::limits::{Limit, LimitOverrides, SearchLimits};
 use crate::literal::Literal;
+use crate::model_finder::{Model, ModelFinder};
+use crate::module::ModuleId;
 use crate::monomorphizer::Monomorphizer;
+use crate::normalization_cache::NormalizationCache;
 use crate::normalizer::{Normalization, NormalizationError, Normalizer};
-use crate::passive_set::PassiveSet;
+use crate::passive_set::{PassiveCheckpoint, PassiveSchedule, PassiveSet};
 use crate::project::Project;
 use crate::proof::{Difficulty, Proof};
+use crate::proof_checker::{CheckReport, ProofChecker};
+use crate::proof_record::ProofRecord;
 use crate::proof_step::{ProofStep, ProofStepId, Rule, Truthiness};
-use crate::proposition::SourceType;
+use crate::proposition::{Attributes, Source, SourceType};
+use crate::prover_config::{ProverConfig, ScorerKind};
+use crate::scorer::Scorer;
+use crate::search_stats::SearchStats;
+use crate::smt::{SmtConfig, SmtProblem, SmtResult};
 use crate::term::Term;
 use crate::term_graph::TermGraphContradiction;
+use crate::tptp::{AtpConfig, AtpResult, TptpProblem};
+use crate::type_map::BOOL;
+use crate::unifier::{Scope, Unifier};
 
 #[derive(Clone)]
 pub struct Prover {
@@ -51,6 +75,13 @@ pub struct Prover {
     // Clauses that we never activated, but we did use to find a contradiction.
     useful_passive: Vec<ProofStep>,
 
+    // The facts that tell the term graph about constructors, indexed by their fact id there.
+    constructor_facts: Vec<ProofStep>,
+
+    // Every clause that has gone into the active or passive set, so that a clause that gets
+    // generated again can be dropped right away.
+    clauses: ClauseArena,
+
     // Setting any of these flags to true externally will stop the prover.
     pub stop_flags: Vec<Arc<AtomicBool>>,
 
@@ -65,6 +96,54 @@ pub struct Prover {
     // The goal of the prover.
     // If this is None, the goal hasn't been set yet.
     goal: Option<NormalizedGoal>,
+
+    // Whether to split long clauses into components that don't share variables.
+    splitting: bool,
+
+    // Simplifies each generated clause on its own, before the active set simplifies it.
+    simplifier: ClauseSimplifier,
+
+    // A finite model of the negated goal, if we found one.
+    countermodel: Option<Model>,
+
+    // Which limit stopped the last search, if one did.
+    limit_hit: Option<Limit>,
+
+    // A deterministic prover ignores time limits, so that the same input always leads to the
+    // same search.
+    deterministic: bool,
+
+    // The induction principle for each inductive type we have seen, keyed by the type.
+    // When the goal is about a value of one of these types, we instantiate the principle with it.
+    induction_facts: HashMap<AcornType, Fact>,
+
+    // Normalized facts, shared with the other provers for the project.
+    normalization_cache: Arc<NormalizationCache>,
+
+    // Whether to re-check each proof with the proof checker after finding it.
+    check_proofs: bool,
+
+    // The statistics that the prover counts itself. The rest come from its parts.
+    stats: SearchStats,
+
+    // How many proofs with different premises to look for before stopping.
+    proofs_wanted: usize,
+
+    // The proofs found so far, when we're looking for more than one.
+    found_proofs: Vec<FoundProof>,
+
+    // The steps of a stored proof that checked out, when we replay one instead of searching.
+    replayed_steps: Vec<(ProofStepId, ProofStep)>,
+}
+
+// A proof that the search found and then kept going past.
+#[derive(Clone)]
+struct FoundProof {
+    final_step: ProofStep,
+    useful_passive: Vec<ProofStep>,
+
+    // The sources of the facts that the proof uses, to tell proofs apart.
+    sources: Vec<Source>,
 }
 
 #[derive(Clone)]
@@ -96,6 +175,7 @@ pub enum Outcome {
     Timeout,
     Constrained,
     Error,
+    Countermodel,
 }
 
 impl fmt::Display for Outcome {
@@ -108,10 +188,23 @@ impl fmt::Display for Outcome {
             Outcome::Timeout => write!(f, "Timeout"),
             Outcome::Constrained => write!(f, "Constrained"),
             Outcome::Error => write!(f, "Error"),
+            Outcome::Countermodel => write!(f, "Countermodel"),
         }
     }
 }
 
+// The state of a search in progress, so that it can be saved and resumed later.
+// It only has the clauses the search has found. The prover that resumes it needs to be set up
+// with the same facts and goal as the one that made it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    // The active steps, in the order they were activated.
+    active: Vec<ProofStep>,
+
+    passive: PassiveCheckpoint,
+    non_factual_activated: usize,
+}
+
 impl Prover {
     pub fn new(project: &Project, verbose: bool) -> Prover {
         Prover {
@@ -124,14 +217,150 @@ impl Prover {
             stop_flags: vec![project.build_stopped.clone()],
             error: None,
             useful_passive: vec![],
+            constructor_facts: vec![],
+            clauses: ClauseArena::new(),
             non_factual_activated: 0,
             goal: None,
+            splitting: false,
+            simplifier: ClauseSimplifier::new(),
+            countermodel: None,
+            limit_hit: None,
+            deterministic: project.deterministic(),
+            induction_facts: HashMap::new(),
+            normalization_cache: project.normalization_cache(),
+            check_proofs: project.check_proofs(),
+            stats: SearchStats::default(),
+            proofs_wanted: 1,
+            found_proofs: vec![],
+            replayed_steps: vec![],
+        }
+    }
+
+    // Controls which literals of long clauses get used for inference.
+    // Set this before adding any facts.
+    pub fn set_literal_selection(&mut self, selection: LiteralSelection) {
+        self.active_set.set_literal_selection(selection);
+    }
+
+    // Controls the order that passive clauses get activated in, once verification is over.
+    // Set this before adding any facts.
+    pub fn set_passive_schedule(&mut self, schedule: PassiveSchedule) {
+        self.passive_set.set_schedule(schedule);
+    }
+
+    // Controls how passive clauses are ranked. Set this before adding any facts.
+    pub fn set_scorer(&mut self, scorer: Arc<dyn Scorer + Send + Sync>) {
+        self.passive_set.set_scorer(scorer);
+    }
+
+    // Applies everything in the configuration except the module ordering, which needs the
+    // facts to be added first.
+    // Set this before adding any facts.
+    pub fn configure(&mut self, config: &ProverConfig) {
+        self.set_literal_selection(config.literal_selection);
+        self.set_passive_schedule(config.passive_schedule.clone());
+        if config.scorer != ScorerKind::default() {
+            self.set_scorer(config.scorer.build());
+        }
+        self.set_splitting(config.splitting);
+    }
+
+    // Orients rewrites with the given ordering, rather than using equalities in both directions.
+    // Set this before searching. It can come after the facts and goal are added, since nothing
+    // gets activated until the search starts.
+    pub fn set_kbo(&mut self, kbo: Kbo) {
+        self.active_set.set_kbo(kbo);
+    }
+
+    // An ordering based on how the constants seen so far are defined.
+    // Constructors are the lightest and come first, so that terms get rewritten towards
+    // constructor form. Defined constants are the heaviest, so that they get expanded.
+    // bindings are for the module being proven, which the project may not have loaded yet.
+    pub fn module_kbo(&self, project: &Project, bindings: &BindingMap) -> Kbo {
+        let mut kbo = Kbo::new();
+        for (atom, module_id, name) in self.normalizer.constants() {
+            let module_bindings = if module_id == bindings.module_id() {
+                Some(bindings)
+            } else {
+                project.get_bindings(module_id)
+            };
+            let Some(module_bindings) = module_bindings else {
+                continue;
+            };
+            if module_bindings.is_constructor(name) {
+                continue;
+            }
+            if module_bindings.get_definition(name).is_some() {
+                kbo.set_weight(atom, 2);
+                kbo.set_precedence(atom, 2);
+            } else {
+                kbo.set_precedence(atom, 1);
+            }
         }
+        kbo
+    }
+
+    // Decides linear arithmetic literals for these numeric classes, rather than leaving it
+    // to the axioms. Set this before adding facts.
+    pub fn set_arithmetic(&mut self, arithmetic: LinearArithmetic) {
+        self.simplifier.set_arithmetic(arithmetic);
+    }
+
+    // Finds the numeric classes among the constants the prover knows about.
+    // A class counts if it has an "add" attribute, and it's registered along with its "lt"
+    // and "lte" attributes when it has those.
+    // This trusts that any class like that is an ordered cancellative commutative monoid,
+    // so it should only be used for libraries where that holds.
+    pub fn module_arithmetic(&self) -> LinearArithmetic {
+        let mut attributes: BTreeMap<(ModuleId, &str), HashMap<&str, Atom>> = BTreeMap::new();
+        for (atom, module_id, name) in self.normalizer.constants() {
+            if let Some((class, attr)) = name.rsplit_once('.') {
+                attributes
+                    .entry((module_id, class))
+                    .or_default()
+                    .insert(attr, atom);
+            }
+        }
+        let mut arithmetic = LinearArithmetic::new();
+        for attrs in attributes.values() {
+            if let Some(add) = attrs.get("add") {
+                arithmetic.register(*add, attrs.get("lt").copied(), attrs.get("lte").copied());
+            }
+        }
+        arithmetic
+    }
+
+    // Turns deterministic mode on or off. Set this before searching.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    // When this is on, a proof that fails the proof checker is an error.
+    pub fn set_check_proofs(&mut self, check_proofs: bool) {
+        self.check_proofs = check_proofs;
+    }
+
+    // With more than one proof wanted, the search keeps going after finding a proof, until it
+    // has found that many proofs that use different sets of facts.
+    // It stops early if the search runs out, and succeeds as long as it found any proof.
+    // Each clause only keeps its first derivation, so two proofs only get found when they end
+    // in different contradictions.
+    pub fn set_proofs_wanted(&mut self, proofs_wanted: usize) {
+        self.proofs_wanted = proofs_wanted.max(1);
+    }
+
+    // Turns clause splitting on or off. Set this before searching.
+    pub fn set_splitting(&mut self, splitting: bool) {
+        self.splitting = splitting;
     }
 
     // Add a fact to the prover.
     // The fact can be either polymorphic or monomorphic.
+    // Facts marked "@no_auto" are ignored, since they should only be used by citation.
     pub fn add_fact(&mut self, fact: Fact) {
+        if fact.attributes.no_auto {
+            return;
+        }
         self.monomorphizer.add_fact(fact);
         for fact in self.monomorphizer.take_facts() {
             self.add_monomorphic_fact(fact);
@@ -140,6 +369,9 @@ impl Prover {
 
     // Used to add facts internally, after the fact has already been monomorphized.
     fn add_monomorphic_fact(&mut self, fact: Fact) {
+        if let Some(induction_type) = induction_type(&fact) {
+            self.induction_facts.insert(induction_type, fact.clone());
+        }
         let local = fact.local();
         let defined = match &fact.source.source_type {
             SourceType::ConstantDefinition(value) => {
@@ -153,7 +385,12 @@ impl Prover {
             }
             _ => None,
         };
-        let clauses = match self.normalize_proposition(&fact.value, local) {
+        let cache = self.normalization_cache.clone();
+        let normalization = match cache.normalize(&mut self.normalizer, &fact.value, local) {
+            Some(clauses) => Normalization::Clauses(clauses),
+            None => self.normalize_proposition(&fact.value, local),
+        };
+        let clauses = match normalization {
             Normalization::Clauses(clauses) => clauses,
             Normalization::Impossible => {
                 // We have a false assumption, so we're done already.
@@ -170,12 +407,53 @@ impl Prover {
                 return;
             }
         };
+        if let Some(atom) = defined {
+            if fact.attributes.ac {
+                self.simplifier.add_ac_operator(atom);
+            }
+        }
         let mut steps = vec![];
         for clause in clauses {
+            if !local {
+                self.simplifier.check_ac_axiom(&clause);
+            }
             let step = ProofStep::new_assumption(clause, fact.truthiness, &fact.source, defined);
+            let step = match self.simplifier.simplify_arithmetic(&step.clause) {
+                None => continue,
+                Some(clause) if clause == step.clause => step,
+                Some(clause) => ProofStep::new_simplified(step, &[], clause),
+            };
+            if step.clause.is_impossible() {
+                self.final_step = Some(step);
+                return;
+            }
+            if !self.clauses.intern(&step.clause, step.truthiness).1 {
+                continue;
+            }
+            let fact_id = self.constructor_facts.len();
+            let type_definition = matches!(fact.source.source_type, SourceType::TypeDefinition(_));
+            if self
+                .active_set
+                .graph
+                .add_constructor_fact(&step.clause, fact_id, type_definition)
+            {
+                self.constructor_facts.push(step.clone());
+            }
+            if fact.attributes.simp
+                && step.clause.literals.len() == 1
+                && step.clause.literals[0].positive
+            {
+                // Simp facts are activated immediately, so that they can be used as
+                // rewrite rules for everything that comes later.
+                if self.activate(step) {
+                    return;
+                }
+                continue;
+            }
             steps.push(step);
         }
         self.passive_set.push_batch(steps);
+        self.note_passive_size();
     }
 
     pub fn set_goal(&mut self, goal_context: &GoalContext) {
@@ -192,8 +470,13 @@ impl Prover {
 
         match &goal_context.goal {
             Goal::Prove(prop) => {
+                let value = prop.value.to_arbitrary();
+                if let Some(instance) = self.induction_instance(&value) {
+                    self.add_monomorphic_fact(instance);
+                }
+
                 // Negate the goal and add it as a counterfactual assumption.
-                let (hypo, counter) = prop.value.to_arbitrary().negate_goal();
+                let (hypo, counter) = value.negate_goal();
                 if let Some(hypo) = hypo {
                     self.add_monomorphic_fact(Fact::new(
                         prop.with_value(hypo),
@@ -220,6 +503,66 @@ impl Prover {
         }
     }
 
+    // When the goal is universally quantified over an inductive type, returns the induction
+    // principle for that type, instantiated with the goal.
+    // Its cases then become things the prover can work on, so that simple facts about inductive
+    // types don't need a manual induction.
+    // A goal about a constant counts as universally quantified when no fact mentions the
+    // constant, like the arguments of a theorem.
+    fn induction_instance(&self, goal: &AcornValue) -> Option<Fact> {
+        let predicate = match goal {
+            AcornValue::ForAll(quants, body) if self.induction_facts.contains_key(&quants[0]) => {
+                AcornValue::Lambda(
+                    vec![quants[0].clone()],
+                    Box::new(AcornValue::new_forall(
+                        quants[1..].to_vec(),
+                        (**body).clone(),
+                    )),
+                )
+            }
+            _ => {
+                let mut constants = vec![];
+                goal.find_constants(
+                    &|c| {
+                        !c.is_generic()
+                            && self.induction_facts.contains_key(&c.instance_type)
+                            && !self.normalizer.has_constant(c.module_id, &c.name)
+                    },
+                    &mut constants,
+                );
+                let constant = constants.into_iter().next()?;
+                let var_type = constant.instance_type.clone();
+                let body = goal.clone().insert_stack(0, 1).replace_constants(1, &|c| {
+                    if *c == constant {
+                        Some(AcornValue::Variable(0, var_type.clone()))
+                    } else {
+                        None
+                    }
+                });
+                AcornValue::Lambda(vec![constant.instance_type], Box::new(body))
+            }
+        };
+        let AcornValue::Lambda(arg_types, _) = &predicate else {
+            return None;
+        };
+        let fact = &self.induction_facts[&arg_types[0]];
+        let AcornValue::ForAll(_, principle) = &fact.value else {
+            return None;
+        };
+        let value = (**principle)
+            .clone()
+            .bind_values(0, 0, &[predicate])
+            .expand_lambdas(0);
+
+        // The instance mentions the goal, so it has to be local.
+        Some(Fact {
+            value,
+            source: fact.source.clone(),
+            truthiness: Truthiness::Hypothetical,
+            attributes: Attributes::default(),
+        })
+    }
+
     fn normalize_proposition(&mut self, proposition: &AcornValue, local: bool) -> Normalization {
         if let Err(e) = proposition.validate() {
             return Normalization::Error(format!(
@@ -250,6 +593,14 @@ impl Prover {
         }
         println!("{} clauses in the active set", self.active_set.len());
         println!("{} clauses in the passive set", self.passive_set.len());
+        let stats = &self.simplifier.stats;
+        println!(
+            "simplified away {} tautologies, {} trivial inequalities, {} duplicate literals",
+            stats.tautologies, stats.trivial_inequalities, stats.duplicate_literals
+        );
+        println!("{} clauses condensed", stats.condensations);
+        println!("{} literals decided by arithmetic", stats.arithmetic);
+        println!("{} clauses normalized modulo AC", stats.ac_normalizations);
     }
 
     // Prints out the entire active set
@@ -338,14 +689,14 @@ impl Prover {
             Rule::EqualityFactoring(source)
             | Rule::EqualityResolution(source)
             | Rule::FunctionElimination(source)
-            | Rule::Specialization(source) => {
+            | Rule::Specialization(source)
+            | Rule::Split(source) => {
                 answer.push(("source".to_string(), ProofStepId::Active(*source)));
             }
             Rule::MultipleRewrite(info) => {
-                answer.push((
-                    "inequality".to_string(),
-                    ProofStepId::Active(info.inequality_id),
-                ));
+                if let Some(id) = info.inequality_id {
+                    answer.push(("inequality".to_string(), ProofStepId::Active(id)));
+                }
                 for &id in &info.active_ids {
                     answer.push(("equality".to_string(), ProofStepId::Active(id)));
                 }
@@ -358,6 +709,11 @@ impl Prover {
                     answer.push(("clause".to_string(), ProofStepId::Passive(id)));
                 }
             }
+            Rule::Smt(ids) | Rule::Atp(ids) => {
+                for &id in ids {
+                    answer.push(("clause".to_string(), ProofStepId::Active(id)));
+                }
+            }
         }
 
         for rule in &step.simplification_rules {
@@ -400,6 +756,22 @@ impl Prover {
         self.passive_set.len()
     }
 
+    fn note_passive_size(&mut self) {
+        self.stats.peak_passive = self.stats.peak_passive.max(self.passive_set.len());
+    }
+
+    // The statistics for the searching this prover has done, including what the provers it was
+    // cloned from did.
+    pub fn search_stats(&self) -> SearchStats {
+        SearchStats {
+            activations: self.active_set.len(),
+            simplifications: self.simplifier.stats.total(),
+            scorer_calls: self.passive_set.scorer_calls(),
+            scoring_time: self.passive_set.scoring_time(),
+            ..self.stats.clone()
+        }
+    }
+
     pub fn get_and_print_proof(&self) -> Option<Proof> {
         let proof = match self.get_proof() {
             Some(proof) => proof,
@@ -433,16 +805,139 @@ impl Prover {
         Some(proof)
     }
 
+    // Re-checks the steps of the proof we found with the proof checker.
+    pub fn check_proof(&self) -> CheckReport {
+        match self.get_proof() {
+            Some(proof) => ProofChecker::new(self.simplifier.clone()).check_proof(&proof.all_steps),
+            None => CheckReport::default(),
+        }
+    }
+
     // Returns a condensed proof, if we have a proof.
     pub fn get_proof(&self) -> Option<Proof> {
         let final_step = match &self.final_step {
             Some(step) => step,
-            None => return None,
+            None => return self.get_replayed_proof(),
+        };
+        self.build_proof(final_step, &self.useful_passive)
+    }
+
+    fn get_replayed_proof(&self) -> Option<Proof<'_>> {
+        if self.replayed_steps.is_empty() {
+            return None;
+        }
+        let Some(NormalizedGoal::ProveNegated(negated_goal, _)) = &self.goal else {
+            return None;
         };
+        let mut proof = Proof::new(&self.normalizer, negated_goal, Difficulty::Simple);
+        for (id, step) in &self.replayed_steps {
+            proof.add_step(*id, step);
+        }
+        proof.condense();
+        Some(proof)
+    }
+
+    // The steps of the proof we found, to save for replaying later.
+    pub fn proof_record(&self) -> Option<ProofRecord> {
+        let proof = self.get_proof()?;
+        let steps = proof
+            .all_steps
+            .iter()
+            .map(|(id, step)| (*id, (*step).clone()))
+            .collect();
+        Some(ProofRecord { steps })
+    }
+
+    // Checks a stored proof instead of searching for one.
+    // The facts it assumes have to be ones this prover was given, or simplify from them, and
+    // each step has to pass the proof checker. A step the checker can't re-derive is a failure,
+    // so proofs that use splitting or an external solver can't be replayed.
+    // If the proof checks out, it becomes this prover's proof.
+    pub fn replay(&mut self, record: &ProofRecord) -> Outcome {
+        if self.error.is_some() {
+            return Outcome::Error;
+        }
+        if self.final_step.is_some() {
+            // The facts already contradict each other, so there's nothing to check.
+            return self.final_outcome();
+        }
+        match self.check_record(record) {
+            Ok(()) => {
+                self.replayed_steps = record.steps.clone();
+                Outcome::Success
+            }
+            Err(message) => {
+                self.error = Some(format!("replay failed: {}", message));
+                Outcome::Error
+            }
+        }
+    }
+
+    fn check_record(&self, record: &ProofRecord) -> Result<(), String> {
+        match record.steps.last() {
+            Some((ProofStepId::Final, step)) if step.clause.is_impossible() => {}
+            _ => return Err("the proof does not end in a contradiction".to_string()),
+        }
+        let ids: HashSet<ProofStepId> = record.steps.iter().map(|(id, _)| *id).collect();
+        for (_, step) in &record.steps {
+            let simplifiers = step
+                .simplification_rules
+                .iter()
+                .map(|id| ProofStepId::Active(*id));
+            if let Some(missing) = step
+                .rule
+                .premises()
+                .into_iter()
+                .chain(simplifiers)
+                .find(|id| !ids.contains(id))
+            {
+                return Err(format!("the proof is missing step {:?}", missing));
+            }
+            if step.rule.is_assumption()
+                && step.simplification_rules.is_empty()
+                && self.clauses.get_id(&step.clause, step.truthiness).is_none()
+            {
+                // The clause may mention constants this prover doesn't know, so it can't
+                // be displayed with our normalizer.
+                return Err(format!("{} is not one of the facts", step.clause));
+            }
+        }
+        let steps: Vec<_> = record.steps.iter().map(|(id, step)| (*id, step)).collect();
+        let mut checker = ProofChecker::new(self.simplifier.clone());
+        for clause in self.clauses.iter() {
+            checker.add_fact(clause.clone());
+        }
+        let report = checker.check_proof(&steps);
+        if !report.failures.is_empty() {
+            return Err(report.failures.join("; "));
+        }
+        if report.unchecked > 0 {
+            return Err(format!("{} steps could not be checked", report.unchecked));
+        }
+        Ok(())
+    }
+
+    // Returns every proof we found, the same one get_proof returns first.
+    // There's only more than one when we asked for more than one.
+    pub fn get_proofs(&self) -> Vec<Proof<'_>> {
+        if self.found_proofs.is_empty() {
+            return self.get_proof().into_iter().collect();
+        }
+        self.found_proofs
+            .iter()
+            .filter_map(|found| self.build_proof(&found.final_step, &found.useful_passive))
+            .collect()
+    }
+
+    fn build_proof<'a>(
+        &'a self,
+        final_step: &'a ProofStep,
+        useful_passive: &'a [ProofStep],
+    ) -> Option<Proof<'a>> {
         let mut useful_active = HashSet::new();
         self.active_set
-            .find_upstream(&final_step, &mut useful_active);
-        for step in &self.useful_passive {
+            .find_upstream(final_step, &mut useful_active);
+        for step in useful_passive {
             self.active_set.find_upstream(step, &mut useful_active);
         }
         let negated_goal = match &self.goal {
@@ -466,21 +961,77 @@ impl Prover {
             let step = self.active_set.get_step(*i);
             proof.add_step(ProofStepId::Active(*i), step);
         }
-        for (i, step) in self.useful_passive.iter().enumerate() {
+        for (i, step) in useful_passive.iter().enumerate() {
             proof.add_step(ProofStepId::Passive(i as u32), step);
         }
         proof.add_step(ProofStepId::Final, final_step);
+        let clause_ids: Vec<_> = proof
+            .all_steps
+            .iter()
+            .filter_map(|(id, step)| {
+                Some((*id, self.clauses.get_id(&step.clause, step.truthiness)?))
+            })
+            .collect();
+        for (id, clause_id) in clause_ids {
+            proof.set_clause_id(id, clause_id);
+        }
         proof.condense();
         Some(proof)
     }
 
+    // The single-literal clauses that the proof derived from global facts alone.
+    // They're true for every later goal in the module too, so they can be given to those provers
+    // as facts, with the goal as their source.
+    // Clauses with skolems are left out, since a skolem only means something to this prover.
+    pub fn get_lemmas(&self, goal_context: &GoalContext) -> Vec<Fact> {
+        let (Some(final_step), Goal::Prove(goal)) = (&self.final_step, &goal_context.goal) else {
+            return vec![];
+        };
+        let mut useful_active = HashSet::new();
+        self.active_set
+            .find_upstream(final_step, &mut useful_active);
+        for step in &self.useful_passive {
+            self.active_set.find_upstream(step, &mut useful_active);
+        }
+        let mut active_ids: Vec<_> = useful_active.into_iter().collect();
+        active_ids.sort();
+        active_ids
+            .into_iter()
+            .map(|id| self.active_set.get_step(id))
+            .chain(self.useful_passive.iter())
+            .filter(|step| {
+                step.truthiness == Truthiness::Factual
+                    && (!step.rule.is_assumption() || !step.simplification_rules.is_empty())
+                    && step.clause.len() == 1
+                    && !step.clause.has_skolem()
+                    && !step.clause.has_local_constant()
+            })
+            .map(|step| Fact {
+                value: self.normalizer.denormalize(&step.clause),
+                source: goal.source.clone(),
+                truthiness: Truthiness::Factual,
+                attributes: Attributes::default(),
+            })
+            .collect()
+    }
+
     fn report_term_graph_contradiction(&mut self, contradiction: TermGraphContradiction) {
         let mut active_ids = vec![];
         let mut passive_ids = vec![];
         let mut new_clauses = HashSet::new();
         let mut max_depth = 0;
-        let inequality_step = self.active_set.get_step(contradiction.inequality_id);
-        let mut truthiness = inequality_step.truthiness;
+        let mut truthiness = Truthiness::Factual;
+        if let Some(inequality_id) = contradiction.inequality_id {
+            truthiness = self.active_set.get_step(inequality_id).truthiness;
+        }
+        for fact_id in contradiction.fact_ids {
+            // The constructor facts may never have been activated, so they go in as passive.
+            let step = self.constructor_facts[fact_id].clone();
+            truthiness = truthiness.combine(step.truthiness);
+            let passive_id = self.useful_passive.len() as u32;
+            self.useful_passive.push(step);
+            passive_ids.push(passive_id);
+        }
         for (left, right, rewrite_info) in contradiction.rewrite_chain {
             let rewrite_step = self.active_set.get_step(rewrite_info.pattern_id);
             truthiness = truthiness.combine(rewrite_step.truthiness);
@@ -558,6 +1109,13 @@ impl Prover {
             self.non_factual_activated += 1;
         }
 
+        // Facts that arrived before we knew an operator was AC aren't normalized yet.
+        let step = match self.simplifier.normalize_ac(&step.clause) {
+            clause if clause == step.clause => step,
+            clause if clause.is_tautology() => return false,
+            clause => ProofStep::new_simplified(step, &[], clause),
+        };
+
         if step.clause.is_impossible() {
             self.final_step = Some(step);
             return true;
@@ -598,8 +1156,37 @@ impl Prover {
         }
 
         // Generate new clauses
-        let (alt_activated_id, generated_steps) = self.active_set.activate(activated_step);
-        assert_eq!(activated_id, alt_activated_id);
+        let start = Instant::now();
+        let split = if self.splitting {
+            self.split_clause(&activated_step.clause)
+        } else {
+            None
+        };
+        let generated_steps = match split {
+            Some(parts) => {
+                let steps = parts
+                    .into_iter()
+                    .map(|clause| ProofStep::new_split(activated_id, &activated_step, clause))
+                    .collect();
+                self.active_set.insert_split(activated_step);
+                steps
+            }
+            None => {
+                let (alt_activated_id, generated_steps) = self.active_set.activate(activated_step);
+                assert_eq!(activated_id, alt_activated_id);
+                generated_steps
+            }
+        };
+        self.stats.inference_time += start.elapsed();
+        self.stats.generated += generated_steps.len();
+
+        // Drop the orphans before checking the new clauses for duplicates, since a new clause
+        // may be a duplicate of an orphan.
+        for id in self.active_set.take_newly_redundant() {
+            for orphan in self.passive_set.remove_orphans(id) {
+                self.clauses.forget(&orphan.clause, orphan.truthiness);
+            }
+        }
 
         let print_limit = 30;
         let len = generated_steps.len();
@@ -621,15 +1208,29 @@ impl Prover {
                 continue;
             }
 
+            let step = match self.simplifier.simplify(&step.clause) {
+                None => continue,
+                Some(clause) if clause == step.clause => step,
+                Some(clause) => ProofStep::new_simplified(step, &[], clause),
+            };
+
             if let Some(simple_step) = self.active_set.simplify(step) {
                 if simple_step.clause.is_impossible() {
                     self.final_step = Some(simple_step);
                     return true;
                 }
+                if !self
+                    .clauses
+                    .intern(&simple_step.clause, simple_step.truthiness)
+                    .1
+                {
+                    continue;
+                }
                 new_steps.push(simple_step);
             }
         }
         self.passive_set.push_batch(new_steps);
+        self.note_passive_size();
 
         // Sometimes we find a bunch of contradictions at once.
         // It doesn't really matter what we pick, so we guess which is most likely
@@ -644,6 +1245,141 @@ impl Prover {
         false
     }
 
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            active: self
+                .active_set
+                .iter_steps()
+                .map(|(_, step)| step.clone())
+                .collect(),
+            passive: self.passive_set.checkpoint(),
+            non_factual_activated: self.non_factual_activated,
+        }
+    }
+
+    // Picks up a search from a checkpoint.
+    // This should be called after the goal is set, before searching.
+    // The active steps get indexed again, in the same order, so that they get the same ids
+    // and name the same split components. The clauses they generate are already in the
+    // passive part of the checkpoint, so they get dropped.
+    pub fn resume(&mut self, checkpoint: Checkpoint) {
+        assert!(self.final_step.is_none());
+        let start = self.active_set.len();
+        for (id, step) in checkpoint.active.iter().take(start).enumerate() {
+            assert_eq!(
+                self.active_set.get_clause(id),
+                &step.clause,
+                "checkpoint is from a different prover"
+            );
+        }
+        for step in checkpoint.active.into_iter().skip(start) {
+            self.clauses.intern(&step.clause, step.truthiness);
+            let split = if self.splitting {
+                self.split_clause(&step.clause)
+            } else {
+                None
+            };
+            if split.is_some() {
+                self.active_set.insert_split(step);
+            } else {
+                self.active_set.activate(step);
+            }
+        }
+        self.active_set.take_newly_redundant();
+        self.passive_set.restore(checkpoint.passive);
+        for step in self.passive_set.iter_steps() {
+            self.clauses.intern(&step.clause, step.truthiness);
+        }
+        self.non_factual_activated = checkpoint.non_factual_activated;
+    }
+
+    // Splits a clause into components that don't share variables, so that each component
+    // gets reasoned about on its own.
+    // The first component with variables stays in the clause, along with the ground literals.
+    // Each other component is replaced by a boolean name p, with "not p or component"
+    // as the definition of the name. Proving "not p" for a name then helps every clause
+    // that shares that component.
+    // When there's only one component, the clause may still split into cases.
+    // Returns None if the clause isn't worth splitting.
+    fn split_clause(&mut self, clause: &Clause) -> Option<Vec<Clause>> {
+        let mut kept = vec![];
+        let mut named = vec![];
+        for component in clause.components() {
+            if component.iter().any(|literal| literal.has_any_variable())
+                && kept
+                    .iter()
+                    .any(|literal: &Literal| literal.has_any_variable())
+            {
+                named.push(Clause::new(component));
+            } else {
+                kept.extend(component);
+            }
+        }
+        if named.is_empty() {
+            return self.case_split(clause);
+        }
+
+        let mut definitions = vec![];
+        for component in named {
+            let (name, is_new) = self.normalizer.name_component(&component);
+            kept.push(Literal::positive(name.clone()));
+            if is_new {
+                let mut literals = component.literals;
+                literals.push(Literal::negative(name));
+                definitions.push(Clause::new(literals));
+            }
+        }
+        let mut answer = vec![Clause::new(kept)];
+        answer.extend(definitions);
+        Some(answer)
+    }
+
+    // Splits a clause into cases on a ground boolean term inside one of its literals, like the
+    // condition of an if-then-else. The clause C[b] becomes the two cases:
+    //   not b or C[true]
+    //   b or C[false]
+    // Boolean terms that are a whole side of a literal are already cases, so they don't count.
+    // Returns None if there's no such term.
+    fn case_split(&mut self, clause: &Clause) -> Option<Vec<Clause>> {
+        let normalizer = &self.normalizer;
+        let splittable = |term: &Term| {
+            term.term_type == BOOL
+                && !term.is_true()
+                && !term.has_any_variable()
+                && !normalizer.is_false(term)
+        };
+        let condition = clause
+            .literals
+            .iter()
+            .find_map(|literal| {
+                literal
+                    .left
+                    .find_subterm(&splittable)
+                    .or_else(|| literal.right.find_subterm(&splittable))
+            })?
+            .clone();
+
+        let (false_term, is_new) = self.normalizer.false_term();
+        let mut answer = vec![];
+        for (value, positive) in [(Term::new_true(), false), (false_term.clone(), true)] {
+            let mut literals: Vec<Literal> = clause
+                .literals
+                .iter()
+                .map(|literal| literal.map(&mut |term| term.replace_term(&condition, &value)))
+                .collect();
+            literals.push(if positive {
+                Literal::positive(condition.clone())
+            } else {
+                Literal::negative(condition.clone())
+            });
+            answer.push(Clause::new(literals));
+        }
+        if is_new {
+            answer.push(Clause::new(vec![Literal::negative(false_term)]));
+        }
+        Some(answer)
+    }
+
     // Searches with a short duration.
     // Designed to be called multiple times in succession.
     // The time-based limit is set low, so that it feels interactive.
@@ -652,10 +1388,20 @@ impl Prover {
     }
 
     // Search in verification mode to see if this goal can be easily proven.
-    // The time-based limit is set high enough so that hopefully it will not apply,
-    // because we don't want the result of verification to be machine-dependent.
     pub fn verification_search(&mut self) -> Outcome {
-        self.search_for_contradiction(2000, 5.0, true)
+        self.search_with_limits(&SearchLimits::default(), true)
+    }
+
+    // Searches with the configuration's limits, with these overrides on top.
+    pub fn configured_search(
+        &mut self,
+        config: &ProverConfig,
+        overrides: &LimitOverrides,
+    ) -> Outcome {
+        self.search_with_limits(
+            &config.limits.with_overrides(overrides),
+            config.verification,
+        )
     }
 
     // A single fast search, intended for unit testing.
@@ -668,60 +1414,310 @@ impl Prover {
         self.search_for_contradiction(500, 0.1, true)
     }
 
-    // When 'verification' flag is set, the prover doesn't have to do arbitrarily deeply.
-    // It is allowed to finish as soon as it finishes checking all the verification steps.
     pub fn search_for_contradiction(
         &mut self,
         size: i32,
         seconds: f32,
         verification: bool,
     ) -> Outcome {
+        let limits = SearchLimits {
+            time: Duration::from_secs_f32(seconds),
+            max_active: size as usize,
+            ..SearchLimits::default()
+        };
+        self.search_with_limits(&limits, verification)
+    }
+
+    // When 'verification' flag is set, the prover doesn't have to do arbitrarily deeply.
+    // It is allowed to finish as soon as it finishes checking all the verification steps.
+    // Hitting the time limit is a Timeout, and hitting any other limit is Constrained.
+    // A deterministic prover never times out.
+    pub fn search_with_limits(&mut self, limits: &SearchLimits, verification: bool) -> Outcome {
+        let start = Instant::now();
+        let outcome = self.search_until_stopped(limits, verification);
+        self.stats.search_time += start.elapsed();
+        outcome
+    }
+
+    fn search_until_stopped(&mut self, limits: &SearchLimits, verification: bool) -> Outcome {
         if self.error.is_some() {
             return Outcome::Error;
         }
+        if self.final_step.is_some() && !self.found_proofs.is_empty() {
+            // We already stopped looking for more proofs.
+            return Outcome::Success;
+        }
+        self.limit_hit = None;
         let start_time = std::time::Instant::now();
         loop {
             if verification && !self.passive_set.verification_phase {
                 return Outcome::Exhausted;
             }
             if self.activate_next() {
-                // The prover terminated. Determine which outcome that is.
-                if let Some(final_step) = &self.final_step {
-                    if final_step.truthiness == Truthiness::Counterfactual {
-                        // The normal success case
-                        return Outcome::Success;
-                    }
-                    if let Some(NormalizedGoal::ProveNegated(_, true)) = self.goal {
-                        // We found an inconsistency in our assumptions, but it's okay
-                        return Outcome::Success;
+                let outcome = self.final_outcome();
+                if outcome == Outcome::Success && self.check_proofs {
+                    let report = self.check_proof();
+                    if !report.failures.is_empty() {
+                        self.error = Some(format!(
+                            "proof check failed: {}",
+                            report.failures.join("; ")
+                        ));
+                        return Outcome::Error;
                     }
-                    // We found an inconsistency and it's not okay
-                    return Outcome::Inconsistent;
                 }
-                return Outcome::Exhausted;
+                if outcome == Outcome::Success && self.proofs_wanted > 1 && self.record_proof() {
+                    continue;
+                }
+                return self.finish_with_found_proofs(outcome);
             }
             for stop_flag in &self.stop_flags {
                 if stop_flag.load(std::sync::atomic::Ordering::Relaxed) {
                     return Outcome::Interrupted;
                 }
             }
-            if self.active_set.len() >= size as usize {
+            if let Some(limit) = self.exceeded_limit(limits) {
                 if self.verbose {
-                    println!("active set size hit the limit: {}", self.active_set.len());
+                    println!("active set size: {}", self.active_set.len());
+                    println!("prover hit the {}: {}", limit, limits.describe(limit));
                 }
-                return Outcome::Constrained;
+                self.limit_hit = Some(limit);
+                return self.finish_with_found_proofs(Outcome::Constrained);
             }
-            let elapsed = start_time.elapsed().as_secs_f32();
-            if elapsed >= seconds {
+            if !self.deterministic && start_time.elapsed() >= limits.time {
                 if self.verbose {
                     println!("active set size: {}", self.active_set.len());
-                    println!("prover hit time limit after {} seconds", elapsed);
+                    println!("prover hit time limit after {:?}", start_time.elapsed());
                 }
-                return Outcome::Timeout;
+                self.limit_hit = Some(Limit::Time);
+                return self.finish_with_found_proofs(Outcome::Timeout);
             }
         }
     }
 
+    // Saves the proof we just found, if it uses a different set of facts than the ones we
+    // already have, and clears it so that the search can look for another.
+    // Returns whether the search should keep going.
+    fn record_proof(&mut self) -> bool {
+        let final_step = self.final_step.take().unwrap();
+        let useful_passive = std::mem::take(&mut self.useful_passive);
+        let mut sources: Vec<Source> = vec![];
+        if let Some(proof) = self.build_proof(&final_step, &useful_passive) {
+            for source in proof.iter_sources() {
+                if !sources.contains(source) {
+                    sources.push(source.clone());
+                }
+            }
+        }
+
+        // A contradiction in the term graph stays there, so we would just find it again.
+        let resumable = match final_step.rule {
+            Rule::MultipleRewrite(_) => false,
+            Rule::PassiveContradiction(_) => {
+                self.passive_set.clear_contradiction();
+                true
+            }
+            _ => true,
+        };
+        let is_new = !self.found_proofs.iter().any(|found| {
+            found.sources.len() == sources.len()
+                && found.sources.iter().all(|source| sources.contains(source))
+        });
+        if is_new {
+            if self.verbose {
+                println!(
+                    "found proof {} of {}",
+                    self.found_proofs.len() + 1,
+                    self.proofs_wanted
+                );
+            }
+            self.found_proofs.push(FoundProof {
+                final_step,
+                useful_passive,
+                sources,
+            });
+        }
+        resumable && self.found_proofs.len() < self.proofs_wanted
+    }
+
+    // When the search stops after finding some proofs, the first one becomes the proof.
+    // Anything else the search ran into after the first proof doesn't matter.
+    fn finish_with_found_proofs(&mut self, outcome: Outcome) -> Outcome {
+        let Some(first) = self.found_proofs.first() else {
+            return outcome;
+        };
+        self.final_step = Some(first.final_step.clone());
+        self.useful_passive = first.useful_passive.clone();
+        Outcome::Success
+    }
+
+    // Which of the deterministic limits the search has gone past, if any.
+    fn exceeded_limit(&self, limits: &SearchLimits) -> Option<Limit> {
+        if self.active_set.len() >= limits.max_active {
+            return Some(Limit::Active);
+        }
+        if let Some(max_clauses) = limits.max_clauses {
+            if self.active_set.len() + self.passive_set.len() >= max_clauses {
+                return Some(Limit::Clauses);
+            }
+        }
+        if let Some(max_memory) = limits.max_memory {
+            if self.passive_set.approximate_memory() >= max_memory {
+                return Some(Limit::Memory);
+            }
+        }
+        None
+    }
+
+    // Which limit stopped the last search, if one did.
+    pub fn limit_hit(&self) -> Option<Limit> {
+        self.limit_hit
+    }
+
+    // The prover terminated. Determine which outcome that is.
+    fn final_outcome(&self) -> Outcome {
+        if let Some(final_step) = &self.final_step {
+            if final_step.truthiness == Truthiness::Counterfactual {
+                // The normal success case
+                return Outcome::Success;
+            }
+            if let Some(NormalizedGoal::ProveNegated(_, true)) = self.goal {
+                // We found an inconsistency in our assumptions, but it's okay
+                return Outcome::Success;
+            }
+            // We found an inconsistency and it's not okay
+            return Outcome::Inconsistent;
+        }
+        Outcome::Exhausted
+    }
+
+    // Hands the ground clauses in the active set to an external SMT solver.
+    // This is meant to be run after a search that didn't succeed, since the search is what
+    // fills the active set with ground instances of the facts.
+    // If the solver finds a contradiction, it becomes the final step, citing the clauses that
+    // the solver used.
+    pub fn smt_search(&mut self, config: &SmtConfig) -> Outcome {
+        if self.error.is_some() {
+            return Outcome::Error;
+        }
+        let mut problem = SmtProblem::new();
+        let mut added = vec![];
+        for (id, step) in self.active_set.iter_steps() {
+            if problem.add_clause(id, &step.clause) {
+                added.push(id);
+            }
+        }
+        if added.is_empty() {
+            return Outcome::Exhausted;
+        }
+        match problem.solve(config) {
+            SmtResult::Unsat(ids) => {
+                // If the solver didn't report a core, cite everything.
+                let ids = if ids.is_empty() { added } else { ids };
+                self.external_contradiction(Rule::Smt(ids.clone()), &ids)
+            }
+            SmtResult::Sat => Outcome::Exhausted,
+            SmtResult::Unknown(reason) => {
+                if self.verbose {
+                    println!("smt solver gave up: {}", reason);
+                }
+                Outcome::Exhausted
+            }
+        }
+    }
+
+    // Exports the active set to an external first-order prover.
+    // Like smt_search, this is meant to be run after a search that didn't succeed.
+    pub fn atp_search(&mut self, config: &AtpConfig) -> Outcome {
+        if self.error.is_some() {
+            return Outcome::Error;
+        }
+        let mut problem = TptpProblem::new();
+        for (id, step) in self.active_set.iter_steps() {
+            problem.add_clause(id, &step.clause, step.rule.is_negated_goal());
+        }
+        if problem.is_empty() {
+            return Outcome::Exhausted;
+        }
+        match problem.solve(config) {
+            AtpResult::Proved(ids) => self.external_contradiction(Rule::Atp(ids.clone()), &ids),
+            AtpResult::Disproved => Outcome::Exhausted,
+            AtpResult::Unknown(reason) => {
+                if self.verbose {
+                    println!("external prover gave up: {}", reason);
+                }
+                Outcome::Exhausted
+            }
+        }
+    }
+
+    // Looks for a small finite model of the active set, to show that the goal is probably false.
+    // Like the external searches, this is meant to be run after a search that didn't succeed.
+    // Without the negated goal in the active set, a model wouldn't tell us anything.
+    pub fn countermodel_search(&mut self, finder: &ModelFinder) -> Outcome {
+        if self.error.is_some() {
+            return Outcome::Error;
+        }
+        let mut has_negated_goal = false;
+        let mut clauses = vec![];
+        for (_, step) in self.active_set.iter_steps() {
+            has_negated_goal |= step.rule.is_negated_goal();
+            clauses.push(&step.clause);
+        }
+        if !has_negated_goal {
+            return Outcome::Exhausted;
+        }
+        match finder.find(&clauses) {
+            Some(model) => {
+                self.countermodel = Some(model);
+                Outcome::Countermodel
+            }
+            None => Outcome::Exhausted,
+        }
+    }
+
+    // Describes the countermodel that countermodel_search found.
+    pub fn display_countermodel(&self) -> Option<String> {
+        let model = self.countermodel.as_ref()?;
+        Some(
+            DisplayModel {
+                model,
+                normalizer: &self.normalizer,
+            }
+            .to_string(),
+        )
+    }
+
+    // The details of the countermodel that countermodel_search found.
+    // The violated facts are the ones the prover hadn't activated yet, that the model
+    // makes false.
+    pub fn counterexample(&self) -> Option<Counterexample> {
+        let model = self.countermodel.as_ref()?;
+        let assignments = self
+            .display_countermodel()?
+            .lines()
+            .map(|line| line.to_string())
+            .collect();
+        let violated = self
+            .passive_set
+            .iter_steps()
+            .filter(|step| {
+                step.rule.is_assumption() && model.satisfies(&step.clause) == Some(false)
+            })
+            .map(|step| self.display(&step.clause).to_string())
+            .collect();
+        Some(Counterexample {
+            assignments,
+            violated,
+        })
+    }
+
+    // Finishes the proof with a contradiction between these active steps.
+    fn external_contradiction(&mut self, rule: Rule, ids: &[usize]) -> Outcome {
+        let steps: Vec<_> = ids.iter().map(|id| self.active_set.get_step(*id)).collect();
+        self.final_step = Some(ProofStep::new_external(rule, &steps));
+        self.final_outcome()
+    }
+
     fn display<'a>(&'a self, clause: &'a Clause) -> DisplayClause<'a> {
         DisplayClause {
             clause,
@@ -743,12 +1739,125 @@ impl Prover {
     // Attempts to convert this clause to code, but shows the clause form if that's all we can.
     fn clause_to_code(&self, bindings: &BindingMap, clause: &Clause) -> String {
         let denormalized = self.normalizer.denormalize(clause);
-        if let Ok(code) = bindings.value_to_code(&denormalized) {
+        if let Ok(code) = bindings.value_to_code_with_style(&denormalized, &CodeStyle::hover()) {
             return code;
         }
         self.display(clause).to_string()
     }
 
+    // Like clause_to_code, for a single term.
+    fn term_to_code(&self, bindings: &BindingMap, term: &Term) -> String {
+        if let Some(value) = self.normalizer.denormalize_term_value(term) {
+            if let Ok(code) = bindings.value_to_code_with_style(&value, &CodeStyle::hover()) {
+                return code;
+            }
+        }
+        DisplayTerm {
+            term,
+            normalizer: &self.normalizer,
+        }
+        .to_string()
+    }
+
+    // How to refer to a premise in a justification.
+    // Named axioms and theorems go by their names, and other clauses by their ids.
+    fn premise_name(&self, id: ProofStepId) -> String {
+        let step = match id {
+            ProofStepId::Active(i) => self.active_set.get_step(i),
+            ProofStepId::Passive(i) => &self.useful_passive[i as usize],
+            ProofStepId::Final => return "the final step".to_string(),
+        };
+        match &step.rule {
+            Rule::Assumption(info) => match &info.source.source_type {
+                SourceType::Axiom(Some(name)) | SourceType::Theorem(Some(name)) => name.clone(),
+                SourceType::NegatedGoal => "the negated goal".to_string(),
+                _ => info.source.description(),
+            },
+            _ => match id {
+                ProofStepId::Active(i) => format!("clause {}", i),
+                _ => "an unactivated clause".to_string(),
+            },
+        }
+    }
+
+    // Explains the step in words, for a user who doesn't know the prover's rules.
+    pub fn justification(&self, bindings: &BindingMap, step: &ProofStep) -> String {
+        let name = |id: usize| self.premise_name(ProofStepId::Active(id));
+        // A simplified step doesn't show what the rule itself produced.
+        let simplified = !step.simplification_rules.is_empty();
+        let contradiction = step.clause.is_impossible() && !simplified;
+        let mut answer = match &step.rule {
+            Rule::Assumption(info) => match &info.source.source_type {
+                SourceType::NegatedGoal => "assumed the negation of the goal".to_string(),
+                _ => format!("by {}", info.source.description()),
+            },
+            Rule::Resolution(info) if contradiction => {
+                format!("{} contradicts {}", name(info.short_id), name(info.long_id))
+            }
+            Rule::Resolution(info) => format!(
+                "combined {} with {}",
+                name(info.long_id),
+                name(info.short_id)
+            ),
+            Rule::Rewrite(info) => {
+                let pattern = self.active_set.get_clause(info.pattern_id);
+                let target = self.active_set.get_clause(info.target_id);
+                let rewritten = if simplified {
+                    find_rewritable_instance(pattern, target)
+                } else {
+                    find_replaced_subterm(target, &step.clause)
+                };
+                match rewritten {
+                    Some((old, new)) => format!(
+                        "rewrote {} to {} using {}",
+                        self.term_to_code(bindings, &old),
+                        self.term_to_code(bindings, &new),
+                        name(info.pattern_id)
+                    ),
+                    None if contradiction => format!(
+                        "rewriting {} with {} gives a contradiction",
+                        name(info.target_id),
+                        name(info.pattern_id)
+                    ),
+                    None => format!(
+                        "rewrote {} using {}",
+                        name(info.target_id),
+                        name(info.pattern_id)
+                    ),
+                }
+            }
+            Rule::Specialization(id) => format!("specialized {}", name(*id)),
+            Rule::Split(id) => format!("split {} into independent parts", name(*id)),
+            Rule::EqualityFactoring(id)
+            | Rule::EqualityResolution(id)
+            | Rule::FunctionElimination(id) => {
+                format!("{} of {}", step.rule.name().to_lowercase(), name(*id))
+            }
+            Rule::MultipleRewrite(info) => format!(
+                "rewriting with {} equalities gives a contradiction",
+                info.active_ids.len() + info.passive_ids.len()
+            ),
+            Rule::PassiveContradiction(1) => "found a contradiction in one clause".to_string(),
+            Rule::PassiveContradiction(n) => format!("{} clauses contradict each other", n),
+            Rule::Smt(ids) | Rule::Atp(ids) => format!(
+                "an external solver found that {} contradict each other",
+                ids.iter()
+                    .map(|id| name(*id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        if simplified {
+            let names: Vec<_> = step
+                .simplification_rules
+                .iter()
+                .map(|id| name(*id))
+                .collect();
+            answer.push_str(&format!(", then simplified using {}", names.join(", ")));
+        }
+        answer
+    }
+
     // Convert a clause to a jsonable form
     // We only take active ids, because the others have no external meaning.
     // If we are given a binding map, use it to make a nicer-looking display.
@@ -781,28 +1890,77 @@ impl Prover {
             premises.push((description, clause_info));
         }
         let (rule, location) = match &step.rule {
-            Rule::Assumption(info) => {
-                let location = project
-                    .path_from_module_id(info.source.module)
-                    .and_then(|path| Url::from_file_path(path).ok())
-                    .map(|uri| Location {
-                        uri,
-                        range: info.source.range,
-                    });
-
-                (info.source.description(), location)
-            }
+            Rule::Assumption(info) => (
+                info.source.description(),
+                source_location(project, &info.source),
+            ),
             _ => (step.rule.name().to_lowercase(), None),
         };
         ProofStepInfo {
             clause,
             premises,
             rule,
+            justification: self.justification(bindings, step),
             location,
             depth: step.depth,
         }
     }
 
+    // The facts that the proof used, each with where it's defined.
+    pub fn to_premise_info(&self, project: &Project, proof: &Proof) -> Vec<PremiseInfo> {
+        let mut sources: Vec<&Source> = vec![];
+        for source in proof.iter_sources() {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        sources
+            .into_iter()
+            .map(|source| PremiseInfo {
+                description: source.description(),
+                location: source_location(project, source),
+            })
+            .collect()
+    }
+
+    // Describes where the search is at, with up to count clauses in each list.
+    // The search releases its lock on the prover between partial searches, so this can be
+    // called while a search is running.
+    pub fn snapshot_state(&self, bindings: &BindingMap, count: usize) -> ProverSnapshot {
+        let mut goal = vec![];
+        for (id, step) in self.active_set.iter_steps() {
+            if step.rule.is_negated_goal() {
+                goal.push(self.to_clause_info(bindings, Some(id), &step.clause));
+            }
+        }
+        for step in self.passive_set.iter_steps() {
+            if step.rule.is_negated_goal() {
+                goal.push(self.to_clause_info(bindings, None, &step.clause));
+            }
+        }
+        let next_passive = self
+            .passive_set
+            .iter_scored_steps()
+            .take(count)
+            .map(|(step, score)| ScoredClauseInfo {
+                clause: self.to_clause_info(bindings, None, &step.clause),
+                score: score.value(),
+            })
+            .collect();
+        let recent_activations = (0..self.active_set.len())
+            .rev()
+            .take(count)
+            .map(|id| self.to_clause_info(bindings, Some(id), self.active_set.get_clause(id)))
+            .collect();
+        ProverSnapshot {
+            goal,
+            next_passive,
+            recent_activations,
+            num_activated: self.num_activated(),
+            num_passive: self.num_passive(),
+        }
+    }
+
     pub fn to_proof_info(
         &self,
         project: &Project,
@@ -865,3 +2023,84 @@ impl Prover {
         })
     }
 }
+
+// If this fact is the induction principle for an inductive type, returns the type.
+// The principle is quantified over a predicate on the type.
+fn induction_type(fact: &Fact) -> Option<AcornType> {
+    match &fact.source.source_type {
+        SourceType::Axiom(Some(name)) if name.ends_with(".induction") => {}
+        _ => return None,
+    }
+    match &fact.value {
+        AcornValue::ForAll(quants, _) if quants.len() == 1 => match &quants[0] {
+            AcornType::Function(f)
+                if f.arg_types.len() == 1 && *f.return_type == AcornType::Bool =>
+            {
+                Some(f.arg_types[0].clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Where the source is in a file the editor can open.
+fn source_location(project: &Project, source: &Source) -> Option<Location> {
+    let path = project.path_from_module_id(source.module)?;
+    let uri = Url::from_file_path(path).ok()?;
+    Some(Location {
+        uri,
+        range: source.range,
+    })
+}
+
+// Finds which subterm of the target was rewritten to produce the clause, if we can tell.
+fn find_replaced_subterm(target: &Clause, clause: &Clause) -> Option<(Term, Term)> {
+    if target.literals.len() != 1 || clause.literals.len() != 1 {
+        return None;
+    }
+    let (before, after) = (&target.literals[0], &clause.literals[0]);
+    for (u, v) in [(&before.left, &before.right), (&before.right, &before.left)] {
+        for (new_u, new_v) in [(&after.left, &after.right), (&after.right, &after.left)] {
+            if v == new_v {
+                if let Some((old, new)) = u.find_replacement(new_u) {
+                    return Some((old.clone(), new.clone()));
+                }
+            }
+        }
+    }
+    None
+}
+
+// Finds a subterm of the target that the pattern can rewrite, along with what it rewrites to.
+// When the rewritten clause has been simplified, this is the best guess at what the rewrite was.
+fn find_rewritable_instance(pattern: &Clause, target: &Clause) -> Option<(Term, Term)> {
+    if pattern.literals.len() != 1 || target.literals.len() != 1 {
+        return None;
+    }
+    let (pattern, target) = (&pattern.literals[0], &target.literals[0]);
+    if !pattern.positive {
+        return None;
+    }
+    for (u, v) in [
+        (&pattern.left, &pattern.right),
+        (&pattern.right, &pattern.left),
+    ] {
+        for side in [&target.left, &target.right] {
+            if side.has_any_variable() {
+                continue;
+            }
+            for (_, subterm) in side.rewritable_subterms() {
+                let mut unifier = Unifier::new();
+                if !unifier.unify(Scope::Left, u, Scope::Right, subterm) {
+                    continue;
+                }
+                let new = unifier.apply(Scope::Left, v);
+                if !new.has_any_variable() {
+                    return Some((subterm.clone(), new));
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use crate::active_set::LiteralSelection;
+use crate::limits::SearchLimits;
+use crate::passive_set::{PassiveQueue, PassiveSchedule};
+use crate::scorer::{default_scorer, HandcraftedScorer, Scorer};
+
+// Which scorer the passive set uses to rank clauses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScorerKind {
+    // The learned model.
+    #[default]
+    Learned,
+
+    Handcrafted,
+}
+
+impl ScorerKind {
+    pub fn build(&self) -> Arc<dyn Scorer + Send + Sync> {
+        match self {
+            ScorerKind::Learned => default_scorer().into(),
+            ScorerKind::Handcrafted => Arc::new(HandcraftedScorer),
+        }
+    }
+}
+
+// Everything that determines how a prover searches, other than the facts and the goal.
+// The default is the configuration that verification uses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProverConfig {
+    // Identifies the strategy, so that we can remember which one worked for a goal.
+    pub name: String,
+
+    pub literal_selection: LiteralSelection,
+    pub passive_schedule: PassiveSchedule,
+    pub scorer: ScorerKind,
+    pub splitting: bool,
+
+    // Whether to orient rewrites with the ordering from how the module's constants are defined.
+    pub module_kbo: bool,
+
+    pub limits: SearchLimits,
+
+    // A verification search stops once it runs out of clauses that are usable for verification.
+    // Without this flag, the search keeps going until it hits its limits.
+    pub verification: bool,
+}
+
+impl Default for ProverConfig {
+    fn default() -> ProverConfig {
+        ProverConfig {
+            name: "default".to_string(),
+            literal_selection: LiteralSelection::default(),
+            passive_schedule: PassiveSchedule::default(),
+            scorer: ScorerKind::default(),
+            splitting: false,
+            module_kbo: false,
+            limits: SearchLimits::default(),
+            verification: true,
+        }
+    }
+}
+
+impl ProverConfig {
+    // The strategies to try after the default one fails.
+    // Each one changes a different part of the search, so that they tend to succeed on
+    // different goals.
+    pub fn portfolio() -> Vec<ProverConfig> {
+        vec![
+            ProverConfig {
+                name: "ordered".to_string(),
+                module_kbo: true,
+                literal_selection: LiteralSelection::SelectLargestNegative,
+                ..ProverConfig::default()
+            },
+            ProverConfig {
+                name: "handcrafted".to_string(),
+                scorer: ScorerKind::Handcrafted,
+                passive_schedule: PassiveSchedule::age_weight_ratio(1, 4),
+                verification: false,
+                ..ProverConfig::default()
+            },
+            ProverConfig {
+                name: "equational".to_string(),
+                passive_schedule: PassiveSchedule {
+                    queues: vec![(PassiveQueue::UnitEquality, 1), (PassiveQueue::Score, 2)],
+                },
+                splitting: true,
+                verification: false,
+                ..ProverConfig::default()
+            },
+        ]
+    }
+}
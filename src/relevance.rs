@@ -0,0 +1,114 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::acorn_value::AcornValue;
+use crate::fact::Fact;
+use crate::module::ModuleId;
+use crate::value_visitor::{UsageCollector, ValueVisitor};
+
+type Symbol = (ModuleId, String);
+
+// The constants that a value uses.
+fn symbols(value: &AcornValue) -> BTreeSet<Symbol> {
+    let mut collector = UsageCollector::default();
+    collector.visit_value(value, 0);
+    collector.constants
+}
+
+// Chooses which imported facts to give the prover, based on the symbols they share with the goal.
+// This is the SInE ("sine qua non") selection from the Vampire prover.
+// A fact is triggered by the rarest symbols in it, where a symbol counts as rare for a fact if
+// it occurs in at most "tolerance" times as many facts as the fact's rarest symbol.
+// The goal's symbols trigger the first tier of facts, their symbols trigger the next tier,
+// and so on, until max_depth tiers have been admitted.
+#[derive(Clone, Debug)]
+pub struct RelevanceFilter {
+    pub tolerance: f64,
+    pub max_depth: usize,
+}
+
+impl Default for RelevanceFilter {
+    fn default() -> RelevanceFilter {
+        RelevanceFilter {
+            tolerance: 1.5,
+            max_depth: 3,
+        }
+    }
+}
+
+impl RelevanceFilter {
+    pub fn new(tolerance: f64, max_depth: usize) -> RelevanceFilter {
+        assert!(tolerance >= 1.0, "relevance tolerance must be at least 1");
+        RelevanceFilter {
+            tolerance,
+            max_depth,
+        }
+    }
+
+    // Filters the facts that are usable for a goal in the given module.
+    // Facts from the module itself are always kept. Hypothetical ones are treated like part of
+    // the goal, so their symbols are the starting point for selecting imported facts.
+    // Imported facts that don't use any constants can't be triggered, so they are always kept.
+    pub fn select(&self, facts: Vec<Fact>, goal: &AcornValue, module_id: ModuleId) -> Vec<Fact> {
+        let mut seeds = symbols(goal);
+        let mut keep = vec![true; facts.len()];
+        let mut candidates: Vec<(usize, BTreeSet<Symbol>)> = vec![];
+        for (i, fact) in facts.iter().enumerate() {
+            if fact.local() {
+                seeds.extend(symbols(&fact.value));
+            } else if fact.source.module != module_id {
+                let fact_symbols = symbols(&fact.value);
+                if !fact_symbols.is_empty() {
+                    keep[i] = false;
+                    candidates.push((i, fact_symbols));
+                }
+            }
+        }
+
+        // Count how many candidates each symbol occurs in.
+        let mut occurrences: HashMap<&Symbol, usize> = HashMap::new();
+        for (_, fact_symbols) in &candidates {
+            for symbol in fact_symbols {
+                *occurrences.entry(symbol).or_default() += 1;
+            }
+        }
+
+        // Map each symbol to the candidates it triggers.
+        let mut triggers: HashMap<&Symbol, Vec<usize>> = HashMap::new();
+        for (c, (_, fact_symbols)) in candidates.iter().enumerate() {
+            let rarest = fact_symbols.iter().map(|s| occurrences[s]).min().unwrap();
+            let limit = self.tolerance * rarest as f64;
+            for symbol in fact_symbols {
+                if occurrences[symbol] as f64 <= limit {
+                    triggers.entry(symbol).or_default().push(c);
+                }
+            }
+        }
+
+        // Admit the candidates tier by tier.
+        let mut seen: BTreeSet<&Symbol> = seeds.iter().collect();
+        let mut frontier: Vec<&Symbol> = seeds.iter().collect();
+        for _ in 0..self.max_depth {
+            let mut next = vec![];
+            for symbol in frontier {
+                for &c in triggers.get(symbol).into_iter().flatten() {
+                    let (i, fact_symbols) = &candidates[c];
+                    if keep[*i] {
+                        continue;
+                    }
+                    keep[*i] = true;
+                    next.extend(fact_symbols.iter().filter(|s| seen.insert(s)));
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        facts
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(fact, keep)| if keep { Some(fact) } else { None })
+            .collect()
+    }
+}
@@ -5,7 +5,6 @@ use crate::atom::AtomId;
 use crate::literal::Literal;
 use crate::pattern_tree::{term_key_prefix, PatternTree, TermComponent};
 use crate::term::Term;
-use crate::type_map::TypeId;
 
 // Each term can correspond with multiple RewriteValues.
 // This is the internal representation of the pattern, before it has been applied to a term.
@@ -20,6 +19,11 @@ struct RewriteValue {
     // The pattern that we are rewriting into.
     // The pattern that we are rewriting *from* is kept in the key.
     output: Vec<TermComponent>,
+
+    // The literals that have to be true for the rewrite to apply, as (positive, left, right).
+    // For a p -> s = t rule, the condition is p.
+    // Unconditional rules have no conditions.
+    conditions: Vec<(bool, Vec<TermComponent>, Vec<TermComponent>)>,
 }
 
 // The external representation of a rewrite, after it has been applied to a particular term.
@@ -33,6 +37,9 @@ pub struct Rewrite {
 
     // The term that we are rewriting into.
     pub term: Term,
+
+    // The rewrite is only valid when all of these literals are true.
+    pub conditions: Vec<Literal>,
 }
 
 #[derive(Clone)]
@@ -55,6 +62,19 @@ impl RewriteTree {
         input_term: &Term,
         output_term: &Term,
         forwards: bool,
+    ) {
+        self.insert_with_conditions(pattern_id, input_term, output_term, &[], forwards);
+    }
+
+    // NOTE: The input term's variable ids must be normalized, and the conditions can only use
+    // variables from the input term.
+    fn insert_with_conditions(
+        &mut self,
+        pattern_id: usize,
+        input_term: &Term,
+        output_term: &Term,
+        conditions: &[Literal],
+        forwards: bool,
     ) {
         if input_term.is_true() {
             panic!("cannot rewrite true to something else");
@@ -63,6 +83,16 @@ impl RewriteTree {
             pattern_id,
             forwards,
             output: TermComponent::flatten_term(output_term),
+            conditions: conditions
+                .iter()
+                .map(|literal| {
+                    (
+                        literal.positive,
+                        TermComponent::flatten_term(&literal.left),
+                        TermComponent::flatten_term(&literal.right),
+                    )
+                })
+                .collect(),
         };
         PatternTree::insert_or_append(&mut self.tree, input_term, value);
     }
@@ -70,65 +100,108 @@ impl RewriteTree {
     // Inserts both directions.
     // NOTE: The input term's variable ids must be normalized.
     pub fn insert_literal(&mut self, pattern_id: usize, literal: &Literal) {
-        // Already normalized
-        self.insert_terms(pattern_id, &literal.left, &literal.right, true);
+        self.insert_oriented(pattern_id, literal, true, true);
+    }
 
-        if !literal.right.is_true() {
+    // Inserts only the directions that are allowed.
+    // Forwards rewrites left to right, backwards rewrites right to left.
+    pub fn insert_oriented(
+        &mut self,
+        pattern_id: usize,
+        literal: &Literal,
+        forwards: bool,
+        backwards: bool,
+    ) {
+        if forwards {
+            // Already normalized
+            self.insert_terms(pattern_id, &literal.left, &literal.right, true);
+        }
+
+        if backwards && !literal.right.is_true() {
             let (right, left) = literal.normalized_reversed();
             self.insert_terms(pattern_id, &right, &left, false);
         }
     }
 
-    // The callback is on (rule id, forwards, new components).
-    fn find_rewrites<F>(
-        &self,
-        term_type: TypeId,
-        components: &[TermComponent],
-        next_var: AtomId,
-        callback: &mut F,
-    ) where
-        F: FnMut(usize, bool, &[TermComponent]),
-    {
-        let mut key = term_key_prefix(term_type);
-        let mut replacements = vec![];
-        self.tree.find_matches_while(
-            &mut key,
-            components,
-            &mut replacements,
-            &mut |value_id, replacements| {
-                for value in &self.tree.values[value_id] {
-                    let new_components = TermComponent::replace_or_shift(
-                        &value.output,
-                        replacements,
-                        Some(next_var),
-                    );
-                    callback(value.pattern_id, value.forwards, &new_components);
-                }
-                true
-            },
-        );
+    // Inserts a rewrite that only applies when the conditions are true.
+    // The conditions can only use variables that appear in the side being rewritten, because
+    // matching a term doesn't give us values for any other variables.
+    // Returns whether the rewrite could be inserted.
+    pub fn insert_conditional(
+        &mut self,
+        pattern_id: usize,
+        literal: &Literal,
+        conditions: &[Literal],
+        forwards: bool,
+    ) -> bool {
+        let (input, output) = if forwards {
+            (&literal.left, &literal.right)
+        } else {
+            (&literal.right, &literal.left)
+        };
+
+        // Number the variables in the order the input term uses them.
+        let mut var_ids = vec![];
+        let mut input = input.clone();
+        input.normalize_var_ids(&mut var_ids);
+        let num_input_vars = var_ids.len();
+        let mut output = output.clone();
+        output.normalize_var_ids(&mut var_ids);
+        let mut normalized = vec![];
+        for condition in conditions {
+            let mut left = condition.left.clone();
+            left.normalize_var_ids(&mut var_ids);
+            let mut right = condition.right.clone();
+            right.normalize_var_ids(&mut var_ids);
+            normalized.push(Literal {
+                positive: condition.positive,
+                left,
+                right,
+            });
+        }
+        if var_ids.len() > num_input_vars {
+            return false;
+        }
+        self.insert_with_conditions(pattern_id, &input, &output, &normalized, forwards);
+        true
     }
 
     // Finds all the ways to rewrite the given term, at the root level.
     //
     // Sometimes rewrites have to create a new variable.
     // When we create new variables, we start numbering from next_var.
-    //
-    // Returns a list of (pattern_id, forwards, new_term) tuples.
     pub fn get_rewrites(&self, input_term: &Term, next_var: AtomId) -> Vec<Rewrite> {
         let mut answer = vec![];
         let components = TermComponent::flatten_term(input_term);
-        self.find_rewrites(
-            input_term.term_type,
+        let mut key = term_key_prefix(input_term.term_type);
+        let mut replacements = vec![];
+        self.tree.find_matches_while(
+            &mut key,
             &components,
-            next_var,
-            &mut |pattern_id, forwards, new_components| {
-                let term = TermComponent::unflatten_term(new_components);
-                answer.push(Rewrite {
-                    pattern_id,
-                    forwards,
-                    term,
-                });
+            &mut replacements,
+            &mut |value_id, replacements| {
+                for value in &self.tree.values[value_id] {
+                    let apply = |components: &[TermComponent]| {
+                        TermComponent::unflatten_term(&TermComponent::replace_or_shift(
+                            components,
+                            replacements,
+                            Some(next_var),
+                        ))
+                    };
+                    answer.push(Rewrite {
+                        pattern_id: value.pattern_id,
+                        forwards: value.forwards,
+                        term: apply(&value.output),
+                        conditions: value
+                            .conditions
+                            .iter()
+                            .map(|(positive, left, right)| {
+                                Literal::new(*positive, apply(left), apply(right))
+                            })
+                            .collect(),
+                    });
+                }
+                true
             },
         );
         answer
@@ -204,4 +277,19 @@ mod tests {
         let rewrites = tree.get_rewrites(&const3, 0);
         assert_eq!(rewrites.len(), 0);
     }
+
+    #[test]
+    fn test_conditional_rewrite() {
+        let mut tree = RewriteTree::new();
+        let conditions = vec![Literal::parse("c2(x0) = c3")];
+        assert!(tree.insert_conditional(0, &Literal::parse("c1(x0) = x0"), &conditions, true));
+        let rewrites = tree.get_rewrites(&Term::parse("c1(c4)"), 0);
+        assert_eq!(rewrites.len(), 1);
+        assert_eq!(rewrites[0].term, Term::parse("c4"));
+        assert_eq!(rewrites[0].conditions, vec![Literal::parse("c2(c4) = c3")]);
+
+        // The condition can't use a variable that the rewritten side doesn't have
+        let conditions = vec![Literal::parse("c2(x1) = c3")];
+        assert!(!tree.insert_conditional(1, &Literal::parse("c1(x0) = x0"), &conditions, true));
+    }
 }
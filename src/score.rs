@@ -1,4 +1,5 @@
 use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
 
 use crate::features::Features;
 use crate::scorer::Scorer;
@@ -6,7 +7,7 @@ use crate::scorer::Scorer;
 // Each proof step has a score, which encapsulates all heuristic judgments about
 // the proof step.
 // The better the score, the more we want to activate this proof step.
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct Score {
     // Contradictions are the most important thing
     contradiction: bool,
@@ -22,6 +23,11 @@ pub struct Score {
 }
 
 impl Score {
+    // The number the scorer gave this step, ignoring the rules that take priority over it.
+    pub fn value(&self) -> f32 {
+        self.score.into_inner()
+    }
+
     // The logic here is logic that we want to use regardless of the policy.
     pub fn new(scorer: &dyn Scorer, features: &Features) -> Score {
         if features.is_contradiction {
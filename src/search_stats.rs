@@ -0,0 +1,145 @@
+use std::fmt;
+use std::time::Duration;
+
+// Counters for one proof search.
+// They get reported with each goal in a build, so that slowdowns in the prover are visible.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    // How many clauses were activated.
+    pub activations: usize,
+
+    // How many clauses inference generated, before simplification and deduplication.
+    pub generated: usize,
+
+    // How many times the single-clause simplifications changed or dropped a clause.
+    pub simplifications: usize,
+
+    // The most clauses the passive set held at once.
+    pub peak_passive: usize,
+
+    // How many batches of clauses the scorer was run on.
+    pub scorer_calls: usize,
+
+    // The time spent searching, and the parts of it spent generating and scoring clauses.
+    pub search_time: Duration,
+    pub inference_time: Duration,
+    pub scoring_time: Duration,
+}
+
+impl SearchStats {
+    // Adds the counts from another search. Peak sizes take the maximum.
+    pub fn add(&mut self, other: &SearchStats) {
+        self.activations += other.activations;
+        self.generated += other.generated;
+        self.simplifications += other.simplifications;
+        self.peak_passive = self.peak_passive.max(other.peak_passive);
+        self.scorer_calls += other.scorer_calls;
+        self.search_time += other.search_time;
+        self.inference_time += other.inference_time;
+        self.scoring_time += other.scoring_time;
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} activated, {} generated, {} simplified, {} peak passive, {} scorer calls, \
+             {:.3}s searching ({:.3}s inference, {:.3}s scoring)",
+            self.activations,
+            self.generated,
+            self.simplifications,
+            self.peak_passive,
+            self.scorer_calls,
+            self.search_time.as_secs_f64(),
+            self.inference_time.as_secs_f64(),
+            self.scoring_time.as_secs_f64()
+        )
+    }
+}
+
+// The search statistics for every goal in a build.
+#[derive(Debug, Default)]
+pub struct SearchReport {
+    // The name of each goal that was searched, with its statistics, in the order they finished.
+    goals: Vec<(String, SearchStats)>,
+}
+
+impl SearchReport {
+    pub fn add(&mut self, goal_name: &str, stats: SearchStats) {
+        self.goals.push((goal_name.to_string(), stats));
+    }
+
+    pub fn len(&self) -> usize {
+        self.goals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.goals.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, SearchStats)> {
+        self.goals.iter()
+    }
+
+    // The statistics for all the goals together.
+    pub fn total(&self) -> SearchStats {
+        let mut total = SearchStats::default();
+        for (_, stats) in &self.goals {
+            total.add(stats);
+        }
+        total
+    }
+
+    // The goals that took the longest to search, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<&(String, SearchStats)> {
+        let mut goals: Vec<_> = self.goals.iter().collect();
+        goals.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.search_time));
+        goals.truncate(n);
+        goals
+    }
+}
+
+impl fmt::Display for SearchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} goals searched: {}", self.len(), self.total())?;
+        for (name, stats) in self.slowest(5) {
+            writeln!(f, "  {}: {}", name, stats)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_report() {
+        let mut report = SearchReport::default();
+        report.add(
+            "fast",
+            SearchStats {
+                activations: 3,
+                peak_passive: 10,
+                search_time: Duration::from_millis(5),
+                ..SearchStats::default()
+            },
+        );
+        report.add(
+            "slow",
+            SearchStats {
+                activations: 20,
+                peak_passive: 7,
+                search_time: Duration::from_millis(50),
+                ..SearchStats::default()
+            },
+        );
+        let total = report.total();
+        assert_eq!(total.activations, 23);
+        assert_eq!(total.peak_passive, 10);
+        assert_eq!(total.search_time, Duration::from_millis(55));
+        let slowest: Vec<_> = report.slowest(1).iter().map(|(name, _)| name).collect();
+        assert_eq!(slowest, vec!["slow"]);
+    }
+}
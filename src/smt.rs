@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::atom::Atom;
+use crate::clause::Clause;
+use crate::common::run_with_timeout;
+use crate::literal::Literal;
+use crate::term::Term;
+use crate::type_map::{TypeId, BOOL};
+
+// How to run an external SMT solver.
+// The solver reads SMT-LIB from stdin, so z3 needs "-in".
+#[derive(Clone, Debug)]
+pub struct SmtConfig {
+    pub command: String,
+    pub args: Vec<String>,
+
+    // The solver process is killed when it runs longer than this.
+    pub timeout: Duration,
+}
+
+impl Default for SmtConfig {
+    fn default() -> SmtConfig {
+        SmtConfig {
+            command: "z3".to_string(),
+            args: vec!["-in".to_string(), "-smt2".to_string()],
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SmtResult {
+    // The clauses are contradictory. These are the ids of the clauses the solver used.
+    Unsat(Vec<usize>),
+
+    Sat,
+
+    // The solver gave up, timed out, or couldn't be run. The string says why.
+    Unknown(String),
+}
+
+// A set of ground clauses, translated into SMT-LIB with uninterpreted functions.
+// Each atom applied to a particular number of arguments becomes its own function, so partial
+// applications aren't connected to full ones. That loses some information, but it never
+// makes a satisfiable problem unsatisfiable.
+#[derive(Default)]
+pub struct SmtProblem {
+    // The declaration for each function, keyed by its name.
+    functions: BTreeMap<String, String>,
+    sorts: BTreeMap<TypeId, String>,
+    assertions: Vec<String>,
+}
+
+impl SmtProblem {
+    pub fn new() -> SmtProblem {
+        SmtProblem::default()
+    }
+
+    // Adds a clause under the given id.
+    // Returns false if the clause has variables, because those can't be translated.
+    pub fn add_clause(&mut self, id: usize, clause: &Clause) -> bool {
+        if clause.has_any_variable() {
+            return false;
+        }
+        let literals: Vec<String> = clause
+            .literals
+            .iter()
+            .map(|literal| self.literal(literal))
+            .collect();
+        let body = match literals.len() {
+            0 => "false".to_string(),
+            1 => literals[0].clone(),
+            _ => format!("(or {})", literals.join(" ")),
+        };
+        self.assertions
+            .push(format!("(assert (! {} :named {}))", body, clause_name(id)));
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assertions.is_empty()
+    }
+
+    fn sort(&mut self, type_id: TypeId) -> String {
+        if type_id == BOOL {
+            return "Bool".to_string();
+        }
+        self.sorts
+            .entry(type_id)
+            .or_insert_with(|| format!("T{}", type_id))
+            .clone()
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        let left = self.term(&literal.left);
+        let answer = if literal.right.is_true() {
+            left
+        } else {
+            format!("(= {} {})", left, self.term(&literal.right))
+        };
+        if literal.positive {
+            answer
+        } else {
+            format!("(not {})", answer)
+        }
+    }
+
+    fn term(&mut self, term: &Term) -> String {
+        if term.head == Atom::True {
+            return "true".to_string();
+        }
+        let name = format!("{}_{}", term.head, term.args.len());
+        if !self.functions.contains_key(&name) {
+            let arg_sorts: Vec<String> = term
+                .args
+                .iter()
+                .map(|arg| self.sort(arg.term_type))
+                .collect();
+            let declaration = format!(
+                "(declare-fun {} ({}) {})",
+                name,
+                arg_sorts.join(" "),
+                self.sort(term.term_type)
+            );
+            self.functions.insert(name.clone(), declaration);
+        }
+        if term.args.is_empty() {
+            return name;
+        }
+        let args: Vec<String> = term.args.iter().map(|arg| self.term(arg)).collect();
+        format!("({} {})", name, args.join(" "))
+    }
+
+    pub fn to_smtlib(&self) -> String {
+        let mut answer = String::new();
+        answer.push_str("(set-option :produce-unsat-cores true)\n");
+        for sort in self.sorts.values() {
+            writeln!(answer, "(declare-sort {} 0)", sort).unwrap();
+        }
+        for declaration in self.functions.values() {
+            writeln!(answer, "{}", declaration).unwrap();
+        }
+        for assertion in &self.assertions {
+            writeln!(answer, "{}", assertion).unwrap();
+        }
+        answer.push_str("(check-sat)\n(get-unsat-core)\n");
+        answer
+    }
+
+    // Runs the solver on this problem.
+    pub fn solve(&self, config: &SmtConfig) -> SmtResult {
+        match run_with_timeout(
+            &config.command,
+            &config.args,
+            &self.to_smtlib(),
+            config.timeout,
+        ) {
+            Ok(output) => parse_output(&output),
+            Err(e) => SmtResult::Unknown(e),
+        }
+    }
+}
+
+fn clause_name(id: usize) -> String {
+    format!("clause{}", id)
+}
+
+// Parses the response to check-sat followed by get-unsat-core.
+fn parse_output(output: &str) -> SmtResult {
+    let mut lines = output.lines().map(|line| line.trim());
+    match lines.next() {
+        Some("unsat") => {}
+        Some("sat") => return SmtResult::Sat,
+        Some(line) => return SmtResult::Unknown(line.to_string()),
+        None => return SmtResult::Unknown("no output".to_string()),
+    }
+    let core: String = lines.collect::<Vec<_>>().join(" ");
+    let ids = core
+        .trim_matches(|c| c == '(' || c == ')' || char::is_whitespace(c))
+        .split_whitespace()
+        .filter_map(|name| name.strip_prefix("clause")?.parse().ok())
+        .collect();
+    SmtResult::Unsat(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smt_translation() {
+        let mut problem = SmtProblem::new();
+        assert!(problem.add_clause(0, &Clause::parse("c0(c1) = c2")));
+        assert!(problem.add_clause(3, &Clause::parse("c0(c1) != c2 or c3")));
+        assert!(!problem.add_clause(4, &Clause::parse("c0(x0) = c2")));
+        let text = problem.to_smtlib();
+        assert!(text.contains("(declare-fun c0_1 (T0) T0)"));
+        assert!(text.contains("(assert (! (= (c0_1 c1_0) c2_0) :named clause0))"));
+        assert!(text.contains(":named clause3))"));
+        assert!(!text.contains("clause4"));
+
+        assert_eq!(
+            parse_output("unsat\n(clause0 clause3)\n"),
+            SmtResult::Unsat(vec![0, 3])
+        );
+        assert_eq!(parse_output("sat\n(error \"no core\")\n"), SmtResult::Sat);
+    }
+}
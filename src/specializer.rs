@@ -1,9 +1,11 @@
 use crate::atom::{Atom, AtomId};
 use crate::term::Term;
 use crate::type_map::TypeId;
+use crate::unifier::split_application;
 
 // A Specializer finds substitutions that specialize, to turn a more general term
 // into a more specific one.
+#[derive(Clone)]
 pub struct Specializer {
     map: Vec<Option<Term>>,
 }
@@ -54,6 +56,18 @@ impl Specializer {
             return self.match_var(i, special);
         }
 
+        // Handle the case where an applied general variable matches part of a longer application
+        if let Atom::Variable(i) = general.head {
+            if let Some((prefix, suffix)) = split_application(general, special) {
+                return self.match_var(i, &prefix)
+                    && general
+                        .args
+                        .iter()
+                        .zip(suffix)
+                        .all(|(g, s)| self.match_terms(g, s));
+            }
+        }
+
         // These checks mean we won't catch higher-order functions whose head types don't match.
         if general.head_type != special.head_type {
             return false;
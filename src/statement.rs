@@ -2,7 +2,7 @@ use tower_lsp::lsp_types::Range;
 
 use crate::compilation::{Error, ErrorSource, Result};
 use crate::expression::{Declaration, Expression, Terminator};
-use crate::token::{Token, TokenIter, TokenType};
+use crate::token::{Token, TokenIter, TokenType, CUSTOM_PRECEDENCES};
 
 use std::fmt;
 
@@ -29,6 +29,19 @@ pub struct LetStatement {
     pub name_token: Token,
     pub type_expr: Expression,
     pub value: Expression,
+
+    // Whether the name deliberately shadows a name from outside the block, like "shadow let k ...".
+    pub shadow: bool,
+}
+
+// Destructuring let statements introduce one constant for each field of a structure.
+// For example:
+//   let (a, b) = p
+//   let Pair.new(a, b) = p
+pub struct DestructuringLetStatement {
+    // Either a parenthesized list of names, or the structure's constructor applied to them.
+    pub pattern: Expression,
+    pub value: Expression,
 }
 
 // Define statements introduce new named functions. For example:
@@ -40,14 +53,30 @@ pub struct DefineStatement {
     // For templated definitions
     pub type_params: Vec<Token>,
 
+    // Whether the type params were written in braces, like "define id{T}(x: T) -> T".
+    // Implicit type params must be inferable from the argument types.
+    pub implicit_type_params: bool,
+
     // A list of the named arg types, like "a: int" and "b: int".
     pub args: Vec<Declaration>,
 
     // The specified return type of the function, like "int"
     pub return_type: Expression,
 
+    // Whether the definition is opaque, written like "opaque define foo ...".
+    // The prover only knows the definition of an opaque function after "unfold foo".
+    pub opaque: bool,
+
+    // An optional measure that decreases with each recursive call, written like
+    // "decreasing size(a)" after the return type.
+    pub measure: Option<Expression>,
+
     // The body of the function, like "a + a + b"
     pub return_value: Expression,
+
+    // Any attributes, like "@algebra".
+    // Definitions can only have tags, not attributes that change how the prover works.
+    pub attributes: Vec<Attribute>,
 }
 
 // There are two keywords for theorems.
@@ -58,9 +87,24 @@ pub struct DefineStatement {
 // axiomatic would be "true", the name is "foo", the args are p, q, and the claim is "p -> (q -> p)".
 pub struct TheoremStatement {
     pub axiomatic: bool,
+
+    // Whether this is a lemma, a named theorem that is local to a proof block.
+    pub lemma: bool,
+
+    // Any attributes, like "@simp".
+    pub attributes: Vec<Attribute>,
+
     pub name: Option<String>,
     pub type_params: Vec<Token>,
+
+    // Whether the type params were written in braces, like for a define statement.
+    pub implicit_type_params: bool,
+
     pub args: Vec<Declaration>,
+
+    // A label for the premise of the claim, like "h" in "h: a = b implies c".
+    pub premise_label: Option<Token>,
+
     pub claim: Expression,
     pub claim_right_brace: Token,
     pub body: Option<Body>,
@@ -69,7 +113,9 @@ pub struct TheoremStatement {
 // Prop statements are a boolean expression.
 // We're implicitly asserting that it is true and provable.
 // It's like an anonymous theorem.
+// It can have a label, like "h1: a = b", so that later statements can cite it as "h1".
 pub struct PropStatement {
+    pub label: Option<Token>,
     pub claim: Expression,
 }
 
@@ -82,7 +128,16 @@ pub struct TypeStatement {
 // ForAll statements create a new block in which new variables are introduced.
 pub struct ForAllStatement {
     pub quantifiers: Vec<Declaration>,
+
+    // An optional constraint on the quantified variables, like:
+    //   forall(x: Nat) where x != Nat.0 { ... }
+    pub constraint: Option<Expression>,
+
     pub body: Body,
+
+    // Whether the quantified names deliberately shadow names from outside the block,
+    // like "shadow forall(k: Nat) { ... }".
+    pub shadow: bool,
 }
 
 // If statements create a new block that introduces no variables but has an implicit condition.
@@ -169,6 +224,28 @@ pub struct ImportStatement {
     // What names to import from the module.
     // If this is empty, we just import the module itself.
     pub names: Vec<Token>,
+
+    // Whether this is a wildcard import, like "from foo import *".
+    pub wildcard: bool,
+}
+
+// An export statement lets other modules import a name from this one, when the name
+// was imported here from somewhere else.
+// "export Nat" exports a name that's already imported, and "export nat.Nat" imports the
+// name from a module and exports it in one step.
+pub struct ExportStatement {
+    // The local name of the module to import from, if there is one.
+    pub module: Option<Token>,
+
+    pub name: Token,
+}
+
+// A module statement declares a child module inside the current file.
+// Its statements are in their own namespace, and the parent refers to it like an imported module.
+pub struct ModuleStatement {
+    pub name: String,
+    pub name_token: Token,
+    pub body: Body,
 }
 
 // A class statement defines some class variables and instance methods that are scoped to the class.
@@ -185,6 +262,72 @@ pub struct NumeralsStatement {
     pub type_expr: Expression,
 }
 
+// A strings statement determines what class is used for string literals.
+pub struct StringsStatement {
+    pub type_expr: Expression,
+}
+
+// A lists statement determines what inductive type is used for list literals.
+pub struct ListsStatement {
+    pub type_expr: Expression,
+}
+
+// A decimals statement determines what class is used for numeric literals with a
+// decimal point, like 3.14.
+pub struct DecimalsStatement {
+    pub type_expr: Expression,
+}
+
+// An assume statement takes the premise of the goal as a hypothesis, like:
+//   assume p
+// The goal must be "p implies q", and for the rest of the block the goal is just q.
+pub struct AssumeStatement {
+    pub claim: Expression,
+}
+
+// A suffices statement replaces the goal with something that implies it, like:
+//   suffices q by { ... }
+// The optional block proves that q implies the goal, and for the rest of the block the
+// goal is just q.
+pub struct SufficesStatement {
+    pub claim: Expression,
+    pub body: Option<Body>,
+}
+
+// A rewrite statement rewrites with a cited equation, left to right, like:
+//   rewrite add_comm(a, b)
+// Without a target, it rewrites the goal. With a target, like:
+//   rewrite add_comm(a, b) in h
+// it rewrites the target claim.
+pub struct RewriteStatement {
+    pub equation: Expression,
+    pub target: Option<Expression>,
+}
+
+// A have statement proves a claim and names it, like:
+//   have h: p by { ... }
+// The name can be cited for the rest of the block.
+pub struct HaveStatement {
+    pub label: Token,
+    pub claim: Expression,
+    pub body: Option<Body>,
+}
+
+// An unfold statement reveals the definition of an opaque function, like:
+//   unfold foo
+// The definition is only available to the prover for the rest of the current block.
+pub struct UnfoldStatement {
+    pub target: Expression,
+}
+
+// A coercion statement registers a function that converts one type to another, like:
+//   coercion Int.from_nat
+// The function is inserted automatically when a value of one type is used where the
+// other type is expected.
+pub struct CoercionStatement {
+    pub function: Expression,
+}
+
 pub struct SolveStatement {
     // The expression we are trying to find equalities for.
     pub target: Expression,
@@ -197,6 +340,20 @@ pub struct MatchStatement {
     // The thing we are matching patterns against.
     pub scrutinee: Expression,
 
+    // (pattern, guard, body) triples.
+    // The guard is an optional condition, written as "if <condition>" after the pattern.
+    pub cases: Vec<(Expression, Option<Expression>, Body)>,
+}
+
+// An induction statement proves the goal by structural induction on a variable, like:
+//   induction n {
+//       Nat.zero { ... }
+//       Nat.suc(k) { ... }
+//   }
+// There is one case for each constructor, with no guards.
+pub struct InductionStatement {
+    pub variable: Expression,
+
     // (pattern, body) pairs.
     pub cases: Vec<(Expression, Body)>,
 }
@@ -232,6 +389,9 @@ pub struct TypeclassStatement {
 // Acorn is a statement-based language. There are several types.
 // Each type has its own struct.
 pub struct Statement {
+    // The "///" comments directly preceding the statement, without the slashes.
+    pub doc_comments: Vec<String>,
+
     pub first_token: Token,
     pub last_token: Token,
     pub statement: StatementInfo,
@@ -246,7 +406,12 @@ impl ErrorSource for Statement {
 // Information about a statement that is specific to the type of statement it is
 pub enum StatementInfo {
     Let(LetStatement),
+    DestructuringLet(DestructuringLetStatement),
     Define(DefineStatement),
+
+    // Several definitions that can call each other, joined with "and".
+    DefineGroup(Vec<DefineStatement>),
+
     Theorem(TheoremStatement),
     Prop(PropStatement),
     Type(TypeStatement),
@@ -257,11 +422,26 @@ pub enum StatementInfo {
     Structure(StructureStatement),
     Inductive(InductiveStatement),
     Import(ImportStatement),
+    Export(ExportStatement),
+    Module(ModuleStatement),
     Class(ClassStatement),
     Numerals(NumeralsStatement),
+    Strings(StringsStatement),
+    Lists(ListsStatement),
+    Decimals(DecimalsStatement),
+    Unfold(UnfoldStatement),
+    Rewrite(RewriteStatement),
+    Have(HaveStatement),
+    Assume(AssumeStatement),
+    Suffices(SufficesStatement),
+    Coercion(CoercionStatement),
     Solve(SolveStatement),
     Problem(Body),
+
+    // A block that proves the goal by assuming its negation and deriving false.
+    ByContradiction(Body),
     Match(MatchStatement),
+    Induction(InductionStatement),
     Typeclass(TypeclassStatement),
 }
 
@@ -312,6 +492,74 @@ fn parse_block(tokens: &mut TokenIter) -> Result<(Vec<Statement>, Token)> {
     }
 }
 
+// Skips past newlines, collecting the doc comments that directly precede the next statement.
+// A blank line or a regular comment separates doc comments from the statement.
+fn parse_doc_comments(tokens: &mut TokenIter) -> Vec<String> {
+    let mut doc_comments = vec![];
+    let mut last_doc_line = None;
+    while let Some(token) = tokens.peek() {
+        if token.token_type != TokenType::NewLine {
+            break;
+        }
+        if let Some(text) = token.doc_comment() {
+            doc_comments.push(text.to_string());
+            last_doc_line = Some(token.line_number);
+        } else if token.len > 0 || last_doc_line != Some(token.line_number) {
+            doc_comments.clear();
+            last_doc_line = None;
+        }
+        tokens.next();
+    }
+    doc_comments
+}
+
+// An attribute that precedes a statement, like "@simp".
+// Some attributes take a numeric argument, like "@max_active(5000)".
+// Tags take the name of the tag, like "@tag(algebra)".
+#[derive(Clone)]
+pub struct Attribute {
+    pub name: Token,
+    pub arg: Option<Token>,
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if let Some(arg) = &self.arg {
+            write!(f, "({})", arg)?;
+        }
+        Ok(())
+    }
+}
+
+// Parses a sequence of attributes, like "@simp", that precede a statement.
+// Each attribute can be on the same line as the statement, or on a line of its own.
+fn parse_attributes(tokens: &mut TokenIter) -> Result<Vec<Attribute>> {
+    let mut attributes = vec![];
+    while tokens.peek_type() == Some(TokenType::At) {
+        tokens.next();
+        let name = tokens.expect_type(TokenType::Identifier)?;
+        let arg = if tokens.peek_type() == Some(TokenType::LeftParen) {
+            tokens.next();
+            let arg_type = if name.text() == "tag" {
+                TokenType::Identifier
+            } else {
+                TokenType::Numeral
+            };
+            let arg = tokens.expect_type(arg_type)?;
+            tokens.expect_type(TokenType::RightParen)?;
+            Some(arg)
+        } else {
+            None
+        };
+        attributes.push(Attribute { name, arg });
+        while tokens.peek_type() == Some(TokenType::NewLine) {
+            tokens.next();
+        }
+    }
+    Ok(attributes)
+}
+
 // Parse a list of type parameters. For example:
 // <T, U>
 // If there are no type parameters, returns an empty list.
@@ -320,21 +568,51 @@ fn parse_params(tokens: &mut TokenIter) -> Result<Vec<Token>> {
         return Ok(vec![]);
     }
     tokens.next();
+    parse_param_list(tokens, TokenType::GreaterThan)
+}
+
+// Parses type params that may be implicit, like "{T, U}", or explicit, like "<T, U>".
+// Returns the params, and whether they are implicit.
+fn parse_define_params(tokens: &mut TokenIter) -> Result<(Vec<Token>, bool)> {
+    if tokens.peek_type() != Some(TokenType::LeftBrace) {
+        return Ok((parse_params(tokens)?, false));
+    }
+    tokens.next();
+    Ok((parse_param_list(tokens, TokenType::RightBrace)?, true))
+}
+
+// Parses a list of type params, after the opening token has been consumed.
+// Consumes the closing token.
+fn parse_param_list(tokens: &mut TokenIter, closing: TokenType) -> Result<Vec<Token>> {
     let mut params = vec![];
     loop {
         let token = tokens.expect_type(TokenType::Identifier)?;
         params.push(token);
         let token = tokens.expect_token()?;
-        match token.token_type {
-            TokenType::GreaterThan => {
-                break;
-            }
-            TokenType::Comma => {
-                continue;
-            }
-            _ => {
-                return Err(token.error("expected '>' or ',' in type params"));
+        if token.token_type == closing {
+            break;
+        }
+        if token.token_type != TokenType::Comma {
+            return Err(token.error(&format!(
+                "expected '{}' or ',' in type params",
+                closing.to_str()
+            )));
+        }
+    }
+    Ok(params)
+}
+
+// Converts an expression like "T, U" into a list of type params.
+// This is used when we can't tell whether braces hold type params until after parsing them.
+fn expression_to_params(expression: &Expression) -> Result<Vec<Token>> {
+    let mut params = vec![];
+    for part in expression.flatten_comma_separated_list() {
+        match part {
+            Expression::Singleton(token) if token.token_type == TokenType::Identifier => {
+                token.expect_type_name()?;
+                params.push(token.clone());
             }
+            _ => return Err(part.error("expected a type param")),
         }
     }
     Ok(params)
@@ -395,35 +673,152 @@ fn parse_by_block(right_brace: Token, tokens: &mut TokenIter) -> Result<(Option<
 
 // Parses a theorem where the keyword identifier (axiom or theorem) has already been found.
 // "axiomatic" is whether this is an axiom.
+// Parses a rewrite statement where the "rewrite" keyword has already been found.
+fn parse_rewrite_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let (equation, terminator) =
+        Expression::parse_value(tokens, Terminator::Or(TokenType::NewLine, TokenType::In))?;
+    let (target, last_token) = if terminator.token_type == TokenType::In {
+        let (target, _) = Expression::parse_value(tokens, Terminator::Is(TokenType::NewLine))?;
+        let last_token = target.last_token().clone();
+        (Some(target), last_token)
+    } else {
+        (None, equation.last_token().clone())
+    };
+    let rs = RewriteStatement { equation, target };
+    Ok(Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token,
+        statement: StatementInfo::Rewrite(rs),
+    })
+}
+
+// Parses a claim that can optionally be followed by "by { ... }".
+// Returns the claim, the body if there is one, and the last token of the whole thing.
+fn parse_claim_with_by(tokens: &mut TokenIter) -> Result<(Expression, Option<Body>, Token)> {
+    let (claim, terminator) =
+        Expression::parse_value(tokens, Terminator::Or(TokenType::NewLine, TokenType::By))?;
+    if terminator.token_type != TokenType::By {
+        let last_token = claim.last_token().clone();
+        return Ok((claim, None, last_token));
+    }
+    let left_brace = tokens.expect_type(TokenType::LeftBrace)?;
+    let (statements, right_brace) = parse_block(tokens)?;
+    let body = Body {
+        left_brace,
+        statements,
+        right_brace: right_brace.clone(),
+    };
+    Ok((claim, Some(body), right_brace))
+}
+
+// Parses a suffices statement where the "suffices" keyword has already been found.
+fn parse_suffices_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let (claim, body, last_token) = parse_claim_with_by(tokens)?;
+    let ss = SufficesStatement { claim, body };
+    let statement = Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token,
+        statement: StatementInfo::Suffices(ss),
+    };
+    Ok(statement)
+}
+
+// Parses a label like "h1:" if there is one, consuming the colon.
+// Skips any newlines before it.
+fn parse_label(tokens: &mut TokenIter) -> Result<Option<Token>> {
+    tokens.skip_newlines();
+    if tokens.peek_type() != Some(TokenType::Identifier)
+        || tokens.peek_type_at(1) != Some(TokenType::Colon)
+    {
+        return Ok(None);
+    }
+    let label = tokens.expect_variable_name(false)?;
+    tokens.expect_type(TokenType::Colon)?;
+    Ok(Some(label))
+}
+
 fn parse_theorem_statement(
     keyword: Token,
     tokens: &mut TokenIter,
     axiomatic: bool,
+    attributes: Vec<Attribute>,
 ) -> Result<Statement> {
     let name = match tokens.peek_type() {
         Some(TokenType::LeftParen) | Some(TokenType::LeftBrace) => None,
         _ => Some(tokens.expect_variable_name(false)?.text().to_string()),
     };
-    let type_params = parse_params(tokens)?;
-    let (args, _) = parse_args(tokens, TokenType::LeftBrace)?;
+    let (type_params, implicit_type_params, args, premise_label, claim, claim_right_brace) =
+        if name.is_some() && tokens.peek_type() == Some(TokenType::LeftBrace) {
+            // This is either implicit type params, like "theorem foo{T}(x: T) { ... }",
+            // or the claim of a theorem with no arguments, like "theorem foo { ... }".
+            // We can only tell which after the closing brace.
+            tokens.next();
+            let premise_label = parse_label(tokens)?;
+            let (braced, right_brace) =
+                Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
+            match tokens.peek_type() {
+                _ if premise_label.is_some() => {
+                    (vec![], false, vec![], premise_label, braced, right_brace)
+                }
+                Some(TokenType::LeftParen) | Some(TokenType::LeftBrace) => {
+                    let type_params = expression_to_params(&braced)?;
+                    let (args, _) = parse_args(tokens, TokenType::LeftBrace)?;
+                    let premise_label = parse_label(tokens)?;
+                    let (claim, claim_right_brace) =
+                        Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
+                    (
+                        type_params,
+                        true,
+                        args,
+                        premise_label,
+                        claim,
+                        claim_right_brace,
+                    )
+                }
+                _ => (vec![], false, vec![], None, braced, right_brace),
+            }
+        } else {
+            let type_params = parse_params(tokens)?;
+            let (args, _) = parse_args(tokens, TokenType::LeftBrace)?;
+            let premise_label = parse_label(tokens)?;
+            let (claim, claim_right_brace) =
+                Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
+            (
+                type_params,
+                false,
+                args,
+                premise_label,
+                claim,
+                claim_right_brace,
+            )
+        };
     if type_params.len() > 1 {
         return Err(type_params[1].error("only one type parameter is supported"));
     }
-    let (claim, claim_right_brace) =
-        Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
+    let lemma = keyword.token_type == TokenType::Lemma;
+    if lemma && name.is_none() {
+        return Err(keyword.error("lemmas must have a name"));
+    }
 
     let (body, last_token) = parse_by_block(claim_right_brace.clone(), tokens)?;
 
     let ts = TheoremStatement {
         axiomatic,
+        lemma,
+        attributes,
         name,
         type_params,
+        implicit_type_params,
         args,
+        premise_label,
         claim,
         claim_right_brace,
         body,
     };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::Theorem(ts),
@@ -445,6 +840,7 @@ fn complete_variable_satisfy(
         condition,
     };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::VariableSatisfy(es),
@@ -457,6 +853,17 @@ fn complete_variable_satisfy(
 fn parse_let_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
     match tokens.peek() {
         Some(token) => {
+            // A list of names without types, or a constructor, means this is destructuring.
+            let destructuring = token.is_type_name()
+                || (token.token_type == TokenType::LeftParen
+                    && matches!(
+                        tokens.peek_type_at(2),
+                        Some(TokenType::Comma) | Some(TokenType::RightParen)
+                    ));
+            if destructuring {
+                return parse_destructuring_let(keyword, tokens);
+            }
+            let token = tokens.peek().unwrap();
             if token.token_type == TokenType::LeftParen {
                 // This is a parenthesized let..satisfy.
                 let (declarations, _) = parse_args(tokens, TokenType::Satisfy)?;
@@ -489,6 +896,7 @@ fn parse_let_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Stateme
                 body,
             };
             return Ok(Statement {
+                doc_comments: vec![],
                 first_token: keyword,
                 last_token,
                 statement: StatementInfo::FunctionSatisfy(fss),
@@ -514,36 +922,102 @@ fn parse_let_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Stateme
         name_token,
         type_expr,
         value,
+        shadow: false,
     };
     Ok(Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::Let(ls),
     })
 }
 
+// Parses a destructuring let statement, after the "let" keyword.
+fn parse_destructuring_let(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let (pattern, _) = Expression::parse_value(tokens, Terminator::Is(TokenType::Equals))?;
+    let (value, last_token) = Expression::parse_value(tokens, Terminator::Is(TokenType::NewLine))?;
+    let dls = DestructuringLetStatement { pattern, value };
+    Ok(Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token,
+        statement: StatementInfo::DestructuringLet(dls),
+    })
+}
+
 // Parses a define statement where the "define" keyword has already been found.
-fn parse_define_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+// Definitions that call each other are joined into a group with "and".
+// An "opaque" modifier applies to every definition in the group.
+fn parse_define_statement(
+    keyword: Token,
+    tokens: &mut TokenIter,
+    opaque: bool,
+    attributes: Vec<Attribute>,
+) -> Result<Statement> {
+    let (mut ds, mut last_token) = parse_define(tokens, opaque)?;
+    ds.attributes = attributes;
+
+    // A declared precedence applies to the code after it, so the parser has to know about it.
+    if let Some((token_type, precedence)) = ds.declared_precedence()? {
+        tokens.declare_precedence(token_type, precedence);
+    }
+    if tokens.peek_type() != Some(TokenType::And) {
+        let statement = Statement {
+            doc_comments: vec![],
+            first_token: keyword,
+            last_token,
+            statement: StatementInfo::Define(ds),
+        };
+        return Ok(statement);
+    }
+    let mut dss = vec![ds];
+    while tokens.peek_type() == Some(TokenType::And) {
+        tokens.next();
+        let (mut ds, token) = parse_define(tokens, opaque)?;
+        ds.attributes = dss[0].attributes.clone();
+        dss.push(ds);
+        last_token = token;
+    }
+    let statement = Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token,
+        statement: StatementInfo::DefineGroup(dss),
+    };
+    Ok(statement)
+}
+
+// Parses a single definition, starting with the name of the function.
+// Returns the definition along with its closing brace.
+fn parse_define(tokens: &mut TokenIter, opaque: bool) -> Result<(DefineStatement, Token)> {
     let name_token = tokens.expect_variable_name(false)?;
-    let type_params = parse_params(tokens)?;
+    let (type_params, implicit_type_params) = parse_define_params(tokens)?;
     let (args, _) = parse_args(tokens, TokenType::RightArrow)?;
-    let (return_type, _) = Expression::parse_type(tokens, Terminator::Is(TokenType::LeftBrace))?;
+    let (return_type, terminator) = Expression::parse_type(
+        tokens,
+        Terminator::Or(TokenType::LeftBrace, TokenType::Decreasing),
+    )?;
+    let measure = if terminator.token_type == TokenType::Decreasing {
+        let (measure, _) = Expression::parse_value(tokens, Terminator::Is(TokenType::LeftBrace))?;
+        Some(measure)
+    } else {
+        None
+    };
     let (return_value, last_token) =
         Expression::parse_value(tokens, Terminator::Is(TokenType::RightBrace))?;
     let ds = DefineStatement {
         name: name_token.text().to_string(),
         name_token,
         type_params,
+        implicit_type_params,
         args,
         return_type,
+        opaque,
+        measure,
         return_value,
+        attributes: vec![],
     };
-    let statement = Statement {
-        first_token: keyword,
-        last_token,
-        statement: StatementInfo::Define(ds),
-    };
-    Ok(statement)
+    Ok((ds, last_token))
 }
 
 // Parses a type statement where the "type" keyword has already been found.
@@ -558,6 +1032,7 @@ fn parse_type_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statem
         type_expr,
     };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::Type(ts),
@@ -567,15 +1042,37 @@ fn parse_type_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statem
 
 // Parses a forall statement where the "forall" keyword has already been found.
 fn parse_forall_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
-    let (quantifiers, left_brace) = parse_args(tokens, TokenType::LeftBrace)?;
+    let quantifiers = if tokens.peek_type() == Some(TokenType::LeftParen) {
+        tokens.next();
+        Declaration::parse_list(tokens)?
+    } else {
+        vec![]
+    };
+    let token = tokens.expect_token()?;
+    let (constraint, left_brace) = match token.token_type {
+        TokenType::LeftBrace => (None, token),
+        TokenType::Where if !quantifiers.is_empty() => {
+            let (constraint, left_brace) =
+                Expression::parse_value(tokens, Terminator::Is(TokenType::LeftBrace))?;
+            (Some(constraint), left_brace)
+        }
+        _ if quantifiers.is_empty() => return Err(token.error("expected an argument list")),
+        _ => return Err(token.error("expected a block or a 'where' constraint")),
+    };
     let (statements, right_brace) = parse_block(tokens)?;
     let body = Body {
         left_brace,
         statements,
         right_brace: right_brace.clone(),
     };
-    let fas = ForAllStatement { quantifiers, body };
+    let fas = ForAllStatement {
+        quantifiers,
+        constraint,
+        body,
+        shadow: false,
+    };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token: right_brace,
         statement: StatementInfo::ForAll(fas),
@@ -583,6 +1080,26 @@ fn parse_forall_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Stat
     Ok(statement)
 }
 
+// Parses a statement where the "shadow" keyword has already been found.
+// The modifier applies to a following let or forall statement.
+fn parse_shadow_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let token = tokens.expect_token()?;
+    let mut statement = match token.token_type {
+        TokenType::Let => parse_let_statement(token, tokens)?,
+        TokenType::ForAll => parse_forall_statement(token, tokens)?,
+        _ => return Err(token.error("expected 'let' or 'forall' after 'shadow'")),
+    };
+    match &mut statement.statement {
+        StatementInfo::Let(ls) => ls.shadow = true,
+        StatementInfo::ForAll(fas) => fas.shadow = true,
+        _ => {
+            return Err(keyword.error("only plain let statements can use 'shadow'"));
+        }
+    }
+    statement.first_token = keyword;
+    Ok(statement)
+}
+
 // If there is an "else { ...statements }" body, parse and consume it.
 // Returns None and consumes nothing if there is not an "else" body here.
 fn parse_else_body(tokens: &mut TokenIter) -> Result<Option<Body>> {
@@ -630,6 +1147,7 @@ fn parse_if_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statemen
         token,
     };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token: right_brace,
         statement: StatementInfo::If(is),
@@ -673,6 +1191,7 @@ fn parse_structure_statement(keyword: Token, tokens: &mut TokenIter) -> Result<S
                 };
 
                 return Ok(Statement {
+                    doc_comments: vec![],
                     first_token: keyword,
                     last_token,
                     statement: StatementInfo::Structure(StructureStatement {
@@ -722,6 +1241,7 @@ fn parse_inductive_statement(keyword: Token, tokens: &mut TokenIter) -> Result<S
                     return Err(type_token.error("inductive types must have a constructor"));
                 }
                 return Ok(Statement {
+                    doc_comments: vec![],
                     first_token: keyword,
                     last_token: tokens.next().unwrap(),
                     statement: StatementInfo::Inductive(InductiveStatement {
@@ -782,8 +1302,10 @@ fn parse_import_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Stat
     let is = ImportStatement {
         components,
         names: vec![],
+        wildcard: false,
     };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::Import(is),
@@ -794,6 +1316,21 @@ fn parse_import_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Stat
 // Parses a "from" statement where the "from" keyword has already been found.
 fn parse_from_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
     let (components, _) = parse_module_components(tokens, TokenType::Import)?;
+    if tokens.peek_type() == Some(TokenType::Asterisk) {
+        let last_token = tokens.next().unwrap();
+        tokens.expect_type(TokenType::NewLine)?;
+        let is = ImportStatement {
+            components,
+            names: vec![],
+            wildcard: true,
+        };
+        return Ok(Statement {
+            doc_comments: vec![],
+            first_token: keyword,
+            last_token,
+            statement: StatementInfo::Import(is),
+        });
+    }
     let mut names = vec![];
     let last_token = loop {
         let token = tokens.expect_type(TokenType::Identifier)?;
@@ -812,8 +1349,13 @@ fn parse_from_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statem
             }
         }
     };
-    let is = ImportStatement { components, names };
+    let is = ImportStatement {
+        components,
+        names,
+        wildcard: false,
+    };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::Import(is),
@@ -821,6 +1363,53 @@ fn parse_from_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statem
     Ok(statement)
 }
 
+// Parses an export statement where the "export" keyword has already been found.
+fn parse_export_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let first = tokens.expect_type(TokenType::Identifier)?;
+    let separator = tokens.expect_token()?;
+    let (module, name, last_token) = match separator.token_type {
+        TokenType::NewLine => (None, first.clone(), first),
+        TokenType::Dot => {
+            let name = tokens.expect_type(TokenType::Identifier)?;
+            tokens.expect_type(TokenType::NewLine)?;
+            (Some(first), name.clone(), name)
+        }
+        _ => return Err(separator.error("expected a dot or newline")),
+    };
+    let es = ExportStatement { module, name };
+    let statement = Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token,
+        statement: StatementInfo::Export(es),
+    };
+    Ok(statement)
+}
+
+// Parses a module statement where the "module" keyword has already been found.
+fn parse_module_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let name_token = tokens.expect_type(TokenType::Identifier)?;
+    let left_brace = tokens.expect_type(TokenType::LeftBrace)?;
+    let (statements, right_brace) = parse_block(tokens)?;
+    let body = Body {
+        left_brace,
+        statements,
+        right_brace: right_brace.clone(),
+    };
+    let ms = ModuleStatement {
+        name: name_token.to_string(),
+        name_token,
+        body,
+    };
+    let statement = Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token: right_brace,
+        statement: StatementInfo::Module(ms),
+    };
+    Ok(statement)
+}
+
 // Parses a class statement where the "class" keyword has already been found.
 fn parse_class_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
     let name_token = tokens.expect_type_name()?;
@@ -837,6 +1426,7 @@ fn parse_class_statement(keyword: Token, tokens: &mut TokenIter) -> Result<State
         body,
     };
     let statement = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token: right_brace,
         statement: StatementInfo::Class(cs),
@@ -856,6 +1446,7 @@ fn parse_solve_statement(keyword: Token, tokens: &mut TokenIter) -> Result<State
     };
     let ss = SolveStatement { target, body };
     let s = Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token: right_brace,
         statement: StatementInfo::Solve(ss),
@@ -878,29 +1469,71 @@ fn parse_match_statement(keyword: Token, tokens: &mut TokenIter) -> Result<State
         if next_type == TokenType::RightBrace {
             break;
         }
-        let (pattern, left_brace) =
-            Expression::parse_value(tokens, Terminator::Is(TokenType::LeftBrace))?;
+        let (pattern, guard, left_brace) = Expression::parse_match_arm_head(tokens)?;
         let (statements, right_brace) = parse_block(tokens)?;
         let body = Body {
             left_brace,
             statements,
             right_brace,
         };
-        cases.push((pattern, body));
+        cases.push((pattern, guard, body));
     }
     tokens.expect_type(TokenType::RightBrace)?;
     let last_token = match cases.last() {
-        Some((_, body)) => body.right_brace.clone(),
+        Some((_, _, body)) => body.right_brace.clone(),
         None => return Err(keyword.error("match must have cases")),
     };
     let ms = MatchStatement { scrutinee, cases };
     Ok(Statement {
+        doc_comments: vec![],
         first_token: keyword,
         last_token,
         statement: StatementInfo::Match(ms),
     })
 }
 
+// Parses an induction statement where the "induction" identifier has already been found.
+fn parse_induction_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
+    let (variable, _) = Expression::parse_value(tokens, Terminator::Is(TokenType::LeftBrace))?;
+    let mut cases = vec![];
+    loop {
+        let next_type = match tokens.peek() {
+            Some(token) => token.token_type,
+            None => return Err(keyword.error("unterminated induction statement")),
+        };
+        if next_type == TokenType::NewLine {
+            tokens.next();
+            continue;
+        }
+        if next_type == TokenType::RightBrace {
+            break;
+        }
+        let (pattern, guard, left_brace) = Expression::parse_match_arm_head(tokens)?;
+        if let Some(guard) = guard {
+            return Err(guard.error("induction cases cannot have guards"));
+        }
+        let (statements, right_brace) = parse_block(tokens)?;
+        let body = Body {
+            left_brace,
+            statements,
+            right_brace,
+        };
+        cases.push((pattern, body));
+    }
+    tokens.expect_type(TokenType::RightBrace)?;
+    let last_token = match cases.last() {
+        Some((_, body)) => body.right_brace.clone(),
+        None => return Err(keyword.error("induction must have cases")),
+    };
+    let is = InductionStatement { variable, cases };
+    Ok(Statement {
+        doc_comments: vec![],
+        first_token: keyword,
+        last_token,
+        statement: StatementInfo::Induction(is),
+    })
+}
+
 fn parse_typeclass_statement(keyword: Token, tokens: &mut TokenIter) -> Result<Statement> {
     let instance_type = tokens.expect_type_name()?;
     tokens.expect_type(TokenType::Colon)?;
@@ -919,6 +1552,7 @@ fn parse_typeclass_statement(keyword: Token, tokens: &mut TokenIter) -> Result<S
                 }
 
                 return Ok(Statement {
+                    doc_comments: vec![],
                     first_token: keyword,
                     last_token: token,
                     statement: StatementInfo::Typeclass(TypeclassStatement {
@@ -969,18 +1603,19 @@ fn parse_typeclass_statement(keyword: Token, tokens: &mut TokenIter) -> Result<S
     Err(keyword.error("unterminated typeclass statement"))
 }
 
-fn write_type_params(f: &mut fmt::Formatter, type_params: &[Token]) -> fmt::Result {
+fn write_type_params(f: &mut fmt::Formatter, type_params: &[Token], implicit: bool) -> fmt::Result {
     if type_params.len() == 0 {
         return Ok(());
     }
-    write!(f, "<")?;
+    let (open, close) = if implicit { ("{", "}") } else { ("<", ">") };
+    write!(f, "{}", open)?;
     for (i, param) in type_params.iter().enumerate() {
         if i > 0 {
             write!(f, ", ")?;
         }
         write!(f, "{}", param)?;
     }
-    write!(f, ">")?;
+    write!(f, "{}", close)?;
     Ok(())
 }
 
@@ -999,51 +1634,117 @@ fn write_args(f: &mut fmt::Formatter, args: &[Declaration]) -> fmt::Result {
     Ok(())
 }
 
-// Writes everything after the name of the theorem.
+// Writes everything after the "define" keyword.
+fn write_define(f: &mut fmt::Formatter, indentation: &str, ds: &DefineStatement) -> fmt::Result {
+    let new_indentation = add_indent(indentation);
+    write!(f, "{}", ds.name)?;
+    write_type_params(f, &ds.type_params, ds.implicit_type_params)?;
+    write_args(f, &ds.args)?;
+    write!(f, " -> {}", ds.return_type)?;
+    if let Some(measure) = &ds.measure {
+        write!(f, " decreasing {}", measure)?;
+    }
+    write!(
+        f,
+        " {{\n{}{}\n{}}}",
+        new_indentation, ds.return_value, indentation
+    )
+}
+
+// Writes everything after the name of the theorem.
 fn write_theorem(
     f: &mut fmt::Formatter,
     indentation: &str,
     type_params: &[Token],
+    implicit_type_params: bool,
     args: &[Declaration],
+    premise_label: Option<&Token>,
     claim: &Expression,
 ) -> fmt::Result {
     let new_indentation = add_indent(indentation);
-    write_type_params(f, type_params)?;
+    write_type_params(f, type_params, implicit_type_params)?;
     write_args(f, args)?;
-    write!(f, " {{\n{}{}\n{}}}", new_indentation, claim, indentation)?;
+    write!(f, " {{\n{}", new_indentation)?;
+    if let Some(label) = premise_label {
+        write!(f, "{}: ", label)?;
+    }
+    write!(f, "{}\n{}}}", claim, indentation)?;
     Ok(())
 }
 
 impl Statement {
     fn fmt_helper(&self, f: &mut fmt::Formatter, indentation: &str) -> fmt::Result {
+        for line in &self.doc_comments {
+            if line.is_empty() {
+                writeln!(f, "{}///", indentation)?;
+            } else {
+                writeln!(f, "{}/// {}", indentation, line)?;
+            }
+        }
         write!(f, "{}", indentation)?;
         match &self.statement {
             StatementInfo::Let(ls) => {
+                if ls.shadow {
+                    write!(f, "shadow ")?;
+                }
                 write!(f, "let {}: {} = {}", ls.name, ls.type_expr, ls.value)
             }
 
+            StatementInfo::DestructuringLet(dls) => {
+                write!(f, "let {} = {}", dls.pattern, dls.value)
+            }
+
             StatementInfo::Define(ds) => {
-                let new_indentation = add_indent(indentation);
-                write!(f, "define {}", ds.name)?;
-                write_type_params(f, &ds.type_params)?;
-                write_args(f, &ds.args)?;
-                write!(
-                    f,
-                    " -> {} {{\n{}{}\n{}}}",
-                    ds.return_type, new_indentation, ds.return_value, indentation
-                )
+                for attribute in &ds.attributes {
+                    write!(f, "{} ", attribute)?;
+                }
+                if ds.opaque {
+                    write!(f, "opaque ")?;
+                }
+                write!(f, "define ")?;
+                write_define(f, indentation, ds)
+            }
+
+            StatementInfo::DefineGroup(dss) => {
+                for attribute in &dss[0].attributes {
+                    write!(f, "{} ", attribute)?;
+                }
+                if dss[0].opaque {
+                    write!(f, "opaque ")?;
+                }
+                write!(f, "define ")?;
+                for (i, ds) in dss.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+                    write_define(f, indentation, ds)?;
+                }
+                Ok(())
             }
 
             StatementInfo::Theorem(ts) => {
+                for attribute in &ts.attributes {
+                    write!(f, "{} ", attribute)?;
+                }
                 if ts.axiomatic {
                     write!(f, "axiom")?;
+                } else if ts.lemma {
+                    write!(f, "lemma")?;
                 } else {
                     write!(f, "theorem")?;
                 }
                 if let Some(name) = &ts.name {
                     write!(f, " {}", &name)?;
                 }
-                write_theorem(f, indentation, &ts.type_params, &ts.args, &ts.claim)?;
+                write_theorem(
+                    f,
+                    indentation,
+                    &ts.type_params,
+                    ts.implicit_type_params,
+                    &ts.args,
+                    ts.premise_label.as_ref(),
+                    &ts.claim,
+                )?;
                 if let Some(body) = &ts.body {
                     write!(f, " by")?;
                     write_block(f, &body.statements, indentation)?;
@@ -1052,6 +1753,9 @@ impl Statement {
             }
 
             StatementInfo::Prop(ps) => {
+                if let Some(label) = &ps.label {
+                    write!(f, "{}: ", label)?;
+                }
                 write!(f, "{}", ps.claim)?;
                 Ok(())
             }
@@ -1061,8 +1765,14 @@ impl Statement {
             }
 
             StatementInfo::ForAll(fas) => {
+                if fas.shadow {
+                    write!(f, "shadow ")?;
+                }
                 write!(f, "forall")?;
                 write_args(f, &fas.quantifiers)?;
+                if let Some(constraint) = &fas.constraint {
+                    write!(f, " where {}", constraint)?;
+                }
                 write_block(f, &fas.body.statements, indentation)
             }
 
@@ -1140,7 +1850,9 @@ impl Statement {
             }
 
             StatementInfo::Import(is) => {
-                if is.names.is_empty() {
+                if is.wildcard {
+                    write!(f, "from {} import *", is.components.join("."))
+                } else if is.names.is_empty() {
                     write!(f, "import {}", is.components.join("."))
                 } else {
                     let names = is
@@ -1153,15 +1865,75 @@ impl Statement {
                 }
             }
 
+            StatementInfo::Export(es) => match &es.module {
+                Some(module) => write!(f, "export {}.{}", module, es.name),
+                None => write!(f, "export {}", es.name),
+            },
+
             StatementInfo::Class(cs) => {
                 write!(f, "class {}", cs.name)?;
                 write_block(f, &cs.body.statements, indentation)
             }
 
+            StatementInfo::Module(ms) => {
+                write!(f, "module {}", ms.name)?;
+                write_block(f, &ms.body.statements, indentation)
+            }
+
             StatementInfo::Numerals(ds) => {
                 write!(f, "default {}", ds.type_expr)
             }
 
+            StatementInfo::Strings(ss) => {
+                write!(f, "strings {}", ss.type_expr)
+            }
+
+            StatementInfo::Lists(ls) => {
+                write!(f, "lists {}", ls.type_expr)
+            }
+
+            StatementInfo::Decimals(ds) => {
+                write!(f, "decimals {}", ds.type_expr)
+            }
+
+            StatementInfo::Unfold(us) => {
+                write!(f, "unfold {}", us.target)
+            }
+
+            StatementInfo::Rewrite(rs) => {
+                write!(f, "rewrite {}", rs.equation)?;
+                if let Some(target) = &rs.target {
+                    write!(f, " in {}", target)?;
+                }
+                Ok(())
+            }
+
+            StatementInfo::Have(hs) => {
+                write!(f, "have {}: {}", hs.label, hs.claim)?;
+                if let Some(body) = &hs.body {
+                    write!(f, " by")?;
+                    write_block(f, &body.statements, indentation)?;
+                }
+                Ok(())
+            }
+
+            StatementInfo::Assume(a) => {
+                write!(f, "assume {}", a.claim)
+            }
+
+            StatementInfo::Suffices(ss) => {
+                write!(f, "suffices {}", ss.claim)?;
+                if let Some(body) = &ss.body {
+                    write!(f, " by")?;
+                    write_block(f, &body.statements, indentation)?;
+                }
+                Ok(())
+            }
+
+            StatementInfo::Coercion(cs) => {
+                write!(f, "coercion {}", cs.function)
+            }
+
             StatementInfo::Solve(ss) => {
                 write!(f, "solve {} by", ss.target)?;
                 write_block(f, &ss.body.statements, indentation)
@@ -1172,10 +1944,28 @@ impl Statement {
                 write_block(f, &body.statements, indentation)
             }
 
+            StatementInfo::ByContradiction(body) => {
+                write!(f, "by_contradiction")?;
+                write_block(f, &body.statements, indentation)
+            }
+
             StatementInfo::Match(ms) => {
                 let new_indentation = add_indent(indentation);
                 write!(f, "match {} {{", ms.scrutinee)?;
-                for (pattern, body) in &ms.cases {
+                for (pattern, guard, body) in &ms.cases {
+                    write!(f, "\n{}{}", new_indentation, pattern)?;
+                    if let Some(guard) = guard {
+                        write!(f, " if {}", guard)?;
+                    }
+                    write_block(f, &body.statements, &new_indentation)?;
+                }
+                write!(f, "\n{}}}", indentation)
+            }
+
+            StatementInfo::Induction(is) => {
+                let new_indentation = add_indent(indentation);
+                write!(f, "induction {} {{", is.variable)?;
+                for (pattern, body) in &is.cases {
                     write!(f, "\n{}{}", new_indentation, pattern)?;
                     write_block(f, &body.statements, &new_indentation)?;
                 }
@@ -1190,7 +1980,15 @@ impl Statement {
                 }
                 for theorem in &ts.theorems {
                     write!(f, "{}{}", new_indentation, theorem.name)?;
-                    write_theorem(f, &new_indentation, &[], &theorem.args, &theorem.claim)?;
+                    write_theorem(
+                        f,
+                        &new_indentation,
+                        &[],
+                        false,
+                        &theorem.args,
+                        None,
+                        &theorem.claim,
+                    )?;
                 }
                 write!(f, "{}}}", indentation)
             }
@@ -1207,8 +2005,23 @@ impl Statement {
     pub fn parse(
         tokens: &mut TokenIter,
         in_block: bool,
+    ) -> Result<(Option<Statement>, Option<Token>)> {
+        let doc_comments = parse_doc_comments(tokens);
+        let (statement, brace) = Statement::parse_undocumented(tokens, in_block)?;
+        let statement = statement.map(|mut s| {
+            s.doc_comments = doc_comments;
+            s
+        });
+        Ok((statement, brace))
+    }
+
+    // Parses a statement, ignoring any doc comments.
+    fn parse_undocumented(
+        tokens: &mut TokenIter,
+        in_block: bool,
     ) -> Result<(Option<Statement>, Option<Token>)> {
         loop {
+            let followed_by_name = tokens.peek_type_at(1) == Some(TokenType::Identifier);
             if let Some(token) = tokens.peek() {
                 match token.token_type {
                     TokenType::NewLine => {
@@ -1222,17 +2035,95 @@ impl Statement {
                     }
                     TokenType::Axiom => {
                         let keyword = tokens.next().unwrap();
-                        let s = parse_theorem_statement(keyword, tokens, true)?;
+                        let s = parse_theorem_statement(keyword, tokens, true, vec![])?;
                         return Ok((Some(s), None));
                     }
-                    TokenType::Theorem => {
+                    TokenType::Theorem | TokenType::Lemma => {
                         let keyword = tokens.next().unwrap();
-                        let s = parse_theorem_statement(keyword, tokens, false)?;
+                        let s = parse_theorem_statement(keyword, tokens, false, vec![])?;
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::At => {
+                        let attributes = parse_attributes(tokens)?;
+                        let keyword = tokens.expect_token()?;
+                        let axiomatic = match keyword.token_type {
+                            TokenType::Axiom => true,
+                            TokenType::Theorem | TokenType::Lemma => false,
+                            TokenType::Define => {
+                                let s = parse_define_statement(keyword, tokens, false, attributes)?;
+                                return Ok((Some(s), None));
+                            }
+                            TokenType::Opaque => {
+                                tokens.expect_type(TokenType::Define)?;
+                                let s = parse_define_statement(keyword, tokens, true, attributes)?;
+                                return Ok((Some(s), None));
+                            }
+                            _ => return Err(keyword.error(
+                                "attributes can only be used on theorems, axioms, and definitions",
+                            )),
+                        };
+                        let s = parse_theorem_statement(keyword, tokens, axiomatic, attributes)?;
                         return Ok((Some(s), None));
                     }
                     TokenType::Define => {
                         let keyword = tokens.next().unwrap();
-                        let s = parse_define_statement(keyword, tokens)?;
+                        let s = parse_define_statement(keyword, tokens, false, vec![])?;
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Opaque => {
+                        let keyword = tokens.next().unwrap();
+                        tokens.expect_type(TokenType::Define)?;
+                        let s = parse_define_statement(keyword, tokens, true, vec![])?;
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Rewrite => {
+                        let keyword = tokens.next().unwrap();
+                        let s = parse_rewrite_statement(keyword, tokens)?;
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Have => {
+                        let keyword = tokens.next().unwrap();
+                        let label = tokens.expect_variable_name(false)?;
+                        tokens.expect_type(TokenType::Colon)?;
+                        let (claim, body, last_token) = parse_claim_with_by(tokens)?;
+                        let hs = HaveStatement { label, claim, body };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Have(hs),
+                        };
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Assume => {
+                        let keyword = tokens.next().unwrap();
+                        let (claim, last_token) =
+                            Expression::parse_value(tokens, Terminator::Is(TokenType::NewLine))?;
+                        let a = AssumeStatement { claim };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Assume(a),
+                        };
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Suffices => {
+                        let keyword = tokens.next().unwrap();
+                        let s = parse_suffices_statement(keyword, tokens)?;
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Unfold => {
+                        let keyword = tokens.next().unwrap();
+                        let (target, last_token) =
+                            Expression::parse_value(tokens, Terminator::Is(TokenType::NewLine))?;
+                        let us = UnfoldStatement { target };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Unfold(us),
+                        };
                         return Ok((Some(s), None));
                     }
                     TokenType::Type => {
@@ -1253,6 +2144,11 @@ impl Statement {
                         let s = parse_forall_statement(keyword, tokens)?;
                         return Ok((Some(s), None));
                     }
+                    TokenType::Shadow => {
+                        let keyword = tokens.next().unwrap();
+                        let s = parse_shadow_statement(keyword, tokens)?;
+                        return Ok((Some(s), None));
+                    }
                     TokenType::If => {
                         let keyword = tokens.next().unwrap();
                         let s = parse_if_statement(keyword, tokens)?;
@@ -1278,23 +2174,86 @@ impl Statement {
                         let s = parse_class_statement(keyword, tokens)?;
                         return Ok((Some(s), None));
                     }
+                    TokenType::Module => {
+                        let keyword = tokens.next().unwrap();
+                        let s = parse_module_statement(keyword, tokens)?;
+                        return Ok((Some(s), None));
+                    }
                     TokenType::Numerals => {
                         let keyword = tokens.next().unwrap();
                         let (type_expr, last_token) =
                             Expression::parse_type(tokens, Terminator::Is(TokenType::NewLine))?;
                         let ds = NumeralsStatement { type_expr };
                         let s = Statement {
+                            doc_comments: vec![],
                             first_token: keyword,
                             last_token,
                             statement: StatementInfo::Numerals(ds),
                         };
                         return Ok((Some(s), None));
                     }
+                    TokenType::Strings => {
+                        let keyword = tokens.next().unwrap();
+                        let (type_expr, last_token) =
+                            Expression::parse_type(tokens, Terminator::Is(TokenType::NewLine))?;
+                        let ss = StringsStatement { type_expr };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Strings(ss),
+                        };
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Lists => {
+                        let keyword = tokens.next().unwrap();
+                        let (type_expr, last_token) =
+                            Expression::parse_type(tokens, Terminator::Is(TokenType::NewLine))?;
+                        let ls = ListsStatement { type_expr };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Lists(ls),
+                        };
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Decimals => {
+                        let keyword = tokens.next().unwrap();
+                        let (type_expr, last_token) =
+                            Expression::parse_type(tokens, Terminator::Is(TokenType::NewLine))?;
+                        let ds = DecimalsStatement { type_expr };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Decimals(ds),
+                        };
+                        return Ok((Some(s), None));
+                    }
+                    TokenType::Coercion => {
+                        let keyword = tokens.next().unwrap();
+                        let (function, last_token) =
+                            Expression::parse_value(tokens, Terminator::Is(TokenType::NewLine))?;
+                        let cs = CoercionStatement { function };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token,
+                            statement: StatementInfo::Coercion(cs),
+                        };
+                        return Ok((Some(s), None));
+                    }
                     TokenType::From => {
                         let keyword = tokens.next().unwrap();
                         let s = parse_from_statement(keyword, tokens)?;
                         return Ok((Some(s), None));
                     }
+                    TokenType::Export => {
+                        let keyword = tokens.next().unwrap();
+                        let s = parse_export_statement(keyword, tokens)?;
+                        return Ok((Some(s), None));
+                    }
                     TokenType::Solve => {
                         let keyword = tokens.next().unwrap();
                         let s = parse_solve_statement(keyword, tokens)?;
@@ -1310,12 +2269,37 @@ impl Statement {
                             right_brace: right_brace.clone(),
                         };
                         let s = Statement {
+                            doc_comments: vec![],
                             first_token: keyword,
                             last_token: right_brace,
                             statement: StatementInfo::Problem(body),
                         };
                         return Ok((Some(s), None));
                     }
+                    TokenType::ByContradiction => {
+                        let keyword = tokens.next().unwrap();
+                        let left_brace = tokens.expect_type(TokenType::LeftBrace)?;
+                        let (statements, right_brace) = parse_block(tokens)?;
+                        let body = Body {
+                            left_brace,
+                            statements,
+                            right_brace: right_brace.clone(),
+                        };
+                        let s = Statement {
+                            doc_comments: vec![],
+                            first_token: keyword,
+                            last_token: right_brace,
+                            statement: StatementInfo::ByContradiction(body),
+                        };
+                        return Ok((Some(s), None));
+                    }
+                    // "induction" is only a keyword when it's followed by the variable, so that
+                    // it can still be used as a name.
+                    TokenType::Identifier if token.text() == "induction" && followed_by_name => {
+                        let keyword = tokens.next().unwrap();
+                        let s = parse_induction_statement(keyword, tokens)?;
+                        return Ok((Some(s), None));
+                    }
                     TokenType::Match => {
                         let keyword = tokens.next().unwrap();
                         let s = parse_match_statement(keyword, tokens)?;
@@ -1331,6 +2315,7 @@ impl Statement {
                             return Err(token.error("unexpected token at the top level"));
                         }
                         let first_token = tokens.peek().unwrap().clone();
+                        let label = parse_label(tokens)?;
                         let (claim, token) = Expression::parse_value(
                             tokens,
                             Terminator::Or(TokenType::NewLine, TokenType::RightBrace),
@@ -1338,8 +2323,9 @@ impl Statement {
                         let block_ended = token.token_type == TokenType::RightBrace;
                         let brace = if block_ended { Some(token) } else { None };
                         let last_token = claim.last_token().clone();
-                        let se = StatementInfo::Prop(PropStatement { claim });
+                        let se = StatementInfo::Prop(PropStatement { label, claim });
                         let s = Statement {
+                            doc_comments: vec![],
                             first_token,
                             last_token,
                             statement: se,
@@ -1376,6 +2362,286 @@ impl Statement {
     pub fn last_line(&self) -> u32 {
         self.last_token.end_pos().line
     }
+
+    // Parses statements from the start of a document, stopping at the first parse error.
+    pub fn parse_prefix(input: &str) -> Vec<Statement> {
+        let mut tokens = TokenIter::new(Token::scan(input));
+        let mut statements = vec![];
+        while let Ok((Some(statement), _)) = Statement::parse(&mut tokens, false) {
+            statements.push(statement);
+        }
+        statements
+    }
+
+    // The names that this statement adds to the block it's in.
+    // Module statements create a whole namespace, but that's just one name out here.
+    pub fn introduced_names(&self) -> Vec<String> {
+        let tokens: Vec<&Token> = match &self.statement {
+            StatementInfo::Let(ls) => vec![&ls.name_token],
+            StatementInfo::DestructuringLet(dls) => {
+                let mut bound = vec![];
+                dls.pattern.bind_pattern(&mut bound, &mut vec![]);
+                return bound;
+            }
+            StatementInfo::Define(ds) => vec![&ds.name_token],
+            StatementInfo::DefineGroup(dss) => dss.iter().map(|ds| &ds.name_token).collect(),
+            StatementInfo::Theorem(ts) => return ts.name.iter().cloned().collect(),
+            StatementInfo::Prop(ps) => ps.label.iter().collect(),
+            StatementInfo::Type(ts) => return vec![ts.name.clone()],
+            StatementInfo::VariableSatisfy(vss) => {
+                vss.declarations.iter().map(|d| d.token()).collect()
+            }
+            StatementInfo::FunctionSatisfy(fss) => vec![&fss.name_token],
+            StatementInfo::Structure(ss) => vec![&ss.name_token],
+            StatementInfo::Inductive(is) => vec![&is.name_token],
+            StatementInfo::Import(is) => {
+                let mut names = vec![is.components.last().unwrap().clone()];
+                names.extend(is.names.iter().map(|name| name.text().to_string()));
+                return names;
+            }
+            StatementInfo::Export(es) if es.module.is_some() => vec![&es.name],
+            StatementInfo::Module(ms) => vec![&ms.name_token],
+            StatementInfo::Class(cs) => vec![&cs.name_token],
+            StatementInfo::Have(hs) => vec![&hs.label],
+            StatementInfo::Typeclass(ts) => vec![&ts.name],
+            _ => vec![],
+        };
+        tokens
+            .into_iter()
+            .map(|token| token.text().to_string())
+            .collect()
+    }
+
+    // Adds the names that this statement refers to without binding them itself, and that
+    // aren't in "bound".
+    // Like for expressions, this works on code that doesn't compile.
+    // The bodies of module statements are skipped, since they're separate modules.
+    pub fn add_free_names(&self, bound: &mut Vec<String>, answer: &mut Vec<Token>) {
+        let depth = bound.len();
+        match &self.statement {
+            StatementInfo::Let(ls) => {
+                ls.type_expr.add_free_names(bound, answer);
+                ls.value.add_free_names(bound, answer);
+            }
+            StatementInfo::DestructuringLet(dls) => {
+                dls.value.add_free_names(bound, answer);
+                dls.pattern.bind_pattern(bound, answer);
+            }
+            StatementInfo::Define(ds) => {
+                bound.push(ds.name.clone());
+                ds.add_free_names(bound, answer);
+            }
+            StatementInfo::DefineGroup(dss) => {
+                bound.extend(dss.iter().map(|ds| ds.name.clone()));
+                for ds in dss {
+                    ds.add_free_names(bound, answer);
+                }
+            }
+            StatementInfo::Theorem(ts) => {
+                // A theorem's proof can refer to the theorem itself, for induction.
+                bound.extend(ts.name.iter().cloned());
+                bound.extend(ts.type_params.iter().map(|t| t.text().to_string()));
+                for arg in &ts.args {
+                    arg.bind(bound, answer);
+                }
+                ts.claim.add_free_names(bound, answer);
+                bound.extend(ts.premise_label.iter().map(|t| t.text().to_string()));
+                add_body_free_names(ts.body.as_ref(), bound, answer);
+            }
+            StatementInfo::Prop(ps) => ps.claim.add_free_names(bound, answer),
+            StatementInfo::Type(ts) => ts.type_expr.add_free_names(bound, answer),
+            StatementInfo::ForAll(fas) => {
+                for quantifier in &fas.quantifiers {
+                    quantifier.bind(bound, answer);
+                }
+                if let Some(constraint) = &fas.constraint {
+                    constraint.add_free_names(bound, answer);
+                }
+                add_body_free_names(Some(&fas.body), bound, answer);
+            }
+            StatementInfo::If(is) => {
+                is.condition.add_free_names(bound, answer);
+                add_body_free_names(Some(&is.body), bound, answer);
+                add_body_free_names(is.else_body.as_ref(), bound, answer);
+            }
+            StatementInfo::VariableSatisfy(vss) => {
+                for declaration in &vss.declarations {
+                    declaration.bind(bound, answer);
+                }
+                vss.condition.add_free_names(bound, answer);
+            }
+            StatementInfo::FunctionSatisfy(fss) => {
+                for declaration in &fss.declarations {
+                    declaration.bind(bound, answer);
+                }
+                fss.condition.add_free_names(bound, answer);
+                add_body_free_names(fss.body.as_ref(), bound, answer);
+            }
+            StatementInfo::Structure(ss) => {
+                bound.push(ss.name.clone());
+                for (_, type_expr) in &ss.fields {
+                    type_expr.add_free_names(bound, answer);
+                }
+                bound.extend(ss.fields.iter().map(|(name, _)| name.text().to_string()));
+                if let Some(constraint) = &ss.constraint {
+                    constraint.add_free_names(bound, answer);
+                }
+                add_body_free_names(ss.body.as_ref(), bound, answer);
+            }
+            StatementInfo::Inductive(is) => {
+                bound.push(is.name.clone());
+                for (_, type_expr) in &is.constructors {
+                    if let Some(type_expr) = type_expr {
+                        type_expr.add_free_names(bound, answer);
+                    }
+                }
+            }
+            StatementInfo::Import(_) | StatementInfo::Module(_) => {}
+            StatementInfo::Export(es) => match &es.module {
+                Some(module) => Expression::Singleton(module.clone()).add_free_names(bound, answer),
+                None => Expression::Singleton(es.name.clone()).add_free_names(bound, answer),
+            },
+            StatementInfo::Class(cs) => add_body_free_names(Some(&cs.body), bound, answer),
+            StatementInfo::Numerals(ns) => ns.type_expr.add_free_names(bound, answer),
+            StatementInfo::Strings(ss) => ss.type_expr.add_free_names(bound, answer),
+            StatementInfo::Lists(ls) => ls.type_expr.add_free_names(bound, answer),
+            StatementInfo::Decimals(ds) => ds.type_expr.add_free_names(bound, answer),
+            StatementInfo::Unfold(us) => us.target.add_free_names(bound, answer),
+            StatementInfo::Rewrite(rs) => {
+                rs.equation.add_free_names(bound, answer);
+                if let Some(target) = &rs.target {
+                    target.add_free_names(bound, answer);
+                }
+            }
+            StatementInfo::Have(hs) => {
+                hs.claim.add_free_names(bound, answer);
+                add_body_free_names(hs.body.as_ref(), bound, answer);
+            }
+            StatementInfo::Assume(a) => a.claim.add_free_names(bound, answer),
+            StatementInfo::Suffices(ss) => {
+                ss.claim.add_free_names(bound, answer);
+                add_body_free_names(ss.body.as_ref(), bound, answer);
+            }
+            StatementInfo::Coercion(cs) => cs.function.add_free_names(bound, answer),
+            StatementInfo::Solve(ss) => {
+                ss.target.add_free_names(bound, answer);
+                add_body_free_names(Some(&ss.body), bound, answer);
+            }
+            StatementInfo::Problem(body) | StatementInfo::ByContradiction(body) => {
+                add_body_free_names(Some(body), bound, answer);
+            }
+            StatementInfo::Match(ms) => {
+                ms.scrutinee.add_free_names(bound, answer);
+                for (pattern, guard, body) in &ms.cases {
+                    let case_depth = bound.len();
+                    pattern.bind_pattern(bound, answer);
+                    if let Some(guard) = guard {
+                        guard.add_free_names(bound, answer);
+                    }
+                    add_body_free_names(Some(body), bound, answer);
+                    bound.truncate(case_depth);
+                }
+            }
+            StatementInfo::Induction(is) => {
+                is.variable.add_free_names(bound, answer);
+                for (pattern, body) in &is.cases {
+                    let case_depth = bound.len();
+                    pattern.bind_pattern(bound, answer);
+                    add_body_free_names(Some(body), bound, answer);
+                    bound.truncate(case_depth);
+                }
+            }
+            StatementInfo::Typeclass(ts) => {
+                bound.push(ts.instance_type.text().to_string());
+                bound.push(ts.name.text().to_string());
+                for (_, type_expr) in &ts.constants {
+                    type_expr.add_free_names(bound, answer);
+                }
+                for theorem in &ts.theorems {
+                    let theorem_depth = bound.len();
+                    for arg in &theorem.args {
+                        arg.bind(bound, answer);
+                    }
+                    theorem.claim.add_free_names(bound, answer);
+                    bound.truncate(theorem_depth);
+                }
+            }
+        }
+        bound.truncate(depth);
+    }
+}
+
+impl DefineStatement {
+    // The custom operator whose precedence this definition declares, and the precedence.
+    // A magic method for a custom operator can declare it like "@precedence(10) define concat".
+    pub fn declared_precedence(&self) -> Result<Option<(TokenType, i8)>> {
+        let Some(attribute) = self
+            .attributes
+            .iter()
+            .find(|a| a.name.text() == "precedence")
+        else {
+            return Ok(None);
+        };
+        let Some(arg) = &attribute.arg else {
+            return Err(attribute
+                .name
+                .error("a precedence needs a value, like @precedence(10)"));
+        };
+        let token_type = match TokenType::from_infix_magic_method_name(&self.name) {
+            Some(token_type) if token_type.is_custom_operator() => token_type,
+            _ => {
+                return Err(attribute
+                    .name
+                    .error("only the magic method of a custom operator can declare a precedence"));
+            }
+        };
+        match arg.text().parse() {
+            Ok(precedence) if CUSTOM_PRECEDENCES.contains(&precedence) => {
+                Ok(Some((token_type, precedence)))
+            }
+            _ => Err(arg.error(&format!(
+                "a precedence must be from {} to {}",
+                CUSTOM_PRECEDENCES.start(),
+                CUSTOM_PRECEDENCES.end()
+            ))),
+        }
+    }
+
+    // Adds the free names in the definition, with the defined name already bound.
+    fn add_free_names(&self, bound: &mut Vec<String>, answer: &mut Vec<Token>) {
+        let depth = bound.len();
+        bound.extend(self.type_params.iter().map(|t| t.text().to_string()));
+        for arg in &self.args {
+            arg.bind(bound, answer);
+        }
+        self.return_type.add_free_names(bound, answer);
+        if let Some(measure) = &self.measure {
+            measure.add_free_names(bound, answer);
+        }
+        self.return_value.add_free_names(bound, answer);
+        bound.truncate(depth);
+    }
+}
+
+// Adds the free names in a block of statements.
+// Each statement can use the names introduced by the statements before it.
+pub fn add_block_free_names(
+    statements: &[Statement],
+    bound: &mut Vec<String>,
+    answer: &mut Vec<Token>,
+) {
+    let depth = bound.len();
+    for statement in statements {
+        statement.add_free_names(bound, answer);
+        bound.extend(statement.introduced_names());
+    }
+    bound.truncate(depth);
+}
+
+fn add_body_free_names(body: Option<&Body>, bound: &mut Vec<String>, answer: &mut Vec<Token>) {
+    if let Some(body) = body {
+        add_block_free_names(&body.statements, bound, answer);
+    }
 }
 
 #[cfg(test)]
@@ -1479,6 +2745,15 @@ mod tests {
             }"});
     }
 
+    #[test]
+    fn test_constrained_forall_statements() {
+        ok(indoc! {"
+            forall(x: Nat, y: Nat) where x != y {
+                f(x) != f(y)
+            }"});
+        fail("forall where x != y { f(x) }");
+    }
+
     #[test]
     fn test_forall_value_in_statement() {
         ok("let p: bool = forall(b: bool) { b or not b }");
@@ -1751,6 +3026,23 @@ mod tests {
         }"});
     }
 
+    #[test]
+    fn test_implicit_type_parameters() {
+        ok(indoc! {"
+        define id{T}(x: T) -> T {
+            x
+        }"});
+        ok(indoc! {"
+        theorem id_eq{T}(x: T) {
+            id(x) = x
+        }"});
+        ok(indoc! {"
+        theorem foo {
+            bar
+        }"});
+        fail("theorem foo{T, bar}(x: T) { true }");
+    }
+
     #[test]
     fn test_import_statement() {
         ok("import foo.bar.baz");
@@ -1780,6 +3072,16 @@ mod tests {
         }"});
     }
 
+    #[test]
+    fn test_module_statement() {
+        ok(indoc! {"
+        module foo {
+            type Foo: axiom
+            let foo: Foo = axiom
+        }"});
+        fail("module Foo.bar { }");
+    }
+
     #[test]
     fn test_from_statement() {
         ok("from foo import bar");
@@ -1788,6 +3090,22 @@ mod tests {
         fail("from foo");
     }
 
+    #[test]
+    fn test_wildcard_from_statement() {
+        ok("from foo import *");
+        ok("from foo.bar import *");
+        fail("from foo import *, bar");
+        fail("from foo import bar, *");
+    }
+
+    #[test]
+    fn test_export_statement() {
+        ok("export Nat");
+        ok("export nat.Nat");
+        fail("export nat.Nat.zero");
+        fail("export");
+    }
+
     #[test]
     fn test_solve_statement() {
         ok(indoc! {"
@@ -1830,6 +3148,166 @@ mod tests {
         }"});
     }
 
+    #[test]
+    fn test_parsing_match_statement_with_guards() {
+        ok(indoc! {"
+        match x {
+            Nat.suc(k) if k != Nat.0 {
+                baz
+            }
+            Nat.suc(k) {
+                qux
+            }
+            Nat.0 {
+                zip
+            }
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_decreasing_measure() {
+        ok(indoc! {"
+        define countdown(n: Nat) -> Nat decreasing n.size {
+            countdown(n.pred)
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_define_group() {
+        ok(indoc! {"
+        define is_even(n: Nat) -> Bool {
+            is_odd(n.pred)
+        } and is_odd(n: Nat) -> Bool {
+            is_even(n.pred)
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_opaque_define() {
+        ok(indoc! {"
+        opaque define add_two(n: Nat) -> Nat {
+            n.suc.suc
+        }"});
+        ok("unfold add_two");
+        fail("opaque theorem foo { true }");
+    }
+
+    #[test]
+    fn test_parsing_shadow() {
+        ok("shadow let k: Nat = Nat.0");
+        ok(indoc! {"
+        shadow forall(k: Nat) {
+            k = k
+        }"});
+        fail("shadow define foo(k: Nat) -> Nat { k }");
+        fail("shadow let (a, b) = p");
+    }
+
+    #[test]
+    fn test_parsing_lemma() {
+        ok(indoc! {"
+        theorem goal(a: Nat) {
+            a = a
+        } by {
+            lemma helper(x: Nat) {
+                x = x
+            } by {
+                x = x
+            }
+            helper(a)
+        }"});
+        fail("lemma { true }");
+    }
+
+    #[test]
+    fn test_parsing_labels() {
+        ok(indoc! {"
+        theorem goal(a: Nat) {
+            h: a = b implies b = a
+        } by {
+            h1: b = a
+            h1
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_assume_and_suffices() {
+        ok(indoc! {"
+        theorem goal(a: Bool, b: Bool, c: Bool) {
+            a implies (b implies c)
+        } by {
+            assume b
+            suffices a and b by {
+                a and b
+            }
+            suffices c
+            c
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_by_contradiction() {
+        ok(indoc! {"
+        theorem goal(a: Bool) {
+            a or not a
+        } by {
+            by_contradiction {
+                not a
+                false
+            }
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_induction() {
+        ok(indoc! {"
+        theorem goal(n: Nat) {
+            add(n, zero) = n
+        } by {
+            induction n {
+                Nat.zero {
+                    add(zero, zero) = zero
+                }
+                Nat.suc(k) {
+                    add(suc(k), zero) = suc(add(k, zero))
+                }
+            }
+        }"});
+
+        // Without a variable after it, "induction" is just a name.
+        ok(indoc! {"
+        theorem goal {
+            foo(zero)
+        } by {
+            induction(foo)
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_rewrite() {
+        ok(indoc! {"
+        theorem goal(a: Nat, b: Nat) {
+            h: add(a, b) = zero implies add(b, a) = zero
+        } by {
+            rewrite add_comm(a, b) in h
+            rewrite add_comm(b, a)
+        }"});
+    }
+
+    #[test]
+    fn test_parsing_have() {
+        ok(indoc! {"
+        theorem goal(a: Nat) {
+            a = a
+        } by {
+            have h1: a = a
+            have h2: a = a by {
+                h1
+            }
+            h2
+        }"});
+    }
+
     #[test]
     fn test_anonymous_theorem_statement() {
         ok(indoc! {"
@@ -1880,6 +3358,141 @@ mod tests {
         }"});
     }
 
+    #[test]
+    fn test_parsing_doc_comments() {
+        ok(indoc! {"
+        /// The identity function.
+        ///
+        /// It returns its argument.
+        define id(x: Bool) -> Bool {
+            x
+        }"});
+        let s = should_parse(indoc! {"
+        /// Not attached, because of the blank line.
+
+        // A regular comment.
+        theorem foo {
+            true
+        }"});
+        assert!(s.doc_comments.is_empty());
+    }
+
+    #[test]
+    fn test_parsing_theorem_attributes() {
+        ok(indoc! {"
+        @simp theorem add_zero(a: Nat) {
+            a + 0 = a
+        }"});
+        ok(indoc! {"
+        @no_auto @simp axiom foo(a: Nat) {
+            a = a
+        }"});
+        let s = should_parse(indoc! {"
+        @simp
+        theorem foo {
+            true
+        }"});
+        assert_eq!(s.to_string(), "@simp theorem foo {\n    true\n}");
+        ok(indoc! {"
+        @max_active(5000) @simp theorem foo {
+            true
+        }"});
+        fail_with("@simp let foo: Bool = true", "attributes can only be used");
+        fail("@ theorem foo { true }");
+        fail("@max_active(x) theorem foo { true }");
+    }
+
+    #[test]
+    fn test_parsing_tags() {
+        ok(indoc! {"
+        @tag(algebra) theorem foo {
+            true
+        }"});
+        ok(indoc! {"
+        @tag(algebra) @tag(order) define foo(a: Nat) -> Bool {
+            true
+        }"});
+        ok(indoc! {"
+        @tag(algebra) opaque define foo(a: Nat) -> Bool {
+            true
+        }"});
+        let s = should_parse(indoc! {"
+        @tag(order)
+        define lt(a: Nat, b: Nat) -> Bool {
+            true
+        }"});
+        assert_eq!(
+            s.to_string(),
+            "@tag(order) define lt(a: Nat, b: Nat) -> Bool {\n    true\n}"
+        );
+        fail("@tag(3) theorem foo { true }");
+    }
+
+    #[test]
+    fn test_parsing_precedence() {
+        ok(indoc! {"
+        @precedence(12) define concat(self, other: Str) -> Str {
+            axiom
+        }"});
+        fail_with(
+            "@precedence(12) define add(self, other: Str) -> Str { axiom }",
+            "only the magic method of a custom operator",
+        );
+        fail_with(
+            "@precedence(2) define concat(self, other: Str) -> Str { axiom }",
+            "a precedence must be from 6 to 12",
+        );
+        fail("@precedence define concat(self, other: Str) -> Str { axiom }");
+    }
+
+    #[test]
+    fn test_parsing_list_literals() {
+        ok("lists NatList");
+        ok("let l: NatList = [1, 2, 3]");
+        ok("let l: NatList = []");
+        fail("let l: NatList = [1, 2");
+    }
+
+    #[test]
+    fn test_parsing_coercion_statement() {
+        ok("coercion Int.from_nat");
+        ok("coercion nat_to_int");
+        fail("coercion");
+    }
+
+    #[test]
+    fn test_parsing_destructuring_let() {
+        ok("let (a, b) = p");
+        ok("let Pair.new(a, b) = p");
+        ok("let (a) = p");
+        ok("let (a: Nat, b: Nat) satisfy {\n    a = b\n}");
+        fail("let (a, b: Nat) = p");
+        fail("let (a, b)");
+    }
+
+    #[test]
+    fn test_parsing_refinement_types() {
+        ok("let x: { n: Nat | n != zero } = one");
+        ok(indoc! {"
+        theorem foo(a: { n: Nat | n != zero }) {
+            a = a
+        }"});
+        ok(indoc! {"
+        define pred(a: Nat) -> { n: Nat | n < a } {
+            axiom
+        }"});
+        fail("let x: Nat -> { n: Nat | n != zero } = axiom");
+    }
+
+    #[test]
+    fn test_parsing_string_literals() {
+        ok("strings Str");
+        ok("decimals Rat");
+        ok(r#"let s: Str = "hello""#);
+        ok(r#"let s: Str = "say \"hi\"""#);
+        fail(r#"let s: Str = "unterminated"#);
+    }
+
     // #[test]
     // fn test_parsing_typeclass_statement_theorems() {
     //     ok(indoc! {"
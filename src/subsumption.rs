@@ -0,0 +1,258 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use im::{HashMap, Vector};
+
+use crate::atom::Atom;
+use crate::clause::Clause;
+use crate::literal::Literal;
+use crate::specializer::Specializer;
+
+// A clause C subsumes a clause D when some substitution maps each literal of C to a different
+// literal of D. Then D is redundant, because anything it could prove, C can prove too.
+
+// A symbol along with the sign of a literal it appears in.
+type SignedSymbol = (bool, Atom);
+
+// Features of a clause that can only grow from a clause to the clauses it subsumes.
+// Comparing them rules out most pairs of clauses without trying to match them.
+#[derive(Clone, Debug)]
+struct FeatureVector {
+    num_literals: usize,
+    num_negative: usize,
+
+    // The symbols that appear in positive and negative literals, hashed into bitsets.
+    positive_bits: u64,
+    negative_bits: u64,
+}
+
+impl FeatureVector {
+    fn new(symbols: &[SignedSymbol], clause: &Clause) -> FeatureVector {
+        let mut positive_bits = 0;
+        let mut negative_bits = 0;
+        for (positive, atom) in symbols {
+            let mut hasher = DefaultHasher::new();
+            atom.hash(&mut hasher);
+            let bit = 1 << (hasher.finish() % 64);
+            if *positive {
+                positive_bits |= bit;
+            } else {
+                negative_bits |= bit;
+            }
+        }
+        FeatureVector {
+            num_literals: clause.len(),
+            num_negative: clause.len() - clause.num_positive_literals(),
+            positive_bits,
+            negative_bits,
+        }
+    }
+
+    // Whether a clause with these features could subsume a clause with the other features.
+    fn could_subsume(&self, other: &FeatureVector) -> bool {
+        self.num_literals <= other.num_literals
+            && self.num_negative <= other.num_negative
+            && self.positive_bits & !other.positive_bits == 0
+            && self.negative_bits & !other.negative_bits == 0
+    }
+}
+
+// The non-variable symbols in a clause, sorted and deduplicated.
+fn signed_symbols(clause: &Clause) -> Vec<SignedSymbol> {
+    let mut answer = vec![];
+    for literal in &clause.literals {
+        for (_, atom) in literal.typed_atoms() {
+            if !atom.is_variable() && atom != Atom::True {
+                answer.push((literal.positive, atom));
+            }
+        }
+    }
+    answer.sort();
+    answer.dedup();
+    answer
+}
+
+#[derive(Clone)]
+struct Entry {
+    id: usize,
+    clause: Clause,
+    features: FeatureVector,
+}
+
+// An index of clauses that finds subsumption in both directions.
+// Forward subsumption finds a stored clause that subsumes a new one, and backward subsumption
+// finds the stored clauses that a new one subsumes.
+// The collections are persistent, so that cloning the index shares them.
+#[derive(Clone, Default)]
+pub struct SubsumptionIndex {
+    entries: Vector<Entry>,
+
+    // Each entry is filed under its first symbol, or in unfiled if it has no symbols.
+    // A clause can only be subsumed by entries filed under one of its own symbols.
+    filed: HashMap<SignedSymbol, Vector<usize>>,
+    unfiled: Vector<usize>,
+
+    // For every symbol, all the entries that contain it.
+    containing: HashMap<SignedSymbol, Vector<usize>>,
+}
+
+impl SubsumptionIndex {
+    pub fn new() -> SubsumptionIndex {
+        SubsumptionIndex::default()
+    }
+
+    pub fn insert(&mut self, id: usize, clause: &Clause) {
+        let index = self.entries.len();
+        let symbols = signed_symbols(clause);
+        match symbols.first() {
+            Some(symbol) => self.filed.entry(*symbol).or_default().push_back(index),
+            None => self.unfiled.push_back(index),
+        }
+        for symbol in &symbols {
+            self.containing.entry(*symbol).or_default().push_back(index);
+        }
+        self.entries.push_back(Entry {
+            id,
+            clause: clause.clone(),
+            features: FeatureVector::new(&symbols, clause),
+        });
+    }
+
+    // Finds the id of a stored clause that subsumes this one, if accept allows it.
+    pub fn find_subsuming(&self, clause: &Clause, accept: impl Fn(usize) -> bool) -> Option<usize> {
+        let symbols = signed_symbols(clause);
+        let features = FeatureVector::new(&symbols, clause);
+        let filed = symbols
+            .iter()
+            .filter_map(|symbol| self.filed.get(symbol))
+            .flatten();
+        for &index in self.unfiled.iter().chain(filed) {
+            let entry = &self.entries[index];
+            if entry.features.could_subsume(&features)
+                && accept(entry.id)
+                && subsumes(&entry.clause, clause)
+            {
+                return Some(entry.id);
+            }
+        }
+        None
+    }
+
+    // Finds the ids of all stored clauses that this one subsumes.
+    pub fn find_subsumed(&self, clause: &Clause) -> Vec<usize> {
+        let symbols = signed_symbols(clause);
+        let features = FeatureVector::new(&symbols, clause);
+
+        // Subsumed clauses contain every symbol, so the rarest one narrows it down the most.
+        let none = Vector::new();
+        let rarest = symbols
+            .iter()
+            .map(|symbol| self.containing.get(symbol).unwrap_or(&none))
+            .min_by_key(|indices| indices.len());
+        let candidates: Box<dyn Iterator<Item = usize>> = match rarest {
+            Some(indices) => Box::new(indices.iter().copied()),
+            None => Box::new(0..self.entries.len()),
+        };
+        candidates
+            .map(|index| &self.entries[index])
+            .filter(|entry| {
+                features.could_subsume(&entry.features) && subsumes(clause, &entry.clause)
+            })
+            .map(|entry| entry.id)
+            .collect()
+    }
+}
+
+// Whether the general clause subsumes the special one.
+pub fn subsumes(general: &Clause, special: &Clause) -> bool {
+    if general.len() > special.len() {
+        return false;
+    }
+    let mut used = vec![false; special.len()];
+    match_literals(
+        &general.literals,
+        &special.literals,
+        &mut used,
+        &Specializer::new(),
+    )
+}
+
+// Tries to match each general literal to a different unused special literal, backtracking
+// when a choice doesn't work out.
+fn match_literals(
+    general: &[Literal],
+    special: &[Literal],
+    used: &mut Vec<bool>,
+    specializer: &Specializer,
+) -> bool {
+    let Some((first, rest)) = general.split_first() else {
+        return true;
+    };
+    for (i, literal) in special.iter().enumerate() {
+        if used[i] || literal.positive != first.positive {
+            continue;
+        }
+        // Equality is symmetric, so either side can match.
+        for (u, v) in [
+            (&literal.left, &literal.right),
+            (&literal.right, &literal.left),
+        ] {
+            let mut attempt = specializer.clone();
+            if attempt.match_terms(&first.left, u) && attempt.match_terms(&first.right, v) {
+                used[i] = true;
+                if match_literals(rest, special, used, &attempt) {
+                    return true;
+                }
+                used[i] = false;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsumes() {
+        let general = Clause::parse("c0(x0) or c1(x0, x1)");
+        assert!(subsumes(
+            &general,
+            &Clause::parse("c0(c2) or c1(c2, c3) or c4")
+        ));
+        assert!(!subsumes(&general, &Clause::parse("c0(c2) or c1(c3, c3)")));
+        assert!(subsumes(
+            &Clause::parse("x0 = c0"),
+            &Clause::parse("c0 = c1 or c2")
+        ));
+
+        // Each general literal needs its own special literal.
+        assert!(!subsumes(
+            &Clause::parse("c0(x0) or c0(x1)"),
+            &Clause::parse("c0(c1)")
+        ));
+    }
+
+    #[test]
+    fn test_subsumption_index() {
+        let mut index = SubsumptionIndex::new();
+        index.insert(0, &Clause::parse("c0(x0) or c1(x0)"));
+        index.insert(1, &Clause::parse("not c2(x0) or c3(x0, c4)"));
+        index.insert(2, &Clause::parse("c0(c5) or c1(c5) or c6"));
+
+        let clause = Clause::parse("c0(c5) or c1(c5) or c3(c5, c5)");
+        assert_eq!(index.find_subsuming(&clause, |_| true), Some(0));
+        assert_eq!(index.find_subsuming(&clause, |id| id != 0), None);
+        assert_eq!(
+            index.find_subsuming(&Clause::parse("not c2(c5) or c0(c6)"), |_| true),
+            None
+        );
+
+        assert_eq!(
+            index.find_subsumed(&Clause::parse("c0(x0) or c1(x0)")),
+            vec![0, 2]
+        );
+        assert_eq!(index.find_subsumed(&Clause::parse("c1(c5) or c6")), vec![2]);
+    }
+}
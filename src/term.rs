@@ -1,11 +1,14 @@
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 use crate::atom::{Atom, AtomId};
 use crate::type_map::{TypeId, BOOL, EMPTY};
 
 // A term with no args is a plain atom.
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Term {
     // The term type is the type of the entire term.
     // For example "2 < 3" has type "Bool".
@@ -343,6 +346,75 @@ impl Term {
         }
     }
 
+    // Replaces every occurrence of the "from" subterm with the "to" term.
+    pub fn replace_term(&self, from: &Term, to: &Term) -> Term {
+        if self == from {
+            return to.clone();
+        }
+        self.replace_args(
+            self.args
+                .iter()
+                .map(|arg| arg.replace_term(from, to))
+                .collect(),
+        )
+    }
+
+    // Finds a proper subterm that matches the predicate, searching outside in.
+    pub fn find_subterm(&self, predicate: &impl Fn(&Term) -> bool) -> Option<&Term> {
+        for arg in &self.args {
+            if predicate(arg) {
+                return Some(arg);
+            }
+            if let Some(answer) = arg.find_subterm(predicate) {
+                return Some(answer);
+            }
+        }
+        None
+    }
+
+    // Whether this term is a binary application of one of the associative-commutative operators.
+    fn is_ac_application(&self, operators: &HashSet<Atom>) -> bool {
+        self.args.len() == 2 && !self.head.is_variable() && operators.contains(&self.head)
+    }
+
+    // Collects the operands of a nested application of the same AC operator.
+    fn push_ac_operands<'a>(&'a self, head: &Atom, answer: &mut Vec<&'a Term>) {
+        for arg in &self.args {
+            if arg.head == *head && arg.args.len() == 2 {
+                arg.push_ac_operands(head, answer);
+            } else {
+                answer.push(arg);
+            }
+        }
+    }
+
+    // Normalizes applications of associative-commutative operators, so that terms that are
+    // equal modulo AC become identical.
+    // Nested applications of an operator get flattened into a list of operands, which are
+    // sorted and then nested to the right again, like f(a, f(b, c)).
+    pub fn ac_normalize(&self, operators: &HashSet<Atom>) -> Term {
+        if !self.is_ac_application(operators) {
+            return self.replace_args(
+                self.args
+                    .iter()
+                    .map(|arg| arg.ac_normalize(operators))
+                    .collect(),
+            );
+        }
+        let mut operands = vec![];
+        self.push_ac_operands(&self.head, &mut operands);
+        let mut operands: Vec<Term> = operands
+            .into_iter()
+            .map(|operand| operand.ac_normalize(operators))
+            .collect();
+        operands.sort_by(|a, b| a.extended_kbo_cmp(b));
+        let mut answer = operands.pop().unwrap();
+        while let Some(operand) = operands.pop() {
+            answer = self.replace_args(vec![operand, answer]);
+        }
+        answer
+    }
+
     pub fn replace_args(&self, new_args: Vec<Term>) -> Term {
         Term {
             term_type: self.term_type,
@@ -540,6 +612,25 @@ impl Term {
         }
     }
 
+    // If other is this term with one subterm replaced, returns the smallest subterm that
+    // differs, along with what it was replaced by.
+    pub fn find_replacement<'a>(&'a self, other: &'a Term) -> Option<(&'a Term, &'a Term)> {
+        if self == other {
+            return None;
+        }
+        if self.head != other.head
+            || self.head_type != other.head_type
+            || self.args.len() != other.args.len()
+        {
+            return Some((self, other));
+        }
+        let mut differences = self.args.iter().zip(&other.args).filter(|(a, b)| a != b);
+        match (differences.next(), differences.next()) {
+            (Some((a, b)), None) => a.find_replacement(b),
+            _ => Some((self, other)),
+        }
+    }
+
     // Finds all rewritable subterms of this term, and with their paths, appends to "answer".
     // It is an error to call this on any variables.
     // Otherwise, any term is rewritable except for "true".
@@ -653,4 +744,24 @@ mod tests {
         let replaced = old_term.replace_at_path(&[1], new_term);
         assert_eq!(replaced, Term::parse("c2(x0, c0(x0))"));
     }
+
+    #[test]
+    fn test_find_and_replace_subterm() {
+        let term = Term::parse("c0(c1(c2), c2)");
+        let c2 = Term::parse("c2");
+        assert_eq!(term.find_subterm(&|t| *t == c2), Some(&c2));
+        assert_eq!(term.find_subterm(&|t| *t == term), None);
+        let replaced = term.replace_term(&c2, &Term::parse("c3"));
+        assert_eq!(replaced, Term::parse("c0(c1(c3), c3)"));
+    }
+
+    #[test]
+    fn test_find_replacement() {
+        let before = Term::parse("c0(c1(c2, c3), c4)");
+        let after = Term::parse("c0(c1(c2, c5(c3)), c4)");
+        let (old, new) = before.find_replacement(&after).unwrap();
+        assert_eq!(old, &Term::parse("c3"));
+        assert_eq!(new, &Term::parse("c5(c3)"));
+        assert_eq!(before.find_replacement(&before), None);
+    }
 }
@@ -1,8 +1,12 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::hash::Hash;
 
-use crate::atom::Atom;
+use im::hashmap::Entry;
+use im::{HashMap, HashSet, Vector};
+
+use crate::atom::{Atom, AtomId};
+use crate::clause::Clause;
 use crate::term::Term;
 
 // Each term has a unique id.
@@ -14,6 +18,13 @@ pub type TermId = u32;
 // The term graph uses it to provide a history of the reasoning that led to a conclusion.
 type StepId = usize;
 
+// Facts about constructors, like that two of them are never equal, are tagged with a FactId.
+// The caller decides what the ids refer to.
+type FactId = usize;
+
+// A head along with how many args it's applied to.
+type HeadKey = (Atom, usize);
+
 // The rationale for a single rewrite step.
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Ord, PartialOrd)]
 pub struct RewriteStep {
@@ -34,10 +45,52 @@ pub struct RewriteStep {
 pub struct TermGraphContradiction {
     // Every contradiction is based on one inequality, plus a set of rewrites that turn
     // one site of the inequality into the other.
-    pub inequality_id: StepId,
+    // The inequality is None when it comes from two constructors that are never equal.
+    pub inequality_id: Option<StepId>,
 
     // The rewrites that turn one side of the inequality into the other.
     pub rewrite_chain: Vec<(Term, Term, RewriteStep)>,
+
+    // The constructor facts that the contradiction uses.
+    pub fact_ids: Vec<FactId>,
+}
+
+// How we know that two adjacent terms are equal.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum Edge {
+    // A rewrite from an external step.
+    Rewrite(RewriteStep),
+
+    // Both terms are compounds, with equal heads and equal args.
+    Compound,
+
+    // Both terms are the same arg of two equal applications of an injective head.
+    // Stores those two applications, and the fact that the head is injective in this arg.
+    Injective(TermId, TermId, FactId),
+}
+
+// Why two terms can't be equal.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum Disequality {
+    Step(StepId),
+
+    // The terms have heads that a fact says are never equal.
+    Distinct(FactId),
+}
+
+// What makes the term graph contradictory.
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum Conflict {
+    // These terms are equal, but they can't be.
+    Unequal(TermId, TermId, Disequality),
+
+    // A term is equal to one of its own proper subterms, which can't happen with constructors
+    // of an inductive type.
+    // Each pair is an arg of a constructor application, and the application that the next
+    // pair's arg is from, which the graph knows are equal. The last pair leads back to the
+    // first application.
+    // Also stores the facts for the constructors that make up the cycle.
+    Cycle(Vec<(TermId, TermId)>, Vec<FactId>),
 }
 
 // Each term has a Decomposition that describes how it is created.
@@ -57,8 +110,7 @@ struct TermInfo {
     decomp: Decomposition,
 
     // The terms that this one can be directly turned into.
-    // When the step id is not provided, we concluded it from composition.
-    adjacent: Vec<(TermId, Option<RewriteStep>)>,
+    adjacent: Vec<(TermId, Edge)>,
 }
 
 // Each term belongs to a group.
@@ -77,7 +129,12 @@ struct GroupInfo {
     // The other groups that we know are not equal to this one.
     // For each inequality, we store the two terms that we know are not equal,
     // along with the step that we know it from.
-    inequalities: HashMap<GroupId, (TermId, TermId, StepId)>,
+    // This is ordered so that when merging groups finds several contradictions, which one gets
+    // reported doesn't depend on hashing.
+    inequalities: BTreeMap<GroupId, (TermId, TermId, StepId)>,
+
+    // One term in this group for each constructor head that terms in this group have.
+    constructors: BTreeMap<HeadKey, TermId>,
 }
 
 impl GroupInfo {
@@ -156,17 +213,19 @@ impl fmt::Display for CompoundInfo {
 
 // The TermGraph stores concrete terms, along with relationships between them that represent
 // equality, inequality, and subterm relationships.
+// The collections are persistent, so that cloning the graph shares them, and only the parts
+// that change afterwards get copied.
 #[derive(Clone)]
 pub struct TermGraph {
     // terms maps TermId to TermInfo.
-    terms: Vec<TermInfo>,
+    terms: Vector<TermInfo>,
 
     // groups maps GroupId to GroupInfo.
-    groups: Vec<Option<GroupInfo>>,
+    groups: Vector<Option<GroupInfo>>,
 
     // compounds maps CompoundId to CompoundInfo.
     // When a compound is deleted, we replace it with None.
-    compounds: Vec<Option<CompoundInfo>>,
+    compounds: Vector<Option<CompoundInfo>>,
 
     // Keying the compounds so that we can check if a composition belongs to an existing group.
     compound_map: HashMap<CompoundKey, TermId>,
@@ -175,27 +234,122 @@ pub struct TermGraph {
     decompositions: HashMap<Decomposition, TermId>,
 
     // Pairs of terms that we have discovered are identical
-    pending: Vec<(TermId, TermId, Option<RewriteStep>)>,
+    pending: Vec<(TermId, TermId, Edge)>,
 
     // Set when we discover a contradiction.
-    // The provided step sets these terms to be unequal. However, the term graph also
-    // knows that they are equal. This is a contradiction.
-    contradiction: Option<(TermId, TermId, StepId)>,
+    contradiction: Option<Conflict>,
+
+    // Pairs of constructor heads that never give equal terms, keyed in both orders.
+    distinct: HashMap<(HeadKey, HeadKey), FactId>,
+
+    // The args that constructors are injective in, keyed by the head and the arg index.
+    injective: HashMap<(HeadKey, usize), FactId>,
+
+    // The constructors that can't be applied to get a term equal to one of its args.
+    acyclic: HashMap<HeadKey, FactId>,
+
+    // Every head that appears in the distinct or injective facts.
+    constructors: HashSet<HeadKey>,
 }
 
 impl TermGraph {
     pub fn new() -> TermGraph {
         TermGraph {
-            terms: Vec::new(),
-            groups: Vec::new(),
-            compounds: Vec::new(),
+            terms: Vector::new(),
+            groups: Vector::new(),
+            compounds: Vector::new(),
             compound_map: HashMap::new(),
             decompositions: HashMap::new(),
             pending: Vec::new(),
             contradiction: None,
+            distinct: HashMap::new(),
+            injective: HashMap::new(),
+            acyclic: HashMap::new(),
+            constructors: HashSet::new(),
         }
     }
 
+    // Learns about constructors from a clause, if it has one of these forms:
+    //   c1(x0, x1) != c2(x2)
+    //   c1(x0, x1) != c1(x2, x3) or x0 = x2
+    // The first form says that the heads never give equal terms, and the second says that
+    // the head is injective in one of its args.
+    // When the fact comes from the definition of a type, the injective head is one of its
+    // constructors, so no term can equal an application of it to that same term.
+    // Returns whether the clause was a constructor fact.
+    // This should be called before inserting any terms that the fact applies to.
+    pub fn add_constructor_fact(
+        &mut self,
+        clause: &Clause,
+        fact_id: FactId,
+        type_definition: bool,
+    ) -> bool {
+        let inequality = match clause.literals.as_slice() {
+            [literal] => literal,
+            [first, _] if !first.positive => first,
+            [_, second] if !second.positive => second,
+            _ => return false,
+        };
+        if inequality.positive || inequality.right.is_true() {
+            return false;
+        }
+        let (Some(left_vars), Some(right_vars)) = (
+            variable_args(&inequality.left),
+            variable_args(&inequality.right),
+        ) else {
+            return false;
+        };
+        let mut all_vars: Vec<_> = left_vars.iter().chain(right_vars.iter()).collect();
+        all_vars.sort();
+        all_vars.dedup();
+        if all_vars.is_empty() || all_vars.len() != left_vars.len() + right_vars.len() {
+            return false;
+        }
+        let left_key = (*inequality.left.get_head(), left_vars.len());
+        let right_key = (*inequality.right.get_head(), right_vars.len());
+
+        if clause.literals.len() == 1 {
+            if left_key == right_key {
+                return false;
+            }
+            self.distinct.insert((left_key, right_key), fact_id);
+            self.distinct.insert((right_key, left_key), fact_id);
+            self.constructors.insert(left_key);
+            self.constructors.insert(right_key);
+            return true;
+        }
+
+        if left_key != right_key {
+            return false;
+        }
+        let equality = if clause.literals[0].positive {
+            &clause.literals[0]
+        } else {
+            &clause.literals[1]
+        };
+        if !equality.positive {
+            return false;
+        }
+        let (Some(a), Some(b)) = (
+            equality.left.atomic_variable(),
+            equality.right.atomic_variable(),
+        ) else {
+            return false;
+        };
+        for i in 0..left_vars.len() {
+            let pair = (left_vars[i], right_vars[i]);
+            if pair == (a, b) || pair == (b, a) {
+                self.injective.insert((left_key, i), fact_id);
+                if type_definition {
+                    self.acyclic.insert(left_key, fact_id);
+                }
+                self.constructors.insert(left_key);
+                return true;
+            }
+        }
+        false
+    }
+
     // Returns None if this term isn't in the graph.
     pub fn get_term_id(&self, term: &Term) -> Option<TermId> {
         // Look up the head
@@ -231,12 +385,31 @@ impl TermGraph {
     // Used to explain which steps lead to a contradiction.
     // Returns None if there is no contradiction.
     pub fn get_contradiction(&self) -> Option<TermGraphContradiction> {
-        let (term1, term2, inequality_id) = self.contradiction?;
         let mut rewrite_chain = vec![];
-        self.expand_steps(term1, term2, &mut rewrite_chain);
+        let mut fact_ids = BTreeSet::new();
+        let inequality_id = match self.contradiction.as_ref()? {
+            Conflict::Unequal(term1, term2, disequality) => {
+                self.expand_steps(*term1, *term2, &mut rewrite_chain, &mut fact_ids);
+                match disequality {
+                    Disequality::Step(step) => Some(*step),
+                    Disequality::Distinct(fact_id) => {
+                        fact_ids.insert(*fact_id);
+                        None
+                    }
+                }
+            }
+            Conflict::Cycle(pairs, cycle_fact_ids) => {
+                for (term1, term2) in pairs {
+                    self.expand_steps(*term1, *term2, &mut rewrite_chain, &mut fact_ids);
+                }
+                fact_ids.extend(cycle_fact_ids);
+                None
+            }
+        };
         Some(TermGraphContradiction {
             inequality_id,
             rewrite_chain,
+            fact_ids: fact_ids.into_iter().collect(),
         })
     }
 
@@ -247,6 +420,22 @@ impl TermGraph {
         }
     }
 
+    // Makes a new group that contains just this term.
+    fn push_group(&mut self, term_id: TermId) {
+        let term = &self.terms[term_id as usize].term;
+        let mut constructors = BTreeMap::new();
+        let key = (*term.get_head(), term.args.len());
+        if self.constructors.contains(&key) {
+            constructors.insert(key, term_id);
+        }
+        self.groups.push_back(Some(GroupInfo {
+            terms: vec![term_id],
+            compounds: vec![],
+            inequalities: BTreeMap::new(),
+            constructors,
+        }));
+    }
+
     // Inserts the head of the provided term as an atom.
     // If it's already in the graph, return the existing term id.
     // Otherwise, make a new term id and give it a new group.
@@ -259,7 +448,6 @@ impl TermGraph {
         // Make a new term and group
         let term_id = self.terms.len() as TermId;
         let group_id = self.groups.len() as GroupId;
-
         let head = Term {
             term_type: term.head_type,
             head_type: term.head_type,
@@ -272,13 +460,8 @@ impl TermGraph {
             decomp: key.clone(),
             adjacent: vec![],
         };
-        self.terms.push(term_info);
-        let group_info = Some(GroupInfo {
-            terms: vec![term_id],
-            compounds: vec![],
-            inequalities: HashMap::new(),
-        });
-        self.groups.push(group_info);
+        self.terms.push_back(term_info);
+        self.push_group(term_id);
         self.decompositions.insert(key, term_id);
         term_id
     }
@@ -301,13 +484,8 @@ impl TermGraph {
             decomp: key.clone(),
             adjacent: vec![],
         };
-        self.terms.push(term_info);
-        let group_info = Some(GroupInfo {
-            terms: vec![term_id],
-            compounds: vec![],
-            inequalities: HashMap::new(),
-        });
-        self.groups.push(group_info);
+        self.terms.push_back(term_info);
+        self.push_group(term_id);
         self.decompositions.insert(key, term_id);
         term_id
     }
@@ -320,7 +498,8 @@ impl TermGraph {
         if let Some(&existing_result_term) = self.compound_map.get(&key) {
             let existing_result_group = self.get_group_id(existing_result_term);
             if existing_result_group != result_group {
-                self.pending.push((existing_result_term, result_term, None));
+                self.pending
+                    .push((existing_result_term, result_term, Edge::Compound));
             }
             return;
         }
@@ -340,7 +519,7 @@ impl TermGraph {
                 }
             }
         }
-        self.compounds.push(Some(compound_info));
+        self.compounds.push_back(Some(compound_info));
         self.compound_map.insert(key, result_term);
         return;
     }
@@ -376,7 +555,7 @@ impl TermGraph {
         old_group: GroupId,
         new_term: TermId,
         new_group: GroupId,
-        step: Option<RewriteStep>,
+        edge: Edge,
     ) {
         let old_info = self.groups[old_group as usize]
             .take()
@@ -409,7 +588,7 @@ impl TermGraph {
                 // Instead of inserting compound.result, we need to delete this compound, and merge the
                 // intended result with result_group.
                 self.pending
-                    .push((compound.result_term, existing_result_term, None));
+                    .push((compound.result_term, existing_result_term, Edge::Compound));
                 self.compounds[compound_id as usize] = None;
             } else {
                 self.compound_map
@@ -429,7 +608,8 @@ impl TermGraph {
                 .expect("inequality not there");
             if unequal_group == new_group {
                 // We found a contradiction.
-                self.contradiction = Some(value);
+                let (term1, term2, step) = value;
+                self.contradiction = Some(Conflict::Unequal(term1, term2, Disequality::Step(step)));
             }
             if !unequal_info.inequalities.contains_key(&new_group) {
                 unequal_info.inequalities.insert(new_group, value);
@@ -448,19 +628,122 @@ impl TermGraph {
             }
         }
 
+        // Terms with constructor heads tell us more about what's equal.
+        for (key, old_constructor) in old_info.constructors {
+            let new_constructors = &self.get_group_info(new_group).constructors;
+            if let Some(&new_constructor) = new_constructors.get(&key) {
+                self.decompose(old_constructor, new_constructor);
+                continue;
+            }
+            let clash = new_constructors
+                .iter()
+                .find_map(|(other_key, &other_constructor)| {
+                    let fact_id = self.distinct.get(&(key, *other_key))?;
+                    Some((other_constructor, *fact_id))
+                });
+            if let Some((other_constructor, fact_id)) = clash {
+                if self.contradiction.is_none() {
+                    self.contradiction = Some(Conflict::Unequal(
+                        old_constructor,
+                        other_constructor,
+                        Disequality::Distinct(fact_id),
+                    ));
+                }
+            }
+            self.groups[new_group as usize]
+                .as_mut()
+                .expect("group is remapped")
+                .constructors
+                .insert(key, old_constructor);
+        }
+
         self.terms[old_term as usize]
             .adjacent
-            .push((new_term, step));
+            .push((new_term, edge));
         self.terms[new_term as usize]
             .adjacent
-            .push((old_term, step));
+            .push((old_term, edge));
+
+        if self.contradiction.is_none() {
+            self.contradiction = self.find_cycle(new_group);
+        }
+    }
+
+    // Looks for a cycle of constructor applications through this group, where each one is
+    // equal to an arg of the one before it.
+    fn find_cycle(&self, start: GroupId) -> Option<Conflict> {
+        // For each group we reach, the application we reached it from, and the arg of that
+        // application that's in the group.
+        let mut parents: HashMap<GroupId, (TermId, TermId)> = HashMap::new();
+        let mut queue = vec![start];
+        let mut last_hop = None;
+        'outer: while let Some(group) = queue.pop() {
+            for (key, &app) in &self.get_group_info(group).constructors {
+                if !self.acyclic.contains_key(key) {
+                    continue;
+                }
+                let app_type = self.get_term(app).term_type;
+                for &arg in self.as_compound(app).1 {
+                    if self.get_term(arg).term_type != app_type {
+                        continue;
+                    }
+                    let arg_group = self.get_group_id(arg);
+                    if arg_group == start {
+                        last_hop = Some((app, arg));
+                        break 'outer;
+                    }
+                    if let Entry::Vacant(entry) = parents.entry(arg_group) {
+                        entry.insert((app, arg));
+                        queue.push(arg_group);
+                    }
+                }
+            }
+        }
+
+        // Walk back to the start to find the whole cycle.
+        let mut hops = vec![last_hop?];
+        let mut group = self.get_group_id(last_hop?.0);
+        while group != start {
+            let hop = parents[&group];
+            hops.push(hop);
+            group = self.get_group_id(hop.0);
+        }
+        hops.reverse();
+
+        let mut pairs = vec![];
+        let mut fact_ids = vec![];
+        for (i, (app, arg)) in hops.iter().enumerate() {
+            let (next_app, _) = hops[(i + 1) % hops.len()];
+            pairs.push((*arg, next_app));
+            let term = self.get_term(*app);
+            fact_ids.push(self.acyclic[&(*term.get_head(), term.args.len())]);
+        }
+        Some(Conflict::Cycle(pairs, fact_ids))
+    }
+
+    // The provided terms are equal applications of the same constructor.
+    // The args that the constructor is injective in must be equal too.
+    fn decompose(&mut self, term1: TermId, term2: TermId) {
+        let term = &self.terms[term1 as usize].term;
+        let key = (*term.get_head(), term.args.len());
+        if key.1 == 0 {
+            return;
+        }
+        let args1 = self.as_compound(term1).1.clone();
+        let args2 = self.as_compound(term2).1.clone();
+        for (i, (arg1, arg2)) in args1.into_iter().zip(args2).enumerate() {
+            if let Some(&fact_id) = self.injective.get(&(key, i)) {
+                self.pending
+                    .push((arg1, arg2, Edge::Injective(term1, term2, fact_id)));
+            }
+        }
     }
 
     fn process_pending(&mut self) {
-        while let Some((term1, term2, step)) = self.pending.pop() {
+        while let Some((term1, term2, edge)) = self.pending.pop() {
             // We can stop processing when we find a contradiction.
             if self.contradiction.is_none() {
-                self.set_terms_equal_once(term1, term2, step)
+                self.set_terms_equal_once(term1, term2, edge)
             }
         }
     }
@@ -468,7 +751,7 @@ impl TermGraph {
     // Set two terms to be equal.
     // Doesn't repeat to find the logical closure.
     // For that, use identify_terms.
-    fn set_terms_equal_once(&mut self, term1: TermId, term2: TermId, step: Option<RewriteStep>) {
+    fn set_terms_equal_once(&mut self, term1: TermId, term2: TermId, edge: Edge) {
         let group1 = self.get_group_id(term1);
         let group2 = self.get_group_id(term2);
         if group1 == group2 {
@@ -480,9 +763,9 @@ impl TermGraph {
 
         // Keep around the smaller number, as a tiebreak
         if (info1.heuristic_size(), group2) < (info2.heuristic_size(), group1) {
-            self.remap_group(term1, group1, term2, group2, step)
+            self.remap_group(term1, group1, term2, group2, edge)
         } else {
-            self.remap_group(term2, group2, term1, group1, step)
+            self.remap_group(term2, group2, term1, group1, edge)
         };
     }
 
@@ -497,7 +780,7 @@ impl TermGraph {
             pattern_id,
             subterm_depth,
         };
-        self.pending.push((term1, term2, Some(step)));
+        self.pending.push((term1, term2, Edge::Rewrite(step)));
         self.process_pending();
     }
 
@@ -505,7 +788,7 @@ impl TermGraph {
         let group1 = self.get_group_id(term1);
         let group2 = self.get_group_id(term2);
         if group1 == group2 {
-            self.contradiction = Some((term1, term2, step));
+            self.contradiction = Some(Conflict::Unequal(term1, term2, Disequality::Step(step)));
             return;
         }
 
@@ -533,9 +816,8 @@ impl TermGraph {
     }
 
     // Gets a step of edges that demonstrate that term1 and term2 are equal.
-    // The step is None if the edge is composite.
     // Panics if there is no path.
-    fn get_path(&self, term1: TermId, term2: TermId) -> Vec<(TermId, TermId, Option<RewriteStep>)> {
+    fn get_path(&self, term1: TermId, term2: TermId) -> Vec<(TermId, TermId, Edge)> {
         if term1 == term2 {
             return vec![];
         }
@@ -576,35 +858,41 @@ impl TermGraph {
     // id of the rule that enabled it, if there is one.
     // This is "postorder" in the sense that we show a rewritten compound term after showing
     // the rewrites for the subterms.
-    // The compound rewrites have a step id of None.
+    // The compound rewrites aren't shown themselves.
     // The rewritten subterms have a step id with the rule that they are based on.
+    // The constructor facts used along the way go in fact_ids.
     fn expand_steps(
         &self,
         term1: TermId,
         term2: TermId,
         output: &mut Vec<(Term, Term, RewriteStep)>,
+        fact_ids: &mut BTreeSet<FactId>,
     ) {
         if term1 == term2 {
             return;
         }
         let path = self.get_path(term1, term2);
-        for (a_id, b_id, step) in path {
-            if step.is_none() {
-                // We have a compound relationship between a_id and b_id
-                let (head_a, args_a) = self.as_compound(a_id);
-                let (head_b, args_b) = self.as_compound(b_id);
-                assert_eq!(args_a.len(), args_b.len());
-                self.expand_steps(head_a, head_b, output);
-                for (arg_a, arg_b) in args_a.iter().zip(args_b.iter()) {
-                    self.expand_steps(*arg_a, *arg_b, output);
+        for (a_id, b_id, edge) in path {
+            match edge {
+                Edge::Rewrite(step) => {
+                    let term_a = self.get_term(a_id);
+                    let term_b = self.get_term(b_id);
+                    output.push((term_a.clone(), term_b.clone(), step));
+                }
+                Edge::Compound => {
+                    // We have a compound relationship between a_id and b_id
+                    let (head_a, args_a) = self.as_compound(a_id);
+                    let (head_b, args_b) = self.as_compound(b_id);
+                    assert_eq!(args_a.len(), args_b.len());
+                    self.expand_steps(head_a, head_b, output, fact_ids);
+                    for (arg_a, arg_b) in args_a.iter().zip(args_b.iter()) {
+                        self.expand_steps(*arg_a, *arg_b, output, fact_ids);
+                    }
+                }
+                Edge::Injective(app_a, app_b, fact_id) => {
+                    self.expand_steps(app_a, app_b, output, fact_ids);
+                    fact_ids.insert(fact_id);
                 }
-            }
-
-            let term_a = self.get_term(a_id);
-            let term_b = self.get_term(b_id);
-
-            if let Some(step) = step {
-                output.push((term_a.clone(), term_b.clone(), step));
             }
         }
     }
@@ -614,12 +902,15 @@ impl TermGraph {
             return;
         }
         let path = self.get_path(term1, term2);
-        for (term_a, term_b, step) in path {
-            match step {
-                Some(step) => {
+        for (term_a, term_b, edge) in path {
+            match edge {
+                Edge::Rewrite(step) => {
                     output.insert(step.pattern_id);
                 }
-                None => {
+                Edge::Injective(app_a, app_b, _) => {
+                    self.get_step_ids_helper(app_a, app_b, output);
+                }
+                Edge::Compound => {
                     let (head_a, args_a) = self.as_compound(term_a);
                     let (head_b, args_b) = self.as_compound(term_b);
                     assert_eq!(args_a.len(), args_b.len());
@@ -633,6 +924,7 @@ impl TermGraph {
     }
 
     // Extract a list of steps ids that we used to prove that these two terms are equal.
+    // Constructor facts aren't included.
     // This does deduplicate.
     pub fn get_step_ids(&self, term1: TermId, term2: TermId) -> Vec<usize> {
         let mut answer = BTreeSet::new();
@@ -735,6 +1027,14 @@ impl TermGraph {
     }
 }
 
+// The variable ids of the args, if the head isn't a variable and every arg is a variable.
+fn variable_args(term: &Term) -> Option<Vec<AtomId>> {
+    if term.head.is_variable() {
+        return None;
+    }
+    term.args.iter().map(|arg| arg.atomic_variable()).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -822,4 +1122,68 @@ mod tests {
         g.set_eq(c3, c6, 3);
         assert!(g.has_contradiction());
     }
+
+    #[test]
+    fn test_injective_constructors() {
+        let mut g = TermGraph::new();
+        let fact = Clause::parse("c1(x0, x1) != c1(x2, x3) or x0 = x2");
+        assert!(g.add_constructor_fact(&fact, 7, false));
+        let term1 = g.insert_str("c1(c2, c3)");
+        let term2 = g.insert_str("c1(c4, c5)");
+        let c2 = g.get_str("c2");
+        let c3 = g.get_str("c3");
+        let c4 = g.get_str("c4");
+        let c5 = g.get_str("c5");
+        g.set_terms_not_equal(c2, c4, 0);
+        g.set_eq(term1, term2, 1);
+        g.assert_ne(c3, c5);
+        let contradiction = g.get_contradiction().unwrap();
+        assert_eq!(contradiction.inequality_id, Some(0));
+        assert_eq!(contradiction.rewrite_chain.len(), 1);
+        assert_eq!(contradiction.fact_ids, vec![7]);
+    }
+
+    #[test]
+    fn test_distinct_constructors() {
+        let mut g = TermGraph::new();
+        assert!(g.add_constructor_fact(&Clause::parse("c1 != c2(x0)"), 7, false));
+        assert!(!g.add_constructor_fact(&Clause::parse("c1 != c2(c3)"), 8, false));
+        let c1 = g.insert_str("c1");
+        let c4 = g.insert_str("c4");
+        let term = g.insert_str("c2(c3)");
+        g.set_eq(c1, c4, 0);
+        assert!(!g.has_contradiction());
+        g.set_eq(term, c4, 1);
+        let contradiction = g.get_contradiction().unwrap();
+        assert_eq!(contradiction.inequality_id, None);
+        assert_eq!(contradiction.rewrite_chain.len(), 2);
+        assert_eq!(contradiction.fact_ids, vec![7]);
+    }
+
+    #[test]
+    fn test_constructor_cycles() {
+        let fact = Clause::parse("c1(x0) != c1(x1) or x0 = x1");
+
+        // Without the type definition, a term can be equal to an application to itself.
+        let mut g = TermGraph::new();
+        assert!(g.add_constructor_fact(&fact, 7, false));
+        let c2 = g.insert_str("c2");
+        let term = g.insert_str("c1(c2)");
+        g.set_eq(c2, term, 0);
+        assert!(!g.has_contradiction());
+
+        let mut g = TermGraph::new();
+        assert!(g.add_constructor_fact(&fact, 7, true));
+        let term1 = g.insert_str("c1(c2)");
+        let term2 = g.insert_str("c1(c3)");
+        let c2 = g.get_str("c2");
+        let c3 = g.get_str("c3");
+        g.set_eq(c2, term2, 0);
+        assert!(!g.has_contradiction());
+        g.set_eq(c3, term1, 1);
+        let contradiction = g.get_contradiction().unwrap();
+        assert_eq!(contradiction.inequality_id, None);
+        assert_eq!(contradiction.rewrite_chain.len(), 2);
+        assert_eq!(contradiction.fact_ids, vec![7]);
+    }
 }
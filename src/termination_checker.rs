@@ -1,36 +1,70 @@
-use crate::acorn_value::AcornValue;
+use crate::acorn_type::AcornType;
+use crate::acorn_value::{AcornValue, ConstantInstance};
 use crate::atom::AtomId;
 use crate::module::ModuleId;
+use crate::value_visitor::{ConstantFinder, ValueVisitor};
+
+// How an argument in a recursive call compares to the original argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Descent {
+    // The argument is a strict substructure of the original argument.
+    Strict,
+
+    // The argument is the original argument itself.
+    Same,
+
+    // We don't know anything.
+    Unknown,
+}
 
 // The TerminationChecker determines whether recursive functions will always terminate,
-// because they always get called on a substructure of the input.
+// because their arguments always descend lexicographically.
+// Simple structural recursion is the special case where one argument always descends.
+//
+// It can also check a group of functions that call each other. Then the arguments are
+// compared by position, so every call between functions in the group must descend
+// lexicographically in the same order.
 pub struct TerminationChecker {
-    // The function whose definition we are checking.
+    // The functions whose definitions we are checking, with their number of arguments.
     module: ModuleId,
-    function_name: String,
+    functions: Vec<(String, usize)>,
+
+    // The number of positions that arguments are compared at.
+    num_args: usize,
+
+    // The number of arguments of the function whose definition we are traversing.
+    caller_num_args: usize,
 
     // substructure[i] = j if x_i is a ubstructure of x_j, due to match relationships.
     // j is the smallest such j.
     // This includes the non-strict substructures.
     substructure_map: Vec<Option<AtomId>>,
 
-    // always_sub[i] is true if the ith argument to the recursive function is always a *strict*
-    // substructure of the original ith argument.
-    always_strict_sub: Vec<bool>,
+    // For each recursive call, how each of its arguments compares to the original arguments.
+    calls: Vec<Vec<Descent>>,
 }
 
 impl TerminationChecker {
     pub fn new(module: ModuleId, function_name: String, num_args: usize) -> Self {
-        let substructure_map = (0..num_args).map(|i| Some(i as AtomId)).collect();
-        let always_strict_sub = vec![true; num_args];
+        TerminationChecker::new_group(module, vec![(function_name, num_args)])
+    }
+
+    pub fn new_group(module: ModuleId, functions: Vec<(String, usize)>) -> Self {
+        let num_args = functions.iter().map(|(_, n)| *n).max().unwrap_or(0);
         TerminationChecker {
             module,
-            function_name,
-            substructure_map,
-            always_strict_sub,
+            functions,
+            num_args,
+            caller_num_args: 0,
+            substructure_map: vec![],
+            calls: vec![],
         }
     }
 
+    fn is_recursive(&self, module: ModuleId, name: &str) -> bool {
+        module == self.module && self.functions.iter().any(|(f, _)| f == name)
+    }
+
     // Traverse the value, updating substructure_map and always_sub.
     fn traverse(&mut self, value: &AcornValue) {
         match value {
@@ -38,10 +72,10 @@ impl TerminationChecker {
                 // These values can't contain function calls within them, so they don't matter.
             }
             AcornValue::Constant(c) => {
-                if c.module_id == self.module && c.name == self.function_name {
+                if self.is_recursive(c.module_id, &c.name) {
                     // We are using the recursive function without calling it, so we can't
-                    // really say that any of its arguments are always strict any more.
-                    self.always_strict_sub = vec![false; self.always_strict_sub.len()];
+                    // say anything about the arguments it will get called with.
+                    self.calls.push(vec![Descent::Unknown; self.num_args]);
                 }
             }
             AcornValue::Lambda(arg_types, value)
@@ -55,6 +89,12 @@ impl TerminationChecker {
                 self.traverse(value);
                 self.substructure_map.truncate(stack_size);
             }
+            AcornValue::Choose(_, condition) => {
+                // The chosen value isn't a substructure of anything either.
+                self.substructure_map.push(None);
+                self.traverse(condition);
+                self.substructure_map.pop();
+            }
             AcornValue::Not(value) => {
                 self.traverse(value);
             }
@@ -79,26 +119,34 @@ impl TerminationChecker {
                 }
 
                 if let Some((module, name)) = app.function.as_name() {
-                    if module == self.module && name == self.function_name {
+                    if self.is_recursive(module, name) {
                         // This is a recursive call. Check the arguments for substructures.
-                        for i in 0..self.always_strict_sub.len() {
-                            if i >= app.args.len() {
+                        let mut call = vec![];
+                        for i in 0..self.num_args {
+                            if i >= app.args.len() || i >= self.caller_num_args {
                                 // This corresponds to partially binding the function arguments.
                                 // I think this is okay as long as some bound argument adheres to
                                 // the substructure argument.
-                                self.always_strict_sub[i] = false;
+                                call.push(Descent::Unknown);
                                 continue;
                             }
 
-                            let arg = &app.args[i];
-                            let strict_sub = if let AcornValue::Variable(j, _) = arg {
-                                let j = *j as usize;
-                                i != j && self.substructure_map[j] == Some(i as AtomId)
-                            } else {
-                                false
+                            let descent = match &app.args[i] {
+                                AcornValue::Variable(j, _) => {
+                                    let j = *j as usize;
+                                    if i == j {
+                                        Descent::Same
+                                    } else if self.substructure_map[j] == Some(i as AtomId) {
+                                        Descent::Strict
+                                    } else {
+                                        Descent::Unknown
+                                    }
+                                }
+                                _ => Descent::Unknown,
                             };
-                            self.always_strict_sub[i] &= strict_sub;
+                            call.push(descent);
                         }
+                        self.calls.push(call);
                     }
                 } else {
                     self.traverse(app.function.as_ref());
@@ -109,8 +157,12 @@ impl TerminationChecker {
             }
             AcornValue::Match(scrutinee, cases) => {
                 self.traverse(scrutinee);
+                // A substructure of a substructure is still a substructure of the original.
                 let superstructure = match scrutinee.as_ref() {
-                    AcornValue::Variable(i, _) => Some(*i as AtomId),
+                    AcornValue::Variable(i, _) => match self.substructure_map[*i as usize] {
+                        Some(j) => Some(j),
+                        None => Some(*i),
+                    },
                     _ => None,
                 };
                 let stack_size = self.substructure_map.len();
@@ -127,9 +179,232 @@ impl TerminationChecker {
     }
 
     pub fn check(&mut self, value: &AcornValue) -> bool {
-        self.traverse(value);
+        self.check_group(&[value])
+    }
+
+    // Checks the definitions of all the functions in the group, in the same order.
+    pub fn check_group(&mut self, values: &[&AcornValue]) -> bool {
+        for ((_, num_args), value) in self.functions.clone().iter().zip(values) {
+            self.caller_num_args = *num_args;
+            self.substructure_map = (0..*num_args).map(|i| Some(i as AtomId)).collect();
+            self.traverse(value);
+        }
+
+        // We look for an order of the arguments such that every recursive call descends
+        // lexicographically in that order.
+        // We build it greedily. Any argument that never gets bigger, and sometimes gets
+        // strictly smaller, can go next. The calls where it gets strictly smaller are then
+        // taken care of, and the rest of the calls need the remaining arguments.
+        // If any order works, picking greedily like this will find one.
+        let mut remaining: Vec<&Vec<Descent>> = self.calls.iter().collect();
+        let mut unused: Vec<usize> = (0..self.num_args).collect();
+        while !remaining.is_empty() {
+            let next = unused.iter().position(|&i| {
+                remaining.iter().all(|call| call[i] != Descent::Unknown)
+                    && remaining.iter().any(|call| call[i] == Descent::Strict)
+            });
+            let i = match next {
+                Some(position) => unused.remove(position),
+                None => return false,
+            };
+            remaining.retain(|call| call[i] != Descent::Strict);
+        }
+        true
+    }
+}
+
+// Something that we know at a particular point inside the definition of a function.
+enum Context {
+    // Variables of these types are bound here.
+    Binder(Vec<AcornType>),
+
+    // This condition is true here.
+    Condition(AcornValue),
+
+    // Variables of these types are bound here, and the scrutinee equals the pattern.
+    Case(AcornValue, Vec<AcornType>, AcornValue),
+}
+
+// The MeasureChecker builds the claim that a user-provided measure decreases with every
+// recursive call, for definitions that use "decreasing".
+// Instead of proving termination itself, it leaves that claim to the prover.
+pub struct MeasureChecker {
+    // The function whose definition we are checking.
+    module: ModuleId,
+    function_name: String,
+    num_args: usize,
+
+    // The measure, in terms of the function arguments.
+    measure: AcornValue,
+
+    // The "lt" function that compares measures.
+    less_than: AcornValue,
+
+    // What we know at the point in the definition that we are traversing.
+    context: Vec<Context>,
+
+    // The claim for each recursive call.
+    claims: Vec<AcornValue>,
+}
+
+impl MeasureChecker {
+    pub fn new(
+        module: ModuleId,
+        function_name: String,
+        num_args: usize,
+        measure: AcornValue,
+        less_than: AcornValue,
+    ) -> Self {
+        MeasureChecker {
+            module,
+            function_name,
+            num_args,
+            measure,
+            less_than,
+            context: vec![],
+            claims: vec![],
+        }
+    }
+
+    fn is_function(&self, c: &ConstantInstance) -> bool {
+        c.module_id == self.module && c.name == self.function_name
+    }
+
+    fn mentions_function(&self, value: &AcornValue) -> bool {
+        let mut output = vec![];
+        let mut finder = ConstantFinder {
+            filter: |c: &ConstantInstance| self.is_function(c),
+            output: &mut output,
+        };
+        finder.visit_value(value, 0);
+        !output.is_empty()
+    }
 
-        // If any of the arguments is always a substructure, that is a proof of termination.
-        self.always_strict_sub.contains(&true)
+    // The claim that the measure decreases for a recursive call with these arguments,
+    // made at the current point in the definition.
+    fn add_claim(&mut self, args: &[AcornValue], stack_size: AtomId) {
+        // The measure might have binders of its own, which need to move past the stack.
+        let smaller = self
+            .measure
+            .clone()
+            .insert_stack(self.num_args as AtomId, stack_size)
+            .bind_values(0, 0, args);
+        let mut claim =
+            AcornValue::new_apply(self.less_than.clone(), vec![smaller, self.measure.clone()]);
+        for context in self.context.iter().rev() {
+            claim = match context {
+                Context::Binder(types) => AcornValue::new_forall(types.clone(), claim),
+                Context::Condition(condition) => AcornValue::new_implies(condition.clone(), claim),
+                Context::Case(scrutinee, types, pattern) => AcornValue::new_forall(
+                    types.clone(),
+                    AcornValue::new_implies(
+                        AcornValue::new_equals(scrutinee.clone(), pattern.clone()),
+                        claim,
+                    ),
+                ),
+            };
+        }
+        self.claims.push(claim);
+    }
+
+    // Returns false if the function is used in a way that we can't make claims about.
+    fn traverse_with(&mut self, context: Context, value: &AcornValue, stack_size: AtomId) -> bool {
+        self.context.push(context);
+        let answer = self.traverse(value, stack_size);
+        self.context.pop();
+        answer
+    }
+
+    // Returns false if the function is used in a way that we can't make claims about.
+    fn traverse(&mut self, value: &AcornValue, stack_size: AtomId) -> bool {
+        match value {
+            AcornValue::Variable(..) | AcornValue::Bool(..) => true,
+            AcornValue::Constant(c) => {
+                // Using the recursive function without calling it is too hard to check.
+                !self.is_function(c)
+            }
+            AcornValue::Lambda(arg_types, value)
+            | AcornValue::ForAll(arg_types, value)
+            | AcornValue::Exists(arg_types, value) => self.traverse_with(
+                Context::Binder(arg_types.clone()),
+                value,
+                stack_size + arg_types.len() as AtomId,
+            ),
+            AcornValue::Choose(choice_type, condition) => self.traverse_with(
+                Context::Binder(vec![choice_type.clone()]),
+                condition,
+                stack_size + 1,
+            ),
+            AcornValue::Not(value) => self.traverse(value, stack_size),
+            AcornValue::Binary(_, left, right) => {
+                self.traverse(left, stack_size) && self.traverse(right, stack_size)
+            }
+            AcornValue::IfThenElse(condition, if_value, else_value) => {
+                if self.mentions_function(condition) {
+                    return false;
+                }
+                let negated = AcornValue::Not(Box::new(condition.as_ref().clone()));
+                self.traverse_with(
+                    Context::Condition(condition.as_ref().clone()),
+                    if_value,
+                    stack_size,
+                ) && self.traverse_with(Context::Condition(negated), else_value, stack_size)
+            }
+            AcornValue::Application(app) => {
+                if let AcornValue::Application(subapp) = &*app.function {
+                    // This is a curried function call, so uncurry it.
+                    let combined_args =
+                        subapp.args.iter().chain(app.args.iter()).cloned().collect();
+                    let uncurried = AcornValue::new_apply(*subapp.function.clone(), combined_args);
+                    return self.traverse(&uncurried, stack_size);
+                }
+                if let AcornValue::Constant(c) = app.function.as_ref() {
+                    if self.is_function(c) {
+                        if app.args.len() != self.num_args {
+                            return false;
+                        }
+                        self.add_claim(&app.args, stack_size);
+                    } else if !self.traverse(&app.function, stack_size) {
+                        return false;
+                    }
+                } else if !self.traverse(&app.function, stack_size) {
+                    return false;
+                }
+                app.args.iter().all(|arg| self.traverse(arg, stack_size))
+            }
+            AcornValue::Match(scrutinee, cases) => {
+                if self.mentions_function(scrutinee) {
+                    return false;
+                }
+                cases.iter().all(|(new_vars, pattern, result)| {
+                    self.traverse_with(
+                        Context::Case(
+                            scrutinee.as_ref().clone(),
+                            new_vars.clone(),
+                            pattern.clone(),
+                        ),
+                        result,
+                        stack_size + new_vars.len() as AtomId,
+                    )
+                })
+            }
+        }
+    }
+
+    // Returns the claim that the measure decreases on every recursive call, in terms of
+    // the function arguments.
+    // The conditions and match cases that lead to each call are hypotheses of its claim.
+    // Returns None if the function is used in a way we can't make claims about, like
+    // passing it around without calling it, or calling it in a condition.
+    pub fn check(&mut self, value: &AcornValue) -> Option<AcornValue> {
+        if !self.traverse(value, self.num_args as AtomId) {
+            return None;
+        }
+        let claim = self
+            .claims
+            .drain(..)
+            .reduce(AcornValue::new_and)
+            .unwrap_or(AcornValue::Bool(true));
+        Some(claim)
     }
 }
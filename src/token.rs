@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::iter::Peekable;
 use std::sync::Arc;
@@ -7,7 +8,7 @@ use tower_lsp::lsp_types::{Position, Range, SemanticTokenType};
 
 use crate::compilation::{Error, ErrorSource, Result};
 
-#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum TokenType {
     Identifier,
     Invalid,
@@ -64,6 +65,33 @@ pub enum TokenType {
     Constraint,
     Implies,
     Typeclass,
+    At,
+    StringLiteral,
+    Strings,
+    LeftBracket,
+    RightBracket,
+    Lists,
+    Decimals,
+    Decreasing,
+    Opaque,
+    Lemma,
+    Assume,
+    Suffices,
+    ByContradiction,
+    Rewrite,
+    In,
+    Have,
+    Where,
+    Unfold,
+    Pipe,
+    Coercion,
+    PlusPlus,
+    Ring,
+    PipeArrow,
+    Export,
+    Module,
+    Shadow,
+    Choose,
 }
 
 // Add a new token here if there's an alphabetical name for it.
@@ -88,7 +116,25 @@ pub fn keyword_map() -> &'static BTreeMap<&'static str, TokenType> {
             ("else", TokenType::Else),
             ("class", TokenType::Class),
             ("numerals", TokenType::Numerals),
+            ("strings", TokenType::Strings),
+            ("lists", TokenType::Lists),
+            ("decimals", TokenType::Decimals),
+            ("decreasing", TokenType::Decreasing),
+            ("opaque", TokenType::Opaque),
+            ("lemma", TokenType::Lemma),
+            ("assume", TokenType::Assume),
+            ("suffices", TokenType::Suffices),
+            ("by_contradiction", TokenType::ByContradiction),
+            ("rewrite", TokenType::Rewrite),
+            ("in", TokenType::In),
+            ("have", TokenType::Have),
+            ("where", TokenType::Where),
+            ("unfold", TokenType::Unfold),
+            ("coercion", TokenType::Coercion),
             ("from", TokenType::From),
+            ("export", TokenType::Export),
+            ("module", TokenType::Module),
+            ("shadow", TokenType::Shadow),
             ("solve", TokenType::Solve),
             ("problem", TokenType::Problem),
             ("satisfy", TokenType::Satisfy),
@@ -102,6 +148,7 @@ pub fn keyword_map() -> &'static BTreeMap<&'static str, TokenType> {
             ("constraint", TokenType::Constraint),
             ("implies", TokenType::Implies),
             ("typeclass", TokenType::Typeclass),
+            ("choose", TokenType::Choose),
         ])
     })
 }
@@ -121,11 +168,15 @@ pub const LSP_TOKEN_TYPES: &[SemanticTokenType] = &[
     SemanticTokenType::KEYWORD,
     SemanticTokenType::OPERATOR,
     SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
 ];
 
 // Infix operators are represented by a "magic method", where you implement a method
 // with that name, and then the infix operator with this token can be used to invoke that method.
 // The term "magic method", along with this general idea, are from Python.
+// Some of these operators, like ++, have no meaning for any built-in type. They are only
+// usable for classes that define the corresponding method, and that method can declare the
+// operator's precedence with an attribute, like "@precedence(10)".
 const INFIX_MAGIC_METHODS: &[(&str, TokenType)] = &[
     ("gt", TokenType::GreaterThan),
     ("lt", TokenType::LessThan),
@@ -136,11 +187,18 @@ const INFIX_MAGIC_METHODS: &[(&str, TokenType)] = &[
     ("mul", TokenType::Asterisk),
     ("mod", TokenType::Percent),
     ("div", TokenType::Slash),
+    ("concat", TokenType::PlusPlus),
+    ("compose", TokenType::Ring),
+    ("pipe", TokenType::PipeArrow),
 ];
 
 // Prefix operators.
 const PREFIX_MAGIC_METHODS: &[(&str, TokenType)] = &[("neg", TokenType::Minus)];
 
+// The precedences that can be declared for a custom operator.
+// They bind tighter than the logical operators, and looser than dot.
+pub const CUSTOM_PRECEDENCES: std::ops::RangeInclusive<i8> = 6..=12;
+
 impl TokenType {
     pub fn is_unary(&self) -> bool {
         match self {
@@ -171,6 +229,9 @@ impl TokenType {
             TokenType::Percent => true,
             TokenType::Slash => true,
             TokenType::Implies => true,
+            TokenType::PlusPlus => true,
+            TokenType::Ring => true,
+            TokenType::PipeArrow => true,
             _ => false,
         }
     }
@@ -188,14 +249,18 @@ impl TokenType {
     // Operators that are not allowed in an expression have a precedence of 0.
     // "Value" expressions also include "declarations" which is why colons are allowed.
     // Function application implicitly has the same precedence as dot.
+    // For custom operators, this is only the default, used when no precedence is declared.
     pub fn binary_precedence(&self) -> i8 {
         match self {
             TokenType::Dot => 13,
+            TokenType::Ring => 11,
             TokenType::Asterisk => 11,
             TokenType::Slash => 11,
             TokenType::Plus => 10,
             TokenType::Minus => 10,
+            TokenType::PlusPlus => 10,
             TokenType::Percent => 9,
+            TokenType::PipeArrow => 9,
             TokenType::GreaterThan => 8,
             TokenType::LessThan => 8,
             TokenType::GreaterThanOrEquals => 8,
@@ -249,6 +314,14 @@ impl TokenType {
         }
     }
 
+    // Custom operators have no built-in meaning, so their precedence can be declared.
+    pub fn is_custom_operator(&self) -> bool {
+        matches!(
+            self,
+            TokenType::PlusPlus | TokenType::Ring | TokenType::PipeArrow
+        )
+    }
+
     // Converts a token to its infix magic method name, if it has one.
     pub fn to_infix_magic_method_name(&self) -> Option<&str> {
         for (name, token_type) in INFIX_MAGIC_METHODS {
@@ -303,6 +376,7 @@ impl TokenType {
             line_number: 0,
             start: 0,
             len,
+            precedence: None,
         }
     }
 
@@ -357,6 +431,7 @@ impl TokenType {
             TokenType::Solve => "solve",
             TokenType::Problem => "problem",
             TokenType::Satisfy => "satisfy",
+            TokenType::Choose => "choose",
             TokenType::SelfToken => "self",
             TokenType::Inductive => "inductive",
             TokenType::Match => "match",
@@ -364,6 +439,32 @@ impl TokenType {
             TokenType::Constraint => "constraint",
             TokenType::Implies => "implies",
             TokenType::Typeclass => "typeclass",
+            TokenType::At => "@",
+            TokenType::StringLiteral => "<string>",
+            TokenType::Strings => "strings",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Lists => "lists",
+            TokenType::Decimals => "decimals",
+            TokenType::Decreasing => "decreasing",
+            TokenType::Opaque => "opaque",
+            TokenType::Lemma => "lemma",
+            TokenType::Assume => "assume",
+            TokenType::Suffices => "suffices",
+            TokenType::ByContradiction => "by_contradiction",
+            TokenType::Rewrite => "rewrite",
+            TokenType::In => "in",
+            TokenType::Have => "have",
+            TokenType::Where => "where",
+            TokenType::Unfold => "unfold",
+            TokenType::Pipe => "|",
+            TokenType::Coercion => "coercion",
+            TokenType::PlusPlus => "++",
+            TokenType::Ring => "∘",
+            TokenType::PipeArrow => "|>",
+            TokenType::Export => "export",
+            TokenType::Module => "module",
+            TokenType::Shadow => "shadow",
         }
     }
 
@@ -374,6 +475,7 @@ impl TokenType {
             TokenType::Invalid => "invalid".to_string(),
             TokenType::NewLine => "newline".to_string(),
             TokenType::Numeral => "number".to_string(),
+            TokenType::StringLiteral => "string".to_string(),
             _ => format!("\"{}\"", self.to_str()),
         }
     }
@@ -398,6 +500,9 @@ pub struct Token {
 
     // The length of this token.
     pub len: u32,
+
+    // The declared precedence, for a custom operator whose precedence has been declared.
+    pub precedence: Option<i8>,
 }
 
 impl fmt::Display for Token {
@@ -421,6 +526,7 @@ impl Token {
             line_number: 0,
             start: 0,
             len: 0,
+            precedence: None,
         }
     }
 
@@ -453,17 +559,105 @@ impl Token {
     }
 
     pub fn binary_precedence(&self) -> i8 {
-        self.token_type.binary_precedence()
+        self.precedence
+            .unwrap_or_else(|| self.token_type.binary_precedence())
+    }
+
+    // Uses a declared precedence for this token, if there is one for its type.
+    pub fn with_precedence(mut self, precedences: &BTreeMap<TokenType, i8>) -> Token {
+        if let Some(precedence) = precedences.get(&self.token_type) {
+            self.precedence = Some(*precedence);
+        }
+        self
     }
 
     pub fn unary_precedence(&self) -> i8 {
         self.token_type.unary_precedence()
     }
 
+    // Doc comments start with three slashes, like "/// this is a doc comment".
+    // They are scanned as newlines like other comments.
+    // Returns the text of the comment, without the slashes, if this is a doc comment.
+    pub fn doc_comment(&self) -> Option<&str> {
+        if self.token_type != TokenType::NewLine {
+            return None;
+        }
+        let text = self.text().strip_prefix("///")?;
+        Some(text.strip_prefix(' ').unwrap_or(text))
+    }
+
+    // Returns the contents of a string literal, without the quotes and with escapes resolved.
+    // Supports \", \\, and \n. Returns an error for other escapes.
+    pub fn string_contents(&self) -> Result<String> {
+        let text = self.text();
+        if self.token_type != TokenType::StringLiteral || text.len() < 2 {
+            return Err(self.error("expected a string literal"));
+        }
+        let mut answer = String::new();
+        let mut chars = text[1..text.len() - 1].chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                answer.push(ch);
+                continue;
+            }
+            match chars.next() {
+                Some('"') => answer.push('"'),
+                Some('\\') => answer.push('\\'),
+                Some('n') => answer.push('\n'),
+                _ => return Err(self.error("unsupported escape in string literal")),
+            }
+        }
+        Ok(answer)
+    }
+
+    // The inverse of string_contents. Creates a string literal token with these contents.
+    pub fn generate_string_literal(contents: &str) -> Token {
+        let mut text = String::from("\"");
+        for ch in contents.chars() {
+            match ch {
+                '"' => text.push_str("\\\""),
+                '\\' => text.push_str("\\\\"),
+                '\n' => text.push_str("\\n"),
+                _ => text.push(ch),
+            }
+        }
+        text.push('"');
+        TokenType::StringLiteral.new_token(&text)
+    }
+
     pub fn identifierish(ch: char) -> bool {
         ch.is_alphanumeric() || ch == '_'
     }
 
+    // For the text of a hexadecimal or binary numeral, like 0xff or 0b1010,
+    // returns the radix and the digits after the prefix.
+    // Returns None for decimal numerals.
+    pub fn radix_digits(text: &str) -> Option<(u32, &str)> {
+        if let Some(digits) = text.strip_prefix("0x") {
+            Some((16, digits))
+        } else if let Some(digits) = text.strip_prefix("0b") {
+            Some((2, digits))
+        } else {
+            None
+        }
+    }
+
+    // For the text of a decimal numeral with a decimal point, like 3.14,
+    // returns the digits before and after the point.
+    pub fn decimal_parts(text: &str) -> Option<(&str, &str)> {
+        text.split_once('.')
+    }
+
+    // The hook used to read hexadecimal or binary numerals, for a given radix.
+    // It works like "read", but appends a digit in the given base.
+    pub fn radix_hook(radix: u32) -> &'static str {
+        if radix == 16 {
+            "read_hex"
+        } else {
+            "read_binary"
+        }
+    }
+
     pub fn lsp_type(&self) -> Option<SemanticTokenType> {
         match self.token_type {
             TokenType::Identifier => Some(SemanticTokenType::VARIABLE),
@@ -480,7 +674,11 @@ impl Token {
             | TokenType::Minus
             | TokenType::Asterisk
             | TokenType::Percent
-            | TokenType::Slash => Some(SemanticTokenType::OPERATOR),
+            | TokenType::Slash
+            | TokenType::Pipe
+            | TokenType::PlusPlus
+            | TokenType::Ring
+            | TokenType::PipeArrow => Some(SemanticTokenType::OPERATOR),
 
             TokenType::Let
             | TokenType::Axiom
@@ -499,10 +697,29 @@ impl Token {
             | TokenType::Else
             | TokenType::Class
             | TokenType::Numerals
+            | TokenType::Strings
+            | TokenType::Lists
+            | TokenType::Decimals
+            | TokenType::Decreasing
+            | TokenType::Opaque
+            | TokenType::Lemma
+            | TokenType::Assume
+            | TokenType::Suffices
+            | TokenType::ByContradiction
+            | TokenType::Rewrite
+            | TokenType::In
+            | TokenType::Have
+            | TokenType::Where
+            | TokenType::Unfold
+            | TokenType::Coercion
             | TokenType::From
+            | TokenType::Export
+            | TokenType::Module
+            | TokenType::Shadow
             | TokenType::Solve
             | TokenType::Problem
             | TokenType::Satisfy
+            | TokenType::Choose
             | TokenType::Not
             | TokenType::Or
             | TokenType::And
@@ -512,7 +729,8 @@ impl Token {
             | TokenType::Todo
             | TokenType::Constraint
             | TokenType::Implies
-            | TokenType::Typeclass => Some(SemanticTokenType::KEYWORD),
+            | TokenType::Typeclass
+            | TokenType::At => Some(SemanticTokenType::KEYWORD),
 
             TokenType::NewLine => {
                 // Comments are encoded as newlines because syntactically they act like newlines.
@@ -525,10 +743,14 @@ impl Token {
 
             TokenType::Numeral => Some(SemanticTokenType::NUMBER),
 
+            TokenType::StringLiteral => Some(SemanticTokenType::STRING),
+
             TokenType::Comma
             | TokenType::Invalid
             | TokenType::LeftParen
             | TokenType::RightParen
+            | TokenType::LeftBracket
+            | TokenType::RightBracket
             | TokenType::LeftBrace
             | TokenType::RightBrace
             | TokenType::Colon
@@ -559,6 +781,8 @@ impl Token {
                     '\t' => continue,
                     '(' => TokenType::LeftParen,
                     ')' => TokenType::RightParen,
+                    '[' => TokenType::LeftBracket,
+                    ']' => TokenType::RightBracket,
                     '{' => TokenType::LeftBrace,
                     '}' => TokenType::RightBrace,
                     '\n' => TokenType::NewLine,
@@ -570,9 +794,18 @@ impl Token {
                         None => TokenType::Invalid,
                     },
                     '=' => TokenType::Equals,
-                    '+' => TokenType::Plus,
+                    '+' => match char_indices.next_if_eq(&(char_index + 1, '+')) {
+                        Some(_) => TokenType::PlusPlus,
+                        None => TokenType::Plus,
+                    },
                     '*' => TokenType::Asterisk,
                     '%' => TokenType::Percent,
+                    '∘' => TokenType::Ring,
+                    '|' => match char_indices.next_if_eq(&(char_index + 1, '>')) {
+                        Some(_) => TokenType::PipeArrow,
+                        None => TokenType::Pipe,
+                    },
+                    '@' => TokenType::At,
                     '-' => match char_indices.next_if_eq(&(char_index + 1, '>')) {
                         Some(_) => TokenType::RightArrow,
                         None => TokenType::Minus,
@@ -603,12 +836,70 @@ impl Token {
                         }
                         None => TokenType::Slash,
                     },
+                    '"' => {
+                        // A string literal continues until an unescaped quote.
+                        // It can't span multiple lines.
+                        let mut terminated = false;
+                        while let Some((_, ch)) = char_indices.next() {
+                            match ch {
+                                '"' => {
+                                    terminated = true;
+                                    break;
+                                }
+                                '\\' => {
+                                    char_indices.next();
+                                }
+                                _ => {}
+                            }
+                        }
+                        if terminated {
+                            TokenType::StringLiteral
+                        } else {
+                            TokenType::Invalid
+                        }
+                    }
+                    '0' if matches!(char_indices.peek(), Some((_, 'x' | 'b'))) => {
+                        // A hexadecimal or binary numeral.
+                        let (_, prefix) = char_indices.next().unwrap();
+                        let radix = if prefix == 'x' { 16 } else { 2 };
+                        let mut num_digits = 0;
+                        let mut valid = true;
+                        loop {
+                            match char_indices.peek() {
+                                Some((_, ch)) if Token::identifierish(*ch) => {
+                                    valid &= ch.is_digit(radix);
+                                    num_digits += 1;
+                                    char_indices.next();
+                                }
+                                _ => break,
+                            }
+                        }
+                        if valid && num_digits > 0 {
+                            TokenType::Numeral
+                        } else {
+                            TokenType::Invalid
+                        }
+                    }
                     t if t.is_ascii_digit() => {
+                        let mut seen_point = false;
                         loop {
                             match char_indices.peek() {
                                 Some((_, ch)) if ch.is_ascii_digit() => {
                                     char_indices.next();
                                 }
+                                Some((_, '.')) if !seen_point => {
+                                    // A decimal point only counts if a digit comes after it.
+                                    // Otherwise, the dot is for member access, like 1.suc.
+                                    let mut lookahead = char_indices.clone();
+                                    lookahead.next();
+                                    match lookahead.peek() {
+                                        Some((_, ch)) if ch.is_ascii_digit() => {
+                                            char_indices.next();
+                                            seen_point = true;
+                                        }
+                                        _ => break,
+                                    }
+                                }
                                 _ => break,
                             }
                         }
@@ -643,6 +934,7 @@ impl Token {
                     line_number,
                     start: char_index as u32,
                     len: (end - char_index) as u32,
+                    precedence: None,
                 };
                 tokens.push(token);
             }
@@ -654,6 +946,7 @@ impl Token {
                 line_number,
                 start: line.len() as u32,
                 len: 0,
+                precedence: None,
             });
         }
 
@@ -699,6 +992,10 @@ pub struct TokenIter {
     inner: Peekable<IntoIter<Token>>,
 
     last: Token,
+
+    // The precedences declared for custom operators so far.
+    // Tokens get their declared precedence as they are popped off.
+    precedences: BTreeMap<TokenType, i8>,
 }
 
 impl TokenIter {
@@ -707,9 +1004,15 @@ impl TokenIter {
         TokenIter {
             inner: tokens.into_iter().peekable(),
             last,
+            precedences: BTreeMap::new(),
         }
     }
 
+    // Declares the precedence of a custom operator, for the rest of the tokens.
+    pub fn declare_precedence(&mut self, token_type: TokenType, precedence: i8) {
+        self.precedences.insert(token_type, precedence);
+    }
+
     pub fn peek(&mut self) -> Option<&Token> {
         self.inner.peek()
     }
@@ -718,8 +1021,17 @@ impl TokenIter {
         self.peek().map(|t| t.token_type)
     }
 
+    // Looks further ahead than peek, without consuming anything.
+    // peek_type_at(0) is the same as peek_type.
+    pub fn peek_type_at(&self, n: usize) -> Option<TokenType> {
+        self.inner.clone().nth(n).map(|t| t.token_type)
+    }
+
     pub fn next(&mut self) -> Option<Token> {
-        self.inner.next()
+        let precedences = &self.precedences;
+        self.inner
+            .next()
+            .map(|token| token.with_precedence(precedences))
     }
 
     pub fn error(&mut self, message: &str) -> Error {
@@ -812,4 +1124,76 @@ mod tests {
         assert_eq!(tokens[3].token_type, TokenType::Axiom);
         assert_eq!(tokens[4].token_type, TokenType::NewLine);
     }
+
+    #[test]
+    fn test_doc_comments() {
+        let tokens = Token::scan("/// The natural numbers.\n// Not a doc comment\ntype Nat: axiom");
+        assert_eq!(tokens[0].doc_comment(), Some("The natural numbers."));
+        assert_eq!(tokens[1].doc_comment(), None);
+        assert_eq!(tokens[2].doc_comment(), None);
+        assert_eq!(tokens[3].doc_comment(), None);
+    }
+
+    #[test]
+    fn test_custom_operators() {
+        let tokens = Token::scan("a ++ b ∘ c |> d + e | f");
+        let types: Vec<_> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Identifier,
+                TokenType::PlusPlus,
+                TokenType::Identifier,
+                TokenType::Ring,
+                TokenType::Identifier,
+                TokenType::PipeArrow,
+                TokenType::Identifier,
+                TokenType::Plus,
+                TokenType::Identifier,
+                TokenType::Pipe,
+                TokenType::Identifier,
+                TokenType::NewLine,
+            ]
+        );
+        assert_eq!(tokens[3].text(), "∘");
+    }
+
+    #[test]
+    fn test_hex_and_binary_numerals() {
+        let tokens = Token::scan("0xff + 0b1010 + 0x0A");
+        assert_eq!(tokens[0].token_type, TokenType::Numeral);
+        assert_eq!(tokens[0].text(), "0xff");
+        assert_eq!(tokens[2].token_type, TokenType::Numeral);
+        assert_eq!(tokens[2].text(), "0b1010");
+        assert_eq!(tokens[4].text(), "0x0A");
+        assert!(Token::has_invalid_token(&Token::scan("0x")));
+        assert!(Token::has_invalid_token(&Token::scan("0b102")));
+        assert!(Token::has_invalid_token(&Token::scan("0xfg")));
+        assert_eq!(Token::radix_digits("0xff"), Some((16, "ff")));
+        assert_eq!(Token::radix_digits("0b10"), Some((2, "10")));
+        assert_eq!(Token::radix_digits("10"), None);
+    }
+
+    #[test]
+    fn test_decimal_numerals() {
+        let tokens = Token::scan("3.14 + 1.suc + Nat.0.suc");
+        let texts: Vec<_> = tokens.iter().map(|t| t.text()).collect();
+        assert_eq!(
+            texts,
+            vec!["3.14", "+", "1", ".", "suc", "+", "Nat", ".", "0", ".", "suc", ""]
+        );
+        assert_eq!(tokens[0].token_type, TokenType::Numeral);
+        assert_eq!(Token::decimal_parts("3.14"), Some(("3", "14")));
+        assert_eq!(Token::decimal_parts("314"), None);
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let tokens = Token::scan(r#"let s = "a \"b\" c\\""#);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[3].token_type, TokenType::StringLiteral);
+        assert_eq!(tokens[3].string_contents().unwrap(), r#"a "b" c\"#);
+        assert!(Token::has_invalid_token(&Token::scan(r#"let s = "abc"#)));
+        assert!(Token::scan(r#""\q""#)[0].string_contents().is_err());
+    }
 }
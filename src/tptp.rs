@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::atom::{Atom, AtomId};
+use crate::clause::Clause;
+use crate::common::run_with_timeout;
+use crate::literal::Literal;
+use crate::term::Term;
+use crate::type_map::{TypeId, BOOL};
+
+// How to run an external first-order prover.
+// The prover reads a TPTP problem from stdin, and reports the result in SZS format.
+#[derive(Clone, Debug)]
+pub struct AtpConfig {
+    pub command: String,
+    pub args: Vec<String>,
+
+    // The prover process is killed when it runs longer than this.
+    pub timeout: Duration,
+}
+
+impl Default for AtpConfig {
+    fn default() -> AtpConfig {
+        AtpConfig {
+            command: "eprover".to_string(),
+            args: vec!["--auto".to_string(), "--proof-object".to_string()],
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl AtpConfig {
+    pub fn vampire() -> AtpConfig {
+        AtpConfig {
+            command: "vampire".to_string(),
+            args: vec!["--mode".to_string(), "casc".to_string()],
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AtpResult {
+    // The clauses are contradictory. These are the ids of the clauses the proof used.
+    // If the prover didn't print a proof, it's every clause in the problem.
+    Proved(Vec<usize>),
+
+    // The prover showed that the clauses are consistent.
+    Disproved,
+
+    // The prover gave up, timed out, or couldn't be run. The string says why.
+    Unknown(String),
+}
+
+// A set of clauses, translated into TPTP's typed first-order form.
+// Clauses that aren't first-order, like ones that apply a variable as a function or pass a
+// boolean as an argument, are left out. Leaving out clauses never makes a proof unsound.
+// Like the SMT translation, each atom applied to a particular number of arguments is its
+// own symbol.
+#[derive(Default)]
+pub struct TptpProblem {
+    // The type declaration for each symbol, keyed by its name.
+    symbols: BTreeMap<String, String>,
+    types: BTreeMap<TypeId, String>,
+    formulas: Vec<String>,
+    ids: Vec<usize>,
+}
+
+impl TptpProblem {
+    pub fn new() -> TptpProblem {
+        TptpProblem::default()
+    }
+
+    // Adds a clause under the given id.
+    // The negated goal should be marked as a conjecture, so that the prover can focus on it.
+    // Returns false if the clause can't be translated.
+    pub fn add_clause(&mut self, id: usize, clause: &Clause, conjecture: bool) -> bool {
+        let mut vars = BTreeMap::new();
+        let mut literals = vec![];
+        for literal in &clause.literals {
+            match self.literal(literal, &mut vars) {
+                Some(s) => literals.push(s),
+                None => return false,
+            }
+        }
+        let mut body = if literals.is_empty() {
+            "$false".to_string()
+        } else {
+            literals.join(" | ")
+        };
+        if !vars.is_empty() {
+            let bindings: Vec<String> = vars
+                .iter()
+                .map(|(i, type_id)| format!("X{}: {}", i, self.type_name(*type_id)))
+                .collect();
+            body = format!("![{}]: ({})", bindings.join(", "), body);
+        }
+        let role = if conjecture {
+            "negated_conjecture"
+        } else {
+            "axiom"
+        };
+        self.formulas
+            .push(format!("tff({}, {}, {}).", clause_name(id), role, body));
+        self.ids.push(id);
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.formulas.is_empty()
+    }
+
+    fn type_name(&mut self, type_id: TypeId) -> String {
+        if type_id == BOOL {
+            return "$o".to_string();
+        }
+        self.types
+            .entry(type_id)
+            .or_insert_with(|| format!("t{}", type_id))
+            .clone()
+    }
+
+    fn literal(
+        &mut self,
+        literal: &Literal,
+        vars: &mut BTreeMap<AtomId, TypeId>,
+    ) -> Option<String> {
+        let sign = if literal.positive { "" } else { "~" };
+        let left = self.term(&literal.left, vars)?;
+        if literal.right.is_true() {
+            return Some(format!("{}{}", sign, left));
+        }
+        let right = self.term(&literal.right, vars)?;
+        if literal.left.term_type == BOOL {
+            if literal.left.is_variable() || literal.right.is_variable() {
+                // Quantifying over booleans isn't first-order.
+                return None;
+            }
+            return Some(format!("{}({} <=> {})", sign, left, right));
+        }
+        let op = if literal.positive { "=" } else { "!=" };
+        Some(format!("{} {} {}", left, op, right))
+    }
+
+    fn term(&mut self, term: &Term, vars: &mut BTreeMap<AtomId, TypeId>) -> Option<String> {
+        if let Atom::Variable(i) = term.head {
+            if !term.args.is_empty() {
+                return None;
+            }
+            vars.insert(i, term.term_type);
+            return Some(format!("X{}", i));
+        }
+        if term.head == Atom::True {
+            return Some("$true".to_string());
+        }
+        let name = format!("{}_{}", term.head, term.args.len());
+        if !self.symbols.contains_key(&name) {
+            let mut arg_types = vec![];
+            for arg in &term.args {
+                if arg.term_type == BOOL {
+                    return None;
+                }
+                arg_types.push(self.type_name(arg.term_type));
+            }
+            let result_type = self.type_name(term.term_type);
+            let signature = match arg_types.len() {
+                0 => result_type,
+                1 => format!("{} > {}", arg_types[0], result_type),
+                _ => format!("({}) > {}", arg_types.join(" * "), result_type),
+            };
+            self.symbols.insert(
+                name.clone(),
+                format!("tff(decl_{}, type, {}: {}).", name, name, signature),
+            );
+        }
+        if term.args.is_empty() {
+            return Some(name);
+        }
+        let mut args = vec![];
+        for arg in &term.args {
+            args.push(self.term(arg, vars)?);
+        }
+        Some(format!("{}({})", name, args.join(", ")))
+    }
+
+    pub fn to_tptp(&self) -> String {
+        let mut answer = String::new();
+        for (type_id, name) in &self.types {
+            writeln!(answer, "tff(type_{}, type, {}: $tType).", type_id, name).unwrap();
+        }
+        for declaration in self.symbols.values() {
+            writeln!(answer, "{}", declaration).unwrap();
+        }
+        for formula in &self.formulas {
+            writeln!(answer, "{}", formula).unwrap();
+        }
+        answer
+    }
+
+    // Runs the prover on this problem.
+    pub fn solve(&self, config: &AtpConfig) -> AtpResult {
+        match run_with_timeout(
+            &config.command,
+            &config.args,
+            &self.to_tptp(),
+            config.timeout,
+        ) {
+            Ok(output) => self.parse_output(&output),
+            Err(e) => AtpResult::Unknown(e),
+        }
+    }
+
+    // Interprets the SZS status line, and the proof if there is one.
+    fn parse_output(&self, output: &str) -> AtpResult {
+        let Some(status) = output.lines().find_map(|line| {
+            let (_, rest) = line.split_once("SZS status ")?;
+            rest.split_whitespace().next()
+        }) else {
+            return AtpResult::Unknown("no SZS status".to_string());
+        };
+        match status {
+            "Theorem" | "Unsatisfiable" | "ContradictoryAxioms" => {}
+            "CounterSatisfiable" | "Satisfiable" => return AtpResult::Disproved,
+            _ => return AtpResult::Unknown(status.to_string()),
+        }
+
+        // Look for our clause names within the proof.
+        let proof = match output.split_once("SZS output start") {
+            Some((_, proof)) => proof,
+            None => return AtpResult::Proved(self.ids.clone()),
+        };
+        let used: Vec<usize> = self
+            .ids
+            .iter()
+            .copied()
+            .filter(|id| mentions(proof, &clause_name(*id)))
+            .collect();
+        if used.is_empty() {
+            AtpResult::Proved(self.ids.clone())
+        } else {
+            AtpResult::Proved(used)
+        }
+    }
+}
+
+fn clause_name(id: usize) -> String {
+    format!("clause{}", id)
+}
+
+// Whether the text contains this name as a whole word.
+fn mentions(text: &str, name: &str) -> bool {
+    text.match_indices(name).any(|(i, _)| {
+        let next = text[i + name.len()..].chars().next();
+        !matches!(next, Some(c) if c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tptp_translation() {
+        let mut problem = TptpProblem::new();
+        assert!(problem.add_clause(1, &Clause::parse("c0(x0) = c1"), false));
+        assert!(problem.add_clause(12, &Clause::parse("c0(c2) != c1"), true));
+        let text = problem.to_tptp();
+        assert!(text.contains("tff(decl_c0_1, type, c0_1: t0 > t0)."));
+        assert!(text.contains("tff(clause1, axiom, ![X0: t0]: (c1_0 = c0_1(X0)))."));
+        assert!(text.contains("tff(clause12, negated_conjecture, c0_1(c2_0) != c1_0)."));
+
+        let output = "# SZS status Unsatisfiable\n\
+                      # SZS output start CNFRefutation\n\
+                      cnf(c_0_2, negated_conjecture, ..., file('stdin', clause12)).\n\
+                      # SZS output end CNFRefutation\n";
+        assert_eq!(problem.parse_output(output), AtpResult::Proved(vec![12]));
+        assert_eq!(
+            problem.parse_output("% SZS status Unsatisfiable for stdin\n"),
+            AtpResult::Proved(vec![1, 12])
+        );
+        assert_eq!(
+            problem.parse_output("% SZS status Satisfiable for stdin\n"),
+            AtpResult::Disproved
+        );
+    }
+}
@@ -122,6 +122,16 @@ impl TypeMap {
     pub fn get_monomorph(&self, id: AtomId) -> &ConstantInstance {
         &self.monomorph_info[id as usize].0
     }
+
+    // All the types so far, in order of their ids.
+    pub fn types(&self) -> &[AcornType] {
+        &self.types
+    }
+
+    // All the monomorphs so far, in order of their ids.
+    pub fn monomorphs(&self) -> impl Iterator<Item = &ConstantInstance> {
+        self.monomorph_info.iter().map(|(c, _)| c)
+    }
 }
 
 #[cfg(test)]
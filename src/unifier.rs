@@ -291,6 +291,34 @@ impl Unifier {
         false
     }
 
+    // Unifies a variable applied to arguments with an application that has more arguments.
+    // This is the part of higher-order pattern unification that doesn't need lambdas.
+    // The variable gets mapped to a partial application, and the arguments get unified with
+    // the trailing arguments of the other term.
+    // For example, x0(c1) unifies with c2(c3, c1) by mapping x0 to c2(c3).
+    // Returns None if the terms don't have this shape.
+    fn unify_applied_variable(
+        &mut self,
+        var_scope: Scope,
+        var_term: &Term,
+        term_scope: Scope,
+        term: &Term,
+    ) -> Option<bool> {
+        let Atom::Variable(i) = var_term.head else {
+            return None;
+        };
+        let (prefix, suffix) = split_application(var_term, term)?;
+        if !self.unify_variable(var_scope, i, term_scope, &prefix) {
+            return Some(false);
+        }
+        for (a1, a2) in var_term.args.iter().zip(suffix) {
+            if !self.unify(var_scope, a1, term_scope, a2) {
+                return Some(false);
+            }
+        }
+        Some(true)
+    }
+
     pub fn unify(&mut self, scope1: Scope, term1: &Term, scope2: Scope, term2: &Term) -> bool {
         if term1.term_type != term2.term_type {
             return false;
@@ -304,6 +332,14 @@ impl Unifier {
             return self.unify_variable(scope2, i, scope1, term1);
         }
 
+        // Handle the case where an applied variable matches part of a longer application
+        if let Some(answer) = self.unify_applied_variable(scope1, term1, scope2, term2) {
+            return answer;
+        }
+        if let Some(answer) = self.unify_applied_variable(scope2, term2, scope1, term1) {
+            return answer;
+        }
+
         // These checks mean we won't unify higher-order functions whose head types don't match.
         if term1.head_type != term2.head_type {
             return false;
@@ -440,8 +476,35 @@ impl Unifier {
     }
 }
 
+// Splits an application into a partial application with the same type as the head of
+// var_term, and the trailing arguments that line up with var_term's arguments.
+// Returns None if var_term has no arguments, or at least as many as the application.
+pub fn split_application<'a>(var_term: &Term, term: &'a Term) -> Option<(Term, &'a [Term])> {
+    if var_term.args.is_empty() || var_term.args.len() >= term.args.len() {
+        return None;
+    }
+    let split = term.args.len() - var_term.args.len();
+    let suffix = &term.args[split..];
+    if var_term
+        .args
+        .iter()
+        .zip(suffix)
+        .any(|(a1, a2)| a1.term_type != a2.term_type)
+    {
+        return None;
+    }
+    let prefix = Term::new(
+        var_term.head_type,
+        term.head_type,
+        term.head,
+        term.args[..split].to_vec(),
+    );
+    Some((prefix, suffix))
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::specializer::Specializer;
     use crate::type_map::BOOL;
 
     use super::*;
@@ -512,6 +575,24 @@ mod tests {
         u.assert_unify(Scope::Left, &const_f_term, Scope::Right, &var_f_term);
     }
 
+    #[test]
+    fn test_unifying_applied_variable_with_longer_application() {
+        let left_term = Term::parse("c0(x0(c1), x0)");
+        let right_term = Term::parse("c0(c2(c3, c1), c2(c3))");
+        let mut u = Unifier::new();
+        u.assert_unify(Scope::Left, &left_term, Scope::Right, &right_term);
+        assert_eq!(u.apply(Scope::Left, &left_term), right_term);
+
+        let mut specializer = Specializer::new();
+        assert!(specializer.match_terms(&left_term, &right_term));
+        assert_eq!(specializer.specialize(&left_term), right_term);
+
+        // The trailing arguments have to match
+        let right_term = Term::parse("c0(c2(c3, c4), c2(c3))");
+        let mut u = Unifier::new();
+        assert!(!u.unify(Scope::Left, &left_term, Scope::Right, &right_term));
+    }
+
     #[test]
     fn test_nested_functional_unify() {
         let left_term = Term::parse("x0(x0(c0))");
@@ -0,0 +1,393 @@
+use std::collections::BTreeSet;
+
+use crate::acorn_type::AcornType;
+use crate::acorn_value::{AcornValue, ConstantInstance, FunctionApplication};
+use crate::atom::AtomId;
+use crate::module::ModuleId;
+
+// A ValueVisitor walks over an AcornValue without changing it.
+// Each method defaults to recursing into the children of whatever it is visiting, so an
+// analysis only needs to override the methods for the parts it cares about.
+//
+// stack_size is how many variables are bound outside of the subvalue being visited.
+// It starts at zero for a root value, and grows as we pass through lambdas, quantifiers,
+// and match cases.
+pub trait ValueVisitor {
+    fn visit_value(&mut self, value: &AcornValue, stack_size: AtomId) {
+        walk_value(self, value, stack_size);
+    }
+
+    fn visit_constant(&mut self, _constant: &ConstantInstance, _stack_size: AtomId) {}
+
+    fn visit_variable(&mut self, _index: AtomId, _var_type: &AcornType, _stack_size: AtomId) {}
+
+    // Called for the types of variables introduced by lambdas, quantifiers, and match cases.
+    fn visit_binder_type(&mut self, _binder_type: &AcornType) {}
+}
+
+// Visits each immediate part of a value.
+// Call this from an overridden visit_value to keep recursing.
+pub fn walk_value<V: ValueVisitor + ?Sized>(
+    visitor: &mut V,
+    value: &AcornValue,
+    stack_size: AtomId,
+) {
+    match value {
+        AcornValue::Variable(i, var_type) => visitor.visit_variable(*i, var_type, stack_size),
+        AcornValue::Constant(c) => visitor.visit_constant(c, stack_size),
+        AcornValue::Application(app) => {
+            visitor.visit_value(&app.function, stack_size);
+            for arg in &app.args {
+                visitor.visit_value(arg, stack_size);
+            }
+        }
+        AcornValue::Lambda(args, value)
+        | AcornValue::ForAll(args, value)
+        | AcornValue::Exists(args, value) => {
+            for arg in args {
+                visitor.visit_binder_type(arg);
+            }
+            visitor.visit_value(value, stack_size + args.len() as AtomId);
+        }
+        AcornValue::Binary(_, left, right) => {
+            visitor.visit_value(left, stack_size);
+            visitor.visit_value(right, stack_size);
+        }
+        AcornValue::Not(x) => visitor.visit_value(x, stack_size),
+        AcornValue::Bool(_) => {}
+        AcornValue::IfThenElse(cond, if_value, else_value) => {
+            visitor.visit_value(cond, stack_size);
+            visitor.visit_value(if_value, stack_size);
+            visitor.visit_value(else_value, stack_size);
+        }
+        AcornValue::Match(scrutinee, cases) => {
+            visitor.visit_value(scrutinee, stack_size);
+            for (new_vars, pattern, result) in cases {
+                for var_type in new_vars {
+                    visitor.visit_binder_type(var_type);
+                }
+                let new_stack_size = stack_size + new_vars.len() as AtomId;
+                visitor.visit_value(pattern, new_stack_size);
+                visitor.visit_value(result, new_stack_size);
+            }
+        }
+        AcornValue::Choose(choice_type, condition) => {
+            visitor.visit_binder_type(choice_type);
+            visitor.visit_value(condition, stack_size + 1);
+        }
+    }
+}
+
+// A ValueFolder rebuilds an AcornValue, with the chance to replace any part of it.
+// Like the visitor, each method defaults to rebuilding the same thing, so a transformation
+// only needs to override the methods for the parts it wants to change.
+//
+// stack_size has the same meaning as in ValueVisitor.
+pub trait ValueFolder {
+    fn fold_value(&mut self, value: &AcornValue, stack_size: AtomId) -> AcornValue {
+        fold_children(self, value, stack_size)
+    }
+
+    fn fold_constant(&mut self, constant: &ConstantInstance, _stack_size: AtomId) -> AcornValue {
+        AcornValue::Constant(constant.clone())
+    }
+
+    fn fold_variable(
+        &mut self,
+        index: AtomId,
+        var_type: &AcornType,
+        _stack_size: AtomId,
+    ) -> AcornValue {
+        AcornValue::Variable(index, self.fold_type(var_type))
+    }
+
+    // Called for the types of variables, and the types introduced by binders.
+    // The types of constants are left to fold_constant.
+    fn fold_type(&mut self, acorn_type: &AcornType) -> AcornType {
+        acorn_type.clone()
+    }
+}
+
+// Rebuilds a value by folding each of its immediate parts.
+// Call this from an overridden fold_value to keep recursing.
+pub fn fold_children<F: ValueFolder + ?Sized>(
+    folder: &mut F,
+    value: &AcornValue,
+    stack_size: AtomId,
+) -> AcornValue {
+    match value {
+        AcornValue::Variable(i, var_type) => folder.fold_variable(*i, var_type, stack_size),
+        AcornValue::Constant(c) => folder.fold_constant(c, stack_size),
+        AcornValue::Application(app) => AcornValue::Application(FunctionApplication {
+            function: Box::new(folder.fold_value(&app.function, stack_size)),
+            args: app
+                .args
+                .iter()
+                .map(|arg| folder.fold_value(arg, stack_size))
+                .collect(),
+        }),
+        AcornValue::Lambda(args, value) => {
+            let (args, value) = fold_binder(folder, args, value, stack_size);
+            AcornValue::Lambda(args, value)
+        }
+        AcornValue::ForAll(args, value) => {
+            let (args, value) = fold_binder(folder, args, value, stack_size);
+            AcornValue::ForAll(args, value)
+        }
+        AcornValue::Exists(args, value) => {
+            let (args, value) = fold_binder(folder, args, value, stack_size);
+            AcornValue::Exists(args, value)
+        }
+        AcornValue::Binary(op, left, right) => AcornValue::Binary(
+            *op,
+            Box::new(folder.fold_value(left, stack_size)),
+            Box::new(folder.fold_value(right, stack_size)),
+        ),
+        AcornValue::Not(x) => AcornValue::Not(Box::new(folder.fold_value(x, stack_size))),
+        AcornValue::Bool(_) => value.clone(),
+        AcornValue::IfThenElse(cond, if_value, else_value) => AcornValue::IfThenElse(
+            Box::new(folder.fold_value(cond, stack_size)),
+            Box::new(folder.fold_value(if_value, stack_size)),
+            Box::new(folder.fold_value(else_value, stack_size)),
+        ),
+        AcornValue::Match(scrutinee, cases) => {
+            let new_scrutinee = folder.fold_value(scrutinee, stack_size);
+            let new_cases = cases
+                .iter()
+                .map(|(new_vars, pattern, result)| {
+                    let new_stack_size = stack_size + new_vars.len() as AtomId;
+                    (
+                        new_vars.iter().map(|t| folder.fold_type(t)).collect(),
+                        folder.fold_value(pattern, new_stack_size),
+                        folder.fold_value(result, new_stack_size),
+                    )
+                })
+                .collect();
+            AcornValue::Match(Box::new(new_scrutinee), new_cases)
+        }
+        AcornValue::Choose(choice_type, condition) => AcornValue::Choose(
+            folder.fold_type(choice_type),
+            Box::new(folder.fold_value(condition, stack_size + 1)),
+        ),
+    }
+}
+
+fn fold_binder<F: ValueFolder + ?Sized>(
+    folder: &mut F,
+    args: &[AcornType],
+    value: &AcornValue,
+    stack_size: AtomId,
+) -> (Vec<AcornType>, Box<AcornValue>) {
+    let new_args = args.iter().map(|t| folder.fold_type(t)).collect();
+    let new_value = folder.fold_value(value, stack_size + args.len() as AtomId);
+    (new_args, Box::new(new_value))
+}
+
+// Collects the constants in a value that match a filter.
+pub struct ConstantFinder<'a, F: Fn(&ConstantInstance) -> bool> {
+    pub filter: F,
+    pub output: &'a mut Vec<ConstantInstance>,
+}
+
+impl<F: Fn(&ConstantInstance) -> bool> ValueVisitor for ConstantFinder<'_, F> {
+    fn visit_constant(&mut self, constant: &ConstantInstance, _stack_size: AtomId) {
+        if (self.filter)(constant) {
+            self.output.push(constant.clone());
+        }
+    }
+}
+
+// Checks whether a value refers to any of the stack variables in a range.
+pub struct VariableFinder {
+    pub range: std::ops::Range<AtomId>,
+    pub found: bool,
+}
+
+impl ValueVisitor for VariableFinder {
+    fn visit_variable(&mut self, index: AtomId, _var_type: &AcornType, _stack_size: AtomId) {
+        if self.range.contains(&index) {
+            self.found = true;
+        }
+    }
+}
+
+// Replaces constants in a value with whatever the replacer returns.
+// Constants for which the replacer returns None are left alone.
+// The replacer gets the stack size, so it can construct values that are valid in place.
+pub struct ConstantReplacer<F: Fn(&ConstantInstance, AtomId) -> Option<AcornValue>> {
+    pub replacer: F,
+}
+
+impl<F: Fn(&ConstantInstance, AtomId) -> Option<AcornValue>> ValueFolder for ConstantReplacer<F> {
+    fn fold_constant(&mut self, constant: &ConstantInstance, stack_size: AtomId) -> AcornValue {
+        (self.replacer)(constant, stack_size)
+            .unwrap_or_else(|| AcornValue::Constant(constant.clone()))
+    }
+}
+
+// Replaces every occurrence of one term with another.
+// The terms shouldn't use any stack variables, so they mean the same thing at any depth.
+pub struct TermReplacer<'a> {
+    pub from: &'a AcornValue,
+    pub to: &'a AcornValue,
+}
+
+impl ValueFolder for TermReplacer<'_> {
+    fn fold_value(&mut self, value: &AcornValue, stack_size: AtomId) -> AcornValue {
+        if value == self.from {
+            self.to.clone()
+        } else {
+            fold_children(self, value, stack_size)
+        }
+    }
+}
+
+// Replaces type variables with other types, everywhere in a value.
+pub struct TypeInstantiator<'a> {
+    pub params: &'a [(String, AcornType)],
+}
+
+impl ValueFolder for TypeInstantiator<'_> {
+    fn fold_constant(&mut self, constant: &ConstantInstance, _stack_size: AtomId) -> AcornValue {
+        AcornValue::Constant(constant.instantiate(self.params))
+    }
+
+    fn fold_type(&mut self, acorn_type: &AcornType) -> AcornType {
+        acorn_type.instantiate(self.params)
+    }
+}
+
+// Replaces the module ids everywhere in a value, in constants and in types.
+pub struct ModuleRemapper<'a> {
+    pub remap: &'a dyn Fn(ModuleId) -> ModuleId,
+}
+
+impl ValueFolder for ModuleRemapper<'_> {
+    fn fold_constant(&mut self, constant: &ConstantInstance, _stack_size: AtomId) -> AcornValue {
+        AcornValue::Constant(ConstantInstance {
+            module_id: (self.remap)(constant.module_id),
+            name: constant.name.clone(),
+            params: constant
+                .params
+                .iter()
+                .map(|t| t.remap_modules(self.remap))
+                .collect(),
+            instance_type: constant.instance_type.remap_modules(self.remap),
+        })
+    }
+
+    fn fold_type(&mut self, acorn_type: &AcornType) -> AcornType {
+        acorn_type.remap_modules(self.remap)
+    }
+}
+
+// Collects the constants and data types that values refer to, by module and name.
+// Uses of the constant in "defining" are skipped, so a definition doesn't count as a use of itself.
+#[derive(Default)]
+pub struct UsageCollector {
+    pub constants: BTreeSet<(ModuleId, String)>,
+    pub data_types: BTreeSet<(ModuleId, String)>,
+    pub defining: Option<(ModuleId, String)>,
+}
+
+impl UsageCollector {
+    pub fn add_type(&mut self, acorn_type: &AcornType) {
+        match acorn_type {
+            AcornType::Data(module, name) => {
+                self.data_types.insert((*module, name.clone()));
+            }
+            AcornType::Function(function_type) => {
+                for arg_type in &function_type.arg_types {
+                    self.add_type(arg_type);
+                }
+                self.add_type(&function_type.return_type);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ValueVisitor for UsageCollector {
+    fn visit_constant(&mut self, constant: &ConstantInstance, _stack_size: AtomId) {
+        let key = (constant.module_id, constant.name.clone());
+        if self.defining.as_ref() != Some(&key) {
+            self.constants.insert(key);
+        }
+        for param in &constant.params {
+            self.add_type(param);
+        }
+        self.add_type(&constant.instance_type);
+    }
+
+    fn visit_variable(&mut self, _index: AtomId, var_type: &AcornType, _stack_size: AtomId) {
+        self.add_type(var_type);
+    }
+
+    fn visit_binder_type(&mut self, binder_type: &AcornType) {
+        self.add_type(binder_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts the variables in a value, along with the deepest stack they were used in.
+    struct VariableCounter {
+        count: usize,
+        max_stack_size: AtomId,
+    }
+
+    impl ValueVisitor for VariableCounter {
+        fn visit_variable(&mut self, _index: AtomId, _var_type: &AcornType, stack_size: AtomId) {
+            self.count += 1;
+            self.max_stack_size = self.max_stack_size.max(stack_size);
+        }
+    }
+
+    // Negates every boolean literal.
+    struct BoolFlipper;
+
+    impl ValueFolder for BoolFlipper {
+        fn fold_value(&mut self, value: &AcornValue, stack_size: AtomId) -> AcornValue {
+            match value {
+                AcornValue::Bool(b) => AcornValue::Bool(!b),
+                _ => fold_children(self, value, stack_size),
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor_tracks_stack_size() {
+        // forall(x0: Bool) { exists(x1: Bool) { x0 = x1 } }
+        let inner = AcornValue::new_equals(
+            AcornValue::Variable(0, AcornType::Bool),
+            AcornValue::Variable(1, AcornType::Bool),
+        );
+        let value = AcornValue::new_forall(
+            vec![AcornType::Bool],
+            AcornValue::new_exists(vec![AcornType::Bool], inner),
+        );
+        let mut counter = VariableCounter {
+            count: 0,
+            max_stack_size: 0,
+        };
+        counter.visit_value(&value, 0);
+        assert_eq!(counter.count, 2);
+        assert_eq!(counter.max_stack_size, 2);
+    }
+
+    #[test]
+    fn test_folder_rebuilds_value() {
+        let value = AcornValue::new_and(
+            AcornValue::Bool(true),
+            AcornValue::Not(Box::new(AcornValue::Bool(false))),
+        );
+        let flipped = BoolFlipper.fold_value(&value, 0);
+        let expected = AcornValue::new_and(
+            AcornValue::Bool(false),
+            AcornValue::Not(Box::new(AcornValue::Bool(true))),
+        );
+        assert_eq!(flipped, expected);
+    }
+}
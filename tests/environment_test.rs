@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod environment_test {
     use acorn::environment::{Environment, LineType};
+    use acorn::fact::TagFilter;
     use acorn::project::Project;
+    use std::path::PathBuf;
 
     #[test]
     fn test_fn_equality() {
@@ -742,6 +744,96 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
         );
     }
 
+    #[test]
+    fn test_custom_infix_operators() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Str: axiom
+            type Fn: axiom
+            let empty: Str = axiom
+            let f: Fn = axiom
+            let g: Fn = axiom
+            let apply: (Fn, Str) -> Str = axiom
+            class Str {
+                define concat(self, other: Str) -> Str { axiom }
+                define pipe(self, h: Fn) -> Str { apply(h, self) }
+            }
+            class Fn {
+                define compose(self, other: Fn) -> Fn { axiom }
+            }
+            theorem concat_assoc(a: Str, b: Str, c: Str) { a ++ b ++ c = a ++ (b ++ c) }
+            theorem pipe_compose(s: Str, h: Fn, k: Fn) { s |> k |> h = s |> h ∘ k }
+        "#,
+        );
+        env.bad("theorem bad(a: Str, f: Fn) { f ++ f = f }");
+        env.bindings.expect_good_code("empty ++ empty = empty");
+        env.bindings.expect_good_code("empty |> f ∘ g");
+    }
+
+    #[test]
+    fn test_declared_operator_precedence() {
+        let header = r#"
+            type Str: axiom
+            type Num: axiom
+            type Fn: axiom
+            let s: Str = axiom
+            let f: Fn = axiom
+        "#;
+
+        // By default, |> binds more loosely than *.
+        let mut env = Environment::new_test();
+        env.add(header);
+        env.add(
+            r#"
+            class Str {
+                define mul(self, other: Str) -> Num { axiom }
+                define pipe(self, g: Fn) -> Str { axiom }
+            }
+        "#,
+        );
+        env.bad("let n: Num = s * s |> f");
+        env.add("let n: Num = s * (s |> f)");
+        env.bindings.expect_good_code("s * (s |> f)");
+
+        // A magic method can declare a tighter precedence.
+        let mut env = Environment::new_test();
+        env.add(header);
+        env.add(
+            r#"
+            class Str {
+                define mul(self, other: Str) -> Num { axiom }
+                @precedence(12) define pipe(self, g: Fn) -> Str { axiom }
+            }
+            let n: Num = s * s |> f
+            theorem same { n = s * (s |> f) }
+        "#,
+        );
+        env.bindings.expect_good_code("s * s |> f");
+        env.bad(
+            r#"
+            class Fn {
+                @precedence(9) define pipe(self, g: Fn) -> Fn { axiom }
+            }
+        "#,
+        );
+        env.bad(
+            r#"
+            class Fn {
+                @precedence(13) define concat(self, g: Fn) -> Fn { axiom }
+            }
+        "#,
+        );
+        env.bad(
+            r#"
+            class Fn {
+                @precedence(10) define mul(self, g: Fn) -> Fn { axiom }
+            }
+        "#,
+        );
+        env.bad("@precedence(10) define concat(a: Fn, b: Fn) -> Fn { axiom }");
+    }
+
     #[test]
     fn test_infix_sub() {
         let mut env = Environment::new_test();
@@ -868,6 +960,36 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
         );
     }
 
+    #[test]
+    fn test_negative_numerals() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Int: axiom
+            class Int {
+                let 0: Int = axiom
+                let 3: Int = axiom
+                define neg(self) -> Int { axiom }
+                define add(self, other: Int) -> Int { axiom }
+            }
+            type Nat: axiom
+            class Nat {
+                let 3: Nat = axiom
+            }
+            numerals Int
+            let a: Int = -3
+            let b: Int = -Int.3
+            theorem neg_cancel { -3 + 3 = 0 }
+            theorem neg_neg { -(-3) = 3 }
+        "#,
+        );
+        env.assert_def_eq("a", "b");
+        env.bad("let c: Nat = -Nat.3");
+        env.bindings.expect_good_code("-3 + 3 = 0");
+        env.bindings.expect_good_code("-(-3)");
+        env.bindings.expect_good_code("-(-3 + 3)");
+    }
+
     #[test]
     fn test_self_must_have_correct_type() {
         let mut env = Environment::new_test();
@@ -1021,6 +1143,48 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
         env.bad("numerals Foo -> Foo");
     }
 
+    #[test]
+    fn test_block_scoped_numerals() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            class Nat {
+                let 0: Nat = axiom
+                let 1: Nat = axiom
+            }
+            type Int: axiom
+            class Int {
+                let 0: Int = axiom
+                let 1: Int = axiom
+            }
+            numerals Nat
+            let n: Nat = 1
+            forall(x: Int) {
+                numerals Int
+                let i: Int = 1
+                x = x
+            }
+            theorem one_is_one {
+                Int.1 = Int.1
+            } by {
+                numerals Int
+                1 = 1
+            }
+            let m: Nat = 0
+        "#,
+        );
+        env.check_lines();
+        env.bad("let k: Int = 1");
+        env.bindings.expect_good_code("Int.1 = Int.0");
+        env.bindings.expect_good_code("1 = 0");
+
+        // Code generated inside the block uses the block's numerals.
+        let block_env = env.env_for_line(16);
+        block_env.bindings.expect_good_code("1 = 0");
+        block_env.bindings.expect_good_code("Nat.1 = Nat.0");
+    }
+
     #[test]
     fn test_no_defining_top_level_numbers() {
         let mut env = Environment::new_test();
@@ -1126,6 +1290,75 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
         env.bindings.expect_good_code("0.suc");
     }
 
+    #[test]
+    fn test_hex_and_binary_literals() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            class Nat {
+                let 0: Nat = axiom
+                define suc(self) -> Nat { axiom }
+                let 1: Nat = Nat.0.suc
+                let 2: Nat = Nat.1.suc
+                let 3: Nat = Nat.2.suc
+                let 4: Nat = Nat.3.suc
+                let 5: Nat = Nat.4.suc
+                let 6: Nat = Nat.5.suc
+                let 7: Nat = Nat.6.suc
+                let 8: Nat = Nat.7.suc
+                let 9: Nat = Nat.8.suc
+                define read(self, other: Nat) -> Nat { axiom }
+                define read_hex(self, other: Nat) -> Nat { axiom }
+                define read_binary(self, other: Nat) -> Nat { axiom }
+                define add(self, other: Nat) -> Nat { axiom }
+            }
+            numerals Nat
+        "#,
+        );
+        env.add("let a: Nat = 0xff");
+        env.expect_def(
+            "a",
+            "Nat.read_hex(Nat.read(Nat.1, Nat.5), Nat.read(Nat.1, Nat.5))",
+        );
+        env.add("let b: Nat = 0b101");
+        env.expect_def("b", "Nat.read_binary(Nat.read_binary(Nat.1, Nat.0), Nat.1)");
+        env.bindings.expect_good_code("0xff");
+        env.bindings.expect_good_code("0x0f");
+        env.bindings.expect_good_code("0x17 + 0x1a2");
+        env.bindings.expect_good_code("0b1010");
+        env.bindings.expect_good_code("0b10 + 0b11");
+        env.bindings.expect_good_code("0xff + 255");
+        env.bad("let c: Nat = 0xfg");
+    }
+
+    #[test]
+    fn test_hex_literals_need_read_hex() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            class Nat {
+                let 0: Nat = axiom
+                define suc(self) -> Nat { axiom }
+                let 1: Nat = Nat.0.suc
+                define read(self, other: Nat) -> Nat { axiom }
+            }
+            numerals Nat
+        "#,
+        );
+        env.add("let a: Nat = 0x1");
+        env.bad("let b: Nat = 0x11");
+        env.bad("let c: Nat = 0b11");
+        env.bad(
+            r#"
+            class Nat {
+                define read_hex(self, other: Bool) -> Nat { axiom }
+            }
+        "#,
+        );
+    }
+
     #[test]
     fn test_non_default_numeric_literals() {
         let mut env = Environment::new_test();
@@ -1533,29 +1766,38 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
     }
 
     #[test]
-    fn test_match_value_results_check_type() {
+    fn test_match_value_with_guards() {
         let mut env = Environment::new_test();
         env.add(
             r#"
             inductive Foo {
-                bar
-            }"#,
-        );
-        env.bad(
-            r#"
+                bar(Bool)
+                baz
+            }
+
             define foo(f: Foo) -> Bool {
                 match f {
-                    Foo.bar {
-                        Foo.bar
+                    Foo.bar(b) if b {
+                        false
+                    }
+                    Foo.baz {
+                        true
+                    }
+                    Foo.bar(c) {
+                        c
                     }
                 }
             }
-        "#,
+            "#,
+        );
+        env.expect_def(
+            "foo",
+            "function(x0: Foo) { match x0 { Foo.bar(x1) { if x1 { false } else { x1 } } Foo.baz { true } } }",
         );
     }
 
     #[test]
-    fn test_match_value_with_let() {
+    fn test_match_value_guards_need_fallback() {
         let mut env = Environment::new_test();
         env.add(
             r#"
@@ -1564,37 +1806,75 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
                 baz
             }"#,
         );
-        env.add(
+        env.bad(
             r#"
-            let f: Foo = Foo.bar(true)
-            let b: Bool = match f {
-                Foo.bar(b) {
-                    b
+            define foo(f: Foo) -> Bool {
+                match f {
+                    Foo.bar(b) if b {
+                        false
+                    }
+                    Foo.baz {
+                        true
+                    }
                 }
-                Foo.baz {
-                    false
+            }
+            "#,
+        );
+        env.bad(
+            r#"
+            define foo(f: Foo) -> Bool {
+                match f {
+                    Foo.bar(b) {
+                        b
+                    }
+                    Foo.bar(b) if b {
+                        false
+                    }
+                    Foo.baz {
+                        true
+                    }
                 }
             }
-        "#,
+            "#,
         );
     }
 
     #[test]
-    fn test_left_recursive_definition() {
+    fn test_match_statement_with_guards() {
         let mut env = Environment::new_test();
         env.add(
             r#"
-            inductive Nat {
-                zero
-                suc(Nat)
+            inductive Foo {
+                bar(Bool)
+                baz
+            }"#,
+        );
+        env.bad(
+            r#"
+            forall (f: Foo) {
+                match f {
+                    Foo.bar(b) if b {
+                        b
+                    }
+                    Foo.baz {
+                        true
+                    }
+                }
             }
-            define add(a: Nat, b: Nat) -> Nat {
-                match a {
-                    Nat.zero {
+            "#,
+        );
+        env.add(
+            r#"
+            forall (f: Foo) {
+                match f {
+                    Foo.bar(b) if b {
                         b
                     }
-                    Nat.suc(pred) {
-                        add(pred, b).suc
+                    Foo.bar(b) {
+                        not b
+                    }
+                    Foo.baz {
+                        true
                     }
                 }
             }
@@ -1603,7 +1883,7 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
     }
 
     #[test]
-    fn test_right_recursive_definition() {
+    fn test_match_nested_patterns() {
         let mut env = Environment::new_test();
         env.add(
             r#"
@@ -1611,34 +1891,40 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
                 zero
                 suc(Nat)
             }
-            define add(a: Nat, b: Nat) -> Nat {
-                match b {
+
+            define even(n: Nat) -> Bool {
+                match n {
                     Nat.zero {
-                        a
+                        true
                     }
-                    Nat.suc(pred) {
-                        add(a, pred).suc
+                    Nat.suc(Nat.zero) {
+                        false
+                    }
+                    Nat.suc(Nat.suc(k)) {
+                        even(k)
                     }
                 }
             }
-            "#,
-        );
-    }
 
-    #[test]
-    fn test_no_recursive_simple_infinite_loops() {
-        let mut env = Environment::new_test();
-        env.bad(
-            r#"
-            define loop(a: Bool) -> Bool {
-                not loop(a)
+            forall(n: Nat) {
+                match n {
+                    Nat.suc(Nat.suc(k)) {
+                        n = k.suc.suc
+                    }
+                    Nat.suc(Nat.zero) {
+                        n = Nat.zero.suc
+                    }
+                    Nat.zero {
+                        n = Nat.zero
+                    }
+                }
             }
             "#,
-        )
+        );
     }
 
     #[test]
-    fn test_no_recursive_complicated_infinite_loops() {
+    fn test_match_nested_patterns_must_cover() {
         let mut env = Environment::new_test();
         env.add(
             r#"
@@ -1648,21 +1934,214 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
             }
             "#,
         );
-
-        // This would infinite loop because it hits a different branch each time.
         env.bad(
             r#"
-            define loop(a: Nat, b: Nat, c: Bool) -> Bool {
-                if c {
-                    match a {
-                        Nat.zero {
-                            false
-                        }
-                        Nat.suc(pred) {
-                            not loop(pred, b.suc, false)
-                        }
-                    }
-                } else {
+            define foo(n: Nat) -> Bool {
+                match n {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(Nat.suc(k)) {
+                        false
+                    }
+                }
+            }
+            "#,
+        );
+        env.bad(
+            r#"
+            forall(n: Nat) {
+                match n {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(Nat.suc(k)) {
+                        true
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_match_nested_patterns_cannot_overlap() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            "#,
+        );
+        env.bad(
+            r#"
+            define foo(n: Nat) -> Bool {
+                match n {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(Nat.zero) {
+                        true
+                    }
+                    Nat.suc(k) {
+                        false
+                    }
+                }
+            }
+            "#,
+        );
+        env.bad(
+            r#"
+            forall(n: Nat) {
+                match n {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(k) {
+                        true
+                    }
+                    Nat.suc(Nat.zero) {
+                        true
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_match_value_results_check_type() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Foo {
+                bar
+            }"#,
+        );
+        env.bad(
+            r#"
+            define foo(f: Foo) -> Bool {
+                match f {
+                    Foo.bar {
+                        Foo.bar
+                    }
+                }
+            }
+        "#,
+        );
+    }
+
+    #[test]
+    fn test_match_value_with_let() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Foo {
+                bar(Bool)
+                baz
+            }"#,
+        );
+        env.add(
+            r#"
+            let f: Foo = Foo.bar(true)
+            let b: Bool = match f {
+                Foo.bar(b) {
+                    b
+                }
+                Foo.baz {
+                    false
+                }
+            }
+        "#,
+        );
+    }
+
+    #[test]
+    fn test_left_recursive_definition() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define add(a: Nat, b: Nat) -> Nat {
+                match a {
+                    Nat.zero {
+                        b
+                    }
+                    Nat.suc(pred) {
+                        add(pred, b).suc
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_right_recursive_definition() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define add(a: Nat, b: Nat) -> Nat {
+                match b {
+                    Nat.zero {
+                        a
+                    }
+                    Nat.suc(pred) {
+                        add(a, pred).suc
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_no_recursive_simple_infinite_loops() {
+        let mut env = Environment::new_test();
+        env.bad(
+            r#"
+            define loop(a: Bool) -> Bool {
+                not loop(a)
+            }
+            "#,
+        )
+    }
+
+    #[test]
+    fn test_no_recursive_complicated_infinite_loops() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            "#,
+        );
+
+        // This would infinite loop because it hits a different branch each time.
+        env.bad(
+            r#"
+            define loop(a: Nat, b: Nat, c: Bool) -> Bool {
+                if c {
+                    match a {
+                        Nat.zero {
+                            false
+                        }
+                        Nat.suc(pred) {
+                            not loop(pred, b.suc, false)
+                        }
+                    }
+                } else {
                     match b {
                         Nat.zero {
                             false
@@ -1677,6 +2156,795 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
         );
     }
 
+    #[test]
+    fn test_lexicographic_recursion() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define ack(m: Nat, n: Nat) -> Nat {
+                match m {
+                    Nat.zero {
+                        n.suc
+                    }
+                    Nat.suc(m_pred) {
+                        match n {
+                            Nat.zero {
+                                ack(m_pred, Nat.zero.suc)
+                            }
+                            Nat.suc(n_pred) {
+                                ack(m_pred, ack(m, n_pred))
+                            }
+                        }
+                    }
+                }
+            }
+
+            inductive List {
+                nil
+                cons(Nat, List)
+            }
+            define merge(a: List, b: List, pick: Nat -> Bool) -> List {
+                match a {
+                    List.nil {
+                        b
+                    }
+                    List.cons(x, xs) {
+                        match b {
+                            List.nil {
+                                a
+                            }
+                            List.cons(y, ys) {
+                                if pick(x) {
+                                    List.cons(x, merge(xs, b, pick))
+                                } else {
+                                    List.cons(y, merge(a, ys, pick))
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_no_lexicographic_recursion_without_descent() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            "#,
+        );
+
+        // Each argument goes down in one call but can go up in the other.
+        env.bad(
+            r#"
+            define swap(a: Nat, b: Nat) -> Bool {
+                match a {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(a_pred) {
+                        match b {
+                            Nat.zero {
+                                swap(a_pred, b.suc)
+                            }
+                            Nat.suc(b_pred) {
+                                swap(a.suc, b_pred)
+                            }
+                        }
+                    }
+                }
+            }
+            "#,
+        );
+
+        // Neither argument ever goes down.
+        env.bad(
+            r#"
+            define stuck(a: Nat, b: Nat) -> Bool {
+                match a {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(a_pred) {
+                        stuck(a, b)
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_recursion_on_nested_substructure() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define half(n: Nat) -> Nat {
+                match n {
+                    Nat.zero {
+                        Nat.zero
+                    }
+                    Nat.suc(pred) {
+                        match pred {
+                            Nat.zero {
+                                Nat.zero
+                            }
+                            Nat.suc(pred_pred) {
+                                half(pred_pred).suc
+                            }
+                        }
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_decreasing_measure() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            class Nat {
+                define lt(self, other: Nat) -> Bool { axiom }
+            }
+            let zero: Nat = axiom
+            let pred: Nat -> Nat = axiom
+            "#,
+        );
+
+        // This doesn't descend syntactically, so it needs a measure.
+        env.bad(
+            r#"
+            define countdown(n: Nat) -> Nat {
+                if n = zero {
+                    zero
+                } else {
+                    countdown(pred(n))
+                }
+            }
+            "#,
+        );
+        env.add(
+            r#"
+            define countdown(n: Nat) -> Nat decreasing n {
+                if n = zero {
+                    zero
+                } else {
+                    countdown(pred(n))
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_bad_decreasing_measures() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            class Nat {
+                define lt(self, other: Nat) -> Bool { axiom }
+            }
+            type Foo: axiom
+            let zero: Nat = axiom
+            let pred: Nat -> Nat = axiom
+            let apply: (Nat -> Nat, Nat) -> Nat = axiom
+            "#,
+        );
+
+        // Foo has no lt.
+        env.bad(
+            r#"
+            define foo(n: Nat, f: Foo) -> Nat decreasing f {
+                if n = zero {
+                    zero
+                } else {
+                    foo(pred(n), f)
+                }
+            }
+            "#,
+        );
+
+        // The measure can't use the function being defined.
+        env.bad(
+            r#"
+            define bar(n: Nat) -> Nat decreasing bar(n) {
+                zero
+            }
+            "#,
+        );
+
+        // Axioms can't have measures.
+        env.bad(
+            r#"
+            define baz(n: Nat) -> Nat decreasing n {
+                axiom
+            }
+            "#,
+        );
+
+        // The function must be called directly.
+        env.bad(
+            r#"
+            define qux(n: Nat) -> Nat decreasing n {
+                if n = zero {
+                    zero
+                } else {
+                    apply(qux, pred(n))
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            define is_even(n: Nat) -> Bool {
+                match n {
+                    Nat.zero {
+                        true
+                    }
+                    Nat.suc(pred) {
+                        is_odd(pred)
+                    }
+                }
+            } and is_odd(n: Nat) -> Bool {
+                match n {
+                    Nat.zero {
+                        false
+                    }
+                    Nat.suc(pred) {
+                        is_even(pred)
+                    }
+                }
+            }
+            "#,
+        );
+        env.expect_type("is_even", "Nat -> Bool");
+        env.expect_type("is_odd", "Nat -> Bool");
+    }
+
+    #[test]
+    fn test_bad_mutual_recursion() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            "#,
+        );
+
+        // This never descends.
+        env.bad(
+            r#"
+            define foo(n: Nat) -> Bool {
+                bar(n)
+            } and bar(n: Nat) -> Bool {
+                foo(n)
+            }
+            "#,
+        );
+
+        // The names must be different.
+        env.bad(
+            r#"
+            define foo(n: Nat) -> Bool {
+                true
+            } and foo(n: Nat) -> Bool {
+                false
+            }
+            "#,
+        );
+
+        // Measures can't be used for groups.
+        env.bad(
+            r#"
+            define foo(n: Nat) -> Bool decreasing n {
+                bar(n)
+            } and bar(n: Nat) -> Bool {
+                true
+            }
+            "#,
+        );
+
+        // When a group fails, none of it gets defined.
+        env.bad(
+            r#"
+            define foo(n: Nat) -> Bool {
+                true
+            } and bar(n: Nat) -> Bool {
+                baz
+            }
+            "#,
+        );
+        env.add(
+            r#"
+            define foo(n: Nat) -> Bool {
+                true
+            }
+            define bar(n: Nat) -> Bool {
+                foo(n)
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_opaque_definitions() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            opaque define two -> Nat {
+                suc(suc(zero))
+            }
+            define three -> Nat {
+                suc(two)
+            }
+            "#,
+        );
+
+        // Unfolding needs a proof block.
+        env.bad("unfold two");
+
+        // Only opaque definitions can be unfolded.
+        env.bad(
+            r#"
+            theorem goal {
+                three = suc(suc(suc(zero)))
+            } by {
+                unfold three
+            }
+            "#,
+        );
+
+        // Axioms have nothing to unfold.
+        env.bad(
+            r#"
+            opaque define four -> Nat {
+                axiom
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem three_is_suc_suc_suc {
+                three = suc(suc(suc(zero)))
+            } by {
+                unfold two
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_lemma_scope() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            "#,
+        );
+        env.bad(
+            r#"
+            lemma helper(x: Nat) {
+                suc(x) = suc(x)
+            }
+            "#,
+        );
+        env.add(
+            r#"
+            theorem goal(a: Nat) {
+                suc(a) = suc(a)
+            } by {
+                lemma helper(x: Nat) {
+                    suc(x) = suc(x)
+                }
+                helper(a)
+            }
+            "#,
+        );
+
+        // The lemma doesn't escape its block.
+        env.bad("let foo: Bool = helper(zero)");
+    }
+
+    #[test]
+    fn test_labeled_props() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            "#,
+        );
+
+        // Only a premise can be labeled.
+        env.bad(
+            r#"
+            theorem bad_label(a: Nat) {
+                h: a = a
+            }
+            "#,
+        );
+
+        // Labels can't reuse names.
+        env.bad(
+            r#"
+            theorem reused_label(a: Nat) {
+                a: a = zero implies suc(a) = suc(zero)
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem goal(a: Nat) {
+                h: a = zero implies suc(a) = suc(zero)
+            } by {
+                h
+                h2: suc(a) = suc(zero)
+                h2
+            }
+            "#,
+        );
+
+        // Labels are local to their block.
+        env.bad("let foo: Bool = h2");
+    }
+
+    #[test]
+    fn test_assume_and_suffices() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            "#,
+        );
+
+        // There's no goal at the top level.
+        env.bad("assume suc(zero) = zero");
+        env.bad("suffices suc(zero) = zero");
+
+        // The outer premise of a theorem is already assumed, so the assumption has to match
+        // the premise of what's left.
+        env.bad(
+            r#"
+            theorem bad_assume(a: Nat) {
+                a = zero implies (suc(a) = zero implies a = suc(a))
+            } by {
+                assume a = zero
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem goal(a: Nat) {
+                a = zero implies (suc(a) = zero implies a = suc(a))
+            } by {
+                assume suc(a) = zero
+                suffices a = zero and suc(a) = zero by {
+                    a = suc(a)
+                }
+                a = zero and suc(a) = zero
+            }
+            "#,
+        );
+
+        // Inside a conditional block there's no goal to assume from.
+        env.bad(
+            r#"
+            if suc(zero) = zero {
+                assume zero = zero
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_by_contradiction() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            "#,
+        );
+
+        // There's no goal at the top level.
+        env.bad(
+            r#"
+            by_contradiction {
+                false
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem goal(a: Nat) {
+                suc(a) != zero or suc(a) = zero
+            } by {
+                by_contradiction {
+                    suc(a) = zero
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_induction_statement() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            "#,
+        );
+
+        // There's no goal at the top level.
+        env.add("let m: Nat = Nat.zero");
+        env.bad(
+            r#"
+            induction m {
+                Nat.zero {
+                }
+                Nat.suc(k) {
+                }
+            }
+            "#,
+        );
+
+        // Every constructor needs a case.
+        env.bad(
+            r#"
+            theorem missing_case(n: Nat) {
+                n = n
+            } by {
+                induction n {
+                    Nat.zero {
+                    }
+                }
+            }
+            "#,
+        );
+
+        // Cases can't be nested patterns.
+        env.bad(
+            r#"
+            theorem nested_case(n: Nat) {
+                n = n
+            } by {
+                induction n {
+                    Nat.zero {
+                    }
+                    Nat.suc(Nat.zero) {
+                    }
+                    Nat.suc(Nat.suc(k)) {
+                    }
+                }
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem goal(n: Nat) {
+                n = n
+            } by {
+                induction n {
+                    Nat.zero {
+                        Nat.zero = Nat.zero
+                    }
+                    Nat.suc(k) {
+                        k = k
+                        Nat.suc(k) = Nat.suc(k)
+                    }
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_rewrite_statement() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_comm(a: Nat, b: Nat) {
+                add(a, b) = add(b, a)
+            }
+            axiom add_zero(a: Nat) {
+                add(a, zero) = a or a = zero
+            }
+            "#,
+        );
+
+        // There's no goal at the top level.
+        env.bad("rewrite add_comm(zero, zero)");
+
+        // The citation has to be an equation.
+        env.bad(
+            r#"
+            theorem not_equation(a: Nat) {
+                add(a, zero) = a
+            } by {
+                rewrite add_zero(a)
+            }
+            "#,
+        );
+
+        // The goal has to contain the left side of the equation.
+        env.bad(
+            r#"
+            theorem nothing_to_rewrite(a: Nat) {
+                add(a, zero) = a
+            } by {
+                rewrite add_comm(a, a)
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem goal(a: Nat, b: Nat) {
+                h: add(a, b) = zero implies add(b, a) = zero
+            } by {
+                rewrite add_comm(a, b) in h
+                rewrite add_comm(b, a)
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_have_statement() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            "#,
+        );
+
+        // A have statement can't reuse a name.
+        env.bad("have zero: suc(zero) = suc(zero)");
+
+        // The name can't be used in its own proof.
+        env.bad(
+            r#"
+            have circular: suc(zero) = suc(zero) by {
+                circular
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            theorem goal(a: Nat) {
+                suc(a) = suc(a)
+            } by {
+                have h: a = a by {
+                    a = a
+                }
+                h
+            }
+            "#,
+        );
+
+        // The name is local to its block.
+        env.bad("let foo: Bool = h");
+    }
+
+    #[test]
+    fn test_constrained_forall() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            "#,
+        );
+
+        // The constraint can only use the quantified variables and outside names.
+        env.bad(
+            r#"
+            forall(x: Nat) where x != y {
+                x = x
+            }
+            "#,
+        );
+
+        env.add(
+            r#"
+            forall(x: Nat) where x != zero {
+                x != zero
+            }
+            "#,
+        );
+
+        // The quantified variables are local to the block.
+        env.bad("let y: Nat = x");
+    }
+
+    #[test]
+    fn test_overloaded_constants() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Int: axiom
+            type Rat: axiom
+            let i: Int = axiom
+            let r: Rat = axiom
+            let zero: Int = axiom
+            let zero: Rat = axiom
+            define abs(x: Int) -> Int { axiom }
+            define abs(x: Rat) -> Rat { axiom }
+            theorem abs_abs(a: Int) { abs(abs(a)) = abs(a) }
+            let j: Int = abs(i)
+            let s: Rat = abs(zero)
+            let f: Int -> Int = abs
+            "#,
+        );
+
+        // Each overload needs a different type.
+        env.bad("define abs(x: Int) -> Int { axiom }");
+        env.bad("let zero: Int = axiom");
+
+        // The types have to pick out exactly one overload.
+        env.bad("theorem bare_abs { abs = abs }");
+        env.bad("theorem ambiguous_abs { abs(zero) = abs(zero) }");
+        env.bad("let b: Bool = abs(true)");
+        env.bad("let g: Bool -> Bool = abs");
+
+        env.bindings.expect_good_code("abs(i)");
+        env.bindings.expect_good_code("abs(r)");
+    }
+
     #[test]
     fn test_templated_recursive_function() {
         let mut env = Environment::new_test();
@@ -1900,4 +3168,523 @@ theorem add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)
             "#,
         );
     }
+
+    #[test]
+    fn test_doc_comments_in_env() {
+        let mut env = Environment::new_test();
+        let project = Project::new_mock();
+        env.add(
+            r#"
+            /// The natural numbers.
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+
+            class Nat {
+                /// One more than zero.
+                let one: Nat = Nat.suc(Nat.zero)
+            }
+
+            // Not documentation.
+            define double(b: Bool) -> Bool {
+                b
+            }
+
+            /// Everything is itself.
+            /// Even booleans.
+            theorem self_eq(b: Bool) {
+                b = b
+            }
+        "#,
+        );
+        assert_eq!(
+            env.bindings.get_documentation(&project, "Nat"),
+            Some("The natural numbers.")
+        );
+        assert_eq!(
+            env.bindings.get_documentation(&project, "Nat.one"),
+            Some("One more than zero.")
+        );
+        assert_eq!(env.bindings.get_documentation(&project, "double"), None);
+        assert_eq!(
+            env.bindings.get_documentation(&project, "self_eq"),
+            Some("Everything is itself.\nEven booleans.")
+        );
+    }
+
+    #[test]
+    fn test_theorem_attributes() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            @simp axiom zero_eq { zero = zero }
+            @no_auto
+            theorem zero_eq_again(a: Nat) { a = zero -> zero = a }
+        "#,
+        );
+        env.bad("@fancy theorem foo { true }");
+        env.bad("@simp theorem foo(a: Nat) { a = zero -> zero = a }");
+        env.bad("@no_auto theorem { zero = zero }");
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            @tag(fancy) theorem zero_eq { zero = zero }
+            @tag(order) @tag(fancy) define is_zero(a: Nat) -> Bool { a = zero }
+        "#,
+        );
+        let all = env.exported_facts(&TagFilter::default()).len();
+        assert_eq!(
+            env.exported_facts(&TagFilter::excluding(["order"])).len(),
+            all - 1
+        );
+        assert_eq!(
+            env.exported_facts(&TagFilter::excluding(["fancy"])).len(),
+            all - 2
+        );
+        env.bad("@simp define foo(a: Nat) -> Bool { a = zero }");
+        env.bad("@fancy define foo(a: Nat) -> Bool { a = zero }");
+        env.bad("@tag theorem foo { true }");
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            class Nat {
+                let 0: Nat = axiom
+                let 1: Nat = axiom
+                let 2: Nat = axiom
+                let 3: Nat = axiom
+                let 4: Nat = axiom
+                let 5: Nat = axiom
+                let 6: Nat = axiom
+                let 7: Nat = axiom
+                let 8: Nat = axiom
+                let 9: Nat = axiom
+                define read(self, other: Nat) -> Nat { axiom }
+            }
+            numerals Nat
+        "#,
+        );
+        env.bad(r#"let s: Nat = "no string type yet""#);
+        env.add(
+            r#"
+            type Str: axiom
+            class Str {
+                let empty: Str = axiom
+                define append(self, c: Nat) -> Str { axiom }
+            }
+            strings Str
+            let hello: Str = "hello"
+            theorem ab_def { "ab" = Str.empty.append(97).append(98) }
+        "#,
+        );
+        env.bad(r#"let n: Nat = "a""#);
+        env.bad(r#"let s: Str = "bad \q escape""#);
+        env.bindings.expect_good_code(r#""""#);
+        env.bindings.expect_good_code(r#""hello world""#);
+        env.bindings.expect_good_code(r#""say \"hi\"\n""#);
+        env.bindings.expect_good_code(r#"hello = "hello""#);
+    }
+
+    #[test]
+    fn test_decimal_literals() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Rat: axiom
+            class Rat {
+                let 0: Rat = axiom
+                let 1: Rat = axiom
+                let 2: Rat = axiom
+                let 3: Rat = axiom
+                let 4: Rat = axiom
+                let 5: Rat = axiom
+                let 6: Rat = axiom
+                let 7: Rat = axiom
+                let 8: Rat = axiom
+                let 9: Rat = axiom
+                define read(self, other: Rat) -> Rat { axiom }
+                define from_parts(self, other: Rat) -> Rat { axiom }
+                define div(self, other: Rat) -> Rat { axiom }
+            }
+        "#,
+        );
+        env.bad("let a: Rat = 3.14");
+        env.add("decimals Rat");
+        env.add("let pi: Rat = 3.14");
+        env.add("let half: Rat = Rat.0.5");
+        env.add("theorem half_def { 0.5 = Rat.from_parts(Rat.5, Rat.10) }");
+        env.bad("let b: Bool = 3.14");
+        env.bad("let c: Rat = 3.14.15");
+        env.bindings.expect_good_code("3.14");
+        env.bindings.expect_good_code("0.05");
+        env.bindings.expect_good_code("1.50 / 0.0");
+        env.bindings.expect_good_code("pi = 3.14");
+        env.bindings.expect_good_code("Rat.1 / Rat.2");
+        env.add("numerals Rat");
+        env.bindings.expect_good_code("1 / 2");
+        env.bindings.expect_good_code("1 / 2 = 0.5");
+    }
+
+    #[test]
+    fn test_decimal_literals_need_from_parts() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Rat: axiom
+            class Rat {
+                let 0: Rat = axiom
+                let 1: Rat = axiom
+                define read(self, other: Rat) -> Rat { axiom }
+            }
+            decimals Rat
+        "#,
+        );
+        env.bad("let a: Rat = 1.1");
+        env.bad(
+            r#"
+            class Rat {
+                define from_parts(self, other: Bool) -> Rat { axiom }
+            }
+        "#,
+        );
+        env.bad(
+            r#"
+            class Rat {
+                let 1.5: Rat = axiom
+            }
+        "#,
+        );
+    }
+
+    #[test]
+    fn test_list_literals() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            let one: Nat = Nat.suc(Nat.zero)
+            inductive NatList {
+                nil
+                cons(Nat, NatList)
+            }
+        "#,
+        );
+        env.bad("let l: NatList = []");
+        env.add(
+            r#"
+            lists NatList
+            let empty: NatList = []
+            let ones: NatList = [one, one]
+            theorem ones_def { ones = NatList.cons(one, NatList.cons(one, NatList.nil)) }
+            define first(l: NatList) -> Nat {
+                match l {
+                    NatList.nil { Nat.zero }
+                    NatList.cons(head, tail) { head }
+                }
+            }
+            theorem first_one { first([one, Nat.zero]) = one }
+            theorem nested_singleton(x: Nat) { first([x]) = x }
+        "#,
+        );
+        env.bad("let bad_element: NatList = [NatList.nil]");
+        env.bad("let bad_type: Nat = [one]");
+        env.bindings.expect_good_code("[]");
+        env.bindings.expect_good_code("[one, Nat.zero]");
+        env.bindings.expect_good_code("first([one]) = one");
+        env.bindings.expect_good_code("NatList.cons(one, ones)");
+    }
+
+    #[test]
+    fn test_lists_requires_constructors() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Thing: axiom
+            let thing: Thing = axiom
+            type Things: axiom
+            class Things {
+                let nil: Things = axiom
+            }
+            lists Things
+        "#,
+        );
+        env.bad("let t: Things = []");
+    }
+
+    #[test]
+    fn test_lists_rejects_generic_constructors() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Things: axiom
+            class Things {
+                define nil<T>(self, x: T) -> Things { axiom }
+            }
+            lists Things
+        "#,
+        );
+        env.bad("let t: Things = []");
+    }
+
+    #[test]
+    fn test_set_builder() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let is_zero: Nat -> Bool = axiom
+            define contains(s: Nat -> Bool, x: Nat) -> Bool { s(x) }
+            let zeros: Nat -> Bool = { x: Nat | is_zero(x) }
+            theorem zero_in_zeros { contains({ n: Nat | n = zero }, zero) }
+        "#,
+        );
+        env.bad("let bad: Nat = { x: Nat | is_zero(x) }");
+        env.bad("let bad: Nat -> Bool = { x: Nat | zero }");
+        env.bad("let bad: Nat -> Bool = { zero: Nat | true }");
+        env.bindings
+            .expect_good_code("contains(function(x0: Nat) { x0 = zero }, zero)");
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            structure Pair {
+                first: Nat
+                second: Bool
+            }
+            let p: Pair = Pair.new(zero, true)
+            let (a, b) = p
+            let Pair.new(c, d) = Pair.new(zero, false)
+            theorem goal { a = p.first and b = p.second }
+            "#,
+        );
+        env.expect_type("a", "Nat");
+        env.expect_type("d", "Bool");
+        env.expect_def("a", "Pair.first(p)");
+        env.expect_def("c", "Pair.first(Pair.new(zero, false))");
+        env.bad("let (e) = p");
+        env.bad("let (e, f, g) = p");
+        env.bad("let (e, a) = p");
+        env.bad("let (e, f) = zero");
+        env.bad("let Nat.new(e, f) = p");
+        env.bad("let (E, f) = p");
+    }
+
+    #[test]
+    fn test_refinement_types() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            let big: { n: Nat | n != zero } = axiom
+            let one: { n: Nat | n != zero } = suc(zero)
+            define double(a: Nat) -> { n: Nat | n != zero } { suc(suc(a)) }
+            theorem nonzero(a: { n: Nat | n != zero }) { a != zero }
+            "#,
+        );
+        assert_eq!(env.iter_goals().count(), 3);
+        env.bad("define bad(a: { n: Nat | n != zero }) -> Nat { a }");
+        env.bad("theorem bad { forall(x: { n: Nat | n != zero }) { x = x } }");
+        env.bad("let bad: { n: Nat | n } = axiom");
+        env.bad("let bad: { n: Nat | n != zero } = true");
+        env.bad("let bad: Nat -> { n: Nat | n != zero } = axiom");
+        env.bad("let bad(a: { n: Nat | n != zero }) -> b: Nat satisfy { a = b }");
+    }
+
+    #[test]
+    fn test_implicit_type_params() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            define id{T}(x: T) -> T { x }
+            define const<T>(n: Nat) -> T { axiom }
+            let z: Nat = id(zero)
+            theorem id_eq{T}(x: T) { id(x) = x }
+            theorem id_zero { id(zero) = zero }
+        "#,
+        );
+        env.bad("define bad{T}(n: Nat) -> T { axiom }");
+        env.bad("theorem bad_theorem{T}(n: Nat) { true }");
+        env.bindings.expect_good_code("id(zero) = zero");
+    }
+
+    #[test]
+    fn test_explicit_type_params() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            define id<T>(x: T) -> T { x }
+            define const<T>(n: Nat) -> T { axiom }
+            let id_nat: Nat -> Nat = id<Nat>
+            let b: Bool = const<Bool>(zero)
+            theorem id_applied { id<Nat>(zero) = zero }
+        "#,
+        );
+        env.bad("let f: Nat -> Nat = id<Bool>");
+        env.bad("let f: Nat -> Nat = id<Nat, Nat>");
+        env.bad("let f: Nat = zero<Nat>");
+        env.bindings.expect_good_code("id<Nat>");
+        env.bindings.expect_good_code("id(zero)");
+        env.bindings.expect_good_code("const<Bool>(zero)");
+    }
+
+    #[test]
+    fn test_coercions() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            type Int: axiom
+            let n: Nat = axiom
+            let i: Int = axiom
+            let nat_to_int: Nat -> Int = axiom
+            class Int {
+                define add(self, other: Int) -> Int { axiom }
+            }
+            let neg: Int -> Int = axiom
+        "#,
+        );
+        env.bad("let j: Int = n");
+        env.bad("coercion n");
+        env.bad("coercion neg");
+        env.add("coercion nat_to_int");
+        env.bad("coercion nat_to_int");
+        env.add(
+            r#"
+            let j: Int = n
+            let k: Int = i + n
+            theorem coerced { neg(n) = neg(nat_to_int(n)) }
+        "#,
+        );
+        env.bad("let m: Nat = i");
+        env.bindings.expect_good_code("neg(n)");
+        env.bindings.expect_good_code("i + n");
+        env.bindings.expect_good_code("nat_to_int(n) = i");
+        env.bindings.expect_good_code("nat_to_int(n) + i");
+    }
+
+    #[test]
+    fn test_shadowing_in_blocks() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let k: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            forall(k: Nat) {
+                forall(k: Nat) {
+                    suc(k) = suc(k)
+                }
+                shadow let k: Nat = suc(k)
+                suc(k) = suc(k)
+            }
+            shadow forall(k: Nat) {
+                k = k
+            }
+        "#,
+        );
+        env.check_lines();
+        env.bad("shadow let k: Nat = suc(k)");
+        env.bad("let k: Nat = suc(k)");
+        env.bad("forall(Nat: Bool) { true }");
+        env.bad("forall(j: Nat, j: Nat) { true }");
+        env.bindings.expect_good_code("suc(k)");
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let mut env = Environment::new_test();
+        env.add(
+            r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+        "#,
+        );
+        let num_nodes = env.nodes.len();
+        let num_lines = env.line_types.len();
+        let snapshot = env.snapshot();
+        env.add(
+            r#"
+            let one: Nat = axiom
+            theorem one_is_zero { one = zero }
+        "#,
+        );
+        env.bindings.expect_good_code("one");
+        env.restore(snapshot);
+        assert_eq!(env.nodes.len(), num_nodes);
+        assert_eq!(env.line_types.len(), num_lines);
+        assert!(!env.bindings.has_identifier("one"));
+        env.add("let one: Nat = zero");
+        env.bindings.expect_good_code("zero");
+    }
+
+    #[test]
+    fn test_import_analysis() {
+        let mut p = Project::new_mock();
+        p.mock(
+            "/mock/foo.ac",
+            r#"
+            type Foo: axiom
+            let foo_zero: Foo = axiom
+            let foo_one: Foo = axiom
+            "#,
+        );
+        p.mock(
+            "/mock/bar.ac",
+            r#"
+            from foo import Foo
+            let bar: Foo = axiom
+            "#,
+        );
+        p.mock(
+            "/mock/main.ac",
+            r#"
+            from foo import Foo, foo_zero, foo_one
+            theorem goal { bar = foo_zero }
+            "#,
+        );
+        p.expect_ok("bar");
+        let descriptor = p
+            .descriptor_from_path(&PathBuf::from("/mock/main.ac"))
+            .unwrap();
+        let analysis = p.analyze_imports(&descriptor).unwrap();
+        assert!(analysis.required_modules.contains("foo"));
+        let unused: Vec<_> = analysis.unused.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(unused, vec!["Foo", "foo_one"]);
+        assert_eq!(analysis.missing.len(), 1);
+        assert_eq!(analysis.missing[0].name, "bar");
+        let modules: Vec<_> = analysis.missing[0]
+            .options
+            .iter()
+            .map(|(module, _)| module.as_str())
+            .collect();
+        assert_eq!(modules, vec!["bar"]);
+    }
 }
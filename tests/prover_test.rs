@@ -5,6 +5,7 @@ mod prover_test {
     use acorn::code_gen_error::CodeGenError;
     use acorn::module::LoadState;
     use acorn::project::Project;
+    use acorn::proof_step::{ProofStepId, Rule};
     use acorn::prover::{Outcome, Prover};
 
     // Tries to prove one thing from the project.
@@ -37,7 +38,10 @@ mod prover_test {
             panic!("prover error: {}", prover.error.unwrap());
         }
         let code = match prover.get_and_print_proof() {
-            Some(proof) => proof.to_code(&env.bindings),
+            Some(proof) => {
+                let block_env = env.env_for_line(goal_context.proof_insertion_line);
+                proof.to_code(&block_env.bindings)
+            }
             None => Err(CodeGenError::NoProof),
         };
         (outcome, code)
@@ -517,6 +521,30 @@ mod prover_test {
         assert_eq!(prove_text(text, "goal"), Outcome::Success);
     }
 
+    #[test]
+    fn test_combining_constructor_properties() {
+        let text = r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            theorem goal(a: Nat, b: Nat) { a.suc.suc = b.suc -> b != Nat.zero }
+        "#;
+        assert_eq!(prove_text(text, "goal"), Outcome::Success);
+    }
+
+    #[test]
+    fn test_inductive_values_are_acyclic() {
+        let text = r#"
+            inductive Nat {
+                zero
+                suc(Nat)
+            }
+            theorem goal(a: Nat, b: Nat) { a != b.suc or b != a.suc }
+        "#;
+        assert_eq!(prove_text(text, "goal"), Outcome::Success);
+    }
+
     #[test]
     fn test_prover_gets_structural_induction() {
         let text = r#"
@@ -538,6 +566,19 @@ mod prover_test {
         assert_eq!(prove_text(text, "goal"), Outcome::Success);
     }
 
+    #[test]
+    fn test_instantiating_predicate_variable_with_partial_application() {
+        let text = r#"
+            type Nat: axiom
+            let c: Nat = axiom
+            let d: Nat = axiom
+            let g: (Nat, Nat) -> Bool = axiom
+            axiom transfer(f: Nat -> Bool) { f(c) -> f(d) }
+            theorem goal(a: Nat) { g(a, c) -> g(a, d) }
+        "#;
+        assert_eq!(prove_text(text, "goal"), Outcome::Success);
+    }
+
     #[test]
     fn test_proving_parametric_theorem_basic() {
         let text = r#"
@@ -809,19 +850,32 @@ mod prover_test {
     //     "#,
     //     );
     // }
-    //
-    // #[test]
-    // fn test_functional_equality_implication() {
-    //     prove_all_succeeds(
-    //         r#"
-    //         type Nat: axiom
-    //         let f: Nat -> Nat = axiom
-    //         let g: Nat -> Nat = axiom
-    //         let p: (Nat -> Nat) -> Nat = axiom
-    //         theorem goal { forall(x: Nat) { f(x) = g(x) } -> p(f) = p(g) }
-    //         "#,
-    //     );
-    // }
+
+    #[test]
+    fn test_functional_equality_implication() {
+        verify_succeeds(
+            r#"
+            type Nat: axiom
+            let f: Nat -> Nat = axiom
+            let g: Nat -> Nat = axiom
+            let p: (Nat -> Nat) -> Nat = axiom
+            theorem goal { forall(x: Nat) { f(x) = g(x) } -> p(f) = p(g) }
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_proving_with_choice() {
+        verify_succeeds(
+            r#"
+            type Nat: axiom
+            let ltx: (Nat, Nat) -> Bool = axiom
+            axiom unbounded(a: Nat) { exists(b: Nat) { ltx(a, b) } }
+            define next(a: Nat) -> Nat { choose b: Nat where ltx(a, b) }
+            theorem goal(a: Nat) { ltx(a, next(a)) }
+        "#,
+        );
+    }
 
     #[test]
     fn test_proving_with_partial_application() {
@@ -1413,204 +1467,795 @@ mod prover_test {
     }
 
     #[test]
-    fn test_prove_with_match_statement() {
-        // An example found when migrating pre-match code.
+    fn test_prove_with_match_guards() {
         let text = r#"
-        type Nat: axiom
-        class Nat {
-            define suc(self) -> Nat { axiom }
+        inductive Nat {
+            zero
+            suc(Nat)
         }
-
-        inductive Int {
-            from_nat(Nat)
-            neg_suc(Nat)
+        define pred_or_zero(n: Nat) -> Nat {
+            match n {
+                Nat.suc(k) if k != Nat.zero {
+                    k
+                }
+                Nat.suc(k) {
+                    Nat.zero
+                }
+                Nat.zero {
+                    Nat.zero
+                }
+            }
         }
-        
-        define abs_case_1(a: Int, n: Nat) -> Bool {
-            a = Int.from_nat(n)
+        theorem goal(n: Nat) {
+            pred_or_zero(Nat.suc(Nat.suc(n))) = Nat.suc(n)
         }
-        
-        define abs_case_2(a: Int, n: Nat) -> Bool {
-            exists(k: Nat) {
-                a = Int.neg_suc(k) and n = k.suc
-            }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_with_guarded_match_statement() {
+        let text = r#"
+        inductive Nat {
+            zero
+            suc(Nat)
         }
-        
-        define abs(a: Int) -> Nat {
-            match a {
-                Int.from_nat(n) {
-                    n
+        forall(n: Nat) {
+            match n {
+                Nat.suc(k) if k = Nat.zero {
+                    n = Nat.suc(Nat.zero)
                 }
-                Int.neg_suc(k) {
-                    k.suc
+                Nat.suc(k) {
+                    k != Nat.zero
+                }
+                Nat.zero {
+                    n = Nat.zero
                 }
             }
         }
-        
-        theorem goal(a: Int) {
-            abs_case_1(a, abs(a)) or abs_case_2(a, abs(a))
-        } by {
-            match a {
-                Int.from_nat(n) {
-                    abs_case_1(a, abs(a))
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_with_match_inside_term() {
+        let text = r#"
+        inductive Nat {
+            zero
+            suc(Nat)
+        }
+        let double: Nat -> Nat = axiom
+        define double_pred(n: Nat) -> Nat {
+            double(match n {
+                Nat.zero {
+                    Nat.zero
                 }
-                Int.neg_suc(k) {
-                    abs_case_2(a, abs(a))
+                Nat.suc(k) {
+                    k
                 }
-            }
+            })
+        }
+        theorem goal(n: Nat) {
+            double_pred(n.suc) = double(n)
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_prove_with_recursive_function() {
+    fn test_prove_with_nested_match() {
         let text = r#"
         inductive Nat {
             zero
             suc(Nat)
         }
-        define repeat<T>(n: Nat, f: T -> T, a: T) -> T {
+        define even(n: Nat) -> Bool {
             match n {
                 Nat.zero {
-                    a
+                    true
                 }
-                Nat.suc(pred) {
-                    repeat(pred, f, f(a))
+                Nat.suc(Nat.zero) {
+                    false
+                }
+                Nat.suc(Nat.suc(k)) {
+                    even(k)
                 }
             }
         }
         theorem goal(n: Nat) {
-            repeat(Nat.zero, Nat.suc, n) = n
+            even(n) implies even(n.suc.suc)
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_prove_with_anonymous_axiom() {
+    fn test_prove_with_nested_match_statement() {
         let text = r#"
-        let b: Bool = axiom
-        axiom foo {
-            b
+        inductive Nat {
+            zero
+            suc(Nat)
         }
-        theorem goal {
-            b
+        let one: Nat = Nat.zero.suc
+        theorem goal(n: Nat) {
+            n = Nat.zero or n = one or exists(k: Nat) { n = k.suc.suc }
+        } by {
+            match n {
+                Nat.zero {
+                    n = Nat.zero
+                }
+                Nat.suc(Nat.zero) {
+                    n = one
+                }
+                Nat.suc(Nat.suc(k)) {
+                    n = k.suc.suc
+                }
+            }
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_prove_easy_constraint() {
+    fn test_proving_with_mutual_recursion() {
         let text = r#"
-        structure Foo {
-            first: Bool
-            second: Bool
-        } constraint {
-            first or second
+        inductive Nat {
+            zero
+            suc(Nat)
+        }
+        define is_even(n: Nat) -> Bool {
+            match n {
+                Nat.zero {
+                    true
+                }
+                Nat.suc(pred) {
+                    is_odd(pred)
+                }
+            }
+        } and is_odd(n: Nat) -> Bool {
+            match n {
+                Nat.zero {
+                    false
+                }
+                Nat.suc(pred) {
+                    is_even(pred)
+                }
+            }
+        }
+        theorem goal {
+            is_even(Nat.zero.suc.suc)
+        } by {
+            is_even(Nat.zero)
+            is_odd(Nat.zero.suc)
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_prove_impossible_constraint() {
+    fn test_opaque_definition_not_unfolded() {
         let text = r#"
-        structure Foo {
-            first: Bool
-        } constraint {
-            first and not first
+        type Nat: axiom
+        let zero: Nat = axiom
+        let suc: Nat -> Nat = axiom
+        opaque define two -> Nat {
+            suc(suc(zero))
+        }
+        theorem goal {
+            two = suc(suc(zero))
         }
         "#;
         verify_fails(text);
     }
 
     #[test]
-    fn test_prove_constraint_equation() {
+    fn test_opaque_definition_unfolded() {
         let text = r#"
-        structure Foo {
-            first: Bool
-            second: Bool
-        } constraint {
-            first or second
+        type Nat: axiom
+        let zero: Nat = axiom
+        let suc: Nat -> Nat = axiom
+        opaque define add_two(n: Nat) -> Nat {
+            suc(suc(n))
         }
-        theorem goal(f: Foo) {
-            f.first or f.second
+        theorem goal(n: Nat) {
+            add_two(n) = suc(suc(n))
+        } by {
+            unfold add_two
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_prove_constrained_member_equation() {
+    fn test_proving_with_lemma() {
         let text = r#"
-        type Foo: axiom
-        let foo: Foo = axiom
-        let foof: Foo -> Bool = axiom
-        axiom {
-            foof(foo)
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        let g: Nat -> Nat = axiom
+        axiom fg(x: Nat) {
+            f(x) = g(x)
         }
-
-        structure Bar {
-            f: Foo
-        } constraint {
-            foof(f)
+        axiom gf(x: Nat) {
+            g(f(x)) = x
         }
-        theorem goal(f: Foo) {
-            foof(f) -> Bar.new(f).f = f
+        theorem goal(a: Nat) {
+            g(g(a)) = a and g(g(f(a))) = f(a)
+        } by {
+            lemma gg(x: Nat) {
+                g(g(x)) = x
+            } by {
+                f(x) = g(x)
+                g(f(x)) = x
+            }
+            gg(a)
+            gg(f(a))
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_prove_member_equation_requires_constraint() {
-        // This shouldn't work, because maybe Bar.new(f) doesn't meet the constraint.
+    fn test_proving_with_labels() {
         let text = r#"
-        type Foo: axiom
-        let foo: Foo = axiom
-        let foof: Foo -> Bool = axiom
-        axiom {
-            foof(foo)
-        }
-
-        structure Bar {
-            f: Foo
-        } constraint {
-            foof(f)
-        }
-        theorem goal(f: Foo) {
-            Bar.new(f).f = f
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        let g: Nat -> Nat = axiom
+        theorem goal(a: Nat, b: Nat) {
+            h: f(a) = b and g(b) = a implies g(f(a)) = a
+        } by {
+            h
+            fact: g(f(a)) = g(b)
+            fact
         }
         "#;
-        verify_fails(text);
+        verify_succeeds(text);
     }
 
     #[test]
-    fn test_proving_boolean_equality() {
+    fn test_proving_with_assume() {
         let text = r#"
-        let a: Bool = axiom
-        let b: Bool = axiom
-        axiom {
-            a -> b
-        }
-        axiom {
-            b -> a
-        }
-        theorem goal {
-            a = b
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        let g: Nat -> Nat = axiom
+        theorem goal(a: Nat, b: Nat) {
+            f(a) = b implies (g(b) = a implies g(f(a)) = a)
+        } by {
+            assume g(b) = a
+            g(f(a)) = g(b)
         }
         "#;
         verify_succeeds(text);
     }
 
     #[test]
-    fn test_proving_with_implies_keyword() {
+    fn test_proving_with_suffices() {
         let text = r#"
-        let a: Bool = axiom
-        theorem {
-            a implies a
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        let g: Nat -> Nat = axiom
+        axiom fg(x: Nat) {
+            f(g(x)) = x
+        }
+        theorem goal(a: Nat, b: Nat) {
+            g(a) = g(b) implies a = b
+        } by {
+            suffices f(g(a)) = f(g(b)) by {
+                f(g(a)) = a
+                f(g(b)) = b
+            }
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_suffices_can_fail() {
+        let text = r#"
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        theorem goal(a: Nat, b: Nat) {
+            a = b
+        } by {
+            suffices f(a) = f(b)
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_by_contradiction() {
+        let text = r#"
+        type Nat: axiom
+        let zero: Nat = axiom
+        let suc: Nat -> Nat = axiom
+        axiom suc_ne_zero(n: Nat) {
+            suc(n) != zero
+        }
+        axiom suc_injective(a: Nat, b: Nat) {
+            suc(a) = suc(b) implies a = b
+        }
+        theorem goal(a: Nat) {
+            suc(suc(a)) != suc(zero)
+        } by {
+            by_contradiction {
+                suc(a) = zero
+                false
+            }
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_by_contradiction_can_fail() {
+        let text = r#"
+        type Nat: axiom
+        let zero: Nat = axiom
+        let suc: Nat -> Nat = axiom
+        theorem goal(a: Nat) {
+            suc(a) != zero
+        } by {
+            by_contradiction {
+                false
+            }
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_with_induction_statement() {
+        let text = r#"
+        inductive Nat {
+            zero
+            suc(Nat)
+        }
+        let add: (Nat, Nat) -> Nat = axiom
+        axiom add_zero(a: Nat) {
+            add(Nat.zero, a) = a
+        }
+        axiom add_suc(a: Nat, b: Nat) {
+            add(Nat.suc(a), b) = Nat.suc(add(a, b))
+        }
+        theorem goal(n: Nat) {
+            add(n, Nat.zero) = n
+        } by {
+            induction n {
+                Nat.zero {
+                    add(Nat.zero, Nat.zero) = Nat.zero
+                }
+                Nat.suc(k) {
+                    add(Nat.suc(k), Nat.zero) = Nat.suc(add(k, Nat.zero))
+                }
+            }
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_proving_with_automatic_induction() {
+        let text = r#"
+        inductive Nat {
+            zero
+            suc(Nat)
+        }
+        let add: (Nat, Nat) -> Nat = axiom
+        axiom add_zero(a: Nat) {
+            add(Nat.zero, a) = a
+        }
+        axiom add_suc(a: Nat, b: Nat) {
+            add(Nat.suc(a), b) = Nat.suc(add(a, b))
+        }
+        theorem goal(n: Nat) {
+            add(n, Nat.zero) = n
+        }
+        theorem forall_goal {
+            forall(n: Nat) {
+                add(add(n, Nat.zero), Nat.zero) = n
+            }
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_induction_statement_can_fail() {
+        let text = r#"
+        inductive Nat {
+            zero
+            suc(Nat)
+        }
+        let f: Nat -> Nat = axiom
+        theorem goal(n: Nat) {
+            f(n) = n
+        } by {
+            induction n {
+                Nat.zero {
+                }
+                Nat.suc(k) {
+                }
+            }
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_with_rewrite() {
+        let text = r#"
+        type Nat: axiom
+        let zero: Nat = axiom
+        let add: (Nat, Nat) -> Nat = axiom
+        let f: Nat -> Nat = axiom
+        axiom add_comm(a: Nat, b: Nat) {
+            add(a, b) = add(b, a)
+        }
+        theorem goal(a: Nat, b: Nat) {
+            h: f(add(a, b)) = zero implies f(add(b, a)) = zero
+        } by {
+            rewrite add_comm(a, b) in h
+            rewrite add_comm(b, a)
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_proving_with_have() {
+        let text = r#"
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        let g: Nat -> Nat = axiom
+        axiom fg(x: Nat) {
+            f(g(x)) = x
+        }
+        theorem goal(a: Nat, b: Nat) {
+            g(a) = g(b) implies a = b
+        } by {
+            have h: f(g(a)) = f(g(b)) by {
+                g(a) = g(b)
+            }
+            h
+            a = f(g(a))
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_have_can_fail() {
+        let text = r#"
+        type Nat: axiom
+        let f: Nat -> Nat = axiom
+        theorem goal(a: Nat, b: Nat) {
+            f(a) = f(b)
+        } by {
+            have h: a = b by {
+                f(a) = f(a)
+            }
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_with_constrained_forall() {
+        let text = r#"
+        type Nat: axiom
+        let zero: Nat = axiom
+        let pred: Nat -> Nat = axiom
+        let suc: Nat -> Nat = axiom
+        axiom suc_pred(x: Nat) {
+            x != zero implies suc(pred(x)) = x
+        }
+        theorem goal(a: Nat) {
+            a != zero implies suc(pred(suc(pred(a)))) = a
+        } by {
+            forall(x: Nat) where x != zero {
+                suc(pred(x)) = x
+            }
+            suc(pred(a)) = a
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_constrained_forall_exports_implication() {
+        // The claim exported from the block only holds when the constraint does.
+        let text = r#"
+        type Nat: axiom
+        let zero: Nat = axiom
+        let pred: Nat -> Nat = axiom
+        let suc: Nat -> Nat = axiom
+        axiom suc_pred(x: Nat) {
+            x != zero implies suc(pred(x)) = x
+        }
+        forall(x: Nat) where x != zero {
+            suc(pred(x)) = x
+        }
+        theorem goal {
+            suc(pred(zero)) = zero
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_with_overloads() {
+        let text = r#"
+        type Nat: axiom
+        type Int: axiom
+        let zero: Nat = axiom
+        let zero: Int = axiom
+        let double: Nat -> Nat = axiom
+        define double(x: Int) -> Int { axiom }
+        axiom double_zero(a: Nat) { a = zero implies double(a) = a }
+        theorem goal(a: Nat) { a = zero implies double(double(a)) = a }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_overloads_are_distinct() {
+        // Knowing about one overload says nothing about the others.
+        let text = r#"
+        type Nat: axiom
+        type Int: axiom
+        let nzero: Nat = axiom
+        let izero: Int = axiom
+        let double: Nat -> Nat = axiom
+        define double(x: Int) -> Int { axiom }
+        axiom double_zero { double(nzero) = nzero }
+        theorem goal { double(izero) = izero }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_decreasing_measure() {
+        let text = r#"
+        type Nat: axiom
+        class Nat {
+            define lt(self, other: Nat) -> Bool { axiom }
+        }
+        let zero: Nat = axiom
+        let pred: Nat -> Nat = axiom
+        axiom pred_lt(n: Nat) {
+            n != zero implies pred(n) < n
+        }
+        define countdown(n: Nat) -> Nat decreasing n {
+            if n = zero {
+                zero
+            } else {
+                countdown(pred(n))
+            }
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_decreasing_measure_can_fail() {
+        // Without knowing when pred descends, the measure isn't justified.
+        let text = r#"
+        type Nat: axiom
+        class Nat {
+            define lt(self, other: Nat) -> Bool { axiom }
+        }
+        let zero: Nat = axiom
+        let pred: Nat -> Nat = axiom
+        define countdown(n: Nat) -> Nat decreasing n {
+            if n = zero {
+                zero
+            } else {
+                countdown(pred(n))
+            }
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_prove_with_match_statement() {
+        // An example found when migrating pre-match code.
+        let text = r#"
+        type Nat: axiom
+        class Nat {
+            define suc(self) -> Nat { axiom }
+        }
+
+        inductive Int {
+            from_nat(Nat)
+            neg_suc(Nat)
+        }
+        
+        define abs_case_1(a: Int, n: Nat) -> Bool {
+            a = Int.from_nat(n)
+        }
+        
+        define abs_case_2(a: Int, n: Nat) -> Bool {
+            exists(k: Nat) {
+                a = Int.neg_suc(k) and n = k.suc
+            }
+        }
+        
+        define abs(a: Int) -> Nat {
+            match a {
+                Int.from_nat(n) {
+                    n
+                }
+                Int.neg_suc(k) {
+                    k.suc
+                }
+            }
+        }
+        
+        theorem goal(a: Int) {
+            abs_case_1(a, abs(a)) or abs_case_2(a, abs(a))
+        } by {
+            match a {
+                Int.from_nat(n) {
+                    abs_case_1(a, abs(a))
+                }
+                Int.neg_suc(k) {
+                    abs_case_2(a, abs(a))
+                }
+            }
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_with_recursive_function() {
+        let text = r#"
+        inductive Nat {
+            zero
+            suc(Nat)
+        }
+        define repeat<T>(n: Nat, f: T -> T, a: T) -> T {
+            match n {
+                Nat.zero {
+                    a
+                }
+                Nat.suc(pred) {
+                    repeat(pred, f, f(a))
+                }
+            }
+        }
+        theorem goal(n: Nat) {
+            repeat(Nat.zero, Nat.suc, n) = n
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_with_anonymous_axiom() {
+        let text = r#"
+        let b: Bool = axiom
+        axiom foo {
+            b
+        }
+        theorem goal {
+            b
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_easy_constraint() {
+        let text = r#"
+        structure Foo {
+            first: Bool
+            second: Bool
+        } constraint {
+            first or second
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_impossible_constraint() {
+        let text = r#"
+        structure Foo {
+            first: Bool
+        } constraint {
+            first and not first
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_prove_constraint_equation() {
+        let text = r#"
+        structure Foo {
+            first: Bool
+            second: Bool
+        } constraint {
+            first or second
+        }
+        theorem goal(f: Foo) {
+            f.first or f.second
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_constrained_member_equation() {
+        let text = r#"
+        type Foo: axiom
+        let foo: Foo = axiom
+        let foof: Foo -> Bool = axiom
+        axiom {
+            foof(foo)
+        }
+
+        structure Bar {
+            f: Foo
+        } constraint {
+            foof(f)
+        }
+        theorem goal(f: Foo) {
+            foof(f) -> Bar.new(f).f = f
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_prove_member_equation_requires_constraint() {
+        // This shouldn't work, because maybe Bar.new(f) doesn't meet the constraint.
+        let text = r#"
+        type Foo: axiom
+        let foo: Foo = axiom
+        let foof: Foo -> Bool = axiom
+        axiom {
+            foof(foo)
+        }
+
+        structure Bar {
+            f: Foo
+        } constraint {
+            foof(f)
+        }
+        theorem goal(f: Foo) {
+            Bar.new(f).f = f
+        }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_boolean_equality() {
+        let text = r#"
+        let a: Bool = axiom
+        let b: Bool = axiom
+        axiom {
+            a -> b
+        }
+        axiom {
+            b -> a
+        }
+        theorem goal {
+            a = b
+        }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_proving_with_implies_keyword() {
+        let text = r#"
+        let a: Bool = axiom
+        theorem {
+            a implies a
         }
         theorem {
             not a implies not a
@@ -1669,4 +2314,572 @@ mod prover_test {
         "#;
         verify_succeeds(text);
     }
+
+    #[test]
+    fn test_no_auto_theorem_is_not_used() {
+        let text = r#"
+            type Thing: axiom
+            let t: Thing = axiom
+            let f: Thing -> Bool = axiom
+            @no_auto axiom fx(x: Thing) { f(x) }
+            theorem goal { f(t) }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_no_auto_theorem_can_be_cited() {
+        let text = r#"
+            type Thing: axiom
+            let t: Thing = axiom
+            let f: Thing -> Bool = axiom
+            @no_auto axiom fx(x: Thing) { f(x) }
+            theorem goal { f(t) } by {
+                fx(t)
+            }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_simp_theorem() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            @simp axiom add_zero(a: Nat) { add(a, zero) = a }
+            theorem goal(a: Nat) { add(add(a, zero), zero) = a }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_ac_normalization() {
+        let text = r#"
+            type Nat: axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_comm(a: Nat, b: Nat) { add(a, b) = add(b, a) }
+            axiom add_assoc(a: Nat, b: Nat, c: Nat) { add(add(a, b), c) = add(a, add(b, c)) }
+            theorem goal(a: Nat, b: Nat, c: Nat, d: Nat) {
+                add(add(d, c), add(b, a)) = add(a, add(b, add(c, d)))
+            }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_proving_refinement_obligations() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            axiom suc_ne_zero(a: Nat) { suc(a) != zero }
+            let one: { n: Nat | n != zero } = suc(zero)
+            define double(a: Nat) -> { n: Nat | n != zero } { suc(suc(a)) }
+            theorem goal(a: { n: Nat | n != zero }, b: Nat) { a != zero and double(b) != zero }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_refinement_obligations_can_fail() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            let one: { n: Nat | n != zero } = suc(zero)
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_proving_with_destructuring_let() {
+        let text = r#"
+            type Nat: axiom
+            structure Pair {
+                first: Nat
+                second: Nat
+            }
+            let p: Pair = axiom
+            let (a, b) = p
+            theorem goal { Pair.new(a, b) = p }
+        "#;
+        verify_succeeds(text);
+    }
+
+    #[test]
+    fn test_shadowed_constants_stay_distinct() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            let k: Nat = zero
+            forall(n: Nat) {
+                shadow let k: Nat = suc(n)
+                k = suc(n)
+            }
+        "#;
+        verify_succeeds(text);
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            let k: Nat = zero
+            forall(n: Nat) {
+                shadow let k: Nat = suc(n)
+                k = zero
+            }
+        "#;
+        verify_fails(text);
+    }
+
+    #[test]
+    fn test_deterministic_search() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let suc: Nat -> Nat = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_zero(a: Nat) { add(a, zero) = a }
+            axiom add_suc(a: Nat, b: Nat) { add(a, suc(b)) = suc(add(a, b)) }
+            theorem goal { add(suc(zero), suc(zero)) = suc(suc(zero)) }
+        "#;
+        let search = || {
+            let mut project = Project::new_mock();
+            project.mock("/mock/main.ac", text);
+            project.set_deterministic(true);
+            let module_id = project.load_module_by_name("main").unwrap();
+            let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+                panic!("no module");
+            };
+            let node = env.get_node_by_name("goal");
+            let goal_context = node.goal_context().unwrap();
+            let mut prover = Prover::new(&project, false);
+            for fact in node.usable_facts(&project) {
+                prover.add_fact(fact);
+            }
+            prover.set_goal(&goal_context);
+            assert_eq!(prover.verification_search(), Outcome::Success);
+            let code = prover.get_proof().unwrap().to_code(&env.bindings).unwrap();
+            (prover.num_activated(), code)
+        };
+        assert_eq!(search(), search());
+    }
+
+    #[test]
+    fn test_resuming_from_checkpoint() {
+        let text = r#"
+            type Thing: axiom
+            let a: Thing = axiom
+            let b: Thing = axiom
+            let c: Thing = axiom
+            let d: Thing = axiom
+            let r: (Thing, Thing) -> Bool = axiom
+            axiom trans(x: Thing, y: Thing, z: Thing) { r(x, y) and r(y, z) implies r(x, z) }
+            axiom ab { r(a, b) }
+            axiom bc { r(b, c) }
+            axiom cd { r(c, d) }
+            theorem goal { r(a, d) }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        project.set_deterministic(true);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let new_prover = || {
+            let mut prover = Prover::new(&project, false);
+            for fact in node.usable_facts(&project) {
+                prover.add_fact(fact);
+            }
+            prover.set_goal(&goal_context);
+            prover
+        };
+        let finish = |mut prover: Prover| {
+            assert_eq!(prover.verification_search(), Outcome::Success);
+            let code = prover.get_proof().unwrap().to_code(&env.bindings).unwrap();
+            (prover.num_activated(), code)
+        };
+
+        let mut paused = new_prover();
+        for _ in 0..5 {
+            assert!(!paused.activate_next());
+        }
+        let saved = serde_json::to_string(&paused.checkpoint()).unwrap();
+        let mut resumed = new_prover();
+        resumed.resume(serde_json::from_str(&saved).unwrap());
+        assert_eq!(finish(resumed), finish(new_prover()));
+    }
+
+    #[test]
+    fn test_getting_lemmas() {
+        let text = r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            let d: Bool = axiom
+            axiom a_true { a }
+            axiom a_imp_b { a implies b }
+            axiom b_imp_c { b implies c }
+            axiom c_imp_d { c implies d }
+            theorem goal { d }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let mut prover = Prover::new(&project, false);
+        for fact in node.usable_facts(&project) {
+            prover.add_fact(fact);
+        }
+        prover.set_goal(&goal_context);
+        assert_eq!(prover.quick_search(), Outcome::Success);
+        let lemmas: Vec<_> = prover
+            .get_lemmas(&goal_context)
+            .iter()
+            .map(|fact| fact.value.to_string())
+            .collect();
+        assert_eq!(lemmas, vec!["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_case_split_on_boolean_argument() {
+        let text = r#"
+            type Nat: axiom
+            let c: Nat = axiom
+            let g: Bool -> Nat = axiom
+            let a: Bool = axiom
+            axiom g_true(b: Bool) { b -> g(b) = c }
+            axiom g_false(b: Bool) { not b -> g(b) = c }
+            theorem goal { g(a) = c }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let mut prover = Prover::new(&project, false);
+        prover.set_splitting(true);
+        for fact in node.usable_facts(&project) {
+            prover.add_fact(fact);
+        }
+        prover.set_goal(&goal_context);
+        assert_eq!(prover.quick_verification_search(), Outcome::Success);
+    }
+
+    #[test]
+    fn test_proof_step_justifications() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_zero(x: Nat) { add(x, zero) = x }
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom fa { f(add(a, zero)) }
+            theorem goal { g(a) }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let mut prover = Prover::new(&project, false);
+        for fact in node.usable_facts(&project) {
+            prover.add_fact(fact);
+        }
+        prover.set_goal(&goal_context);
+        assert_eq!(prover.quick_search(), Outcome::Success);
+        let proof = prover.get_proof().unwrap();
+        let bindings = &env.env_for_line(goal_context.proof_insertion_line).bindings;
+        let justifications: Vec<_> = prover
+            .to_proof_info(&project, bindings, &proof)
+            .into_iter()
+            .map(|info| info.justification)
+            .collect();
+        assert!(justifications.contains(&"assumed the negation of the goal".to_string()));
+        assert!(justifications.contains(&"combined f_imp_g with the negated goal".to_string()));
+        assert!(justifications
+            .iter()
+            .any(|j| j.starts_with("rewrote a to add(a, zero) using add_zero")));
+    }
+
+    #[test]
+    fn test_exporting_proof_dag() {
+        let text = r#"
+            type Nat: axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom fa { f(a) }
+            theorem goal { g(a) }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let mut prover = Prover::new(&project, false);
+        for fact in node.usable_facts(&project) {
+            prover.add_fact(fact);
+        }
+        prover.set_goal(&goal_context);
+        assert_eq!(prover.quick_search(), Outcome::Success);
+        let proof = prover.get_proof().unwrap();
+
+        let dot = proof.to_dot();
+        assert!(dot.starts_with("digraph proof {"));
+        assert!(dot.contains("[label=\"Resolution\"]"));
+        assert!(dot.contains("the 'fa' axiom"));
+
+        let json: serde_json::Value = serde_json::from_str(&proof.to_json()).unwrap();
+        let nodes = json["nodes"].as_array().unwrap();
+        let edges = json["edges"].as_array().unwrap();
+        assert_eq!(nodes.len(), proof.all_steps.len());
+        assert!(nodes.iter().any(|node| node["clause"] == "false"));
+
+        // Every edge connects two nodes of the proof.
+        let ids: Vec<_> = nodes.iter().map(|node| &node["id"]).collect();
+        for edge in edges {
+            assert!(ids.contains(&&edge["from"]));
+            assert!(ids.contains(&&edge["to"]));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_state() {
+        let text = r#"
+            type Nat: axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            let h: Nat -> Bool = axiom
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom g_imp_h(x: Nat) { g(x) -> h(x) }
+            axiom fa { f(a) }
+            theorem goal { h(a) }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let mut prover = Prover::new(&project, false);
+        for fact in node.usable_facts(&project) {
+            prover.add_fact(fact);
+        }
+        prover.set_goal(&goal_context);
+        let bindings = &env.bindings;
+
+        let before = prover.snapshot_state(bindings, 10);
+        assert!(!before.goal.is_empty());
+        assert!(before.recent_activations.is_empty());
+        assert_eq!(before.num_passive, before.next_passive.len());
+
+        assert!(!prover.activate_next());
+        assert!(!prover.activate_next());
+        let during = prover.snapshot_state(bindings, 1);
+        assert_eq!(during.num_activated, 2);
+        assert_eq!(during.recent_activations.len(), 1);
+        assert_eq!(during.recent_activations[0].id, Some(1));
+        assert_eq!(during.next_passive.len(), 1);
+
+        // The first passive clause is the one that gets activated next.
+        let next = during.next_passive[0].clause.text.clone();
+        assert!(!prover.activate_next());
+        let after = prover.snapshot_state(bindings, 1);
+        assert_eq!(after.recent_activations[0].text, next);
+    }
+
+    #[test]
+    fn test_finding_multiple_proofs() {
+        let text = r#"
+            let a: Bool = axiom
+            let b: Bool = axiom
+            let c: Bool = axiom
+            let d: Bool = axiom
+            axiom a_true { a }
+            axiom a_imp_c { a implies c }
+            axiom b_true { b }
+            axiom b_imp_d { b implies d }
+            theorem goal { c or d }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let new_prover = || {
+            let mut prover = Prover::new(&project, false);
+            for fact in node.usable_facts(&project) {
+                prover.add_fact(fact);
+            }
+            prover.set_goal(&goal_context);
+            prover
+        };
+
+        let mut single = new_prover();
+        assert_eq!(single.quick_search(), Outcome::Success);
+        assert_eq!(single.get_proofs().len(), 1);
+
+        let mut multiple = new_prover();
+        multiple.set_proofs_wanted(3);
+        assert_eq!(multiple.quick_search(), Outcome::Success);
+        let proofs = multiple.get_proofs();
+        assert_eq!(proofs.len(), 2);
+        let names: Vec<Vec<String>> = proofs
+            .iter()
+            .map(|proof| {
+                let mut names: Vec<_> = proof.iter_sources().map(|s| s.description()).collect();
+                names.sort();
+                names
+            })
+            .collect();
+        assert_ne!(names[0], names[1]);
+
+        // The first proof is the one that get_proof returns.
+        let first: Vec<_> = multiple
+            .get_proof()
+            .unwrap()
+            .iter_sources()
+            .map(|s| s.description())
+            .collect();
+        assert!(first.iter().all(|name| names[0].contains(name)));
+    }
+
+    #[test]
+    fn test_premise_locations() {
+        let text = r#"
+            type Nat: axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom fa { f(a) }
+            theorem goal { g(a) }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let mut prover = Prover::new(&project, false);
+        for fact in node.usable_facts(&project) {
+            prover.add_fact(fact);
+        }
+        prover.set_goal(&goal_context);
+        assert_eq!(prover.quick_search(), Outcome::Success);
+        let proof = prover.get_proof().unwrap();
+        let premises = prover.to_premise_info(&project, &proof);
+        let descriptions: Vec<_> = premises.iter().map(|p| p.description.as_str()).collect();
+        assert_eq!(descriptions.len(), 2);
+        assert!(descriptions.contains(&"the 'f_imp_g' axiom"));
+        assert!(descriptions.contains(&"the 'fa' axiom"));
+        for premise in &premises {
+            let location = premise.location.as_ref().unwrap();
+            assert!(location.uri.path().ends_with("/mock/main.ac"));
+        }
+        let fa = premises
+            .iter()
+            .find(|p| p.description == "the 'fa' axiom")
+            .unwrap();
+        assert_eq!(fa.location.as_ref().unwrap().range.start.line, 6);
+    }
+
+    #[test]
+    fn test_replaying_a_proof() {
+        let text = r#"
+            type Nat: axiom
+            let zero: Nat = axiom
+            let a: Nat = axiom
+            let f: Nat -> Bool = axiom
+            let g: Nat -> Bool = axiom
+            let add: (Nat, Nat) -> Nat = axiom
+            axiom add_zero(x: Nat) { add(x, zero) = x }
+            axiom f_imp_g(x: Nat) { f(x) -> g(x) }
+            axiom fa { f(add(a, zero)) }
+            theorem goal { g(a) }
+        "#;
+        let mut project = Project::new_mock();
+        project.mock("/mock/main.ac", text);
+        let module_id = project.load_module_by_name("main").unwrap();
+        let LoadState::Ok(env) = project.get_module_by_id(module_id) else {
+            panic!("no module");
+        };
+        let node = env.get_node_by_name("goal");
+        let goal_context = node.goal_context().unwrap();
+        let new_prover = || {
+            let mut prover = Prover::new(&project, false);
+            for fact in node.usable_facts(&project) {
+                prover.add_fact(fact);
+            }
+            prover.set_goal(&goal_context);
+            prover
+        };
+
+        let mut searcher = new_prover();
+        assert_eq!(searcher.quick_search(), Outcome::Success);
+        let record = searcher.proof_record().unwrap();
+
+        // The replayed proof is the same one, found without activating anything.
+        let mut replayer = new_prover();
+        assert_eq!(replayer.replay(&record), Outcome::Success);
+        assert_eq!(replayer.num_activated(), 0);
+        let replayed = replayer.get_proof().unwrap();
+        assert_eq!(replayed.all_steps.len(), record.steps.len());
+
+        // Steps can't be left out.
+        let mut skipped = record.clone();
+        skipped.steps.retain(|(id, _)| *id == ProofStepId::Final);
+        let mut replayer = new_prover();
+        assert_eq!(replayer.replay(&skipped), Outcome::Error);
+        assert!(replayer.error.unwrap().starts_with("replay failed"));
+
+        // Steps that the checker can't re-derive aren't trusted.
+        let mut unchecked = record.clone();
+        let (_, last) = unchecked.steps.last_mut().unwrap();
+        last.rule = Rule::Smt(
+            last.rule
+                .premises()
+                .iter()
+                .filter_map(|id| id.active_id())
+                .collect(),
+        );
+        let mut replayer = new_prover();
+        assert_eq!(replayer.replay(&unchecked), Outcome::Error);
+        assert!(replayer.error.unwrap().ends_with("could not be checked"));
+
+        // The facts have to match the prover's.
+        let mut other = Prover::new(&project, false);
+        other.set_goal(&goal_context);
+        assert_eq!(other.replay(&record), Outcome::Error);
+    }
 }
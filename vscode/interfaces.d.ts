@@ -45,17 +45,30 @@ interface ProofStepInfo {
   clause: ClauseInfo;
   premises: Array<[String, ClauseInfo]>;
   rule: string;
+  justification: string;
   location: Location | null;
   depth: number;
 }
 
+interface PremiseInfo {
+  description: string;
+  location: Location | null;
+}
+
+interface Counterexample {
+  assignments: Array<string>;
+  violated: Array<string>;
+}
+
 interface SearchStatus {
   code: Array<string> | null;
   codeError: string | null;
   steps: Array<ProofStepInfo> | null;
+  premises: Array<PremiseInfo> | null;
   outcome: string | null;
   needsSimplification: boolean | null;
   numActivated: number;
+  counterexample: Counterexample | null;
 }
 
 interface SearchResponse {
@@ -89,6 +102,30 @@ interface InfoResponse {
   result: InfoResult | null;
 }
 
+interface ScoredClauseInfo {
+  clause: ClauseInfo;
+  score: number;
+}
+
+interface ProverSnapshot {
+  goal: Array<ClauseInfo>;
+  nextPassive: Array<ScoredClauseInfo>;
+  recentActivations: Array<ClauseInfo>;
+  numActivated: number;
+  numPassive: number;
+}
+
+interface SnapshotParams {
+  searchId: number;
+  count: number;
+}
+
+interface SnapshotResponse {
+  searchId: number;
+  failure: string | null;
+  snapshot: ProverSnapshot | null;
+}
+
 // This section is for the extension to communicate with the assistant.
 // There's no Rust equivalent for these interfaces.
 